@@ -0,0 +1,221 @@
+// This module contains archive storage handling for the Rustored application
+// Snapshots that lifecycle rules moved to Glacier or Deep Archive cannot be
+// read until a temporary copy is restored with `RestoreObject`, which takes
+// minutes to days depending on the retrieval tier. This module tracks which
+// snapshots are archived, requests their restores and polls until they can be
+// downloaded.
+
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::storage::SnapshotStore;
+
+/// Days a restored copy of an archived snapshot stays readable
+pub const DEFAULT_ARCHIVE_RESTORE_DAYS: i32 = 3;
+
+/// How often a pending archive restore is checked
+pub const ARCHIVE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether objects in a storage class must be restored before they can be read
+///
+/// Glacier Instant Retrieval is readable directly, so only Flexible Retrieval
+/// (`GLACIER`) and Deep Archive count.
+pub fn is_archived_class(storage_class: &str) -> bool {
+    debug!("Checking whether storage class {} is archived", storage_class);
+    matches!(storage_class, "GLACIER" | "DEEP_ARCHIVE")
+}
+
+/// Retrieval tier of an archive restore, trading speed for cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchiveTier {
+    /// Minutes, most expensive; not available for Deep Archive
+    Expedited,
+    /// Hours
+    #[default]
+    Standard,
+    /// Up to two days, cheapest
+    Bulk,
+}
+
+impl ArchiveTier {
+    /// Tier name as S3 expects it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveTier::Expedited => "Expedited",
+            ArchiveTier::Standard => "Standard",
+            ArchiveTier::Bulk => "Bulk",
+        }
+    }
+
+    /// The tier after this one, for cycling through tiers in the TUI
+    pub fn next(&self) -> Self {
+        match self {
+            ArchiveTier::Standard => ArchiveTier::Bulk,
+            ArchiveTier::Bulk => ArchiveTier::Expedited,
+            ArchiveTier::Expedited => ArchiveTier::Standard,
+        }
+    }
+
+    /// Typical time until an object in `storage_class` is readable with this tier
+    pub fn typical_wait(&self, storage_class: &str) -> &'static str {
+        match (self, storage_class) {
+            (ArchiveTier::Expedited, "DEEP_ARCHIVE") => "not available",
+            (ArchiveTier::Expedited, _) => "1-5 minutes",
+            (ArchiveTier::Standard, "DEEP_ARCHIVE") => "up to 12 hours",
+            (ArchiveTier::Standard, _) => "3-5 hours",
+            (ArchiveTier::Bulk, "DEEP_ARCHIVE") => "up to 48 hours",
+            (ArchiveTier::Bulk, _) => "5-12 hours",
+        }
+    }
+}
+
+impl fmt::Display for ArchiveTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ArchiveTier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "expedited" => Ok(ArchiveTier::Expedited),
+            "standard" => Ok(ArchiveTier::Standard),
+            "bulk" => Ok(ArchiveTier::Bulk),
+            _ => Err(anyhow!("Unknown retrieval tier '{}', expected expedited, standard or bulk", s)),
+        }
+    }
+}
+
+/// Whether an archived snapshot can be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveStatus {
+    /// Not in an archive storage class, readable as is
+    NotArchived,
+    /// Archived with no restore requested
+    Archived,
+    /// A restore was requested and has not finished
+    Restoring,
+    /// A restored copy is readable until `expiry`, if the backend reports it
+    Restored { expiry: Option<String> },
+}
+
+impl ArchiveStatus {
+    /// Whether the snapshot can be downloaded now
+    pub fn is_retrievable(&self) -> bool {
+        matches!(self, ArchiveStatus::NotArchived | ArchiveStatus::Restored { .. })
+    }
+
+    /// Work out the status from an object's storage class and S3 `x-amz-restore` header
+    ///
+    /// The header reads `ongoing-request="true"` while a restore runs and
+    /// `ongoing-request="false", expiry-date="..."` once it is done.
+    pub fn from_restore_header(storage_class: Option<&str>, restore: Option<&str>) -> Self {
+        debug!("Parsing archive status of {:?} object, restore header {:?}", storage_class, restore);
+        if !storage_class.is_some_and(is_archived_class) {
+            return ArchiveStatus::NotArchived;
+        }
+        match restore {
+            None => ArchiveStatus::Archived,
+            Some(header) if header.contains("ongoing-request=\"true\"") => ArchiveStatus::Restoring,
+            Some(header) => ArchiveStatus::Restored {
+                expiry: header
+                    .split_once("expiry-date=\"")
+                    .and_then(|(_, rest)| rest.split_once('"'))
+                    .map(|(expiry, _)| expiry.to_string()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ArchiveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveStatus::NotArchived => write!(f, "not archived"),
+            ArchiveStatus::Archived => write!(f, "archived"),
+            ArchiveStatus::Restoring => write!(f, "restoring"),
+            ArchiveStatus::Restored { expiry: Some(expiry) } => write!(f, "restored until {}", expiry),
+            ArchiveStatus::Restored { expiry: None } => write!(f, "restored"),
+        }
+    }
+}
+
+/// Poll a snapshot until its archive restore finishes
+///
+/// # Arguments
+///
+/// * `store` - Storage backend holding the snapshot
+/// * `key` - Key of the snapshot
+/// * `interval` - Time between checks
+/// * `cancel` - Token that stops polling
+///
+/// # Returns
+///
+/// The retrievable status, or a `Cancelled` error
+pub async fn wait_until_retrievable(
+    store: &dyn SnapshotStore,
+    key: &str,
+    interval: Duration,
+    cancel: &CancellationToken,
+) -> Result<ArchiveStatus> {
+    debug!("Waiting for archive restore of {} every {:?}", key, interval);
+    loop {
+        let status = store.archive_status(key).await?;
+        if status.is_retrievable() {
+            debug!("{} is retrievable: {}", key, status);
+            return Ok(status);
+        }
+        if status == ArchiveStatus::Archived {
+            return Err(anyhow!("{} is archived and no restore has been requested", key));
+        }
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(Cancelled::error(format!("Wait for archive restore of {}", key))),
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+}
+
+/// Archive status of snapshots looked up or restored during this session
+///
+/// Cloning the tracker shares it, so background polls update what the
+/// snapshot list shows.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveTracker {
+    statuses: Arc<Mutex<HashMap<String, ArchiveStatus>>>,
+}
+
+impl ArchiveTracker {
+    /// Last known status of a snapshot
+    pub fn status(&self, key: &str) -> Option<ArchiveStatus> {
+        debug!("Reading archive status of {}", key);
+        self.statuses.lock().ok()?.get(key).cloned()
+    }
+
+    /// Record the status of a snapshot
+    pub fn set(&self, key: &str, status: ArchiveStatus) {
+        debug!("Recording archive status of {}: {}", key, status);
+        if let Ok(mut statuses) = self.statuses.lock() {
+            statuses.insert(key.to_string(), status);
+        }
+    }
+
+    /// Poll a snapshot in the background until it is retrievable, recording each result
+    pub fn watch(&self, store: Arc<dyn SnapshotStore>, key: &str, interval: Duration, cancel: CancellationToken) {
+        debug!("Watching archive restore of {}", key);
+        let tracker = self.clone();
+        let key = key.to_string();
+        tokio::spawn(async move {
+            match wait_until_retrievable(store.as_ref(), &key, interval, &cancel).await {
+                Ok(status) => tracker.set(&key, status),
+                Err(e) => warn!("Stopped waiting for archive restore of {}: {}", key, e),
+            }
+        });
+    }
+}
@@ -0,0 +1,962 @@
+// This module contains the command line arguments of the Rustored binary
+// Global connection settings live on `Cli`; each subcommand and its options
+// are variants of `Commands`, with nested actions in their own enums.
+
+use chrono::{DateTime, Utc};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Parser, Subcommand};
+use rustored::as_of::parse_as_of;
+use rustored::bandwidth::parse_rate;
+use rustored::chains::DEFAULT_CHAIN_WORKERS;
+use rustored::compression::Codec;
+use rustored::datastore::RestoreTarget;
+use rustored::download_ledger::DEFAULT_DOWNLOAD_CONCURRENCY;
+use rustored::presets::RestoreMode;
+use rustored::telemetry::{parse_headers, TelemetryConfig};
+use rustored::profiles::{ConnectionProfile, TargetServer};
+use rustored::promote::DEFAULT_ROLLBACK_WINDOW_HOURS;
+use rustored::ui::models::QdrantTransport;
+
+#[derive(Parser)]
+#[command(name = "rustored")]
+#[command(about = "PostgreSQL database management tool")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+
+    #[arg(short, long, help = "Postgres File Path")]
+    pub file: Option<String>,
+
+    #[arg(short = 'H', long, env = "PG_HOST", help = "Postgres Host")]
+    pub host: Option<String>,
+
+    #[arg(short, default_value = "5432", long, env = "PG_PORT", help = "Postgres Port")]
+    pub port: Option<u16>,
+
+    #[arg(short, long, env = "PG_USERNAME", help = "Postgres Username")]
+    pub username: Option<String>,
+
+    #[arg(short = 'P', long, env = "PG_PASSWORD", help = "Postgres Password")]
+    pub password: Option<String>,
+
+    #[arg(short = 'D', default_value = "postgres", long, env = "PG_DB_NAME", help = "Postgres Database Name")]
+    pub db_name: Option<String>,
+
+    #[arg(long, env = "PG_TARGET_DB", help = "Postgres database to restore into, a new <word>-restored one if unset")]
+    pub target_db: Option<String>,
+
+    #[arg(long, env = "PG_RESTORE_JOBS", value_parser = clap::value_parser!(u32).range(1..), help = "Parallel pg_restore jobs for restores whose preset sets none")]
+    pub restore_jobs: Option<u32>,
+
+    #[arg(long, default_value = "false", env = "PG_USE_SSL", help = "Postgres Enable SSL")]
+    pub use_ssl: bool,
+
+    #[arg(long, env = "PG_ROOT_CERT_PATH", help = "Postgres Path to custom root certificates")]
+    pub root_cert_path: Option<String>,
+
+    #[arg(long, default_value = "false", env = "PG_VERIFY_SSL", help = "Postgres Verify SSL certificates")]
+    pub verify_ssl: bool,
+
+    #[arg(short = 'B', long, env = "S3_BUCKET", help = "S3 Bucket Name")]
+    pub bucket: Option<String>,
+
+    #[arg(short = 'R', long, env = "S3_REGION", help = "S3 Region")]
+    pub region: Option<String>,
+
+    #[arg(short = 'x', long, default_value = "postgres", env = "S3_PREFIX", help = "S3 Prefix for snapshot keys")]
+    pub prefix: Option<String>,
+
+    #[arg(short = 'E', long, env = "S3_ENDPOINT_URL", help = "S3 Endpoint URL")]
+    pub endpoint_url: Option<String>,
+
+    #[arg(short = 'A', long, env = "S3_ACCESS_KEY_ID", help = "S3 Access Key ID")]
+    pub access_key_id: Option<String>,
+
+    #[arg(short = 'S', long, env = "S3_SECRET_ACCESS_KEY", help = "S3 Secret Access Key")]
+    pub secret_access_key: Option<String>,
+
+    #[arg(long, default_value = "true", env = "S3_PATH_STYLE", help = "S3 Force path-style")]
+    pub path_style: bool,
+
+    #[arg(long, env = "S3_SSE_CUSTOMER_KEY", help = "Base64 encoded 256-bit key for SSE-C encrypted snapshots")]
+    pub sse_customer_key: Option<String>,
+
+    #[arg(long = "age-recipient", env = "AGE_RECIPIENTS", value_delimiter = ',', help = "Encrypt backups with age for this recipient before upload; repeatable")]
+    pub age_recipients: Vec<String>,
+
+    #[arg(long = "gpg-recipient", env = "GPG_RECIPIENTS", value_delimiter = ',', conflicts_with = "age_recipients", help = "Encrypt backups with GPG for this key ID or email before upload; repeatable")]
+    pub gpg_recipients: Vec<String>,
+
+    #[arg(long, env = "AGE_IDENTITY_FILE", help = "age identity file used to decrypt age encrypted snapshots")]
+    pub age_identity: Option<std::path::PathBuf>,
+
+    /// Elasticsearch host or URL
+    #[arg(long, help = "Elasticsearch host or URL")]
+    pub es_host: Option<String>,
+
+    /// Elasticsearch index or Qdrant collection name
+    #[arg(long, help = "Elasticsearch index or Qdrant collection name")]
+    pub es_index: Option<String>,
+
+    /// Qdrant API key (optional)
+    #[arg(long, help = "Qdrant API key (optional)")]
+    pub qdrant_api_key: Option<String>,
+
+    #[arg(long, env = "QDRANT_TRANSPORT", value_enum, default_value = "http", help = "API Qdrant points are upserted through: http, or grpc with a fallback to http")]
+    pub qdrant_transport: QdrantTransport,
+
+    #[arg(long, env = "QDRANT_GRPC_PORT", help = "Port of Qdrant's gRPC API (default 6334)")]
+    pub qdrant_grpc_port: Option<u16>,
+
+    #[arg(long, env = "QDRANT_VECTOR_SIZE", help = "Vector size of a Qdrant collection the restore creates, instead of the stored or sampled one")]
+    pub qdrant_vector_size: Option<u64>,
+
+    #[arg(long, env = "QDRANT_DISTANCE", help = "Distance of a Qdrant collection the restore creates: Cosine, Euclid, Dot or Manhattan (default the stored one, or Cosine)")]
+    pub qdrant_distance: Option<String>,
+
+    #[arg(long, env = "QDRANT_BATCH_SIZE", help = "Points per Qdrant upsert (default 256)")]
+    pub qdrant_batch_size: Option<usize>,
+
+    #[arg(long, env = "QDRANT_CONCURRENCY", help = "Qdrant upserts in flight at once (default 4)")]
+    pub qdrant_concurrency: Option<usize>,
+
+    #[arg(long, env = "OPENSEARCH_HOST", help = "OpenSearch host or URL")]
+    pub opensearch_host: Option<String>,
+
+    #[arg(long, env = "OPENSEARCH_INDEX", help = "OpenSearch index name")]
+    pub opensearch_index: Option<String>,
+
+    #[arg(long, env = "OPENSEARCH_REGION", help = "AWS region to sign OpenSearch requests for with SigV4; unsigned when unset")]
+    pub opensearch_region: Option<String>,
+
+    #[arg(long, env = "OPENSEARCH_SERVICE", default_value = "es", value_parser = ["es", "aoss"], help = "SigV4 service of OpenSearch: es for domains, aoss for Serverless collections")]
+    pub opensearch_service: String,
+
+    #[arg(long, env = "MEILI_HOST", help = "Meilisearch host or URL")]
+    pub meili_host: Option<String>,
+
+    #[arg(long, env = "MEILI_INDEX", help = "Meilisearch index name")]
+    pub meili_index: Option<String>,
+
+    #[arg(long, env = "MEILI_API_KEY", help = "Meilisearch API key (optional)")]
+    pub meili_api_key: Option<String>,
+
+    #[arg(long, env = "MEILI_PRIMARY_KEY", help = "Primary key of a new Meilisearch index, detected from the documents when unset")]
+    pub meili_primary_key: Option<String>,
+
+    #[arg(long, env = "WEAVIATE_HOST", help = "Weaviate URL")]
+    pub weaviate_host: Option<String>,
+
+    #[arg(long, env = "WEAVIATE_CLASS", help = "Weaviate class to import objects into")]
+    pub weaviate_class: Option<String>,
+
+    #[arg(long, env = "WEAVIATE_API_KEY", help = "Weaviate API key (optional)")]
+    pub weaviate_api_key: Option<String>,
+
+    #[arg(long, env = "WEAVIATE_OIDC_CLIENT_SECRET", help = "OIDC client secret for Weaviate's client credentials grant, used without an API key")]
+    pub weaviate_oidc_client_secret: Option<String>,
+
+    #[arg(long, env = "WEAVIATE_OIDC_SCOPE", help = "Scopes requested with the Weaviate OIDC token, if the issuer needs any")]
+    pub weaviate_oidc_scope: Option<String>,
+
+    #[arg(long, env = "MILVUS_HOST", help = "Milvus gRPC URL, e.g. http://localhost:19530")]
+    pub milvus_host: Option<String>,
+
+    #[arg(long, env = "MILVUS_COLLECTION", help = "Milvus collection to insert rows into")]
+    pub milvus_collection: Option<String>,
+
+    #[arg(long, env = "MILVUS_TOKEN", help = "Milvus API key or user:password (optional)")]
+    pub milvus_token: Option<String>,
+
+    #[arg(long, env = "MILVUS_DB", help = "Milvus database holding the collection (default database when unset)")]
+    pub milvus_db: Option<String>,
+
+    #[arg(long, env = "MILVUS_VECTOR_FIELD", help = "Field holding each row's vector (default vector)")]
+    pub milvus_vector_field: Option<String>,
+
+    #[arg(long, env = "MILVUS_METRIC", help = "Metric of the vector index of a new Milvus collection: COSINE, L2 or IP (default COSINE)")]
+    pub milvus_metric: Option<String>,
+
+    #[arg(long, env = "DYNAMODB_TABLE", help = "Existing DynamoDB table to write items into")]
+    pub dynamodb_table: Option<String>,
+
+    #[arg(long, env = "DYNAMODB_REGION", help = "AWS region of the DynamoDB table (default AWS_REGION)")]
+    pub dynamodb_region: Option<String>,
+
+    #[arg(long, env = "DYNAMODB_ENDPOINT_URL", help = "DynamoDB endpoint to use instead of AWS's, e.g. http://localhost:8000 for DynamoDB Local")]
+    pub dynamodb_endpoint_url: Option<String>,
+
+    #[arg(long, env = "INFLUX_HOST", help = "InfluxDB 2.x URL, e.g. http://localhost:8086")]
+    pub influx_host: Option<String>,
+
+    #[arg(long, env = "INFLUX_ORG", help = "InfluxDB organization owning the bucket")]
+    pub influx_org: Option<String>,
+
+    #[arg(long, env = "INFLUX_BUCKET", help = "InfluxDB bucket to write points into")]
+    pub influx_bucket: Option<String>,
+
+    #[arg(long, env = "INFLUX_TOKEN", help = "InfluxDB API token with write access to the bucket")]
+    pub influx_token: Option<String>,
+
+    #[arg(long, env = "INFLUX_PRECISION", help = "Timestamp precision of the line protocol: ns, us, ms or s (default ns)")]
+    pub influx_precision: Option<String>,
+
+    #[arg(long, env = "ES_BATCH_SIZE", help = "Documents per Elasticsearch bulk request (default 1000)")]
+    pub es_batch_size: Option<usize>,
+
+    #[arg(long, env = "ES_BULK_CONCURRENCY", help = "Elasticsearch bulk requests in flight at once (default 4)")]
+    pub es_bulk_concurrency: Option<usize>,
+
+    #[arg(long, help = "Keep Elasticsearch index refreshes on during a restore instead of turning them off until it ends")]
+    pub es_keep_refresh: bool,
+
+    #[arg(long, env = "ES_SHARDS", help = "Primary shards of an Elasticsearch index created by a restore, overriding its index definition")]
+    pub es_shards: Option<u32>,
+
+    #[arg(long, env = "ES_REPLICAS", help = "Replicas of an Elasticsearch index created by a restore, overriding its index definition")]
+    pub es_replicas: Option<u32>,
+
+    #[arg(long, env = "ES_ALIAS_SWAP", help = "Treat the Elasticsearch index as an alias: load a new index and move the alias to it once every document is in")]
+    pub es_alias_swap: bool,
+
+    #[arg(long, env = "ES_CA_CERT", help = "Elasticsearch/Qdrant Path to a PEM bundle of CA certificates to trust")]
+    pub es_ca_cert: Option<String>,
+
+    #[arg(long, env = "ES_CLIENT_CERT", help = "Elasticsearch/Qdrant Path to a PEM client certificate for mutual TLS")]
+    pub es_client_cert: Option<String>,
+
+    #[arg(long, env = "ES_CLIENT_KEY", help = "Elasticsearch/Qdrant Path to the client certificate's PKCS#8 PEM key")]
+    pub es_client_key: Option<String>,
+
+    #[arg(long, default_value = "false", env = "ES_INSECURE_SKIP_VERIFY", help = "Elasticsearch/Qdrant Accept any server certificate, e.g. a self-signed one")]
+    pub es_insecure_skip_verify: bool,
+
+    #[arg(long, env = "RESTORE_MAPPING_FILE", help = "TOML file renaming indices, collections and fields during Elasticsearch and Qdrant restores")]
+    pub mapping_file: Option<String>,
+
+    #[arg(long, env = "GCS_BUCKET", help = "GCS Bucket Name; reads snapshots from GCS instead of S3")]
+    pub gcs_bucket: Option<String>,
+
+    #[arg(long, default_value = "postgres", env = "GCS_PREFIX", help = "GCS Prefix for snapshot keys")]
+    pub gcs_prefix: String,
+
+    #[arg(long, env = "GCS_SERVICE_ACCOUNT_PATH", help = "Path to a GCS service-account JSON file (default: Application Default Credentials)")]
+    pub gcs_service_account_path: Option<String>,
+
+    #[arg(long, env = "GCS_SERVICE_ACCOUNT_KEY", help = "GCS service-account JSON key (default: Application Default Credentials)")]
+    pub gcs_service_account_key: Option<String>,
+
+    #[arg(long, env = "AZURE_STORAGE_ACCOUNT", help = "Azure storage account; reads snapshots from Azure Blob instead of S3")]
+    pub azure_account: Option<String>,
+
+    #[arg(long, env = "AZURE_CONTAINER", help = "Azure Blob container name")]
+    pub azure_container: Option<String>,
+
+    #[arg(long, default_value = "postgres", env = "AZURE_PREFIX", help = "Azure Blob prefix for snapshot keys")]
+    pub azure_prefix: String,
+
+    #[arg(long, env = "AZURE_STORAGE_ACCESS_KEY", help = "Azure storage account access key")]
+    pub azure_access_key: Option<String>,
+
+    #[arg(long, env = "AZURE_STORAGE_SAS_TOKEN", help = "Azure SAS token, used when no access key is given")]
+    pub azure_sas_token: Option<String>,
+
+    #[arg(long, env = "B2_BUCKET", help = "Backblaze B2 bucket to read snapshots from using the native B2 API")]
+    pub b2_bucket: Option<String>,
+
+    #[arg(long, default_value = "postgres", env = "B2_PREFIX", help = "B2 prefix for snapshot keys")]
+    pub b2_prefix: String,
+
+    #[arg(long, env = "B2_APPLICATION_KEY_ID", help = "B2 application key ID")]
+    pub b2_key_id: Option<String>,
+
+    #[arg(long, env = "B2_APPLICATION_KEY", help = "B2 application key")]
+    pub b2_application_key: Option<String>,
+
+    #[arg(long, env = "SFTP_HOST", help = "SFTP server to read snapshots from instead of a bucket")]
+    pub sftp_host: Option<String>,
+
+    #[arg(long, default_value = "22", env = "SFTP_PORT", help = "SFTP server port")]
+    pub sftp_port: u16,
+
+    #[arg(long, env = "SFTP_USERNAME", help = "SFTP username")]
+    pub sftp_username: Option<String>,
+
+    #[arg(long, env = "SFTP_PASSWORD", help = "SFTP password, used when no private key is given")]
+    pub sftp_password: Option<String>,
+
+    #[arg(long, env = "SFTP_PRIVATE_KEY", help = "Path to a private key for SFTP authentication")]
+    pub sftp_private_key: Option<String>,
+
+    #[arg(long, env = "SFTP_PRIVATE_KEY_PASSPHRASE", help = "Passphrase of the SFTP private key")]
+    pub sftp_private_key_passphrase: Option<String>,
+
+    #[arg(long, default_value = "", env = "SFTP_PREFIX", help = "Remote path prefix for snapshot files, e.g. /backups/postgres")]
+    pub sftp_prefix: String,
+
+    #[arg(long, env = "S3_CONNECT_TIMEOUT", help = "S3/GCS/Azure connect timeout in seconds (default 10)")]
+    pub s3_connect_timeout: Option<u64>,
+
+    #[arg(long, env = "S3_READ_TIMEOUT", help = "S3/GCS/Azure read timeout in seconds (default 60)")]
+    pub s3_read_timeout: Option<u64>,
+
+    #[arg(long, env = "S3_OPERATION_TIMEOUT", help = "S3/GCS/Azure whole-operation timeout in seconds (default none)")]
+    pub s3_operation_timeout: Option<u64>,
+
+    #[arg(long, env = "TARGET_CONNECT_TIMEOUT", help = "Elasticsearch/Qdrant connect timeout in seconds (default 10)")]
+    pub target_connect_timeout: Option<u64>,
+
+    #[arg(long, env = "TARGET_READ_TIMEOUT", help = "Elasticsearch/Qdrant read timeout in seconds (default 60)")]
+    pub target_read_timeout: Option<u64>,
+
+    #[arg(long, env = "TARGET_OPERATION_TIMEOUT", help = "Elasticsearch/Qdrant request timeout in seconds (default none)")]
+    pub target_operation_timeout: Option<u64>,
+
+    #[arg(long, default_value_t = DEFAULT_DOWNLOAD_CONCURRENCY, env = "DOWNLOAD_CONCURRENCY", help = "Number of snapshot parts to download at once")]
+    pub download_concurrency: usize,
+
+    #[arg(long, env = "RUSTORED_MAX_BANDWIDTH", value_parser = parse_rate, help = "Bytes per second all downloads may use together, split evenly between the jobs running at once, e.g. 50M or 1.5G (default: unlimited)")]
+    pub max_bandwidth: Option<u64>,
+
+    #[arg(long, env = "S3_MAX_KEYS", help = "Stop listing snapshots after this many keys (default: list all)")]
+    pub max_keys: Option<usize>,
+
+    #[arg(long, global = true, help = "Print tables without color (also off when NO_COLOR is set or output is not a terminal)")]
+    pub no_color: bool,
+
+    #[arg(long, env = "RUSTORED_CACHE_DIR", help = "Directory the last snapshot listing is cached in (default: ~/.cache/rustored)")]
+    pub listing_cache_dir: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "Do not cache snapshot listings or show a cached listing on startup")]
+    pub no_listing_cache: bool,
+
+    #[arg(long, help = "Show synthetic snapshots and simulate downloads and restores, contacting no bucket or server")]
+    pub demo: bool,
+
+    #[arg(long, value_name = "FILE", help = "Record the keys handled by the TUI and its state after each, with credentials redacted, for `replay`")]
+    pub record_session: Option<String>,
+
+    #[arg(long, help = "Do not show the running operation and its progress in the terminal title")]
+    pub no_terminal_title: bool,
+
+    #[arg(long, help = "Also set the @rustored_status option of the tmux window to the running operation")]
+    pub tmux_status: bool,
+
+    #[arg(long, help = "Do not ring the terminal bell when a long operation ends waiting for input; the status bar still flashes")]
+    pub no_bell: bool,
+
+    #[arg(long, env = "RUSTORED_ATTENTION_AFTER", default_value_t = 10, help = "Seconds an operation must take before its end waiting for input is signalled")]
+    pub attention_after: u64,
+
+    #[arg(long, env = "RESTORE_PRESETS_FILE", help = "TOML file of per-database restore presets, backup schedules and retention rules")]
+    pub presets_file: Option<String>,
+
+    #[arg(long, env = "RUSTORED_PROFILE", help = "Connection profile in the presets file to take settings from; flags and environment variables still win")]
+    pub profile: Option<String>,
+
+    #[arg(long, env = "RUSTORED_ENVIRONMENT", help = "Environment the settings point at, shown as a banner in the TUI; `production` asks for its name before destructive actions")]
+    pub environment: Option<String>,
+
+    /// PostgreSQL servers of the profile, picked from before each restore in the TUI
+    #[arg(skip)]
+    pub servers: Vec<TargetServer>,
+
+    #[arg(long, env = "MAINTENANCE_ON_HOOK", help = "URL to POST to or command to run before a restore to enable maintenance mode")]
+    pub maintenance_on_hook: Option<String>,
+
+    #[arg(long, env = "MAINTENANCE_OFF_HOOK", help = "URL to POST to or command to run after a restore to disable maintenance mode")]
+    pub maintenance_off_hook: Option<String>,
+
+    #[arg(long, env = "RUSTORED_SHARE_SECRET", help = "Secret share descriptors are signed and checked with, agreed between the sharing teams")]
+    pub share_secret: Option<String>,
+
+    #[arg(long, env = "PRE_RESTORE_HOOK", help = "URL to POST to or command to run before each restore whose preset sets no pre_hook; a failure aborts the restore")]
+    pub pre_restore_hook: Option<String>,
+
+    #[arg(long, env = "RESTORE_ROW_FILTER_FILE", help = "TOML file of per-table WHERE predicates applied to PostgreSQL restores whose preset sets no row_filters")]
+    pub row_filter_file: Option<String>,
+
+    #[arg(long, env = "POST_RESTORE_HOOK", help = "URL to POST to or command to run after each successful restore whose preset sets no post_hook")]
+    pub post_restore_hook: Option<String>,
+
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT", help = "OTLP/HTTP collector to send restore phase spans and metrics to, e.g. http://localhost:4318")]
+    pub otlp_endpoint: Option<String>,
+
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_HEADERS", help = "Headers sent to the OTLP collector, as KEY=VALUE,KEY=VALUE")]
+    pub otlp_headers: Option<String>,
+
+    #[arg(long, env = "STATSD_ADDR", help = "statsd server to send restore phase timings to, as host:port")]
+    pub statsd: Option<String>,
+
+    #[arg(long, default_value = "rustored", env = "OTEL_SERVICE_NAME", help = "Service name of the spans, and prefix of the statsd metrics")]
+    pub otel_service_name: String,
+}
+
+impl Cli {
+    /// Fill in the settings a flag or environment variable did not set from a profile
+    pub fn apply_profile(&mut self, profile: &ConnectionProfile, matches: &ArgMatches) {
+        let unset = |id: &str| !matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable));
+        macro_rules! fill {
+            ($($field:ident),*) => {$(
+                if let Some(value) = &profile.$field {
+                    if unset(stringify!($field)) {
+                        self.$field = Some(value.clone());
+                    }
+                }
+            )*};
+        }
+        fill!(environment, host, port, username, db_name, target_db, bucket, region, prefix, endpoint_url, access_key_id);
+        if let (Some(use_ssl), true) = (profile.use_ssl, unset("use_ssl")) {
+            self.use_ssl = use_ssl;
+        }
+        if let (Some(path_style), true) = (profile.path_style, unset("path_style")) {
+            self.path_style = path_style;
+        }
+        // Settings a server leaves out are the ones the profile, flags and environment variables give
+        self.servers = profile.servers.iter().map(|server| TargetServer {
+            environment: server.environment.clone().or_else(|| self.environment.clone()),
+            port: server.port.or(self.port),
+            username: server.username.clone().or_else(|| self.username.clone()),
+            use_ssl: server.use_ssl.or(Some(self.use_ssl)),
+            target_db: server.target_db.clone().or_else(|| self.target_db.clone()),
+            ..server.clone()
+        }).collect();
+    }
+
+    /// Exporter settings for restore phase spans and metrics
+    ///
+    /// A `TRACEPARENT` environment variable, as set by a CI job or scheduler
+    /// that traces its own run, makes restores join that trace.
+    pub fn telemetry_config(&self) -> anyhow::Result<TelemetryConfig> {
+        Ok(TelemetryConfig {
+            otlp_endpoint: self.otlp_endpoint.clone(),
+            otlp_headers: self.otlp_headers.as_deref().map(parse_headers).transpose()?.unwrap_or_default(),
+            statsd: self.statsd.clone(),
+            service_name: self.otel_service_name.clone(),
+            traceparent: std::env::var("TRACEPARENT").ok(),
+        })
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    #[command(about = "List all databases")]
+    List,
+
+    #[command(about = "Create a new database")]
+    Create {
+        #[arg(help = "Name of the database to create")]
+        name: String,
+    },
+
+    #[command(about = "Clone a database")]
+    Clone {
+        #[arg(help = "Name of the database to clone from. Will create a new database with the name '<same_name>-clone'")]
+        name: String,
+    },
+
+    #[command(about = "Drop a database")]
+    Drop {
+        #[arg(help = "Name of the database to drop")]
+        name: String,
+    },
+
+    #[command(about = "Drop a database with force")]
+    DropForce {
+        #[arg(help = "Name of the database to drop")]
+        name: String,
+    },
+
+    #[command(about = "Rename a database")]
+    Rename {
+        #[arg(help = "Name of the database to rename")]
+        old_name: String,
+
+        #[arg(help = "New name for the database")]
+        new_name: String,
+    },
+
+    #[command(about = "Set database owner")]
+    SetOwner {
+        #[arg(help = "Name of the database")]
+        name: String,
+
+        #[arg(help = "New owner for the database")]
+        owner: String,
+    },
+
+    #[command(about = "Change the password of a user")]
+    ChangePassword {
+        #[arg(help = "Name of the user")]
+        user: String,
+
+        #[arg(help = "New password for the user")]
+        password: String,
+    },
+
+    #[command(about = "Dump a database")]
+    Dump {
+        #[arg(help = "Name of the database to dump")]
+        name: String,
+
+        #[arg(help = "Output file path")]
+        output: String,
+
+        #[arg(long, value_enum, help = "Compress the dump as it is written; the codec's extension is added to the output path")]
+        compress: Option<Codec>,
+
+        #[arg(long, requires = "compress", help = "Compression level: 1-9 for gzip, 1-19 for zstd, 1-12 for lz4")]
+        compress_level: Option<u32>,
+
+        #[arg(long, conflicts_with = "compress", value_parser = clap::value_parser!(u32).range(1..), help = "Dump in directory format with this many parallel workers, packed into a tar file")]
+        jobs: Option<u32>,
+    },
+
+    #[command(about = "Dump a database and upload it as a snapshot to the configured bucket and prefix")]
+    Backup {
+        #[arg(help = "Name of the database to back up")]
+        name: String,
+
+        #[arg(long, help = "Label to embed in the snapshot key, e.g. pre-change")]
+        label: Option<String>,
+
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..), help = "Dump in directory format with this many parallel workers and upload it as a .dir.tar snapshot")]
+        jobs: Option<u32>,
+    },
+
+    #[command(about = "Dump the cluster's roles and tablespaces with pg_dumpall and upload them as a snapshot")]
+    BackupGlobals,
+
+    #[command(about = "Back up databases on the cron schedules in the presets file until stopped")]
+    Schedule,
+
+    #[command(about = "Restore a snapshot to a datastore")]
+    Restore {
+        #[arg(help = "Name of the destination database, index, or collection")]
+        name: String,
+
+        #[arg(required_unless_present = "url", help = "Input dump file path")]
+        input: Option<String>,
+
+        #[arg(long, conflicts_with = "input", help = "Download the dump from this HTTP(S) URL, e.g. a presigned S3 link, instead of a local file")]
+        url: Option<String>,
+
+        #[arg(long, requires = "url", help = "Expected SHA-256 of the file at --url, checked before restoring")]
+        sha256: Option<String>,
+
+        #[arg(long, value_enum, default_value = "postgres", help = "Target datastore")]
+        target: RestoreTarget,
+
+        #[arg(long, help = "Apply this pg_dumpall --globals-only file before a PostgreSQL restore")]
+        globals: Option<String>,
+
+        // Elasticsearch/Qdrant/OpenSearch/Meilisearch/Weaviate/Milvus/DynamoDB/InfluxDB options
+        #[arg(long, help = "Elasticsearch/Qdrant/OpenSearch/Meilisearch/Weaviate/Milvus/InfluxDB host or URL, or DynamoDB endpoint URL")]
+        es_host: Option<String>,
+        #[arg(long, help = "Elasticsearch, OpenSearch or Meilisearch index, Qdrant or Milvus collection, Weaviate class, DynamoDB table or InfluxDB bucket name")]
+        es_index: Option<String>,
+        #[arg(long, help = "Qdrant API key (optional)")]
+        qdrant_api_key: Option<String>,
+    },
+
+    #[command(about = "Load the documents or points of a dead-letter file written by an Elasticsearch or Qdrant restore")]
+    RetryFailed {
+        #[arg(help = "Dead-letter file, e.g. orders-20240601T030000Z.dead-letter.ndjson")]
+        file: String,
+
+        #[arg(long, help = "Elasticsearch/Qdrant host or URL, instead of the one recorded in the file")]
+        es_host: Option<String>,
+        #[arg(long, help = "Qdrant API key (optional)")]
+        qdrant_api_key: Option<String>,
+    },
+
+    /// Browse and restore S3 snapshots using TUI
+    BrowseSnapshots,
+
+    #[command(about = "Replay a recorded TUI session against the demo backend and report where the state differs")]
+    Replay {
+        #[arg(help = "Session recording written with --record-session")]
+        file: String,
+
+        #[arg(long, default_value_t = 1.0, help = "Multiplier of the recorded pace; 0 replays without pauses")]
+        speed: f64,
+
+        #[arg(long, help = "Replay without drawing to the terminal, e.g. in CI")]
+        headless: bool,
+    },
+
+    #[command(about = "Download a snapshot from the configured source and restore it, as the TUI does")]
+    RestoreFromS3 {
+        #[arg(required_unless_present_any = ["pick", "as_of"], help = "Key of the snapshot to restore, or with --as-of a key prefix to choose from")]
+        key: Option<String>,
+
+        #[arg(long, conflicts_with_all = ["key", "version_id"], help = "Choose the snapshot from a fuzzy-filtered list instead of giving its key")]
+        pick: bool,
+
+        #[arg(long, value_parser = parse_as_of, conflicts_with_all = ["pick", "version_id"], help = "Restore the newest snapshot taken at or before this time, e.g. \"2024-06-01 03:00\" (UTC)")]
+        as_of: Option<DateTime<Utc>>,
+
+        #[arg(long, value_enum, default_value = "postgres", help = "Target datastore")]
+        target: RestoreTarget,
+
+        #[arg(long, help = "Restore this version of the snapshot from a versioned bucket instead of the current one")]
+        version_id: Option<String>,
+
+        #[arg(long, help = "Restore without applying the matching preset")]
+        no_preset: bool,
+
+        #[arg(long, help = "Override the number of parallel pg_restore jobs")]
+        jobs: Option<u32>,
+
+        #[arg(long, num_args = 0..=1, default_missing_value = "true", help = "Override whether indexes, constraints and triggers are skipped")]
+        drop_indexes: Option<bool>,
+
+        #[arg(long, num_args = 0..=1, default_missing_value = "true", help = "Override whether the restored database is compared with the live one")]
+        compare: Option<bool>,
+
+        #[arg(long, num_args = 0..=1, default_missing_value = "true", help = "Override whether the cluster globals snapshot is applied before a PostgreSQL restore")]
+        globals: Option<bool>,
+
+        #[arg(long, num_args = 0..=1, default_missing_value = "true", help = "Override whether a PostgreSQL database created for the restore is dropped if the restore is cancelled")]
+        drop_on_cancel: Option<bool>,
+
+        #[arg(long, num_args = 0..=1, default_missing_value = "true", help = "Override whether a PostgreSQL restore runs gently, one section at a time with pauses between and at the preset's copy rate")]
+        gentle: Option<bool>,
+
+        #[arg(long = "recipe", help = "Run a built-in recipe after a PostgreSQL restore, e.g. reset-passwords:password=dev; replaces the preset's recipes, 'none' runs none")]
+        recipes: Vec<String>,
+
+        #[arg(long, help = "Restore a PostgreSQL snapshot over the target database, or the configured one: recreate drops it first, clean runs pg_restore --clean --if-exists in it")]
+        into_existing: Option<RestoreMode>,
+
+        #[arg(long = "include-table", help = "Restore only this table of a PostgreSQL snapshot, as schema.table, table or schema.*; repeat for more")]
+        include_tables: Vec<String>,
+
+        #[arg(long = "exclude-table", help = "Leave this table out of a PostgreSQL restore, as schema.table, table or schema.*; repeat for more")]
+        exclude_tables: Vec<String>,
+
+        #[arg(long, help = "Download the snapshot and list the objects the restore would create, without contacting the PostgreSQL server")]
+        dry_run: bool,
+
+        #[arg(long, requires = "key", conflicts_with_all = ["pick", "as_of", "version_id"], help = "Restore a snapshot another team shared with this bucket, from their bucket")]
+        shared: bool,
+
+        #[arg(long, help = "Restore into an Elasticsearch index, Qdrant collection or PostgreSQL database that holds data without asking for its name")]
+        yes: bool,
+    },
+
+    #[command(about = "Promote a restored database to the production name, keeping the live one under a new name")]
+    Promote {
+        #[arg(help = "Name of the restored database to promote")]
+        restored: String,
+
+        #[arg(long, help = "Production name the restored database takes over")]
+        live: String,
+
+        #[arg(long, help = "Give the promoted database the live database's owner and grants")]
+        transfer_ownership: bool,
+
+        #[arg(long, help = "Promote without asking for the live database name to confirm")]
+        yes: bool,
+    },
+
+    #[command(about = "Roll back the latest promotion of a database, putting the previous live database back")]
+    Rollback {
+        #[arg(help = "Production name of the promoted database")]
+        live: String,
+
+        #[arg(long, default_value_t = DEFAULT_ROLLBACK_WINDOW_HOURS, env = "PROMOTE_ROLLBACK_WINDOW_HOURS", help = "Refuse to roll back promotions older than this many hours")]
+        window_hours: i64,
+
+        #[arg(long, help = "Roll back without asking for the live database name to confirm")]
+        yes: bool,
+    },
+
+    #[command(about = "Check bucket, snapshot age and target reachability; exits 1 on failure")]
+    Healthcheck {
+        #[arg(long, help = "Fail if the newest snapshot is older than this many hours")]
+        max_age_hours: Option<f64>,
+
+        #[arg(long, value_enum, help = "Also check that a target is reachable")]
+        target: Option<RestoreTarget>,
+
+        #[arg(long, help = "Print the check results as JSON after the status line")]
+        json: bool,
+    },
+
+    #[command(about = "Download a snapshot to a local file")]
+    Download {
+        #[arg(help = "Key of the snapshot to download")]
+        key: String,
+
+        #[arg(long, help = "Download this version of the snapshot from a versioned bucket instead of the current one")]
+        version_id: Option<String>,
+
+        #[arg(long, help = "File to write, defaults to the last part of the key in the current directory")]
+        output: Option<String>,
+
+        #[arg(long, help = "Continue an interrupted download of the same snapshot instead of starting over")]
+        resume: bool,
+    },
+
+    #[command(about = "Manage the target server restores go to")]
+    Target {
+        #[command(subcommand)]
+        action: TargetAction,
+    },
+
+    #[command(about = "Delete snapshots from the configured source, refusing any under a legal hold")]
+    DeleteSnapshot {
+        #[arg(required = true, help = "Keys of the snapshots to delete")]
+        keys: Vec<String>,
+
+        #[arg(long, help = "Delete without asking for confirmation")]
+        yes: bool,
+    },
+
+    #[command(about = "Delete snapshots that retention rules no longer keep, sparing any under a legal hold")]
+    Prune {
+        #[arg(long, help = "Key prefix to prune under; with --keep-* the rules apply to it, otherwise only presets file rules under it apply")]
+        prefix: Option<String>,
+
+        #[arg(long, help = "Days to keep the newest snapshot of")]
+        keep_daily: Option<usize>,
+
+        #[arg(long, help = "ISO weeks to keep the newest snapshot of")]
+        keep_weekly: Option<usize>,
+
+        #[arg(long, help = "Months to keep the newest snapshot of")]
+        keep_monthly: Option<usize>,
+
+        #[arg(long, help = "List what would be kept and deleted without deleting anything")]
+        dry_run: bool,
+
+        #[arg(long, help = "Delete without asking for confirmation")]
+        yes: bool,
+    },
+
+    #[command(about = "Report keys that break the naming template and manifests or snapshots missing their pair; exits 1 on findings")]
+    LintBucket {
+        #[arg(long, help = "Key prefix to lint under, defaults to the configured prefix")]
+        prefix: Option<String>,
+
+        #[arg(long, help = "Naming template such as {db}-{timestamp}.{ext}, overriding naming_template in the presets file")]
+        template: Option<String>,
+
+        #[arg(long, help = "Print the report as JSON after the table")]
+        json: bool,
+    },
+
+    #[command(about = "Drop the <word>-restored databases restores created that are older than an age")]
+    Gc {
+        #[arg(long, default_value = rustored::gc::DEFAULT_GC_AGE, help = "Drop throwaway restores created longer ago than this, e.g. 12h, 7d or 2w")]
+        older_than: String,
+
+        #[arg(long, help = "List what would be dropped and kept without dropping anything")]
+        dry_run: bool,
+
+        #[arg(long, help = "Drop without asking for confirmation")]
+        yes: bool,
+    },
+
+    #[command(about = "Declare the Elasticsearch and Qdrant snapshots restored together with a PostgreSQL snapshot")]
+    Bundle {
+        #[arg(help = "Key of the PostgreSQL snapshot whose manifest declares the bundle")]
+        key: String,
+
+        #[arg(long, help = "Key of the Elasticsearch snapshot in the bundle")]
+        elasticsearch: Option<String>,
+
+        #[arg(long, help = "Key of the Qdrant snapshot in the bundle")]
+        qdrant: Option<String>,
+    },
+
+    #[command(about = "Restore every snapshot of a bundle to PostgreSQL, Elasticsearch and Qdrant together")]
+    RestoreBundle {
+        #[arg(help = "Key of any snapshot in the bundle")]
+        key: String,
+
+        #[arg(long, help = "Qdrant URL, when it differs from --es-host")]
+        qdrant_host: Option<String>,
+
+        #[arg(long, help = "Qdrant collection, when it differs from --es-index")]
+        qdrant_collection: Option<String>,
+
+        #[arg(long, help = "Restore without asking for confirmation")]
+        yes: bool,
+    },
+
+    #[command(about = "List the built-in post-restore recipes, or print the SQL of one")]
+    Recipes {
+        #[arg(help = "Recipe to print, e.g. reset-passwords:password=dev")]
+        recipe: Option<String>,
+    },
+
+    #[command(about = "Place, release or list legal holds that block pruning and deleting snapshots")]
+    Hold {
+        #[command(subcommand)]
+        action: HoldAction,
+    },
+
+    #[command(about = "Hand snapshots to another team's bucket without copying them")]
+    Share {
+        #[command(subcommand)]
+        action: ShareAction,
+    },
+
+    #[command(about = "Manage the presets file")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    #[command(about = "Manage the manifests and catalogs rustored keeps in the bucket")]
+    Catalog {
+        #[command(subcommand)]
+        action: CatalogAction,
+    },
+
+    #[command(about = "Archive WAL and take base backups for incremental backups of a whole cluster")]
+    Wal {
+        #[command(subcommand)]
+        action: WalAction,
+    },
+
+    #[command(about = "Verify snapshots, or every incremental backup chain when no key is given; exits 1 on failure")]
+    Verify {
+        #[arg(help = "Keys of the snapshots to download and check; omit to verify backup chains from their manifests")]
+        keys: Vec<String>,
+
+        #[arg(long, help = "Only read the head of each snapshot: check its size against the manifest and its table of contents with pg_restore --list")]
+        quick: bool,
+
+        #[arg(long, default_value_t = DEFAULT_CHAIN_WORKERS, help = "Number of chain links checked at once")]
+        workers: usize,
+
+        #[arg(long, help = "Print the reports as JSON")]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TargetAction {
+    #[command(about = "Create the roles, extensions and settings a target profile in the presets file declares")]
+    Prepare {
+        #[arg(long, help = "Name of the target profile; may be omitted when only one is defined")]
+        profile: Option<String>,
+
+        #[arg(long, help = "Show the changes without making them")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    #[command(about = "Write the PG_*/S3_* environment variables and .env file out as a profile in the presets file")]
+    ImportEnv {
+        #[arg(long, default_value = "default", help = "Name of the new profile")]
+        name: String,
+
+        #[arg(long, help = "File to add the profile to, created if missing (default: --presets-file)")]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CatalogAction {
+    #[command(about = "Upgrade manifests and catalogs written by older rustored versions to the current format")]
+    Migrate {
+        #[arg(long, help = "List what would be upgraded without writing anything")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WalAction {
+    #[command(about = "Upload a WAL file; use as the server's archive_command with %p")]
+    Push {
+        #[arg(help = "Path of the WAL file to archive")]
+        path: std::path::PathBuf,
+
+        #[arg(long, value_enum, help = "Compress the file before upload")]
+        compress: Option<Codec>,
+    },
+
+    #[command(about = "Download an archived WAL file; use as the server's restore_command with %f %p")]
+    Fetch {
+        #[arg(help = "Name of the WAL file")]
+        file_name: String,
+
+        #[arg(help = "Path to write it to")]
+        output: std::path::PathBuf,
+    },
+
+    #[command(about = "Take a base backup of the cluster with pg_basebackup and upload it")]
+    BaseBackup,
+
+    #[command(about = "List base backups with the WAL archived after each")]
+    List,
+
+    #[command(about = "Recover a base backup and its WAL to a point in time on a scratch postgres instance")]
+    Recover {
+        #[arg(help = "Key of the base backup, as listed by `wal list`")]
+        key: String,
+
+        #[arg(long, help = "Time to recover to, e.g. '2025-03-01 14:30' (UTC) or RFC 3339")]
+        target_time: String,
+
+        #[arg(long, help = "New data directory for the scratch instance (default: rustored-pitr-<time> in the temp directory)")]
+        data_dir: Option<std::path::PathBuf>,
+
+        #[arg(long, default_value_t = rustored::pitr::DEFAULT_SCRATCH_PORT, help = "Port the scratch instance listens on")]
+        port: u16,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ShareAction {
+    #[command(about = "Write a signed descriptor of a snapshot: its location, checksum and encryption")]
+    Export {
+        #[arg(help = "Key of the snapshot to share")]
+        key: String,
+
+        #[arg(long, help = "Share this version of the snapshot from a versioned bucket instead of the current one")]
+        version_id: Option<String>,
+
+        #[arg(long, short, help = "Write the descriptor to this file instead of stdout")]
+        output: Option<String>,
+    },
+
+    #[command(about = "Check a descriptor's signature and record it in this bucket's shared catalog")]
+    Import {
+        #[arg(help = "Descriptor written by share export")]
+        file: String,
+    },
+
+    #[command(about = "List the snapshots shared with this bucket")]
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum HoldAction {
+    #[command(about = "Pin every snapshot under a prefix until a date")]
+    Create {
+        #[arg(long, help = "Key prefix of the snapshots to hold")]
+        prefix: String,
+
+        #[arg(long, help = "Last day the hold is in force (YYYY-MM-DD)")]
+        until: String,
+
+        #[arg(long, help = "Why the hold is placed, e.g. a case or ticket reference")]
+        reason: String,
+    },
+
+    #[command(about = "Release the holds on a prefix")]
+    Release {
+        #[arg(long, help = "Key prefix the hold was placed on")]
+        prefix: String,
+    },
+
+    #[command(about = "List all holds")]
+    List,
+}
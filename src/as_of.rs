@@ -0,0 +1,46 @@
+// This module contains point-in-time snapshot selection for the Rustored application
+// Operators usually know when they want to go back to rather than which key
+// holds that state. `--as-of` picks the newest snapshot taken at or before a
+// given time, optionally limited to a key prefix such as one database's dumps.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use log::debug;
+use crate::ui::models::BackupMetadata;
+
+/// Formats accepted for times without a zone, which are read as UTC
+const NAIVE_FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"];
+
+/// Parse an `--as-of` time
+///
+/// Accepts RFC 3339 (`2024-06-01T03:00:00+02:00`), a date and time without a
+/// zone (`2024-06-01 03:00`), read as UTC, or a date alone, which means the
+/// end of that day in UTC so every snapshot taken on it qualifies.
+pub fn parse_as_of(value: &str) -> Result<DateTime<Utc>> {
+    debug!("Parsing as-of time {}", value);
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Some(time) = NAIVE_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(value, format).ok()) {
+        return Ok(time.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|time| time.and_utc())
+        .ok_or_else(|| anyhow!("Invalid time '{}', expected e.g. \"2024-06-01 03:00\" (UTC) or RFC 3339", value))
+}
+
+/// Newest snapshot under `prefix` last modified at or before `as_of`
+///
+/// Snapshots modified at the same second are told apart by key, so the choice
+/// is stable for timestamped key names.
+pub fn newest_as_of<'a>(snapshots: &'a [BackupMetadata], prefix: &str, as_of: DateTime<Utc>) -> Option<&'a BackupMetadata> {
+    debug!("Finding newest snapshot under {} as of {}", prefix, as_of);
+    let cutoff = as_of.timestamp() as f64;
+    snapshots
+        .iter()
+        .filter(|snapshot| snapshot.key.starts_with(prefix) && snapshot.last_modified <= cutoff)
+        .max_by(|a, b| a.last_modified.total_cmp(&b.last_modified).then_with(|| a.key.cmp(&b.key)))
+}
@@ -1,26 +1,88 @@
-use anyhow::{Context, Result};
-use std::process::Command;
-use log::{debug, error};
+use anyhow::{anyhow, Context, Result};
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::process::{Command, Stdio};
+use std::task::{ready, Context as TaskContext, Poll};
+use std::future::Future;
+use log::{debug, error, info, warn};
+use chrono::{DateTime, Utc};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::process::ChildStdout;
+use tokio::task::JoinHandle;
+use crate::compression::{decompress_file, Compression};
+use crate::encryption::ClientEncryption;
+use crate::presets::RestoreOptions;
+use crate::restore_tool::RestoreTool;
+use crate::storage::SnapshotStore;
+use crate::ui::models::PostgresConfig;
 
-pub async fn dump_database(
+/// Label attached to snapshots taken with the TUI "backup now" action
+pub const PRE_CHANGE_LABEL: &str = "pre-change";
+
+/// Build the S3 key for a labelled snapshot of a database
+///
+/// The label is embedded in the key itself so that it survives a plain
+/// `ListObjectsV2` call, which does not return object metadata or tags.
+///
+/// # Arguments
+///
+/// * `prefix` - S3 prefix the snapshot is uploaded under (may be empty)
+/// * `db_name` - Name of the database being dumped
+/// * `label` - Label to embed in the key, e.g. `pre-change`
+/// * `timestamp` - Time the snapshot was taken
+///
+/// # Returns
+///
+/// The full object key, e.g. `backups/mydb-pre-change-20250101T120000Z.dump`
+pub fn labeled_snapshot_key(prefix: &str, db_name: &str, label: &str, timestamp: DateTime<Utc>) -> String {
+    debug!("Building labelled snapshot key for database {} with label {}", db_name, label);
+    let file_name = format!("{}-{}-{}.dump", db_name, label, timestamp.format("%Y%m%dT%H%M%SZ"));
+    join_prefix(prefix, file_name)
+}
+
+/// Build the S3 key for an unlabelled snapshot of a database
+///
+/// # Arguments
+///
+/// * `prefix` - S3 prefix the snapshot is uploaded under (may be empty)
+/// * `db_name` - Name of the database being dumped
+/// * `timestamp` - Time the snapshot was taken
+///
+/// # Returns
+///
+/// The full object key, e.g. `backups/mydb-20250101T120000Z.dump`
+pub fn snapshot_key(prefix: &str, db_name: &str, timestamp: DateTime<Utc>) -> String {
+    debug!("Building snapshot key for database {}", db_name);
+    let file_name = format!("{}-{}.dump", db_name, timestamp.format("%Y%m%dT%H%M%SZ"));
+    join_prefix(prefix, file_name)
+}
+
+/// Put a file name under an S3 prefix, adding a `/` between them if needed
+pub(crate) fn join_prefix(prefix: &str, file_name: String) -> String {
+    if prefix.is_empty() {
+        file_name
+    } else if prefix.ends_with('/') {
+        format!("{}{}", prefix, file_name)
+    } else {
+        format!("{}/{}", prefix, file_name)
+    }
+}
+
+/// Build the pg_dump command shared by file and stream dumps
+///
+/// Without a `--file` argument pg_dump writes the dump to stdout.
+pub(crate) fn pg_dump_command(
     name: &str,
-    output: &str,
     host: &str,
     port: u16,
     username: Option<&str>,
     password: Option<&str>,
-    ssl: bool,
-) -> Result<()> {
-
-    // Add PGSSLMODE environment variable if SSL is enabled
-    if ssl {
-        std::env::set_var("PGSSLMODE", "require");
-    }
-
+    custom_format: bool,
+) -> Command {
     debug!("Building pg_dump command");
     let mut cmd = Command::new("pg_dump");
     cmd.arg("--dbname").arg(name)
-        .arg("--file").arg(output)
         .arg("--host").arg(host)
         .arg("--port").arg(port.to_string());
 
@@ -28,25 +90,154 @@ pub async fn dump_database(
         cmd.arg("--username").arg(user);
     }
 
+    if custom_format {
+        cmd.arg("--format").arg("custom");
+    }
+
+    // pg_dump only accepts the password through the environment
     if let Some(pass) = password {
-        cmd.arg("--password").arg(pass);
+        cmd.env("PGPASSWORD", pass);
+    }
+    cmd
+}
+
+/// Dump a database with pg_dump
+///
+/// # Arguments
+///
+/// * `name` - Name of the database to dump
+/// * `output` - Path of the file to write the dump to
+/// * `host`, `port`, `username`, `password`, `ssl` - Connection settings
+/// * `custom_format` - Write a pg_restore-compatible custom format archive instead of plain SQL
+/// * `compression` - Codec and level to compress the dump with as it is written, if any
+///
+/// # Returns
+///
+/// The path the dump was written to, `output` with the codec's extension when compressed
+#[allow(clippy::too_many_arguments)]
+pub async fn dump_database(
+    name: &str,
+    output: &str,
+    host: &str,
+    port: u16,
+    username: Option<&str>,
+    password: Option<&str>,
+    ssl: bool,
+    custom_format: bool,
+    compression: Option<Compression>,
+) -> Result<String> {
+    debug!("Dumping database {} to {}", name, output);
+
+    // Add PGSSLMODE environment variable if SSL is enabled
+    if ssl {
+        std::env::set_var("PGSSLMODE", "require");
+    }
+
+    let mut cmd = pg_dump_command(name, host, port, username, password, custom_format);
+    if let Some(compression) = compression {
+        return dump_compressed(cmd, &compression.output_path(output), compression).await;
     }
+    cmd.arg("--file").arg(output);
 
     debug!("Executing pg_dump command");
-    let output = cmd
+    // Killed if the dump is cancelled
+    let dumped = tokio::process::Command::from(cmd)
+        .kill_on_drop(true)
         .output()
+        .await
         .context("Failed to execute pg_dump")?;
 
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
+    if !dumped.status.success() {
+        let error_msg = String::from_utf8_lossy(&dumped.stderr);
         error!("pg_dump failed: {}", error_msg);
         anyhow::bail!("pg_dump failed: {}", error_msg);
     }
 
-    Ok(())
+    Ok(output.to_string())
+}
+
+/// Pipe pg_dump's output through a compressor into `output`
+async fn dump_compressed(cmd: Command, output: &str, compression: Compression) -> Result<String> {
+    debug!("Dumping with {} compression to {}", compression.codec, output);
+    // Both are killed if the dump is cancelled
+    let mut dump = tokio::process::Command::from(cmd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute pg_dump")?;
+    let stdout: Stdio = dump.stdout.take().ok_or_else(|| anyhow!("pg_dump output is not available"))?.try_into()?;
+    let compressor = compression.spawn(stdout, Path::new(output))?;
+    let (dumped, compressed) = tokio::join!(dump.wait_with_output(), compressor.wait_with_output());
+
+    let dumped = dumped.context("Failed to wait for pg_dump")?;
+    if !dumped.status.success() {
+        let error_msg = String::from_utf8_lossy(&dumped.stderr);
+        error!("pg_dump failed: {}", error_msg);
+        anyhow::bail!("pg_dump failed: {}", error_msg);
+    }
+    let compressed = compressed.with_context(|| format!("Failed to wait for {}", compression.codec))?;
+    if !compressed.status.success() {
+        anyhow::bail!("{} compression failed: {}", compression.codec, String::from_utf8_lossy(&compressed.stderr).trim());
+    }
+    info!("Dumped and compressed to {}", output);
+    Ok(output.to_string())
 }
 
-pub fn restore_database(
+/// Build the pg_restore command shared by file and stream restores
+///
+/// Without an input file argument pg_restore reads the archive from stdin.
+fn pg_restore_command(name: &str, host: &str, port: u16, username: Option<&str>, options: &RestoreOptions) -> Command {
+    debug!("Building pg_restore command");
+    let mut cmd = Command::new("pg_restore");
+    cmd.arg("--host").arg(host)
+        .arg("--port").arg(port.to_string())
+        .arg("-c").arg("--if-exists")
+        .arg("--dbname").arg(name)
+        .args(options.pg_restore_args());
+
+    if let Some(user) = username {
+        cmd.arg("--username").arg(user);
+    }
+    cmd
+}
+
+/// A snapshot file in a form pg_restore reads, and the temporary files behind it
+pub struct RestoreInput {
+    /// Archive file or dump directory to hand to pg_restore
+    pub path: std::path::PathBuf,
+    /// Whether the snapshot is a directory-format dump, which restores with parallel jobs
+    pub directory: bool,
+    _decompressed: Option<tempfile::NamedTempFile>,
+    _unpacked: Option<tempfile::TempDir>,
+}
+
+/// Decompress and unpack a downloaded snapshot for pg_restore
+///
+/// The temporary copies are removed when the returned value is dropped.
+///
+/// # Arguments
+///
+/// * `input` - Path of the downloaded snapshot
+pub async fn restore_input(input: &str) -> Result<RestoreInput> {
+    debug!("Preparing {} for pg_restore", input);
+    // pg_restore cannot read compressed snapshots, so they are decompressed first
+    let decompressed = decompress_file(Path::new(input)).await?;
+    let path = decompressed.as_ref().map_or(Path::new(input), |file| file.path()).to_path_buf();
+    // Packed directory-format dumps are unpacked so pg_restore can run parallel jobs
+    let unpacked = match crate::directory_dump::is_tar_file(&path)? {
+        true => Some(crate::directory_dump::unpack(&path).await?),
+        false => None,
+    };
+    if unpacked.as_ref().is_some_and(|dir| crate::wal::is_base_backup_dir(dir.path())) {
+        return Err(anyhow!("{} is a base backup, which pg_restore cannot read; recover it to a point in time with `rustored wal recover` instead", input));
+    }
+    let path = unpacked.as_ref().map_or(path, |dir| dir.path().to_path_buf());
+    Ok(RestoreInput { path, directory: unpacked.is_some(), _decompressed: decompressed, _unpacked: unpacked })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn restore_database(
     name: &str,
     input: &str,
     host: &str,
@@ -54,6 +245,7 @@ pub fn restore_database(
     username: Option<&str>,
     password: Option<&str>,
     ssl: bool,
+    options: &RestoreOptions,
 ) -> Result<()> {
     // Add PGSSLMODE environment variable if SSL is enabled
     if ssl {
@@ -66,37 +258,539 @@ pub fn restore_database(
         std::env::set_var("PGSSLMODE", "disable");
     }
 
-    debug!("Building pg_restore command");
+    if let Some(pass) = password {
+        std::env::set_var("PGPASSWORD", pass);
+    }
 
-    let mut cmd = Command::new("pg_restore");
-    cmd.arg("--host").arg(host)
+    let prepared = restore_input(input).await?;
+    let mut options = options.clone();
+    if prepared.directory {
+        let jobs = *options.jobs.get_or_insert_with(crate::directory_dump::default_jobs);
+        info!("Restoring directory-format dump with {} jobs", jobs);
+    }
+    let options = &options;
+    let input = prepared.path.to_str().ok_or_else(|| anyhow!("Invalid snapshot path"))?;
+    let tool = RestoreTool::detect_file(&prepared.path, options.restore_command.as_deref())?;
+    info!("Restoring {} with {}", input, tool);
+    let mut cmd = match &tool {
+        RestoreTool::PgRestore => return run_pg_restore(name, input, &prepared.path, host, port, username, options).await,
+        RestoreTool::Psql => psql_file_command(name, input, host, port, username, options)?,
+        RestoreTool::Custom(_) if options.row_filters.is_some() => {
+            return Err(anyhow!("Row filters need a pg_dump archive; {} restores the snapshot whole", tool));
+        }
+        RestoreTool::Custom(template) => crate::restore_tool::custom_command(template, input, name, host, port, username)?,
+    };
+    let program = tool.to_string();
+    let span = crate::telemetry::Span::start("restore_command").with("database", name).with("tool", &program);
+    crate::telemetry::instrument(span, async {
+        // Killed if the restore is cancelled
+        let output = cmd.kill_on_drop(true).output().await.with_context(|| format!("Failed to execute {}", program))?;
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            error!("{} failed: {}", program, error_msg);
+            anyhow::bail!("{} failed: {}", program, error_msg.trim());
+        }
+        Ok(())
+    }).await
+}
+
+/// Build the psql command that restores a plain SQL dump file
+///
+/// Options that only pg_restore understands are refused rather than silently ignored.
+fn psql_file_command(name: &str, input: &str, host: &str, port: u16, username: Option<&str>, options: &RestoreOptions) -> Result<tokio::process::Command> {
+    debug!("Building psql command for plain SQL dump {}", input);
+    if !options.include_tables.is_empty() || !options.exclude_tables.is_empty() {
+        return Err(anyhow!("Restoring only some tables needs a pg_dump archive; a plain SQL dump is restored whole"));
+    }
+    if options.drop_indexes {
+        return Err(anyhow!("Leaving out indexes needs a pg_dump archive; a plain SQL dump is restored whole"));
+    }
+    if options.row_filters.is_some() {
+        return Err(anyhow!("Row filters need a pg_dump archive; a plain SQL dump is restored whole"));
+    }
+    let mut cmd = tokio::process::Command::new("psql");
+    cmd.args(["--no-psqlrc", "--quiet", "--set", "ON_ERROR_STOP=1"])
+        .arg("--host").arg(host)
         .arg("--port").arg(port.to_string())
-        .arg("-C").arg("-c").arg("--if-exists")
         .arg("--dbname").arg(name)
-        .arg(input);
-
+        .arg("--file").arg(input)
+        .stdout(std::process::Stdio::null());
     if let Some(user) = username {
         cmd.arg("--username").arg(user);
     }
+    Ok(cmd)
+}
 
-    if let Some(pass) = password {
-        std::env::set_var("PGPASSWORD", pass);
+/// Run pg_restore on a prepared archive or dump directory
+///
+/// With row filters, the data of the filtered tables is left out of the main
+/// run and loaded through `row_filter::staged_load` before the post-data
+/// section, so indexes and foreign keys are built over the rows kept. A gentle
+/// restore runs each section on its own with a pause between them, and copies
+/// table data through psql at its copy rate, if it has one.
+#[allow(clippy::too_many_arguments)]
+async fn run_pg_restore(
+    name: &str,
+    input: &str,
+    path: &Path,
+    host: &str,
+    port: u16,
+    username: Option<&str>,
+    options: &RestoreOptions,
+) -> Result<()> {
+    debug!("Running pg_restore on {}", input);
+    let pause = options.section_pause();
+    let copy_rate = options.copy_rate_limit()?;
+    if options.row_filters.is_none() && pause.is_none() {
+        // Only the chosen tables are restored, through a filtered table of contents
+        let use_list = crate::toc::write_use_list(path, &options.include_tables, &options.exclude_tables).await?;
+        return exec_pg_restore(name, input, host, port, username, options, use_list.as_ref().map(|list| list.path()), &[]).await;
     }
+    let toc = crate::toc::Toc::read(path).await?;
+    let (list, staged) = match &options.row_filters {
+        Some(filters) => crate::row_filter::RowFilters::load(Path::new(filters))?.plan(&toc, &options.include_tables, &options.exclude_tables)?,
+        None => (crate::row_filter::RowFilters::default().plan(&toc, &options.include_tables, &options.exclude_tables)?.0, Vec::new()),
+    };
+    let use_list = tempfile::NamedTempFile::new().context("Failed to create use-list")?;
+    tokio::fs::write(use_list.path(), &list).await.context("Failed to write use-list")?;
+    info!("Restoring {} section by section with {} row filtered tables", name, staged.len());
+
+    let mut section_options = options.clone();
+    section_options.drop_indexes = false;
+    let section_pause = || async {
+        if let Some(pause) = pause {
+            info!("Pausing {}s before the next section of the restore of {}", pause.as_secs(), name);
+            tokio::time::sleep(pause).await;
+        }
+    };
+    exec_pg_restore(name, input, host, port, username, &section_options, Some(use_list.path()), &["--section", "pre-data"]).await?;
+    section_pause().await;
+    match copy_rate {
+        // Copied through psql, where the script's rate can be capped
+        Some(rate) => {
+            load_data_script(path, &list, None, Some(rate), name, host, port, username).await?;
+        }
+        None => exec_pg_restore(name, input, host, port, username, &section_options, Some(use_list.path()), &["--section", "data"]).await?,
+    }
+    for (entry, predicate) in &staged {
+        crate::row_filter::staged_load(path, entry, predicate, copy_rate, name, host, port, username).await?;
+    }
+    if options.drop_indexes {
+        return Ok(());
+    }
+    section_pause().await;
+    exec_pg_restore(name, input, host, port, username, &section_options, Some(use_list.path()), &["--section", "post-data"]).await
+}
+
+/// Load the data section of an archive through psql, from the script pg_restore writes of it
+///
+/// This is the native data path: the script can be rewritten on its way, as
+/// row filters do, and the rate it is fed to psql at capped, as gentle
+/// restores do. It loads in a single transaction.
+///
+/// # Arguments
+///
+/// * `archive` - Custom-format file or directory-format dump
+/// * `list` - Use-list of the entries to load
+/// * `rewriter` - Rewrites the script's `COPY` blocks into staged, filtered loads
+/// * `rate` - Bytes per second the script is fed at, unlimited if `None`
+/// * `name` - Database to load into
+/// * `host` - PostgreSQL server host
+/// * `port` - PostgreSQL server port
+/// * `username` - User to connect as, if set
+#[allow(clippy::too_many_arguments)]
+pub async fn load_data_script(
+    archive: &Path,
+    list: &str,
+    mut rewriter: Option<crate::row_filter::StagedCopy>,
+    rate: Option<u64>,
+    name: &str,
+    host: &str,
+    port: u16,
+    username: Option<&str>,
+) -> Result<()> {
+    debug!("Loading data of {:?} through psql at {:?} bytes per second", archive, rate);
+    let use_list = tempfile::NamedTempFile::new().context("Failed to create use-list")?;
+    tokio::fs::write(use_list.path(), list).await.context("Failed to write use-list")?;
+    let mut dump = tokio::process::Command::new("pg_restore")
+        .args(["--section", "data", "--file", "-", "--use-list"])
+        .arg(use_list.path())
+        .arg(archive)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute pg_restore")?;
+    let mut psql = tokio::process::Command::new("psql");
+    psql.args(["--no-psqlrc", "--quiet", "--single-transaction", "--set", "ON_ERROR_STOP=1"])
+        .arg("--host").arg(host)
+        .arg("--port").arg(port.to_string())
+        .arg("--dbname").arg(name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(user) = username {
+        psql.arg("--username").arg(user);
+    }
+    let mut psql = psql.spawn().context("Failed to execute psql")?;
+
+    let budget = crate::bandwidth::TransferBudget::default();
+    budget.set_limit(rate);
+    let mut script = tokio::io::BufReader::new(dump.stdout.take().ok_or_else(|| anyhow!("pg_restore has no output"))?);
+    let mut sql = psql.stdin.take().ok_or_else(|| anyhow!("psql has no input"))?;
+    let mut line = Vec::new();
+    let piped: Result<()> = async {
+        while tokio::io::AsyncBufReadExt::read_until(&mut script, b'\n', &mut line).await? > 0 {
+            budget.take(name, line.len() as u64).await;
+            match rewriter.as_mut() {
+                Some(rewriter) => sql.write_all(&rewriter.line(&line)).await?,
+                None => sql.write_all(&line).await?,
+            }
+            line.clear();
+        }
+        sql.shutdown().await?;
+        Ok(())
+    }.await;
+    drop(sql);
+    drop(script);
+    if piped.is_err() {
+        // psql stopped reading, so pg_restore would block on its full pipe forever
+        let _ = dump.start_kill();
+    }
+
+    // psql's error is the cause when both fail, pg_restore only lost its reader
+    let loaded = psql.wait_with_output().await.context("Failed to execute psql")?;
+    if !loaded.status.success() {
+        return Err(anyhow!("psql failed loading table data: {}", String::from_utf8_lossy(&loaded.stderr).trim()));
+    }
+    piped.context("Failed to stream table data into psql")?;
+    let dumped = dump.wait_with_output().await.context("Failed to execute pg_restore")?;
+    if !dumped.status.success() {
+        return Err(anyhow!("pg_restore failed: {}", String::from_utf8_lossy(&dumped.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Run one pg_restore of an archive, optionally through a use-list and with extra arguments
+#[allow(clippy::too_many_arguments)]
+async fn exec_pg_restore(
+    name: &str,
+    input: &str,
+    host: &str,
+    port: u16,
+    username: Option<&str>,
+    options: &RestoreOptions,
+    use_list: Option<&Path>,
+    extra: &[&str],
+) -> Result<()> {
+    debug!("Executing pg_restore on {} with {:?}", input, extra);
+    let mut cmd = pg_restore_command(name, host, port, username, options);
+    if let Some(list) = use_list {
+        cmd.arg("--use-list").arg(list);
+    }
+    cmd.args(extra).arg(input);
 
     // Create a debug-friendly representation of the command
-    let cmd_str = format!("pg_restore --host {} --port {} -C -c --if-exists --dbname {} {} {}",
-        host, port, name, username.map_or(String::new(), |u| format!(" --username {}", u)), input,
+    let cmd_str = format!("pg_restore --host {} --port {} -c --if-exists --dbname {} {} {}{}{} {}",
+        host, port, name, options.pg_restore_args().join(" "), username.map_or(String::new(), |u| format!(" --username {}", u)),
+        use_list.map_or(String::new(), |list| format!(" --use-list {}", list.display())),
+        extra.iter().map(|arg| format!(" {}", arg)).collect::<String>(), input,
     );
     debug!("Executing pg_restore command: {} to database {}", cmd_str, name);
-    let output = cmd
-        .output()
-        .context("Failed to execute pg_restore")?;
+    let span = crate::telemetry::Span::start("pg_restore").with("database", name).with("jobs", options.jobs.unwrap_or(1));
+    crate::telemetry::instrument(span, async {
+        // Killed if the restore is cancelled
+        let output = tokio::process::Command::from(cmd)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("Failed to execute pg_restore")?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            error!("pg_restore failed: {}", error_msg);
+            anyhow::bail!("pg_restore failed: {}", error_msg);
+        }
 
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        error!("pg_restore failed: {}", error_msg);
-        anyhow::bail!("pg_restore failed: {}", error_msg);
+        Ok(())
+    }).await
+}
+
+/// Output of a running pg_dump, read as a stream
+///
+/// Reads return the dump as pg_dump writes it. Once the output ends, the
+/// final read fails if pg_dump exited with an error, so a truncated dump is
+/// never mistaken for a complete one.
+pub struct DumpStream {
+    stdout: ChildStdout,
+    /// Resolves to how pg_dump exited, taken once it has been reported
+    status: Option<JoinHandle<Result<()>>>,
+}
+
+impl AsyncRead for DumpStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.stdout).poll_read(cx, buf))?;
+        if buf.filled().len() > filled || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        // End of output: surface how pg_dump exited
+        let Some(status) = this.status.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let result = ready!(Pin::new(status).poll(cx));
+        this.status = None;
+        Poll::Ready(match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(io::Error::other(e.to_string())),
+            Err(e) => Err(io::Error::other(e)),
+        })
     }
+}
 
-    Ok(())
+/// Dump a database with pg_dump as a stream instead of a file
+///
+/// Must be called from within a Tokio runtime. The dump can be piped to any
+/// sink, such as an upload or `restore_from_stream`, without touching disk.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `name` - Name of the database to dump
+/// * `custom_format` - Write a pg_restore-compatible custom format archive instead of plain SQL
+pub fn dump_stream(pg_config: &PostgresConfig, name: &str, custom_format: bool) -> Result<DumpStream> {
+    debug!("Starting streaming dump of database {}", name);
+    let mut cmd = tokio::process::Command::from(pg_dump_command(
+        name,
+        pg_config.host.as_deref().unwrap_or("localhost"),
+        pg_config.port.unwrap_or(5432),
+        pg_config.username.as_deref(),
+        pg_config.password.as_deref(),
+        custom_format,
+    ));
+    if pg_config.use_ssl {
+        cmd.env("PGSSLMODE", "require");
+    }
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+    let mut child = cmd.spawn().context("Failed to execute pg_dump")?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow!("pg_dump output is not available"))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| anyhow!("pg_dump errors are not available"))?;
+
+    // Collect errors while the dump is read, so a full stderr pipe never stalls pg_dump
+    let status = tokio::spawn(async move {
+        let mut errors = String::new();
+        stderr.read_to_string(&mut errors).await?;
+        let status = child.wait().await?;
+        if !status.success() {
+            error!("pg_dump failed: {}", errors);
+            anyhow::bail!("pg_dump failed: {}", errors);
+        }
+        Ok(())
+    });
+    Ok(DumpStream { stdout, status: Some(status) })
+}
+
+/// Bytes copied between two progress reports of `copy_with_progress`
+pub const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// Copy a stream into a writer, reporting how many bytes have been copied
+///
+/// `on_progress` is called each time another `PROGRESS_INTERVAL` bytes have
+/// been copied, and once more at the end with the total.
+///
+/// # Returns
+///
+/// The number of bytes copied
+pub async fn copy_with_progress<R, W>(mut reader: R, mut writer: W, mut on_progress: impl FnMut(u64)) -> Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    debug!("Copying stream with progress reports");
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut copied = 0u64;
+    let mut reported = 0u64;
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buf[..read]).await?;
+        copied += read as u64;
+        if copied - reported >= PROGRESS_INTERVAL {
+            on_progress(copied);
+            reported = copied;
+        }
+    }
+    writer.flush().await?;
+    on_progress(copied);
+    Ok(copied)
+}
+
+/// Dump a database in custom format to a file, reporting the dump size as it grows
+///
+/// The custom format is compressed by pg_dump itself, so the file can be
+/// uploaded as-is and restored with pg_restore.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `name` - Name of the database to dump
+/// * `output` - Path of the file to write the dump to
+/// * `on_progress` - Called with the number of bytes dumped so far
+///
+/// # Returns
+///
+/// The size of the dump in bytes
+pub async fn dump_to_file(pg_config: &PostgresConfig, name: &str, output: &Path, on_progress: impl FnMut(u64)) -> Result<u64> {
+    debug!("Dumping database {} to {:?}", name, output);
+    let stream = dump_stream(pg_config, name, true)?;
+    let file = tokio::fs::File::create(output)
+        .await
+        .with_context(|| format!("Failed to create dump file {:?}", output))?;
+    copy_with_progress(stream, file, on_progress).await
+}
+
+/// Dump a database and upload it as a snapshot under `prefix`
+///
+/// This is the one-step backup behind the `backup` command and the schedule
+/// daemon. The dump goes through a temporary file, removed once uploaded, so
+/// backends that need the size up front can upload it. With `jobs` set, the
+/// database is dumped in directory format with that many workers and
+/// uploaded as a `.dir.tar` snapshot. With age or GPG recipients set, only
+/// the encrypted dump is uploaded. A manifest with the uploaded file's
+/// checksum is written next to the snapshot.
+///
+/// # Arguments
+///
+/// * `store` - Storage backend to upload the snapshot to
+/// * `pg_config` - Connection settings of the server
+/// * `prefix` - Key prefix the snapshot is uploaded under (may be empty)
+/// * `name` - Name of the database to back up
+/// * `label` - Label to embed in the key, if any
+/// * `jobs` - Number of parallel pg_dump workers, for a directory-format dump
+/// * `encryption` - Recipients to encrypt the dump for before upload
+/// * `on_progress` - Called with the number of bytes dumped so far
+///
+/// # Returns
+///
+/// The key of the uploaded snapshot
+#[allow(clippy::too_many_arguments)]
+pub async fn backup_database(
+    store: &dyn SnapshotStore,
+    pg_config: &PostgresConfig,
+    prefix: &str,
+    name: &str,
+    label: Option<&str>,
+    jobs: Option<u32>,
+    encryption: &ClientEncryption,
+    on_progress: impl FnMut(u64),
+) -> Result<String> {
+    debug!("Backing up database {} under {}", name, prefix);
+    let dump_file = tempfile::NamedTempFile::new()?;
+    let started = Utc::now();
+    let size = match jobs {
+        Some(jobs) => crate::directory_dump::dump_directory(pg_config, name, jobs, dump_file.path(), on_progress).await?,
+        None => dump_to_file(pg_config, name, dump_file.path(), on_progress).await?,
+    };
+
+    let now = Utc::now();
+    let key = match label {
+        Some(label) => labeled_snapshot_key(prefix, name, label, now),
+        None => snapshot_key(prefix, name, now),
+    };
+    let key = match jobs {
+        Some(_) => crate::directory_dump::directory_key(&key),
+        None => key,
+    };
+    let encrypted = encryption.encrypt_file(dump_file.path()).await?;
+    let (upload, key) = match &encrypted {
+        Some(encrypted) => (encrypted.path(), encryption.encrypted_key(&key)),
+        None => (dump_file.path(), key),
+    };
+    info!("Uploading {} ({} bytes dumped)", key, size);
+    store.upload_file(upload, &key, label).await?;
+    crate::manifest::record_backup(store, &key, name, upload, started, now).await;
+    Ok(key)
+}
+
+/// Restore a pg_dump custom format archive read from a stream into a new database
+///
+/// Like a restore from a file, the archive goes into the target database or a
+/// new `<word>-restored` one, or over an existing database when `options.mode`
+/// asks for it. pg_restore cannot run parallel jobs on a stream, so `jobs` is
+/// ignored.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `input` - The archive, e.g. a download or a `DumpStream`
+/// * `options` - Restore options, as for a file restore
+///
+/// # Returns
+///
+/// The name of the database restored into
+pub async fn restore_from_stream<R: AsyncRead + Unpin>(
+    pg_config: &PostgresConfig,
+    mut input: R,
+    options: &RestoreOptions,
+) -> Result<String> {
+    debug!("Starting restore from stream");
+    if !options.include_tables.is_empty() || !options.exclude_tables.is_empty() {
+        return Err(anyhow!("Restoring only some tables needs the table of contents of a snapshot file, which a stream does not have"));
+    }
+    if options.row_filters.is_some() {
+        return Err(anyhow!("Row filters need the table of contents of a snapshot file, which a stream does not have"));
+    }
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    let name = crate::postgres::prepare_restore_database(
+        &client,
+        pg_config.db_name.as_deref(),
+        pg_config.target_db.as_deref(),
+        options.mode,
+    ).await?;
+    // Dropped with the database if the restore is cancelled part way
+    let partial = crate::postgres::PartialRestore::new(client, &name, options);
+
+    let mut options = options.clone();
+    if options.jobs.take().is_some() {
+        warn!("pg_restore cannot run parallel jobs when reading from a stream, restoring with one job");
+    }
+    let span = crate::telemetry::Span::start("pg_restore").with("database", &name).with("jobs", 1);
+    let result = crate::telemetry::instrument(span, async move {
+        let mut cmd = tokio::process::Command::from(pg_restore_command(
+            &name,
+            pg_config.host.as_deref().unwrap_or("localhost"),
+            pg_config.port.unwrap_or(5432),
+            pg_config.username.as_deref(),
+            &options,
+        ));
+        if let Some(pass) = &pg_config.password {
+            cmd.env("PGPASSWORD", pass);
+        }
+        cmd.env("PGSSLMODE", if pg_config.use_ssl { "require" } else { "disable" });
+        cmd.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).kill_on_drop(true);
+        let mut child = cmd.spawn().context("Failed to execute pg_restore")?;
+
+        let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("pg_restore input is not available"))?;
+        let copied = tokio::io::copy(&mut input, &mut stdin).await;
+        // Closing stdin tells pg_restore the archive is complete
+        drop(stdin);
+        let output = child.wait_with_output().await.context("Failed to wait for pg_restore")?;
+
+        // pg_restore's own error explains a broken pipe better than the copy error does
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            error!("pg_restore failed: {}", error_msg);
+            anyhow::bail!("pg_restore failed: {}", error_msg);
+        }
+        let copied = copied.map_err(|e| anyhow!("Failed to stream the archive to pg_restore: {}", e))?;
+        info!("Restored {} bytes from stream to database: {}", copied, name);
+        Ok(name)
+    }).await;
+    partial.finish();
+    result
 }
@@ -0,0 +1,136 @@
+// This module contains the transfer budget of the Rustored application
+// `--max-bandwidth` caps the bytes per second all running jobs transfer
+// together. Rather than each job saturating the link on its own, the budget is
+// split evenly between the jobs transferring at the time, and a job's share
+// grows again as the others finish. Each job draws from its own token bucket,
+// refilled at its share of the limit, so a job with many parallel parts cannot
+// crowd out one with few.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Bytes in the `M` of a rate such as `50M`, matching the sizes shown elsewhere
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Parse a transfer rate in bytes per second, e.g. `50M`, `1.5G`, `800k` or `50MB/s`
+///
+/// # Arguments
+///
+/// * `value` - The rate as typed; a bare number is bytes per second
+pub fn parse_rate(value: &str) -> Result<u64> {
+    debug!("Parsing transfer rate {}", value);
+    let trimmed = value.trim();
+    let trimmed = trimmed.strip_suffix("/s").unwrap_or(trimmed);
+    let trimmed = trimmed.strip_suffix(['B', 'b']).unwrap_or(trimmed);
+    let (number, unit) = match trimmed.char_indices().last() {
+        Some((index, c)) if c.is_ascii_alphabetic() => (&trimmed[..index], c.to_ascii_lowercase()),
+        _ => (trimmed, ' '),
+    };
+    let multiplier = match unit {
+        ' ' => 1.0,
+        'k' => 1024.0,
+        'm' => MB,
+        'g' => MB * 1024.0,
+        _ => return Err(anyhow!("Unknown unit in transfer rate {}, use k, M or G", value)),
+    };
+    let rate = number.trim().parse::<f64>().map_err(|_| anyhow!("Invalid transfer rate {}, e.g. 50M or 1.5G", value))? * multiplier;
+    if rate < 1.0 {
+        return Err(anyhow!("Transfer rate {} must be at least one byte per second", value));
+    }
+    Ok(rate as u64)
+}
+
+/// Describe a transfer rate for the jobs panel, e.g. `12.5 MB/s`
+pub fn format_rate(bytes_per_second: f64) -> String {
+    debug!("Formatting transfer rate {}", bytes_per_second);
+    format!("{:.1} MB/s", bytes_per_second / MB)
+}
+
+/// Tokens a job has left, and when they were last refilled
+#[derive(Debug)]
+struct Share {
+    tokens: f64,
+    refilled: Instant,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    limit: Option<u64>,
+    shares: HashMap<String, Share>,
+}
+
+/// Bytes per second shared fairly between the jobs transferring at once
+///
+/// Cloning the budget shares it, as the progress board is shared. Without a
+/// limit, taking bytes never waits.
+#[derive(Debug, Clone, Default)]
+pub struct TransferBudget {
+    state: Arc<Mutex<BudgetState>>,
+}
+
+impl TransferBudget {
+    /// Set the limit every job shares, or lift it with `None`
+    pub fn set_limit(&self, limit: Option<u64>) {
+        debug!("Setting transfer budget to {:?} bytes per second", limit);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.limit = limit.filter(|limit| *limit > 0);
+        state.shares.clear();
+    }
+
+    /// The limit every job shares, if any
+    pub fn limit(&self) -> Option<u64> {
+        debug!("Reading transfer budget limit");
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).limit
+    }
+
+    /// Jobs currently drawing from the budget
+    pub fn active_jobs(&self) -> usize {
+        debug!("Counting jobs sharing the transfer budget");
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).shares.len()
+    }
+
+    /// Bytes per second each transferring job gets, if there is a limit
+    pub fn fair_share(&self) -> Option<f64> {
+        debug!("Computing fair share of the transfer budget");
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.limit.map(|limit| limit as f64 / state.shares.len().max(1) as f64)
+    }
+
+    /// Account for bytes a job transferred, waiting until they fit in its share
+    ///
+    /// A job joins the budget the first time it takes bytes, which shrinks the
+    /// share of every other job, and a share holds at most one second of
+    /// bytes so an idle job cannot save up a burst.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - Name of the job, as on the progress board
+    /// * `bytes` - Bytes the job just transferred
+    pub async fn take(&self, job: &str, bytes: u64) {
+        // No logging here: transfers take from the budget for every chunk read
+        let wait = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(limit) = state.limit else { return };
+            let now = Instant::now();
+            let jobs = state.shares.len() + usize::from(!state.shares.contains_key(job));
+            let rate = limit as f64 / jobs as f64;
+            let share = state.shares.entry(job.to_string()).or_insert(Share { tokens: 0.0, refilled: now });
+            share.tokens = (share.tokens + now.duration_since(share.refilled).as_secs_f64() * rate).min(rate);
+            share.refilled = now;
+            share.tokens -= bytes as f64;
+            (share.tokens < 0.0).then(|| Duration::from_secs_f64(-share.tokens / rate))
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Stop sharing the budget with a finished job
+    pub fn release(&self, job: &str) {
+        debug!("Releasing transfer budget share of {}", job);
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).shares.remove(job);
+    }
+}
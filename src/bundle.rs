@@ -0,0 +1,143 @@
+// This module contains restore bundles for the Rustored application
+// An application's data often spans several stores: the relational database,
+// a search index exported from it and a vector collection built from it.
+// Restored on their own they drift apart, so the backup manifest of a
+// PostgreSQL snapshot can declare a bundle naming the Elasticsearch and Qdrant
+// snapshots taken with it. A bundle restore downloads every member and
+// restores them to their targets together, behind one confirmation.
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::manifest::{read_manifest, write_manifest, Manifest};
+use crate::storage::SnapshotStore;
+use crate::ui::models::{BackupMetadata, RestoreTarget};
+
+/// Word typed to confirm a bundle restore
+pub const BUNDLE_CONFIRMATION: &str = "restore";
+
+/// Snapshots restored together with the PostgreSQL snapshot whose manifest declares them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    /// Key of the snapshot of the search index exported from the database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elasticsearch: Option<String>,
+    /// Key of the snapshot of the vector collection built from the database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qdrant: Option<String>,
+}
+
+impl Bundle {
+    /// Whether the bundle names any snapshot besides the PostgreSQL one
+    pub fn is_empty(&self) -> bool {
+        debug!("Checking if bundle is empty");
+        self.elasticsearch.is_none() && self.qdrant.is_none()
+    }
+
+    /// Keys of every member, with the target each is restored to, PostgreSQL first
+    pub fn keys<'a>(&'a self, postgres: &'a str) -> Vec<(RestoreTarget, &'a str)> {
+        debug!("Listing bundle members of {}", postgres);
+        let mut keys = vec![(RestoreTarget::Postgres, postgres)];
+        keys.extend(self.elasticsearch.as_deref().map(|key| (RestoreTarget::Elasticsearch, key)));
+        keys.extend(self.qdrant.as_deref().map(|key| (RestoreTarget::Qdrant, key)));
+        keys
+    }
+}
+
+/// One snapshot of a bundle and the target it is restored to
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleMember {
+    pub target: RestoreTarget,
+    pub snapshot: BackupMetadata,
+}
+
+impl BundleMember {
+    /// Name of the member's target, e.g. `Elasticsearch`
+    pub fn label(&self) -> &'static str {
+        debug!("Getting label of bundle member {}", self.snapshot.key);
+        match self.target {
+            RestoreTarget::Postgres => "PostgreSQL",
+            RestoreTarget::Elasticsearch => "Elasticsearch",
+            RestoreTarget::Qdrant => "Qdrant",
+            RestoreTarget::OpenSearch => "OpenSearch",
+            RestoreTarget::Meilisearch => "Meilisearch",
+            RestoreTarget::Weaviate => "Weaviate",
+            RestoreTarget::Milvus => "Milvus",
+            RestoreTarget::DynamoDb => "DynamoDB",
+            RestoreTarget::InfluxDb => "InfluxDB",
+        }
+    }
+}
+
+/// The manifest declaring the bundle a snapshot belongs to, if any
+///
+/// A snapshot belongs to a bundle if it is the PostgreSQL snapshot whose
+/// manifest declares it or one of the snapshots the bundle names.
+pub fn find_bundle<'a>(manifests: &'a HashMap<String, Manifest>, key: &str) -> Option<&'a Manifest> {
+    debug!("Finding bundle of {}", key);
+    if let Some(manifest) = manifests.get(key).filter(|manifest| manifest.bundle.is_some()) {
+        return Some(manifest);
+    }
+    // A snapshot named by several bundles belongs to the newest one
+    manifests
+        .values()
+        .filter(|manifest| {
+            manifest.bundle.as_ref().is_some_and(|bundle| bundle.keys(&manifest.key).iter().any(|(_, member)| *member == key))
+        })
+        .max_by_key(|manifest| manifest.started)
+}
+
+/// Look up the snapshots of the bundle a manifest declares
+///
+/// # Arguments
+///
+/// * `manifest` - Manifest of the PostgreSQL snapshot declaring the bundle
+/// * `snapshots` - Listed snapshots to find the members among
+///
+/// # Returns
+///
+/// The members, PostgreSQL first, or an error naming a member that is not listed
+pub fn resolve_members(manifest: &Manifest, snapshots: &[BackupMetadata]) -> Result<Vec<BundleMember>> {
+    debug!("Resolving bundle members of {}", manifest.key);
+    let bundle = manifest.bundle.as_ref().ok_or_else(|| anyhow!("{} does not declare a bundle", manifest.key))?;
+    bundle
+        .keys(&manifest.key)
+        .into_iter()
+        .map(|(target, key)| {
+            let snapshot = snapshots
+                .iter()
+                .find(|snapshot| snapshot.key == key)
+                .cloned()
+                .ok_or_else(|| anyhow!("Snapshot {} of the bundle of {} not found", key, manifest.key))?;
+            Ok(BundleMember { target, snapshot })
+        })
+        .collect()
+}
+
+/// Declare the bundle of a PostgreSQL snapshot in its manifest
+///
+/// Only snapshots with a manifest, as written by rustored backups, can
+/// declare a bundle. An empty bundle removes the declaration.
+///
+/// # Arguments
+///
+/// * `store` - Storage backend holding the snapshot and its manifest
+/// * `postgres` - Key of the PostgreSQL snapshot
+/// * `bundle` - The snapshots restored with it
+pub async fn declare_bundle(store: &dyn SnapshotStore, postgres: &str, bundle: Bundle) -> Result<Manifest> {
+    debug!("Declaring bundle of {}: {:?}", postgres, bundle);
+    let mut manifest = read_manifest(store, postgres)
+        .await?
+        .ok_or_else(|| anyhow!("{} has no manifest; only snapshots backed up by rustored can declare a bundle", postgres))?;
+    for (_, key) in bundle.keys(postgres).into_iter().skip(1) {
+        // Listed rather than read, since snapshots can be large
+        if !store.list_snapshots(key).await?.iter().any(|snapshot| snapshot.key == key) {
+            return Err(anyhow!("Snapshot {} not found", key));
+        }
+    }
+    manifest.bundle = (!bundle.is_empty()).then_some(bundle);
+    write_manifest(store, &manifest).await?;
+    info!("Declared bundle of {}: {:?}", postgres, manifest.bundle);
+    Ok(manifest)
+}
@@ -0,0 +1,102 @@
+// This module contains cancellation for the Rustored application
+// Downloads, uploads, dumps, restores and verifications all take a
+// `CancellationToken`, so the TUI, signal handlers and anything embedding the
+// library cancel them the same way. Each operation runs under a child of the
+// process-wide token: cancelling the child stops that operation, and a signal
+// cancels the root and with it everything still running.
+
+use anyhow::Result;
+use log::{debug, warn};
+use std::fmt;
+use std::future::Future;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Error returned by an operation that was cancelled before it finished
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cancelled {
+    /// What was cancelled, e.g. `Download of pg/a.dump`
+    pub operation: String,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cancelled", self.operation)
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl Cancelled {
+    /// Cancellation error for the named operation
+    pub fn error(operation: impl Into<String>) -> anyhow::Error {
+        let operation = operation.into();
+        debug!("{} cancelled", operation);
+        anyhow::Error::new(Cancelled { operation })
+    }
+}
+
+/// Whether an error means the operation was cancelled rather than failed
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    debug!("Checking whether error is a cancellation: {}", error);
+    error.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Run an operation until it finishes or the token is cancelled
+///
+/// On cancellation the operation's future is dropped, so it must clean up on
+/// drop; child processes are spawned with `kill_on_drop` for this reason.
+///
+/// # Arguments
+///
+/// * `token` - Token that cancels the operation
+/// * `operation` - Name used in the `Cancelled` error
+/// * `future` - The operation
+///
+/// # Returns
+///
+/// The operation's result, or a `Cancelled` error
+pub async fn cancellable<T>(token: &CancellationToken, operation: &str, future: impl Future<Output = Result<T>>) -> Result<T> {
+    debug!("Running cancellable operation: {}", operation);
+    tokio::select! {
+        // Never start work that is already cancelled
+        biased;
+        _ = token.cancelled() => Err(Cancelled::error(operation)),
+        result = future => result,
+    }
+}
+
+/// Cancel `token` on SIGINT or SIGTERM
+///
+/// A second signal exits straight away, for operations that do not check
+/// the token.
+pub fn cancel_on_signals(token: CancellationToken) {
+    debug!("Installing signal handlers for cancellation");
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        warn!("Cancelling, send the signal again to exit immediately");
+        token.cancel();
+        wait_for_signal().await;
+        std::process::exit(130);
+    });
+}
+
+/// Wait for SIGINT, or SIGTERM on unix
+async fn wait_for_signal() {
+    debug!("Waiting for a termination signal");
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
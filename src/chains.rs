@@ -0,0 +1,265 @@
+// This module contains backup chain verification for the Rustored application
+// Incremental backups are only restorable (and point-in-time recovery only
+// possible) when every link back to the base backup is present and intact.
+// Each backup in a chain is described by a `<backup>.manifest.json` object that
+// names its parent backup and the WAL range it covers. Links are checked in
+// parallel with a bounded worker pool, then assembled into chains from each
+// tip back to its base so broken chains can be reported as a whole.
+
+use anyhow::{anyhow, Context, Result};
+use futures_util::stream::{self, StreamExt};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use crate::storage::SnapshotStore;
+use crate::table::{Align, Cell, Table};
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::BackupMetadata;
+
+/// Suffix appended to a backup key to form the key of its chain manifest
+pub const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Default number of chain links checked at once
+pub const DEFAULT_CHAIN_WORKERS: usize = 4;
+
+/// Manifest describing one backup in a chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainManifest {
+    /// Key of the backup object this manifest describes
+    pub backup: String,
+    /// Key of the backup this one is an increment of, `None` for a base backup
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// First WAL location covered by the backup, e.g. `0/3000028`
+    pub start_lsn: String,
+    /// Last WAL location covered by the backup
+    pub end_lsn: String,
+    /// Hex encoded SHA-256 checksum of the backup object, checked when present
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl ChainManifest {
+    /// Key of the manifest object for a backup key
+    pub fn manifest_key(backup: &str) -> String {
+        debug!("Computing manifest key for {}", backup);
+        format!("{}{}", backup, MANIFEST_SUFFIX)
+    }
+}
+
+/// Parse a PostgreSQL LSN such as `16/B374D848` into a comparable number
+pub fn parse_lsn(lsn: &str) -> Result<u64> {
+    debug!("Parsing LSN {}", lsn);
+    let (high, low) = lsn.split_once('/').ok_or_else(|| anyhow!("Invalid LSN '{}'", lsn))?;
+    let high = u32::from_str_radix(high, 16).with_context(|| format!("Invalid LSN '{}'", lsn))?;
+    let low = u32::from_str_radix(low, 16).with_context(|| format!("Invalid LSN '{}'", lsn))?;
+    Ok((u64::from(high) << 32) | u64::from(low))
+}
+
+/// Result of checking a single link of a chain on its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkCheck {
+    /// Key of the backup the link describes
+    pub backup: String,
+    /// Key of the parent backup, if any
+    pub parent: Option<String>,
+    /// Parsed start and end LSN, if both were valid
+    pub lsn_range: Option<(u64, u64)>,
+    /// Problems found with this link
+    pub problems: Vec<String>,
+}
+
+/// Verification result of one chain, from its tip back to its base
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainReport {
+    /// Newest backup of the chain
+    pub tip: String,
+    /// Backups in the chain, from the tip back to the base
+    pub backups: Vec<String>,
+    /// Problems found anywhere in the chain
+    pub problems: Vec<String>,
+}
+
+impl ChainReport {
+    /// Whether the chain can be restored
+    pub fn is_ok(&self) -> bool {
+        debug!("Checking if chain {} is intact", self.tip);
+        self.problems.is_empty()
+    }
+}
+
+/// The chain reports as a table for `verify`
+pub fn table(reports: &[ChainReport]) -> Table {
+    debug!("Tabulating {} chain reports", reports.len());
+    let mut table = Table::new(&[("STATE", Align::Left), ("TIP", Align::Left), ("BACKUPS", Align::Right), ("PROBLEMS", Align::Left)]);
+    for report in reports {
+        let state = match report.is_ok() {
+            true => Cell::colored("ok", crossterm::style::Color::Green),
+            false => Cell::colored("broken", crossterm::style::Color::Red),
+        };
+        table.row(vec![state, report.tip.as_str().into(), report.backups.len().to_string().into(), report.problems.join("; ").into()]);
+    }
+    table
+}
+
+/// Check one link: parse its manifest, make sure its backup exists and matches its checksum
+///
+/// # Arguments
+///
+/// * `store` - Storage backend holding the chain
+/// * `manifest_key` - Key of the link's manifest
+/// * `objects` - Snapshots listed in the bucket, by key
+async fn check_link(
+    store: &dyn SnapshotStore,
+    manifest_key: &str,
+    objects: &HashMap<String, BackupMetadata>,
+) -> LinkCheck {
+    debug!("Checking chain link {}", manifest_key);
+    let backup = manifest_key.trim_end_matches(MANIFEST_SUFFIX).to_string();
+    let mut check = LinkCheck { backup: backup.clone(), parent: None, lsn_range: None, problems: Vec::new() };
+
+    let manifest: ChainManifest = match store.read_object(manifest_key).await {
+        Ok(Some(bytes)) => match serde_json::from_slice(&bytes) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                check.problems.push(format!("manifest {} is invalid: {}", manifest_key, e));
+                return check;
+            }
+        },
+        Ok(None) => {
+            check.problems.push(format!("manifest {} is missing", manifest_key));
+            return check;
+        }
+        Err(e) => {
+            check.problems.push(format!("manifest {} could not be read: {}", manifest_key, e));
+            return check;
+        }
+    };
+    if manifest.backup != backup {
+        check.problems.push(format!("manifest {} describes {} instead of {}", manifest_key, manifest.backup, backup));
+    }
+    check.parent = manifest.parent.clone();
+
+    match (parse_lsn(&manifest.start_lsn), parse_lsn(&manifest.end_lsn)) {
+        (Ok(start), Ok(end)) if start <= end => check.lsn_range = Some((start, end)),
+        (Ok(_), Ok(_)) => check.problems.push(format!("{} ends before it starts ({} < {})", backup, manifest.end_lsn, manifest.start_lsn)),
+        (Err(e), _) | (_, Err(e)) => check.problems.push(format!("{}: {}", backup, e)),
+    }
+
+    let Some(object) = objects.get(&backup) else {
+        check.problems.push(format!("backup {} is missing", backup));
+        return check;
+    };
+    if let Some(expected) = &manifest.sha256 {
+        match object_checksum(store, object).await {
+            Ok(actual) if &actual == expected => {}
+            Ok(actual) => check.problems.push(format!("backup {} checksum {} does not match manifest {}", backup, actual, expected)),
+            Err(e) => check.problems.push(format!("backup {} could not be read: {}", backup, e)),
+        }
+    }
+    check
+}
+
+/// Hex encoded SHA-256 checksum of a stored object, streamed without saving it
+async fn object_checksum(store: &dyn SnapshotStore, object: &BackupMetadata) -> Result<String> {
+    debug!("Computing checksum of {}", object.key);
+    let mut hasher = Sha256::new();
+    if object.size > 0 {
+        let mut body = store.read_range(&object.key, 0, object.size as u64 - 1).await?;
+        let mut buffer = [0; 1024 * 64]; // 64KB buffer
+        loop {
+            let n = body.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[0..n]);
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Assemble checked links into chains, one per tip, and find where they break
+///
+/// A tip is a backup that no other link names as its parent. Each chain is
+/// walked back to its base; a missing parent, a WAL gap between a parent and
+/// its increment, or a cycle breaks the chain, as do problems with any link.
+pub fn assemble_chains(links: &[LinkCheck]) -> Vec<ChainReport> {
+    debug!("Assembling chains from {} links", links.len());
+    let by_backup: BTreeMap<&str, &LinkCheck> = links.iter().map(|link| (link.backup.as_str(), link)).collect();
+    let parents: HashSet<&str> = links.iter().filter_map(|link| link.parent.as_deref()).collect();
+
+    by_backup
+        .values()
+        .filter(|link| !parents.contains(link.backup.as_str()))
+        .map(|tip| {
+            let mut report = ChainReport { tip: tip.backup.clone(), backups: Vec::new(), problems: Vec::new() };
+            let mut seen = HashSet::new();
+            let mut link = *tip;
+            loop {
+                report.backups.push(link.backup.clone());
+                report.problems.extend(link.problems.iter().cloned());
+                seen.insert(link.backup.as_str());
+
+                let Some(parent_key) = link.parent.as_deref() else { break };
+                if seen.contains(parent_key) {
+                    report.problems.push(format!("chain loops back to {}", parent_key));
+                    break;
+                }
+                let Some(parent) = by_backup.get(parent_key) else {
+                    report.problems.push(format!("parent {} of {} has no manifest", parent_key, link.backup));
+                    break;
+                };
+                // An increment must start where its parent ends or WAL is missing in between
+                if let (Some((start, _)), Some((_, parent_end))) = (link.lsn_range, parent.lsn_range) {
+                    if start > parent_end {
+                        report.problems.push(format!("WAL gap between {} and {}", parent.backup, link.backup));
+                    }
+                }
+                link = parent;
+            }
+            report
+        })
+        .collect()
+}
+
+/// Verify every backup chain under the browser's prefix
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket
+/// * `workers` - Maximum number of links checked at once
+///
+/// # Returns
+///
+/// One report per chain, broken chains first
+pub async fn verify_chains(browser: &mut SnapshotBrowser, workers: usize) -> Result<Vec<ChainReport>> {
+    debug!("Verifying backup chains with {} workers", workers);
+    browser.load_snapshots().await?;
+    let store: Arc<dyn SnapshotStore> = browser.store().await?;
+    let objects: HashMap<String, BackupMetadata> = browser.snapshots
+        .iter()
+        .map(|snapshot| (snapshot.key.clone(), snapshot.clone()))
+        .collect();
+    let manifests: Vec<String> = objects.keys().filter(|key| key.ends_with(MANIFEST_SUFFIX)).cloned().collect();
+    info!("Checking {} chain links", manifests.len());
+
+    let links: Vec<LinkCheck> = stream::iter(manifests)
+        .map(|key| {
+            let store = store.clone();
+            let objects = &objects;
+            async move { check_link(store.as_ref(), &key, objects).await }
+        })
+        .buffer_unordered(workers.max(1))
+        .collect()
+        .await;
+
+    let mut reports = assemble_chains(&links);
+    reports.sort_by_key(|report| report.is_ok());
+    for report in reports.iter().filter(|report| !report.is_ok()) {
+        warn!("Chain ending at {} is broken: {}", report.tip, report.problems.join("; "));
+    }
+    Ok(reports)
+}
@@ -0,0 +1,406 @@
+// This module contains the longer subcommands of the rustored binary
+// They print tables and ask for confirmation like the rest of main.rs, and
+// live here so main.rs stays a readable dispatch over the commands.
+
+use crate::args::{Cli, ShareAction};
+use crate::confirm_typed;
+use anyhow::Result;
+use crossterm::style::Color;
+use log::debug;
+use rustored::datastore::{DatastoreRestoreTarget, RestoreTarget};
+use rustored::download_ledger::DownloadLedger;
+use rustored::lint;
+use rustored::mapping::RestoreMapping;
+use rustored::retention::{self, RetentionPolicy};
+use rustored::pitr::{self, RecoveryWindow, ScratchInstance};
+use rustored::share;
+use rustored::table::{Align, Cell, Table};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::influx_lines::InfluxBucket;
+use rustored::dead_letter::RetryTargets;
+use rustored::ui::models::{
+    BulkConfig, DynamoDbConfig, ElasticsearchConfig, InfluxDbConfig, MeilisearchConfig, MilvusConfig, OpenSearchConfig, PostgresConfig, QdrantConfig,
+    QdrantUploadConfig, TlsConfig, WeaviateConfig,
+};
+use rustored::ui::rustored::RustoredApp;
+use rustored::weaviate_objects::WeaviateCredentials;
+use std::path::PathBuf;
+
+/// Delete the snapshots the retention rules no longer keep
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the bucket to prune
+/// * `policies` - The retention rules to apply
+/// * `dry_run` - Only list what would be kept and deleted
+/// * `yes` - Delete without asking for confirmation
+/// * `color` - Whether to color the table
+pub async fn prune(browser: &mut SnapshotBrowser, policies: &[RetentionPolicy], dry_run: bool, yes: bool, color: bool) -> Result<()> {
+    debug!("Pruning with {} retention rules", policies.len());
+    if policies.is_empty() {
+        return Err(anyhow::anyhow!("No retention rules: pass --keep-daily, --keep-weekly or --keep-monthly, or add [[retention]] to the presets file"));
+    }
+    browser.load_snapshots().await?;
+    browser.load_holds().await?;
+    let today = chrono::Utc::now().date_naive();
+    let plan = retention::plan_prune(&browser.snapshots, policies, &browser.holds, today)?;
+    for policy in policies {
+        println!("Retention: {}", policy.summary());
+    }
+    let mut table = Table::new(&[("ACTION", Align::Left), ("KEY", Align::Left), ("REASON", Align::Left)]);
+    for kept in &plan.keep {
+        table.row(vec![Cell::colored("keep", Color::Green), kept.key.as_str().into(), kept.reasons.join(", ").into()]);
+    }
+    for snapshot in &plan.delete {
+        table.row(vec![Cell::colored("delete", Color::Red), snapshot.key.as_str().into(), "".into()]);
+    }
+    print!("{}", table.render(color));
+    if plan.delete.is_empty() {
+        println!("Nothing to prune");
+        return Ok(());
+    }
+    if dry_run {
+        println!("Dry run: would delete {} snapshot(s)", plan.delete.len());
+        return Ok(());
+    }
+    if !yes && !confirm_typed("the word", rustored::ui::browser::DELETE_CONFIRMATION)? {
+        println!("Prune cancelled");
+        return Ok(());
+    }
+    browser.delete_snapshots(&plan.delete, today).await?;
+    println!("Pruned {} snapshot(s), kept {}", plan.delete.len(), plan.keep.len());
+    Ok(())
+}
+
+/// Export, import or list snapshots shared between teams
+pub async fn share(browser: &mut SnapshotBrowser, action: &ShareAction, secret: &str, color: bool) -> Result<()> {
+    debug!("Running share command");
+    match action {
+        ShareAction::Export { key, version_id, output } => {
+            let descriptor = share::export(browser, key, version_id.as_deref(), secret).await?;
+            match output {
+                Some(output) => std::fs::write(output, descriptor.to_json()?)?,
+                None => println!("{}", String::from_utf8(descriptor.to_json()?)?),
+            }
+        }
+        ShareAction::Import { file } => {
+            let (share, replaced) = share::import(browser, &std::fs::read(file)?, secret).await?;
+            println!("{} {} from {}", if replaced { "Updated the share of" } else { "Imported" }, share.key, share.bucket);
+        }
+        ShareAction::List => print!("{}", share::load_catalog(browser.store().await?.as_ref()).await?.table().render(color)),
+    }
+    Ok(())
+}
+
+/// Drop the throwaway restores older than an age
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `older_than` - The age, e.g. `7d`
+/// * `dry_run` - Only list what would be dropped and kept
+/// * `yes` - Drop without asking for confirmation
+/// * `color` - Whether to color the table
+pub async fn gc(pg_config: &PostgresConfig, older_than: &str, dry_run: bool, yes: bool, color: bool) -> Result<()> {
+    debug!("Collecting throwaway restores older than {}", older_than);
+    let plan = rustored::gc::plan(pg_config, rustored::gc::parse_age(older_than)?, chrono::Utc::now()).await?;
+    print!("{}", plan.table().render(color));
+    if plan.expired.is_empty() {
+        println!("No throwaway restores older than {}", older_than);
+        return Ok(());
+    }
+    if dry_run {
+        println!("Dry run: would drop {} database(s)", plan.expired.len());
+        return Ok(());
+    }
+    if !yes && !confirm_typed("the word", rustored::ui::browser::DELETE_CONFIRMATION)? {
+        println!("Gc cancelled");
+        return Ok(());
+    }
+    let dropped = rustored::gc::drop_expired(pg_config, &plan).await?;
+    println!("Dropped {} throwaway restore(s), kept {}", dropped.len(), plan.kept.len());
+    Ok(())
+}
+
+/// Lint the keys under a prefix, printing what does not fit
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the bucket to lint
+/// * `prefix` - Key prefix to lint under, or the configured one
+/// * `template` - The naming template snapshots should follow
+/// * `json` - Also print the report as JSON
+/// * `color` - Whether to color the table
+///
+/// # Returns
+///
+/// Whether the bucket is clean
+pub async fn lint_bucket(browser: &mut SnapshotBrowser, prefix: Option<&str>, template: &str, json: bool, color: bool) -> Result<bool> {
+    debug!("Linting bucket with template {}", template);
+    let template = lint::NamingTemplate::parse(template)?;
+    let prefix = prefix.unwrap_or(browser.prefix()).to_string();
+    let report = lint::lint_bucket(browser.store().await?.as_ref(), &prefix, &template).await?;
+    if !report.is_clean() {
+        print!("{}", report.table().render(color));
+    }
+    println!("{}", report.summary());
+    if json {
+        println!("{}", serde_json::to_string(&report)?);
+    }
+    Ok(report.is_clean())
+}
+
+/// Recover a base backup to a point in time on a scratch instance
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the bucket holding the base backup and its WAL
+/// * `key` - Key of the base backup
+/// * `target_time` - Time to recover to, as typed
+/// * `data_dir` - Data directory of the scratch instance, or one under the temp directory
+/// * `port` - Port the scratch instance listens on
+pub async fn recover(browser: &mut SnapshotBrowser, key: &str, target_time: &str, data_dir: Option<PathBuf>, port: u16) -> Result<ScratchInstance> {
+    debug!("Recovering {} to {}", key, target_time);
+    let target = pitr::parse_target_time(target_time)?;
+    browser.load_snapshots().await?;
+    let chain = browser.wal_chains.get(key).cloned()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a base backup; `rustored wal list` shows them", key))?;
+    let window = RecoveryWindow::of(&chain)?;
+    println!("Recoverable window: {} (UTC)", window);
+    window.check(target)?;
+    let data_dir = data_dir.unwrap_or_else(|| pitr::default_data_dir(target));
+    println!("Recovering into {} on port {}", data_dir.display(), port);
+    pitr::recover(browser, &chain, target, &data_dir, port).await
+}
+
+/// Download a snapshot to a local file, resuming an interrupted download of it if asked
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the bucket holding the snapshot
+/// * `key` - Key of the snapshot
+/// * `version_id` - Version of the snapshot to download, the latest if `None`
+/// * `output` - File to write, or the last part of the key in the working directory
+/// * `resume` - Keep the verified parts of an earlier download instead of starting over
+pub async fn download(browser: &mut SnapshotBrowser, key: &str, version_id: Option<&str>, output: Option<&str>, resume: bool) -> Result<()> {
+    debug!("Downloading {} to {:?}", key, output);
+    let snapshot = browser.find_snapshot(key, version_id).await?;
+    let path = PathBuf::from(output.unwrap_or_else(|| key.rsplit('/').next().unwrap_or(key)));
+
+    if !resume {
+        DownloadLedger::discard(&path)?;
+    } else if let Some(done) = DownloadLedger::resumable_bytes(
+        &path,
+        key,
+        snapshot.size.max(0) as u64,
+        snapshot.last_modified,
+        snapshot.etag.as_deref(),
+    ) {
+        println!("Resuming {} from {} of {} bytes", key, done, snapshot.size);
+    }
+    browser.download_snapshot(&snapshot, &path).await?
+        .ok_or_else(|| anyhow::anyhow!("Download of {} failed: {:?}", key, browser.popup_state))?;
+    println!("Downloaded {} to {}", key, path.display());
+    Ok(())
+}
+
+/// The datastore `restore --target` names, configured from the flags
+///
+/// `--es-host` and `--es-index` name the host and index of every search
+/// target, falling back to the target's own flags and then to the snapshot name.
+pub fn datastore_target(
+    cli: &Cli,
+    target: RestoreTarget,
+    name: &str,
+    es_host: &Option<String>,
+    es_index: &Option<String>,
+    qdrant_api_key: &Option<String>,
+    mapping: &RestoreMapping,
+) -> DatastoreRestoreTarget {
+    debug!("Configuring {} restore target for {}", target, name);
+    let bulk = bulk_config(cli);
+    match target {
+        RestoreTarget::Postgres => DatastoreRestoreTarget::Postgres,
+        RestoreTarget::Elasticsearch => DatastoreRestoreTarget::Elasticsearch {
+            host: es_host.clone().unwrap_or_else(|| "http://localhost:9200".to_string()),
+            index: es_index.clone().unwrap_or_else(|| name.to_string()),
+            bulk,
+            tls: tls_config(cli),
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::Qdrant => DatastoreRestoreTarget::Qdrant {
+            host: es_host.clone().unwrap_or_else(|| "http://localhost:6333".to_string()),
+            collection: es_index.clone().unwrap_or_else(|| name.to_string()),
+            api_key: qdrant_api_key.clone(),
+            tls: tls_config(cli),
+            upload: qdrant_upload_config(cli),
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::OpenSearch => DatastoreRestoreTarget::OpenSearch {
+            host: es_host.clone().or_else(|| cli.opensearch_host.clone()).unwrap_or_else(|| "http://localhost:9200".to_string()),
+            index: es_index.clone().or_else(|| cli.opensearch_index.clone()).unwrap_or_else(|| name.to_string()),
+            region: cli.opensearch_region.clone(),
+            service: cli.opensearch_service.clone(),
+            bulk,
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::Meilisearch => DatastoreRestoreTarget::Meilisearch {
+            host: es_host.clone().or_else(|| cli.meili_host.clone()).unwrap_or_else(|| "http://localhost:7700".to_string()),
+            index: es_index.clone().or_else(|| cli.meili_index.clone()).unwrap_or_else(|| name.to_string()),
+            api_key: cli.meili_api_key.clone(),
+            primary_key: cli.meili_primary_key.clone(),
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::Weaviate => DatastoreRestoreTarget::Weaviate {
+            host: es_host.clone().or_else(|| cli.weaviate_host.clone()).unwrap_or_else(|| "http://localhost:8080".to_string()),
+            class: es_index.clone().or_else(|| cli.weaviate_class.clone()).unwrap_or_else(|| name.to_string()),
+            credentials: WeaviateCredentials {
+                api_key: cli.weaviate_api_key.clone(),
+                oidc_client_secret: cli.weaviate_oidc_client_secret.clone(),
+                oidc_scope: cli.weaviate_oidc_scope.clone(),
+            },
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::Milvus => DatastoreRestoreTarget::Milvus {
+            host: es_host.clone().or_else(|| cli.milvus_host.clone()).unwrap_or_else(|| "http://localhost:19530".to_string()),
+            collection: es_index.clone().or_else(|| cli.milvus_collection.clone()).unwrap_or_else(|| name.to_string()),
+            token: cli.milvus_token.clone(),
+            db_name: cli.milvus_db.clone(),
+            vector_field: cli.milvus_vector_field.clone().unwrap_or_else(|| rustored::milvus_rows::DEFAULT_VECTOR_FIELD.to_string()),
+            metric: cli.milvus_metric.as_deref().unwrap_or(rustored::milvus_rows::DEFAULT_METRIC).to_uppercase(),
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::DynamoDb => DatastoreRestoreTarget::DynamoDb {
+            table: es_index.clone().or_else(|| cli.dynamodb_table.clone()).unwrap_or_else(|| name.to_string()),
+            region: cli.dynamodb_region.clone(),
+            endpoint_url: es_host.clone().or_else(|| cli.dynamodb_endpoint_url.clone()),
+            mapping: mapping.clone(),
+        },
+        RestoreTarget::InfluxDb => {
+            let config = influx_config(cli);
+            DatastoreRestoreTarget::InfluxDb {
+                target: InfluxBucket {
+                    host: es_host.clone().or(config.host).unwrap_or_else(|| "http://localhost:8086".to_string()),
+                    org: config.org.unwrap_or_default(),
+                    bucket: es_index.clone().or(config.bucket).unwrap_or_else(|| name.to_string()),
+                    token: config.token,
+                    precision: config.precision.unwrap_or_else(|| rustored::influx_lines::DEFAULT_PRECISION.to_string()),
+                },
+                mapping: mapping.clone(),
+            }
+        }
+    }
+}
+
+/// Configure the OpenSearch, Meilisearch, Weaviate, Milvus, DynamoDB and InfluxDB targets, and the bulk and TLS settings, from the flags
+pub fn configure_search_targets(app: &mut RustoredApp, cli: &Cli) {
+    debug!("Configuring search restore targets from flags");
+    app.es_config.bulk = bulk_config(cli);
+    app.es_config.tls = tls_config(cli);
+    app.qdrant_config.tls = app.es_config.tls.clone();
+    app.qdrant_config.upload = qdrant_upload_config(cli);
+    app.os_config.host = cli.opensearch_host.clone();
+    app.os_config.index = cli.opensearch_index.clone();
+    app.os_config.region = cli.opensearch_region.clone();
+    app.os_config.service = cli.opensearch_service.clone();
+    app.os_config.bulk = app.es_config.bulk.clone();
+    app.meili_config.host = cli.meili_host.clone();
+    app.meili_config.index = cli.meili_index.clone();
+    app.meili_config.api_key = cli.meili_api_key.clone();
+    app.meili_config.primary_key = cli.meili_primary_key.clone();
+    app.weaviate_config.host = cli.weaviate_host.clone();
+    app.weaviate_config.class = cli.weaviate_class.clone();
+    app.weaviate_config.api_key = cli.weaviate_api_key.clone();
+    app.weaviate_config.oidc_client_secret = cli.weaviate_oidc_client_secret.clone();
+    app.weaviate_config.oidc_scope = cli.weaviate_oidc_scope.clone();
+    app.milvus_config.host = cli.milvus_host.clone();
+    app.milvus_config.collection = cli.milvus_collection.clone();
+    app.milvus_config.token = cli.milvus_token.clone();
+    app.milvus_config.db_name = cli.milvus_db.clone();
+    app.milvus_config.vector_field = cli.milvus_vector_field.clone();
+    app.milvus_config.metric = cli.milvus_metric.as_deref().map(str::to_uppercase);
+    app.dynamodb_config.table = cli.dynamodb_table.clone();
+    app.dynamodb_config.region = cli.dynamodb_region.clone();
+    app.dynamodb_config.endpoint_url = cli.dynamodb_endpoint_url.clone();
+    app.influxdb_config = influx_config(cli);
+}
+
+/// The settings of each target `retry-failed` can load into, given by the flags
+///
+/// `qdrant_api_key` is the key given to `retry-failed` itself, used before the global one.
+pub fn retry_targets(cli: &Cli, qdrant_api_key: &Option<String>) -> RetryTargets {
+    debug!("Reading retry-failed target settings from flags");
+    RetryTargets {
+        elasticsearch: ElasticsearchConfig { bulk: bulk_config(cli), tls: tls_config(cli), ..Default::default() },
+        qdrant: QdrantConfig {
+            api_key: qdrant_api_key.clone().or_else(|| cli.qdrant_api_key.clone()),
+            tls: tls_config(cli),
+            upload: qdrant_upload_config(cli),
+            ..Default::default()
+        },
+        opensearch: OpenSearchConfig {
+            region: cli.opensearch_region.clone(),
+            service: cli.opensearch_service.clone(),
+            bulk: bulk_config(cli),
+            ..Default::default()
+        },
+        meilisearch: MeilisearchConfig { api_key: cli.meili_api_key.clone(), ..Default::default() },
+        weaviate: WeaviateConfig {
+            api_key: cli.weaviate_api_key.clone(),
+            oidc_client_secret: cli.weaviate_oidc_client_secret.clone(),
+            oidc_scope: cli.weaviate_oidc_scope.clone(),
+            ..Default::default()
+        },
+        milvus: MilvusConfig { token: cli.milvus_token.clone(), db_name: cli.milvus_db.clone(), ..Default::default() },
+        dynamodb: DynamoDbConfig { region: cli.dynamodb_region.clone(), ..Default::default() },
+        influxdb: influx_config(cli),
+    }
+}
+
+/// The Qdrant transport, collection and batching settings given by the flags
+pub fn qdrant_upload_config(cli: &Cli) -> QdrantUploadConfig {
+    debug!("Reading Qdrant transport, collection and batching settings from flags");
+    QdrantUploadConfig {
+        transport: cli.qdrant_transport,
+        grpc_port: cli.qdrant_grpc_port,
+        vector_size: cli.qdrant_vector_size,
+        distance: cli.qdrant_distance.clone(),
+        batch_size: cli.qdrant_batch_size,
+        concurrency: cli.qdrant_concurrency,
+    }
+}
+
+/// The InfluxDB settings given by the flags
+pub fn influx_config(cli: &Cli) -> InfluxDbConfig {
+    debug!("Reading InfluxDB settings from flags");
+    InfluxDbConfig {
+        host: cli.influx_host.clone(),
+        org: cli.influx_org.clone(),
+        bucket: cli.influx_bucket.clone(),
+        token: cli.influx_token.clone(),
+        precision: cli.influx_precision.as_deref().map(str::to_lowercase),
+        ..Default::default()
+    }
+}
+
+/// The Elasticsearch and Qdrant TLS settings given by the flags
+pub fn tls_config(cli: &Cli) -> TlsConfig {
+    debug!("Reading Elasticsearch and Qdrant TLS settings from flags");
+    TlsConfig {
+        ca_cert_path: cli.es_ca_cert.clone(),
+        client_cert_path: cli.es_client_cert.clone(),
+        client_key_path: cli.es_client_key.clone(),
+        insecure_skip_verify: cli.es_insecure_skip_verify,
+    }
+}
+
+/// The Elasticsearch and OpenSearch bulk settings given by the flags
+pub fn bulk_config(cli: &Cli) -> BulkConfig {
+    debug!("Reading bulk settings from flags");
+    BulkConfig {
+        shards: cli.es_shards,
+        replicas: cli.es_replicas,
+        alias_swap: cli.es_alias_swap,
+        ..BulkConfig::new(cli.es_batch_size, cli.es_bulk_concurrency, cli.es_keep_refresh)
+    }
+}
@@ -0,0 +1,169 @@
+// This module contains the "what changed" summary for the Rustored application
+// PostgreSQL snapshots are always restored into a new database. Before that
+// copy is promoted, operators want to know how it differs from the live
+// database it was taken from, so this module collects the table list and
+// exact row counts of both and summarises the differences.
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use crate::ui::models::PostgresConfig;
+
+/// Row counts of every user table in a database, keyed by `schema.table`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DatabaseStats {
+    pub tables: BTreeMap<String, i64>,
+}
+
+/// A table whose row count differs between the live and restored databases
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowCountChange {
+    pub table: String,
+    pub live: i64,
+    pub restored: i64,
+}
+
+/// Differences between a restored database and the live database of the same name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangeSummary {
+    pub live_db: String,
+    pub restored_db: String,
+    /// Whether a live database existed to compare with
+    pub live_exists: bool,
+    /// Tables only in the restored database, with their row counts
+    pub added: Vec<(String, i64)>,
+    /// Tables only in the live database, with their row counts
+    pub removed: Vec<(String, i64)>,
+    /// Tables in both whose row counts differ
+    pub changed: Vec<RowCountChange>,
+    /// Number of tables in both with the same row count
+    pub unchanged: usize,
+}
+
+impl ChangeSummary {
+    /// Compare the stats of a restored database with the live one
+    ///
+    /// # Arguments
+    ///
+    /// * `live_db` - Name of the live database
+    /// * `restored_db` - Name of the restored database
+    /// * `live` - Stats of the live database, `None` if it does not exist
+    /// * `restored` - Stats of the restored database
+    pub fn compare(live_db: &str, restored_db: &str, live: Option<&DatabaseStats>, restored: &DatabaseStats) -> Self {
+        debug!("Comparing restored database {} with live database {}", restored_db, live_db);
+        let empty = DatabaseStats::default();
+        let live_stats = live.unwrap_or(&empty);
+        let mut summary = Self {
+            live_db: live_db.to_string(),
+            restored_db: restored_db.to_string(),
+            live_exists: live.is_some(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            unchanged: 0,
+        };
+        for (table, &rows) in &restored.tables {
+            match live_stats.tables.get(table) {
+                None => summary.added.push((table.clone(), rows)),
+                Some(&live_rows) if live_rows != rows => summary.changed.push(RowCountChange {
+                    table: table.clone(),
+                    live: live_rows,
+                    restored: rows,
+                }),
+                Some(_) => summary.unchanged += 1,
+            }
+        }
+        for (table, &rows) in &live_stats.tables {
+            if !restored.tables.contains_key(table) {
+                summary.removed.push((table.clone(), rows));
+            }
+        }
+        summary
+    }
+
+    /// Human readable summary, one line per difference
+    pub fn lines(&self) -> Vec<String> {
+        debug!("Formatting change summary for {}", self.restored_db);
+        if !self.live_exists {
+            return vec![format!("No live database named {} to compare {} with", self.live_db, self.restored_db)];
+        }
+        let mut lines = vec![format!(
+            "{} vs live {}: {} added, {} removed, {} changed, {} unchanged tables",
+            self.restored_db, self.live_db, self.added.len(), self.removed.len(), self.changed.len(), self.unchanged
+        )];
+        lines.extend(self.added.iter().map(|(table, rows)| format!("+ {} ({} rows)", table, rows)));
+        lines.extend(self.removed.iter().map(|(table, rows)| format!("- {} ({} rows)", table, rows)));
+        lines.extend(self.changed.iter().map(|change| format!(
+            "~ {}: {} -> {} rows ({:+})",
+            change.table, change.live, change.restored, change.restored - change.live
+        )));
+        lines
+    }
+}
+
+/// Quote a PostgreSQL identifier
+pub(crate) fn quote_ident(name: &str) -> String {
+    debug!("Quoting identifier {}", name);
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Collect the table list and exact row counts of a database
+///
+/// Row counts use `count(*)`, because planner estimates are not available yet
+/// on a freshly restored database. This reads every table once.
+pub async fn collect_stats(pg_config: &PostgresConfig, db_name: &str) -> Result<DatabaseStats> {
+    debug!("Collecting table stats for database: {}", db_name);
+    let client = pg_config.connect_to(db_name).await?;
+    let rows = client
+        .query(
+            "SELECT table_schema, table_name FROM information_schema.tables \
+             WHERE table_type = 'BASE TABLE' AND table_schema NOT IN ('pg_catalog', 'information_schema', $1) \
+             ORDER BY 1, 2",
+            &[&crate::provenance::PROVENANCE_SCHEMA],
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to list tables of {}: {}", db_name, e))?;
+
+    let mut stats = DatabaseStats::default();
+    for row in rows {
+        let schema: String = row.get(0);
+        let table: String = row.get(1);
+        let count: i64 = client
+            .query_one(&format!("SELECT count(*) FROM {}.{}", quote_ident(&schema), quote_ident(&table)), &[])
+            .await
+            .map_err(|e| anyhow!("Failed to count rows of {}.{} in {}: {}", schema, table, db_name, e))?
+            .get(0);
+        stats.tables.insert(format!("{}.{}", schema, table), count);
+    }
+    debug!("Collected stats for {} tables in {}", stats.tables.len(), db_name);
+    Ok(stats)
+}
+
+/// Compare a restored database with the live database it was taken from
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server holding both databases
+/// * `live_db` - Name of the live database
+/// * `restored_db` - Name of the restored database
+pub async fn compare_with_live(pg_config: &PostgresConfig, live_db: &str, restored_db: &str) -> Result<ChangeSummary> {
+    debug!("Comparing {} with live database {}", restored_db, live_db);
+    let span = crate::telemetry::Span::start("validation").with("database", restored_db);
+    crate::telemetry::instrument(span, async move {
+        let restored = collect_stats(pg_config, restored_db).await?;
+        let live_exists = pg_config
+            .connect_to(restored_db)
+            .await?
+            .query_opt("SELECT 1 FROM pg_database WHERE datname = $1", &[&live_db])
+            .await?
+            .is_some();
+        let live = match live_exists {
+            true => Some(collect_stats(pg_config, live_db).await?),
+            false => None,
+        };
+        let summary = ChangeSummary::compare(live_db, restored_db, live.as_ref(), &restored);
+        info!("{}", summary.lines()[0]);
+        Ok(summary)
+    }).await
+}
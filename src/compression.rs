@@ -0,0 +1,197 @@
+// This module contains dump compression for the Rustored application
+// Plain SQL dumps, and snapshots uploaded by other tools, are often compressed
+// with gzip, zstd or lz4. Dumps are piped through the codec's command line
+// tool as they are written, and compressed snapshots are recognised by their
+// magic bytes and decompressed next to the download before pg_restore or the
+// Elasticsearch and Qdrant loaders read them.
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use log::{debug, info};
+use std::fmt;
+use std::io::Read;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::process::Stdio;
+use tempfile::NamedTempFile;
+
+/// Compression codec of a dump or snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+impl Codec {
+    /// Recognise a compressed file from its first bytes
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        debug!("Detecting compression from {} header bytes", header.len());
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if header.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+            Some(Self::Lz4)
+        } else {
+            None
+        }
+    }
+
+    /// File extension of the codec, e.g. `.zst`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gzip => ".gz",
+            Self::Zstd => ".zst",
+            Self::Lz4 => ".lz4",
+        }
+    }
+
+    /// The codec a file name's extension says it is compressed with, e.g. `.sql.zst`
+    pub fn from_name(name: &str) -> Option<Self> {
+        debug!("Detecting compression of {} from its extension", name);
+        [Self::Gzip, Self::Zstd, Self::Lz4].into_iter().find(|codec| name.ends_with(codec.extension()))
+    }
+
+    /// Command line tool that compresses and decompresses with the codec
+    fn program(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    /// Compression levels the codec's tool accepts
+    pub fn levels(&self) -> RangeInclusive<u32> {
+        match self {
+            Self::Gzip => 1..=9,
+            Self::Zstd => 1..=19,
+            Self::Lz4 => 1..=12,
+        }
+    }
+
+    /// Command that reads stdin and writes it compressed to stdout
+    fn compress_command(&self, level: Option<u32>) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(self.program());
+        cmd.arg("-c");
+        if *self != Self::Gzip {
+            cmd.arg("-q");
+        }
+        if let Some(level) = level {
+            cmd.arg(format!("-{}", level));
+        }
+        cmd
+    }
+
+    /// Command that decompresses its argument, or stdin without one, to stdout
+    pub fn decompress_command(&self) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(self.program());
+        cmd.arg("-dc");
+        cmd
+    }
+
+    /// Error for a codec whose tool could not be started
+    pub fn missing_tool(&self) -> String {
+        format!("Failed to execute {}; install it to restore {} compressed snapshots", self.program(), self)
+    }
+}
+
+/// Codec and level to compress a dump with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    pub codec: Codec,
+    /// Level passed to the codec's tool, or its default
+    pub level: Option<u32>,
+}
+
+impl Compression {
+    /// Choose a codec and level, checking the level is one the codec accepts
+    pub fn new(codec: Codec, level: Option<u32>) -> Result<Self> {
+        debug!("Choosing {} compression at level {:?}", codec, level);
+        if let Some(level) = level.filter(|level| !codec.levels().contains(level)) {
+            let levels = codec.levels();
+            return Err(anyhow!("{} compression level must be {} to {}, not {}", codec, levels.start(), levels.end(), level));
+        }
+        Ok(Self { codec, level })
+    }
+
+    /// Path of the compressed output, adding the codec's extension if it is missing
+    pub fn output_path(&self, output: &str) -> String {
+        debug!("Building compressed output path for {}", output);
+        match output.ends_with(self.codec.extension()) {
+            true => output.to_string(),
+            false => format!("{}{}", output, self.codec.extension()),
+        }
+    }
+
+    /// Start the codec's tool reading `input` and writing compressed bytes to `output`
+    ///
+    /// The caller waits for the returned child, which is killed if dropped.
+    pub fn spawn(&self, input: Stdio, output: &Path) -> Result<tokio::process::Child> {
+        debug!("Starting {} compression into {:?}", self.codec, output);
+        let file = std::fs::File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+        self.codec
+            .compress_command(self.level)
+            .stdin(input)
+            .stdout(file)
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to execute {}; install it to compress dumps with {}", self.codec.program(), self.codec))
+    }
+}
+
+/// The codec a file is compressed with, if any
+pub fn detect_file(path: &Path) -> Result<Option<Codec>> {
+    debug!("Detecting compression of {:?}", path);
+    let mut header = Vec::with_capacity(4);
+    std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(4)
+        .read_to_end(&mut header)?;
+    Ok(Codec::detect(&header))
+}
+
+/// Decompress a snapshot if it is compressed
+///
+/// The decompressed copy is written next to `path`, where the download
+/// already had room, and removed when the returned file is dropped.
+///
+/// # Arguments
+///
+/// * `path` - The downloaded snapshot or dump file
+///
+/// # Returns
+///
+/// The decompressed copy, or `None` if the file is not compressed and can be read as it is
+pub async fn decompress_file(path: &Path) -> Result<Option<NamedTempFile>> {
+    debug!("Checking whether {:?} needs decompressing", path);
+    let Some(codec) = detect_file(path)? else {
+        return Ok(None);
+    };
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let decompressed = NamedTempFile::new_in(dir)?;
+    info!("Decompressing {} snapshot {:?}", codec, path);
+    let output = codec
+        .decompress_command()
+        .arg(path)
+        .stdout(decompressed.reopen()?)
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        // `output()` would replace the file with a pipe
+        .spawn()
+        .with_context(|| codec.missing_tool())?
+        .wait_with_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to decompress {}: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(Some(decompressed))
+}
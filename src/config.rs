@@ -1,4 +1,4 @@
-use crate::ui::models::{S3Config, PostgresConfig};
+use crate::ui::models::{S3Config, PostgresConfig, TimeoutConfig};
 use std::env;
 
 /// Load environment variables from .env file or from the file specified in DOTENV_PATH
@@ -34,8 +34,10 @@ pub fn load_s3_config() -> S3Config {
         access_key_id: get_env_with_default("S3_ACCESS_KEY_ID", ""),
         secret_access_key: get_env_with_default("S3_SECRET_ACCESS_KEY", ""),
         path_style: get_env_bool("S3_PATH_STYLE", true),
+        sse_customer_key: get_env_with_default("S3_SSE_CUSTOMER_KEY", ""),
         error_message: None,
         test_s3_button: false,
+        timeouts: TimeoutConfig::from_env("S3"),
     }
 }
 
@@ -48,5 +50,7 @@ pub fn load_postgres_config() -> PostgresConfig {
         password: Some(get_env_with_default("PG_PASSWORD", "")),
         use_ssl: get_env_bool("PG_USE_SSL", false),
         db_name: Some(get_env_with_default("PG_DB_NAME", "postgres")),
+        target_db: std::env::var("PG_TARGET_DB").ok().filter(|name| !name.is_empty()),
+        restore_jobs: std::env::var("PG_RESTORE_JOBS").ok().and_then(|jobs| jobs.parse().ok()).filter(|jobs| *jobs > 0),
     }
 }
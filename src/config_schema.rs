@@ -0,0 +1,305 @@
+// This module contains schema validation of the Rustored TOML files
+// serde skips keys it does not know, so a typo such as `drop_indexs` or
+// `jitter_second` in the presets or mapping file silently falls back to the
+// default and only shows up as a confusing restore or schedule later. Each
+// file is checked against a schema before it is deserialized, and every
+// unknown key, mistyped value, key that has no effect without another, and
+// duplicated entry is reported at once with its line and column.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+/// Type a key's value must have
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    String,
+    /// An integer no smaller than `min`
+    Integer { min: i64 },
+    Bool,
+    /// An array of strings
+    Strings,
+    /// A table of string values, with keys of the user's choosing
+    StringMap,
+    /// An array of recipe names, or tables with a `name` and string parameters
+    Recipes,
+    /// An array of tables, each checked against the schema
+    Tables(&'static TableSchema),
+}
+
+/// A key a table may have
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub required: bool,
+}
+
+impl Field {
+    /// An optional key
+    pub const fn optional(name: &'static str, kind: Kind) -> Self {
+        Self { name, kind, required: false }
+    }
+
+    /// A key every table must have
+    pub const fn required(name: &'static str, kind: Kind) -> Self {
+        Self { name, kind, required: true }
+    }
+}
+
+/// Keys and rules of one kind of table, e.g. `[[preset]]`
+#[derive(Debug)]
+pub struct TableSchema {
+    /// How the table is named in messages, e.g. `[[preset]]`
+    pub name: &'static str,
+    pub fields: &'static [Field],
+    /// Keys that do nothing unless another key is set, as (key, needed key)
+    pub requires: &'static [(&'static str, &'static str)],
+    /// Key whose value must differ between the tables of an array, since only the first would be used
+    pub unique: Option<&'static str>,
+}
+
+/// A problem found in a file, at a 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Check a TOML file against a schema
+///
+/// Syntax errors are left to the deserializer, which reports them with
+/// their position already.
+///
+/// # Arguments
+///
+/// * `contents` - The file's contents
+/// * `schema` - Schema of the file's top-level table
+///
+/// # Returns
+///
+/// Every problem found, in file order
+pub fn check(contents: &str, schema: &TableSchema) -> Vec<Issue> {
+    debug!("Checking TOML against the {} schema", schema.name);
+    let Ok(document) = ImDocument::parse(contents) else {
+        return Vec::new();
+    };
+    let mut checker = Checker { contents, issues: Vec::new() };
+    checker.table(document.as_table(), schema, document.as_table().span());
+    checker.issues.sort_by_key(|issue| (issue.line, issue.column));
+    checker.issues
+}
+
+/// Check a TOML file against a schema, failing with every problem found
+///
+/// # Arguments
+///
+/// * `contents` - The file's contents
+/// * `schema` - Schema of the file's top-level table
+/// * `what` - What the file holds, e.g. `restore presets`
+pub fn validate(contents: &str, schema: &TableSchema, what: &str) -> Result<()> {
+    debug!("Validating {}", what);
+    let issues = check(contents, schema);
+    if issues.is_empty() {
+        return Ok(());
+    }
+    let lines: Vec<String> = issues.iter().map(|issue| format!("  {}", issue)).collect();
+    Err(anyhow!("Invalid {}:\n{}", what, lines.join("\n")))
+}
+
+struct Checker<'a> {
+    contents: &'a str,
+    issues: Vec<Issue>,
+}
+
+impl Checker<'_> {
+    /// Record a problem at the start of a span, or at the top of the file without one
+    fn report(&mut self, span: Option<Range<usize>>, message: String) {
+        let offset = span.map_or(0, |span| span.start).min(self.contents.len());
+        let before = &self.contents[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        self.issues.push(Issue { line, column, message });
+    }
+
+    fn table(&mut self, table: &dyn TableLike, schema: &TableSchema, span: Option<Range<usize>>) {
+        for (key, item) in table.iter() {
+            let key_span = table.key(key).and_then(|key| key.span());
+            match schema.fields.iter().find(|field| field.name == key) {
+                Some(field) => self.value(item, field, schema, key_span),
+                None => {
+                    let message = match suggest(key, schema) {
+                        Some(known) => format!("unknown key `{}` in {}, did you mean `{}`?", key, schema.name, known),
+                        None => format!("unknown key `{}` in {}; expected one of {}", key, schema.name, field_names(schema)),
+                    };
+                    self.report(key_span, message);
+                }
+            }
+        }
+        for field in schema.fields.iter().filter(|field| field.required && !table.contains_key(field.name)) {
+            self.report(span.clone(), format!("{} is missing the required key `{}`", schema.name, field.name));
+        }
+        for (key, needed) in schema.requires {
+            if table.contains_key(key) && !table.contains_key(needed) {
+                let key_span = table.key(key).and_then(|key| key.span());
+                self.report(key_span, format!("`{}` in {} has no effect without `{}`", key, schema.name, needed));
+            }
+        }
+    }
+
+    fn value(&mut self, item: &Item, field: &Field, parent: &TableSchema, key_span: Option<Range<usize>>) {
+        let span = item.span().or(key_span);
+        let expected = match field.kind {
+            Kind::String if item.is_str() => return,
+            Kind::String => "a string",
+            Kind::Integer { min } => match item.as_integer() {
+                Some(n) if n >= min => return,
+                Some(n) => {
+                    self.report(span, format!("`{}` in {} must be at least {}, not {}", field.name, parent.name, min, n));
+                    return;
+                }
+                None => "an integer",
+            },
+            Kind::Bool if item.is_bool() => return,
+            Kind::Bool => "true or false",
+            Kind::Strings => match item.as_array() {
+                Some(array) => {
+                    for element in array.iter().filter(|element| !element.is_str()) {
+                        let message = format!("`{}` in {} must hold only strings, found {}", field.name, parent.name, value_type(element));
+                        self.report(element.span(), message);
+                    }
+                    return;
+                }
+                None => "an array of strings",
+            },
+            Kind::StringMap => match item.as_table_like() {
+                Some(table) => {
+                    for (key, value) in table.iter().filter(|(_, value)| !value.is_str()) {
+                        let message = format!("`{}.{}` in {} must be a string, found {}", field.name, key, parent.name, item_type(value));
+                        self.report(value.span(), message);
+                    }
+                    return;
+                }
+                None => "a table of strings",
+            },
+            Kind::Recipes => match item.as_array() {
+                Some(array) => {
+                    for element in array.iter() {
+                        self.recipe(element, parent);
+                    }
+                    return;
+                }
+                None => "an array of recipe names or tables",
+            },
+            Kind::Tables(schema) => {
+                self.tables(item, schema);
+                return;
+            }
+        };
+        self.report(span, format!("`{}` in {} must be {}, found {}", field.name, parent.name, expected, item_type(item)));
+    }
+
+    fn recipe(&mut self, element: &Value, parent: &TableSchema) {
+        match element {
+            Value::String(_) => {}
+            Value::InlineTable(table) => {
+                if !table.contains_key("name") {
+                    self.report(element.span(), format!("recipe table in {} is missing the required key `name`", parent.name));
+                }
+                for (key, value) in table.iter().filter(|(_, value)| !value.is_str()) {
+                    let message = format!("recipe parameter `{}` in {} must be a string, found {}", key, parent.name, value_type(value));
+                    self.report(value.span(), message);
+                }
+            }
+            other => {
+                self.report(other.span(), format!("recipes in {} must be names or tables, found {}", parent.name, value_type(other)));
+            }
+        }
+    }
+
+    /// Check an array of tables, written as `[[name]]` sections or as an array of inline tables
+    fn tables(&mut self, item: &Item, schema: &'static TableSchema) {
+        let tables: Vec<(&dyn TableLike, Option<Range<usize>>)> = match item {
+            Item::ArrayOfTables(array) => array.iter().map(|table| (table as &dyn TableLike, table.span())).collect(),
+            Item::Value(Value::Array(array)) => {
+                let mut tables = Vec::new();
+                for element in array.iter() {
+                    match element.as_inline_table() {
+                        Some(table) => tables.push((table as &dyn TableLike, table.span())),
+                        None => self.report(element.span(), format!("{} entries must be tables, found {}", schema.name, value_type(element))),
+                    }
+                }
+                tables
+            }
+            other => {
+                self.report(other.span(), format!("{} must be an array of tables, found {}", schema.name, item_type(other)));
+                return;
+            }
+        };
+        let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+        for (table, span) in tables {
+            self.table(table, schema, span.clone());
+            let Some(unique) = schema.unique else {
+                continue;
+            };
+            let Some(value) = table.get(unique).and_then(|value| value.as_str()) else {
+                continue;
+            };
+            match seen.get(value) {
+                Some(first_line) => {
+                    let message = format!("{} with {} = \"{}\" repeats the one on line {}, which is always used instead", schema.name, unique, value, first_line);
+                    self.report(span, message);
+                }
+                None => {
+                    let line = self.contents[..span.map_or(0, |span| span.start).min(self.contents.len())].matches('\n').count() + 1;
+                    seen.insert(value.to_string(), line);
+                }
+            }
+        }
+    }
+}
+
+/// The known key closest to a misspelled one, if any is close enough to be the intended one
+fn suggest(key: &str, schema: &TableSchema) -> Option<&'static str> {
+    schema
+        .fields
+        .iter()
+        .map(|field| (field.name, strsim::jaro_winkler(key, field.name)))
+        .filter(|(_, similarity)| *similarity >= 0.85)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(name, _)| name)
+}
+
+fn field_names(schema: &TableSchema) -> String {
+    schema.fields.iter().map(|field| format!("`{}`", field.name)).collect::<Vec<_>>().join(", ")
+}
+
+fn item_type(item: &Item) -> &'static str {
+    match item {
+        Item::None => "nothing",
+        Item::Value(value) => value_type(value),
+        Item::Table(_) => "a table",
+        Item::ArrayOfTables(_) => "an array of tables",
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "a string",
+        Value::Integer(_) => "an integer",
+        Value::Float(_) => "a float",
+        Value::Boolean(_) => "a boolean",
+        Value::Datetime(_) => "a datetime",
+        Value::Array(_) => "an array",
+        Value::InlineTable(_) => "a table",
+    }
+}
@@ -1,5 +1,8 @@
 use anyhow::Result;
 use log::{info, debug};
+use std::path::Path;
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::mapping::RestoreMapping;
 
 /// Configuration for Elasticsearch
 #[derive(Debug, Clone)]
@@ -35,17 +38,29 @@ impl Default for QdrantConfig {
     }
 }
 
-/// Target for restore operations
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Target for restore operations, as named by `--target`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
 pub enum RestoreTarget {
+    #[default]
     Postgres,
     Elasticsearch,
     Qdrant,
+    #[value(name = "opensearch")]
+    OpenSearch,
+    Meilisearch,
+    Weaviate,
+    Milvus,
+    #[value(name = "dynamodb")]
+    DynamoDb,
+    #[value(name = "influxdb")]
+    InfluxDb,
 }
 
-impl Default for RestoreTarget {
-    fn default() -> Self {
-        Self::Postgres
+impl std::fmt::Display for RestoreTarget {
+    /// The name `--target` takes, e.g. `opensearch`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = clap::ValueEnum::to_possible_value(self).ok_or(std::fmt::Error)?;
+        f.write_str(value.get_name())
     }
 }
 
@@ -55,64 +70,282 @@ pub enum DatastoreRestoreTarget {
     Elasticsearch {
         host: String,
         index: String,
+        bulk: crate::ui::models::BulkConfig,
+        tls: crate::ui::models::TlsConfig,
+        mapping: RestoreMapping,
     },
     Qdrant {
         host: String,
         collection: String,
         api_key: Option<String>,
+        tls: crate::ui::models::TlsConfig,
+        upload: crate::ui::models::QdrantUploadConfig,
+        mapping: RestoreMapping,
+    },
+    OpenSearch {
+        host: String,
+        index: String,
+        /// AWS region requests are signed for with SigV4, unsigned if `None`
+        region: Option<String>,
+        /// SigV4 service name, `es` or `aoss`
+        service: String,
+        bulk: crate::ui::models::BulkConfig,
+        mapping: RestoreMapping,
+    },
+    Meilisearch {
+        host: String,
+        index: String,
+        api_key: Option<String>,
+        /// Primary key of a new index, detected from the documents if `None`
+        primary_key: Option<String>,
+        mapping: RestoreMapping,
+    },
+    Weaviate {
+        host: String,
+        class: String,
+        credentials: crate::weaviate_objects::WeaviateCredentials,
+        mapping: RestoreMapping,
+    },
+    Milvus {
+        host: String,
+        collection: String,
+        /// API key or `user:password`
+        token: Option<String>,
+        /// Database holding the collection, the default database if `None`
+        db_name: Option<String>,
+        /// Field holding each row's vector
+        vector_field: String,
+        /// Metric of the vector index of a new collection
+        metric: String,
+        mapping: RestoreMapping,
+    },
+    DynamoDb {
+        table: String,
+        /// AWS region of the table, the credential chain's region if `None`
+        region: Option<String>,
+        /// Endpoint to use instead of AWS's, e.g. DynamoDB Local
+        endpoint_url: Option<String>,
+        mapping: RestoreMapping,
+    },
+    InfluxDb {
+        /// Host, organization, bucket, token and precision
+        target: crate::influx_lines::InfluxBucket,
+        mapping: RestoreMapping,
     },
 }
 
 impl DatastoreRestoreTarget {
-    pub async fn restore(&self, name: &str, input: &str) -> Result<()> {
+    /// Restore `input`, returning the records the target rejected, if any
+    pub async fn restore(&self, name: &str, input: &str, options: &crate::presets::RestoreOptions) -> Result<Option<DeadLetterReport>> {
         match self {
             DatastoreRestoreTarget::Postgres => {
                 // Call existing postgres restore logic
-                crate::backup::restore_database(name, input, "localhost", 5432, None, None, false)
+                crate::backup::restore_database(name, input, "localhost", 5432, None, None, false, options).await?;
+                Ok(None)
             }
-            DatastoreRestoreTarget::Elasticsearch { host, index } => {
+            DatastoreRestoreTarget::Elasticsearch { host, index, bulk, tls, mapping } => {
                 // Call Elasticsearch restore logic
-                restore_to_elasticsearch(host, index, input).await
+                restore_to_elasticsearch(host, index, input, bulk, tls, mapping).await
             }
-            DatastoreRestoreTarget::Qdrant { host, collection, api_key } => {
+            DatastoreRestoreTarget::Qdrant { host, collection, api_key, tls, upload, mapping } => {
                 // Call Qdrant restore logic
-                restore_to_qdrant(host, collection, api_key.as_deref(), input).await
+                restore_to_qdrant(host, collection, api_key.as_deref(), tls, upload, input, mapping).await
+            }
+            DatastoreRestoreTarget::OpenSearch { host, index, region, service, bulk, mapping } => {
+                restore_to_opensearch(host, index, region.as_deref(), service, input, bulk, mapping).await
+            }
+            DatastoreRestoreTarget::Meilisearch { host, index, api_key, primary_key, mapping } => {
+                restore_to_meilisearch(host, index, api_key.as_deref(), primary_key.as_deref(), input, mapping).await
             }
+            DatastoreRestoreTarget::Weaviate { host, class, credentials, mapping } => {
+                restore_to_weaviate(host, class, credentials, input, mapping).await
+            }
+            DatastoreRestoreTarget::Milvus { host, collection, token, db_name, vector_field, metric, mapping } => {
+                restore_to_milvus(host, collection, token.as_deref(), db_name.as_deref(), vector_field, metric, input, mapping).await
+            }
+            DatastoreRestoreTarget::DynamoDb { table, region, endpoint_url, mapping } => {
+                restore_to_dynamodb(table, region.as_deref(), endpoint_url.as_deref(), input, mapping).await
+            }
+            DatastoreRestoreTarget::InfluxDb { target, mapping } => restore_to_influxdb(target, input, mapping).await,
         }
     }
 }
 
-/// Restore a snapshot to Elasticsearch
-pub async fn restore_to_elasticsearch(host: &str, index: &str, file_path: &str) -> Result<()> {
+/// Restore an NDJSON snapshot to Elasticsearch with bulk requests
+///
+/// The index is renamed by `mapping`, which also renames document fields.
+/// HTTPS connections use the CA bundle and client certificate of `tls`.
+/// Rejected documents go to a dead-letter file in the working directory.
+pub async fn restore_to_elasticsearch(
+    host: &str,
+    index: &str,
+    file_path: &str,
+    bulk: &crate::ui::models::BulkConfig,
+    tls: &crate::ui::models::TlsConfig,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let index = &mapping.map_name(index);
     info!("Restoring to Elasticsearch at {}, index {}", host, index);
-    
-    // TODO: Implement actual Elasticsearch restore logic
-    // This would involve:
-    // 1. Reading the JSON file
-    // 2. Creating the index if it doesn't exist
-    // 3. Bulk uploading the documents to Elasticsearch
-    
-    // For now, just log what would happen
-    debug!("Would restore file {} to Elasticsearch index {} at {}", file_path, index, host);
-    info!("[STUB] Elasticsearch restore completed successfully");
-    
-    Ok(())
-}
-
-/// Restore a snapshot to Qdrant
-pub async fn restore_to_qdrant(host: &str, collection: &str, api_key: Option<&str>, file_path: &str) -> Result<()> {
+    let client = crate::ui::models::TimeoutConfig::default().tls_client(tls)?;
+    let mut dead_letters = DeadLetterWriter::new("elasticsearch", host, index, "documents", dead_letter_path(Path::new("."), index, chrono::Utc::now()));
+    let indexed = crate::es_bulk::bulk_restore(&client, None, host, index, Path::new(file_path), bulk, mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} documents from {} to Elasticsearch index {} at {}", indexed, file_path, index, host);
+    dead_letters.finish()
+}
+
+/// Restore an NDJSON snapshot to OpenSearch with bulk requests
+///
+/// Requests are signed with SigV4 for `service` in `region` when a region
+/// is given, with credentials from the default AWS credential chain.
+pub async fn restore_to_opensearch(
+    host: &str,
+    index: &str,
+    region: Option<&str>,
+    service: &str,
+    file_path: &str,
+    bulk: &crate::ui::models::BulkConfig,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let index = &mapping.map_name(index);
+    info!("Restoring to OpenSearch at {}, index {}", host, index);
+    let client = crate::ui::models::TimeoutConfig::default().http_client()?;
+    let signer = match region {
+        Some(region) => Some(crate::sigv4::SigV4Signer::from_default_chain(region, service).await?),
+        None => None,
+    };
+    let mut dead_letters = DeadLetterWriter::new("opensearch", host, index, "documents", dead_letter_path(Path::new("."), index, chrono::Utc::now()));
+    let indexed = crate::es_bulk::bulk_restore(&client, signer.as_ref(), host, index, Path::new(file_path), bulk, mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} documents from {} to OpenSearch index {} at {}", indexed, file_path, index, host);
+    dead_letters.finish()
+}
+
+/// Restore an NDJSON snapshot of points to Qdrant
+///
+/// The collection is renamed by `mapping`, which also renames payload fields.
+/// HTTPS connections use the CA bundle and client certificate of `tls`, and
+/// points go over HTTP or gRPC as `upload` selects.
+/// Rejected points go to a dead-letter file in the working directory.
+pub async fn restore_to_qdrant(
+    host: &str,
+    collection: &str,
+    api_key: Option<&str>,
+    tls: &crate::ui::models::TlsConfig,
+    upload: &crate::ui::models::QdrantUploadConfig,
+    file_path: &str,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let collection = &mapping.map_name(collection);
     info!("Restoring to Qdrant at {}, collection {}", host, collection);
-    
-    // TODO: Implement actual Qdrant restore logic
-    // This would involve:
-    // 1. Reading the vector data file
-    // 2. Creating the collection if it doesn't exist
-    // 3. Uploading the vectors to Qdrant
-    
-    // For now, just log what would happen
-    let auth_info = if api_key.is_some() { "with API key" } else { "without API key" };
-    debug!("Would restore file {} to Qdrant collection {} at {} {}", file_path, collection, host, auth_info);
-    info!("[STUB] Qdrant restore completed successfully");
-    
-    Ok(())
+    let connection = crate::qdrant_points::QdrantConnection::connect(host, api_key, &crate::ui::models::TimeoutConfig::default(), tls, upload).await?;
+    let mut dead_letters = DeadLetterWriter::new("qdrant", host, collection, "points", dead_letter_path(Path::new("."), collection, chrono::Utc::now()));
+    let loaded = crate::qdrant_points::upload_points(&connection, collection, Path::new(file_path), mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} points from {} to Qdrant collection {} at {}", loaded, file_path, collection, host);
+    dead_letters.finish()
+}
+
+/// Restore an NDJSON snapshot of documents to Meilisearch
+///
+/// The index is renamed by `mapping`, which also renames document fields.
+/// Documents of failed tasks go to a dead-letter file in the working directory.
+pub async fn restore_to_meilisearch(
+    host: &str,
+    index: &str,
+    api_key: Option<&str>,
+    primary_key: Option<&str>,
+    file_path: &str,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let index = &mapping.map_name(index);
+    info!("Restoring to Meilisearch at {}, index {}", host, index);
+    let client = crate::ui::models::TimeoutConfig::default().http_client()?;
+    let mut dead_letters = DeadLetterWriter::new("meilisearch", host, index, "documents", dead_letter_path(Path::new("."), index, chrono::Utc::now()));
+    let indexed = crate::meili_documents::add_documents(&client, host, index, api_key, primary_key, Path::new(file_path), mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} documents from {} to Meilisearch index {} at {}", indexed, file_path, index, host);
+    dead_letters.finish()
+}
+
+/// Restore an NDJSON snapshot of objects to Weaviate
+///
+/// The class is renamed by `mapping`, which also renames object properties.
+/// Objects Weaviate rejects go to a dead-letter file in the working directory.
+pub async fn restore_to_weaviate(
+    host: &str,
+    class: &str,
+    credentials: &crate::weaviate_objects::WeaviateCredentials,
+    file_path: &str,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let class = &crate::weaviate_objects::class_name(&mapping.map_name(class));
+    info!("Restoring to Weaviate at {}, class {}", host, class);
+    let client = crate::ui::models::TimeoutConfig::default().http_client()?;
+    let mut dead_letters = DeadLetterWriter::new("weaviate", host, class, "objects", dead_letter_path(Path::new("."), class, chrono::Utc::now()));
+    let imported = crate::weaviate_objects::import_objects(&client, host, class, credentials, Path::new(file_path), mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} objects from {} to Weaviate class {} at {}", imported, file_path, class, host);
+    dead_letters.finish()
+}
+
+/// Restore an NDJSON snapshot of rows to Milvus over gRPC
+///
+/// The collection is renamed by `mapping`, which also renames row fields, and
+/// created from the first row if it does not exist. Rows Milvus rejects go to
+/// a dead-letter file in the working directory.
+#[allow(clippy::too_many_arguments)]
+pub async fn restore_to_milvus(
+    host: &str,
+    collection: &str,
+    token: Option<&str>,
+    db_name: Option<&str>,
+    vector_field: &str,
+    metric: &str,
+    file_path: &str,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let collection = &crate::milvus_rows::collection_name(&mapping.map_name(collection));
+    info!("Restoring to Milvus at {}, collection {}", host, collection);
+    let mut client = crate::milvus_rows::MilvusClient::connect(host, token, db_name, &crate::ui::models::TimeoutConfig::default()).await?;
+    let mut dead_letters = DeadLetterWriter::new("milvus", host, collection, "rows", dead_letter_path(Path::new("."), collection, chrono::Utc::now()));
+    let inserted = crate::milvus_rows::import_rows(&mut client, collection, vector_field, metric, Path::new(file_path), mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} rows from {} to Milvus collection {} at {}", inserted, file_path, collection, host);
+    dead_letters.finish()
+}
+
+/// Restore an NDJSON snapshot of items to an existing DynamoDB table
+///
+/// The table is renamed by `mapping`, which also renames item attributes.
+/// Items DynamoDB rejects, or that stay throttled, go to a dead-letter file in
+/// the working directory.
+pub async fn restore_to_dynamodb(
+    table: &str,
+    region: Option<&str>,
+    endpoint_url: Option<&str>,
+    file_path: &str,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let table = &mapping.map_name(table);
+    info!("Restoring to DynamoDB table {}", table);
+    let client = crate::dynamodb_items::connect(region, endpoint_url, &crate::ui::models::TimeoutConfig::default()).await?;
+    let endpoint = crate::dynamodb_items::endpoint_name(&client, endpoint_url);
+    let mut dead_letters = DeadLetterWriter::new("dynamodb", &endpoint, table, "items", dead_letter_path(Path::new("."), table, chrono::Utc::now()));
+    let written = crate::dynamodb_items::import_items(&client, table, Path::new(file_path), mapping, None, &mut dead_letters).await?;
+    debug!("Restored {} items from {} to DynamoDB table {} at {}", written, file_path, table, endpoint);
+    dead_letters.finish()
+}
+
+/// Restore a line protocol snapshot to an InfluxDB 2.x bucket
+///
+/// The bucket is renamed by `mapping` and created if it does not exist. Lines
+/// InfluxDB refuses go to a dead-letter file in the working directory.
+pub async fn restore_to_influxdb(
+    target: &crate::influx_lines::InfluxBucket,
+    file_path: &str,
+    mapping: &RestoreMapping,
+) -> Result<Option<DeadLetterReport>> {
+    let target = crate::influx_lines::InfluxBucket { bucket: mapping.map_name(&target.bucket), ..target.clone() };
+    info!("Restoring to InfluxDB at {}, bucket {}", target.host, target.bucket);
+    let client = crate::ui::models::TimeoutConfig::default().http_client()?;
+    let path = dead_letter_path(Path::new("."), &target.bucket, chrono::Utc::now());
+    let mut dead_letters = DeadLetterWriter::new("influxdb", &target.host, &target.bucket, "points", path);
+    let written = crate::influx_lines::write_lines(&client, &target, Path::new(file_path), None, &mut dead_letters).await?;
+    debug!("Restored {} points from {} to InfluxDB bucket {} at {}", written, file_path, target.bucket, target.host);
+    dead_letters.finish()
 }
@@ -0,0 +1,361 @@
+// This module contains dead-letter files for the Rustored application
+// A bulk restore into Elasticsearch or Qdrant should not fail outright because
+// a few documents or points are rejected, e.g. for a mapping conflict. Rejected
+// records are written with the reason to a local newline-delimited JSON file
+// instead, the count is reported with the restore, and `retry-failed` loads the
+// file again once the cause is fixed. The loaders read their snapshots through
+// `NdjsonBatches`, which dead-letters the lines that are not JSON.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use crate::mapping::RestoreMapping;
+
+/// A document or point a restore could not load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Kind of target, `elasticsearch` or `qdrant`
+    pub target: String,
+    /// Host the restore wrote to
+    pub host: String,
+    /// Index or collection the record was meant for
+    pub name: String,
+    /// Why the record was rejected
+    pub reason: String,
+    /// The record as read from the snapshot
+    pub record: Value,
+}
+
+/// Summary of the records a restore wrote to a dead-letter file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetterReport {
+    pub count: u64,
+    /// What the records are, e.g. `documents` or `points`
+    pub unit: &'static str,
+    pub path: PathBuf,
+}
+
+impl DeadLetterReport {
+    /// One line for the restore summary, with the command that retries the records
+    pub fn summary(&self) -> String {
+        debug!("Summarising {} dead letters", self.count);
+        format!(
+            "{} {} rejected, written to {}; fix the cause and run `rustored retry-failed {}`",
+            self.count,
+            self.unit,
+            self.path.display(),
+            self.path.display()
+        )
+    }
+}
+
+/// Path of the dead-letter file of a restore into `name` started at `started`
+pub fn dead_letter_path(dir: &Path, name: &str, started: DateTime<Utc>) -> PathBuf {
+    debug!("Building dead-letter path for {}", name);
+    dir.join(format!("{}-{}.dead-letter.ndjson", name, started.format("%Y%m%dT%H%M%SZ")))
+}
+
+/// Writes the rejected records of one restore, creating the file on the first one
+pub struct DeadLetterWriter {
+    target: &'static str,
+    host: String,
+    name: String,
+    unit: &'static str,
+    path: PathBuf,
+    file: Option<BufWriter<std::fs::File>>,
+    count: u64,
+}
+
+impl DeadLetterWriter {
+    /// Writer for a restore into `name` on `host`
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Kind of target, `elasticsearch` or `qdrant`
+    /// * `host` - Host the restore writes to
+    /// * `name` - Index or collection the restore writes to
+    /// * `unit` - What the records are, e.g. `documents`
+    /// * `path` - File the rejected records are written to
+    pub fn new(target: &'static str, host: &str, name: &str, unit: &'static str, path: PathBuf) -> Self {
+        debug!("Creating dead-letter writer for {} at {:?}", name, path);
+        Self { target, host: host.to_string(), name: name.to_string(), unit, path, file: None, count: 0 }
+    }
+
+    /// Record later rejections as meant for `name`, e.g. the index behind an alias
+    pub fn retarget(&mut self, name: &str) {
+        debug!("Retargeting dead letters from {} to {}", self.name, name);
+        self.name = name.to_string();
+    }
+
+    /// Number of records written so far
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Write a rejected record with the reason it was rejected
+    pub fn write(&mut self, record: Value, reason: &str) -> Result<()> {
+        debug!("Writing dead letter for {}: {}", self.name, reason);
+        if self.file.is_none() {
+            let file = std::fs::File::create(&self.path)
+                .with_context(|| format!("Failed to create dead-letter file {}", self.path.display()))?;
+            self.file = Some(BufWriter::new(file));
+        }
+        let letter = DeadLetter {
+            target: self.target.to_string(),
+            host: self.host.clone(),
+            name: self.name.clone(),
+            reason: reason.to_string(),
+            record,
+        };
+        if let Some(file) = self.file.as_mut() {
+            serde_json::to_writer(&mut *file, &letter)?;
+            file.write_all(b"\n")?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Flush the file and report what was written, if anything
+    pub fn finish(mut self) -> Result<Option<DeadLetterReport>> {
+        debug!("Finishing dead-letter file {:?} with {} records", self.path, self.count);
+        match self.file.as_mut() {
+            Some(file) => {
+                file.flush()?;
+                info!("Wrote {} rejected {} to {}", self.count, self.unit, self.path.display());
+                Ok(Some(DeadLetterReport { count: self.count, unit: self.unit, path: self.path }))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads a newline-delimited snapshot in batches, decompressing it first if needed
+///
+/// Blank lines are skipped. `next_batch` writes the lines that are not JSON to
+/// the dead-letter file, so a batch only holds records worth sending.
+pub struct NdjsonBatches {
+    // Keeps the decompressed copy until the snapshot is read
+    _decompressed: Option<NamedTempFile>,
+    path: PathBuf,
+    lines: Lines<BufReader<tokio::fs::File>>,
+    batch_size: usize,
+    size: u64,
+    read_bytes: u64,
+}
+
+impl NdjsonBatches {
+    /// Open a snapshot to read `batch_size` records at a time
+    pub async fn open(path: &Path, batch_size: usize) -> Result<Self> {
+        debug!("Opening snapshot {:?} in batches of {}", path, batch_size);
+        let decompressed = crate::compression::decompress_file(path).await?;
+        let path = decompressed.as_ref().map_or(path, |file| file.path()).to_path_buf();
+        let file = tokio::fs::File::open(&path).await.with_context(|| format!("Failed to open snapshot {:?}", path))?;
+        let size = file.metadata().await?.len().max(1);
+        let lines = BufReader::new(file).lines();
+        Ok(Self { _decompressed: decompressed, path, lines, batch_size: batch_size.max(1), size, read_bytes: 0 })
+    }
+
+    /// Path of the snapshot as read, after decompressing
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the snapshot as read, at least one byte
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Bytes read so far, including the lines of batches not yet sent
+    pub fn read_bytes(&self) -> u64 {
+        self.read_bytes
+    }
+
+    /// Share of the snapshot read so far
+    pub fn progress(&self) -> f32 {
+        self.read_bytes as f32 / self.size as f32
+    }
+
+    /// The next line that is not blank, or `None` at the end of the snapshot
+    async fn next_line(&mut self) -> Result<Option<String>> {
+        while let Some(line) = self.lines.next_line().await? {
+            self.read_bytes += line.len() as u64 + 1;
+            if !line.trim().is_empty() {
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The next lines that are not blank, unparsed, or `None` at the end of the snapshot
+    pub async fn next_lines(&mut self) -> Result<Option<Vec<String>>> {
+        debug!("Reading up to {} lines from {:?}", self.batch_size, self.path);
+        let mut lines = Vec::with_capacity(self.batch_size);
+        while lines.len() < self.batch_size {
+            match self.next_line().await? {
+                Some(line) => lines.push(line),
+                None => break,
+            }
+        }
+        Ok(Some(lines).filter(|lines| !lines.is_empty()))
+    }
+
+    /// The next records, or `None` at the end of the snapshot
+    ///
+    /// Lines that are not JSON are written to `dead_letters` as strings.
+    pub async fn next_batch(&mut self, dead_letters: &mut DeadLetterWriter) -> Result<Option<Vec<Value>>> {
+        debug!("Reading up to {} records from {:?}", self.batch_size, self.path);
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while batch.len() < self.batch_size {
+            let Some(line) = self.next_line().await? else {
+                break;
+            };
+            match serde_json::from_str::<Value>(&line) {
+                Ok(record) => batch.push(record),
+                Err(e) => dead_letters.write(Value::String(line), &format!("invalid JSON: {}", e))?,
+            }
+        }
+        Ok(Some(batch).filter(|batch| !batch.is_empty()))
+    }
+}
+
+/// Read the records of a dead-letter file
+pub fn read_dead_letters(path: &Path) -> Result<Vec<DeadLetter>> {
+    debug!("Reading dead letters from {:?}", path);
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open dead-letter file {}", path.display()))?;
+    std::io::BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(i, line)| {
+            serde_json::from_str(&line?).map_err(|e| anyhow!("Invalid dead letter on line {} of {}: {}", i + 1, path.display(), e))
+        })
+        .collect()
+}
+
+/// Result of retrying a dead-letter file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryOutcome {
+    /// Records read from the file
+    pub retried: u64,
+    /// Records rejected again, written to a new dead-letter file
+    pub dead_letters: Option<DeadLetterReport>,
+}
+
+impl RetryOutcome {
+    /// Records loaded by the retry
+    pub fn loaded(&self) -> u64 {
+        self.retried - self.dead_letters.as_ref().map_or(0, |report| report.count)
+    }
+}
+
+/// Settings of the targets a dead-letter file can be retried into
+///
+/// Only the settings of the target the file records are used, so each target
+/// gets its own credentials and region.
+#[derive(Debug, Clone, Default)]
+pub struct RetryTargets {
+    /// Elasticsearch bulk and TLS settings
+    pub elasticsearch: crate::ui::models::ElasticsearchConfig,
+    /// Qdrant API key, TLS and transport
+    pub qdrant: crate::ui::models::QdrantConfig,
+    /// OpenSearch region, SigV4 service and bulk settings
+    pub opensearch: crate::ui::models::OpenSearchConfig,
+    /// Meilisearch API key
+    pub meilisearch: crate::ui::models::MeilisearchConfig,
+    /// Weaviate API key or OIDC client credentials
+    pub weaviate: crate::ui::models::WeaviateConfig,
+    /// Milvus token and database
+    pub milvus: crate::ui::models::MilvusConfig,
+    /// DynamoDB region
+    pub dynamodb: crate::ui::models::DynamoDbConfig,
+    /// InfluxDB organization, token and precision
+    pub influxdb: crate::ui::models::InfluxDbConfig,
+}
+
+/// Load the records of a dead-letter file into the index or collection they were meant for
+///
+/// Records rejected again go to a new dead-letter file in the working
+/// directory, so the retry can be repeated until none are left.
+///
+/// # Arguments
+///
+/// * `path` - The dead-letter file
+/// * `host` - Host to load into instead of the one recorded in the file
+/// * `targets` - Settings of each target, of which the recorded target's are used
+pub async fn retry_dead_letters(path: &Path, host: Option<&str>, targets: &RetryTargets) -> Result<RetryOutcome> {
+    debug!("Retrying dead letters from {:?}", path);
+    let letters = read_dead_letters(path)?;
+    let first = letters.first().ok_or_else(|| anyhow!("Dead-letter file {} holds no records", path.display()))?;
+    if letters.iter().any(|letter| letter.target != first.target || letter.name != first.name) {
+        return Err(anyhow!("Dead-letter file {} mixes records of several targets", path.display()));
+    }
+    let host = host.unwrap_or(&first.host);
+
+    // Write the records back out as a snapshot the loaders can read; lines
+    // that were not JSON are written as they were read
+    let mut snapshot = tempfile::NamedTempFile::new()?;
+    for letter in &letters {
+        match &letter.record {
+            Value::String(line) => writeln!(snapshot, "{}", line)?,
+            record => writeln!(snapshot, "{}", record)?,
+        }
+    }
+    snapshot.flush()?;
+    let snapshot_path = snapshot.path().to_string_lossy().to_string();
+
+    info!("Retrying {} records into {} {} at {}", letters.len(), first.target, first.name, host);
+    // The records go straight into the index they were meant for, never to a new one behind an alias
+    let single = |bulk: &crate::ui::models::BulkConfig| crate::ui::models::BulkConfig { alias_swap: false, ..bulk.clone() };
+    // Dead letters hold the name and records as they were loaded, after any mapping
+    let dead_letters = match first.target.as_str() {
+        "elasticsearch" => {
+            let es = &targets.elasticsearch;
+            crate::datastore::restore_to_elasticsearch(host, &first.name, &snapshot_path, &single(&es.bulk), &es.tls, &RestoreMapping::default()).await?
+        }
+        "qdrant" => {
+            let qdrant = &targets.qdrant;
+            crate::datastore::restore_to_qdrant(host, &first.name, qdrant.api_key.as_deref(), &qdrant.tls, &qdrant.upload, &snapshot_path, &RestoreMapping::default()).await?
+        }
+        "opensearch" => {
+            let os = &targets.opensearch;
+            crate::datastore::restore_to_opensearch(host, &first.name, os.region.as_deref(), &os.service, &snapshot_path, &single(&os.bulk), &RestoreMapping::default()).await?
+        }
+        // The index already exists, so it keeps its primary key
+        "meilisearch" => {
+            let api_key = targets.meilisearch.api_key.as_deref();
+            crate::datastore::restore_to_meilisearch(host, &first.name, api_key, None, &snapshot_path, &RestoreMapping::default()).await?
+        }
+        "weaviate" => crate::datastore::restore_to_weaviate(host, &first.name, &targets.weaviate.credentials(), &snapshot_path, &RestoreMapping::default()).await?,
+        // The collection already exists, so its schema decides the vector field
+        "milvus" => {
+            let milvus = &targets.milvus;
+            let (vector_field, metric) = (crate::milvus_rows::DEFAULT_VECTOR_FIELD, crate::milvus_rows::DEFAULT_METRIC);
+            crate::datastore::restore_to_milvus(host, &first.name, milvus.token.as_deref(), milvus.db_name.as_deref(), vector_field, metric, &snapshot_path, &RestoreMapping::default()).await?
+        }
+        // The host is an endpoint URL, or `dynamodb.<region>` for AWS's own
+        "dynamodb" => {
+            let endpoint_url = host.starts_with("http").then_some(host);
+            let region = targets.dynamodb.region.as_deref().or_else(|| host.strip_prefix("dynamodb."));
+            crate::datastore::restore_to_dynamodb(&first.name, region, endpoint_url, &snapshot_path, &RestoreMapping::default()).await?
+        }
+        "influxdb" => {
+            let influx = &targets.influxdb;
+            let target = crate::influx_lines::InfluxBucket {
+                host: host.to_string(),
+                org: influx.org.clone().ok_or_else(|| anyhow!("Retrying InfluxDB dead letters needs --influx-org"))?,
+                bucket: first.name.clone(),
+                token: influx.token.clone(),
+                precision: influx.precision.clone().unwrap_or_else(|| crate::influx_lines::DEFAULT_PRECISION.to_string()),
+            };
+            crate::datastore::restore_to_influxdb(&target, &snapshot_path, &RestoreMapping::default()).await?
+        }
+        other => return Err(anyhow!("Unknown dead-letter target: {}", other)),
+    };
+    Ok(RetryOutcome { retried: letters.len() as u64, dead_letters })
+}
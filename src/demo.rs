@@ -0,0 +1,58 @@
+// This module contains the demo mode of the Rustored application
+// `rustored --demo` fills the TUI with synthetic snapshots served from memory,
+// streams them at a throttled rate so downloads show real progress, and
+// simulates restores, so the UI can be shown, screenshotted or worked on
+// without credentials, a bucket or a database server.
+
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use std::sync::Arc;
+use crate::storage::DemoStore;
+use crate::ui::rustored::RustoredApp;
+
+/// Bucket shown in the S3 settings in demo mode
+pub const DEMO_BUCKET: &str = "demo-snapshots";
+
+/// Prefix the demo snapshots are listed under
+pub const DEMO_PREFIX: &str = "postgres";
+
+/// Contents of a "backup now" snapshot taken in demo mode
+pub const DEMO_DUMP: &[u8] = b"PGDMP demo snapshot, not a real pg_dump archive\n";
+
+/// Switch an app to demo mode
+///
+/// The settings panels get placeholder servers so every target counts as
+/// configured, the snapshot browser lists from a `DemoStore`, and restores go
+/// to a simulated target. Listings are not cached, so the demo never shows up
+/// as a stale listing of a real bucket.
+///
+/// # Arguments
+///
+/// * `app` - The app to switch, built from the command line as usual
+/// * `now` - Time the synthetic snapshots are dated back from
+pub fn enable(app: &mut RustoredApp, now: DateTime<Utc>) {
+    debug!("Enabling demo mode");
+    app.demo = true;
+    app.s3_config.bucket = DEMO_BUCKET.to_string();
+    app.s3_config.region = "us-east-1".to_string();
+    app.s3_config.prefix = DEMO_PREFIX.to_string();
+    app.s3_config.endpoint_url.clear();
+    app.snapshot_browser.s3_config = app.s3_config.clone();
+    app.snapshot_browser.gcs_config = None;
+    app.snapshot_browser.azure_config = None;
+    app.snapshot_browser.b2_config = None;
+    app.snapshot_browser.sftp_config = None;
+    app.snapshot_browser.listing_cache = None;
+
+    app.pg_config.host = Some("demo.internal".to_string());
+    app.pg_config.port = Some(5432);
+    app.pg_config.username = Some("demo".to_string());
+    app.pg_config.db_name = Some("orders".to_string());
+    app.es_config.host = Some("http://demo.internal:9200".to_string());
+    app.es_config.index = Some("products".to_string());
+    app.qdrant_config.host = Some("http://demo.internal:6333".to_string());
+    app.qdrant_config.collection = Some("embeddings".to_string());
+
+    app.snapshot_browser.set_store(Arc::new(DemoStore::new(DEMO_PREFIX, now)));
+    info!("Demo mode enabled, no bucket or server will be contacted");
+}
@@ -0,0 +1,180 @@
+// This module contains directory-format dumps for the Rustored application
+// `pg_dump --format directory` is the only format pg_dump can write with
+// parallel workers, one file per table. The directory is packed into a single
+// tar file so it can be uploaded like any other snapshot, and tar snapshots
+// are unpacked again before pg_restore reads them with parallel jobs. A tar
+// written by `pg_dump --format tar` unpacks into a valid directory archive as
+// well, so it is restored in parallel too.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tempfile::TempDir;
+use crate::ui::models::PostgresConfig;
+
+/// Extension of a packed directory-format snapshot, in place of `.dump`
+pub const DIRECTORY_EXTENSION: &str = ".dir.tar";
+
+/// Offset of the `ustar` magic in the header of a tar file
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// How often the size of a running directory dump is reported
+const PROGRESS_POLL: Duration = Duration::from_millis(500);
+
+/// Key of a directory-format snapshot, swapping the `.dump` extension of `key`
+pub fn directory_key(key: &str) -> String {
+    debug!("Building directory snapshot key from {}", key);
+    format!("{}{}", key.strip_suffix(".dump").unwrap_or(key), DIRECTORY_EXTENSION)
+}
+
+/// Whether a file is a tar archive, such as a packed directory-format dump
+pub fn is_tar_file(path: &Path) -> Result<bool> {
+    debug!("Checking whether {:?} is a tar archive", path);
+    let mut header = Vec::with_capacity(TAR_MAGIC_OFFSET + 5);
+    std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(TAR_MAGIC_OFFSET as u64 + 5)
+        .read_to_end(&mut header)?;
+    Ok(header.get(TAR_MAGIC_OFFSET..) == Some(b"ustar"))
+}
+
+/// Number of parallel jobs to restore a directory-format dump with when none is set
+pub fn default_jobs() -> u32 {
+    debug!("Choosing default number of restore jobs");
+    std::thread::available_parallelism().map_or(1, |cpus| cpus.get() as u32)
+}
+
+/// Dump a database in directory format with parallel workers and pack it into a tar file
+///
+/// The directory is written next to `output` and removed once packed.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `name` - Name of the database to dump
+/// * `jobs` - Number of tables pg_dump dumps at once
+/// * `output` - Path of the tar file to write
+/// * `on_progress` - Called with the number of bytes dumped so far
+///
+/// # Returns
+///
+/// The size of the dumped directory in bytes
+pub async fn dump_directory(
+    pg_config: &PostgresConfig,
+    name: &str,
+    jobs: u32,
+    output: &Path,
+    mut on_progress: impl FnMut(u64),
+) -> Result<u64> {
+    debug!("Dumping database {} in directory format with {} jobs to {:?}", name, jobs, output);
+    let parent = output.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let work = TempDir::new_in(parent)?;
+    let dir = work.path().join(name);
+
+    let mut cmd = tokio::process::Command::from(crate::backup::pg_dump_command(
+        name,
+        pg_config.host.as_deref().unwrap_or("localhost"),
+        pg_config.port.unwrap_or(5432),
+        pg_config.username.as_deref(),
+        pg_config.password.as_deref(),
+        false,
+    ));
+    cmd.args(["--format", "directory", "--jobs", &jobs.max(1).to_string(), "--file"]).arg(&dir);
+    if pg_config.use_ssl {
+        cmd.env("PGSSLMODE", "require");
+    }
+    // Killed if the dump is cancelled
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute pg_dump")?;
+    let mut stderr = child.stderr.take().ok_or_else(|| anyhow!("pg_dump errors are not available"))?;
+    let errors = tokio::spawn(async move {
+        let mut errors = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut stderr, &mut errors).await.map(|_| errors)
+    });
+
+    // The workers write files of their own, so progress is the size of the directory
+    let mut poll = tokio::time::interval(PROGRESS_POLL);
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status.context("Failed to wait for pg_dump")?,
+            _ = poll.tick() => on_progress(directory_size(&dir)),
+        }
+    };
+    if !status.success() {
+        let errors = errors.await?.unwrap_or_default();
+        return Err(anyhow!("pg_dump failed: {}", errors.trim()));
+    }
+    let size = directory_size(&dir);
+    on_progress(size);
+
+    pack_directory(&dir, output).await?;
+    info!("Dumped {} in directory format and packed it into {:?}", name, output);
+    Ok(size)
+}
+
+/// Total size of the files directly inside a directory, as pg_dump writes them
+fn directory_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().filter_map(|entry| entry.metadata().ok()).map(|meta| meta.len()).sum())
+        .unwrap_or(0)
+}
+
+/// Pack the contents of a directory into a tar file with the `tar` tool
+pub async fn pack_directory(dir: &Path, output: &Path) -> Result<()> {
+    debug!("Packing {:?} into {:?}", dir, output);
+    let mut cmd = Command::new("tar");
+    cmd.arg("--create").arg("--file").arg(output).arg("--directory").arg(dir).arg(".");
+    run_tar(cmd).await
+}
+
+/// Unpack a tar snapshot into a directory pg_restore can read with parallel jobs
+///
+/// The directory is created next to `path`, where the download already had
+/// room, and removed when the returned directory is dropped.
+pub async fn unpack(path: &Path) -> Result<TempDir> {
+    debug!("Unpacking tar snapshot {:?}", path);
+    let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let dir = TempDir::new_in(parent)?;
+    run_tar(extract_command(path, dir.path())).await?;
+    Ok(dir)
+}
+
+/// Unpack a tar snapshot without a runtime, for the blocking archive checks
+pub fn unpack_blocking(path: &Path) -> Result<TempDir> {
+    debug!("Unpacking tar snapshot {:?} for a check", path);
+    let dir = TempDir::new()?;
+    let output = extract_command(path, dir.path()).output().context("Failed to execute tar")?;
+    if !output.status.success() {
+        return Err(anyhow!("tar failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(dir)
+}
+
+/// Command extracting the tar file at `path` into `dir`
+fn extract_command(path: &Path, dir: &Path) -> Command {
+    let mut cmd = Command::new("tar");
+    cmd.arg("--extract").arg("--file").arg(path).arg("--directory").arg(dir);
+    cmd
+}
+
+/// Run a tar command to completion, failing with its errors
+async fn run_tar(cmd: Command) -> Result<()> {
+    let output = tokio::process::Command::from(cmd)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to execute tar; install it to pack and unpack directory-format dumps")?;
+    if !output.status.success() {
+        return Err(anyhow!("tar failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
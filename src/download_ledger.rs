@@ -0,0 +1,249 @@
+// This module contains the download ledger for the Rustored application
+// Snapshots are downloaded in fixed-size parts. The SHA-256 checksum of every
+// completed part is persisted in a sidecar file next to the download, so an
+// interrupted download can be resumed by re-verifying the parts already on disk
+// instead of trusting the length of the partial file.
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Default size of a single download part (8 MiB)
+pub const DEFAULT_PART_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Default number of parts downloaded at once
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// Extension appended to the download path to form the ledger path
+const LEDGER_EXTENSION: &str = "ledger";
+
+/// Sidecar ledger recording the checksums of completed download parts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadLedger {
+    /// S3 key of the snapshot being downloaded
+    pub key: String,
+    /// Total size of the snapshot in bytes
+    pub size: u64,
+    /// Last modified timestamp of the snapshot, used to detect a replaced object
+    pub last_modified: f64,
+    /// ETag (or backend version id) of the snapshot, used to detect a replaced object
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// Size of each part in bytes (the last part may be shorter)
+    pub part_size: u64,
+    /// Hex encoded SHA-256 checksum of each completed part, keyed by part index
+    pub parts: BTreeMap<u64, String>,
+}
+
+impl DownloadLedger {
+    /// Create an empty ledger for a snapshot
+    pub fn new(key: &str, size: u64, last_modified: f64, part_size: u64) -> Self {
+        debug!("Creating new download ledger for {} ({} bytes)", key, size);
+        Self {
+            key: key.to_string(),
+            size,
+            last_modified,
+            etag: None,
+            part_size: part_size.max(1),
+            parts: BTreeMap::new(),
+        }
+    }
+
+    /// Path of the ledger sidecar for a download path
+    pub fn ledger_path(download_path: &Path) -> PathBuf {
+        debug!("Computing ledger path for {:?}", download_path);
+        let mut path = download_path.as_os_str().to_owned();
+        path.push(".");
+        path.push(LEDGER_EXTENSION);
+        PathBuf::from(path)
+    }
+
+    /// Load the ledger for a download path, if one exists
+    pub fn load(download_path: &Path) -> Result<Option<Self>> {
+        let path = Self::ledger_path(download_path);
+        debug!("Loading download ledger from {:?}", path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read download ledger {}", path.display()))?;
+        let ledger = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse download ledger {}", path.display()))?;
+        Ok(Some(ledger))
+    }
+
+    /// Whether the ledger describes this exact object
+    ///
+    /// The key, size and last modified time must match, and so must the ETag
+    /// when the backend reports one.
+    pub fn describes(&self, key: &str, size: u64, last_modified: f64, etag: Option<&str>) -> bool {
+        debug!("Checking if ledger describes {}", key);
+        self.key == key && self.size == size && self.last_modified == last_modified && self.etag.as_deref() == etag
+    }
+
+    /// Load the ledger for a download and verify it, or start a new one
+    ///
+    /// An existing ledger is only reused when it describes the same object
+    /// (see `describes`). Parts whose on-disk checksum no longer matches the
+    /// ledger are dropped so they get downloaded again.
+    pub fn resume_or_new(
+        download_path: &Path,
+        key: &str,
+        size: u64,
+        last_modified: f64,
+        etag: Option<&str>,
+        part_size: u64,
+    ) -> Self {
+        debug!("Resuming or creating download ledger for {}", key);
+        let fresh = || Self { etag: etag.map(str::to_string), ..Self::new(key, size, last_modified, part_size) };
+        match Self::load(download_path) {
+            Ok(Some(mut ledger)) if ledger.describes(key, size, last_modified, etag) => {
+                match ledger.verify_parts(download_path) {
+                    Ok(verified) => {
+                        info!("Resuming download of {} with {} verified parts", key, verified);
+                        ledger
+                    }
+                    Err(e) => {
+                        warn!("Failed to verify existing parts of {}: {}", key, e);
+                        fresh()
+                    }
+                }
+            }
+            Ok(Some(_)) => {
+                debug!("Existing ledger describes a different object, starting over");
+                fresh()
+            }
+            Ok(None) => fresh(),
+            Err(e) => {
+                warn!("Ignoring unreadable download ledger: {}", e);
+                fresh()
+            }
+        }
+    }
+
+    /// Bytes already downloaded to `download_path` for an object, if a partial
+    /// download of that exact object can be resumed
+    ///
+    /// The parts are not re-verified here; that happens when the download resumes.
+    pub fn resumable_bytes(download_path: &Path, key: &str, size: u64, last_modified: f64, etag: Option<&str>) -> Option<u64> {
+        debug!("Checking for a resumable download of {} at {:?}", key, download_path);
+        match Self::load(download_path) {
+            Ok(Some(ledger)) if ledger.describes(key, size, last_modified, etag) && download_path.exists() => {
+                Some(ledger.completed_bytes())
+            }
+            _ => None,
+        }
+    }
+
+    /// Remove a partial download and its ledger so the next download starts over
+    pub fn discard(download_path: &Path) -> Result<()> {
+        debug!("Discarding partial download {:?}", download_path);
+        if download_path.exists() {
+            std::fs::remove_file(download_path)?;
+        }
+        Self::remove(download_path)
+    }
+
+    /// Persist the ledger next to the download
+    pub fn save(&self, download_path: &Path) -> Result<()> {
+        let path = Self::ledger_path(download_path);
+        debug!("Saving download ledger with {} parts to {:?}", self.parts.len(), path);
+        // Write to a temporary file first so a crash never leaves a truncated ledger
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Remove the ledger once the download has completed
+    pub fn remove(download_path: &Path) -> Result<()> {
+        let path = Self::ledger_path(download_path);
+        debug!("Removing download ledger {:?}", path);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Number of parts the snapshot is split into
+    pub fn part_count(&self) -> u64 {
+        debug!("Computing part count for {}", self.key);
+        self.size.div_ceil(self.part_size)
+    }
+
+    /// Byte range `(start, end_inclusive)` covered by a part
+    pub fn part_range(&self, index: u64) -> (u64, u64) {
+        debug!("Computing byte range of part {}", index);
+        let start = index * self.part_size;
+        let end = (start + self.part_size).min(self.size).saturating_sub(1);
+        (start, end)
+    }
+
+    /// Whether a part has been downloaded and recorded
+    pub fn is_part_complete(&self, index: u64) -> bool {
+        debug!("Checking if part {} is complete", index);
+        self.parts.contains_key(&index)
+    }
+
+    /// Record the checksum of a completed part
+    pub fn record_part(&mut self, index: u64, checksum: String) {
+        debug!("Recording part {} of {}", index, self.key);
+        self.parts.insert(index, checksum);
+    }
+
+    /// Number of bytes covered by completed parts
+    pub fn completed_bytes(&self) -> u64 {
+        debug!("Computing completed bytes for {}", self.key);
+        self.parts.keys()
+            .map(|&index| {
+                let (start, end) = self.part_range(index);
+                end - start + 1
+            })
+            .sum()
+    }
+
+    /// Re-hash recorded parts from disk and drop any that no longer match
+    ///
+    /// # Returns
+    ///
+    /// The number of parts that were verified successfully
+    pub fn verify_parts(&mut self, download_path: &Path) -> Result<usize> {
+        debug!("Verifying {} recorded parts of {:?}", self.parts.len(), download_path);
+        let mut file = File::open(download_path)?;
+        let mut corrupt = Vec::new();
+        for (&index, expected) in &self.parts {
+            let (start, end) = self.part_range(index);
+            let mut data = vec![0u8; (end - start + 1) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            let matches = file.read_exact(&mut data).is_ok() && Self::checksum(&data) == *expected;
+            if !matches {
+                warn!("Part {} of {} failed verification and will be downloaded again", index, self.key);
+                corrupt.push(index);
+            }
+        }
+        for index in corrupt {
+            self.parts.remove(&index);
+        }
+        Ok(self.parts.len())
+    }
+
+    /// Hex encoded SHA-256 checksum of a part
+    pub fn checksum(data: &[u8]) -> String {
+        debug!("Computing checksum of {} bytes", data.len());
+        hex::encode(Sha256::digest(data))
+    }
+}
+
+/// Default path the TUI and `restore-from-s3` download a snapshot to
+///
+/// The path only depends on the key, so an interrupted download of the same
+/// snapshot is picked up again by the next attempt.
+pub fn temp_download_path(key: &str) -> PathBuf {
+    debug!("Computing temporary download path for {}", key);
+    std::env::temp_dir().join(format!("rustored_snapshot_{}", key.replace('/', "_")))
+}
@@ -0,0 +1,347 @@
+// This module contains the DynamoDB item loader for the Rustored application
+// DynamoDB snapshots are newline-delimited JSON, one item per line: either the
+// `{"Item": {...}}` lines of a DynamoDB export to S3, whose attributes carry
+// their types (`{"S": "x"}`, `{"N": "1"}`), or plain JSON documents, whose
+// values are typed from JSON as strings, numbers, booleans, nulls, lists and
+// maps. Items are written 25 at a time with BatchWriteItem, the most it takes.
+//
+// A throttled table hands back the items it did not write, which are sent
+// again after a growing pause; items still unwritten after the last attempt go
+// to the dead-letter file. A batch DynamoDB refuses as a whole, such as one
+// with an item missing its key, is written item by item instead so only the
+// bad items are dead-lettered. Dead letters are written as export lines, so
+// retrying them types every attribute as it was.
+//
+// The table must exist: an export does not record its key schema. Credentials
+// come from the default AWS credential chain, as for S3 and OpenSearch.
+
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_dynamodb::error::{DisplayErrorContext, ProvideErrorMetadata};
+use aws_sdk_dynamodb::primitives::Blob;
+use aws_sdk_dynamodb::types::{AttributeValue, PutRequest, WriteRequest};
+use aws_sdk_dynamodb::Client;
+use base64::Engine;
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+use crate::mapping::RestoreMapping;
+use crate::ui::models::TimeoutConfig;
+
+/// Items sent in one BatchWriteItem request, the most DynamoDB accepts
+pub const BATCH_WRITE_SIZE: usize = 25;
+
+/// Times a throttled write is sent before its items are given up on
+pub const MAX_WRITE_ATTEMPTS: u32 = 8;
+
+/// Pause before the first resend of throttled items, doubled for each one after
+const BASE_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Longest pause between resends
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Error codes DynamoDB answers with when a table or account is over its throughput
+const THROTTLING_CODES: &[&str] = &["ProvisionedThroughputExceededException", "ThrottlingException", "RequestLimitExceeded"];
+
+/// A DynamoDB item, by attribute name
+pub type Item = HashMap<String, AttributeValue>;
+
+/// Connect to DynamoDB with the default AWS credential chain
+///
+/// # Arguments
+///
+/// * `region` - AWS region of the table, the chain's region (e.g. `AWS_REGION`) if `None`
+/// * `endpoint_url` - Endpoint to use instead of AWS's, e.g. DynamoDB Local
+/// * `timeouts` - Connect and operation timeouts
+pub async fn connect(region: Option<&str>, endpoint_url: Option<&str>, timeouts: &TimeoutConfig) -> Result<Client> {
+    debug!("Connecting to DynamoDB in {:?} at {:?}", region, endpoint_url);
+    let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+    if let Some(region) = region {
+        loader = loader.region(aws_sdk_dynamodb::config::Region::new(region.to_string()));
+    }
+    let sdk_config = loader.load().await;
+    if sdk_config.region().is_none() {
+        return Err(anyhow!("No AWS region for DynamoDB; set --dynamodb-region or AWS_REGION"));
+    }
+    let mut builder = aws_sdk_dynamodb::config::Builder::from(&sdk_config).timeout_config(timeouts.to_aws());
+    if let Some(endpoint_url) = endpoint_url {
+        builder = builder.endpoint_url(endpoint_url);
+    }
+    Ok(Client::from_conf(builder.build()))
+}
+
+/// Where a client sends its requests, recorded in dead-letter files
+///
+/// The endpoint URL when one is set, otherwise `dynamodb.<region>`.
+pub fn endpoint_name(client: &Client, endpoint_url: Option<&str>) -> String {
+    debug!("Naming DynamoDB endpoint");
+    match (endpoint_url, client.config().region()) {
+        (Some(url), _) => url.to_string(),
+        (None, Some(region)) => format!("dynamodb.{}", region),
+        (None, None) => "dynamodb".to_string(),
+    }
+}
+
+/// Item count and size of a table, or `None` when it does not exist
+///
+/// DynamoDB updates both about every six hours, so they are approximate.
+pub async fn table_state(client: &Client, table: &str) -> Result<Option<(u64, u64)>> {
+    debug!("Describing DynamoDB table {}", table);
+    match client.describe_table().table_name(table).send().await {
+        Ok(output) => {
+            let description = output.table.ok_or_else(|| anyhow!("DynamoDB returned no description of table {}", table))?;
+            Ok(Some((description.item_count.unwrap_or_default().max(0) as u64, description.table_size_bytes.unwrap_or_default().max(0) as u64)))
+        }
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_resource_not_found_exception()) => Ok(None),
+        Err(e) => Err(anyhow!("Failed to describe DynamoDB table {}: {}", table, DisplayErrorContext(e))),
+    }
+}
+
+/// Read one attribute of an export line, e.g. `{"N": "1"}`
+fn typed_value(value: &Value) -> std::result::Result<AttributeValue, String> {
+    let (kind, inner) = value
+        .as_object()
+        .filter(|typed| typed.len() == 1)
+        .and_then(|typed| typed.iter().next())
+        .ok_or_else(|| format!("attribute {} is not a typed DynamoDB value", value))?;
+    let strings = |inner: &Value| -> std::result::Result<Vec<String>, String> {
+        inner.as_array()
+            .and_then(|values| values.iter().map(|value| value.as_str().map(String::from)).collect())
+            .ok_or_else(|| format!("{} must be a list of strings", kind))
+    };
+    let blob = |encoded: &str| {
+        base64::engine::general_purpose::STANDARD.decode(encoded).map(Blob::new).map_err(|e| format!("B is not base64: {}", e))
+    };
+    Ok(match (kind.as_str(), inner) {
+        ("S", Value::String(text)) => AttributeValue::S(text.clone()),
+        // Numbers are strings in exports, to keep their precision
+        ("N", Value::String(number)) => AttributeValue::N(number.clone()),
+        ("N", Value::Number(number)) => AttributeValue::N(number.to_string()),
+        ("B", Value::String(encoded)) => AttributeValue::B(blob(encoded)?),
+        ("BOOL", Value::Bool(flag)) => AttributeValue::Bool(*flag),
+        ("NULL", _) => AttributeValue::Null(true),
+        ("SS", inner) => AttributeValue::Ss(strings(inner)?),
+        ("NS", inner) => AttributeValue::Ns(strings(inner)?),
+        ("BS", inner) => AttributeValue::Bs(strings(inner)?.iter().map(|encoded| blob(encoded)).collect::<std::result::Result<_, _>>()?),
+        ("L", Value::Array(values)) => AttributeValue::L(values.iter().map(typed_value).collect::<std::result::Result<_, _>>()?),
+        ("M", Value::Object(fields)) => AttributeValue::M(typed_item(fields)?),
+        (kind, inner) => return Err(format!("unsupported DynamoDB value {} of type {}", inner, kind)),
+    })
+}
+
+/// Read the attributes of an export line's `Item`
+fn typed_item(fields: &Map<String, Value>) -> std::result::Result<Item, String> {
+    fields.iter().map(|(name, value)| Ok((name.clone(), typed_value(value)?))).collect()
+}
+
+/// Type a plain JSON value
+fn plain_value(value: Value) -> AttributeValue {
+    match value {
+        Value::Null => AttributeValue::Null(true),
+        Value::Bool(flag) => AttributeValue::Bool(flag),
+        Value::Number(number) => AttributeValue::N(number.to_string()),
+        Value::String(text) => AttributeValue::S(text),
+        Value::Array(values) => AttributeValue::L(values.into_iter().map(plain_value).collect()),
+        Value::Object(fields) => AttributeValue::M(fields.into_iter().map(|(name, value)| (name, plain_value(value))).collect()),
+    }
+}
+
+/// Build an item from a snapshot line
+///
+/// An `{"Item": {...}}` export line keeps its attribute types, and any other
+/// object is typed from JSON. Field renames of `mapping` apply to both.
+///
+/// # Returns
+///
+/// The item, or why the line cannot be written
+pub fn dynamodb_item(line: Value, mapping: &RestoreMapping) -> std::result::Result<Item, String> {
+    debug!("Building DynamoDB item");
+    match line {
+        Value::Object(mut fields) if fields.len() == 1 && fields.get("Item").is_some_and(Value::is_object) => {
+            let typed = mapping.map_document(fields.remove("Item").unwrap_or_default());
+            typed_item(typed.as_object().ok_or("Item must be an object")?)
+        }
+        Value::Object(_) => match mapping.map_document(line) {
+            Value::Object(fields) => Ok(fields.into_iter().map(|(name, value)| (name, plain_value(value))).collect()),
+            _ => Err("items must be JSON objects".to_string()),
+        },
+        _ => Err("items must be JSON objects".to_string()),
+    }
+}
+
+/// One attribute as it appears in an export line
+fn attribute_json(value: &AttributeValue) -> Value {
+    let encode = |blob: &Blob| Value::String(base64::engine::general_purpose::STANDARD.encode(blob.as_ref()));
+    match value {
+        AttributeValue::S(text) => json!({ "S": text }),
+        AttributeValue::N(number) => json!({ "N": number }),
+        AttributeValue::B(blob) => json!({ "B": encode(blob) }),
+        AttributeValue::Bool(flag) => json!({ "BOOL": flag }),
+        AttributeValue::Ss(texts) => json!({ "SS": texts }),
+        AttributeValue::Ns(numbers) => json!({ "NS": numbers }),
+        AttributeValue::Bs(blobs) => json!({ "BS": blobs.iter().map(encode).collect::<Vec<_>>() }),
+        AttributeValue::L(values) => json!({ "L": values.iter().map(attribute_json).collect::<Vec<_>>() }),
+        AttributeValue::M(fields) => json!({ "M": fields.iter().map(|(name, value)| (name.clone(), attribute_json(value))).collect::<Map<_, _>>() }),
+        _ => json!({ "NULL": true }),
+    }
+}
+
+/// An item as an export line, `{"Item": {...}}`
+pub fn item_json(item: &Item) -> Value {
+    json!({ "Item": item.iter().map(|(name, value)| (name.clone(), attribute_json(value))).collect::<Map<_, _>>() })
+}
+
+/// Whether an error means the table or account is over its throughput
+fn is_throttled(code: Option<&str>) -> bool {
+    code.is_some_and(|code| THROTTLING_CODES.contains(&code))
+}
+
+/// Write one item, retrying while throttled
+///
+/// # Returns
+///
+/// `Ok(Err(reason))` when DynamoDB refused the item, and an error when the
+/// write could not be made
+async fn put_item(client: &Client, table: &str, item: Item) -> Result<std::result::Result<(), String>> {
+    debug!("Writing one item to DynamoDB table {}", table);
+    let mut delay = BASE_BACKOFF;
+    for _ in 1..MAX_WRITE_ATTEMPTS {
+        match client.put_item().table_name(table).set_item(Some(item.clone())).send().await {
+            Ok(_) => return Ok(Ok(())),
+            Err(e) if is_throttled(e.code()) => {}
+            Err(e) if e.as_service_error().is_some() => {
+                return Ok(Err(e.message().map(String::from).unwrap_or_else(|| DisplayErrorContext(&e).to_string())));
+            }
+            Err(e) => return Err(anyhow!("Writing to DynamoDB table {} failed: {}", table, DisplayErrorContext(e))),
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_BACKOFF);
+    }
+    Ok(Err(format!("still throttled after {} attempts", MAX_WRITE_ATTEMPTS)))
+}
+
+/// Write a batch of at most 25 items
+///
+/// Items DynamoDB leaves unprocessed are sent again after a pause, and a batch
+/// it refuses as a whole is written item by item. Items that are not written
+/// go to `dead_letters`.
+///
+/// # Returns
+///
+/// The number of items written
+async fn write_batch(client: &Client, table: &str, batch: Vec<Item>, dead_letters: &mut DeadLetterWriter) -> Result<u64> {
+    debug!("Writing a batch of {} items to DynamoDB table {}", batch.len(), table);
+    let total = batch.len() as u64;
+    let mut requests = batch
+        .into_iter()
+        .map(|item| Ok(WriteRequest::builder().put_request(PutRequest::builder().set_item(Some(item)).build()?).build()))
+        .collect::<Result<Vec<_>>>()?;
+    let mut delay = BASE_BACKOFF;
+    for attempt in 1..=MAX_WRITE_ATTEMPTS {
+        match client.batch_write_item().request_items(table, requests.clone()).send().await {
+            Ok(output) => {
+                requests = output.unprocessed_items.and_then(|mut unprocessed| unprocessed.remove(table)).unwrap_or_default();
+                if requests.is_empty() {
+                    return Ok(total);
+                }
+                debug!("DynamoDB left {} items unprocessed on attempt {}", requests.len(), attempt);
+            }
+            Err(e) if is_throttled(e.code()) => debug!("DynamoDB throttled attempt {} at a batch", attempt),
+            // One bad item, or two with the same key, fails the whole batch
+            Err(e) if e.code() == Some("ValidationException") => {
+                warn!("DynamoDB refused a batch ({}), writing its items one by one", e.message().unwrap_or_default());
+                let mut written = total - requests.len() as u64;
+                for item in requests.into_iter().filter_map(|request| request.put_request.map(|put| put.item)) {
+                    match put_item(client, table, item.clone()).await? {
+                        Ok(()) => written += 1,
+                        Err(reason) => dead_letters.write(item_json(&item), &reason)?,
+                    }
+                }
+                return Ok(written);
+            }
+            Err(e) => return Err(anyhow!("Writing to DynamoDB table {} failed: {}", table, DisplayErrorContext(e))),
+        }
+        if attempt < MAX_WRITE_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_BACKOFF);
+        }
+    }
+    warn!("Giving up on {} throttled items after {} attempts", requests.len(), MAX_WRITE_ATTEMPTS);
+    let reason = format!("still throttled after {} attempts", MAX_WRITE_ATTEMPTS);
+    for request in &requests {
+        if let Some(put) = &request.put_request {
+            dead_letters.write(item_json(&put.item), &reason)?;
+        }
+    }
+    Ok(total - requests.len() as u64)
+}
+
+/// Write an NDJSON snapshot of items into an existing table
+///
+/// Lines that are not items, and items DynamoDB refuses or keeps throttling,
+/// are written to `dead_letters` and do not fail the load.
+///
+/// # Arguments
+///
+/// * `client` - DynamoDB client
+/// * `table` - Table to write the items to
+/// * `path` - The snapshot file
+/// * `mapping` - Field renames applied to each item before it is written
+/// * `progress` - Called with the share of the snapshot's items processed
+/// * `dead_letters` - Receives the items that could not be written
+///
+/// # Returns
+///
+/// The number of items written
+pub async fn import_items(
+    client: &Client,
+    table: &str,
+    path: &Path,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Importing items from {:?} to DynamoDB table {}", path, table);
+    if table_state(client, table).await?.is_none() {
+        return Err(anyhow!("DynamoDB table {} does not exist; create it with the key schema of the exported table first", table));
+    }
+    let mut snapshot = NdjsonBatches::open(path, BATCH_WRITE_SIZE).await?;
+
+    // Progress is by item, so the items are counted first
+    let file = tokio::fs::File::open(snapshot.path()).await.with_context(|| format!("Failed to open snapshot {:?}", snapshot.path()))?;
+    let mut counting = BufReader::new(file).lines();
+    let mut total = 0u64;
+    while let Some(line) = counting.next_line().await? {
+        total += u64::from(!line.trim().is_empty());
+    }
+    let total = total.max(1);
+
+    let (mut written, mut processed) = (0u64, 0u64);
+    let mut rejected = dead_letters.count();
+    while let Some(records) = snapshot.next_batch(dead_letters).await? {
+        // Lines that are not JSON were dead-lettered while the batch was read
+        processed += dead_letters.count() - rejected + records.len() as u64;
+        let mut batch = Vec::with_capacity(records.len());
+        for record in records {
+            match dynamodb_item(record.clone(), mapping) {
+                Ok(item) => batch.push(item),
+                Err(reason) => dead_letters.write(record, &reason)?,
+            }
+        }
+        if !batch.is_empty() {
+            written += write_batch(client, table, batch, dead_letters).await?;
+        }
+        rejected = dead_letters.count();
+        if let Some(progress) = progress {
+            progress(processed as f32 / total as f32);
+        }
+    }
+    if let Some(progress) = progress {
+        progress(1.0);
+    }
+    info!("Wrote {} items to DynamoDB table {}, {} rejected", written, table, dead_letters.count());
+    Ok(written)
+}
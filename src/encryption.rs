@@ -0,0 +1,222 @@
+// This module contains client-side encryption of backups for the Rustored application
+// Server-side encryption still leaves the plaintext readable to anyone who can
+// read the bucket. With age or GPG recipients configured, dumps are encrypted
+// before upload, so only ciphertext ever reaches the bucket, and downloaded
+// snapshots are decrypted with the age identity or the GPG keyring before
+// they are restored. Encryption uses the `age` and `gpg` command line tools.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tempfile::NamedTempFile;
+
+/// Tool a snapshot is encrypted with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Age,
+    Gpg,
+}
+
+impl fmt::Display for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Age => "age",
+            Self::Gpg => "gpg",
+        })
+    }
+}
+
+impl Cipher {
+    /// Recognise an encrypted file from its first bytes
+    ///
+    /// Binary OpenPGP messages are recognised by the tag of their first
+    /// packet, an encrypted session key, in the old or new packet format.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        debug!("Detecting encryption from {} header bytes", header.len());
+        if header.starts_with(b"age-encryption.org/") || header.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----") {
+            Some(Self::Age)
+        } else if header.starts_with(b"-----BEGIN PGP MESSAGE-----") || matches!(header.first(), Some(0x84 | 0x85 | 0x8c | 0x8d | 0xc1 | 0xc3)) {
+            Some(Self::Gpg)
+        } else {
+            None
+        }
+    }
+
+    /// Extension added to the keys of snapshots encrypted with the tool
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Age => ".age",
+            Self::Gpg => ".gpg",
+        }
+    }
+}
+
+/// Recipients backups are encrypted for, and the age identity to decrypt them with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientEncryption {
+    /// age recipients (`age1...` or SSH public keys)
+    pub age_recipients: Vec<String>,
+    /// GPG key IDs, fingerprints or email addresses
+    pub gpg_recipients: Vec<String>,
+    /// age identity file used to decrypt; GPG decrypts with the keys in its keyring
+    pub age_identity: Option<PathBuf>,
+}
+
+impl ClientEncryption {
+    /// Build the encryption settings, checking only one tool is chosen
+    pub fn new(age_recipients: Vec<String>, gpg_recipients: Vec<String>, age_identity: Option<PathBuf>) -> Result<Self> {
+        debug!("Building client-side encryption settings");
+        if !age_recipients.is_empty() && !gpg_recipients.is_empty() {
+            return Err(anyhow!("Backups can be encrypted for age or GPG recipients, not both"));
+        }
+        Ok(Self { age_recipients, gpg_recipients, age_identity })
+    }
+
+    /// The tool new backups are encrypted with, if any recipients are set
+    pub fn cipher(&self) -> Option<Cipher> {
+        debug!("Getting backup encryption cipher");
+        match (self.age_recipients.is_empty(), self.gpg_recipients.is_empty()) {
+            (false, _) => Some(Cipher::Age),
+            (true, false) => Some(Cipher::Gpg),
+            (true, true) => None,
+        }
+    }
+
+    /// Key to upload an encrypted backup under, `key` with the cipher's extension
+    pub fn encrypted_key(&self, key: &str) -> String {
+        debug!("Building encrypted key for {}", key);
+        match self.cipher() {
+            Some(cipher) => format!("{}{}", key, cipher.extension()),
+            None => key.to_string(),
+        }
+    }
+
+    /// Encrypt a dump for the configured recipients
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The plaintext dump
+    ///
+    /// # Returns
+    ///
+    /// The encrypted copy, removed when dropped, or `None` if no recipients are set
+    pub async fn encrypt_file(&self, input: &Path) -> Result<Option<NamedTempFile>> {
+        debug!("Encrypting {:?}", input);
+        let Some(cipher) = self.cipher() else {
+            return Ok(None);
+        };
+        let dir = input.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let encrypted = NamedTempFile::new_in(dir)?;
+        let mut cmd = match cipher {
+            Cipher::Age => {
+                let mut cmd = tokio::process::Command::new("age");
+                cmd.arg("--encrypt");
+                for recipient in &self.age_recipients {
+                    cmd.arg("--recipient").arg(recipient);
+                }
+                cmd
+            }
+            Cipher::Gpg => {
+                let mut cmd = tokio::process::Command::new("gpg");
+                // Recipients are given explicitly, so their keys need not be signed as trusted
+                cmd.args(["--batch", "--yes", "--quiet", "--trust-model", "always", "--encrypt"]);
+                for recipient in &self.gpg_recipients {
+                    cmd.arg("--recipient").arg(recipient);
+                }
+                cmd
+            }
+        };
+        cmd.arg("--output").arg(encrypted.path()).arg(input);
+        run(cmd, cipher, "encrypt").await?;
+        info!("Encrypted {:?} with {}", input, cipher);
+        Ok(Some(encrypted))
+    }
+
+    /// Decrypt a downloaded snapshot in place if it is encrypted
+    ///
+    /// The plaintext is written next to the download and then replaces it, so
+    /// it never leaves the local disk.
+    ///
+    /// # Returns
+    ///
+    /// The cipher the snapshot was encrypted with, or `None` if it was not encrypted
+    pub async fn decrypt_in_place(&self, path: &Path) -> Result<Option<Cipher>> {
+        debug!("Checking whether {:?} needs decrypting", path);
+        let Some(decrypted) = self.decrypt_file(path).await? else {
+            return Ok(None);
+        };
+        let cipher = detect_file(path)?;
+        decrypted.persist(path).map_err(|e| anyhow!("Failed to replace {} with its plaintext: {}", path.display(), e))?;
+        Ok(cipher)
+    }
+
+    /// Decrypt a snapshot into a copy next to it if it is encrypted
+    ///
+    /// # Returns
+    ///
+    /// The decrypted copy, removed when dropped, or `None` if the file is not encrypted
+    pub async fn decrypt_file(&self, path: &Path) -> Result<Option<NamedTempFile>> {
+        debug!("Decrypting {:?}", path);
+        let Some(cipher) = detect_file(path)? else {
+            return Ok(None);
+        };
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let decrypted = NamedTempFile::new_in(dir)?;
+        let mut cmd = match cipher {
+            Cipher::Age => {
+                let identity = self.age_identity.as_ref()
+                    .ok_or_else(|| anyhow!("{} is encrypted with age; pass --age-identity to decrypt it", path.display()))?;
+                let mut cmd = tokio::process::Command::new("age");
+                cmd.arg("--decrypt").arg("--identity").arg(identity);
+                cmd
+            }
+            Cipher::Gpg => {
+                let mut cmd = tokio::process::Command::new("gpg");
+                cmd.args(["--batch", "--yes", "--quiet", "--decrypt"]);
+                cmd
+            }
+        };
+        cmd.arg("--output").arg(decrypted.path()).arg(path);
+        run(cmd, cipher, "decrypt").await?;
+        info!("Decrypted {:?} with {}", path, cipher);
+        Ok(Some(decrypted))
+    }
+}
+
+/// The cipher a file is encrypted with, if any
+pub fn detect_file(path: &Path) -> Result<Option<Cipher>> {
+    debug!("Detecting encryption of {:?}", path);
+    let mut header = Vec::with_capacity(64);
+    std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(64)
+        .read_to_end(&mut header)?;
+    Ok(Cipher::detect(&header))
+}
+
+/// Key of a snapshot without the extension encryption added, e.g. `orders.dump` for `orders.dump.age`
+pub fn plain_key(key: &str) -> &str {
+    debug!("Stripping encryption extension from {}", key);
+    [Cipher::Age, Cipher::Gpg]
+        .iter()
+        .find_map(|cipher| key.strip_suffix(cipher.extension()))
+        .unwrap_or(key)
+}
+
+/// Run an age or gpg command, turning a failure into an error with its output
+async fn run(mut cmd: tokio::process::Command, cipher: Cipher, action: &str) -> Result<()> {
+    debug!("Running {} to {}", cipher, action);
+    let output = cmd
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("Failed to execute {}; install it to {} snapshots with {}", cipher, action, cipher))?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to {} with {}: {}", action, cipher, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+// This module contains alias-swap restores for the Rustored application
+// Restoring into the index an application searches shows it a partly loaded
+// index for the length of the load. In alias-swap mode the configured index
+// name is an alias instead: documents are loaded into a new index named after
+// it and the time, e.g. `orders-20240601030000`, and once the load ends and
+// the new index holds every document indexed, one `_aliases` request moves
+// the alias from the indices it pointed at to the new one. Applications see
+// the old index until that request, and the complete new one after it. The
+// old indices are kept, so the swap can be undone by moving the alias back.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde_json::{json, Value};
+use crate::sigv4::{send, SigV4Signer};
+
+/// Name of the index an alias-swap restore into `alias` loads, started at `started`
+pub fn alias_index_name(alias: &str, started: DateTime<Utc>) -> String {
+    debug!("Building alias-swap index name for {}", alias);
+    // Index names must be lowercase, so the time has no T or Z
+    format!("{}-{}", alias, started.format("%Y%m%d%H%M%S"))
+}
+
+/// Indices the alias points at, empty when it does not exist
+///
+/// Fails when an index, rather than an alias, has the name, since an alias
+/// cannot be created over it.
+pub async fn alias_indices(client: &reqwest::Client, signer: Option<&SigV4Signer>, host: &str, alias: &str) -> Result<Vec<String>> {
+    debug!("Looking up indices of alias {}", alias);
+    let response = send(client, signer, client.get(format!("{}/_alias/{}", host, alias))).await?;
+    match response.status() {
+        reqwest::StatusCode::NOT_FOUND => {
+            let exists = send(client, signer, client.head(format!("{}/{}", host, alias))).await?;
+            match exists.status().is_success() {
+                true => Err(anyhow!("{} is an index, not an alias; restore into another name, or reindex it behind an alias first", alias)),
+                false => Ok(Vec::new()),
+            }
+        }
+        status if status.is_success() => {
+            let body: Value = response.json().await?;
+            let mut indices: Vec<String> = body.as_object().map(|indices| indices.keys().cloned().collect()).unwrap_or_default();
+            indices.sort();
+            Ok(indices)
+        }
+        status => Err(anyhow!("Elasticsearch returned {} for alias {}", status, alias)),
+    }
+}
+
+/// Number of documents searchable in an index, after refreshing it
+pub async fn count_documents(client: &reqwest::Client, signer: Option<&SigV4Signer>, host: &str, index: &str) -> Result<u64> {
+    debug!("Counting documents of {}", index);
+    let refreshed = send(client, signer, client.post(format!("{}/{}/_refresh", host, index))).await?;
+    if !refreshed.status().is_success() {
+        return Err(anyhow!("Failed to refresh {}: {}", index, refreshed.status()));
+    }
+    let response = send(client, signer, client.get(format!("{}/{}/_count", host, index))).await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to count documents of {}: {}", index, response.status()));
+    }
+    let body: Value = response.json().await?;
+    body["count"].as_u64().ok_or_else(|| anyhow!("Elasticsearch returned no document count for {}", index))
+}
+
+/// Check the loaded index and move the alias to it
+///
+/// The swap is refused, leaving the alias where it was, when documents were
+/// rejected or the index does not hold every document indexed.
+///
+/// # Arguments
+///
+/// * `alias` - The alias applications search
+/// * `index` - The index the restore loaded
+/// * `previous` - Indices the alias pointed at before the restore
+/// * `indexed` - Documents the restore indexed
+/// * `rejected` - Documents the restore dead-lettered
+#[allow(clippy::too_many_arguments)]
+pub async fn swap_alias(
+    client: &reqwest::Client,
+    signer: Option<&SigV4Signer>,
+    host: &str,
+    alias: &str,
+    index: &str,
+    previous: &[String],
+    indexed: u64,
+    rejected: u64,
+) -> Result<()> {
+    debug!("Swapping alias {} from {:?} to {}", alias, previous, index);
+    if rejected > 0 {
+        return Err(anyhow!("{} documents were rejected, so alias {} still points at {}; {} is kept for inspection", rejected, alias, describe(previous), index));
+    }
+    let count = count_documents(client, signer, host, index).await?;
+    if count != indexed {
+        return Err(anyhow!(
+            "{} holds {} documents but {} were indexed, so alias {} still points at {}",
+            index, count, indexed, alias, describe(previous)
+        ));
+    }
+    let mut actions: Vec<Value> = previous.iter().map(|old| json!({ "remove": { "index": old, "alias": alias } })).collect();
+    actions.push(json!({ "add": { "index": index, "alias": alias } }));
+    let response = send(client, signer, client.post(format!("{}/_aliases", host)).json(&json!({ "actions": actions }))).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to move alias {} to {}: {} {}", alias, index, status, text));
+    }
+    info!("Alias {} now points at {} ({} documents)", alias, index, count);
+    Ok(())
+}
+
+/// The indices an alias pointed at, for messages
+fn describe(indices: &[String]) -> String {
+    debug!("Describing alias indices {:?}", indices);
+    match indices.is_empty() {
+        true => "nothing".to_string(),
+        false => indices.join(", "),
+    }
+}
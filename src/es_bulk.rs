@@ -0,0 +1,394 @@
+// This module contains the Elasticsearch bulk loader for the Rustored application
+// Elasticsearch snapshots are newline-delimited JSON, one document per line,
+// either as a search hit (`{"_id": ..., "_source": {...}}`) or as the bare
+// document. The loader streams the file in batches of `_bulk` requests with
+// several in flight at once, and turns index refreshes off for the duration
+// of the load, since refreshing a large index while it is written to slows
+// the load down many times over. A missing index is created with the
+// settings and mappings stored next to the snapshot (see `es_index`).
+// Documents Elasticsearch rejects, and lines that are not JSON, go to a
+// dead-letter file instead of failing the load.
+//
+// A cluster under too much load answers a whole request with 429, or some of
+// its items with 429 while indexing the rest. Either way what was turned away
+// is sent again after a backoff, honouring Retry-After, and documents still
+// turned away after the last attempt are dead-lettered like rejected ones.
+//
+// In alias-swap mode the documents go to a new index and the alias is moved
+// to it once the load is checked (see `es_alias`). Data streams take
+// `create` actions instead of `index` ones and keep their refresh interval
+// (see `es_data_stream`).
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+use crate::es_alias::{alias_index_name, alias_indices, swap_alias};
+use crate::es_data_stream::{ensure_data_stream, is_data_stream, is_data_stream_dump};
+use crate::es_index::IndexDefinition;
+use crate::mapping::RestoreMapping;
+use crate::sigv4::{send, SigV4Signer};
+use crate::ui::models::BulkConfig;
+
+/// A document Elasticsearch rejected, with the reason
+type Rejected = (Value, String);
+
+/// Times a document is sent while the cluster turns it away as too busy
+pub const MAX_BULK_ATTEMPTS: u32 = 5;
+
+/// Pause before resending when the cluster gives no Retry-After
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest pause between resends
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Whether a request or item status means the cluster is too busy and it can be sent again
+fn is_busy(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503 | 504)
+}
+
+/// Whether a document is sent with its own `_id`, so sending it again overwrites rather than duplicates it
+fn has_id(document: &Value) -> bool {
+    document.get("_source").is_some() && document.get("_id").is_some()
+}
+
+/// How long the cluster asks a client to wait before sending again
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_BACKOFF))
+}
+
+/// The reason Elasticsearch gives for an item error
+fn error_reason(error: &Value) -> String {
+    match (error["type"].as_str(), error["reason"].as_str()) {
+        (Some(kind), Some(reason)) => format!("{}: {}", kind, reason),
+        _ => error.to_string(),
+    }
+}
+
+/// Turn snapshot documents into a `_bulk` request body writing them into `index`
+///
+/// Search hits keep their `_id`; bare documents get one generated by
+/// Elasticsearch. `op_type` is the bulk action, `index`, or `create` for a
+/// data stream.
+pub fn bulk_body(index: &str, documents: &[Value], op_type: &str) -> String {
+    debug!("Building bulk body for {} documents", documents.len());
+    let mut body = String::new();
+    for document in documents {
+        let (id, source) = match document.get("_source") {
+            Some(source) => (document.get("_id").cloned(), source),
+            None => (None, document),
+        };
+        let action = match id {
+            Some(id) => json!({ op_type: { "_index": index, "_id": id } }),
+            None => json!({ op_type: { "_index": index } }),
+        };
+        body.push_str(&action.to_string());
+        body.push('\n');
+        body.push_str(&source.to_string());
+        body.push('\n');
+    }
+    body
+}
+
+/// Send one batch of documents with `_bulk` requests
+///
+/// Documents the cluster turns away as too busy, in a 429 answer to the
+/// whole request or in the items of a partial failure, are sent again until
+/// `MAX_BULK_ATTEMPTS` is reached. A 502, 503 or 504 answer to the whole
+/// request may come after some of it was indexed, so only documents with an
+/// `_id` are sent again; the others are rejected rather than risk indexing
+/// them twice.
+///
+/// # Returns
+///
+/// The number of documents indexed and the documents rejected, with the reasons
+async fn send_bulk(
+    client: reqwest::Client,
+    signer: Option<SigV4Signer>,
+    url: String,
+    index: String,
+    op_type: &'static str,
+    documents: Vec<Value>,
+) -> Result<(u64, Vec<Rejected>)> {
+    debug!("Sending bulk request with {} documents", documents.len());
+    let mut pending = documents;
+    let mut rejected = Vec::new();
+    let mut indexed = 0u64;
+    let mut delay = BASE_BACKOFF;
+    let mut busy_reason = String::new();
+    for attempt in 1..=MAX_BULK_ATTEMPTS {
+        let request = client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(bulk_body(&index, &pending, op_type));
+        let response = send(&client, signer.as_ref(), request)
+            .await
+            .map_err(|e| anyhow!("Bulk request to {} failed: {}", url, e))?;
+        let status = response.status();
+        let mut pause = delay;
+        if status.as_u16() == 429 {
+            pause = retry_after(&response).unwrap_or(delay);
+            busy_reason = format!("cluster answered {}", status);
+        } else if is_busy(status.as_u16()) {
+            pause = retry_after(&response).unwrap_or(delay);
+            busy_reason = format!("cluster answered {}", status);
+            let (retry, unsafe_to_resend): (Vec<Value>, Vec<Value>) = std::mem::take(&mut pending).into_iter().partition(has_id);
+            let reason = format!("cluster answered {} and the document has no _id, so it may already be indexed", status);
+            rejected.extend(unsafe_to_resend.into_iter().map(|document| (document, reason.clone())));
+            if retry.is_empty() {
+                return Ok((indexed, rejected));
+            }
+            pending = retry;
+        } else if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Elasticsearch returned {} for a bulk request: {}", status, text));
+        } else {
+            let result: Value = response.json().await?;
+            if !result["errors"].as_bool().unwrap_or(false) {
+                return Ok((indexed + pending.len() as u64, rejected));
+            }
+
+            // Items are reported in the order the documents were sent, keyed by their action
+            let items = result["items"].as_array().cloned().unwrap_or_default();
+            let (sent, refused) = (pending.len(), rejected.len());
+            let mut retry = Vec::new();
+            for (document, item) in std::mem::take(&mut pending).into_iter().zip(items) {
+                let outcome = item.as_object().and_then(|actions| actions.values().next()).cloned().unwrap_or_default();
+                let error = &outcome["error"];
+                if error.is_null() {
+                    continue;
+                }
+                match outcome["status"].as_u64().is_some_and(|status| is_busy(status as u16)) {
+                    true => {
+                        busy_reason = error_reason(error);
+                        retry.push(document);
+                    }
+                    false => rejected.push((document, error_reason(error))),
+                }
+            }
+            indexed += (sent - retry.len() - (rejected.len() - refused)) as u64;
+            if retry.is_empty() {
+                return Ok((indexed, rejected));
+            }
+            pending = retry;
+        }
+        debug!("{} documents turned away on attempt {} ({}), waiting {:?}", pending.len(), attempt, busy_reason, pause);
+        if attempt < MAX_BULK_ATTEMPTS {
+            tokio::time::sleep(pause).await;
+            delay = (delay * 2).min(MAX_BACKOFF);
+        }
+    }
+    warn!("Giving up on {} documents after {} attempts: {}", pending.len(), MAX_BULK_ATTEMPTS, busy_reason);
+    let reason = format!("still turned away after {} attempts: {}", MAX_BULK_ATTEMPTS, busy_reason);
+    rejected.extend(pending.into_iter().map(|document| (document, reason.clone())));
+    Ok((indexed, rejected))
+}
+
+/// Create the index with `definition` unless it exists
+///
+/// An existing index keeps its own settings and mappings.
+///
+/// # Returns
+///
+/// Whether the index, or a data stream or alias of that name, existed
+async fn ensure_index(client: &reqwest::Client, signer: Option<&SigV4Signer>, index_url: &str, definition: &IndexDefinition) -> Result<bool> {
+    debug!("Ensuring index {} exists", index_url);
+    let response = send(client, signer, client.head(index_url)).await?;
+    if response.status().is_success() {
+        if !definition.is_empty() {
+            warn!("Index {} already exists, so its settings and mappings are kept", index_url);
+        }
+        return Ok(true);
+    }
+    if response.status() != reqwest::StatusCode::NOT_FOUND {
+        return Err(anyhow!("Elasticsearch returned {} for {}", response.status(), index_url));
+    }
+    info!("Creating index {}", index_url);
+    let request = match definition.is_empty() {
+        true => client.put(index_url),
+        false => client.put(index_url).json(&definition.create_body()),
+    };
+    let response = send(client, signer, request).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to create index {}: {} {}", index_url, status, text));
+    }
+    Ok(false)
+}
+
+/// Set the index's refresh interval, `None` putting back the cluster default
+async fn set_refresh_interval(client: &reqwest::Client, signer: Option<&SigV4Signer>, index_url: &str, interval: Option<&Value>) -> Result<()> {
+    debug!("Setting refresh interval of {} to {:?}", index_url, interval);
+    let body = json!({ "index": { "refresh_interval": interval.cloned().unwrap_or(Value::Null) } });
+    let response = send(client, signer, client.put(format!("{}/_settings", index_url)).json(&body)).await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to set refresh interval of {}: {}", index_url, response.status()));
+    }
+    Ok(())
+}
+
+/// Turn refreshes off, returning the refresh interval set on the index before, if any
+async fn disable_refresh(client: &reqwest::Client, signer: Option<&SigV4Signer>, index_url: &str, index: &str) -> Result<Option<Value>> {
+    debug!("Disabling refreshes of {}", index_url);
+    let response = send(client, signer, client.get(format!("{}/_settings/index.refresh_interval", index_url))).await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to read settings of {}: {}", index_url, response.status()));
+    }
+    let settings: Value = response.json().await?;
+    let previous = settings[index]["settings"]["index"]["refresh_interval"].clone();
+    set_refresh_interval(client, signer, index_url, Some(&json!("-1"))).await?;
+    Ok((!previous.is_null()).then_some(previous))
+}
+
+/// Stream an NDJSON snapshot into an index with concurrent `_bulk` requests
+///
+/// The index is created if it does not exist, with the settings and mappings
+/// of the index definition stored next to the snapshot, if any, and the
+/// shard and replica counts of `bulk`. A data-stream dump creates a data
+/// stream instead, and an existing data stream is written to with `create`. When refreshes are disabled,
+/// the previous refresh interval is put back and the index refreshed once
+/// the load ends, even if it failed. Rejected documents are written to
+/// `dead_letters` and do not fail the load.
+///
+/// # Arguments
+///
+/// * `client` - HTTP client, carrying the target timeouts
+/// * `signer` - Signs every request with SigV4, for Amazon OpenSearch Service
+/// * `host` - Elasticsearch or OpenSearch URL
+/// * `index` - Index to load the documents into, or the alias to move in alias-swap mode
+/// * `path` - The snapshot file
+/// * `bulk` - Batch size, concurrency, refresh handling and shard and replica counts
+/// * `mapping` - Field renames applied to each document before it is sent
+/// * `progress` - Called with the share of the file loaded
+/// * `dead_letters` - Receives the documents that could not be indexed
+///
+/// # Returns
+///
+/// The number of documents indexed
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_restore(
+    client: &reqwest::Client,
+    signer: Option<&SigV4Signer>,
+    host: &str,
+    index: &str,
+    path: &Path,
+    bulk: &BulkConfig,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Bulk restoring {:?} into {} with {:?}", path, index, bulk);
+    let definition = IndexDefinition::read_sidecar(path)?.unwrap_or_default().with_overrides(bulk.shards, bulk.replicas);
+    let mut snapshot = NdjsonBatches::open(path, bulk.batch_size).await?;
+    let host = host.trim_end_matches('/');
+    let dumped_stream = definition.is_data_stream() || is_data_stream_dump(snapshot.path())?;
+    if dumped_stream && bulk.alias_swap {
+        return Err(anyhow!("{} was dumped from a data stream, which cannot be restored behind an alias", index));
+    }
+    let (alias, aliased) = match bulk.alias_swap {
+        true => (Some(index), alias_indices(client, signer, host, index).await?),
+        false => (None, Vec::new()),
+    };
+    let swapped = alias.map(|alias| alias_index_name(alias, Utc::now()));
+    let index = swapped.as_deref().unwrap_or(index);
+    if let Some(index) = &swapped {
+        // Rejected documents are retried into the new index, not through the alias
+        dead_letters.retarget(index);
+    }
+    let index_url = format!("{}/{}", host, index);
+    let stream = match dumped_stream {
+        true => {
+            ensure_data_stream(client, signer, host, index, &definition, bulk).await?;
+            true
+        }
+        false => ensure_index(client, signer, &index_url, &definition).await? && is_data_stream(client, signer, host, index).await?,
+    };
+    let op_type = match stream {
+        true => "create",
+        false => "index",
+    };
+
+    // The backing indices of a data stream each have their own refresh interval
+    let previous = if bulk.disable_refresh && !stream {
+        Some(disable_refresh(client, signer, &index_url, index).await?)
+    } else {
+        None
+    };
+    let result = load(client, signer, host, index, op_type, &mut snapshot, bulk, mapping, progress, dead_letters).await;
+
+    if let Some(previous) = previous {
+        if let Err(e) = set_refresh_interval(client, signer, &index_url, previous.as_ref()).await {
+            warn!("Failed to put back the refresh interval of {}: {}", index, e);
+        }
+        if let Err(e) = send(client, signer, client.post(format!("{}/_refresh", index_url))).await {
+            warn!("Failed to refresh {}: {}", index, e);
+        }
+    }
+    let indexed = result?;
+    info!("Indexed {} documents into {}, {} rejected", indexed, index, dead_letters.count());
+    if let Some(alias) = alias {
+        swap_alias(client, signer, host, alias, index, &aliased, indexed, dead_letters.count()).await?;
+    }
+    Ok(indexed)
+}
+
+/// Read the snapshot in batches, keeping up to `bulk.concurrency` requests in flight
+#[allow(clippy::too_many_arguments)]
+async fn load(
+    client: &reqwest::Client,
+    signer: Option<&SigV4Signer>,
+    host: &str,
+    index: &str,
+    op_type: &'static str,
+    snapshot: &mut NdjsonBatches,
+    bulk: &BulkConfig,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Loading {:?} into {} with {} actions", snapshot.path(), index, op_type);
+    let total = snapshot.size();
+    let url = format!("{}/_bulk", host);
+
+    let mut in_flight: JoinSet<Result<(u64, Vec<Rejected>, u64)>> = JoinSet::new();
+    let (mut indexed, mut loaded_bytes, mut sent_bytes) = (0u64, 0u64, 0u64);
+    // Only this task writes dead letters, so the file never interleaves
+    let mut record = |result: Result<Result<(u64, Vec<Rejected>, u64)>, tokio::task::JoinError>, dead_letters: &mut DeadLetterWriter| -> Result<()> {
+        let (documents, rejected, bytes) = result??;
+        for (document, reason) in rejected {
+            dead_letters.write(document, &reason)?;
+        }
+        indexed += documents;
+        loaded_bytes += bytes;
+        if let Some(progress) = progress {
+            progress(loaded_bytes as f32 / total as f32);
+        }
+        Ok(())
+    };
+
+    while let Some(batch) = snapshot.next_batch(dead_letters).await? {
+        while in_flight.len() >= bulk.concurrency {
+            if let Some(result) = in_flight.join_next().await {
+                record(result, dead_letters)?;
+            }
+        }
+        let documents = batch.into_iter().map(|document| mapping.map_document(document)).collect();
+        let bytes = snapshot.read_bytes() - sent_bytes;
+        sent_bytes = snapshot.read_bytes();
+        let (client, signer, url, index) = (client.clone(), signer.cloned(), url.clone(), index.to_string());
+        in_flight.spawn(async move {
+            let (indexed, rejected) = send_bulk(client, signer, url, index, op_type, documents).await?;
+            Ok((indexed, rejected, bytes))
+        });
+    }
+    while let Some(result) = in_flight.join_next().await {
+        record(result, dead_letters)?;
+    }
+    Ok(indexed)
+}
@@ -0,0 +1,129 @@
+// This module contains Elasticsearch data streams for the Rustored application
+// A data stream is one name in front of hidden, append-only backing indices
+// named `.ds-<stream>-<date>-<generation>`. It accepts `create` bulk actions
+// only, so indexing a dump into it the usual way fails on every document, and
+// it can only be created when an index template with a `data_stream` section
+// matches its name. A snapshot is restored as a data stream when the index
+// definition stored next to it holds such a template, or when its documents
+// are search hits read from backing indices. The stream is then created from
+// the template before the load, and a stream that already exists is detected
+// and written to with `create`.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::io::BufRead;
+use std::path::Path;
+use crate::es_index::IndexDefinition;
+use crate::sigv4::{send, SigV4Signer};
+use crate::ui::models::BulkConfig;
+
+/// Prefix of the backing indices of data streams
+pub const BACKING_INDEX_PREFIX: &str = ".ds-";
+
+/// Whether `name` is an existing data stream
+pub async fn is_data_stream(client: &reqwest::Client, signer: Option<&SigV4Signer>, host: &str, name: &str) -> Result<bool> {
+    debug!("Checking if {} is a data stream", name);
+    let response = send(client, signer, client.get(format!("{}/_data_stream/{}", host, name))).await?;
+    if !response.status().is_success() {
+        // 404 for indices and missing names, and clusters without data streams
+        return Ok(false);
+    }
+    let body: Value = response.json().await?;
+    Ok(body["data_streams"].as_array().is_some_and(|streams| !streams.is_empty()))
+}
+
+/// Whether the snapshot was dumped from a data stream, judged by its first document
+///
+/// Search hits read from a data stream carry the backing index they came from
+/// in `_index`; bare documents say nothing either way.
+pub fn is_data_stream_dump(path: &Path) -> Result<bool> {
+    debug!("Checking if {:?} was dumped from a data stream", path);
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open snapshot {:?}", path))?;
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let document: Value = serde_json::from_str(&line).unwrap_or(Value::Null);
+        return Ok(document["_index"].as_str().is_some_and(|index| index.starts_with(BACKING_INDEX_PREFIX)));
+    }
+    Ok(false)
+}
+
+/// Body of the `PUT /_index_template/<name>` request creating the template of stream `name`
+///
+/// The template is narrowed to the stream's own name, so restoring under
+/// another name than the dump's still matches it. Its settings and mappings
+/// come from the template, or from the definition's index settings when the
+/// template has none, with the shard and replica counts of `bulk`.
+pub fn template_body(definition: &IndexDefinition, name: &str, bulk: &BulkConfig) -> Value {
+    debug!("Building index template of data stream {}", name);
+    let mut template = definition.template.clone().filter(Value::is_object).unwrap_or_else(|| json!({}));
+    let inner = template.get("template").and_then(Value::as_object).cloned().unwrap_or_default();
+    let index = IndexDefinition {
+        settings: inner.get("settings").and_then(Value::as_object).cloned().unwrap_or_else(|| definition.settings.clone()),
+        mappings: inner.get("mappings").cloned().or_else(|| definition.mappings.clone()),
+        template: None,
+    }
+    .with_overrides(bulk.shards, bulk.replicas);
+    let mut merged: Map<String, Value> = inner;
+    if let Value::Object(body) = index.create_body() {
+        merged.extend(body);
+    }
+    template["index_patterns"] = json!([name]);
+    template["template"] = Value::Object(merged);
+    if template.get("data_stream").is_none_or(|stream| !stream.is_object()) {
+        template["data_stream"] = json!({});
+    }
+    template
+}
+
+/// Create data stream `name` unless it exists
+///
+/// The index template stored with the snapshot is put first, unless a
+/// template of that name exists. Without one the stream is created from
+/// whichever template of the cluster matches its name, e.g. the built-in
+/// `logs-*-*` one.
+pub async fn ensure_data_stream(
+    client: &reqwest::Client,
+    signer: Option<&SigV4Signer>,
+    host: &str,
+    name: &str,
+    definition: &IndexDefinition,
+    bulk: &BulkConfig,
+) -> Result<()> {
+    debug!("Ensuring data stream {} exists", name);
+    if is_data_stream(client, signer, host, name).await? {
+        if definition.is_data_stream() {
+            warn!("Data stream {} already exists, so its index template is kept", name);
+        }
+        return Ok(());
+    }
+    if definition.is_data_stream() {
+        let template_url = format!("{}/_index_template/{}", host, name);
+        let exists = send(client, signer, client.head(&template_url)).await?;
+        if exists.status().is_success() {
+            warn!("Index template {} already exists, so it is used instead of the stored one", name);
+        } else {
+            info!("Creating index template {}", name);
+            let response = send(client, signer, client.put(&template_url).json(&template_body(definition, name, bulk))).await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                return Err(anyhow!("Failed to create index template {}: {} {}", name, status, text));
+            }
+        }
+    }
+    info!("Creating data stream {}", name);
+    let response = send(client, signer, client.put(format!("{}/_data_stream/{}", host, name))).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "Failed to create data stream {}: {} {}; store its index template next to the snapshot, or restore into a name a template of the cluster matches",
+            name, status, text
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+// This module contains Elasticsearch index definitions for the Rustored application
+// An NDJSON export holds documents only, so an index created from it gets
+// dynamic mappings and the cluster's default settings. The settings and
+// mappings of the exported index can be stored next to its snapshot as
+// `<snapshot key>.index.json`, in the form `GET /<index>` returns them. The
+// sidecar is downloaded with the snapshot, and a missing index is created
+// with it before any document is loaded. Settings the cluster sets on an
+// index itself, such as its UUID and creation date, are dropped, and the
+// shard and replica counts can be overridden at restore time.
+//
+// A data stream is stored the same way with its index template, in the form
+// `GET /_index_template/<name>` returns it (see `es_data_stream`).
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use crate::storage::SnapshotStore;
+
+/// Suffix added to a snapshot key, or a local snapshot path, to name its index definition
+pub const INDEX_DEFINITION_SUFFIX: &str = ".index.json";
+
+/// Settings the cluster sets on an index itself and refuses in a create request
+const READ_ONLY_SETTINGS: &[&str] = &["uuid", "creation_date", "creation_date_string", "provided_name", "version", "history_uuid", "resize", "verified_before_close"];
+
+/// Settings and mappings an index is created with
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexDefinition {
+    /// Index settings, nested under `index` as `GET /<index>` returns them, or flat
+    pub settings: Map<String, Value>,
+    /// Field mappings, `{"properties": {...}}`
+    pub mappings: Option<Value>,
+    /// Index template of a data stream, `{"index_patterns": [...], "data_stream": {}, ...}`
+    pub template: Option<Value>,
+}
+
+impl IndexDefinition {
+    /// Parse a definition from `{"settings": ..., "mappings": ..., "index_template": ...}`
+    ///
+    /// The `GET /<index>` answer, which wraps the definition in an object
+    /// keyed by the index name, is taken as well when it names one index, and
+    /// so is the `GET /_index_template/<name>` answer when it holds one template.
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing index definition ({} bytes)", bytes.len());
+        let value: Value = serde_json::from_slice(bytes).context("Index definition is not JSON")?;
+        let object = value.as_object().ok_or_else(|| anyhow!("Index definition is not a JSON object"))?;
+        if let Some(templates) = object.get("index_templates").and_then(Value::as_array) {
+            return match templates.as_slice() {
+                [template] => Ok(Self { template: template.get("index_template").cloned(), ..Self::default() }),
+                _ => Err(anyhow!("Index definition holds {} index templates instead of one", templates.len())),
+            };
+        }
+        let named = |inner: &Map<String, Value>| ["settings", "mappings", "index_template"].iter().any(|key| inner.contains_key(*key));
+        let definition = match object.values().next() {
+            Some(Value::Object(inner)) if object.len() == 1 && !named(object) => inner,
+            _ if named(object) => object,
+            _ => return Err(anyhow!("Index definition has neither settings, mappings nor an index template")),
+        };
+        let template = match definition.get("index_template") {
+            Some(Value::Object(template)) => Some(Value::Object(template.clone())),
+            None | Some(Value::Null) => None,
+            Some(_) => return Err(anyhow!("Index definition index template is not an object")),
+        };
+        let settings = match definition.get("settings") {
+            Some(Value::Object(settings)) => settings.clone(),
+            None | Some(Value::Null) => Map::new(),
+            Some(_) => return Err(anyhow!("Index definition settings are not an object")),
+        };
+        Ok(Self { settings, mappings: definition.get("mappings").filter(|mappings| !mappings.is_null()).cloned(), template })
+    }
+
+    /// Read the definition stored next to a local snapshot, if there is one
+    pub fn read_sidecar(snapshot: &Path) -> Result<Option<Self>> {
+        debug!("Looking for an index definition next to {:?}", snapshot);
+        let path = sidecar_path(snapshot);
+        match std::fs::read(&path) {
+            Ok(bytes) => Self::from_json(&bytes).with_context(|| format!("Invalid index definition {:?}", path)).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow!("Failed to read index definition {:?}: {}", path, e)),
+        }
+    }
+
+    /// Replace the shard and replica counts, where given
+    pub fn with_overrides(mut self, shards: Option<u32>, replicas: Option<u32>) -> Self {
+        debug!("Overriding index definition shards={:?}, replicas={:?}", shards, replicas);
+        for (name, count) in [("number_of_shards", shards), ("number_of_replicas", replicas)] {
+            let Some(count) = count else { continue };
+            self.settings.remove(&format!("index.{}", name));
+            let index = self.settings.entry("index").or_insert_with(|| json!({}));
+            if let Some(index) = index.as_object_mut() {
+                index.insert(name.to_string(), json!(count.to_string()));
+            }
+        }
+        self
+    }
+
+    /// Whether creating an index with the definition differs from creating it bare
+    pub fn is_empty(&self) -> bool {
+        debug!("Checking if index definition is empty");
+        self.settings.is_empty() && self.mappings.is_none() && self.template.is_none()
+    }
+
+    /// Whether the definition is of a data stream rather than an index
+    pub fn is_data_stream(&self) -> bool {
+        debug!("Checking if index definition is of a data stream");
+        self.template.as_ref().is_some_and(|template| template.get("data_stream").is_some())
+    }
+
+    /// Body of the `PUT /<index>` request creating an index with the definition
+    pub fn create_body(&self) -> Value {
+        debug!("Building index creation body");
+        let mut settings = self.settings.clone();
+        for name in READ_ONLY_SETTINGS {
+            settings.remove(&format!("index.{}", name));
+            if let Some(index) = settings.get_mut("index").and_then(Value::as_object_mut) {
+                index.remove(*name);
+            }
+        }
+        let mut body = json!({ "settings": settings });
+        if let Some(mappings) = &self.mappings {
+            body["mappings"] = mappings.clone();
+        }
+        body
+    }
+}
+
+/// Path of the index definition stored next to a local snapshot
+pub fn sidecar_path(snapshot: &Path) -> PathBuf {
+    debug!("Building index definition path of {:?}", snapshot);
+    let mut path = snapshot.as_os_str().to_owned();
+    path.push(INDEX_DEFINITION_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Key of the index definition of the snapshot stored under `key`
+pub fn index_definition_key(key: &str) -> String {
+    debug!("Building index definition key of {}", key);
+    format!("{}{}", key, INDEX_DEFINITION_SUFFIX)
+}
+
+/// Whether an object key names an index definition rather than a snapshot
+pub fn is_index_definition_key(key: &str) -> bool {
+    debug!("Checking if {} is an index definition", key);
+    key.ends_with(INDEX_DEFINITION_SUFFIX)
+}
+
+/// Download the index definition of a snapshot next to its local copy, if it has one
+///
+/// A definition left by an earlier download of another snapshot to the same
+/// path is removed, so it is never applied to the wrong index. Failing to
+/// read the definition only logs a warning, since the documents can still be
+/// restored without it.
+///
+/// # Returns
+///
+/// Whether a definition was downloaded
+pub async fn fetch_index_definition(store: &dyn SnapshotStore, key: &str, snapshot: &Path) -> Result<bool> {
+    debug!("Fetching index definition of {}", key);
+    let path = sidecar_path(snapshot);
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    match store.read_object(&index_definition_key(key)).await {
+        Ok(Some(bytes)) => {
+            info!("Downloaded index definition of {}", key);
+            std::fs::write(&path, bytes)?;
+            Ok(true)
+        }
+        Ok(None) => Ok(false),
+        Err(e) => {
+            warn!("Could not read index definition of {}: {}", key, e);
+            Ok(false)
+        }
+    }
+}
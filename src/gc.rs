@@ -0,0 +1,230 @@
+// This module contains the cleanup of throwaway restores for the Rustored application
+// A restore without a chosen target goes into a new `<word>-restored` database,
+// which is tagged with a database comment recording when it was created.
+// `rustored gc` and `[[gc]]` entries in the presets file drop tagged databases
+// older than a given age, so a staging server does not fill up with restores
+// nobody remembers. A database is only dropped when it still carries the tag
+// and the `-restored` name, so one promoted under a production name is safe.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use croner::Cron;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::cancel::CancellationToken;
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::table::{Align, Cell, Table};
+use crate::ui::models::PostgresConfig;
+
+/// Start of the comment that marks a database as a throwaway restore
+pub const THROWAWAY_TAG: &str = "rustored:throwaway";
+
+/// Suffix of the names rustored gives the databases it restores into
+pub const RESTORED_SUFFIX: &str = "-restored";
+
+/// Age past which `gc` drops throwaway restores unless told otherwise
+pub const DEFAULT_GC_AGE: &str = "7d";
+
+/// Parse an age such as `30m`, `12h`, `7d` or `2w`; a bare number is days
+pub fn parse_age(value: &str) -> Result<Duration> {
+    debug!("Parsing age: {}", value);
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "d"),
+    };
+    let number: i64 = number.parse().map_err(|_| anyhow!("{:?} is not an age, e.g. 12h, 7d or 2w", value))?;
+    let age = match unit {
+        "m" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        _ => return Err(anyhow!("Unknown unit in {:?}, use m, h, d or w, e.g. 7d", value)),
+    };
+    let age = age.ok_or_else(|| anyhow!("{} is too long an age", value))?;
+    if age <= Duration::zero() {
+        return Err(anyhow!("An age must be more than zero, not {}", value));
+    }
+    Ok(age)
+}
+
+/// Comment marking a database as a throwaway restore created at `created`
+pub fn throwaway_comment(created: DateTime<Utc>) -> String {
+    debug!("Building throwaway comment for {}", created);
+    format!("{} created={}", THROWAWAY_TAG, created.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// When a throwaway restore was created, or `None` if the comment is not a throwaway tag
+pub fn parse_throwaway_comment(comment: &str) -> Option<DateTime<Utc>> {
+    debug!("Parsing database comment: {}", comment);
+    // Restores add their provenance after the creation time
+    crate::provenance::Provenance::from_comment(comment)
+        .filter(|provenance| provenance.throwaway)
+        .map(|provenance| provenance.restored_at)
+}
+
+/// Tag a database just restored into as a throwaway
+///
+/// Logs rather than fails, since the database itself was created.
+pub async fn tag_throwaway(client: &tokio_postgres::Client, name: &str, created: DateTime<Utc>) {
+    debug!("Tagging {} as a throwaway restore", name);
+    let statement = format!("COMMENT ON DATABASE \"{}\" IS '{}'", name, throwaway_comment(created));
+    if let Err(e) = client.batch_execute(&statement).await {
+        warn!("Failed to tag {} as a throwaway restore, gc will not drop it: {}", name, e);
+    }
+}
+
+/// A tagged throwaway restore on the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throwaway {
+    pub name: String,
+    pub created: DateTime<Utc>,
+}
+
+/// Throwaway restores past their age, and the ones kept for now
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GcPlan {
+    pub expired: Vec<Throwaway>,
+    pub kept: Vec<Throwaway>,
+}
+
+impl GcPlan {
+    /// Split throwaway restores into those created more than `older_than` before `now` and the rest
+    pub fn new(throwaways: Vec<Throwaway>, older_than: Duration, now: DateTime<Utc>) -> Self {
+        debug!("Planning gc of {} throwaway restores older than {}", throwaways.len(), older_than);
+        let (expired, kept) = throwaways.into_iter().partition(|throwaway| now - throwaway.created > older_than);
+        Self { expired, kept }
+    }
+
+    /// The plan as a table for `gc`
+    pub fn table(&self) -> Table {
+        debug!("Tabulating gc plan");
+        let mut table = Table::new(&[("ACTION", Align::Left), ("DATABASE", Align::Left), ("CREATED", Align::Left)]);
+        let rows = self.expired.iter().map(|throwaway| (Cell::colored("drop", crossterm::style::Color::Red), throwaway))
+            .chain(self.kept.iter().map(|throwaway| (Cell::colored("keep", crossterm::style::Color::Green), throwaway)));
+        for (action, throwaway) in rows {
+            table.row(vec![action, throwaway.name.as_str().into(), throwaway.created.format("%Y-%m-%d %H:%M UTC").to_string().into()]);
+        }
+        table
+    }
+}
+
+/// The tagged throwaway restores on the server, oldest first
+pub async fn list_throwaways(client: &tokio_postgres::Client) -> Result<Vec<Throwaway>> {
+    debug!("Listing throwaway restores");
+    let rows = client
+        .query("SELECT datname, shobj_description(oid, 'pg_database') FROM pg_database WHERE NOT datistemplate", &[])
+        .await?;
+    let mut throwaways: Vec<Throwaway> = rows
+        .iter()
+        .filter_map(|row| {
+            let name: String = row.get(0);
+            let comment: Option<String> = row.get(1);
+            let created = parse_throwaway_comment(comment.as_deref()?)?;
+            name.ends_with(RESTORED_SUFFIX).then_some(Throwaway { name, created })
+        })
+        .collect();
+    throwaways.sort_by_key(|throwaway| throwaway.created);
+    Ok(throwaways)
+}
+
+/// Find the throwaway restores on the server older than `older_than`
+pub async fn plan(pg_config: &PostgresConfig, older_than: Duration, now: DateTime<Utc>) -> Result<GcPlan> {
+    debug!("Planning gc on {:?}", pg_config.host);
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    Ok(GcPlan::new(list_throwaways(&client).await?, older_than, now))
+}
+
+/// Drop the expired throwaway restores of a plan
+///
+/// The configured database is never dropped, whatever its tag says.
+///
+/// # Returns
+///
+/// The names of the dropped databases
+pub async fn drop_expired(pg_config: &PostgresConfig, plan: &GcPlan) -> Result<Vec<String>> {
+    debug!("Dropping {} expired throwaway restores", plan.expired.len());
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    let mut dropped = Vec::new();
+    for throwaway in &plan.expired {
+        if pg_config.db_name.as_deref() == Some(throwaway.name.as_str()) {
+            warn!("Not dropping {}, it is the configured database", throwaway.name);
+            continue;
+        }
+        crate::postgres::drop_database_with_force(&client, &throwaway.name).await
+            .map_err(|e| anyhow!("Failed to drop throwaway restore {}: {}", throwaway.name, e))?;
+        info!("Dropped throwaway restore {} created {}", throwaway.name, throwaway.created);
+        dropped.push(throwaway.name.clone());
+    }
+    Ok(dropped)
+}
+
+/// Throwaway restores dropped on a cron schedule by `schedule`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GcSchedule {
+    /// Five-field cron expression, evaluated in UTC, e.g. `0 4 * * *`
+    pub cron: String,
+    /// Age past which throwaway restores are dropped, e.g. `7d`
+    #[serde(default = "default_gc_age")]
+    pub older_than: String,
+}
+
+fn default_gc_age() -> String {
+    DEFAULT_GC_AGE.to_string()
+}
+
+/// Schema of a `[[gc]]` table in the presets file
+pub(crate) const GC_SCHEMA: TableSchema = TableSchema {
+    name: "[[gc]]",
+    fields: &[
+        Field::required("cron", Kind::String),
+        Field::optional("older_than", Kind::String),
+    ],
+    requires: &[],
+    unique: None,
+};
+
+impl GcSchedule {
+    /// First time the cleanup is due strictly after `after`
+    pub fn next_run(&self, after: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        debug!("Finding next gc run ({}) after {}", self.cron, after);
+        Cron::new(&self.cron)
+            .parse()
+            .and_then(|cron| cron.find_next_occurrence(&after, false))
+            .map_err(|e| anyhow!("Invalid cron expression '{}' for gc: {}", self.cron, e))
+    }
+}
+
+/// Drop expired throwaway restores on each schedule until `cancel` is cancelled
+///
+/// A failed cleanup is logged and retried at the next run, so an unreachable
+/// server never stops the daemon.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `schedules` - The `[[gc]]` entries of the presets file
+/// * `cancel` - Stops the loop
+pub async fn run_gc_schedule(pg_config: PostgresConfig, schedules: Vec<GcSchedule>, cancel: CancellationToken) -> Result<()> {
+    debug!("Starting gc schedule with {} entries", schedules.len());
+    let ages = schedules.iter().map(|schedule| parse_age(&schedule.older_than)).collect::<Result<Vec<_>>>()?;
+    let mut next: Vec<DateTime<Utc>> = schedules.iter().map(|schedule| schedule.next_run(Utc::now())).collect::<Result<_>>()?;
+    while let Some((index, wake)) = next.iter().copied().enumerate().min_by_key(|(_, wake)| *wake) {
+        let delay = (wake - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        let now = Utc::now();
+        next[index] = schedules[index].next_run(now)?;
+        let dropped = match plan(&pg_config, ages[index], now).await {
+            Ok(plan) => drop_expired(&pg_config, &plan).await,
+            Err(e) => Err(e),
+        };
+        match dropped {
+            Ok(dropped) => info!("Scheduled gc dropped {} throwaway restores older than {}", dropped.len(), schedules[index].older_than),
+            Err(e) => warn!("Scheduled gc failed: {}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,182 @@
+// This module contains cluster globals backups for the Rustored application
+// Roles, their memberships and passwords, and tablespaces belong to the
+// cluster rather than to a database, so a per-database dump restored on a new
+// server fails on every missing owner and grant. `pg_dumpall --globals-only`
+// captures them as plain SQL, uploaded as a `globals-<timestamp>.sql`
+// snapshot, and applied with psql before a database dump is restored.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use std::path::Path;
+use std::process::Stdio;
+use crate::encryption::ClientEncryption;
+use crate::manifest::Manifest;
+use crate::storage::SnapshotStore;
+use crate::ui::models::{BackupMetadata, PostgresConfig};
+
+/// Name globals snapshots are keyed and recorded under, in place of a database
+pub const GLOBALS_NAME: &str = "globals";
+
+/// Build the key of a globals snapshot, e.g. `postgres/globals-20250101T120000Z.sql`
+pub fn globals_key(prefix: &str, timestamp: DateTime<Utc>) -> String {
+    debug!("Building globals snapshot key under {}", prefix);
+    let file_name = format!("{}-{}.sql", GLOBALS_NAME, timestamp.format("%Y%m%dT%H%M%SZ"));
+    crate::backup::join_prefix(prefix, file_name)
+}
+
+/// Whether a snapshot key names a globals snapshot, encrypted or not
+pub fn is_globals_key(key: &str) -> bool {
+    debug!("Checking whether {} is a globals snapshot", key);
+    let key = crate::encryption::plain_key(key);
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    file_name.starts_with(&format!("{}-", GLOBALS_NAME)) && file_name.ends_with(".sql")
+}
+
+/// Find the globals snapshot to apply before restoring a database snapshot
+///
+/// The newest globals snapshot taken no later than the database snapshot
+/// matches the roles its grants refer to. When every globals snapshot is
+/// newer, the oldest of them is the closest match.
+pub fn find_globals<'a>(snapshots: &'a [BackupMetadata], snapshot: &BackupMetadata) -> Option<&'a BackupMetadata> {
+    debug!("Finding globals snapshot for {}", snapshot.key);
+    let mut globals: Vec<&BackupMetadata> = snapshots.iter().filter(|candidate| is_globals_key(&candidate.key)).collect();
+    globals.sort_by(|a, b| a.last_modified.total_cmp(&b.last_modified));
+    globals
+        .iter()
+        .rev()
+        .find(|candidate| candidate.last_modified <= snapshot.last_modified)
+        .or_else(|| globals.first())
+        .copied()
+}
+
+/// Dump the cluster's roles and tablespaces with `pg_dumpall --globals-only`
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `output` - Path of the SQL file to write
+pub async fn dump_globals(pg_config: &PostgresConfig, output: &Path) -> Result<()> {
+    debug!("Dumping cluster globals to {:?}", output);
+    let mut cmd = tokio::process::Command::new("pg_dumpall");
+    cmd.arg("--globals-only").arg("--file").arg(output);
+    connection_args(&mut cmd, pg_config);
+    let result = cmd
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to execute pg_dumpall; install the PostgreSQL client tools to back up globals")?;
+    if !result.status.success() {
+        return Err(anyhow!("pg_dumpall failed: {}", String::from_utf8_lossy(&result.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Dump the cluster globals and upload them as a snapshot under `prefix`
+///
+/// Like a database backup, the dump is encrypted for the configured recipients
+/// before upload and a manifest is written next to it. Role passwords are in
+/// the dump, so encrypting it is recommended.
+///
+/// # Returns
+///
+/// The key of the uploaded snapshot
+pub async fn backup_globals(
+    store: &dyn SnapshotStore,
+    pg_config: &PostgresConfig,
+    prefix: &str,
+    encryption: &ClientEncryption,
+) -> Result<String> {
+    debug!("Backing up cluster globals under {}", prefix);
+    let dump_file = tempfile::NamedTempFile::new()?;
+    let started = Utc::now();
+    dump_globals(pg_config, dump_file.path()).await?;
+
+    let finished = Utc::now();
+    let key = globals_key(prefix, finished);
+    let encrypted = encryption.encrypt_file(dump_file.path()).await?;
+    let (upload, key) = match &encrypted {
+        Some(encrypted) => (encrypted.path(), encryption.encrypted_key(&key)),
+        None => (dump_file.path(), key),
+    };
+    info!("Uploading cluster globals to {}", key);
+    store.upload_file(upload, &key, None).await?;
+
+    // The snapshot is usable without its manifest, so a failure is only logged
+    match Manifest::for_dump(&key, GLOBALS_NAME, upload, started, finished) {
+        Ok(mut manifest) => {
+            manifest.format = GLOBALS_NAME.to_string();
+            if let Err(e) = crate::manifest::write_manifest(store, &manifest).await {
+                warn!("Failed to write manifest of {}: {}", key, e);
+            }
+        }
+        Err(e) => warn!("Failed to build manifest of {}: {}", key, e),
+    }
+    Ok(key)
+}
+
+/// Apply a globals dump to the server with psql
+///
+/// Roles that already exist, such as the superuser running the restore, make
+/// their `CREATE ROLE` fail while the `ALTER ROLE` after it still applies, so
+/// psql carries on past errors. Those "already exists" errors are expected
+/// and dropped; any other error is returned for the caller to report.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `path` - The globals dump, decompressed and decrypted
+///
+/// # Returns
+///
+/// The unexpected errors psql reported, empty when every statement applied
+pub async fn apply_globals(pg_config: &PostgresConfig, path: &Path) -> Result<Vec<String>> {
+    debug!("Applying cluster globals from {:?}", path);
+    let decompressed = crate::compression::decompress_file(path).await?;
+    let path = decompressed.as_ref().map_or(path, |file| file.path());
+    let mut cmd = tokio::process::Command::new("psql");
+    cmd.args(["--no-psqlrc", "--quiet", "--dbname", crate::promote::MAINTENANCE_DB, "--file"]).arg(path);
+    connection_args(&mut cmd, pg_config);
+    let result = cmd
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to execute psql; install the PostgreSQL client tools to restore globals")?;
+    if !result.status.success() {
+        return Err(anyhow!("psql failed to apply globals: {}", String::from_utf8_lossy(&result.stderr).trim()));
+    }
+    let errors: Vec<String> = String::from_utf8_lossy(&result.stderr)
+        .lines()
+        .filter(|line| line.contains("ERROR:") && !line.contains("already exists"))
+        .map(|line| line.trim().to_string())
+        .collect();
+    for error in &errors {
+        warn!("Applying globals: {}", error);
+    }
+    info!("Applied cluster globals from {:?} with {} unexpected errors", path, errors.len());
+    Ok(errors)
+}
+
+/// One-line outcome of applying a globals snapshot, for the restore summary
+pub fn summary(key: &str, errors: &[String]) -> String {
+    debug!("Summarising globals applied from {}", key);
+    match errors {
+        [] => format!("Applied cluster globals from {}", key),
+        [first, ..] => format!("Applied cluster globals from {} with {} errors, first: {}", key, errors.len(), first),
+    }
+}
+
+/// Add the server's host, port, user and password to a pg_dumpall, pg_basebackup or psql command
+pub(crate) fn connection_args(cmd: &mut tokio::process::Command, pg_config: &PostgresConfig) {
+    cmd.arg("--host").arg(pg_config.host.as_deref().unwrap_or("localhost"))
+        .arg("--port").arg(pg_config.port.unwrap_or(5432).to_string());
+    if let Some(user) = &pg_config.username {
+        cmd.arg("--username").arg(user);
+    }
+    // Like pg_dump, these only accept the password through the environment
+    if let Some(pass) = &pg_config.password {
+        cmd.env("PGPASSWORD", pass);
+    }
+}
@@ -0,0 +1,127 @@
+// This module contains the healthcheck for the Rustored application
+// It evaluates a set of assertions (bucket reachable, latest snapshot fresh,
+// restore target reachable) and summarises them in a single status line that
+// monitoring systems such as Nagios or cron wrappers can consume.
+
+use crate::restore::RestoreTarget;
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::BackupMetadata;
+use log::{debug, info};
+use serde::Serialize;
+
+/// Outcome of a single healthcheck assertion
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    /// Create a passing check
+    pub fn pass(name: &str, detail: String) -> Self {
+        debug!("Healthcheck {} passed: {}", name, detail);
+        Self { name: name.to_string(), ok: true, detail }
+    }
+
+    /// Create a failing check
+    pub fn fail(name: &str, detail: String) -> Self {
+        debug!("Healthcheck {} failed: {}", name, detail);
+        Self { name: name.to_string(), ok: false, detail }
+    }
+}
+
+/// Results of all healthcheck assertions
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl HealthReport {
+    /// Whether every assertion passed
+    pub fn ok(&self) -> bool {
+        debug!("Evaluating healthcheck report with {} checks", self.checks.len());
+        self.checks.iter().all(|c| c.ok)
+    }
+
+    /// One-line, Nagios style status such as `OK - bucket: reachable; ...`
+    pub fn status_line(&self) -> String {
+        debug!("Formatting healthcheck status line");
+        let status = if self.ok() { "OK" } else { "CRITICAL" };
+        let details: Vec<String> = self.checks
+            .iter()
+            .map(|c| format!("{}: {}", c.name, c.detail))
+            .collect();
+        format!("{} - {}", status, details.join("; "))
+    }
+}
+
+/// Assert that the most recent snapshot is newer than `max_age_hours`
+///
+/// # Arguments
+///
+/// * `snapshots` - Snapshots found in the bucket
+/// * `max_age_hours` - Maximum allowed age of the newest snapshot
+/// * `now` - Current time as seconds since the Unix epoch
+pub fn check_snapshot_age(snapshots: &[BackupMetadata], max_age_hours: f64, now: f64) -> CheckResult {
+    debug!("Checking that the latest of {} snapshots is newer than {}h", snapshots.len(), max_age_hours);
+    let latest = snapshots
+        .iter()
+        .map(|s| s.last_modified)
+        .fold(None, |acc: Option<f64>, t| Some(acc.map_or(t, |a| a.max(t))));
+
+    match latest {
+        None => CheckResult::fail("latest_snapshot", "no snapshots found".to_string()),
+        Some(latest) => {
+            let age_hours = (now - latest).max(0.0) / 3600.0;
+            if age_hours <= max_age_hours {
+                CheckResult::pass("latest_snapshot", format!("{:.1}h old", age_hours))
+            } else {
+                CheckResult::fail("latest_snapshot", format!("{:.1}h old, limit {}h", age_hours, max_age_hours))
+            }
+        }
+    }
+}
+
+/// Run the configured healthcheck assertions
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser configured for the S3 or GCS bucket
+/// * `max_age_hours` - If set, assert the newest snapshot is at most this old
+/// * `target` - If set, assert the restore target is reachable
+pub async fn run_healthcheck(
+    mut browser: SnapshotBrowser,
+    max_age_hours: Option<f64>,
+    target: Option<&(dyn RestoreTarget + Send + Sync)>,
+) -> HealthReport {
+    info!("Running healthcheck against bucket {}", browser.bucket());
+    let mut report = HealthReport::default();
+
+    // Listing snapshots proves the bucket is reachable and feeds the age check
+    match browser.load_snapshots().await {
+        Ok(()) => {
+            report.checks.push(CheckResult::pass("bucket", format!("reachable, {} snapshots", browser.snapshots.len())));
+            if let Some(max_age) = max_age_hours {
+                let now = chrono::Utc::now().timestamp() as f64;
+                report.checks.push(check_snapshot_age(&browser.snapshots, max_age, now));
+            }
+        }
+        Err(e) => {
+            report.checks.push(CheckResult::fail("bucket", format!("unreachable: {}", e)));
+            if max_age_hours.is_some() {
+                report.checks.push(CheckResult::fail("latest_snapshot", "bucket unreachable".to_string()));
+            }
+        }
+    }
+
+    if let Some(target) = target {
+        let name = target.name().to_lowercase();
+        match target.test_connection().await {
+            Ok(_) => report.checks.push(CheckResult::pass(&name, "reachable".to_string())),
+            Err(e) => report.checks.push(CheckResult::fail(&name, format!("unreachable: {}", e))),
+        }
+    }
+
+    report
+}
@@ -0,0 +1,162 @@
+// This module contains the legal hold catalog for the Rustored application
+// A hold pins every snapshot under a key prefix until a date, for legal or
+// regulatory retention requests. Holds are stored as a JSON catalog object in
+// the snapshot bucket so every rustored instance sees the same holds, and any
+// prune or delete of a snapshot must pass `HoldCatalog::ensure_deletable` first.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use crate::ui::browser::SnapshotBrowser;
+
+/// Key of the hold catalog object in the snapshot bucket
+pub const CATALOG_KEY: &str = ".rustored/holds.json";
+
+/// A legal hold on every snapshot under a key prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hold {
+    /// Key prefix of the held snapshots
+    pub prefix: String,
+    /// Last day (inclusive, `YYYY-MM-DD`) the hold is in force
+    pub until: NaiveDate,
+    /// Why the hold was placed, e.g. a case or ticket reference
+    pub reason: String,
+    /// Day the hold was created
+    pub created: NaiveDate,
+}
+
+impl Hold {
+    /// Whether the hold is in force on `today`
+    pub fn is_active(&self, today: NaiveDate) -> bool {
+        debug!("Checking if hold on {} is active", self.prefix);
+        today <= self.until
+    }
+
+    /// Whether the hold covers a snapshot key
+    pub fn covers(&self, key: &str) -> bool {
+        debug!("Checking if hold on {} covers {}", self.prefix, key);
+        key.starts_with(&self.prefix)
+    }
+}
+
+/// Catalog of all legal holds on a bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HoldCatalog {
+    pub holds: Vec<Hold>,
+}
+
+impl HoldCatalog {
+    /// Parse a catalog from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing hold catalog ({} bytes)", bytes.len());
+        crate::migration::from_json(crate::migration::MetadataKind::Holds, bytes)
+    }
+
+    /// Serialize the catalog to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing hold catalog with {} holds", self.holds.len());
+        crate::migration::to_json(self)
+    }
+
+    /// Place a hold on every snapshot under `prefix` until `until`
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Key prefix of the snapshots to hold
+    /// * `until` - Last day the hold is in force, `YYYY-MM-DD`
+    /// * `reason` - Why the hold was placed
+    /// * `today` - Current day, recorded as the creation date
+    pub fn create(&mut self, prefix: &str, until: &str, reason: &str, today: NaiveDate) -> Result<&Hold> {
+        debug!("Creating hold on {} until {}", prefix, until);
+        let until = NaiveDate::parse_from_str(until, "%Y-%m-%d")
+            .with_context(|| format!("Invalid hold date '{}', expected YYYY-MM-DD", until))?;
+        if until < today {
+            return Err(anyhow!("Hold date {} is in the past", until));
+        }
+        if reason.trim().is_empty() {
+            return Err(anyhow!("A hold needs a reason"));
+        }
+        self.holds.push(Hold {
+            prefix: prefix.to_string(),
+            until,
+            reason: reason.to_string(),
+            created: today,
+        });
+        info!("Created hold on {} until {}", prefix, until);
+        Ok(&self.holds[self.holds.len() - 1])
+    }
+
+    /// Release every hold on exactly `prefix`, returning how many were removed
+    pub fn release(&mut self, prefix: &str) -> Result<usize> {
+        debug!("Releasing holds on {}", prefix);
+        let before = self.holds.len();
+        self.holds.retain(|hold| hold.prefix != prefix);
+        let released = before - self.holds.len();
+        if released == 0 {
+            return Err(anyhow!("No hold on prefix '{}'", prefix));
+        }
+        info!("Released {} hold(s) on {}", released, prefix);
+        Ok(released)
+    }
+
+    /// Holds in force on `today` that cover a snapshot key
+    pub fn active_holds(&self, key: &str, today: NaiveDate) -> Vec<&Hold> {
+        debug!("Finding active holds for {}", key);
+        self.holds
+            .iter()
+            .filter(|hold| hold.is_active(today) && hold.covers(key))
+            .collect()
+    }
+
+    /// Whether a snapshot key is under an active hold
+    pub fn is_held(&self, key: &str, today: NaiveDate) -> bool {
+        debug!("Checking if {} is held", key);
+        !self.active_holds(key, today).is_empty()
+    }
+
+    /// Fail if a snapshot may not be pruned or deleted because of a hold
+    pub fn ensure_deletable(&self, key: &str, today: NaiveDate) -> Result<()> {
+        debug!("Checking that {} may be deleted", key);
+        match self.active_holds(key, today).first() {
+            Some(hold) => Err(anyhow!(
+                "{} is under legal hold until {} ({}); release the hold on '{}' first",
+                key, hold.until, hold.reason, hold.prefix
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Place a hold in the bucket's catalog and return the keys of the snapshots it pins
+///
+/// The catalog is re-read from the bucket first so concurrent holds placed by
+/// other instances are kept.
+pub async fn place_hold(
+    browser: &mut SnapshotBrowser,
+    prefix: &str,
+    until: &str,
+    reason: &str,
+    today: NaiveDate,
+) -> Result<Vec<String>> {
+    debug!("Placing hold on {} until {}", prefix, until);
+    browser.load_holds().await?;
+    browser.holds.create(prefix, until, reason, today)?;
+    browser.save_holds().await?;
+
+    browser.load_snapshots().await?;
+    Ok(browser.snapshots
+        .iter()
+        .filter(|snapshot| snapshot.key.starts_with(prefix))
+        .map(|snapshot| snapshot.key.clone())
+        .collect())
+}
+
+/// Release the holds on `prefix` in the bucket's catalog, returning how many were removed
+pub async fn release_hold(browser: &mut SnapshotBrowser, prefix: &str) -> Result<usize> {
+    debug!("Releasing hold on {}", prefix);
+    browser.load_holds().await?;
+    let released = browser.holds.release(prefix)?;
+    browser.save_holds().await?;
+    Ok(released)
+}
@@ -0,0 +1,263 @@
+// This module contains restore hooks for the Rustored application
+// Hooks let a dependent application be put into maintenance mode before its
+// database is dropped or replaced, and brought back once the restore finishes,
+// and run scripts before and after the restore itself.
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info};
+use std::future::Future;
+use std::time::Duration;
+
+/// Timeout applied to URL hooks so a dead endpoint cannot stall a restore
+const URL_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A single hook action
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestoreHook {
+    /// POST a JSON event to the given URL
+    Url(String),
+    /// Run the given shell command with `sh -c`
+    Command(String),
+}
+
+impl RestoreHook {
+    /// Parse a hook specification
+    ///
+    /// Specifications starting with `http://` or `https://` are URL hooks,
+    /// anything else is treated as a shell command.
+    pub fn parse(spec: &str) -> Option<Self> {
+        debug!("Parsing restore hook specification");
+        let spec = spec.trim();
+        if spec.is_empty() {
+            None
+        } else if spec.starts_with("http://") || spec.starts_with("https://") {
+            Some(RestoreHook::Url(spec.to_string()))
+        } else {
+            Some(RestoreHook::Command(spec.to_string()))
+        }
+    }
+
+    /// Run the hook for the given event and restore target
+    ///
+    /// URL hooks receive `{"event": ..., "target": ...}` as a JSON body.
+    /// Command hooks receive `RUSTORED_HOOK_EVENT` and `RUSTORED_TARGET` in their environment.
+    pub async fn run(&self, event: &str, target: &str) -> Result<()> {
+        self.run_with_vars(event, target, &[]).await
+    }
+
+    /// Run the hook with extra variables
+    ///
+    /// Each `(name, value)` pair is added to the JSON body of URL hooks and
+    /// passed to command hooks as `RUSTORED_<NAME>`.
+    pub async fn run_with_vars(&self, event: &str, target: &str, vars: &[(&str, &str)]) -> Result<()> {
+        debug!("Running {} hook for target {}", event, target);
+        match self {
+            RestoreHook::Url(url) => {
+                let client = reqwest::Client::builder().timeout(URL_HOOK_TIMEOUT).build()?;
+                let mut body = serde_json::json!({ "event": event, "target": target });
+                for (name, value) in vars {
+                    body[*name] = serde_json::Value::from(*value);
+                }
+                let response = client
+                    .post(url)
+                    .json(&body)
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("{} hook request to {} failed: {}", event, url, e))?;
+                if !response.status().is_success() {
+                    return Err(anyhow!("{} hook at {} returned {}", event, url, response.status()));
+                }
+            }
+            RestoreHook::Command(command) => {
+                let output = tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("RUSTORED_HOOK_EVENT", event)
+                    .env("RUSTORED_TARGET", target)
+                    .envs(vars.iter().map(|(name, value)| (format!("RUSTORED_{}", name.to_uppercase()), *value)))
+                    .output()
+                    .await
+                    .map_err(|e| anyhow!("Failed to run {} hook: {}", event, e))?;
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    return Err(anyhow!("{} hook exited with {}: {}", event, output.status, stderr.trim()));
+                }
+            }
+        }
+        info!("{} hook completed for {}", event, target);
+        Ok(())
+    }
+}
+
+/// Variables available to pre- and post-restore hooks as `{{name}}` placeholders
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateVars {
+    /// Database, index, or collection the snapshot was restored into
+    pub restored_db: String,
+    /// Key of the restored snapshot
+    pub snapshot_key: String,
+    /// Database the snapshot was taken from, derived from the snapshot key
+    pub source_db: String,
+    /// Kind of restore target: `postgres`, `elasticsearch` or `qdrant`
+    pub datastore: String,
+    /// Host of the restore target, as configured
+    pub target_host: String,
+}
+
+impl TemplateVars {
+    /// Build the variables for a finished restore
+    pub fn new(restored_db: &str, snapshot_key: &str) -> Self {
+        debug!("Building template variables for {}", restored_db);
+        Self {
+            restored_db: restored_db.to_string(),
+            snapshot_key: snapshot_key.to_string(),
+            source_db: source_db_from_key(snapshot_key),
+            ..Default::default()
+        }
+    }
+
+    /// Describe the restore target the snapshot goes to
+    pub fn with_target(mut self, datastore: &str, target_host: &str) -> Self {
+        debug!("Adding {} target {} to template variables", datastore, target_host);
+        self.datastore = datastore.to_string();
+        self.target_host = target_host.to_string();
+        self
+    }
+
+    /// The variables as `(name, value)` pairs
+    pub fn pairs(&self) -> [(&'static str, &str); 5] {
+        [
+            ("restored_db", &self.restored_db),
+            ("snapshot_key", &self.snapshot_key),
+            ("source_db", &self.source_db),
+            ("datastore", &self.datastore),
+            ("target_host", &self.target_host),
+        ]
+    }
+
+    /// Replace every `{{name}}` placeholder in `template` with the raw value
+    ///
+    /// Whitespace inside the braces is ignored. Unknown names are an error so
+    /// that a typo cannot silently run a hook against the wrong database.
+    pub fn expand(&self, template: &str) -> Result<String> {
+        debug!("Expanding hook template");
+        self.expand_with(template, |_, value| value.to_string())
+    }
+
+    /// Expand a shell command, quoting each value as one shell word
+    ///
+    /// A snapshot key such as `x;rm -rf ~` stays a single argument instead
+    /// of running as a command.
+    pub fn expand_command(&self, template: &str) -> Result<String> {
+        debug!("Expanding hook command template");
+        self.expand_with(template, |_, value| crate::ui::command::shell_quote(value))
+    }
+
+    /// Expand SQL, quoting database names as identifiers and the other values as literals
+    pub fn expand_sql(&self, template: &str) -> Result<String> {
+        debug!("Expanding SQL template");
+        self.expand_with(template, |name, value| match name {
+            "restored_db" | "source_db" => crate::compare::quote_ident(value),
+            _ => crate::provenance::quote_literal(value),
+        })
+    }
+
+    fn expand_with(&self, template: &str, quote: impl Fn(&str, &str) -> String) -> Result<String> {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..].find("}}")
+                .ok_or_else(|| anyhow!("Unclosed '{{{{' in hook template"))?;
+            let name = rest[start + 2..start + end].trim();
+            let value = self.pairs()
+                .into_iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| anyhow!("Unknown hook template variable '{}'", name))?;
+            output.push_str(&rest[..start]);
+            output.push_str(&quote(name, value));
+            rest = &rest[start + end + 2..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+/// Derive the source database name from a snapshot key
+///
+/// The file name is stripped of its extensions and of trailing `-`-separated
+/// parts that start with a digit (timestamps) or are the `pre-change` label,
+/// so both `orders-20250101.dump` and `orders-pre-change-20250101T120000Z.dump`
+/// give `orders`.
+pub fn source_db_from_key(key: &str) -> String {
+    debug!("Deriving source database from key: {}", key);
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let mut name = stem;
+    loop {
+        let trimmed = name.strip_suffix(&format!("-{}", crate::backup::PRE_CHANGE_LABEL));
+        if let Some(trimmed) = trimmed {
+            name = trimmed;
+            continue;
+        }
+        match name.rsplit_once('-') {
+            Some((head, tail)) if !head.is_empty() && tail.starts_with(|c: char| c.is_ascii_digit()) => name = head,
+            _ => break,
+        }
+    }
+    name.to_string()
+}
+
+/// Hooks that toggle maintenance mode on the application using the restore target
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaintenanceHooks {
+    /// Hook run before the target is dropped or replaced
+    pub enter: Option<RestoreHook>,
+    /// Hook run after the restore finishes, whether it succeeded or not
+    pub exit: Option<RestoreHook>,
+}
+
+impl MaintenanceHooks {
+    /// Build maintenance hooks from optional hook specifications
+    pub fn from_specs(enter: Option<&str>, exit: Option<&str>) -> Self {
+        debug!("Building maintenance hooks from specifications");
+        Self {
+            enter: enter.and_then(RestoreHook::parse),
+            exit: exit.and_then(RestoreHook::parse),
+        }
+    }
+
+    /// Run a restore inside a maintenance window
+    ///
+    /// The enter hook runs first and aborts the restore if it fails. The exit
+    /// hook always runs once the restore has been attempted, so the application
+    /// is brought back even when the restore fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Name of the database, index, or collection being restored
+    /// * `restore` - The restore operation to run
+    pub async fn run<F, T>(&self, target: &str, restore: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        debug!("Running restore of {} inside maintenance window", target);
+        if let Some(hook) = &self.enter {
+            hook.run("maintenance_on", target).await?;
+        }
+
+        let result = restore.await;
+
+        if let Some(hook) = &self.exit {
+            if let Err(e) = hook.run("maintenance_off", target).await {
+                error!("Failed to leave maintenance mode: {}", e);
+                // Surface the hook failure only when it is the sole problem
+                if result.is_ok() {
+                    return Err(anyhow!("Restore succeeded but leaving maintenance mode failed: {}", e));
+                }
+            }
+        }
+
+        result
+    }
+}
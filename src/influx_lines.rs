@@ -0,0 +1,225 @@
+// This module contains the InfluxDB line protocol loader for the Rustored application
+// InfluxDB snapshots are line protocol, one point per line, as written by
+// `influx export` or `influxd inspect export-lp`; blank lines and `#` comment
+// lines are skipped. Points are written to an InfluxDB 2.x bucket through
+// `/api/v2/write` in chunks of 5000 lines, the batch size InfluxDB recommends.
+//
+// A missing bucket is created in the organization with infinite retention. A
+// chunk InfluxDB answers with 429 or 503 is sent again after the pause its
+// Retry-After asks for, and a chunk too large for it (413) is split in half.
+// A chunk InfluxDB refuses as invalid goes to the dead-letter file with its
+// error. InfluxDB may already have written the chunk's valid points, but a
+// point written again with the same series and timestamp replaces itself, so
+// retrying the dead letters is safe.
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+
+/// Lines sent in one write request
+pub const LINE_BATCH_SIZE: usize = 5000;
+
+/// Timestamp precision of the points when none is configured
+pub const DEFAULT_PRECISION: &str = "ns";
+
+/// Precisions `/api/v2/write` accepts
+const PRECISIONS: &[&str] = &["ns", "us", "ms", "s"];
+
+/// Times a chunk is sent while InfluxDB asks to slow down
+pub const MAX_WRITE_ATTEMPTS: u32 = 5;
+
+/// Pause before resending a chunk when InfluxDB gives no Retry-After
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest pause between resends
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The InfluxDB bucket points are written to
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InfluxBucket {
+    /// InfluxDB URL, e.g. `http://localhost:8086`
+    pub host: String,
+    /// Organization owning the bucket
+    pub org: String,
+    pub bucket: String,
+    /// API token, sent as `Authorization: Token ...`
+    pub token: Option<String>,
+    /// Timestamp precision of the points: `ns`, `us`, `ms` or `s`
+    pub precision: String,
+}
+
+impl InfluxBucket {
+    /// A request to an InfluxDB API path, carrying the token
+    pub fn request(&self, client: &reqwest::Client, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        debug!("Building InfluxDB request {} {}", method, path);
+        let request = client.request(method, format!("{}{}", self.host.trim_end_matches('/'), path));
+        match &self.token {
+            Some(token) => request.header(reqwest::header::AUTHORIZATION, format!("Token {}", token)),
+            None => request,
+        }
+    }
+
+    /// ID of the bucket, or `None` when it does not exist
+    pub async fn bucket_id(&self, client: &reqwest::Client) -> Result<Option<String>> {
+        debug!("Looking up InfluxDB bucket {} of {}", self.bucket, self.org);
+        let response = self.request(client, reqwest::Method::GET, "/api/v2/buckets")
+            .query(&[("org", self.org.as_str()), ("name", self.bucket.as_str())])
+            .send()
+            .await?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        match status {
+            // Versions before 2.1 answer a missing bucket with 404 rather than an empty list
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(body["buckets"][0]["id"].as_str().map(String::from)),
+            status => Err(anyhow!("InfluxDB returned {} for bucket {}: {}", status, self.bucket, error_message(&body, status))),
+        }
+    }
+
+    /// Make sure the bucket exists, creating it with infinite retention if it does not
+    async fn ensure_bucket(&self, client: &reqwest::Client) -> Result<()> {
+        debug!("Ensuring InfluxDB bucket {} exists", self.bucket);
+        if self.bucket_id(client).await?.is_some() {
+            return Ok(());
+        }
+        let response = self.request(client, reqwest::Method::GET, "/api/v2/orgs").query(&[("org", self.org.as_str())]).send().await?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        let org_id = body["orgs"][0]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("InfluxDB organization {} not found: {}", self.org, error_message(&body, status)))?;
+        info!("Creating InfluxDB bucket {} in {}", self.bucket, self.org);
+        let response = self.request(client, reqwest::Method::POST, "/api/v2/buckets")
+            .json(&json!({ "orgID": org_id, "name": self.bucket, "retentionRules": [] }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body: Value = response.json().await.unwrap_or_default();
+            return Err(anyhow!("Failed to create InfluxDB bucket {}: {}", self.bucket, error_message(&body, status)));
+        }
+        Ok(())
+    }
+}
+
+/// The message of an InfluxDB error body, `{"code": ..., "message": ...}`
+fn error_message(body: &Value, status: reqwest::StatusCode) -> String {
+    body["message"].as_str().map(String::from).unwrap_or_else(|| status.to_string())
+}
+
+/// How long InfluxDB asks a client to wait before writing again
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_BACKOFF))
+}
+
+/// Write a chunk of lines
+///
+/// Chunks InfluxDB is too busy for are sent again, chunks too large are
+/// split, and lines of chunks it refuses go to `dead_letters`.
+///
+/// # Returns
+///
+/// The number of lines written
+async fn write_chunk(client: &reqwest::Client, target: &InfluxBucket, lines: Vec<String>, dead_letters: &mut DeadLetterWriter) -> Result<u64> {
+    debug!("Writing {} lines to InfluxDB bucket {}", lines.len(), target.bucket);
+    let body = lines.join("\n");
+    let query = [("org", target.org.as_str()), ("bucket", target.bucket.as_str()), ("precision", target.precision.as_str())];
+    let mut delay = BASE_BACKOFF;
+    let mut busy = reqwest::StatusCode::TOO_MANY_REQUESTS;
+    for attempt in 1..=MAX_WRITE_ATTEMPTS {
+        let response = target.request(client, reqwest::Method::POST, "/api/v2/write")
+            .query(&query)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(body.clone())
+            .send()
+            .await
+            .map_err(|e| anyhow!("Writing to InfluxDB bucket {} failed: {}", target.bucket, e))?;
+        let status = response.status();
+        match status {
+            status if status.is_success() => return Ok(lines.len() as u64),
+            reqwest::StatusCode::TOO_MANY_REQUESTS | reqwest::StatusCode::SERVICE_UNAVAILABLE => {
+                let pause = retry_after(&response).unwrap_or(delay);
+                debug!("InfluxDB answered {} on attempt {}, waiting {:?}", status, attempt, pause);
+                busy = status;
+                if attempt < MAX_WRITE_ATTEMPTS {
+                    tokio::time::sleep(pause).await;
+                    delay = (delay * 2).min(MAX_BACKOFF);
+                }
+            }
+            reqwest::StatusCode::PAYLOAD_TOO_LARGE if lines.len() > 1 => {
+                debug!("InfluxDB found {} lines too large, splitting them", lines.len());
+                let mut first = lines;
+                let second = first.split_off(first.len() / 2);
+                let written = Box::pin(write_chunk(client, target, first, dead_letters)).await?;
+                return Ok(written + Box::pin(write_chunk(client, target, second, dead_letters)).await?);
+            }
+            reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::PAYLOAD_TOO_LARGE | reqwest::StatusCode::UNPROCESSABLE_ENTITY => {
+                let reason = error_message(&response.json().await.unwrap_or_default(), status);
+                warn!("InfluxDB refused {} lines: {}", lines.len(), reason);
+                for line in lines {
+                    dead_letters.write(Value::String(line), &reason)?;
+                }
+                return Ok(0);
+            }
+            status => {
+                let reason = error_message(&response.json().await.unwrap_or_default(), status);
+                return Err(anyhow!("InfluxDB refused a write to bucket {}: {}", target.bucket, reason));
+            }
+        }
+    }
+    let reason = format!("still answered {} after {} attempts", busy, MAX_WRITE_ATTEMPTS);
+    warn!("Giving up on {} lines: {}", lines.len(), reason);
+    for line in lines {
+        dead_letters.write(Value::String(line), &reason)?;
+    }
+    Ok(0)
+}
+
+/// Stream a line protocol snapshot into a bucket
+///
+/// The bucket is created if it does not exist. Lines InfluxDB refuses, or
+/// that it stays too busy for, are written to `dead_letters` and do not fail
+/// the load.
+///
+/// # Arguments
+///
+/// * `client` - HTTP client, carrying the target timeouts
+/// * `target` - The bucket, its organization and the token
+/// * `path` - The snapshot file
+/// * `progress` - Called with the share of the file written
+/// * `dead_letters` - Receives the lines that could not be written
+///
+/// # Returns
+///
+/// The number of lines written
+pub async fn write_lines(
+    client: &reqwest::Client,
+    target: &InfluxBucket,
+    path: &Path,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Writing lines from {:?} to InfluxDB bucket {}", path, target.bucket);
+    if !PRECISIONS.contains(&target.precision.as_str()) {
+        return Err(anyhow!("Unknown InfluxDB precision {}; use ns, us, ms or s", target.precision));
+    }
+    target.ensure_bucket(client).await?;
+    let mut snapshot = NdjsonBatches::open(path, LINE_BATCH_SIZE).await?;
+
+    let mut written = 0u64;
+    while let Some(lines) = snapshot.next_lines().await? {
+        let chunk: Vec<String> = lines.iter().map(|line| line.trim()).filter(|point| !point.starts_with('#')).map(String::from).collect();
+        if !chunk.is_empty() {
+            written += write_chunk(client, target, chunk, dead_letters).await?;
+        }
+        if let Some(progress) = progress {
+            progress(snapshot.progress());
+        }
+    }
+    info!("Wrote {} points to InfluxDB bucket {}, {} refused", written, target.bucket, dead_letters.count());
+    Ok(written)
+}
@@ -1,8 +1,67 @@
 // Export modules for testing and usage
 pub mod ui;
+pub mod archive;
+pub mod as_of;
+pub mod bandwidth;
+pub mod cancel;
+pub mod chains;
+pub mod compare;
+pub mod compression;
 pub mod config;
+pub mod config_schema;
+pub mod dead_letter;
+pub mod directory_dump;
+pub mod dynamodb_items;
+pub mod demo;
+pub mod encryption;
+pub mod gc;
+pub mod globals;
 pub mod backup;
+pub mod bundle;
 pub mod datastore;
+pub mod download_ledger;
+pub mod es_alias;
+pub mod es_bulk;
+pub mod es_data_stream;
+pub mod es_index;
+pub mod healthcheck;
+pub mod influx_lines;
+pub mod holds;
+pub mod listing_cache;
+pub mod lint;
+pub mod manifest;
+pub mod mapping;
+pub mod meili_documents;
+pub mod migration;
+pub mod milvus_proto;
+pub mod milvus_rows;
+pub mod storage;
+pub mod hooks;
 pub mod postgres;
+pub mod prepare;
+pub mod profiles;
+pub mod presets;
+pub mod picker;
+pub mod pitr;
+pub mod progress;
+pub mod provenance;
+pub mod promote;
+pub mod qdrant_points;
+pub mod qdrant_snapshot;
+pub mod recipes;
 pub mod restore;
+pub mod restore_history;
+pub mod restore_tool;
+pub mod retention;
+pub mod row_filter;
+pub mod schedule;
+pub mod share;
+pub mod sigv4;
+pub mod stream_restore;
+pub mod table;
 pub mod targets;
+pub mod telemetry;
+pub mod toc;
+pub mod verification;
+pub mod wal;
+pub mod weaviate_objects;
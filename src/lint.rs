@@ -0,0 +1,210 @@
+// This module contains bucket linting for the Rustored application
+// Long-lived buckets collect objects nobody can account for: dumps uploaded by
+// hand under ad-hoc names, manifests left behind when a dump was deleted by
+// something other than rustored, and dumps whose manifest upload failed.
+// `lint-bucket` checks every key under the prefix against a naming template and
+// pairs dumps with their `.backup.json` manifests, reporting what does not fit.
+// Catalog objects under `.rustored/` and archived WAL files follow their own
+// naming and are left alone.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use crate::storage::SnapshotStore;
+use crate::table::{Align, Cell, Table};
+use crate::ui::models::BackupMetadata;
+
+/// Template of the names rustored gives snapshots, e.g. `orders-20250101T120000Z.dump`
+pub const DEFAULT_TEMPLATE: &str = "{name}-{timestamp}.{ext}";
+
+/// Suffixes of backup manifests and incremental chain manifests, either of which pairs with a snapshot
+const MANIFEST_SUFFIXES: [&str; 2] = [crate::manifest::MANIFEST_SUFFIX, crate::chains::MANIFEST_SUFFIX];
+
+/// Extensions compression adds on top of a snapshot's own, ignored when matching the template
+const COMPRESSION_EXTENSIONS: [&str; 3] = [".gz", ".zst", ".lz4"];
+
+/// A naming template snapshot keys are checked against
+///
+/// Placeholders are `{db}` and `{label}` for a database name and a label,
+/// `{name}` for either with an optional `-<label>`, `{timestamp}` for a UTC
+/// time such as `20250101T120000Z`, `{date}` for `20250101` and `{ext}` for
+/// the file extension. Anything else is literal. A template without a `/` is
+/// matched against the file name; one with a `/` against the key below the
+/// prefix. Encryption and compression extensions are stripped first.
+#[derive(Debug, Clone)]
+pub struct NamingTemplate {
+    template: String,
+    pattern: Regex,
+}
+
+impl NamingTemplate {
+    /// Parse a naming template, rejecting unknown placeholders
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template, e.g. `{db}-{timestamp}.dump`
+    pub fn parse(template: &str) -> Result<Self> {
+        debug!("Parsing naming template {}", template);
+        let mut pattern = String::from("^");
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let end = rest[start..].find('}').ok_or_else(|| anyhow!("Unclosed placeholder in naming template {}", template))? + start;
+            pattern.push_str(match &rest[start + 1..end] {
+                "db" | "label" | "name" => "[^/]+?",
+                "timestamp" => r"\d{8}T\d{6}Z",
+                "date" => r"\d{8}",
+                "ext" => r"[A-Za-z0-9]+(?:\.[A-Za-z0-9]+)?",
+                other => return Err(anyhow!("Unknown placeholder {{{}}} in naming template {}, use db, label, name, timestamp, date or ext", other, template)),
+            });
+            rest = &rest[end + 1..];
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+        Ok(Self { template: template.to_string(), pattern: Regex::new(&pattern)? })
+    }
+
+    /// The template as it was given
+    pub fn template(&self) -> &str {
+        debug!("Getting naming template text");
+        &self.template
+    }
+
+    /// Whether a key under `prefix` follows the template
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Key prefix the bucket is linted under
+    /// * `key` - Key of the object
+    pub fn matches(&self, prefix: &str, key: &str) -> bool {
+        debug!("Matching {} against naming template {}", key, self.template);
+        let key = crate::encryption::plain_key(key);
+        let key = COMPRESSION_EXTENSIONS.iter().find_map(|extension| key.strip_suffix(extension)).unwrap_or(key);
+        let name = match self.template.contains('/') {
+            true => key.strip_prefix(prefix).unwrap_or(key).trim_start_matches('/'),
+            false => key.rsplit('/').next().unwrap_or(key),
+        };
+        self.pattern.is_match(name)
+    }
+}
+
+/// What is wrong with an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Finding {
+    /// The key does not follow the naming template
+    Nonconforming,
+    /// A manifest whose snapshot is gone
+    OrphanedManifest,
+    /// A snapshot without a manifest
+    MissingManifest,
+}
+
+impl Finding {
+    /// Short description for the report
+    pub fn describe(&self) -> &'static str {
+        debug!("Describing lint finding {:?}", self);
+        match self {
+            Self::Nonconforming => "does not follow the naming template",
+            Self::OrphanedManifest => "manifest without a snapshot",
+            Self::MissingManifest => "snapshot without a manifest",
+        }
+    }
+}
+
+/// An object that does not fit, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintIssue {
+    pub key: String,
+    pub finding: Finding,
+}
+
+/// Result of linting a bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LintReport {
+    /// Objects checked, not counting catalog objects and archived WAL
+    pub checked: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    /// Whether every checked object fits
+    pub fn is_clean(&self) -> bool {
+        debug!("Checking whether lint report is clean");
+        self.issues.is_empty()
+    }
+
+    /// One line summing up the report, e.g. `3 problems in 120 objects`
+    pub fn summary(&self) -> String {
+        debug!("Summarising lint report");
+        match self.issues.len() {
+            0 => format!("No problems in {} objects", self.checked),
+            1 => format!("1 problem in {} objects", self.checked),
+            n => format!("{} problems in {} objects", n, self.checked),
+        }
+    }
+
+    /// The issues as a table of keys and findings
+    pub fn table(&self) -> Table {
+        debug!("Building lint report table");
+        let mut table = Table::new(&[("FINDING", Align::Left), ("KEY", Align::Left)]);
+        for issue in &self.issues {
+            table.row(vec![Cell::from(issue.finding.describe()), issue.key.clone().into()]);
+        }
+        table
+    }
+}
+
+/// Check listed objects against a naming template and pair snapshots with manifests
+///
+/// # Arguments
+///
+/// * `objects` - Every object listed under `prefix`, manifests included
+/// * `prefix` - Key prefix the objects were listed under
+/// * `template` - The naming template snapshots should follow
+pub fn lint(objects: &[BackupMetadata], prefix: &str, template: &NamingTemplate) -> LintReport {
+    debug!("Linting {} objects under {}", objects.len(), prefix);
+    let objects: Vec<&str> = objects
+        .iter()
+        .map(|object| object.key.as_str())
+        .filter(|key| !key.starts_with(".rustored/") && !key.contains("/.rustored/") && !key.ends_with('/') && !crate::wal::is_wal_key(key))
+        .filter(|key| !crate::es_index::is_index_definition_key(key))
+        .collect();
+    let keys: HashSet<&str> = objects.iter().copied().collect();
+    let mut report = LintReport { checked: objects.len(), issues: Vec::new() };
+    for key in objects {
+        let manifest_of = MANIFEST_SUFFIXES.iter().find_map(|suffix| key.strip_suffix(suffix));
+        let finding = if let Some(snapshot) = manifest_of {
+            (!keys.contains(snapshot)).then_some(Finding::OrphanedManifest)
+        } else if !MANIFEST_SUFFIXES.iter().any(|suffix| keys.contains(format!("{}{}", key, suffix).as_str())) {
+            Some(Finding::MissingManifest)
+        } else {
+            None
+        };
+        // Base backups are named by `wal base-backup`, not the template
+        let conforms = manifest_of.is_some() || crate::wal::base_start_segment(key).is_some() || template.matches(prefix, key);
+        if !conforms {
+            report.issues.push(LintIssue { key: key.to_string(), finding: Finding::Nonconforming });
+        }
+        if let Some(finding) = finding {
+            report.issues.push(LintIssue { key: key.to_string(), finding });
+        }
+    }
+    report.issues.sort_by(|a, b| a.key.cmp(&b.key));
+    report
+}
+
+/// List every object under a prefix and lint it
+///
+/// # Arguments
+///
+/// * `store` - The store holding the bucket
+/// * `prefix` - Key prefix to lint under
+/// * `template` - The naming template snapshots should follow
+pub async fn lint_bucket(store: &dyn SnapshotStore, prefix: &str, template: &NamingTemplate) -> Result<LintReport> {
+    debug!("Linting bucket under {} with template {}", prefix, template.template());
+    let objects = store.list_snapshots(prefix).await?;
+    Ok(lint(&objects, prefix, template))
+}
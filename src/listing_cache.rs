@@ -0,0 +1,126 @@
+// This module contains the snapshot listing cache for the Rustored application
+// Listing a large bucket over a slow link can take a long time, so the last
+// complete listing of each source is kept on disk. The TUI shows it straight
+// away, marked stale, while the live listing loads, and replaces it when the
+// live listing completes.
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use crate::ui::models::BackupMetadata;
+
+/// Last complete listing of one snapshot source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedListing {
+    /// Source the listing came from, e.g. `s3://dumps/pg/`
+    pub source: String,
+    /// Unix timestamp of when the listing completed
+    pub saved_at: f64,
+    pub snapshots: Vec<BackupMetadata>,
+}
+
+/// Merge newly listed snapshots into a listing
+///
+/// A snapshot already listed under the same key is replaced only when its
+/// ETag or last modified time changed, so unchanged entries stay as they are.
+///
+/// # Returns
+///
+/// How many snapshots were added or replaced
+pub fn merge_listing(snapshots: &mut Vec<BackupMetadata>, listed: Vec<BackupMetadata>) -> usize {
+    debug!("Merging {} listed snapshots into {}", listed.len(), snapshots.len());
+    let mut changed = 0;
+    for snapshot in listed {
+        match snapshots.iter_mut().find(|existing| existing.key == snapshot.key) {
+            Some(existing) if existing.etag == snapshot.etag && existing.last_modified == snapshot.last_modified => {}
+            Some(existing) => {
+                *existing = snapshot;
+                changed += 1;
+            }
+            None => {
+                snapshots.push(snapshot);
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Directory of cached listings, one file per source
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListingCache {
+    pub dir: PathBuf,
+}
+
+impl ListingCache {
+    /// Cache in `dir`, which is created when the first listing is saved
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        debug!("Using listing cache in {}", dir.display());
+        Self { dir }
+    }
+
+    /// `$XDG_CACHE_HOME/rustored`, falling back to `~/.cache/rustored` and then the temp directory
+    pub fn default_dir() -> PathBuf {
+        debug!("Computing default listing cache directory");
+        std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("rustored")
+    }
+
+    /// File holding the listing of `source`
+    ///
+    /// Sources are hashed so bucket names and prefixes never need escaping.
+    pub fn path_for(&self, source: &str) -> PathBuf {
+        let digest = hex::encode(Sha256::digest(source.as_bytes()));
+        self.dir.join(format!("listing-{}.json", &digest[..16]))
+    }
+
+    /// Cached listing of `source`, if there is a readable one
+    ///
+    /// A missing or unreadable cache is not an error; the live listing is
+    /// loaded either way.
+    pub fn load(&self, source: &str) -> Option<CachedListing> {
+        debug!("Loading cached listing of {}", source);
+        let path = self.path_for(source);
+        let bytes = std::fs::read(&path).ok()?;
+        match serde_json::from_slice::<CachedListing>(&bytes) {
+            Ok(listing) if listing.source == source => Some(listing),
+            Ok(listing) => {
+                debug!("Cached listing at {} is for {}, not {}", path.display(), listing.source, source);
+                None
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable listing cache {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Replace the cached listing of `source`
+    ///
+    /// The file is written beside its final name and renamed into place, so a
+    /// crash never leaves a half-written cache.
+    pub fn save(&self, source: &str, snapshots: &[BackupMetadata]) -> Result<()> {
+        debug!("Caching listing of {} snapshots from {}", snapshots.len(), source);
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create listing cache directory {}", self.dir.display()))?;
+        let listing = CachedListing {
+            source: source.to_string(),
+            saved_at: chrono::Utc::now().timestamp() as f64,
+            snapshots: snapshots.to_vec(),
+        };
+        let path = self.path_for(source);
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_vec(&listing)?)
+            .with_context(|| format!("Failed to write listing cache {}", partial.display()))?;
+        std::fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to replace listing cache {}", path.display()))?;
+        Ok(())
+    }
+}
@@ -1,7 +1,20 @@
-use rustored::{backup, config};
+mod args;
+mod commands;
+
+use args::{CatalogAction, Cli, Commands, ConfigAction, HoldAction, TargetAction, WalAction};
+use rustored::{backup, bundle, cancel, chains, config, download_ledger, healthcheck, holds, lint, migration, prepare, promote, recipes, schedule, share, verification, wal};
+use rustored::retention::RetentionPolicy;
 use anyhow::Result;
-use clap::{Parser, Subcommand, command, arg};
+use clap::{CommandFactory, FromArgMatches};
 use rustored::postgres;
+use rustored::hooks::MaintenanceHooks;
+use rustored::listing_cache::ListingCache;
+use rustored::restore_history::RestoreHistory;
+use rustored::table::{use_color, Align, Cell, Table};
+use crossterm::style::Color;
+use rustored::presets::RestorePresets;
+use rustored::encryption::ClientEncryption;
+use rustored::mapping::RestoreMapping;
 use tokio_postgres::config::SslMode;
 use tokio_postgres::Config as PgConfig;
 use log::{error, info, warn, debug, LevelFilter};
@@ -10,168 +23,148 @@ use crossterm::{execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlte
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use rustored::ui::rustored::RustoredApp;
-
-#[derive(Parser)]
-#[command(name = "rustored")]
-#[command(about = "PostgreSQL database management tool")]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
-
-    #[arg(short, long, help = "Postgres File Path")]
-    file: Option<String>,
-
-    #[arg(short = 'H', long, env = "PG_HOST", help = "Postgres Host")]
-    host: Option<String>,
-
-    #[arg(short, default_value = "5432", long, env = "PG_PORT", help = "Postgres Port")]
-    port: Option<u16>,
-
-    #[arg(short, long, env = "PG_USERNAME", help = "Postgres Username")]
-    username: Option<String>,
-
-    #[arg(short = 'P', long, env = "PG_PASSWORD", help = "Postgres Password")]
-    password: Option<String>,
-
-    #[arg(short = 'D', default_value = "postgres", long, env = "PG_DB_NAME", help = "Postgres Database Name")]
-    db_name: Option<String>,
-
-    #[arg(long, default_value = "false", env = "PG_USE_SSL", help = "Postgres Enable SSL")]
-    use_ssl: bool,
-
-    #[arg(long, env = "PG_ROOT_CERT_PATH", help = "Postgres Path to custom root certificates")]
-    root_cert_path: Option<String>,
-
-    #[arg(long, default_value = "false", env = "PG_VERIFY_SSL", help = "Postgres Verify SSL certificates")]
-    verify_ssl: bool,
-
-    #[arg(short = 'B', long, env = "S3_BUCKET", help = "S3 Bucket Name")]
-    bucket: Option<String>,
-
-    #[arg(short = 'R', long, env = "S3_REGION", help = "S3 Region")]
-    region: Option<String>,
-
-    #[arg(short = 'x', long, default_value = "postgres", env = "S3_PREFIX", help = "S3 Prefix for snapshot keys")]
-    prefix: Option<String>,
-
-    #[arg(short = 'E', long, env = "S3_ENDPOINT_URL", help = "S3 Endpoint URL")]
-    endpoint_url: Option<String>,
-
-    #[arg(short = 'A', long, env = "S3_ACCESS_KEY_ID", help = "S3 Access Key ID")]
-    access_key_id: Option<String>,
-
-    #[arg(short = 'S', long, env = "S3_SECRET_ACCESS_KEY", help = "S3 Secret Access Key")]
-    secret_access_key: Option<String>,
-
-    #[arg(long, default_value = "true", env = "S3_PATH_STYLE", help = "S3 Force path-style")]
-    path_style: bool,
-
-    /// Elasticsearch host or URL
-    #[arg(long, help = "Elasticsearch host or URL")]
-    es_host: Option<String>,
-
-    /// Elasticsearch index or Qdrant collection name
-    #[arg(long, help = "Elasticsearch index or Qdrant collection name")]
-    es_index: Option<String>,
-
-    /// Qdrant API key (optional)
-    #[arg(long, help = "Qdrant API key (optional)")]
-    qdrant_api_key: Option<String>,
+use rustored::ui::terminal_status::TerminalStatus;
+use rustored::ui::attention::Attention;
+use rustored::ui::models::{AzureConfig, B2Config, GcsConfig, SftpConfig, TimeoutConfig};
+
+/// Client-side encryption settings from the command line
+///
+/// clap rejects age and GPG recipients together, which is all `ClientEncryption::new` checks.
+fn client_encryption(cli: &Cli) -> ClientEncryption {
+    debug!("Building client-side encryption from command line settings");
+    ClientEncryption {
+        age_recipients: cli.age_recipients.clone(),
+        gpg_recipients: cli.gpg_recipients.clone(),
+        age_identity: cli.age_identity.clone(),
+    }
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    #[command(about = "List all databases")]
-    List,
-
-    #[command(about = "Create a new database")]
-    Create {
-        #[arg(help = "Name of the database to create")]
-        name: String,
-    },
-
-    #[command(about = "Clone a database")]
-    Clone {
-        #[arg(help = "Name of the database to clone from. Will create a new database with the name '<same_name>-clone'")]
-        name: String,
-    },
-
-    #[command(about = "Drop a database")]
-    Drop {
-        #[arg(help = "Name of the database to drop")]
-        name: String,
-    },
-
-    #[command(about = "Drop a database with force")]
-    DropForce {
-        #[arg(help = "Name of the database to drop")]
-        name: String,
-    },
-
-    #[command(about = "Rename a database")]
-    Rename {
-        #[arg(help = "Name of the database to rename")]
-        old_name: String,
-
-        #[arg(help = "New name for the database")]
-        new_name: String,
-    },
-
-    #[command(about = "Set database owner")]
-    SetOwner {
-        #[arg(help = "Name of the database")]
-        name: String,
-
-        #[arg(help = "New owner for the database")]
-        owner: String,
-    },
-
-    #[command(about = "Change the password of a user")]
-    ChangePassword {
-        #[arg(help = "Name of the user")]
-        user: String,
-
-        #[arg(help = "New password for the user")]
-        password: String,
-    },
-
-    #[command(about = "Dump a database")]
-    Dump {
-        #[arg(help = "Name of the database to dump")]
-        name: String,
-
-        #[arg(help = "Output file path")]
-        output: String,
-    },
-
-    #[command(about = "Restore a snapshot to a datastore")]
-    Restore {
-        #[arg(help = "Name of the destination database, index, or collection")]
-        name: String,
+/// Run the TUI on the terminal until it is left, restoring the terminal afterwards
+async fn run_tui(cli: &Cli, app: &mut RustoredApp) -> Result<Option<String>> {
+    debug!("Running the TUI");
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen, crossterm::event::EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    if !cli.no_terminal_title {
+        let status = TerminalStatus::new(cli.tmux_status);
+        status.begin();
+        app.terminal_status = Some(status);
+    }
 
-        #[arg(help = "Input dump file path")]
-        input: String,
+    app.attention = Attention::new(!cli.no_bell, std::time::Duration::from_secs(cli.attention_after));
 
-        #[arg(long, default_value = "postgres", help = "Target datastore: postgres, elasticsearch, or qdrant")]
-        target: String,
+    let res = app.run(&mut terminal).await;
+    if let Some(status) = &app.terminal_status {
+        status.end();
+    }
+    let res = res?;
+    disable_raw_mode()?;
+    execute!(std::io::stdout(), LeaveAlternateScreen, crossterm::event::DisableMouseCapture)?;
+    terminal.show_cursor()?;
+    Ok(res)
+}
 
-        // Elasticsearch/Qdrant options
-        #[arg(long, help = "Elasticsearch/Qdrant host or URL")]
-        es_host: Option<String>,
-        #[arg(long, help = "Elasticsearch index or Qdrant collection name")]
-        es_index: Option<String>,
-        #[arg(long, help = "Qdrant API key (optional)")]
-        qdrant_api_key: Option<String>,
-    },
+/// Build the TUI application state from the command line settings
+fn build_app(cli: &Cli, maintenance_hooks: MaintenanceHooks, cancel: &cancel::CancellationToken) -> RustoredApp {
+    debug!("Building RustoredApp from command line settings");
+    let mut app = RustoredApp::new(
+        &cli.bucket,
+        &cli.region,
+        &cli.prefix,
+        &cli.endpoint_url,
+        &cli.access_key_id,
+        &cli.secret_access_key,
+        cli.path_style,
+        &cli.host,
+        &cli.port,
+        &cli.username,
+        &cli.password,
+        cli.use_ssl,
+        &cli.db_name,
+        &cli.es_host,
+        &cli.es_index,
+        &cli.qdrant_api_key,
+    );
+    app.maintenance_hooks = maintenance_hooks;
+    app.cancel = cancel.clone();
+    app.begin_operation();
+    app.pg_config.target_db = cli.target_db.clone();
+    app.pg_config.restore_jobs = cli.restore_jobs;
+    app.pre_restore_hook = cli.pre_restore_hook.clone();
+    app.post_restore_hook = cli.post_restore_hook.clone();
+    app.row_filter_file = cli.row_filter_file.clone();
+    app.environment = cli.environment.clone();
+    app.pg_servers = cli.servers.clone();
+    app.s3_config.sse_customer_key = cli.sse_customer_key.clone().unwrap_or_default();
+    app.snapshot_browser.s3_config.sse_customer_key = app.s3_config.sse_customer_key.clone();
+    app.snapshot_browser.download_concurrency = cli.download_concurrency;
+    app.progress.budget().set_limit(cli.max_bandwidth);
+    app.snapshot_browser.encryption = client_encryption(cli);
+    app.snapshot_browser.max_keys = cli.max_keys;
+    app.snapshot_browser.listing_cache = (!cli.no_listing_cache).then(|| {
+        ListingCache::new(cli.listing_cache_dir.clone().unwrap_or_else(ListingCache::default_dir))
+    });
+    app.restore_history = (!cli.demo).then(|| RestoreHistory::in_dir(cli.listing_cache_dir.clone().unwrap_or_else(ListingCache::default_dir)));
+    app.snapshot_browser.gcs_config = cli.gcs_bucket.as_ref().map(|bucket| GcsConfig {
+        bucket: bucket.clone(),
+        prefix: cli.gcs_prefix.clone(),
+        service_account_path: cli.gcs_service_account_path.clone(),
+        service_account_key: cli.gcs_service_account_key.clone(),
+        timeouts: TimeoutConfig::default(),
+    });
+    app.snapshot_browser.azure_config = cli.azure_account.as_ref().map(|account| AzureConfig {
+        account: account.clone(),
+        container: cli.azure_container.clone().unwrap_or_default(),
+        prefix: cli.azure_prefix.clone(),
+        access_key: cli.azure_access_key.clone().unwrap_or_default(),
+        sas_token: cli.azure_sas_token.clone().unwrap_or_default(),
+        timeouts: TimeoutConfig::default(),
+    });
+    app.snapshot_browser.b2_config = cli.b2_bucket.as_ref().map(|bucket| B2Config {
+        bucket: bucket.clone(),
+        prefix: cli.b2_prefix.clone(),
+        key_id: cli.b2_key_id.clone().unwrap_or_default(),
+        application_key: cli.b2_application_key.clone().unwrap_or_default(),
+        api_url: String::new(),
+        timeouts: TimeoutConfig::default(),
+    });
+    app.snapshot_browser.sftp_config = cli.sftp_host.as_ref().map(|host| SftpConfig {
+        host: host.clone(),
+        port: cli.sftp_port,
+        username: cli.sftp_username.clone().unwrap_or_default(),
+        password: cli.sftp_password.clone(),
+        private_key_path: cli.sftp_private_key.clone(),
+        private_key_passphrase: cli.sftp_private_key_passphrase.clone(),
+        prefix: cli.sftp_prefix.clone(),
+        timeouts: TimeoutConfig::default(),
+    });
+    commands::configure_search_targets(&mut app, cli);
+    app.set_timeouts(
+        TimeoutConfig::new(cli.s3_connect_timeout, cli.s3_read_timeout, cli.s3_operation_timeout),
+        TimeoutConfig::new(cli.target_connect_timeout, cli.target_read_timeout, cli.target_operation_timeout),
+    );
+    if cli.demo {
+        rustored::demo::enable(&mut app, chrono::Utc::now());
+    }
+    app
+}
 
-    /// Browse and restore S3 snapshots using TUI
-    BrowseSnapshots,
+/// Ask for `expected`, described by `what`, to be typed back before a destructive step
+fn confirm_typed(what: &str, expected: &str) -> Result<bool> {
+    debug!("Asking for confirmation of {}", expected);
+    print!("Type {} ({}) to confirm: ", what, expected);
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim() == expected)
 }
 
 async fn connect(cli: &Cli) -> Result<Option<tokio_postgres::Client>> {
     debug!("Attempting to connect to PostgreSQL with settings: host={:?}, port={:?}, user={:?}, ssl={}", 
            cli.host, cli.port, cli.username, cli.use_ssl);
-    if !cli.host.is_some() && !cli.port.is_some() && !cli.username.is_some() && !cli.password.is_some() {
+    if cli.host.is_none() && cli.port.is_none() && cli.username.is_none() && cli.password.is_none() {
         // If no PostgreSQL settings are provided, return None
         debug!("No PostgreSQL connection settings provided, skipping connection");
         return Ok(None);
@@ -184,7 +177,7 @@ async fn connect(cli: &Cli) -> Result<Option<tokio_postgres::Client>> {
     }
 
     // Set default host and port if not provided
-    config.host(&cli.host.clone().unwrap_or_else(|| "localhost".to_string()));
+    config.host(cli.host.clone().unwrap_or_else(|| "localhost".to_string()));
     config.port(cli.port.unwrap_or(5432));
 
     if let Some(ref user) = cli.username {
@@ -229,18 +222,59 @@ async fn main() -> Result<()> {
     config::load_env();
     info!("Loaded environment variables");
 
-    let cli: Cli = Cli::parse();
-    let client = connect(&cli).await?;
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Commands::Config { action: ConfigAction::ImportEnv { name, output } } = &cli.command {
+        let path = output.clone().or_else(|| cli.presets_file.clone().map(Into::into))
+            .ok_or_else(|| anyhow::anyhow!("Pass --output or --presets-file to choose the file to write the profile to"))?;
+        let imported = rustored::profiles::from_env(name, std::env::vars())?;
+        rustored::profiles::write_profile(&path, &imported.profile)?;
+        println!("Wrote profile {} to {}", name, path.display());
+        if !imported.left_out.is_empty() {
+            println!("Left in the environment: {}", imported.left_out.join(", "));
+        }
+        return Ok(());
+    }
+
+    let presets = match &cli.presets_file {
+        Some(path) => RestorePresets::load(std::path::Path::new(path))?,
+        None => RestorePresets::default(),
+    };
+    if let Some(name) = cli.profile.clone() {
+        cli.apply_profile(presets.profile(&name)?, &matches);
+        info!("Using connection profile {}", name);
+    }
+    // Exports the last spans when main returns
+    let _telemetry = rustored::telemetry::init(cli.telemetry_config()?)?;
+    // A dry run must not touch the server
+    let client = match &cli.command {
+        Commands::RestoreFromS3 { dry_run: true, .. } => None,
+        _ => connect(&cli).await?,
+    };
+    let maintenance_hooks = MaintenanceHooks::from_specs(
+        cli.maintenance_on_hook.as_deref(),
+        cli.maintenance_off_hook.as_deref(),
+    );
+
+    // Ctrl-C and SIGTERM cancel downloads, uploads, dumps and restores cleanly
+    let cancel = cancel::CancellationToken::new();
+    cancel::cancel_on_signals(cancel.clone());
+
+    let mapping = match &cli.mapping_file {
+        Some(path) => RestoreMapping::load(std::path::Path::new(path))?,
+        None => RestoreMapping::default(),
+    };
 
     // Add PGSSLMODE environment variable if SSL is enabled
     if cli.use_ssl {
         std::env::set_var("PGSSLMODE", "require");
     }
 
+    let color = use_color(cli.no_color);
     match &cli.command {
         Commands::ChangePassword { user, password } => {
             if let Some(client) = client {
-                postgres::change_password(&client, &user, &password).await?;
+                postgres::change_password(&client, user, password).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::change_password");
                 return Ok(());
@@ -248,7 +282,12 @@ async fn main() -> Result<()> {
         }
         Commands::List => {
             if let Some(client) = client {
-                postgres::list_databases(&client).await?;
+                let mut table = Table::new(&[("NAME", Align::Left), ("OWNER", Align::Left), ("SIZE", Align::Right), ("RESTORED FROM", Align::Left)]);
+                for database in postgres::list_databases(&client).await? {
+                    let provenance = database.provenance.map(|provenance| provenance.summary()).unwrap_or_default();
+                    table.row(vec![database.name.into(), database.owner.into(), database.size.unwrap_or_default().into(), provenance.into()]);
+                }
+                print!("{}", table.render(color));
             } else {
                 error!("PostgreSQL connection required for postgres::list_databases");
                 return Ok(());
@@ -256,7 +295,7 @@ async fn main() -> Result<()> {
         }
         Commands::Create { name } => {
             if let Some(client) = client {
-                postgres::create_database(&client, &name).await?;
+                postgres::create_database(&client, name).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::create_database");
                 return Ok(());
@@ -264,7 +303,7 @@ async fn main() -> Result<()> {
         }
         Commands::Drop { name } => {
             if let Some(client) = client {
-                postgres::drop_database(&client, &name).await?;
+                postgres::drop_database(&client, name).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::drop_database");
                 return Ok(());
@@ -272,7 +311,7 @@ async fn main() -> Result<()> {
         }
         Commands::Clone { name } => {
             if let Some(client) = client {
-                postgres::clone_database(&client, &name).await?;
+                postgres::clone_database(&client, name).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::clone_database");
                 return Ok(());
@@ -280,7 +319,7 @@ async fn main() -> Result<()> {
         }
         Commands::DropForce { name } => {
             if let Some(client) = client {
-                postgres::drop_database_with_force(&client, &name).await?;
+                postgres::drop_database_with_force(&client, name).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::drop_database_with_force");
                 return Ok(());
@@ -288,7 +327,7 @@ async fn main() -> Result<()> {
         }
         Commands::Rename { old_name, new_name } => {
             if let Some(client) = client {
-                postgres::rename_database(&client, &old_name, &new_name).await?;
+                postgres::rename_database(&client, old_name, new_name).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::rename_database");
                 return Ok(());
@@ -296,84 +335,617 @@ async fn main() -> Result<()> {
         }
         Commands::SetOwner { name, owner } => {
             if let Some(client) = client {
-                postgres::set_database_owner(&client, &name, &owner).await?;
+                postgres::set_database_owner(&client, name, owner).await?;
             } else {
                 error!("PostgreSQL connection required for postgres::set_database_owner");
                 return Ok(());
             }
         }
-        Commands::Dump { name, output } => {
-            if let Some(_) = client {
+        Commands::Dump { name, output, jobs: Some(jobs), .. } => {
+            info!("Dumping database '{}' to '{}' with {} jobs", name, output, jobs);
+            let pg_config = rustored::ui::models::PostgresConfig {
+                host: cli.host.clone().or_else(|| Some("localhost".to_string())),
+                port: cli.port.or(Some(5432)),
+                username: cli.username.clone(),
+                password: cli.password.clone(),
+                use_ssl: cli.use_ssl,
+                db_name: Some(name.clone()),
+                target_db: None,
+                restore_jobs: None,
+            };
+            let dump = rustored::directory_dump::dump_directory(&pg_config, name, *jobs, std::path::Path::new(output), |bytes| {
+                eprint!("\rDumped {} of {}", humansize::format_size(bytes, humansize::DECIMAL), name);
+            });
+            cancel::cancellable(&cancel, &format!("Dump of {}", name), dump).await?;
+            eprintln!();
+            println!("Dumped {} to {}", name, output);
+        }
+        Commands::Dump { name, output, compress, compress_level, jobs: None } => {
+            if client.is_some() {
                 info!("Dumping database '{}' to '{}'", name, output);
-                backup::dump_database(
-                    &name,
-                    &output,
-                    &cli.host.clone().unwrap_or_else(|| "localhost".to_string()),
+                let compression = compress.map(|codec| rustored::compression::Compression::new(codec, *compress_level)).transpose()?;
+                let host = cli.host.clone().unwrap_or_else(|| "localhost".to_string());
+                let dump = backup::dump_database(
+                    name,
+                    output,
+                    &host,
                     cli.port.unwrap_or(5432),
                     cli.username.as_deref(),
                     cli.password.as_deref(),
                     cli.use_ssl,
-                )
-                .await?
+                    false,
+                    compression,
+                );
+                let written = cancel::cancellable(&cancel, &format!("Dump of {}", name), dump).await?;
+                println!("Dumped {} to {}", name, written);
             } else {
                 error!("PostgreSQL connection required for postgres::dump_database");
                 return Ok(());
             }
         }
-        Commands::Restore { name, input, target, es_host, es_index, qdrant_api_key } => {
+        Commands::Backup { name, label, jobs } => {
+            let app = build_app(&cli, maintenance_hooks, &cancel);
+            let mut browser = app.snapshot_browser;
+            let store = browser.store().await?;
+            let backup = backup::backup_database(store.as_ref(), &app.pg_config, browser.prefix(), name, label.as_deref(), *jobs, &browser.encryption, |bytes| {
+                eprint!("\rDumped {} of {}", humansize::format_size(bytes, humansize::DECIMAL), name);
+            });
+            let key = cancel::cancellable(&cancel, &format!("Backup of {}", name), backup).await;
+            eprintln!();
+            println!("Backed up {} to {}", name, key?);
+        }
+        Commands::BackupGlobals => {
+            let app = build_app(&cli, maintenance_hooks, &cancel);
+            let mut browser = app.snapshot_browser;
+            let store = browser.store().await?;
+            let backup = rustored::globals::backup_globals(store.as_ref(), &app.pg_config, browser.prefix(), &browser.encryption);
+            let key = cancel::cancellable(&cancel, "Backup of cluster globals", backup).await?;
+            println!("Backed up cluster globals to {}", key);
+        }
+        Commands::Schedule => {
+            let app = build_app(&cli, maintenance_hooks, &cancel);
+            let mut browser = app.snapshot_browser;
+            let store = browser.store().await?;
+            for job in &presets.schedules {
+                println!("Backing up {} on \"{}\"", job.database, job.cron);
+            }
+            for job in &presets.gc {
+                println!("Dropping throwaway restores older than {} on \"{}\"", job.older_than, job.cron);
+            }
+            println!("Running until stopped with Ctrl-C or SIGTERM");
+            let gc = tokio::spawn(rustored::gc::run_gc_schedule(app.pg_config.clone(), presets.gc.clone(), cancel.clone()));
+            // Without backup jobs only the cleanups run; with neither, run_schedule says so
+            if !presets.schedules.is_empty() || presets.gc.is_empty() {
+                schedule::run_schedule(
+                    store,
+                    app.pg_config,
+                    browser.prefix().to_string(),
+                    presets.schedules.clone(),
+                    presets.retention.clone(),
+                    browser.encryption.clone(),
+                    cancel.clone(),
+                ).await?;
+            }
+            gc.await??;
+        }
+        Commands::Restore { name, input, url, sha256, target, globals, es_host, es_index, qdrant_api_key } => {
             use rustored::datastore::DatastoreRestoreTarget;
-            let datastore = match target.as_str() {
-                "postgres" => DatastoreRestoreTarget::Postgres,
-                "elasticsearch" => DatastoreRestoreTarget::Elasticsearch {
-                    host: es_host.clone().unwrap_or_else(|| "http://localhost:9200".to_string()),
-                    index: es_index.clone().unwrap_or_else(|| name.clone()),
-                },
-                "qdrant" => DatastoreRestoreTarget::Qdrant {
-                    host: es_host.clone().unwrap_or_else(|| "http://localhost:6333".to_string()),
-                    collection: es_index.clone().unwrap_or_else(|| name.clone()),
-                    api_key: qdrant_api_key.clone(),
-                },
-                other => {
-                    error!("Unknown restore target: {}", other);
-                    return Ok(());
+            let datastore = commands::datastore_target(&cli, *target, name, es_host, es_index, qdrant_api_key, &mapping);
+            let mut options = presets.find(name).map(|preset| preset.options.clone()).unwrap_or_default();
+            options.jobs = options.jobs.or(cli.restore_jobs);
+            options.pre_hook = options.pre_hook.or_else(|| cli.pre_restore_hook.clone());
+            options.post_hook = options.post_hook.or_else(|| cli.post_restore_hook.clone());
+            options.row_filters = options.row_filters.or_else(|| cli.row_filter_file.clone());
+            info!("Restoring {} with options: {}", name, options.summary());
+            // A URL is downloaded like a snapshot, which also decrypts it
+            let downloaded = match url {
+                Some(url) => {
+                    let mut browser = build_app(&cli, maintenance_hooks.clone(), &cancel).snapshot_browser;
+                    Some(rustored::storage::download_url(&mut browser, url, sha256.as_deref()).await?)
                 }
+                None => None,
+            };
+            let input = downloaded.clone().or_else(|| input.clone()).unwrap_or_default();
+            let decrypted = match &downloaded {
+                Some(_) => None,
+                None => client_encryption(&cli).decrypt_file(std::path::Path::new(&input)).await?,
             };
-            datastore.restore(&name, &input).await?;
+            let input = &decrypted.as_ref().map_or(input.clone(), |file| file.path().to_string_lossy().to_string());
+            let pg_config = rustored::ui::models::PostgresConfig {
+                host: Some("localhost".to_string()),
+                port: Some(5432),
+                ..Default::default()
+            };
+            // Roles the dump's owners and grants name are created first
+            if let (Some(globals), DatastoreRestoreTarget::Postgres) = (globals, &datastore) {
+                let decrypted = client_encryption(&cli).decrypt_file(std::path::Path::new(globals)).await?;
+                let path = decrypted.as_ref().map_or(std::path::Path::new(globals), |file| file.path());
+                let errors = rustored::globals::apply_globals(&pg_config, path).await?;
+                println!("{}", rustored::globals::summary(globals, &errors));
+            }
+            let target_host = match &datastore {
+                DatastoreRestoreTarget::Elasticsearch { host, .. }
+                | DatastoreRestoreTarget::Qdrant { host, .. }
+                | DatastoreRestoreTarget::OpenSearch { host, .. }
+                | DatastoreRestoreTarget::Meilisearch { host, .. }
+                | DatastoreRestoreTarget::Weaviate { host, .. }
+                | DatastoreRestoreTarget::Milvus { host, .. } => host.clone(),
+                DatastoreRestoreTarget::InfluxDb { target, .. } => target.host.clone(),
+                DatastoreRestoreTarget::DynamoDb { endpoint_url, .. } => endpoint_url.clone().unwrap_or_else(|| "dynamodb".to_string()),
+                _ => pg_config.host.clone().unwrap_or_default(),
+            };
+            let vars = rustored::hooks::TemplateVars::new(name, input).with_target(&target.to_string(), &target_host);
+            let dead_letters = maintenance_hooks.run(name, async {
+                options.run_pre_hook(&vars).await?;
+                let dead_letters = cancel::cancellable(&cancel, &format!("Restore of {}", name), datastore.restore(name, input, &options)).await?;
+                if matches!(datastore, DatastoreRestoreTarget::Postgres) {
+                    rustored::provenance::record(&pg_config, &vars).await;
+                    options.run_post_sql(&pg_config, &vars).await?;
+                }
+                options.run_post_hook(&vars).await?;
+                Ok(dead_letters)
+            }).await?;
+            if let Some(report) = dead_letters {
+                println!("{}", report.summary());
+            }
+            if let Some(path) = downloaded {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        Commands::RetryFailed { file, es_host, qdrant_api_key } => {
+            let targets = commands::retry_targets(&cli, qdrant_api_key);
+            let outcome = rustored::dead_letter::retry_dead_letters(std::path::Path::new(file), es_host.as_deref(), &targets).await?;
+            println!("Loaded {} of {} records from {}", outcome.loaded(), outcome.retried, file);
+            if let Some(report) = &outcome.dead_letters {
+                println!("{}", report.summary());
+            }
         }
         Commands::BrowseSnapshots => {
-            // TUI using RustoredApp
-            enable_raw_mode()?;
-            let mut stdout = std::io::stdout();
-            execute!(stdout, EnterAlternateScreen, crossterm::event::EnableMouseCapture)?;
-            let backend = CrosstermBackend::new(stdout);
-            let mut terminal = Terminal::new(backend)?;
-            let mut app = RustoredApp::new(
-                &cli.bucket,
-                &cli.region,
-                &cli.prefix,
-                &cli.endpoint_url,
-                &cli.access_key_id,
-                &cli.secret_access_key,
-                cli.path_style,
-                &cli.host,
-                &cli.port,
-                &cli.username,
-                &cli.password,
-                cli.use_ssl,
-                &cli.db_name,
-                &cli.es_host,
-                &cli.es_index,
-                &cli.qdrant_api_key,
-            );
-
-            let res = app.run(&mut terminal).await?;
-            disable_raw_mode()?;
-            execute!(std::io::stdout(), LeaveAlternateScreen, crossterm::event::DisableMouseCapture)?;
-            terminal.show_cursor()?;
-            if let Some(snapshot_key) = res {
+            let mut app = build_app(&cli, maintenance_hooks, &cancel);
+            app.restore_presets = presets;
+            app.set_restore_mapping(mapping);
+            if let Some(path) = &cli.record_session {
+                app.recorder = Some(rustored::ui::recording::SessionRecorder::create(std::path::Path::new(path), app.demo, chrono::Utc::now())?);
+            }
+            if let Some(snapshot_key) = run_tui(&cli, &mut app).await? {
                 info!("Snapshot processed: {}", snapshot_key);
             }
         }
+        Commands::Replay { file, speed, headless } => {
+            let replay = rustored::ui::recording::SessionReplay::load(std::path::Path::new(file), *speed)?;
+            let mut app = build_app(&cli, maintenance_hooks, &cancel);
+            app.restore_presets = presets;
+            app.set_restore_mapping(mapping);
+            // Dated like the recording, so a demo-mode recording sees the same snapshots
+            rustored::demo::enable(&mut app, replay.recorded_at);
+            app.replay = Some(replay);
+            if *headless {
+                let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(160, 48))?;
+                app.run(&mut terminal).await?;
+            } else {
+                run_tui(&cli, &mut app).await?;
+            }
+            let replay = app.replay.take().ok_or_else(|| anyhow::anyhow!("Replay state was lost"))?;
+            for divergence in &replay.divergences {
+                println!("{}", divergence);
+            }
+            println!("{}", replay.summary());
+        }
+        Commands::RestoreFromS3 { key, pick, as_of, target, version_id, no_preset, jobs, drop_indexes, compare, globals, drop_on_cancel, gentle, recipes, into_existing, include_tables, exclude_tables, dry_run, shared, yes } => {
+            let mut app = build_app(&cli, maintenance_hooks, &cancel);
+            app.restore_target = (*target).into();
+            app.restore_presets = presets;
+            app.set_restore_mapping(mapping);
+
+            let snapshot = match (key, as_of) {
+                (prefix, Some(as_of)) => {
+                    let snapshot = app.snapshot_browser.find_snapshot_as_of(prefix.as_deref().unwrap_or_default(), *as_of).await?;
+                    let taken = chrono::DateTime::from_timestamp(snapshot.last_modified as i64, 0).unwrap_or_default();
+                    println!("Selected {} taken {}", snapshot.key, taken.format("%Y-%m-%d %H:%M:%S UTC"));
+                    snapshot
+                }
+                (Some(key), None) if *shared => share::open_shared(&mut app.snapshot_browser, key, cli.share_secret.as_deref().unwrap_or_default()).await?,
+                (Some(key), None) if !pick => app.snapshot_browser.find_snapshot(key, version_id.as_deref()).await?,
+                _ => {
+                    app.snapshot_browser.load_snapshots().await?;
+                    match rustored::picker::pick_snapshot(&app.snapshot_browser.snapshots)? {
+                        Some(snapshot) => snapshot,
+                        None => {
+                            println!("Restore cancelled");
+                            return Ok(());
+                        }
+                    }
+                }
+            };
+            let key = &snapshot.key;
+
+            // Seed the options like the confirmation popup, then apply the overrides
+            if !no_preset {
+                app.apply_restore_preset(&snapshot);
+            } else {
+                app.apply_restore_defaults();
+            }
+            // --jobs wins over the preset, which wins over --restore-jobs
+            app.restore_options.jobs = jobs.or(app.restore_options.jobs).or(app.pg_config.restore_jobs);
+            if let Some(drop_indexes) = drop_indexes {
+                app.restore_options.drop_indexes = *drop_indexes;
+            }
+            if let Some(compare) = compare {
+                app.restore_options.compare_live = *compare;
+            }
+            if let Some(globals) = globals {
+                app.restore_options.globals = *globals;
+            }
+            if let Some(drop_on_cancel) = drop_on_cancel {
+                app.restore_options.drop_on_cancel = *drop_on_cancel;
+            }
+            if let Some(gentle) = gentle {
+                app.restore_options.gentle = *gentle;
+            }
+            if let Some(mode) = into_existing {
+                app.restore_options.mode = *mode;
+            }
+            app.restore_options.include_tables = include_tables.clone();
+            app.restore_options.exclude_tables = exclude_tables.clone();
+            if !recipes.is_empty() {
+                app.restore_options.recipes = match recipes.as_slice() {
+                    [none] if none == "none" => Vec::new(),
+                    recipes => recipes.iter().map(|recipe| recipes::RecipeRef::parse(recipe)).collect::<Result<_>>()?,
+                };
+            }
+            info!("Restoring {} with options: {}", key, app.restore_options.summary());
+
+            if *dry_run {
+                if app.restore_target != rustored::ui::models::RestoreTarget::Postgres {
+                    return Err(anyhow::anyhow!("--dry-run previews PostgreSQL snapshots only"));
+                }
+                let path = app.snapshot_browser.download_snapshot(&snapshot, &download_ledger::temp_download_path(key)).await?
+                    .ok_or_else(|| anyhow::anyhow!("Download of {} failed: {:?}", key, app.snapshot_browser.popup_state))?;
+                let options = &app.restore_options;
+                let preview = rustored::toc::preview_snapshot(&path, &options.include_tables, &options.exclude_tables).await;
+                // The download is only needed for the listing
+                let _ = std::fs::remove_file(&path);
+                for line in preview? {
+                    println!("{}", line);
+                }
+                println!("{}", app.estimate_line(&snapshot));
+                println!("Dry run, nothing was restored");
+                return Ok(());
+            }
+
+            for line in app.mapping_lines() {
+                println!("{}", line);
+            }
+            println!("{}", app.estimate_line(&snapshot));
+            // Show what the restore changes in an existing index or collection, and confirm it
+            if let Some(impact) = app.get_current_restore_target().impact().await?.map(|impact| impact.for_snapshot(key)) {
+                for line in impact.lines() {
+                    println!("{}", line);
+                }
+                if impact.needs_typed_confirmation() && !yes && !confirm_typed(&format!("the {} name", impact.kind), &impact.name)? {
+                    println!("Restore cancelled");
+                    return Ok(());
+                }
+            }
+
+            let span = rustored::telemetry::Span::operation("restore").with("key", key).with("target", target);
+            rustored::telemetry::instrument(span, async {
+                if app.restore_options.globals && app.restore_target == rustored::ui::models::RestoreTarget::Postgres {
+                    let (globals_key, errors) = app.apply_cluster_globals(&snapshot).await?;
+                    println!("{}", rustored::globals::summary(&globals_key, &errors));
+                }
+                // Plain SQL dumps are streamed into psql, anything else is downloaded first
+                let outcome = app.restore_from_bucket(&snapshot).await?;
+                println!("Restored {} to {}", key, outcome.name);
+                if let Some(report) = &outcome.dead_letters {
+                    println!("{}", report.summary());
+                }
+                if let Some(summary) = app.change_summary(&snapshot, &outcome.name).await? {
+                    for line in summary.lines() {
+                        println!("{}", line);
+                    }
+                }
+                Ok(())
+            }).await?;
+        }
+        Commands::Promote { restored, live, transfer_ownership, yes } => {
+            let mut app = build_app(&cli, maintenance_hooks, &cancel);
+            let mut plan = promote::PromotePlan::new(live, restored, chrono::Utc::now());
+            plan.transfer_ownership = *transfer_ownership;
+            for step in plan.steps() {
+                println!("{}", step);
+            }
+            if !yes && !confirm_typed("the live database name", live)? {
+                println!("Promotion cancelled");
+                return Ok(());
+            }
+            app.promote(&plan).await?;
+            println!("Promoted {} to {}, previous database kept as {}", restored, live, plan.retired_db);
+        }
+        Commands::Rollback { live, window_hours, yes } => {
+            let mut app = build_app(&cli, maintenance_hooks, &cancel);
+            let now = chrono::Utc::now();
+            let window = chrono::Duration::try_hours(*window_hours)
+                .ok_or_else(|| anyhow::anyhow!("--window-hours {} is too long", window_hours))?;
+            // Check the history up front so the prompt describes the real swap
+            let history = promote::load_history(&mut app.snapshot_browser).await?;
+            let promotion = history.rollback_candidate(&app.pg_config.server(), live, now, window)?;
+            println!("Rename {} back to {}", live, promotion.restored_db);
+            println!("Rename {} back to {}", promotion.retired_db, live);
+            if !yes && !confirm_typed("the live database name", live)? {
+                println!("Rollback cancelled");
+                return Ok(());
+            }
+            let pg_config = app.pg_config.clone();
+            let promotion = app.maintenance_hooks
+                .run(live, promote::rollback_promotion(&mut app.snapshot_browser, &pg_config, live, window, now))
+                .await?;
+            println!("Rolled back promotion of {} from {}, promoted database kept as {}", live, promotion.promoted_at, promotion.restored_db);
+        }
+        Commands::Download { key, version_id, output, resume } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            commands::download(&mut browser, key, version_id.as_deref(), output.as_deref(), *resume).await?;
+        }
+        Commands::Healthcheck { max_age_hours, target, json } => {
+            let app = build_app(&cli, maintenance_hooks, &cancel);
+            let target = target.map(|kind| rustored::targets::create_restore_target(
+                kind,
+                app.pg_config.clone(),
+                app.es_config.clone(),
+                app.qdrant_config.clone(),
+                app.os_config.clone(),
+                app.meili_config.clone(),
+                app.weaviate_config.clone(),
+                app.milvus_config.clone(),
+                app.dynamodb_config.clone(),
+                app.influxdb_config.clone(),
+            ));
+            let report = healthcheck::run_healthcheck(app.snapshot_browser, *max_age_hours, target.as_deref()).await;
+            println!("{}", report.status_line());
+            if *json {
+                println!("{}", serde_json::to_string(&report)?);
+            }
+            std::process::exit(if report.ok() { 0 } else { 1 });
+        }
+        Commands::Target { action: TargetAction::Prepare { profile, dry_run } } => {
+            let app = build_app(&cli, maintenance_hooks, &cancel);
+            let profile = presets.target_profile(profile.as_deref())?;
+            let report = prepare::prepare_target(&app.pg_config, profile, *dry_run).await?;
+            let verb = if *dry_run { "Would" } else { "Done" };
+            for action in &report.actions {
+                println!("{}: {}", verb, action);
+            }
+            if report.actions.is_empty() {
+                println!("Target already matches profile {}", profile.name);
+            }
+            for setting in &report.pending_restart {
+                println!("Restart the server to apply {}", setting);
+            }
+        }
+        Commands::DeleteSnapshot { keys, yes } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            browser.load_snapshots().await?;
+            let snapshots = keys
+                .iter()
+                .map(|key| {
+                    browser.snapshots.iter().find(|snapshot| &snapshot.key == key).cloned()
+                        .ok_or_else(|| anyhow::anyhow!("Snapshot {} not found", key))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            for snapshot in &snapshots {
+                println!("Delete {}", snapshot.key);
+            }
+            if !yes && !confirm_typed("the word", rustored::ui::browser::DELETE_CONFIRMATION)? {
+                println!("Delete cancelled");
+                return Ok(());
+            }
+            browser.delete_snapshots(&snapshots, chrono::Utc::now().date_naive()).await?;
+            println!("Deleted {} snapshot(s)", snapshots.len());
+        }
+        Commands::Bundle { key, elasticsearch, qdrant } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            let store = browser.store().await?;
+            let bundle = bundle::Bundle { elasticsearch: elasticsearch.clone(), qdrant: qdrant.clone() };
+            let manifest = bundle::declare_bundle(store.as_ref(), key, bundle).await?;
+            match manifest.bundle {
+                Some(_) => println!("{} {}", key, manifest.lines().last().map(|line| line.to_lowercase()).unwrap_or_default()),
+                None => println!("Removed the bundle of {}", key),
+            }
+        }
+        Commands::RestoreBundle { key, qdrant_host, qdrant_collection, yes } => {
+            let mut app = build_app(&cli, maintenance_hooks, &cancel);
+            // The global Elasticsearch settings double as the Qdrant ones, which a bundle rarely shares
+            if qdrant_host.is_some() {
+                app.qdrant_config.host = qdrant_host.clone();
+            }
+            if qdrant_collection.is_some() {
+                app.qdrant_config.collection = qdrant_collection.clone();
+            }
+            app.restore_presets = presets;
+            app.set_restore_mapping(mapping);
+            app.snapshot_browser.load_snapshots().await?;
+            let manifest = bundle::find_bundle(&app.snapshot_browser.manifests, key)
+                .ok_or_else(|| anyhow::anyhow!("{} is not part of a bundle", key))?;
+            let members = bundle::resolve_members(manifest, &app.snapshot_browser.snapshots)?;
+            app.apply_restore_preset(&members[0].snapshot);
+            for line in app.bundle_lines(&members).await {
+                println!("{}", line);
+            }
+            println!("PostgreSQL options: {}", app.restore_options.summary());
+            if !yes && !confirm_typed("the word", bundle::BUNDLE_CONFIRMATION)? {
+                println!("Restore cancelled");
+                return Ok(());
+            }
+            let mut paths = Vec::new();
+            for member in &members {
+                let tmp_path = download_ledger::temp_download_path(&member.snapshot.key);
+                let path = app.snapshot_browser.download_snapshot(&member.snapshot, &tmp_path).await?
+                    .ok_or_else(|| anyhow::anyhow!("Download of {} failed: {:?}", member.snapshot.key, app.snapshot_browser.popup_state))?;
+                paths.push(std::path::PathBuf::from(path));
+            }
+            let outcomes = app.run_bundle_restore(&members, &paths).await?;
+            for (member, outcome) in members.iter().zip(outcomes) {
+                println!("{}: restored {} to {}", member.label(), member.snapshot.key, outcome.name);
+                if let Some(report) = &outcome.dead_letters {
+                    println!("{}", report.summary());
+                }
+            }
+        }
+        Commands::Prune { prefix, keep_daily, keep_weekly, keep_monthly, dry_run, yes } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            let policies = match (keep_daily, keep_weekly, keep_monthly) {
+                (None, None, None) => presets.retention
+                    .iter()
+                    .filter(|policy| prefix.as_deref().is_none_or(|prefix| policy.prefix.starts_with(prefix)))
+                    .cloned()
+                    .collect(),
+                _ => vec![RetentionPolicy {
+                    prefix: prefix.clone().unwrap_or_else(|| browser.prefix().to_string()),
+                    daily: keep_daily.unwrap_or(0),
+                    weekly: keep_weekly.unwrap_or(0),
+                    monthly: keep_monthly.unwrap_or(0),
+                }],
+            };
+            commands::prune(&mut browser, &policies, *dry_run, *yes, color).await?;
+        }
+        Commands::LintBucket { prefix, template, json } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            let template = template.as_deref().or(presets.naming_template.as_deref()).unwrap_or(lint::DEFAULT_TEMPLATE);
+            let clean = commands::lint_bucket(&mut browser, prefix.as_deref(), template, *json, color).await?;
+            std::process::exit(if clean { 0 } else { 1 });
+        }
+        Commands::Catalog { action: CatalogAction::Migrate { dry_run } } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            let report = migration::migrate(browser.store().await?.as_ref(), *dry_run, chrono::Utc::now()).await?;
+            if report.upgraded() + report.failed() > 0 {
+                print!("{}", report.table().render(color));
+            }
+            println!("{}", report.summary(*dry_run));
+            std::process::exit(if report.failed() == 0 { 0 } else { 1 });
+        }
+        Commands::Recipes { recipe: Some(recipe) } => {
+            print!("{}", recipes::RecipeRef::parse(recipe)?.render()?);
+        }
+        Commands::Recipes { recipe: None } => {
+            let mut table = Table::new(&[("NAME", Align::Left), ("PARAMETERS", Align::Left), ("DESCRIPTION", Align::Left)]);
+            for recipe in recipes::RECIPES {
+                let params: Vec<String> = recipe.params.iter().map(|param| format!("{}={}", param.name, param.default)).collect();
+                table.row(vec![recipe.name.into(), params.join(" ").into(), recipe.description.into()]);
+            }
+            print!("{}", table.render(color));
+        }
+        Commands::Hold { action } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            let today = chrono::Utc::now().date_naive();
+            match action {
+                HoldAction::Create { prefix, until, reason } => {
+                    let held = holds::place_hold(&mut browser, prefix, until, reason, today).await?;
+                    println!("Hold placed on '{}' until {}, pinning {} snapshot(s)", prefix, until, held.len());
+                    let mut table = Table::new(&[("PINNED SNAPSHOT", Align::Left)]);
+                    for key in held {
+                        table.row(vec![key.into()]);
+                    }
+                    print!("{}", table.render(color));
+                }
+                HoldAction::Release { prefix } => {
+                    let released = holds::release_hold(&mut browser, prefix).await?;
+                    println!("Released {} hold(s) on '{}'", released, prefix);
+                }
+                HoldAction::List => {
+                    browser.load_holds().await?;
+                    let mut table = Table::new(&[("PREFIX", Align::Left), ("UNTIL", Align::Left), ("STATE", Align::Left), ("REASON", Align::Left)]);
+                    for hold in &browser.holds.holds {
+                        let state = match hold.is_active(today) {
+                            true => Cell::colored("active", Color::Green),
+                            false => Cell::colored("expired", Color::DarkGrey),
+                        };
+                        table.row(vec![hold.prefix.as_str().into(), hold.until.to_string().into(), state, hold.reason.as_str().into()]);
+                    }
+                    print!("{}", table.render(color));
+                }
+            }
+        }
+        Commands::Share { action } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            commands::share(&mut browser, action, cli.share_secret.as_deref().unwrap_or_default(), color).await?;
+        }
+        Commands::Gc { older_than, dry_run, yes } => {
+            let pg_config = build_app(&cli, maintenance_hooks, &cancel).pg_config;
+            commands::gc(&pg_config, older_than, *dry_run, *yes, color).await?;
+        }
+        // Handled before the presets file is loaded, since it may not exist yet
+        Commands::Config { .. } => {}
+        Commands::Wal { action } => {
+            let app = build_app(&cli, maintenance_hooks, &cancel);
+            let mut browser = app.snapshot_browser;
+            match action {
+                WalAction::Push { path, compress } => {
+                    let compression = compress.map(|codec| rustored::compression::Compression::new(codec, None)).transpose()?;
+                    println!("Archived {}", wal::push(&mut browser, path, compression).await?);
+                }
+                WalAction::Fetch { file_name, output } => {
+                    println!("Restored {}", wal::fetch(&mut browser, file_name, output).await?);
+                }
+                WalAction::BaseBackup => {
+                    let key = cancel::cancellable(&cancel, "Base backup", wal::base_backup(&mut browser, &app.pg_config)).await?;
+                    println!("Took base backup {}", key);
+                }
+                WalAction::List => {
+                    browser.load_snapshots().await?;
+                    let mut table = Table::new(&[("BASE BACKUP", Align::Left), ("WAL", Align::Right), ("SIZE", Align::Right), ("RECOVERS TO", Align::Left), ("MISSING", Align::Left)]);
+                    for chain in browser.wal_chains.values() {
+                        let size = humansize::format_size(chain.size().max(0) as u64, humansize::DECIMAL);
+                        let missing = match chain.missing.as_slice() {
+                            [] => Cell::from(""),
+                            [first, ..] => Cell::colored(format!("{} from {}", chain.missing.len(), first), Color::Red),
+                        };
+                        let end = chain.last_segment().unwrap_or("start of backup");
+                        table.row(vec![chain.base.key.as_str().into(), chain.segments.len().to_string().into(), size.into(), end.into(), missing]);
+                    }
+                    print!("{}", table.render(color));
+                }
+                WalAction::Recover { key, target_time, data_dir, port } => {
+                    let recovery = commands::recover(&mut browser, key, target_time, data_dir.clone(), *port);
+                    println!("{}", cancel::cancellable(&cancel, "Recovery", recovery).await?.summary());
+                }
+            }
+        }
+        Commands::Verify { keys, workers, json, .. } if keys.is_empty() => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            let reports = chains::verify_chains(&mut browser, *workers).await?;
+            print!("{}", chains::table(&reports).render(color));
+            if *json {
+                println!("{}", serde_json::to_string(&reports)?);
+            }
+            std::process::exit(if reports.iter().all(|report| report.is_ok()) { 0 } else { 1 });
+        }
+        Commands::Verify { keys, quick, json, .. } => {
+            let mut browser = build_app(&cli, maintenance_hooks, &cancel).snapshot_browser;
+            browser.load_snapshots().await?;
+            let mut reports = Vec::new();
+            // Every snapshot is checked, so one failure does not hide the others from CI
+            for key in keys {
+                let Some(snapshot) = browser.snapshots.iter().find(|snapshot| &snapshot.key == key).cloned() else {
+                    reports.push(verification::VerifyReport::from_error(key, &anyhow::anyhow!("snapshot not found")));
+                    continue;
+                };
+                let report = match quick {
+                    true => verification::quick_verify_snapshot(&mut browser, &snapshot).await,
+                    false => verification::verify_snapshot(&mut browser, &snapshot, chrono::Utc::now())
+                        .await
+                        .map(|result| verification::VerifyReport::from_verification(key, &result)),
+                };
+                let report = report.unwrap_or_else(|e| verification::VerifyReport::from_error(key, &e));
+                info!("{}", report.line());
+                reports.push(report);
+            }
+            print!("{}", verification::table(&reports).render(color));
+            println!("{}", verification::summarize(&reports));
+            if *json {
+                println!("{}", serde_json::to_string(&reports)?);
+            }
+            std::process::exit(if reports.iter().all(|report| report.passed) { 0 } else { 1 });
+        }
     }
 
     Ok(())
@@ -0,0 +1,188 @@
+// This module contains backup manifests for the Rustored application
+// Every backup rustored uploads gets a sidecar JSON manifest next to it, at
+// `<snapshot key>.backup.json`, recording the dump's SHA-256 checksum,
+// format, the pg_dump version that wrote it, the source database and how long
+// the dump took. Object metadata differs between storage backends and cannot
+// hold all of this everywhere, so the manifest is a plain object that every
+// backend can store. The snapshot browser reads manifests to show the source
+// of each snapshot in the list and the full record before a restore.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::storage::SnapshotStore;
+
+/// Suffix added to a snapshot key to name its manifest
+///
+/// Differs from the `.manifest.json` of incremental chain links (see
+/// `chains`), which describe backups taken by other tools.
+pub const MANIFEST_SUFFIX: &str = ".backup.json";
+
+/// Record of how a snapshot was taken
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Key of the snapshot the manifest describes
+    pub key: String,
+    /// Hex encoded SHA-256 checksum of the uploaded dump
+    pub sha256: String,
+    /// Size of the dump in bytes
+    pub size: u64,
+    /// Dump format, e.g. `custom` for a pg_dump custom format archive
+    pub format: String,
+    /// Version of the pg_dump that wrote the dump, e.g. `16.2`
+    #[serde(default)]
+    pub pg_dump_version: Option<String>,
+    /// Database the dump was taken from
+    pub database: String,
+    /// When the dump started
+    pub started: DateTime<Utc>,
+    /// How long the dump took, in seconds
+    pub duration_seconds: f64,
+    /// Elasticsearch and Qdrant snapshots restored together with this one, see `bundle`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<crate::bundle::Bundle>,
+}
+
+impl Manifest {
+    /// Manifest of a custom format dump written to `path`
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key the dump is uploaded under
+    /// * `database` - Database the dump was taken from
+    /// * `path` - The dump file, checksummed here
+    /// * `started` - When the dump started
+    /// * `finished` - When the dump finished
+    pub fn for_dump(key: &str, database: &str, path: &Path, started: DateTime<Utc>, finished: DateTime<Utc>) -> Result<Self> {
+        debug!("Building manifest of {} for {}", key, database);
+        Ok(Self {
+            key: key.to_string(),
+            sha256: crate::verification::file_checksum(path)?,
+            size: std::fs::metadata(path)?.len(),
+            format: "custom".to_string(),
+            pg_dump_version: pg_dump_version(),
+            database: database.to_string(),
+            started,
+            duration_seconds: (finished - started).num_milliseconds() as f64 / 1000.0,
+            bundle: None,
+        })
+    }
+
+    /// Parse a manifest from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing manifest ({} bytes)", bytes.len());
+        crate::migration::from_json(crate::migration::MetadataKind::Manifest, bytes)
+    }
+
+    /// Serialize the manifest to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing manifest of {}", self.key);
+        crate::migration::to_json(self)
+    }
+
+    /// Short description for the snapshot list, e.g. `orders (pg_dump 16.2)`
+    pub fn source(&self) -> String {
+        debug!("Describing source of {}", self.key);
+        match &self.pg_dump_version {
+            Some(version) => format!("{} (pg_dump {})", self.database, version),
+            None => self.database.clone(),
+        }
+    }
+
+    /// Lines describing the snapshot for the restore confirmation
+    pub fn lines(&self) -> Vec<String> {
+        debug!("Formatting manifest lines of {}", self.key);
+        let version = self.pg_dump_version.as_deref().unwrap_or("unknown");
+        let mut lines = vec![
+            format!("Source: {}, {} format, pg_dump {}", self.database, self.format, version),
+            format!(
+                "Dumped {} in {:.1}s",
+                self.started.format("%Y-%m-%d %H:%M:%S UTC"),
+                self.duration_seconds
+            ),
+            format!("SHA-256: {}", self.sha256),
+        ];
+        if let Some(bundle) = &self.bundle {
+            let members: Vec<&str> = bundle.keys(&self.key).into_iter().skip(1).map(|(_, key)| key).collect();
+            lines.push(format!("Bundled with {}", members.join(", ")));
+        }
+        lines
+    }
+}
+
+/// Key of the manifest of the snapshot stored under `key`
+pub fn manifest_key(key: &str) -> String {
+    debug!("Building manifest key of {}", key);
+    format!("{}{}", key, MANIFEST_SUFFIX)
+}
+
+/// Whether an object key names a manifest rather than a snapshot
+pub fn is_manifest_key(key: &str) -> bool {
+    debug!("Checking if {} is a manifest", key);
+    key.ends_with(MANIFEST_SUFFIX)
+}
+
+/// Version of the installed pg_dump, e.g. `16.2`, or `None` if it cannot be run
+pub fn pg_dump_version() -> Option<String> {
+    debug!("Reading pg_dump version");
+    let output = std::process::Command::new("pg_dump").arg("--version").output().ok()?;
+    // Prints e.g. "pg_dump (PostgreSQL) 16.2 (Debian 16.2-1.pgdg120+2)"
+    let text = String::from_utf8_lossy(&output.stdout);
+    let version = text.trim().strip_prefix("pg_dump (PostgreSQL) ").unwrap_or(text.trim());
+    version.split_whitespace().next().map(String::from)
+}
+
+/// Write a manifest next to its snapshot
+pub async fn write_manifest(store: &dyn SnapshotStore, manifest: &Manifest) -> Result<()> {
+    debug!("Writing manifest of {}", manifest.key);
+    store.write_object(&manifest_key(&manifest.key), manifest.to_json()?).await
+}
+
+/// Write the manifest of an uploaded dump, logging rather than failing
+///
+/// The snapshot is usable without its manifest, so a backup that uploaded
+/// is not failed because the manifest could not be written.
+///
+/// # Arguments
+///
+/// * `store` - Storage backend the dump was uploaded to
+/// * `key` - Key the dump was uploaded under
+/// * `database` - Database the dump was taken from
+/// * `path` - The dump file
+/// * `started` - When the dump started
+/// * `finished` - When the dump finished
+pub async fn record_backup(
+    store: &dyn SnapshotStore,
+    key: &str,
+    database: &str,
+    path: &Path,
+    started: DateTime<Utc>,
+    finished: DateTime<Utc>,
+) -> Option<Manifest> {
+    debug!("Recording manifest of backup {}", key);
+    let manifest = match Manifest::for_dump(key, database, path, started, finished) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            warn!("Failed to build manifest of {}: {}", key, e);
+            return None;
+        }
+    };
+    match write_manifest(store, &manifest).await {
+        Ok(()) => {
+            info!("Wrote manifest of {}", key);
+            Some(manifest)
+        }
+        Err(e) => {
+            warn!("Failed to write manifest of {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Read the manifest of a snapshot, returning `None` if it has none
+pub async fn read_manifest(store: &dyn SnapshotStore, key: &str) -> Result<Option<Manifest>> {
+    debug!("Reading manifest of {}", key);
+    store.read_object(&manifest_key(key)).await?.map(|bytes| Manifest::from_json(&bytes)).transpose()
+}
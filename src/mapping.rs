@@ -0,0 +1,208 @@
+// This module contains restore mappings for the Rustored application
+// Restoring production data into another environment usually needs different
+// names: `prod-logs-*` indices restored as `staging-logs-*`, or a field renamed
+// to match the target's schema. A mapping file, given with `--mapping-file`,
+// renames the Elasticsearch index or Qdrant collection restored into and the
+// fields of each document's source or point's payload as they are loaded.
+//
+// ```toml
+// [[name]]
+// from = "prod-logs-*"
+// to = "staging-logs-*"
+//
+// [[field]]
+// from = "customer.email"
+// to = "customer.contact_email"
+// ```
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use crate::config_schema::{Field, Kind, TableSchema};
+
+/// Renames indices or collections matching `from`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameRule {
+    /// Name or pattern with at most one `*`, e.g. `prod-logs-*`
+    pub from: String,
+    /// New name; a `*` is replaced by what the `*` of `from` matched
+    pub to: String,
+}
+
+impl NameRule {
+    /// The new name for `name`, or `None` if the rule does not match it
+    pub fn apply(&self, name: &str) -> Option<String> {
+        debug!("Applying name rule {} -> {} to {}", self.from, self.to, name);
+        match self.from.split_once('*') {
+            None => (name == self.from).then(|| self.to.clone()),
+            Some((prefix, suffix)) => {
+                let matched = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                // `prefix` and `suffix` must not overlap in `name`
+                (name.len() >= prefix.len() + suffix.len()).then(|| self.to.replacen('*', matched, 1))
+            }
+        }
+    }
+}
+
+/// Renames a field, given as a dotted path into nested objects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldRule {
+    pub from: String,
+    pub to: String,
+}
+
+/// Schema of a `[[name]]` rule
+const NAME_RULE_SCHEMA: TableSchema = TableSchema {
+    name: "[[name]]",
+    fields: &[Field::required("from", Kind::String), Field::required("to", Kind::String)],
+    requires: &[],
+    // Only the first rule matching a name applies
+    unique: Some("from"),
+};
+
+/// Schema of a `[[field]]` rule
+const FIELD_RULE_SCHEMA: TableSchema = TableSchema {
+    name: "[[field]]",
+    fields: &[Field::required("from", Kind::String), Field::required("to", Kind::String)],
+    requires: &[],
+    unique: None,
+};
+
+/// Schema of the mapping file, checked before it is deserialized
+pub const SCHEMA: TableSchema = TableSchema {
+    name: "the mapping file",
+    fields: &[Field::optional("name", Kind::Tables(&NAME_RULE_SCHEMA)), Field::optional("field", Kind::Tables(&FIELD_RULE_SCHEMA))],
+    requires: &[],
+    unique: None,
+};
+
+/// Renames applied to an Elasticsearch or Qdrant restore
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RestoreMapping {
+    /// Index and collection renames; the first matching rule applies
+    #[serde(default, rename = "name")]
+    pub names: Vec<NameRule>,
+    /// Field renames, applied in order
+    #[serde(default, rename = "field")]
+    pub fields: Vec<FieldRule>,
+}
+
+impl RestoreMapping {
+    /// Parse a mapping from TOML, checking its patterns
+    pub fn parse(contents: &str) -> Result<Self> {
+        debug!("Parsing restore mapping");
+        crate::config_schema::validate(contents, &SCHEMA, "restore mapping")?;
+        let mapping: Self = toml::from_str(contents).map_err(|e| anyhow!("Invalid restore mapping: {}", e))?;
+        for rule in &mapping.names {
+            if rule.from.matches('*').count() > 1 || rule.to.matches('*').count() > 1 {
+                return Err(anyhow!("Invalid name rule {} -> {}: use at most one '*'", rule.from, rule.to));
+            }
+            if rule.to.contains('*') && !rule.from.contains('*') {
+                return Err(anyhow!("Invalid name rule {} -> {}: '*' in 'to' needs one in 'from'", rule.from, rule.to));
+            }
+        }
+        if let Some(rule) = mapping.fields.iter().find(|rule| rule.from.is_empty() || rule.to.is_empty()) {
+            return Err(anyhow!("Invalid field rule '{}' -> '{}': both fields must be named", rule.from, rule.to));
+        }
+        Ok(mapping)
+    }
+
+    /// Load a mapping from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading restore mapping from {:?}", path);
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read restore mapping {}: {}", path.display(), e))?;
+        let mapping = Self::parse(&contents).with_context(|| format!("Failed to load {}", path.display()))?;
+        info!("Loaded {} name and {} field rules from {}", mapping.names.len(), mapping.fields.len(), path.display());
+        Ok(mapping)
+    }
+
+    /// Whether the mapping renames nothing
+    pub fn is_empty(&self) -> bool {
+        debug!("Checking if restore mapping is empty");
+        self.names.is_empty() && self.fields.is_empty()
+    }
+
+    /// The name to restore into in place of `name`
+    pub fn map_name(&self, name: &str) -> String {
+        debug!("Mapping name {}", name);
+        self.names.iter().find_map(|rule| rule.apply(name)).unwrap_or_else(|| name.to_string())
+    }
+
+    /// Rename the fields of an Elasticsearch document, a search hit's `_source` or a bare document
+    pub fn map_document(&self, mut document: Value) -> Value {
+        debug!("Mapping document fields");
+        match document.get_mut("_source") {
+            Some(source) => self.rename_fields(source),
+            None => self.rename_fields(&mut document),
+        }
+        document
+    }
+
+    /// Rename the fields of a Qdrant point's payload
+    pub fn map_point(&self, mut point: Value) -> Value {
+        debug!("Mapping point payload fields");
+        if let Some(payload) = point.get_mut("payload") {
+            self.rename_fields(payload);
+        }
+        point
+    }
+
+    /// Apply the field rules to an object, skipping fields it does not have
+    fn rename_fields(&self, object: &mut Value) {
+        debug!("Renaming fields with {} rules", self.fields.len());
+        for rule in &self.fields {
+            if let Some(value) = take_path(object, &rule.from) {
+                put_path(object, &rule.to, value);
+            }
+        }
+    }
+
+    /// Lines describing what the mapping does to a restore into `name`, for confirmation
+    pub fn lines(&self, name: &str) -> Vec<String> {
+        debug!("Formatting restore mapping lines for {}", name);
+        let mut lines = Vec::new();
+        let mapped = self.map_name(name);
+        if mapped != name {
+            lines.push(format!("Mapping renames {} to {}", name, mapped));
+        }
+        if !self.fields.is_empty() {
+            let renames: Vec<String> = self.fields.iter().map(|rule| format!("{} -> {}", rule.from, rule.to)).collect();
+            lines.push(format!("Renaming fields: {}", renames.join(", ")));
+        }
+        lines
+    }
+}
+
+/// Remove the value at a dotted path, if there is one
+fn take_path(object: &mut Value, path: &str) -> Option<Value> {
+    debug!("Taking field {}", path);
+    let (parents, leaf) = match path.rsplit_once('.') {
+        Some((parents, leaf)) => (Some(parents), leaf),
+        None => (None, path),
+    };
+    let mut parent = object;
+    for key in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        parent = parent.get_mut(key)?;
+    }
+    parent.as_object_mut()?.remove(leaf)
+}
+
+/// Set the value at a dotted path, creating objects along it as needed
+fn put_path(object: &mut Value, path: &str, value: Value) {
+    debug!("Putting field {}", path);
+    let mut keys: Vec<&str> = path.split('.').collect();
+    let leaf = keys.pop().unwrap_or(path);
+    let mut parent = object;
+    for key in keys {
+        let Some(map) = parent.as_object_mut() else {
+            return;
+        };
+        parent = map.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    }
+    if let Some(map) = parent.as_object_mut() {
+        map.insert(leaf.to_string(), value);
+    }
+}
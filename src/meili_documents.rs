@@ -0,0 +1,247 @@
+// This module contains the Meilisearch document loader for the Rustored application
+// Meilisearch snapshots are newline-delimited JSON, one document per line, as
+// written by a documents export. Meilisearch indexes documents asynchronously:
+// every request only enqueues a task, so the loader sends the documents in
+// NDJSON batches and polls each batch's task until it has been processed,
+// reporting progress as tasks finish. A failed task rejects its whole batch,
+// whose documents go to a dead-letter file with the task's error.
+//
+// Every index has a primary key. An existing index keeps its own; a new one
+// takes the configured key, or the `id` field of the first document, or its
+// only field ending in `id`, since Meilisearch refuses documents when it
+// cannot tell the key itself.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Duration;
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+use crate::mapping::RestoreMapping;
+
+/// Documents sent in one request
+pub const DOCUMENT_BATCH_SIZE: usize = 1000;
+
+/// Batches enqueued but not yet processed at once
+const MAX_PENDING_TASKS: usize = 4;
+
+/// How often an enqueued task is checked
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Build a request, adding the API key when one is set
+fn request(client: &reqwest::Client, method: reqwest::Method, url: &str, api_key: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client.request(method, url);
+    match api_key {
+        Some(key) => request.bearer_auth(key),
+        None => request,
+    }
+}
+
+/// The primary key Meilisearch would need for a document
+///
+/// `id` wins, otherwise the one field whose name ends in `id`, ignoring case.
+///
+/// # Returns
+///
+/// The field, or an error naming the candidates when there is not exactly one
+pub fn detect_primary_key(document: &Value) -> Result<String> {
+    debug!("Detecting primary key of a document");
+    let fields = document.as_object().ok_or_else(|| anyhow!("Documents must be JSON objects"))?;
+    if fields.contains_key("id") {
+        return Ok("id".to_string());
+    }
+    let candidates: Vec<&String> = fields.keys().filter(|field| field.to_lowercase().ends_with("id")).collect();
+    match candidates.as_slice() {
+        [field] => Ok(field.to_string()),
+        [] => Err(anyhow!("No field of the documents looks like a primary key; set one with --meili-primary-key")),
+        _ => Err(anyhow!(
+            "Several fields could be the primary key ({}); set one with --meili-primary-key",
+            candidates.iter().map(|field| field.as_str()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// The enqueued task's ID from an asynchronous response
+async fn task_uid(response: reqwest::Response, what: &str) -> Result<u64> {
+    debug!("Reading task of {}", what);
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let reason = body["message"].as_str().map(String::from).unwrap_or_else(|| status.to_string());
+        return Err(anyhow!("Meilisearch refused to {}: {}", what, reason));
+    }
+    body["taskUid"].as_u64().ok_or_else(|| anyhow!("Meilisearch returned no task for {}", what))
+}
+
+/// Wait for a task to be processed
+///
+/// # Returns
+///
+/// `Ok(Err(reason))` when the task failed or was canceled, and an error when
+/// it could not be checked
+pub async fn wait_for_task(client: &reqwest::Client, host: &str, api_key: Option<&str>, uid: u64) -> Result<std::result::Result<(), String>> {
+    debug!("Waiting for Meilisearch task {}", uid);
+    let url = format!("{}/tasks/{}", host, uid);
+    loop {
+        let response = request(client, reqwest::Method::GET, &url, api_key).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Meilisearch returned {} for task {}", response.status(), uid));
+        }
+        let task: Value = response.json().await?;
+        match task["status"].as_str() {
+            Some("succeeded") => return Ok(Ok(())),
+            Some("failed") => {
+                let error = &task["error"];
+                return Ok(Err(match (error["code"].as_str(), error["message"].as_str()) {
+                    (Some(code), Some(message)) => format!("{}: {}", code, message),
+                    _ => error.to_string(),
+                }));
+            }
+            Some("canceled") => return Ok(Err(format!("task {} was canceled", uid))),
+            _ => tokio::time::sleep(TASK_POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// Make sure the index exists, returning the primary key documents are sent with
+///
+/// An existing index with a primary key keeps it, and a configured key that
+/// differs is an error rather than a silent change. Otherwise the configured
+/// key is used, or one detected from `first`, and a missing index is created
+/// with it.
+async fn ensure_index(
+    client: &reqwest::Client,
+    host: &str,
+    index: &str,
+    api_key: Option<&str>,
+    primary_key: Option<&str>,
+    first: &Value,
+) -> Result<String> {
+    debug!("Ensuring index {} exists", index);
+    let response = request(client, reqwest::Method::GET, &format!("{}/indexes/{}", host, index), api_key).send().await?;
+    let existing = match response.status() {
+        status if status.is_success() => Some(response.json::<Value>().await?["primaryKey"].as_str().map(String::from)),
+        reqwest::StatusCode::NOT_FOUND => None,
+        status => return Err(anyhow!("Meilisearch returned {} for index {}", status, index)),
+    };
+    if let Some(Some(existing)) = &existing {
+        if primary_key.is_some_and(|key| key != existing) {
+            return Err(anyhow!("Index {} has primary key {}, not {}", index, existing, primary_key.unwrap_or_default()));
+        }
+        return Ok(existing.clone());
+    }
+    let primary_key = match primary_key {
+        Some(key) => key.to_string(),
+        None => detect_primary_key(first).with_context(|| format!("Cannot tell the primary key of {}", index))?,
+    };
+    if existing.is_none() {
+        info!("Creating index {} with primary key {}", index, primary_key);
+        let response = request(client, reqwest::Method::POST, &format!("{}/indexes", host), api_key)
+            .json(&json!({ "uid": index, "primaryKey": primary_key }))
+            .send()
+            .await?;
+        let uid = task_uid(response, &format!("create index {}", index)).await?;
+        wait_for_task(client, host, api_key, uid).await?
+            .map_err(|reason| anyhow!("Failed to create index {}: {}", index, reason))?;
+    }
+    Ok(primary_key)
+}
+
+/// A batch whose task has not finished yet
+struct PendingBatch {
+    task: u64,
+    documents: Vec<Value>,
+    /// Bytes of the snapshot read up to the end of the batch
+    read_bytes: u64,
+}
+
+/// Stream an NDJSON snapshot of documents into an index
+///
+/// The index is created if it does not exist. Documents of failed tasks, and
+/// lines that are not JSON, are written to `dead_letters` and do not fail the
+/// load.
+///
+/// # Arguments
+///
+/// * `client` - HTTP client, carrying the target timeouts
+/// * `host` - Meilisearch URL
+/// * `index` - Index to load the documents into
+/// * `api_key` - Meilisearch API key, if any
+/// * `primary_key` - Primary key for a new index, detected from the documents if `None`
+/// * `path` - The snapshot file
+/// * `mapping` - Field renames applied to each document before it is sent
+/// * `progress` - Called with the share of the file processed by Meilisearch
+/// * `dead_letters` - Receives the documents that could not be indexed
+///
+/// # Returns
+///
+/// The number of documents indexed
+#[allow(clippy::too_many_arguments)]
+pub async fn add_documents(
+    client: &reqwest::Client,
+    host: &str,
+    index: &str,
+    api_key: Option<&str>,
+    primary_key: Option<&str>,
+    path: &Path,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Adding documents from {:?} to {}", path, index);
+    let host = host.trim_end_matches('/');
+    let mut snapshot = NdjsonBatches::open(path, DOCUMENT_BATCH_SIZE).await?;
+    let total = snapshot.size();
+
+    let mut documents_url = None;
+    let mut pending: VecDeque<PendingBatch> = VecDeque::new();
+    let mut indexed = 0u64;
+    let mut finish = |done: PendingBatch, result: std::result::Result<(), String>, dead_letters: &mut DeadLetterWriter| -> Result<()> {
+        match result {
+            Ok(()) => indexed += done.documents.len() as u64,
+            Err(reason) => {
+                warn!("Meilisearch rejected a batch of {} documents: {}", done.documents.len(), reason);
+                for document in done.documents {
+                    dead_letters.write(document, &reason)?;
+                }
+            }
+        }
+        if let Some(progress) = progress {
+            progress(done.read_bytes as f32 / total as f32);
+        }
+        Ok(())
+    };
+
+    while let Some(batch) = snapshot.next_batch(dead_letters).await? {
+        let batch: Vec<Value> = batch.into_iter().map(|document| mapping.map_document(document)).collect();
+        let url = match &documents_url {
+            Some(url) => url,
+            None => {
+                let key = ensure_index(client, host, index, api_key, primary_key, &batch[0]).await?;
+                documents_url.insert(format!("{}/indexes/{}/documents?primaryKey={}", host, index, key))
+            }
+        };
+        while pending.len() >= MAX_PENDING_TASKS {
+            if let Some(done) = pending.pop_front() {
+                let result = wait_for_task(client, host, api_key, done.task).await?;
+                finish(done, result, dead_letters)?;
+            }
+        }
+        let body: String = batch.iter().map(|document| format!("{}\n", document)).collect();
+        let response = request(client, reqwest::Method::POST, url, api_key)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Adding documents to {} failed: {}", index, e))?;
+        let task = task_uid(response, &format!("add documents to {}", index)).await?;
+        pending.push_back(PendingBatch { task, documents: batch, read_bytes: snapshot.read_bytes() });
+    }
+    while let Some(done) = pending.pop_front() {
+        let result = wait_for_task(client, host, api_key, done.task).await?;
+        finish(done, result, dead_letters)?;
+    }
+    info!("Indexed {} documents into {}, {} rejected", indexed, index, dead_letters.count());
+    Ok(indexed)
+}
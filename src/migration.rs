@@ -0,0 +1,268 @@
+// This module contains metadata format migrations for the Rustored application
+// Manifests and the `.rustored/` catalogs are JSON objects in the bucket that
+// outlive the rustored that wrote them. Each is stamped with a
+// `format_version`; objects without one are version 1. Reading an object runs
+// the migrations from its version up in memory, so an older bucket keeps
+// working straight away, and `catalog migrate` writes the upgraded objects back,
+// keeping a copy of each old one under `.rustored/migration-backups/`. An object
+// from a newer rustored is refused rather than half understood.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use crate::storage::SnapshotStore;
+use crate::table::{Align, Table};
+
+/// Format version this build writes
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Field holding the format version of a metadata object
+pub const VERSION_FIELD: &str = "format_version";
+
+/// Prefix old objects are copied under before `catalog migrate` overwrites them
+pub const BACKUP_PREFIX: &str = ".rustored/migration-backups/";
+
+/// The kinds of metadata object rustored keeps in a bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataKind {
+    Manifest,
+    Holds,
+    Verifications,
+    Shared,
+    Promotions,
+    Schedule,
+}
+
+impl MetadataKind {
+    /// Kind of the metadata object stored under `key`, if it is one
+    pub fn of_key(key: &str) -> Option<Self> {
+        debug!("Finding metadata kind of {}", key);
+        match key {
+            crate::holds::CATALOG_KEY => Some(Self::Holds),
+            crate::verification::VERIFICATION_CATALOG_KEY => Some(Self::Verifications),
+            crate::share::SHARED_CATALOG_KEY => Some(Self::Shared),
+            crate::promote::PROMOTION_HISTORY_KEY => Some(Self::Promotions),
+            crate::schedule::STATUS_KEY => Some(Self::Schedule),
+            _ if key.starts_with(BACKUP_PREFIX) => None,
+            _ if crate::manifest::is_manifest_key(key) => Some(Self::Manifest),
+            _ => None,
+        }
+    }
+
+    /// Name of the kind in messages, e.g. `hold catalog`
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Manifest => "manifest",
+            Self::Holds => "hold catalog",
+            Self::Verifications => "verification catalog",
+            Self::Shared => "shared catalog",
+            Self::Promotions => "promotion history",
+            Self::Schedule => "schedule status",
+        }
+    }
+}
+
+/// A step upgrading metadata objects from one format version to the next
+struct Migration {
+    /// Version the step upgrades from
+    from: u32,
+    /// What the step changes, shown by `catalog migrate`
+    description: &'static str,
+    apply: fn(MetadataKind, &mut Map<String, Value>),
+}
+
+/// Every migration, in version order; add a step here whenever a format changes meaning
+const MIGRATIONS: &[Migration] = &[
+    // Version 1 objects are the unstamped ones written before formats were versioned
+    Migration { from: 1, description: "stamp the format version", apply: |_, _| {} },
+];
+
+/// Format version of a parsed metadata object, 1 when it has none
+pub fn version_of(value: &Value) -> u32 {
+    debug!("Reading format version of a metadata object");
+    value.get(VERSION_FIELD).and_then(Value::as_u64).map_or(1, |version| version as u32)
+}
+
+/// Upgrade a parsed metadata object to `FORMAT_VERSION` in place
+///
+/// # Arguments
+///
+/// * `kind` - What the object is
+/// * `value` - The parsed object
+///
+/// # Returns
+///
+/// The descriptions of the migrations applied, empty if it was current
+pub fn upgrade(kind: MetadataKind, value: &mut Value) -> Result<Vec<&'static str>> {
+    debug!("Upgrading {} to format version {}", kind.describe(), FORMAT_VERSION);
+    let version = version_of(value);
+    if version > FORMAT_VERSION {
+        return Err(anyhow!(
+            "This {} has format version {}, written by a newer rustored; this one reads up to version {}, upgrade rustored to use it",
+            kind.describe(), version, FORMAT_VERSION,
+        ));
+    }
+    let object = value.as_object_mut().ok_or_else(|| anyhow!("This {} is not a JSON object", kind.describe()))?;
+    let applied: Vec<&'static str> = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.from >= version)
+        .map(|migration| {
+            (migration.apply)(kind, object);
+            migration.description
+        })
+        .collect();
+    object.insert(VERSION_FIELD.to_string(), Value::from(FORMAT_VERSION));
+    Ok(applied)
+}
+
+/// A metadata object stamped with the current format version when serialized
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    format_version: u32,
+    #[serde(flatten)]
+    inner: &'a T,
+}
+
+/// Serialize a metadata object as pretty JSON stamped with `FORMAT_VERSION`
+pub fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    debug!("Serializing metadata object at format version {}", FORMAT_VERSION);
+    Ok(serde_json::to_vec_pretty(&Versioned { format_version: FORMAT_VERSION, inner: value })?)
+}
+
+/// Parse a metadata object of any format version this build reads
+///
+/// # Arguments
+///
+/// * `kind` - What the object is, for the migrations and messages
+/// * `bytes` - The object's JSON
+pub fn from_json<T: DeserializeOwned>(kind: MetadataKind, bytes: &[u8]) -> Result<T> {
+    debug!("Parsing {} ({} bytes)", kind.describe(), bytes.len());
+    let context = || format!("Failed to parse {}", kind.describe());
+    let mut value: Value = serde_json::from_slice(bytes).with_context(context)?;
+    upgrade(kind, &mut value).with_context(context)?;
+    serde_json::from_value(value).with_context(context)
+}
+
+/// What migrating one metadata object did, or would do
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStep {
+    pub key: String,
+    pub kind: MetadataKind,
+    pub from: u32,
+    /// Descriptions of the migrations applied; empty when the object was current
+    pub changes: Vec<&'static str>,
+    /// Why the object could not be migrated, if it could not
+    pub error: Option<String>,
+}
+
+/// Result of `catalog migrate`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationReport {
+    pub steps: Vec<MigrationStep>,
+    /// Prefix the old objects were copied under, unless it was a dry run
+    pub backup_prefix: Option<String>,
+}
+
+impl MigrationReport {
+    /// Objects that were, or would be, upgraded
+    pub fn upgraded(&self) -> usize {
+        debug!("Counting upgraded metadata objects");
+        self.steps.iter().filter(|step| step.error.is_none() && !step.changes.is_empty()).count()
+    }
+
+    /// Objects that could not be migrated
+    pub fn failed(&self) -> usize {
+        debug!("Counting metadata objects that failed to migrate");
+        self.steps.iter().filter(|step| step.error.is_some()).count()
+    }
+
+    /// Every object that needed or failed a migration, as a table
+    pub fn table(&self) -> Table {
+        debug!("Building migration report table");
+        let mut table = Table::new(&[("KEY", Align::Left), ("KIND", Align::Left), ("FROM", Align::Right), ("TO", Align::Right), ("CHANGES", Align::Left)]);
+        for step in self.steps.iter().filter(|step| step.error.is_some() || !step.changes.is_empty()) {
+            let changes = step.error.clone().unwrap_or_else(|| step.changes.join("; "));
+            let to = match step.error {
+                Some(_) => "-".to_string(),
+                None => FORMAT_VERSION.to_string(),
+            };
+            table.row(vec![step.key.clone().into(), step.kind.describe().into(), step.from.to_string().into(), to.into(), changes.into()]);
+        }
+        table
+    }
+
+    /// One line summing up the run
+    pub fn summary(&self, dry_run: bool) -> String {
+        debug!("Summarising migration report");
+        let current = self.steps.len() - self.upgraded() - self.failed();
+        let verb = if dry_run { "would upgrade" } else { "upgraded" };
+        let mut summary = format!("{} metadata objects: {} {}, {} already at version {}", self.steps.len(), verb, self.upgraded(), current, FORMAT_VERSION);
+        if self.failed() > 0 {
+            summary.push_str(&format!(", {} failed", self.failed()));
+        }
+        if let Some(prefix) = self.backup_prefix.as_ref().filter(|_| self.upgraded() > 0) {
+            summary.push_str(&format!("; old objects copied under {}", prefix));
+        }
+        summary
+    }
+}
+
+/// Serialize an upgraded object through the type of its kind, as rustored would write it
+fn rewrite(kind: MetadataKind, value: Value) -> Result<Vec<u8>> {
+    debug!("Rewriting upgraded {}", kind.describe());
+    match kind {
+        MetadataKind::Manifest => serde_json::from_value::<crate::manifest::Manifest>(value)?.to_json(),
+        MetadataKind::Holds => serde_json::from_value::<crate::holds::HoldCatalog>(value)?.to_json(),
+        MetadataKind::Verifications => serde_json::from_value::<crate::verification::VerificationCatalog>(value)?.to_json(),
+        MetadataKind::Shared => serde_json::from_value::<crate::share::SharedCatalog>(value)?.to_json(),
+        MetadataKind::Promotions => serde_json::from_value::<crate::promote::PromotionHistory>(value)?.to_json(),
+        MetadataKind::Schedule => serde_json::from_value::<crate::schedule::ScheduleStatus>(value)?.to_json(),
+    }
+}
+
+/// Upgrade every metadata object in a bucket to `FORMAT_VERSION`
+///
+/// Each old object is copied under a timestamped backup prefix before it is
+/// overwritten, so a migration can be undone by copying the objects back. An
+/// object that cannot be read or is newer than this build is reported and left
+/// alone.
+///
+/// # Arguments
+///
+/// * `store` - The store holding the bucket
+/// * `dry_run` - Only report what would be upgraded
+/// * `now` - Time the backup prefix is named after
+pub async fn migrate(store: &dyn SnapshotStore, dry_run: bool, now: DateTime<Utc>) -> Result<MigrationReport> {
+    debug!("Migrating metadata objects, dry run: {}", dry_run);
+    let backup_prefix = format!("{}{}/", BACKUP_PREFIX, now.format("%Y%m%dT%H%M%SZ"));
+    let mut report = MigrationReport { steps: Vec::new(), backup_prefix: (!dry_run).then(|| backup_prefix.clone()) };
+    for object in store.list_snapshots("").await? {
+        let Some(kind) = MetadataKind::of_key(&object.key) else {
+            continue;
+        };
+        let Some(bytes) = store.read_object(&object.key).await? else {
+            continue;
+        };
+        let mut step = MigrationStep { key: object.key.clone(), kind, from: 1, changes: Vec::new(), error: None };
+        // Read back through the current types, so an object they reject is reported and never written
+        let rewritten = serde_json::from_slice::<Value>(&bytes).map_err(anyhow::Error::from).and_then(|mut value| {
+            step.from = version_of(&value);
+            step.changes = upgrade(kind, &mut value)?;
+            rewrite(kind, value)
+        });
+        match rewritten {
+            Err(e) => step.error = Some(e.to_string()),
+            Ok(_) if step.changes.is_empty() || dry_run => {}
+            Ok(rewritten) => {
+                store.write_object(&format!("{}{}", backup_prefix, object.key), bytes).await?;
+                store.write_object(&object.key, rewritten).await?;
+                info!("Upgraded {} {} from format version {} to {}", kind.describe(), object.key, step.from, FORMAT_VERSION);
+            }
+        }
+        report.steps.push(step);
+    }
+    Ok(report)
+}
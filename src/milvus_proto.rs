@@ -0,0 +1,273 @@
+// This module contains the Milvus gRPC messages used by the Rustored application
+// Milvus publishes its API as protobuf files (milvus.proto, schema.proto and
+// common.proto in milvus-io/milvus-proto). Only the handful of messages a
+// restore needs are declared here, by hand, with the field numbers of those
+// files, so no protoc is needed to build. Fields a restore never sets or reads
+// are left out; protobuf skips unknown fields, so responses from newer
+// servers still decode. A `oneof` is declared as its members, each optional,
+// which encodes the same on the wire.
+
+/// Milvus data types, the `DataType` enum of schema.proto
+pub mod data_type {
+    pub const BOOL: i32 = 1;
+    pub const INT8: i32 = 2;
+    pub const INT16: i32 = 3;
+    pub const INT32: i32 = 4;
+    pub const INT64: i32 = 5;
+    pub const FLOAT: i32 = 10;
+    pub const DOUBLE: i32 = 11;
+    pub const VARCHAR: i32 = 21;
+    pub const JSON: i32 = 23;
+    pub const FLOAT_VECTOR: i32 = 101;
+}
+
+/// Outcome of a call, returned in every response
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Status {
+    /// Deprecated error code, still set by older servers
+    #[prost(int32, tag = "1")]
+    pub error_code: i32,
+    #[prost(string, tag = "2")]
+    pub reason: String,
+    /// Error code set by Milvus 2.3 and later, zero on success
+    #[prost(int32, tag = "3")]
+    pub code: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct KeyValuePair {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FieldSchema {
+    #[prost(int64, tag = "1")]
+    pub field_id: i64,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(bool, tag = "3")]
+    pub is_primary_key: bool,
+    #[prost(string, tag = "4")]
+    pub description: String,
+    #[prost(int32, tag = "5")]
+    pub data_type: i32,
+    /// Type parameters such as `dim` and `max_length`
+    #[prost(message, repeated, tag = "6")]
+    pub type_params: Vec<KeyValuePair>,
+    #[prost(bool, tag = "8")]
+    pub auto_id: bool,
+    /// Set on the `$meta` field holding a collection's dynamic fields
+    #[prost(bool, tag = "12")]
+    pub is_dynamic: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CollectionSchema {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+    #[prost(message, repeated, tag = "4")]
+    pub fields: Vec<FieldSchema>,
+    #[prost(bool, tag = "5")]
+    pub enable_dynamic_field: bool,
+}
+
+/// Request naming a collection: HasCollection, DescribeCollection, GetCollectionStatistics and LoadCollection
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CollectionRequest {
+    #[prost(string, tag = "2")]
+    pub db_name: String,
+    #[prost(string, tag = "3")]
+    pub collection_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BoolResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+    #[prost(bool, tag = "2")]
+    pub value: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DescribeCollectionResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+    #[prost(message, optional, tag = "2")]
+    pub schema: Option<CollectionSchema>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetCollectionStatisticsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+    /// Statistics by name, `row_count` among them
+    #[prost(message, repeated, tag = "2")]
+    pub stats: Vec<KeyValuePair>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateCollectionRequest {
+    #[prost(string, tag = "2")]
+    pub db_name: String,
+    #[prost(string, tag = "3")]
+    pub collection_name: String,
+    /// The encoded CollectionSchema
+    #[prost(bytes = "vec", tag = "4")]
+    pub schema: Vec<u8>,
+    #[prost(int32, tag = "5")]
+    pub shards_num: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateIndexRequest {
+    #[prost(string, tag = "2")]
+    pub db_name: String,
+    #[prost(string, tag = "3")]
+    pub collection_name: String,
+    #[prost(string, tag = "4")]
+    pub field_name: String,
+    /// `index_type`, `metric_type` and `params`
+    #[prost(message, repeated, tag = "5")]
+    pub extra_params: Vec<KeyValuePair>,
+    #[prost(string, tag = "6")]
+    pub index_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FlushRequest {
+    #[prost(string, tag = "2")]
+    pub db_name: String,
+    #[prost(string, repeated, tag = "3")]
+    pub collection_names: Vec<String>,
+}
+
+/// Response carrying only a status, also used for FlushResponse whose other fields are not read
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StatusResponse {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BoolArray {
+    #[prost(bool, repeated, tag = "1")]
+    pub data: Vec<bool>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IntArray {
+    #[prost(int32, repeated, tag = "1")]
+    pub data: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LongArray {
+    #[prost(int64, repeated, tag = "1")]
+    pub data: Vec<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FloatArray {
+    #[prost(float, repeated, tag = "1")]
+    pub data: Vec<f32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DoubleArray {
+    #[prost(double, repeated, tag = "1")]
+    pub data: Vec<f64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StringArray {
+    #[prost(string, repeated, tag = "1")]
+    pub data: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct JsonArray {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub data: Vec<Vec<u8>>,
+}
+
+/// A column of scalars; exactly one member is set
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScalarField {
+    #[prost(message, optional, tag = "1")]
+    pub bool_data: Option<BoolArray>,
+    /// Int8, Int16 and Int32 columns
+    #[prost(message, optional, tag = "2")]
+    pub int_data: Option<IntArray>,
+    #[prost(message, optional, tag = "3")]
+    pub long_data: Option<LongArray>,
+    #[prost(message, optional, tag = "4")]
+    pub float_data: Option<FloatArray>,
+    #[prost(message, optional, tag = "5")]
+    pub double_data: Option<DoubleArray>,
+    #[prost(message, optional, tag = "6")]
+    pub string_data: Option<StringArray>,
+    #[prost(message, optional, tag = "9")]
+    pub json_data: Option<JsonArray>,
+}
+
+/// A column of vectors, laid end to end
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VectorField {
+    #[prost(int64, tag = "1")]
+    pub dim: i64,
+    #[prost(message, optional, tag = "2")]
+    pub float_vector: Option<FloatArray>,
+}
+
+/// One column of an insert; either `scalars` or `vectors` is set
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FieldData {
+    #[prost(int32, tag = "1")]
+    pub r#type: i32,
+    #[prost(string, tag = "2")]
+    pub field_name: String,
+    #[prost(message, optional, tag = "3")]
+    pub scalars: Option<ScalarField>,
+    #[prost(message, optional, tag = "4")]
+    pub vectors: Option<VectorField>,
+    #[prost(bool, tag = "6")]
+    pub is_dynamic: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InsertRequest {
+    #[prost(string, tag = "2")]
+    pub db_name: String,
+    #[prost(string, tag = "3")]
+    pub collection_name: String,
+    #[prost(message, repeated, tag = "5")]
+    pub fields_data: Vec<FieldData>,
+    #[prost(uint32, tag = "7")]
+    pub num_rows: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MutationResult {
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+    /// Rows of the request that were inserted
+    #[prost(uint32, repeated, tag = "3")]
+    pub succ_index: Vec<u32>,
+    /// Rows of the request that were not
+    #[prost(uint32, repeated, tag = "4")]
+    pub err_index: Vec<u32>,
+    #[prost(int64, tag = "6")]
+    pub insert_cnt: i64,
+}
+
+impl Status {
+    /// Whether the call succeeded
+    pub fn is_success(&self) -> bool {
+        self.error_code == 0 && self.code == 0
+    }
+}
@@ -0,0 +1,573 @@
+// This module contains the Milvus row loader for the Rustored application
+// Milvus snapshots are newline-delimited JSON, one row per line, as returned
+// by querying a collection: an `id`, the vector under the vector field's name
+// and the row's other fields. Qdrant points (`{"id", "vector", "payload"}`)
+// load too, their payload fields becoming the row's fields, so vectors
+// exported from Qdrant can move to Milvus.
+//
+// Milvus is spoken to over gRPC. A missing collection is created from the
+// first row: an Int64 or VarChar `id` primary key, or an auto ID when rows
+// have none, a FloatVector field sized from the row's vector with an
+// AUTOINDEX index, and dynamic fields holding everything else. Rows go to an
+// existing collection in the shape of its schema. Inserts are column-wise, so
+// each batch is turned into one column per field; rows that do not fit the
+// schema, and rows Milvus reports as failed, go to a dead-letter file while
+// the rest are loaded. Rows with an ID are upserted, so a restore run twice
+// does not duplicate them.
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use log::{debug, info, warn};
+use serde_json::{Map, Value};
+use std::path::Path;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+use crate::mapping::RestoreMapping;
+use crate::milvus_proto::{self as proto, data_type};
+
+/// Rows sent in one insert request
+pub const ROW_BATCH_SIZE: usize = 1000;
+
+/// Longest string a VarChar primary key created by a restore holds
+pub const ID_MAX_LENGTH: usize = 512;
+
+/// Field holding a row's vector when none is configured
+pub const DEFAULT_VECTOR_FIELD: &str = "vector";
+
+/// Metric of the vector index of a new collection when none is configured
+pub const DEFAULT_METRIC: &str = "COSINE";
+
+/// Name of the field holding the dynamic fields of a row
+const DYNAMIC_FIELD: &str = "$meta";
+
+/// A connection to Milvus' gRPC API
+#[derive(Clone)]
+pub struct MilvusClient {
+    grpc: tonic::client::Grpc<Channel>,
+    /// API key or `user:password`, sent in the authorization header
+    token: Option<String>,
+    /// Database holding the collection, the default database when empty
+    db_name: String,
+}
+
+impl MilvusClient {
+    /// Connect to Milvus
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Milvus URL, e.g. `http://localhost:19530`; `https://` connects with TLS
+    /// * `token` - API key or `user:password`, if Milvus needs authentication
+    /// * `db_name` - Database holding the collection, the default database when `None`
+    /// * `timeouts` - Connect timeout, and the read or operation timeout each call is bounded by
+    pub async fn connect(host: &str, token: Option<&str>, db_name: Option<&str>, timeouts: &crate::ui::models::TimeoutConfig) -> Result<Self> {
+        debug!("Connecting to Milvus at {}", host);
+        let mut endpoint = Endpoint::from_shared(host.trim_end_matches('/').to_string())
+            .map_err(|e| anyhow!("Invalid Milvus host URL {}: {}", host, e))?;
+        if host.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        if let Some(connect) = timeouts.connect() {
+            endpoint = endpoint.connect_timeout(connect);
+        }
+        if let Some(timeout) = timeouts.operation().or(timeouts.read()) {
+            endpoint = endpoint.timeout(timeout);
+        }
+        let channel = endpoint.connect().await.map_err(|e| anyhow!("Failed to connect to Milvus at {}: {}", host, e))?;
+        Ok(Self {
+            grpc: tonic::client::Grpc::new(channel),
+            token: token.map(String::from),
+            db_name: db_name.unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Call a MilvusService method
+    async fn call<Req, Resp>(&mut self, method: &str, message: Req) -> Result<Resp>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        debug!("Calling Milvus {}", method);
+        let mut request = tonic::Request::new(message);
+        if let Some(token) = &self.token {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(token);
+            request.metadata_mut().insert("authorization", encoded.parse()?);
+        }
+        if !self.db_name.is_empty() {
+            request.metadata_mut().insert("dbname", self.db_name.parse()?);
+        }
+        self.grpc.ready().await.map_err(|e| anyhow!("Milvus is not reachable: {}", e))?;
+        let path = format!("/milvus.proto.milvus.MilvusService/{}", method).parse()?;
+        let response = self.grpc.unary(request, path, tonic_prost::ProstCodec::default()).await
+            .map_err(|status| anyhow!("Milvus {} failed: {}", method, status.message()))?;
+        Ok(response.into_inner())
+    }
+
+    /// A request naming `collection`
+    fn collection_request(&self, collection: &str) -> proto::CollectionRequest {
+        debug!("Building Milvus request for collection {}", collection);
+        proto::CollectionRequest { db_name: self.db_name.clone(), collection_name: collection.to_string() }
+    }
+
+    /// Whether the collection exists
+    pub async fn has_collection(&mut self, collection: &str) -> Result<bool> {
+        debug!("Checking if Milvus collection {} exists", collection);
+        let request = self.collection_request(collection);
+        let response: proto::BoolResponse = self.call("HasCollection", request).await?;
+        check(response.status, &format!("check collection {}", collection))?;
+        Ok(response.value)
+    }
+
+    /// The schema of an existing collection
+    pub async fn describe_collection(&mut self, collection: &str) -> Result<proto::CollectionSchema> {
+        debug!("Describing Milvus collection {}", collection);
+        let request = self.collection_request(collection);
+        let response: proto::DescribeCollectionResponse = self.call("DescribeCollection", request).await?;
+        check(response.status, &format!("describe collection {}", collection))?;
+        response.schema.ok_or_else(|| anyhow!("Milvus returned no schema for collection {}", collection))
+    }
+
+    /// The number of rows in a collection
+    pub async fn row_count(&mut self, collection: &str) -> Result<Option<u64>> {
+        debug!("Counting rows of Milvus collection {}", collection);
+        let request = self.collection_request(collection);
+        let response: proto::GetCollectionStatisticsResponse = self.call("GetCollectionStatistics", request).await?;
+        check(response.status, &format!("count rows of {}", collection))?;
+        Ok(response.stats.iter().find(|stat| stat.key == "row_count").and_then(|stat| stat.value.parse().ok()))
+    }
+
+    /// Create a collection with `schema`, index its vector field with AUTOINDEX and load it
+    pub async fn create_collection(&mut self, schema: &proto::CollectionSchema, metric: &str) -> Result<()> {
+        debug!("Creating Milvus collection {}", schema.name);
+        let request = proto::CreateCollectionRequest {
+            db_name: self.db_name.clone(),
+            collection_name: schema.name.clone(),
+            schema: prost::Message::encode_to_vec(schema),
+            shards_num: 1,
+        };
+        let status: proto::Status = self.call("CreateCollection", request).await?;
+        check(Some(status), &format!("create collection {}", schema.name))?;
+        let Some(vector) = schema.fields.iter().find(|field| field.data_type == data_type::FLOAT_VECTOR) else {
+            return Ok(());
+        };
+        let param = |key: &str, value: &str| proto::KeyValuePair { key: key.to_string(), value: value.to_string() };
+        let request = proto::CreateIndexRequest {
+            db_name: self.db_name.clone(),
+            collection_name: schema.name.clone(),
+            field_name: vector.name.clone(),
+            extra_params: vec![param("index_type", "AUTOINDEX"), param("metric_type", metric), param("params", "{}")],
+            index_name: String::new(),
+        };
+        let status: proto::Status = self.call("CreateIndex", request).await?;
+        check(Some(status), &format!("index {} of {}", vector.name, schema.name))?;
+        // Loaded collections take inserts as well, and are searchable once the restore ends
+        let request = self.collection_request(&schema.name);
+        let status: proto::Status = self.call("LoadCollection", request).await?;
+        check(Some(status), &format!("load collection {}", schema.name))
+    }
+
+    /// Insert, or upsert when `upsert` is set, one batch of columns
+    pub async fn insert(&mut self, collection: &str, columns: Vec<proto::FieldData>, rows: usize, upsert: bool) -> Result<proto::MutationResult> {
+        debug!("Inserting {} rows into Milvus collection {}", rows, collection);
+        let request = proto::InsertRequest {
+            db_name: self.db_name.clone(),
+            collection_name: collection.to_string(),
+            fields_data: columns,
+            num_rows: rows as u32,
+        };
+        self.call(if upsert { "Upsert" } else { "Insert" }, request).await
+    }
+
+    /// Seal the collection's growing segments so the rows are persisted
+    pub async fn flush(&mut self, collection: &str) -> Result<()> {
+        debug!("Flushing Milvus collection {}", collection);
+        let request = proto::FlushRequest { db_name: self.db_name.clone(), collection_names: vec![collection.to_string()] };
+        let response: proto::StatusResponse = self.call("Flush", request).await?;
+        check(response.status, &format!("flush {}", collection))
+    }
+}
+
+/// The Milvus collection a name restores into
+///
+/// Collection names hold only letters, digits and underscores and do not
+/// start with a digit, so `movies-restored` becomes `movies_restored`.
+pub fn collection_name(name: &str) -> String {
+    debug!("Building Milvus collection name from {}", name);
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{}", name),
+        false => name,
+    }
+}
+
+/// Turn a failed status into an error
+fn check(status: Option<proto::Status>, action: &str) -> Result<()> {
+    debug!("Checking Milvus status of {}", action);
+    match status {
+        Some(status) if !status.is_success() => Err(anyhow!("Milvus could not {}: {}", action, status.reason)),
+        _ => Ok(()),
+    }
+}
+
+/// The type parameter `key` of a field, such as a vector's `dim`
+fn type_param(field: &proto::FieldSchema, key: &str) -> Option<usize> {
+    debug!("Reading type parameter {} of Milvus field {}", key, field.name);
+    field.type_params.iter().find(|param| param.key == key).and_then(|param| param.value.parse().ok())
+}
+
+/// Turn a snapshot line into a row
+///
+/// A Qdrant point's payload fields become the row's fields, then `mapping`
+/// renames them. Null fields are left out, so they take their defaults.
+///
+/// # Returns
+///
+/// The row, or why the line cannot be one
+pub fn milvus_row(line: Value, mapping: &RestoreMapping) -> std::result::Result<Map<String, Value>, String> {
+    debug!("Building Milvus row");
+    let Value::Object(mut row) = line else {
+        return Err("rows must be JSON objects".to_string());
+    };
+    if let Some(payload) = row.remove("payload") {
+        let Value::Object(payload) = payload else {
+            return Err("payload must be a JSON object".to_string());
+        };
+        for (key, value) in payload {
+            row.entry(key).or_insert(value);
+        }
+    }
+    let Value::Object(row) = mapping.map_document(Value::Object(row)) else {
+        unreachable!("mapping keeps a row an object");
+    };
+    Ok(row.into_iter().filter(|(_, value)| !value.is_null()).collect())
+}
+
+/// The schema of a collection created for rows shaped like `first`
+///
+/// `id` becomes the primary key, Int64 for numbers and VarChar for strings,
+/// or an auto ID when the row has none. `vector_field` must hold the row's
+/// vector, whose length sizes the collection. Other fields are dynamic.
+pub fn collection_schema(collection: &str, first: &Map<String, Value>, vector_field: &str) -> std::result::Result<proto::CollectionSchema, String> {
+    debug!("Building Milvus schema for collection {} from the first row", collection);
+    let param = |key: &str, value: usize| proto::KeyValuePair { key: key.to_string(), value: value.to_string() };
+    let id = match first.get("id") {
+        Some(Value::String(_)) => proto::FieldSchema {
+            data_type: data_type::VARCHAR,
+            type_params: vec![param("max_length", ID_MAX_LENGTH)],
+            ..Default::default()
+        },
+        Some(id) if id.is_i64() || id.is_u64() => proto::FieldSchema { data_type: data_type::INT64, ..Default::default() },
+        Some(_) => return Err("id must be an integer or a string".to_string()),
+        None => proto::FieldSchema { data_type: data_type::INT64, auto_id: true, ..Default::default() },
+    };
+    let dim = match first.get(vector_field) {
+        Some(Value::Array(vector)) if !vector.is_empty() && vector.iter().all(Value::is_number) => vector.len(),
+        _ => return Err(format!("the first row has no {} vector to size the collection", vector_field)),
+    };
+    Ok(proto::CollectionSchema {
+        name: collection.to_string(),
+        description: "Restored by rustored".to_string(),
+        fields: vec![
+            proto::FieldSchema { name: "id".to_string(), is_primary_key: true, ..id },
+            proto::FieldSchema {
+                name: vector_field.to_string(),
+                data_type: data_type::FLOAT_VECTOR,
+                type_params: vec![param("dim", dim)],
+                ..Default::default()
+            },
+        ],
+        enable_dynamic_field: true,
+    })
+}
+
+/// One value of a row, converted to its field's type
+enum Cell {
+    Bool(bool),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Str(String),
+    Json(Vec<u8>),
+    Vector(Vec<f32>),
+}
+
+/// Convert a row's value of `field` to the field's type
+fn cell(field: &proto::FieldSchema, value: &Value) -> std::result::Result<Cell, String> {
+    debug!("Converting value of Milvus field {}", field.name);
+    let wrong = || format!("{} does not fit its Milvus type", field.name);
+    Ok(match field.data_type {
+        data_type::BOOL => Cell::Bool(value.as_bool().ok_or_else(wrong)?),
+        data_type::INT8 | data_type::INT16 | data_type::INT32 => {
+            let bits = match field.data_type {
+                data_type::INT8 => 8,
+                data_type::INT16 => 16,
+                _ => 32,
+            };
+            let int = value.as_i64().filter(|int| int.unsigned_abs() < 1 << (bits - 1)).ok_or_else(wrong)?;
+            Cell::Int(int as i32)
+        }
+        data_type::INT64 => Cell::Long(value.as_i64().ok_or_else(wrong)?),
+        data_type::FLOAT => Cell::Float(value.as_f64().ok_or_else(wrong)? as f32),
+        data_type::DOUBLE => Cell::Double(value.as_f64().ok_or_else(wrong)?),
+        data_type::VARCHAR => match value {
+            Value::String(text) => Cell::Str(text.clone()),
+            Value::Number(number) => Cell::Str(number.to_string()),
+            _ => return Err(wrong()),
+        },
+        data_type::JSON => Cell::Json(serde_json::to_vec(value).map_err(|e| e.to_string())?),
+        data_type::FLOAT_VECTOR => {
+            let vector: Vec<f32> = value.as_array().ok_or_else(wrong)?
+                .iter()
+                .map(|x| x.as_f64().map(|x| x as f32))
+                .collect::<Option<_>>()
+                .ok_or_else(wrong)?;
+            let dim = type_param(field, "dim").unwrap_or(vector.len());
+            if vector.len() != dim {
+                return Err(format!("{} has {} dimensions, the collection expects {}", field.name, vector.len(), dim));
+            }
+            Cell::Vector(vector)
+        }
+        other => return Err(format!("{} has a Milvus type rustored cannot restore ({})", field.name, other)),
+    })
+}
+
+/// An empty column for `field`
+fn empty_column(field: &proto::FieldSchema) -> proto::FieldData {
+    debug!("Creating Milvus column for field {}", field.name);
+    let scalars = |scalars: proto::ScalarField| proto::FieldData {
+        r#type: field.data_type,
+        field_name: field.name.clone(),
+        scalars: Some(scalars),
+        is_dynamic: field.is_dynamic,
+        ..Default::default()
+    };
+    match field.data_type {
+        data_type::BOOL => scalars(proto::ScalarField { bool_data: Some(Default::default()), ..Default::default() }),
+        data_type::INT8 | data_type::INT16 | data_type::INT32 => scalars(proto::ScalarField { int_data: Some(Default::default()), ..Default::default() }),
+        data_type::INT64 => scalars(proto::ScalarField { long_data: Some(Default::default()), ..Default::default() }),
+        data_type::FLOAT => scalars(proto::ScalarField { float_data: Some(Default::default()), ..Default::default() }),
+        data_type::DOUBLE => scalars(proto::ScalarField { double_data: Some(Default::default()), ..Default::default() }),
+        data_type::VARCHAR => scalars(proto::ScalarField { string_data: Some(Default::default()), ..Default::default() }),
+        data_type::JSON => scalars(proto::ScalarField { json_data: Some(Default::default()), ..Default::default() }),
+        _ => proto::FieldData {
+            r#type: field.data_type,
+            field_name: field.name.clone(),
+            vectors: Some(proto::VectorField {
+                dim: type_param(field, "dim").unwrap_or_default() as i64,
+                float_vector: Some(Default::default()),
+            }),
+            ..Default::default()
+        },
+    }
+}
+
+/// Append a converted value to its column
+fn append(column: &mut proto::FieldData, cell: Cell) {
+    if let Cell::Vector(vector) = cell {
+        let vectors = column.vectors.get_or_insert_with(Default::default);
+        vectors.float_vector.get_or_insert_with(Default::default).data.extend(vector);
+        return;
+    }
+    let scalars = column.scalars.get_or_insert_with(Default::default);
+    match cell {
+        Cell::Bool(value) => scalars.bool_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Int(value) => scalars.int_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Long(value) => scalars.long_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Float(value) => scalars.float_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Double(value) => scalars.double_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Str(value) => scalars.string_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Json(value) => scalars.json_data.get_or_insert_with(Default::default).data.push(value),
+        Cell::Vector(_) => unreachable!("vectors were appended above"),
+    }
+}
+
+/// A batch of rows laid out as the columns of a collection's schema
+pub struct Columns {
+    /// Fields rows give values for, without auto IDs and the dynamic field
+    fields: Vec<proto::FieldSchema>,
+    /// Whether fields outside the schema are kept as dynamic fields
+    dynamic: bool,
+    /// The primary key Milvus generates, if it does
+    auto_id: Option<String>,
+    columns: Vec<proto::FieldData>,
+    rows: usize,
+}
+
+impl Columns {
+    /// Empty columns for `schema`
+    pub fn new(schema: &proto::CollectionSchema) -> Self {
+        debug!("Creating columns for Milvus collection {}", schema.name);
+        let fields: Vec<proto::FieldSchema> = schema.fields.iter()
+            .filter(|field| !(field.is_dynamic || field.is_primary_key && field.auto_id))
+            .cloned()
+            .collect();
+        let auto_id = schema.fields.iter().find(|field| field.is_primary_key && field.auto_id).map(|field| field.name.clone());
+        let mut columns = Self { dynamic: schema.enable_dynamic_field, auto_id, fields, columns: Vec::new(), rows: 0 };
+        columns.clear();
+        columns
+    }
+
+    /// Whether the rows carry their primary key, so can be upserted
+    pub fn has_ids(&self) -> bool {
+        debug!("Checking if Milvus rows carry their primary key");
+        self.fields.iter().any(|field| field.is_primary_key)
+    }
+
+    /// Add a row, leaving the columns untouched when it does not fit the schema
+    ///
+    /// Fields outside the schema go to the dynamic field, or are dropped when
+    /// the collection has none.
+    pub fn push(&mut self, mut row: Map<String, Value>) -> std::result::Result<(), String> {
+        debug!("Adding row {} to Milvus columns", self.rows);
+        let mut cells = Vec::with_capacity(self.fields.len() + 1);
+        for field in &self.fields {
+            let value = row.remove(&field.name).ok_or_else(|| format!("missing field {}", field.name))?;
+            cells.push(cell(field, &value)?);
+        }
+        if self.dynamic {
+            // An ID the row carries for an auto ID field is not a dynamic field
+            if let Some(auto_id) = &self.auto_id {
+                row.remove(auto_id);
+            }
+            cells.push(Cell::Json(serde_json::to_vec(&row).map_err(|e| e.to_string())?));
+        }
+        for (column, cell) in self.columns.iter_mut().zip(cells) {
+            append(column, cell);
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// The number of rows added
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    /// Whether no rows were added
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Take the columns, leaving them empty for the next batch
+    pub fn take(&mut self) -> Vec<proto::FieldData> {
+        debug!("Taking {} rows of Milvus columns", self.rows);
+        let columns = std::mem::take(&mut self.columns);
+        self.clear();
+        columns
+    }
+
+    /// Reset to empty columns
+    fn clear(&mut self) {
+        debug!("Clearing Milvus columns");
+        self.columns = self.fields.iter().map(empty_column).collect();
+        if self.dynamic {
+            self.columns.push(empty_column(&proto::FieldSchema {
+                name: DYNAMIC_FIELD.to_string(),
+                data_type: data_type::JSON,
+                is_dynamic: true,
+                ..Default::default()
+            }));
+        }
+        self.rows = 0;
+    }
+}
+
+/// Make sure the collection exists, creating it from the first row if it does not
+///
+/// # Returns
+///
+/// The collection's schema
+async fn ensure_collection(client: &mut MilvusClient, collection: &str, first: &Map<String, Value>, vector_field: &str, metric: &str) -> Result<proto::CollectionSchema> {
+    debug!("Ensuring Milvus collection {} exists", collection);
+    if client.has_collection(collection).await? {
+        return client.describe_collection(collection).await;
+    }
+    let schema = collection_schema(collection, first, vector_field).map_err(|reason| anyhow!("Cannot create collection {}: {}", collection, reason))?;
+    info!("Creating Milvus collection {}", collection);
+    client.create_collection(&schema, metric).await?;
+    // The created schema lacks the field IDs Milvus assigned, which inserts do not need
+    Ok(schema)
+}
+
+/// Stream an NDJSON snapshot of rows into a collection
+///
+/// The collection is created if it does not exist. Rows Milvus rejects, and
+/// lines that are not rows of the collection, are written to `dead_letters`
+/// and do not fail the load.
+///
+/// # Arguments
+///
+/// * `client` - Connection to Milvus
+/// * `collection` - Collection to insert the rows into
+/// * `vector_field` - Field holding the vector of a row, for a new collection
+/// * `metric` - Metric of the vector index of a new collection, e.g. `COSINE`
+/// * `path` - The snapshot file
+/// * `mapping` - Field renames applied to each row before it is sent
+/// * `progress` - Called with the share of the file inserted
+/// * `dead_letters` - Receives the rows that could not be inserted
+///
+/// # Returns
+///
+/// The number of rows inserted
+#[allow(clippy::too_many_arguments)]
+pub async fn import_rows(
+    client: &mut MilvusClient,
+    collection: &str,
+    vector_field: &str,
+    metric: &str,
+    path: &Path,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Importing rows from {:?} into {}", path, collection);
+    let mut snapshot = NdjsonBatches::open(path, ROW_BATCH_SIZE).await?;
+
+    // The collection is created once the first row shows its shape
+    let mut columns: Option<Columns> = None;
+    let mut imported = 0u64;
+    while let Some(records) = snapshot.next_batch(dead_letters).await? {
+        let mut batch = Vec::with_capacity(records.len());
+        for record in records {
+            let row = match milvus_row(record.clone(), mapping) {
+                Ok(row) => row,
+                Err(reason) => {
+                    dead_letters.write(record, &reason)?;
+                    continue;
+                }
+            };
+            if columns.is_none() {
+                let schema = ensure_collection(client, collection, &row, vector_field, metric).await?;
+                columns = Some(Columns::new(&schema));
+            }
+            let columns = columns.as_mut().expect("columns were just created");
+            match columns.push(row.clone()) {
+                Ok(()) => batch.push(Value::Object(row)),
+                Err(reason) => dead_letters.write(Value::Object(row), &reason)?,
+            }
+        }
+        if let Some(columns) = columns.as_mut().filter(|_| !batch.is_empty()) {
+            let result = client.insert(collection, columns.take(), batch.len(), columns.has_ids()).await
+                .map_err(|e| anyhow!("Inserting rows into {} failed: {}", collection, e))?;
+            check(result.status.clone(), &format!("insert rows into {}", collection))?;
+            let reason = result.status.as_ref().map(|status| status.reason.clone()).filter(|reason| !reason.is_empty());
+            for (i, row) in batch.into_iter().enumerate() {
+                match result.err_index.contains(&(i as u32)) {
+                    true => {
+                        warn!("Milvus did not insert row {} of a batch into {}", i, collection);
+                        dead_letters.write(row, reason.as_deref().unwrap_or("rejected by Milvus"))?;
+                    }
+                    false => imported += 1,
+                }
+            }
+        }
+        if let Some(progress) = progress {
+            progress(snapshot.progress());
+        }
+    }
+    if imported > 0 {
+        client.flush(collection).await?;
+    }
+    info!("Imported {} rows into {}, {} rejected", imported, collection, dead_letters.count());
+    Ok(imported)
+}
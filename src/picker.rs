@@ -0,0 +1,229 @@
+// This module contains the fuzzy snapshot picker for the Rustored command line
+// `restore-from-s3 --pick` lets users who don't know the exact key choose a
+// snapshot without launching the full TUI. The picker draws a prompt and a
+// short list of matches in place below the cursor, narrows the list as the
+// user types, and clears itself when a snapshot is chosen or the pick is
+// cancelled.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use crossterm::{
+    cursor::{MoveToColumn, MoveUp},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Print, Stylize},
+    terminal::{self, Clear, ClearType},
+};
+use log::debug;
+use std::io::Write;
+use crate::ui::models::BackupMetadata;
+
+/// Most matches shown below the prompt at once
+pub const PICKER_HEIGHT: usize = 10;
+
+/// Score how well `query` fuzzy-matches `candidate`
+///
+/// Every character of the query must appear in the candidate in order,
+/// ignoring case. Matches score higher when matched characters are adjacent
+/// or start a path segment or word, and when the candidate is shorter.
+///
+/// # Returns
+///
+/// The score, or `None` if the candidate does not match
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let found = (position..candidate.len())
+            .find(|&i| candidate[i].to_lowercase().eq(std::iter::once(wanted)))?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score * 100 - candidate.len() as i64)
+}
+
+/// Indices of the snapshots matching `query`, best match first
+///
+/// An empty query matches everything, newest snapshot first.
+pub fn filter_snapshots(query: &str, snapshots: &[BackupMetadata]) -> Vec<usize> {
+    debug!("Filtering {} snapshots by {:?}", snapshots.len(), query);
+    let mut matches: Vec<(i64, usize)> = snapshots
+        .iter()
+        .enumerate()
+        .filter_map(|(i, snapshot)| fuzzy_score(query, &snapshot.key).map(|score| (score, i)))
+        .collect();
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        b_score.cmp(a_score).then_with(|| {
+            snapshots[*b].last_modified.total_cmp(&snapshots[*a].last_modified)
+        })
+    });
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+/// What a key press does to the picker
+#[derive(Debug, Clone, PartialEq)]
+pub enum PickerAction {
+    /// Keep picking
+    Continue,
+    /// Choose the snapshot at this index
+    Pick(usize),
+    /// Leave without choosing
+    Cancel,
+}
+
+/// State of the picker: the query typed so far and the highlighted match
+#[derive(Debug, Clone)]
+pub struct Picker<'a> {
+    pub query: String,
+    pub selected: usize,
+    pub matches: Vec<usize>,
+    snapshots: &'a [BackupMetadata],
+}
+
+impl<'a> Picker<'a> {
+    /// Picker over `snapshots` with an empty query
+    pub fn new(snapshots: &'a [BackupMetadata]) -> Self {
+        debug!("Creating snapshot picker over {} snapshots", snapshots.len());
+        Self {
+            query: String::new(),
+            selected: 0,
+            matches: filter_snapshots("", snapshots),
+            snapshots,
+        }
+    }
+
+    /// Apply a key press
+    pub fn handle_key(&mut self, key: KeyEvent) -> PickerAction {
+        debug!("Picker key: {:?}", key);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return PickerAction::Cancel,
+            KeyCode::Char('c') if ctrl => return PickerAction::Cancel,
+            KeyCode::Enter => {
+                return match self.matches.get(self.selected) {
+                    Some(&index) => PickerAction::Pick(index),
+                    None => PickerAction::Continue,
+                };
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.select_next(),
+            KeyCode::Char('n') if ctrl => self.select_next(),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refilter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.refilter();
+            }
+            _ => {}
+        }
+        PickerAction::Continue
+    }
+
+    fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    fn refilter(&mut self) {
+        self.matches = filter_snapshots(&self.query, self.snapshots);
+        self.selected = 0;
+    }
+
+    /// Lines shown below the prompt, scrolled to keep the selection visible
+    ///
+    /// # Returns
+    ///
+    /// Each line's text and whether it is the selected match
+    pub fn visible_lines(&self, height: usize) -> Vec<(String, bool)> {
+        let first = (self.selected + 1).saturating_sub(height);
+        self.matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .map(|(i, &index)| {
+                let snapshot = &self.snapshots[index];
+                let modified: DateTime<Utc> = DateTime::from_timestamp(snapshot.last_modified as i64, 0).unwrap_or_default();
+                let line = format!(
+                    "{}  {:.2} MB  {}",
+                    snapshot.key,
+                    snapshot.size as f64 / 1024.0 / 1024.0,
+                    modified.format("%Y-%m-%d %H:%M:%S")
+                );
+                (line, i == self.selected)
+            })
+            .collect()
+    }
+
+    /// Draw the prompt and matches below the cursor, leaving the cursor on the prompt
+    fn draw(&self, out: &mut impl Write, height: usize, width: usize) -> Result<()> {
+        let lines = self.visible_lines(height);
+        let prompt = format!("{}/{} > {}", self.matches.len(), self.snapshots.len(), self.query);
+        queue!(out, MoveToColumn(0), Clear(ClearType::FromCursorDown), Print(&prompt))?;
+        for (line, selected) in &lines {
+            let line: String = line.chars().take(width.saturating_sub(2)).collect();
+            if *selected {
+                queue!(out, Print("\r\n"), Print(format!("> {}", line).reverse()))?;
+            } else {
+                queue!(out, Print("\r\n"), Print(format!("  {}", line)))?;
+            }
+        }
+        if !lines.is_empty() {
+            queue!(out, MoveUp(lines.len() as u16))?;
+        }
+        queue!(out, MoveToColumn(prompt.chars().count() as u16))?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Let the user pick one of `snapshots` on the terminal
+///
+/// The picker is drawn on stderr so stdout stays free for command output.
+///
+/// # Returns
+///
+/// The chosen snapshot, or `None` if the pick was cancelled
+pub fn pick_snapshot(snapshots: &[BackupMetadata]) -> Result<Option<BackupMetadata>> {
+    debug!("Picking one of {} snapshots", snapshots.len());
+    if snapshots.is_empty() {
+        bail!("No snapshots to pick from");
+    }
+    let mut picker = Picker::new(snapshots);
+    let mut out = std::io::stderr();
+    terminal::enable_raw_mode()?;
+    let result = (|| -> Result<Option<BackupMetadata>> {
+        loop {
+            let (width, rows) = terminal::size().unwrap_or((80, 24));
+            let height = PICKER_HEIGHT.min((rows as usize).saturating_sub(1)).max(1);
+            picker.draw(&mut out, height, width as usize)?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match picker.handle_key(key) {
+                    PickerAction::Continue => {}
+                    PickerAction::Pick(index) => return Ok(Some(snapshots[index].clone())),
+                    PickerAction::Cancel => return Ok(None),
+                }
+            }
+        }
+    })();
+    queue!(out, MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+    out.flush()?;
+    terminal::disable_raw_mode()?;
+    result
+}
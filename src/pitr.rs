@@ -0,0 +1,305 @@
+// This module contains point-in-time recovery for the Rustored application
+// A base backup and the WAL archived after it can rebuild the cluster as it
+// was at any moment the WAL covers. Recovery runs on a scratch instance: the
+// base backup is unpacked into a new data directory, the segments up to the
+// target time are fetched next to it, and a local postgres replays them with
+// `recovery_target_time` and promotes itself. The live server is never touched,
+// and no bucket credentials end up in the scratch instance's settings.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::BackupMetadata;
+use crate::wal::{archived_name, WalChain};
+
+/// Port the scratch instance listens on unless told otherwise
+pub const DEFAULT_SCRATCH_PORT: u16 = 5433;
+
+/// Log file of the scratch instance, inside its data directory
+pub const RECOVERY_LOG: &str = "rustored-recovery.log";
+
+/// How often the scratch instance is checked while it replays WAL
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Earliest and latest times a chain can be recovered to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecoveryWindow {
+    /// When the base backup finished; recovery cannot stop before it is consistent
+    pub earliest: DateTime<Utc>,
+    /// When the newest usable segment was archived
+    pub latest: DateTime<Utc>,
+}
+
+impl RecoveryWindow {
+    /// The window of a chain, from its base backup's key and its segments' upload times
+    pub fn of(chain: &WalChain) -> Result<Self> {
+        debug!("Finding recovery window of {}", chain.base.key);
+        let earliest = base_time(&chain.base.key).ok_or_else(|| anyhow!("{} is not a base backup", chain.base.key))?;
+        let latest = usable_segments(chain)
+            .filter_map(|segment| DateTime::from_timestamp(segment.last_modified as i64, 0))
+            .max()
+            .unwrap_or(earliest)
+            .max(earliest);
+        Ok(Self { earliest, latest })
+    }
+
+    /// Check that recovery can stop at `target`
+    pub fn check(&self, target: DateTime<Utc>) -> Result<()> {
+        debug!("Checking recovery target {} against {:?}", target, self);
+        if target < self.earliest || target > self.latest {
+            return Err(anyhow!("{} is outside the recoverable window, {}", format_time(target), self));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for RecoveryWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} to {}", format_time(self.earliest), format_time(self.latest))
+    }
+}
+
+/// A time as shown and accepted for recovery targets, e.g. `2025-03-01 14:30:00`
+pub fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Parse a recovery target, RFC 3339 or `YYYY-MM-DD HH:MM[:SS]` in UTC
+pub fn parse_target_time(value: &str) -> Result<DateTime<Utc>> {
+    debug!("Parsing recovery target time: {}", value);
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| Utc.from_utc_datetime(&time))
+        .ok_or_else(|| anyhow!("{:?} is not a time, e.g. 2025-03-01 14:30 (UTC)", value))
+}
+
+/// When a base backup finished, from the timestamp in its key
+pub fn base_time(key: &str) -> Option<DateTime<Utc>> {
+    debug!("Reading base backup time of {}", key);
+    crate::wal::base_start_segment(key)?;
+    let name = crate::encryption::plain_key(key).rsplit('/').next()?;
+    let timestamp = name.split('-').next()?;
+    NaiveDateTime::parse_from_str(timestamp, "%Y%m%dT%H%M%SZ").ok().map(|time| Utc.from_utc_datetime(&time))
+}
+
+/// Segments recovery can replay, stopping before the first gap
+fn usable_segments(chain: &WalChain) -> impl Iterator<Item = &BackupMetadata> {
+    let last = chain.last_segment().map(str::to_string);
+    let mut done = last.is_none();
+    chain.segments.iter().take_while(move |segment| {
+        if done {
+            return false;
+        }
+        done = Some(archived_name(&segment.key)) == last.as_deref();
+        true
+    })
+}
+
+/// Names of the segments needed to reach `target`, oldest first
+///
+/// A segment uploaded at or after the target holds the first records past
+/// it, so it is the last one needed.
+pub fn segments_until(chain: &WalChain, target: DateTime<Utc>) -> Vec<String> {
+    debug!("Listing segments of {} needed to reach {}", chain.base.key, target);
+    let mut names = Vec::new();
+    for segment in usable_segments(chain) {
+        names.push(archived_name(&segment.key).to_string());
+        if segment.last_modified >= target.timestamp() as f64 {
+            break;
+        }
+    }
+    names
+}
+
+/// Settings appended to the scratch instance's `postgresql.auto.conf`
+///
+/// Archiving is turned off so the scratch instance never pushes WAL into the
+/// live cluster's archive, and it listens only on localhost and a socket in
+/// its own data directory.
+///
+/// # Arguments
+///
+/// * `wal_dir` - Directory the needed segments were fetched into
+/// * `data_dir` - The scratch instance's data directory
+/// * `target` - Time to stop replaying at
+/// * `port` - Port to listen on
+pub fn recovery_settings(wal_dir: &Path, data_dir: &Path, target: DateTime<Utc>, port: u16) -> String {
+    debug!("Building recovery settings for {}", target);
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let restore_command = format!("cp \"{}/%f\" \"%p\"", wal_dir.display());
+    [
+        ("restore_command", quote(&restore_command)),
+        ("recovery_target_time", quote(&format!("{}+00", format_time(target)))),
+        ("recovery_target_action", quote("promote")),
+        ("archive_mode", quote("off")),
+        ("port", port.to_string()),
+        ("listen_addresses", quote("localhost")),
+        ("unix_socket_directories", quote(&data_dir.display().to_string())),
+    ]
+    .iter()
+    .map(|(name, value)| format!("{} = {}\n", name, value))
+    .collect::<String>()
+}
+
+/// A promoted scratch instance recovered to a point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScratchInstance {
+    pub data_dir: PathBuf,
+    pub port: u16,
+    pub recovered_to: DateTime<Utc>,
+}
+
+impl ScratchInstance {
+    /// Where the instance is and how to stop it, for the CLI and the TUI
+    pub fn summary(&self) -> String {
+        debug!("Summarising scratch instance in {}", self.data_dir.display());
+        format!(
+            "Recovered to {} UTC on localhost:{} (data directory {}); stop it with: pg_ctl --pgdata {} stop",
+            format_time(self.recovered_to),
+            self.port,
+            self.data_dir.display(),
+            self.data_dir.display(),
+        )
+    }
+}
+
+/// Stops a scratch instance that did not finish recovering
+struct StartedInstance<'a> {
+    data_dir: &'a Path,
+    recovered: bool,
+}
+
+impl Drop for StartedInstance<'_> {
+    fn drop(&mut self) {
+        if self.recovered {
+            return;
+        }
+        warn!("Stopping scratch instance in {}, recovery did not finish", self.data_dir.display());
+        let _ = std::process::Command::new("pg_ctl")
+            .arg("--pgdata").arg(self.data_dir)
+            .args(["--mode", "immediate", "stop"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Last lines of the scratch instance's log, to explain a failed recovery
+fn log_tail(data_dir: &Path) -> String {
+    let log = std::fs::read_to_string(data_dir.join(RECOVERY_LOG)).unwrap_or_default();
+    let lines: Vec<&str> = log.lines().collect();
+    lines[lines.len().saturating_sub(5)..].join("\n")
+}
+
+/// Recover a base backup and its WAL to a point in time on a scratch instance
+///
+/// The base backup is unpacked into `data_dir`, which must not exist or be
+/// empty, and the segments up to `target` are fetched into `<data_dir>.wal`.
+/// A local postgres of the same major version as the backed up cluster then
+/// replays them and promotes itself once it reaches the target, which is when
+/// PostgreSQL removes `recovery.signal`.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket
+/// * `chain` - The base backup and its archived WAL
+/// * `target` - Time to recover to, in the chain's recovery window
+/// * `data_dir` - Data directory of the scratch instance
+/// * `port` - Port the scratch instance listens on
+///
+/// # Returns
+///
+/// The running, promoted scratch instance
+pub async fn recover(browser: &mut SnapshotBrowser, chain: &WalChain, target: DateTime<Utc>, data_dir: &Path, port: u16) -> Result<ScratchInstance> {
+    debug!("Recovering {} to {} in {}", chain.base.key, target, data_dir.display());
+    RecoveryWindow::of(chain)?.check(target)?;
+    if data_dir.exists() && data_dir.read_dir()?.next().is_some() {
+        return Err(anyhow!("{} is not empty; recover into a new data directory", data_dir.display()));
+    }
+
+    // The base backup is a tar holding pg_basebackup's base.tar.gz
+    let work = tempfile::TempDir::new()?;
+    let downloaded = work.path().join("base.tar");
+    browser.download_snapshot(&chain.base, &downloaded).await?
+        .ok_or_else(|| anyhow!("Download of {} failed: {:?}", chain.base.key, browser.popup_state))?;
+    let unpacked = crate::directory_dump::unpack(&downloaded).await?;
+    std::fs::create_dir_all(data_dir).with_context(|| format!("Failed to create {}", data_dir.display()))?;
+    let output = tokio::process::Command::new("tar")
+        .arg("--extract").arg("--gzip")
+        .arg("--file").arg(unpacked.path().join("base.tar.gz"))
+        .arg("--directory").arg(data_dir)
+        .output()
+        .await
+        .context("Failed to execute tar")?;
+    if !output.status.success() {
+        return Err(anyhow!("Unpacking the base backup failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    drop(unpacked);
+    // Backups taken without WAL leave pg_wal out, and postgres refuses a group- or world-readable data directory
+    std::fs::create_dir_all(data_dir.join("pg_wal"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(data_dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    let wal_dir = data_dir.with_extension("wal");
+    std::fs::create_dir_all(&wal_dir).with_context(|| format!("Failed to create {}", wal_dir.display()))?;
+    let segments = segments_until(chain, target);
+    for name in &segments {
+        crate::wal::fetch(browser, name, &wal_dir.join(name)).await?;
+    }
+    info!("Fetched {} WAL segments into {}", segments.len(), wal_dir.display());
+
+    let auto_conf = data_dir.join("postgresql.auto.conf");
+    let mut settings = std::fs::read_to_string(&auto_conf).unwrap_or_default();
+    settings.push_str("\n# Point-in-time recovery by rustored\n");
+    settings.push_str(&recovery_settings(&wal_dir, data_dir, target, port));
+    std::fs::write(&auto_conf, settings).with_context(|| format!("Failed to write {}", auto_conf.display()))?;
+    std::fs::write(data_dir.join("recovery.signal"), "")?;
+
+    let output = tokio::process::Command::new("pg_ctl")
+        .arg("--pgdata").arg(data_dir)
+        .arg("--log").arg(data_dir.join(RECOVERY_LOG))
+        .arg("start")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("Failed to execute pg_ctl; install the PostgreSQL server of the backed up cluster's major version")?;
+    if !output.status.success() {
+        return Err(anyhow!("Starting the scratch instance failed: {}\n{}", String::from_utf8_lossy(&output.stderr).trim(), log_tail(data_dir)));
+    }
+    let mut started = StartedInstance { data_dir, recovered: false };
+
+    // Promotion at the target removes recovery.signal; a server that stops first could not reach it
+    while data_dir.join("recovery.signal").exists() {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let status = tokio::process::Command::new("pg_ctl")
+            .arg("--pgdata").arg(data_dir)
+            .arg("status")
+            .stdout(Stdio::null())
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(anyhow!("The scratch instance stopped before reaching {}:\n{}", format_time(target), log_tail(data_dir)));
+        }
+    }
+    started.recovered = true;
+    info!("Recovered {} to {} in {}", chain.base.key, target, data_dir.display());
+    Ok(ScratchInstance { data_dir: data_dir.to_path_buf(), port, recovered_to: target })
+}
+
+/// Default data directory of a scratch instance, under the temp directory
+pub fn default_data_dir(target: DateTime<Utc>) -> PathBuf {
+    debug!("Choosing scratch data directory for {}", target);
+    std::env::temp_dir().join(format!("rustored-pitr-{}", target.format("%Y%m%dT%H%M%SZ")))
+}
@@ -8,7 +8,7 @@ use anyhow::{Result, anyhow};
 // Import PostgreSQL configuration from tokio-postgres
 use tokio_postgres::Config as PgConfig;
 // Import logging utilities for error and info level logging
-use log::{error, info, debug};
+use log::{error, info, debug, warn};
 // Import TLS connector for secure connections
 use native_tls::TlsConnector;
 // Import PostgreSQL-specific TLS connector
@@ -16,7 +16,12 @@ use postgres_native_tls::MakeTlsConnector;
 // Import random word generator for creating random database names
 use random_word::{Lang, get as random_word};
 // Import task utilities for spawning async tasks
-use tokio::task;
+use crate::presets::{RestoreMode, RestoreOptions};
+use std::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Drops of partially restored databases still in flight
+static PARTIAL_DROPS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
 
 /// Connect to PostgreSQL with SSL security
 /// 
@@ -112,10 +117,21 @@ pub async fn connect_no_ssl(config: &PgConfig) -> Result<tokio_postgres::Client>
   Ok(client)
 }
 
+/// A database on the PostgreSQL server
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseInfo {
+  pub name: String,
+  pub owner: String,
+  /// Human readable size, `None` when the connected user may not connect to it
+  pub size: Option<String>,
+  /// Where the database was restored from, if rustored restored it
+  pub provenance: Option<crate::provenance::Provenance>,
+}
+
 /// List all databases in the PostgreSQL server
 /// 
-/// This function retrieves and displays a list of all non-template databases
-/// available on the connected PostgreSQL server.
+/// This function retrieves a list of all non-template databases
+/// available on the connected PostgreSQL server, ordered by name.
 /// 
 /// # Arguments
 /// 
@@ -123,26 +139,33 @@ pub async fn connect_no_ssl(config: &PgConfig) -> Result<tokio_postgres::Client>
 /// 
 /// # Returns
 /// 
-/// A Result indicating success or an error
-pub async fn list_databases(client: &tokio_postgres::Client) -> Result<()> {
+/// The name, owner, size and restore provenance of each database
+pub async fn list_databases(client: &tokio_postgres::Client) -> Result<Vec<DatabaseInfo>> {
   debug!("Retrieving list of all PostgreSQL databases");
   // Query the pg_database system catalog to get all non-template databases
-  // Template databases are special system databases used as templates for new databases
+  // Template databases are special system databases used as templates for new databases.
+  // pg_database_size needs CONNECT on the database, so sizes are skipped where it is missing.
   let rows = client
-      .query("SELECT datname FROM pg_database WHERE datistemplate = false;", &[])
+      .query(
+          "SELECT datname, pg_get_userbyid(datdba), \
+                  CASE WHEN has_database_privilege(datname, 'CONNECT') \
+                       THEN pg_size_pretty(pg_database_size(datname)) END, \
+                  shobj_description(oid, 'pg_database') \
+           FROM pg_database WHERE datistemplate = false ORDER BY datname;",
+          &[],
+      )
       .await?;
 
-  // Iterate through the result rows and display each database name
   debug!("Found {} databases", rows.len());
-  for row in rows {
-      // Extract the database name from the first column
-      let name: String = row.get(0);
-      // Log the database name at info level for user visibility
-      info!("{}", name);
-  }
-
-  debug!("Successfully listed all databases");
-  Ok(())
+  Ok(rows
+      .iter()
+      .map(|row| DatabaseInfo {
+          name: row.get(0),
+          owner: row.get(1),
+          size: row.get(2),
+          provenance: row.get::<_, Option<String>>(3).as_deref().and_then(crate::provenance::Provenance::from_comment),
+      })
+      .collect())
 }
 
 /// Create a new PostgreSQL database
@@ -366,10 +389,172 @@ pub async fn change_password(client: &tokio_postgres::Client, user: &str, passwo
   Ok(())
 }
 
+/// Name for a new database to restore a snapshot into, e.g. `apple-restored`
+pub fn new_restored_db_name() -> String {
+    debug!("Generating name for restored database");
+    format!("{}-restored", random_word(Lang::En))
+}
+
+/// Check that a name can be used for the database a snapshot is restored into
+///
+/// The name is quoted in `CREATE DATABASE`, so any characters are allowed except
+/// double quotes and NUL, up to PostgreSQL's 63 byte identifier limit.
+pub fn check_db_name(name: &str) -> Result<()> {
+    debug!("Checking target database name: {}", name);
+    if name.is_empty() {
+        return Err(anyhow!("Target database name is empty"));
+    }
+    if name.len() > 63 {
+        return Err(anyhow!("Target database name {} is longer than 63 bytes", name));
+    }
+    if name.contains(['"', '\0']) {
+        return Err(anyhow!("Target database name {} contains a double quote or NUL", name));
+    }
+    Ok(())
+}
+
+/// Create the empty database a snapshot is restored into
+///
+/// Restores never write into an existing database, so a name that is already
+/// taken fails with a message asking for another one.
+///
+/// # Arguments
+///
+/// * `client` - Client connected to another database on the server
+/// * `name` - Name of the database to create
+pub async fn create_restore_database(client: &tokio_postgres::Client, name: &str) -> Result<()> {
+    debug!("Creating database to restore into: {}", name);
+    check_db_name(name)?;
+    match client.execute(&format!("CREATE DATABASE \"{}\";", name), &[]).await {
+        Ok(_) => Ok(()),
+        Err(e) if e.code() == Some(&tokio_postgres::error::SqlState::DUPLICATE_DATABASE) => Err(anyhow!(
+            "Database {} already exists; drop it or choose another target database",
+            name
+        )),
+        Err(e) => Err(anyhow!("Failed to create new database {}: {}", name, e)),
+    }
+}
+
+/// Get the database a snapshot is restored into ready, and return its name
+///
+/// A new database is named after the target database, or randomly when none
+/// is set. Restores over an existing database use the target database, falling
+/// back to the configured one, and create it if it does not exist yet.
+///
+/// # Arguments
+///
+/// * `client` - Client connected to the maintenance database
+/// * `db_name` - The configured database
+/// * `target_db` - The chosen target database, if any
+/// * `mode` - Whether to create a new database or restore over an existing one
+///
+/// # Returns
+///
+/// The name of the database to run pg_restore against
+pub async fn prepare_restore_database(
+    client: &tokio_postgres::Client,
+    db_name: Option<&str>,
+    target_db: Option<&str>,
+    mode: RestoreMode,
+) -> Result<String> {
+    debug!("Preparing database to restore into, mode {}", mode);
+    let span = crate::telemetry::Span::start("create_db").with("mode", mode);
+    crate::telemetry::instrument(span, async move {
+        if mode == RestoreMode::New {
+            let name = target_db.map_or_else(new_restored_db_name, str::to_string);
+            create_restore_database(client, &name).await?;
+            // Only databases rustored named itself are throwaways that gc may drop
+            if target_db.is_none() {
+                crate::gc::tag_throwaway(client, &name, chrono::Utc::now()).await;
+            }
+            return Ok(name);
+        }
+        let name = target_db.or(db_name).ok_or_else(|| anyhow!("No database to restore into; set the database or target database"))?;
+        check_db_name(name)?;
+        let exists = client.query_opt("SELECT 1 FROM pg_database WHERE datname = $1", &[&name]).await?.is_some();
+        match (mode, exists) {
+            (RestoreMode::Recreate, true) => {
+                if name == crate::promote::MAINTENANCE_DB {
+                    return Err(anyhow!("Refusing to drop the {} maintenance database", name));
+                }
+                warn!("Dropping database {} to restore into it", name);
+                drop_database_with_force(client, name).await
+                    .map_err(|e| anyhow!("Failed to drop database {}: {}", name, e))?;
+                create_database(client, name).await
+                    .map_err(|e| anyhow!("Failed to create new database {}: {}", name, e))?;
+            }
+            (_, false) => create_restore_database(client, name).await?,
+            (_, true) => info!("Restoring into existing database {} with --clean", name),
+        }
+        Ok(name.to_string())
+    }).await
+}
+
+/// A database being restored into, dropped if the restore is cancelled part way
+///
+/// Cancelling a restore drops its future, which kills pg_restore or psql; the
+/// guard goes with it and, when `drop_on_cancel` is set, drops the half-filled
+/// database in the background. Databases restored over with `--clean` held data
+/// before the restore and are never dropped.
+pub struct PartialRestore {
+    /// Maintenance connection kept open to drop the database, `None` once finished
+    client: Option<tokio_postgres::Client>,
+    name: String,
+}
+
+impl PartialRestore {
+    /// Watch a database prepared for a restore
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - Client connected to the maintenance database, kept only when the database may be dropped
+    /// * `name` - The database restored into
+    /// * `options` - Options of the restore, whether to drop on cancel and the mode
+    pub fn new(client: tokio_postgres::Client, name: &str, options: &RestoreOptions) -> Self {
+        debug!("Watching {} for a cancelled restore", name);
+        let droppable = options.drop_on_cancel && options.mode != RestoreMode::Clean;
+        Self { client: droppable.then_some(client), name: name.to_string() }
+    }
+
+    /// The restore ran to the end, successfully or not, so the database is kept
+    pub fn finish(mut self) {
+        debug!("Restore into {} finished", self.name);
+        self.client = None;
+    }
+}
+
+impl Drop for PartialRestore {
+    fn drop(&mut self) {
+        let Some(client) = self.client.take() else { return };
+        let name = std::mem::take(&mut self.name);
+        warn!("Restore into {} was cancelled, dropping the partially restored database", name);
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("No runtime to drop {} on, drop it by hand", name);
+            return;
+        };
+        let handle = runtime.spawn(async move {
+            if let Err(e) = drop_database_with_force(&client, &name).await {
+                warn!("Failed to drop partially restored database {}: {}", name, e);
+            }
+        });
+        PARTIAL_DROPS.lock().unwrap_or_else(|e| e.into_inner()).push(handle);
+    }
+}
+
+/// Wait for partially restored databases of cancelled restores to be dropped
+pub async fn finish_partial_drops() {
+    debug!("Waiting for partially restored databases to be dropped");
+    let handles = std::mem::take(&mut *PARTIAL_DROPS.lock().unwrap_or_else(|e| e.into_inner()));
+    for handle in handles {
+        let _ = handle.await;
+    }
+}
+
 /// Restore a PostgreSQL database from a snapshot file
 /// 
 /// This function restores a database from a previously created snapshot file.
-/// It creates a new database with a random name, then restores the snapshot into it.
+/// It creates the target database, or one with a random name when no target is
+/// given, then restores the snapshot into it.
 /// 
 /// # Arguments
 /// 
@@ -379,10 +564,13 @@ pub async fn change_password(client: &tokio_postgres::Client, user: &str, passwo
 /// * `password` - Optional password for authentication
 /// * `use_ssl` - Whether to use SSL for the connection
 /// * `file_path` - Path to the snapshot file to restore
+/// * `db_name` - The configured database, restored over when `options.mode` asks for it
+/// * `target_db` - Name of the database to restore into, or `None` for `<word>-restored`
 /// 
 /// # Returns
 /// 
 /// A Result containing the name of the newly created database or an error
+#[allow(clippy::too_many_arguments)]
 pub async fn restore_snapshot(
     host: &str,
     port: u16,
@@ -390,14 +578,14 @@ pub async fn restore_snapshot(
     password: Option<String>,
     use_ssl: bool,
     file_path: &str,
+    db_name: Option<&str>,
+    target_db: Option<&str>,
+    options: RestoreOptions,
 ) -> Result<String> {
     debug!("Starting database restore from snapshot file: {}", file_path);
     debug!("Connection parameters: host={}, port={}, use_ssl={}", host, port, use_ssl);
-    // Create a new database with a random name by combining a random English word with the suffix
-    // This ensures the restored database has a unique but recognizable name
-    let new_dbname = format!("{}-restored", random_word(Lang::En));
-    debug!("Generated new database name for restoration: {}", new_dbname);
-    
+    debug!("Restore mode: {}", options.mode);
+
     // Create a connection configuration to the default postgres database
     // We need to connect to an existing database first before we can create a new one
     debug!("Setting up connection configuration to PostgreSQL server");
@@ -425,6 +613,7 @@ pub async fn restore_snapshot(
     
     // Connect to the default postgres database using the appropriate connection method
     // based on whether SSL is required or not
+    config.dbname(crate::promote::MAINTENANCE_DB);
     debug!("Connecting to PostgreSQL server to create new database");
     let client = if use_ssl {
         debug!("Using SSL connection");
@@ -434,62 +623,40 @@ pub async fn restore_snapshot(
         connect_no_ssl(&config).await?
     };
     
-    // Create the new database with the randomly generated name
-    // This will be the target database for our restoration
-    debug!("Creating new database: {}", new_dbname);
-    let create_query = format!("CREATE DATABASE \"{}\";", new_dbname);
-    match client.execute(&create_query, &[]).await {
-        Ok(_) => debug!("Database creation query executed successfully"),
-        Err(e) => return Err(anyhow!("Failed to create new database {}: {}", new_dbname, e)),
-    };
+    // Create the database to restore into, or get an existing one ready
+    // Without a chosen target, a new one combines a random English word with the suffix
+    let new_dbname = prepare_restore_database(&client, db_name, target_db, options.mode).await?;
+    debug!("Database name for restoration: {}", new_dbname);
     debug!("Successfully created new database");
     
-    // Close the connection to the default database
-    // We need to do this before connecting to our new database
-    debug!("Closing connection to default database");
-    drop(client);
+    // The connection to the default database is only kept to drop a cancelled restore
+    let partial = PartialRestore::new(client, &new_dbname, &options);
     
-    // Create owned versions of parameters for the blocking task
-    let file_path_owned = file_path.to_string();
-    let host_owned = host.to_string();
-    let new_dbname_owned = new_dbname.clone();
-    
-    // Spawn a blocking task to handle the restore operation
-    let restore_handle = task::spawn_blocking(move || {
-        // Call the restore_database function from the backup module
-        let result = crate::backup::restore_database(
-            &new_dbname_owned,
-            &file_path_owned,
-            &host_owned,
-            port,
-            username.as_deref(),
-            password.as_deref(),
-            use_ssl,
-        );
-        result
-    });
-    
-    // Wait for the restore operation to complete
-    match restore_handle.await {
-        Ok(inner_result) => {
-            match inner_result {
-                Ok(_) => {
-                    // Log the successful restoration at info level for user visibility
-                    info!("Snapshot restored to database: {}", new_dbname);
-                    
-                    // Return the name of the newly created and restored database
-                    debug!("Database restoration process completed");
-                    Ok(new_dbname)
-                },
-                Err(e) => {
-                    error!("pg_restore failed: {}", e);
-                    Err(anyhow!("pg_restore task failed: {}", e))
-                }
-            }
+    // pg_restore, psql or the custom command runs as a child process that is killed if the restore is cancelled
+    let result = crate::backup::restore_database(
+        &new_dbname,
+        file_path,
+        host,
+        port,
+        username.as_deref(),
+        password.as_deref(),
+        use_ssl,
+        &options,
+    ).await;
+    partial.finish();
+
+    match result {
+        Ok(_) => {
+            // Log the successful restoration at info level for user visibility
+            info!("Snapshot restored to database: {}", new_dbname);
+
+            // Return the name of the newly created and restored database
+            debug!("Database restoration process completed");
+            Ok(new_dbname)
         },
         Err(e) => {
-            error!("pg_restore task panicked: {}", e);
-            Err(anyhow!("pg_restore task issues: {}", e))
+            error!("Restore failed: {}", e);
+            Err(anyhow!("Restore task failed: {}", e))
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,223 @@
+// This module contains target preparation for the Rustored application
+// A fresh PostgreSQL server often lacks the roles that own the dumped objects,
+// the extensions the schema uses and the settings the application expects, so
+// the first restore fails or comes out incomplete. Target profiles declare that
+// state in the presets file and `rustored target prepare` converges the server
+// to it: missing roles are created, extensions are created in `template1` so
+// every restored database inherits them, and settings are applied with
+// `ALTER SYSTEM`.
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::ui::models::PostgresConfig;
+
+/// Database new databases are created from, so extensions created there are inherited
+pub const TEMPLATE_DB: &str = "template1";
+
+/// State a target server must be in before restores, declared in the presets file
+///
+/// ```toml
+/// [[target]]
+/// name = "staging"
+/// roles = ["app", "readonly"]
+/// extensions = ["pg_trgm", "pgcrypto"]
+/// settings = { work_mem = "64MB" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetProfile {
+    /// Name the profile is selected by
+    pub name: String,
+    /// Roles that must exist, created without login when missing
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Extensions that must be available in every new database
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Server settings and their required values
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Schema of a `[[target]]` table in the presets file
+pub(crate) const PROFILE_SCHEMA: TableSchema = TableSchema {
+    name: "[[target]]",
+    fields: &[
+        Field::required("name", Kind::String),
+        Field::optional("roles", Kind::Strings),
+        Field::optional("extensions", Kind::Strings),
+        Field::optional("settings", Kind::StringMap),
+    ],
+    requires: &[],
+    unique: Some("name"),
+};
+
+/// Current state of a target server, as far as profiles are concerned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetState {
+    pub roles: HashSet<String>,
+    /// Extensions installed in the template database
+    pub extensions: HashSet<String>,
+    /// Extensions the server has packages for
+    pub available_extensions: HashSet<String>,
+    pub settings: HashMap<String, String>,
+}
+
+/// One change needed to bring a server in line with a profile
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrepareAction {
+    CreateRole(String),
+    CreateExtension(String),
+    SetSetting { name: String, from: Option<String>, to: String },
+}
+
+impl fmt::Display for PrepareAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrepareAction::CreateRole(role) => write!(f, "create role {}", role),
+            PrepareAction::CreateExtension(extension) => write!(f, "create extension {} in {}", extension, TEMPLATE_DB),
+            PrepareAction::SetSetting { name, from: Some(from), to } => write!(f, "set {} from {} to {}", name, from, to),
+            PrepareAction::SetSetting { name, from: None, to } => write!(f, "set {} to {}", name, to),
+        }
+    }
+}
+
+impl PrepareAction {
+    /// SQL statement carrying out the action
+    pub fn sql(&self) -> String {
+        debug!("Building SQL for {}", self);
+        match self {
+            PrepareAction::CreateRole(role) => format!("CREATE ROLE \"{}\";", role),
+            PrepareAction::CreateExtension(extension) => format!("CREATE EXTENSION IF NOT EXISTS \"{}\";", extension),
+            PrepareAction::SetSetting { name, to, .. } => format!("ALTER SYSTEM SET {} = '{}';", name, to.replace('\'', "''")),
+        }
+    }
+}
+
+/// Outcome of preparing a target
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrepareReport {
+    /// Changes made, or that would be made on a dry run
+    pub actions: Vec<PrepareAction>,
+    /// Settings that only take effect after the server is restarted
+    pub pending_restart: Vec<String>,
+}
+
+impl TargetProfile {
+    /// Work out the changes that bring a server in line with this profile
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - Current state of the server
+    ///
+    /// # Returns
+    ///
+    /// The changes in the order they are applied, or an error if an extension
+    /// is not available on the server at all
+    pub fn plan(&self, state: &TargetState) -> Result<Vec<PrepareAction>> {
+        debug!("Planning preparation of target for profile {}", self.name);
+        let mut actions: Vec<PrepareAction> = self.roles
+            .iter()
+            .filter(|role| !state.roles.contains(*role))
+            .map(|role| PrepareAction::CreateRole(role.clone()))
+            .collect();
+
+        let unavailable: Vec<&str> = self.extensions
+            .iter()
+            .filter(|extension| !state.available_extensions.contains(*extension))
+            .map(String::as_str)
+            .collect();
+        if !unavailable.is_empty() {
+            return Err(anyhow!("Extensions not installed on the server: {}", unavailable.join(", ")));
+        }
+        actions.extend(self.extensions
+            .iter()
+            .filter(|extension| !state.extensions.contains(*extension))
+            .map(|extension| PrepareAction::CreateExtension(extension.clone())));
+
+        for (name, value) in &self.settings {
+            let current = state.settings.get(name);
+            if current != Some(value) {
+                actions.push(PrepareAction::SetSetting { name: name.clone(), from: current.cloned(), to: value.clone() });
+            }
+        }
+        Ok(actions)
+    }
+}
+
+/// Read the roles, extensions and settings a profile refers to from the server
+pub async fn collect_state(pg_config: &PostgresConfig, profile: &TargetProfile) -> Result<TargetState> {
+    debug!("Collecting target state for profile {}", profile.name);
+    let mut state = TargetState::default();
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    for row in client.query("SELECT rolname FROM pg_roles", &[]).await? {
+        state.roles.insert(row.get(0));
+    }
+    for row in client.query("SELECT name FROM pg_available_extensions", &[]).await? {
+        state.available_extensions.insert(row.get(0));
+    }
+    for name in profile.settings.keys() {
+        let row = client
+            .query_opt("SELECT current_setting($1, true)", &[name])
+            .await
+            .map_err(|e| anyhow!("Failed to read setting {}: {}", name, e))?;
+        if let Some(value) = row.and_then(|row| row.get::<_, Option<String>>(0)) {
+            state.settings.insert(name.clone(), value);
+        }
+    }
+
+    let template = pg_config.connect_to(TEMPLATE_DB).await?;
+    for row in template.query("SELECT extname FROM pg_extension", &[]).await? {
+        state.extensions.insert(row.get(0));
+    }
+    Ok(state)
+}
+
+/// Converge a target server to a profile
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the target server
+/// * `profile` - The declared state
+/// * `dry_run` - Only work out the changes without making them
+///
+/// # Returns
+///
+/// The changes made, or that would be made on a dry run
+pub async fn prepare_target(pg_config: &PostgresConfig, profile: &TargetProfile, dry_run: bool) -> Result<PrepareReport> {
+    debug!("Preparing target for profile {}, dry run: {}", profile.name, dry_run);
+    let state = collect_state(pg_config, profile).await?;
+    let mut report = PrepareReport { actions: profile.plan(&state)?, pending_restart: Vec::new() };
+    if dry_run || report.actions.is_empty() {
+        return Ok(report);
+    }
+    let actions = &report.actions;
+
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    let template = pg_config.connect_to(TEMPLATE_DB).await?;
+    for action in actions {
+        // Extensions live in a database, the rest is server-wide
+        let target = match action {
+            PrepareAction::CreateExtension(_) => &template,
+            _ => &client,
+        };
+        target
+            .batch_execute(&action.sql())
+            .await
+            .map_err(|e| anyhow!("Failed to {}: {}", action, e))?;
+        info!("Target prepare: {}", action);
+    }
+
+    if actions.iter().any(|action| matches!(action, PrepareAction::SetSetting { .. })) {
+        client.batch_execute("SELECT pg_reload_conf();").await?;
+        for row in client.query("SELECT name FROM pg_settings WHERE pending_restart", &[]).await? {
+            let name: String = row.get(0);
+            warn!("Setting {} only takes effect after a server restart", name);
+            report.pending_restart.push(name);
+        }
+    }
+    Ok(report)
+}
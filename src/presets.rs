@@ -0,0 +1,448 @@
+// This module contains per-database restore presets for the Rustored application
+// Presets are read from a TOML file and keyed by a database name pattern, so
+// that restores of e.g. every `orders*` database get the same pg_restore
+// tuning and post-restore hook without retyping them.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::hooks::{RestoreHook, TemplateVars};
+use crate::prepare::TargetProfile;
+use crate::profiles::ConnectionProfile;
+use crate::recipes::RecipeRef;
+use crate::retention::RetentionPolicy;
+use crate::schedule::ScheduleJob;
+use crate::ui::models::PostgresConfig;
+
+/// Where a PostgreSQL snapshot is restored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RestoreMode {
+    /// A new database: the target database, or a random `<word>-restored` one
+    #[default]
+    #[value(skip)]
+    New,
+    /// Drop the existing database and restore into an empty one of the same name
+    Recreate,
+    /// Restore into the existing database with `pg_restore --clean --if-exists`,
+    /// which drops and reloads the objects in the snapshot and keeps the rest
+    Clean,
+}
+
+impl RestoreMode {
+    /// The mode after this one, for cycling through them in the TUI
+    pub fn next(self) -> Self {
+        debug!("Cycling restore mode from {:?}", self);
+        match self {
+            RestoreMode::New => RestoreMode::Recreate,
+            RestoreMode::Recreate => RestoreMode::Clean,
+            RestoreMode::Clean => RestoreMode::New,
+        }
+    }
+}
+
+impl std::fmt::Display for RestoreMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestoreMode::New => write!(f, "new"),
+            RestoreMode::Recreate => write!(f, "recreate"),
+            RestoreMode::Clean => write!(f, "clean"),
+        }
+    }
+}
+
+/// Seconds a gentle restore pauses between sections when its preset sets no pause
+pub const DEFAULT_SECTION_PAUSE: u64 = 30;
+
+/// Options applied to a single restore
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RestoreOptions {
+    /// Number of parallel `pg_restore` jobs
+    #[serde(default)]
+    pub jobs: Option<u32>,
+    /// Skip the post-data section (indexes, constraints and triggers)
+    #[serde(default)]
+    pub drop_indexes: bool,
+    /// Masking ruleset name handed to the post-restore hook
+    #[serde(default)]
+    pub masking_ruleset: Option<String>,
+    /// URL or shell command run before the restore starts; a failure aborts it
+    #[serde(default)]
+    pub pre_hook: Option<String>,
+    /// URL or shell command run after a successful restore
+    #[serde(default)]
+    pub post_hook: Option<String>,
+    /// SQL run against the restored PostgreSQL database after a successful restore
+    #[serde(default)]
+    pub post_sql: Option<String>,
+    /// Built-in recipes run against the restored PostgreSQL database before `post_sql`
+    #[serde(default)]
+    pub recipes: Vec<RecipeRef>,
+    /// Compare tables and row counts of the restored PostgreSQL database with the live one
+    #[serde(default)]
+    pub compare_live: bool,
+    /// Apply the cluster globals snapshot taken with a PostgreSQL snapshot before restoring it
+    #[serde(default)]
+    pub globals: bool,
+    /// Drop the database a PostgreSQL restore created when the restore is cancelled part way
+    #[serde(default)]
+    pub drop_on_cancel: bool,
+    /// Command template restoring PostgreSQL snapshots that are neither pg_dump archives nor SQL, e.g. `myrestore {file} {db}`
+    #[serde(default)]
+    pub restore_command: Option<String>,
+    /// Row filter file whose predicates decide which rows of a PostgreSQL snapshot are restored
+    #[serde(default)]
+    pub row_filters: Option<String>,
+    /// Restore a PostgreSQL snapshot one section at a time with pauses between, sparing a shared cluster
+    #[serde(default)]
+    pub gentle: bool,
+    /// Seconds a gentle restore pauses between sections, `DEFAULT_SECTION_PAUSE` if unset
+    #[serde(default)]
+    pub section_pause_seconds: Option<u64>,
+    /// Bytes per second a gentle restore copies table data at, e.g. `20M`; unlimited if unset
+    #[serde(default)]
+    pub copy_rate: Option<String>,
+    /// Restore a PostgreSQL snapshot over an existing database instead of into a new one
+    ///
+    /// Never read from a preset, so overwriting a database is always chosen for
+    /// the restore at hand.
+    #[serde(skip)]
+    pub mode: RestoreMode,
+    /// Tables restored from a PostgreSQL snapshot, all if empty, e.g. `orders` or `sales.*`
+    ///
+    /// Chosen for the restore at hand, like `mode`.
+    #[serde(skip)]
+    pub include_tables: Vec<String>,
+    /// Tables left out of a PostgreSQL restore
+    #[serde(skip)]
+    pub exclude_tables: Vec<String>,
+}
+
+impl RestoreOptions {
+    /// Extra `pg_restore` arguments for these options
+    pub fn pg_restore_args(&self) -> Vec<String> {
+        debug!("Building pg_restore arguments for {:?}", self);
+        let mut args = Vec::new();
+        if let Some(jobs) = self.jobs {
+            args.push("--jobs".to_string());
+            args.push(jobs.to_string());
+        }
+        if self.drop_indexes {
+            // Restoring only these sections leaves out indexes, constraints and triggers
+            args.extend(["--section", "pre-data", "--section", "data"].map(String::from));
+        }
+        args
+    }
+
+    /// One-line summary of the options for display
+    pub fn summary(&self) -> String {
+        debug!("Summarising restore options");
+        let mut parts = vec![match self.jobs {
+            Some(jobs) => format!("jobs={}", jobs),
+            None => "jobs=default".to_string(),
+        }];
+        if self.drop_indexes {
+            parts.push("drop indexes".to_string());
+        }
+        if self.globals {
+            parts.push("globals".to_string());
+        }
+        if let Some(ruleset) = &self.masking_ruleset {
+            parts.push(format!("masking={}", ruleset));
+        }
+        if let Some(hook) = &self.pre_hook {
+            parts.push(format!("pre-hook={}", hook));
+        }
+        if let Some(hook) = &self.post_hook {
+            parts.push(format!("post-hook={}", hook));
+        }
+        if !self.recipes.is_empty() {
+            let names: Vec<&str> = self.recipes.iter().map(|recipe| recipe.name.as_str()).collect();
+            parts.push(format!("recipes={}", names.join(",")));
+        }
+        if self.post_sql.is_some() {
+            parts.push("post-sql".to_string());
+        }
+        if self.compare_live {
+            parts.push("compare with live".to_string());
+        }
+        if self.drop_on_cancel {
+            parts.push("drop if cancelled".to_string());
+        }
+        if let Some(command) = &self.restore_command {
+            parts.push(format!("restore-command={}", command));
+        }
+        if let Some(filters) = &self.row_filters {
+            parts.push(format!("row-filters={}", filters));
+        }
+        if let Some(pause) = self.section_pause() {
+            match &self.copy_rate {
+                Some(rate) => parts.push(format!("gentle (pause {}s, copy {}/s)", pause.as_secs(), rate)),
+                None => parts.push(format!("gentle (pause {}s)", pause.as_secs())),
+            }
+        }
+        if self.mode != RestoreMode::New {
+            parts.push(format!("into existing ({})", self.mode));
+        }
+        if !self.include_tables.is_empty() {
+            parts.push(format!("tables={}", self.include_tables.join(",")));
+        }
+        if !self.exclude_tables.is_empty() {
+            parts.push(format!("excluding={}", self.exclude_tables.join(",")));
+        }
+        parts.join(", ")
+    }
+
+    /// Pause between the sections of a gentle restore, `None` unless the restore is gentle
+    pub fn section_pause(&self) -> Option<Duration> {
+        debug!("Reading section pause of restore options");
+        self.gentle.then(|| Duration::from_secs(self.section_pause_seconds.unwrap_or(DEFAULT_SECTION_PAUSE)))
+    }
+
+    /// Bytes per second table data is copied at, `None` unless a gentle restore caps it
+    pub fn copy_rate_limit(&self) -> Result<Option<u64>> {
+        debug!("Reading copy rate of restore options");
+        match &self.copy_rate {
+            Some(rate) if self.gentle => Ok(Some(crate::bandwidth::parse_rate(rate).map_err(|e| anyhow!("Invalid copy_rate: {}", e))?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Run the pre-restore hook, if one is set
+    ///
+    /// It runs like the post-restore hook with the `pre_restore` event, once
+    /// the maintenance window is entered and before anything is restored.
+    pub async fn run_pre_hook(&self, vars: &TemplateVars) -> Result<()> {
+        debug!("Running pre-restore hook for {}", vars.restored_db);
+        self.run_hook(self.pre_hook.as_deref(), "pre_restore", vars).await
+    }
+
+    /// Run the post-restore hook, if one is set
+    ///
+    /// `{{name}}` placeholders in the hook are expanded from `vars` first,
+    /// shell-quoted in a command hook. The hook receives the `post_restore`
+    /// event, the template variables and the masking ruleset, as JSON fields
+    /// or as `RUSTORED_<NAME>` environment variables.
+    pub async fn run_post_hook(&self, vars: &TemplateVars) -> Result<()> {
+        debug!("Running post-restore hook for {}", vars.restored_db);
+        self.run_hook(self.post_hook.as_deref(), "post_restore", vars).await
+    }
+
+    async fn run_hook(&self, spec: Option<&str>, event: &str, vars: &TemplateVars) -> Result<()> {
+        debug!("Running {} hook for {}", event, vars.restored_db);
+        let Some(spec) = spec else {
+            return Ok(());
+        };
+        let hook = match RestoreHook::parse(spec) {
+            Some(RestoreHook::Command(command)) => RestoreHook::Command(vars.expand_command(&command)?),
+            Some(RestoreHook::Url(url)) => RestoreHook::Url(vars.expand(&url)?),
+            None => return Ok(()),
+        };
+        let ruleset = self.masking_ruleset.clone().unwrap_or_default();
+        let mut pairs = vars.pairs().to_vec();
+        pairs.push(("masking_ruleset", &ruleset));
+        hook.run_with_vars(event, &vars.restored_db, &pairs).await
+    }
+
+    /// Run the chosen recipes and then the post-restore SQL, if set, against the restored database
+    ///
+    /// `{{name}}` placeholders in the post-restore SQL are expanded from `vars` before it is sent,
+    /// database names as quoted identifiers and the other values as string literals.
+    pub async fn run_post_sql(&self, pg_config: &PostgresConfig, vars: &TemplateVars) -> Result<()> {
+        debug!("Running post-restore SQL for {}", vars.restored_db);
+        for recipe in &self.recipes {
+            let sql = recipe.render()?;
+            pg_config.execute_sql(&vars.restored_db, &sql).await
+                .map_err(|e| anyhow!("Recipe {} failed on {}: {}", recipe.name, vars.restored_db, e))?;
+            info!("Recipe {} completed for {}", recipe.name, vars.restored_db);
+        }
+        let Some(sql) = &self.post_sql else {
+            return Ok(());
+        };
+        let sql = vars.expand_sql(sql)?;
+        pg_config.execute_sql(&vars.restored_db, &sql).await?;
+        info!("Post-restore SQL completed for {}", vars.restored_db);
+        Ok(())
+    }
+}
+
+/// A preset applied to databases whose name matches `pattern`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestorePreset {
+    /// Glob pattern where `*` matches any run of characters and `?` one character
+    pub pattern: String,
+    #[serde(flatten)]
+    pub options: RestoreOptions,
+}
+
+/// Schema of a `[[preset]]` table: its pattern and the keys of `RestoreOptions`
+const PRESET_SCHEMA: TableSchema = TableSchema {
+    name: "[[preset]]",
+    fields: &[
+        Field::required("pattern", Kind::String),
+        Field::optional("jobs", Kind::Integer { min: 1 }),
+        Field::optional("drop_indexes", Kind::Bool),
+        Field::optional("masking_ruleset", Kind::String),
+        Field::optional("pre_hook", Kind::String),
+        Field::optional("post_hook", Kind::String),
+        Field::optional("post_sql", Kind::String),
+        Field::optional("recipes", Kind::Recipes),
+        Field::optional("compare_live", Kind::Bool),
+        Field::optional("globals", Kind::Bool),
+        Field::optional("drop_on_cancel", Kind::Bool),
+        Field::optional("restore_command", Kind::String),
+        Field::optional("row_filters", Kind::String),
+        Field::optional("gentle", Kind::Bool),
+        Field::optional("section_pause_seconds", Kind::Integer { min: 0 }),
+        Field::optional("copy_rate", Kind::String),
+    ],
+    // The ruleset is only handed to the post-restore hook, and pacing only applies to gentle restores
+    requires: &[("masking_ruleset", "post_hook"), ("section_pause_seconds", "gentle"), ("copy_rate", "gentle")],
+    unique: Some("pattern"),
+};
+
+/// Schema of the presets file, checked before it is deserialized
+pub const SCHEMA: TableSchema = TableSchema {
+    name: "the presets file",
+    fields: &[
+        Field::optional("preset", Kind::Tables(&PRESET_SCHEMA)),
+        Field::optional("target", Kind::Tables(&crate::prepare::PROFILE_SCHEMA)),
+        Field::optional("schedule", Kind::Tables(&crate::schedule::JOB_SCHEMA)),
+        Field::optional("gc", Kind::Tables(&crate::gc::GC_SCHEMA)),
+        Field::optional("retention", Kind::Tables(&crate::retention::POLICY_SCHEMA)),
+        Field::optional("profile", Kind::Tables(&crate::profiles::PROFILE_SCHEMA)),
+        Field::optional("naming_template", Kind::String),
+    ],
+    requires: &[],
+    unique: None,
+};
+
+/// All configured restore presets, in file order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RestorePresets {
+    #[serde(default, rename = "preset")]
+    pub presets: Vec<RestorePreset>,
+    /// Target profiles for `target prepare`
+    #[serde(default, rename = "target")]
+    pub targets: Vec<TargetProfile>,
+    /// Backup schedules for `schedule`
+    #[serde(default, rename = "schedule")]
+    pub schedules: Vec<ScheduleJob>,
+    /// Throwaway restore cleanups run by `schedule`
+    #[serde(default, rename = "gc")]
+    pub gc: Vec<crate::gc::GcSchedule>,
+    /// Retention rules for `prune` and the schedule daemon
+    #[serde(default, rename = "retention")]
+    pub retention: Vec<RetentionPolicy>,
+    /// Connection profiles for `--profile`
+    #[serde(default, rename = "profile")]
+    pub profiles: Vec<ConnectionProfile>,
+    /// Naming template snapshot keys should follow, for `lint-bucket`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub naming_template: Option<String>,
+    /// File the presets were loaded from, if any
+    #[serde(skip)]
+    pub source: Option<std::path::PathBuf>,
+}
+
+impl RestorePresets {
+    /// Parse presets from TOML
+    ///
+    /// ```toml
+    /// [[preset]]
+    /// pattern = "orders*"
+    /// jobs = 8
+    /// drop_indexes = true
+    /// recipes = ["truncate-audit-tables"]
+    /// ```
+    pub fn parse(contents: &str) -> Result<Self> {
+        debug!("Parsing restore presets");
+        crate::config_schema::validate(contents, &SCHEMA, "restore presets")?;
+        let presets: Self = toml::from_str(contents).map_err(|e| anyhow!("Invalid restore presets: {}", e))?;
+        // Catch unknown recipes and bad parameters before a restore needs them
+        for preset in &presets.presets {
+            for recipe in &preset.options.recipes {
+                recipe.render().map_err(|e| anyhow!("Invalid preset {}: {}", preset.pattern, e))?;
+            }
+            preset.options.copy_rate_limit().map_err(|e| anyhow!("Invalid preset {}: {}", preset.pattern, e))?;
+        }
+        Ok(presets)
+    }
+
+    /// Load presets from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading restore presets from {:?}", path);
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read restore presets {}: {}", path.display(), e))?;
+        let mut presets = Self::parse(&contents).with_context(|| format!("Failed to load {}", path.display()))?;
+        presets.source = Some(path.to_path_buf());
+        info!("Loaded {} restore presets from {}", presets.presets.len(), path.display());
+        Ok(presets)
+    }
+
+    /// Find a connection profile by name
+    pub fn profile(&self, name: &str) -> Result<&ConnectionProfile> {
+        debug!("Finding connection profile {}", name);
+        if self.source.is_none() {
+            return Err(anyhow!("--profile {} needs a presets file with --presets-file", name));
+        }
+        self.profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| anyhow!("No profile named {} in the presets file", name))
+    }
+
+    /// Find a target profile by name, or the only one when no name is given
+    pub fn target_profile(&self, name: Option<&str>) -> Result<&TargetProfile> {
+        debug!("Finding target profile {:?}", name);
+        match (name, self.targets.as_slice()) {
+            (Some(name), targets) => targets
+                .iter()
+                .find(|profile| profile.name == name)
+                .ok_or_else(|| anyhow!("No target profile named {}", name)),
+            (None, [profile]) => Ok(profile),
+            (None, []) => Err(anyhow!("No target profiles are defined in the presets file")),
+            (None, _) => Err(anyhow!("Several target profiles are defined, choose one with --profile")),
+        }
+    }
+
+    /// Find the first preset whose pattern matches `name`
+    ///
+    /// Snapshot keys are matched on their file name, so `orders*` matches
+    /// `backups/orders-20250101.dump`.
+    pub fn find(&self, name: &str) -> Option<&RestorePreset> {
+        debug!("Finding restore preset for {}", name);
+        let name = name.rsplit('/').next().unwrap_or(name);
+        self.presets.iter().find(|preset| matches_pattern(&preset.pattern, name))
+    }
+}
+
+/// Match `name` against a glob `pattern` supporting `*` and `?`
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    debug!("Matching {} against pattern {}", name, pattern);
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name index it is currently matching up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the last `*` swallow one more character and retry
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
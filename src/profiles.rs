@@ -0,0 +1,299 @@
+// This module contains connection profiles, named sets of the PostgreSQL and
+// S3 settings otherwise passed as flags or PG_*/S3_* environment variables.
+// Profiles live in the presets file and are picked with `--profile`; flags and
+// environment variables still win over them. `config import-env` writes the
+// current environment out as a profile so existing setups can move over. A
+// profile's `environment` is shown as a banner across the TUI, and a production
+// one asks for its name before anything destructive. A profile may also list
+// several PostgreSQL servers, e.g. staging-a, staging-b and dev; the TUI then
+// asks which one to restore onto before each restore.
+
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::presets::RestorePresets;
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Settings carried by a profile and the environment variables they replace
+pub const PROFILE_VARS: &[(&str, &str)] = &[
+    ("host", "PG_HOST"),
+    ("port", "PG_PORT"),
+    ("username", "PG_USERNAME"),
+    ("db_name", "PG_DB_NAME"),
+    ("use_ssl", "PG_USE_SSL"),
+    ("target_db", "PG_TARGET_DB"),
+    ("bucket", "S3_BUCKET"),
+    ("region", "S3_REGION"),
+    ("prefix", "S3_PREFIX"),
+    ("endpoint_url", "S3_ENDPOINT_URL"),
+    ("access_key_id", "S3_ACCESS_KEY_ID"),
+    ("path_style", "S3_PATH_STYLE"),
+];
+
+/// Environment names treated as production, compared without case
+pub const PRODUCTION_ENVIRONMENTS: &[&str] = &["production", "prod"];
+
+/// Environment variables holding secrets, which are never written to a profile
+pub const SECRET_VARS: &[&str] = &["PG_PASSWORD", "S3_SECRET_ACCESS_KEY", "S3_SSE_CUSTOMER_KEY"];
+
+/// Named connection settings, declared in the presets file
+///
+/// ```toml
+/// [[profile]]
+/// name = "staging"
+/// environment = "staging"
+/// host = "db.staging.internal"
+/// bucket = "acme-backups"
+/// prefix = "postgres"
+///
+/// [[profile.server]]
+/// name = "staging-b"
+/// host = "db-b.staging.internal"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    /// Name the profile is selected by with `--profile`
+    pub name: String,
+    /// Environment the settings point at, e.g. `production`, shown as a banner in the TUI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_ssl: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_db: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_style: Option<bool>,
+    /// PostgreSQL servers to choose from before each restore in the TUI
+    #[serde(default, rename = "server", skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<TargetServer>,
+}
+
+/// A PostgreSQL server a profile can restore onto, declared as `[[profile.server]]`
+///
+/// Settings it leaves out keep the values of the profile, flags and environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetServer {
+    /// Name shown in the server picker
+    pub name: String,
+    /// Environment of the server, taking over the profile's while it is picked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_ssl: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_db: Option<String>,
+}
+
+impl TargetServer {
+    /// Line describing the server in the picker, e.g. `staging-a  db-a.internal:5432 (staging)`
+    pub fn label(&self) -> String {
+        debug!("Building label for server {}", self.name);
+        let address = match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.clone(),
+        };
+        match &self.environment {
+            Some(environment) => format!("{:<16} {} ({})", self.name, address, environment),
+            None => format!("{:<16} {}", self.name, address),
+        }
+    }
+}
+
+/// Schema of a `[[profile.server]]` table
+const SERVER_SCHEMA: TableSchema = TableSchema {
+    name: "[[profile.server]]",
+    fields: &[
+        Field::required("name", Kind::String),
+        Field::optional("environment", Kind::String),
+        Field::required("host", Kind::String),
+        Field::optional("port", Kind::Integer { min: 1 }),
+        Field::optional("username", Kind::String),
+        Field::optional("use_ssl", Kind::Bool),
+        Field::optional("target_db", Kind::String),
+    ],
+    requires: &[],
+    unique: Some("name"),
+};
+
+/// Schema of a `[[profile]]` table in the presets file
+pub(crate) const PROFILE_SCHEMA: TableSchema = TableSchema {
+    name: "[[profile]]",
+    fields: &[
+        Field::required("name", Kind::String),
+        Field::optional("environment", Kind::String),
+        Field::optional("host", Kind::String),
+        Field::optional("port", Kind::Integer { min: 1 }),
+        Field::optional("username", Kind::String),
+        Field::optional("db_name", Kind::String),
+        Field::optional("use_ssl", Kind::Bool),
+        Field::optional("target_db", Kind::String),
+        Field::optional("bucket", Kind::String),
+        Field::optional("region", Kind::String),
+        Field::optional("prefix", Kind::String),
+        Field::optional("endpoint_url", Kind::String),
+        Field::optional("access_key_id", Kind::String),
+        Field::optional("path_style", Kind::Bool),
+        Field::optional("server", Kind::Tables(&SERVER_SCHEMA)),
+    ],
+    requires: &[],
+    unique: Some("name"),
+};
+
+/// A profile built from environment variables, and the variables it leaves out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedProfile {
+    pub profile: ConnectionProfile,
+    /// Set PG_*/S3_* variables a profile cannot hold, secrets included
+    pub left_out: Vec<String>,
+}
+
+/// Parse a boolean the way the `true`/`false` flags accept it
+fn parse_bool(var: &str, value: &str) -> Result<bool> {
+    debug!("Parsing {} as a boolean", var);
+    match value.to_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(anyhow!("{} must be true or false, not {}", var, value)),
+    }
+}
+
+/// Build a profile from PG_*/S3_* environment variables
+///
+/// Empty variables are treated as unset. Secrets and variables a profile has no
+/// setting for are listed in `left_out`, so they can be kept in the environment.
+///
+/// # Arguments
+///
+/// * `name` - Name of the new profile
+/// * `vars` - Environment variables, e.g. `std::env::vars()` after the `.env` file is loaded
+///
+/// # Returns
+///
+/// The profile and the variables left out of it
+pub fn from_env(name: &str, vars: impl IntoIterator<Item = (String, String)>) -> Result<ImportedProfile> {
+    debug!("Building profile {} from environment variables", name);
+    let vars: BTreeMap<String, String> = vars
+        .into_iter()
+        .filter(|(var, value)| (var.starts_with("PG_") || var.starts_with("S3_")) && !value.is_empty())
+        .collect();
+    let get = |var: &str| vars.get(var).cloned();
+
+    let profile = ConnectionProfile {
+        name: name.to_string(),
+        environment: None,
+        host: get("PG_HOST"),
+        port: get("PG_PORT")
+            .map(|port| port.parse().map_err(|_| anyhow!("PG_PORT must be a port number, not {}", port)))
+            .transpose()?,
+        username: get("PG_USERNAME"),
+        db_name: get("PG_DB_NAME"),
+        use_ssl: get("PG_USE_SSL").map(|value| parse_bool("PG_USE_SSL", &value)).transpose()?,
+        target_db: get("PG_TARGET_DB"),
+        bucket: get("S3_BUCKET"),
+        region: get("S3_REGION"),
+        prefix: get("S3_PREFIX"),
+        endpoint_url: get("S3_ENDPOINT_URL"),
+        access_key_id: get("S3_ACCESS_KEY_ID"),
+        path_style: get("S3_PATH_STYLE").map(|value| parse_bool("S3_PATH_STYLE", &value)).transpose()?,
+        servers: Vec::new(),
+    };
+    let left_out = vars
+        .keys()
+        .filter(|var| !PROFILE_VARS.iter().any(|(_, profile_var)| profile_var == var))
+        .cloned()
+        .collect();
+    Ok(ImportedProfile { profile, left_out })
+}
+
+/// Whether an environment is production, whose destructive actions need an extra confirmation
+pub fn is_production(environment: &str) -> bool {
+    debug!("Checking if environment {} is production", environment);
+    PRODUCTION_ENVIRONMENTS.iter().any(|name| name.eq_ignore_ascii_case(environment.trim()))
+}
+
+/// Text of the banner shown across the top of the TUI, e.g. `PRODUCTION — us-east-1`
+///
+/// # Arguments
+///
+/// * `environment` - Environment of the connection settings
+/// * `region` - S3 region of the snapshots, left out when empty
+pub fn banner(environment: &str, region: &str) -> String {
+    debug!("Building banner for environment {}", environment);
+    match region.trim() {
+        "" => environment.trim().to_uppercase(),
+        region => format!("{} — {}", environment.trim().to_uppercase(), region),
+    }
+}
+
+/// Render a profile as a `[[profile]]` table
+pub fn to_toml(profile: &ConnectionProfile) -> Result<String> {
+    debug!("Rendering profile {} as TOML", profile.name);
+    #[derive(Serialize)]
+    struct File<'a> {
+        profile: [&'a ConnectionProfile; 1],
+    }
+    toml::to_string(&File { profile: [profile] }).map_err(|e| anyhow!("Failed to render profile {}: {}", profile.name, e))
+}
+
+/// Append a profile to a presets file, creating the file if needed
+///
+/// The rest of the file, comments included, is left as it is. A profile with
+/// the same name is never replaced, and the result is checked like any presets
+/// file before it is written.
+///
+/// # Arguments
+///
+/// * `path` - Presets file to add the profile to
+/// * `profile` - Profile to add
+pub fn write_profile(path: &Path, profile: &ConnectionProfile) -> Result<()> {
+    debug!("Writing profile {} to {}", profile.name, path.display());
+    let mut contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+    if !contents.trim().is_empty() {
+        let existing = RestorePresets::parse(&contents).map_err(|e| anyhow!("Failed to load {}: {}", path.display(), e))?;
+        if existing.profiles.iter().any(|other| other.name == profile.name) {
+            return Err(anyhow!(
+                "{} already has a profile named {}; remove it or choose another name with --name",
+                path.display(),
+                profile.name
+            ));
+        }
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push('\n');
+    }
+    contents.push_str(&to_toml(profile)?);
+    RestorePresets::parse(&contents)?;
+    std::fs::write(path, contents).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    info!("Wrote profile {} to {}", profile.name, path.display());
+    Ok(())
+}
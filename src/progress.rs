@@ -0,0 +1,139 @@
+// This module contains progress reporting for the Rustored application
+// Transfers and restores can report progress far faster than the UI redraws.
+// Rather than queueing every update, the board keeps only the latest progress
+// of each job in a watch channel: reporting never blocks or allocates per
+// update, and the renderer always reads the current state however far behind
+// it is. The board also carries the transfer budget its jobs share (see
+// `crate::bandwidth`).
+
+use crate::bandwidth::TransferBudget;
+use log::debug;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Latest progress of one job
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    /// Share of the job done, from 0.0 to 1.0
+    pub fraction: f32,
+    /// Transfer rate in bytes per second, 0.0 when not known
+    pub rate: f64,
+}
+
+/// Latest progress of every running job, keyed by job name
+///
+/// Cloning the board shares it, so reporters and the renderer can live on
+/// different tasks.
+#[derive(Debug, Clone)]
+pub struct ProgressBoard {
+    jobs: Arc<watch::Sender<BTreeMap<String, Progress>>>,
+    budget: TransferBudget,
+}
+
+impl Default for ProgressBoard {
+    fn default() -> Self {
+        debug!("Creating progress board");
+        Self { jobs: Arc::new(watch::Sender::new(BTreeMap::new())), budget: TransferBudget::default() }
+    }
+}
+
+impl ProgressBoard {
+    /// Start reporting progress for a job, replacing any earlier job of that name
+    pub fn reporter(&self, job: &str) -> ProgressReporter {
+        debug!("Starting progress reporting for {}", job);
+        self.jobs.send_modify(|jobs| {
+            jobs.insert(job.to_string(), Progress::default());
+        });
+        ProgressReporter { board: self.clone(), job: job.to_string() }
+    }
+
+    /// Latest progress of a job, if it is running
+    pub fn latest(&self, job: &str) -> Option<Progress> {
+        debug!("Reading latest progress of {}", job);
+        self.jobs.borrow().get(job).copied()
+    }
+
+    /// Latest progress of every running job
+    pub fn jobs(&self) -> Vec<(String, Progress)> {
+        debug!("Reading latest progress of all jobs");
+        self.jobs.borrow().iter().map(|(job, progress)| (job.clone(), *progress)).collect()
+    }
+
+    /// Receiver that is notified when any job's progress changes
+    ///
+    /// However many updates arrive between two reads, the receiver sees one
+    /// change and the latest values.
+    pub fn subscribe(&self) -> watch::Receiver<BTreeMap<String, Progress>> {
+        debug!("Subscribing to progress updates");
+        self.jobs.subscribe()
+    }
+
+    /// Transfer budget shared by the board's jobs
+    pub fn budget(&self) -> &TransferBudget {
+        debug!("Getting transfer budget of the progress board");
+        &self.budget
+    }
+
+    /// Combined transfer rate of every running job, in bytes per second
+    pub fn total_rate(&self) -> f64 {
+        debug!("Summing transfer rates of all jobs");
+        self.jobs.borrow().values().map(|progress| progress.rate).sum()
+    }
+
+    /// Stop tracking a job, and give its share of the transfer budget to the others
+    pub fn finish(&self, job: &str) {
+        debug!("Finishing progress reporting for {}", job);
+        self.budget.release(job);
+        self.jobs.send_if_modified(|jobs| jobs.remove(job).is_some());
+    }
+}
+
+/// Handle a job reports its progress through
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    board: ProgressBoard,
+    job: String,
+}
+
+impl ProgressReporter {
+    /// Name of the job being reported on
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+
+    /// Replace the job's progress, waking subscribers only if it changed
+    pub fn report(&self, progress: Progress) {
+        // No logging here: fast transfers report thousands of times a second
+        self.board.jobs.send_if_modified(|jobs| match jobs.get_mut(&self.job) {
+            Some(current) if *current != progress => {
+                *current = progress;
+                true
+            }
+            _ => false,
+        });
+    }
+
+    /// Report the share of the job done, keeping the last known rate
+    pub fn fraction(&self, fraction: f32) {
+        let rate = self.board.latest(&self.job).map(|p| p.rate).unwrap_or_default();
+        self.report(Progress { fraction: fraction.clamp(0.0, 1.0), rate });
+    }
+
+    /// Account for bytes the job transferred, waiting for its share of the transfer budget
+    pub async fn transfer(&self, bytes: u64) {
+        self.board.budget.take(&self.job, bytes).await;
+    }
+
+    /// Callback for APIs that report progress as a fraction, such as restore targets
+    pub fn callback(&self) -> Box<dyn Fn(f32) + Send + Sync> {
+        debug!("Creating progress callback for {}", self.job);
+        let reporter = self.clone();
+        Box::new(move |fraction| reporter.fraction(fraction))
+    }
+
+    /// Stop tracking the job
+    pub fn finish(&self) {
+        self.board.finish(&self.job);
+    }
+}
@@ -0,0 +1,392 @@
+// This module contains the promote action for the Rustored application
+// PostgreSQL snapshots are always restored into a new database. Once that copy
+// has been checked, promoting it renames the live database aside (it is kept,
+// not dropped), renames the restored database to the production name and
+// optionally hands it the live database's owner and database-level grants.
+// Each promotion is recorded, with the server it happened on, in a JSON history
+// object in the snapshot bucket, so it can be rolled back by swapping the names
+// again within a time window.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::postgres;
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::PostgresConfig;
+
+/// Key of the promotion history object in the snapshot bucket
+pub const PROMOTION_HISTORY_KEY: &str = ".rustored/promotions.json";
+
+/// Default number of hours a promotion can be rolled back for
+pub const DEFAULT_ROLLBACK_WINDOW_HOURS: i64 = 24;
+
+/// Database connected to while renaming, since a database cannot be renamed while connected to it
+pub const MAINTENANCE_DB: &str = "postgres";
+
+/// Longest database name PostgreSQL keeps without truncating it
+const MAX_IDENTIFIER_LEN: usize = 63;
+
+/// A promotion of a restored database to the production name
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromotePlan {
+    /// Production name, currently held by the live database
+    pub live_db: String,
+    /// Database restored from a snapshot that takes over the production name
+    pub restored_db: String,
+    /// Name the live database is renamed to and kept under
+    pub retired_db: String,
+    /// Whether the promoted database gets the live database's owner and grants
+    pub transfer_ownership: bool,
+}
+
+impl PromotePlan {
+    /// Plan the promotion of `restored_db` to `live_db`
+    ///
+    /// # Arguments
+    ///
+    /// * `live_db` - Production name, currently held by the live database
+    /// * `restored_db` - Database restored from a snapshot
+    /// * `now` - Time of the promotion, used to name the retired database
+    pub fn new(live_db: &str, restored_db: &str, now: DateTime<Utc>) -> Self {
+        debug!("Planning promotion of {} to {}", restored_db, live_db);
+        Self {
+            live_db: live_db.to_string(),
+            restored_db: restored_db.to_string(),
+            retired_db: retired_name(live_db, now),
+            transfer_ownership: false,
+        }
+    }
+
+    /// Describe what the promotion will do, one step per line
+    pub fn steps(&self) -> Vec<String> {
+        debug!("Describing promotion of {} to {}", self.restored_db, self.live_db);
+        let mut steps = vec![
+            format!("Disconnect all sessions from {} and {}", self.live_db, self.restored_db),
+            format!("Rename live {} to {} (kept)", self.live_db, self.retired_db),
+            format!("Rename restored {} to {}", self.restored_db, self.live_db),
+        ];
+        if self.transfer_ownership {
+            steps.push(format!("Give {} the owner and grants of the old {}", self.live_db, self.live_db));
+        }
+        steps
+    }
+}
+
+/// Name the live database is kept under once a restored copy is promoted
+///
+/// The live name is shortened when needed so the result fits in a PostgreSQL
+/// identifier, e.g. `orders` becomes `orders-retired-20250101120000`.
+pub fn retired_name(live_db: &str, now: DateTime<Utc>) -> String {
+    debug!("Computing retired name for {}", live_db);
+    let suffix = format!("-retired-{}", now.format("%Y%m%d%H%M%S"));
+    let mut base = live_db.to_string();
+    while base.len() + suffix.len() > MAX_IDENTIFIER_LEN {
+        base.pop();
+    }
+    format!("{}{}", base, suffix)
+}
+
+/// Statement granting a database privilege, as read from the live database's ACL
+///
+/// # Arguments
+///
+/// * `db_name` - Database the privilege is granted on
+/// * `grantee` - Role name, or `PUBLIC`
+/// * `privilege` - Privilege such as `CONNECT`, `CREATE` or `TEMPORARY`
+/// * `grantable` - Whether the grantee may pass the privilege on
+pub fn grant_statement(db_name: &str, grantee: &str, privilege: &str, grantable: bool) -> String {
+    debug!("Building grant of {} on {} to {}", privilege, db_name, grantee);
+    let grantee = match grantee {
+        "PUBLIC" => grantee.to_string(),
+        role => format!("\"{}\"", role),
+    };
+    let option = if grantable { " WITH GRANT OPTION" } else { "" };
+    format!("GRANT {} ON DATABASE \"{}\" TO {}{};", privilege, db_name, grantee, option)
+}
+
+/// Whether a database exists on the server
+async fn database_exists(client: &tokio_postgres::Client, name: &str) -> Result<bool> {
+    debug!("Checking if database {} exists", name);
+    Ok(client.query_opt("SELECT 1 FROM pg_database WHERE datname = $1", &[&name]).await?.is_some())
+}
+
+/// Disconnect every other session from a database so it can be renamed
+async fn disconnect_sessions(client: &tokio_postgres::Client, name: &str) -> Result<()> {
+    debug!("Disconnecting sessions from {}", name);
+    client
+        .execute(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE datname = $1 AND pid <> pg_backend_pid()",
+            &[&name],
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to disconnect sessions from {}: {}", name, e))?;
+    Ok(())
+}
+
+/// Read the owner and database-level grants of a database as SQL statements for another database
+async fn ownership_statements(client: &tokio_postgres::Client, from_db: &str, to_db: &str) -> Result<Vec<String>> {
+    debug!("Reading owner and grants of {}", from_db);
+    let owner: String = client
+        .query_one("SELECT pg_get_userbyid(datdba) FROM pg_database WHERE datname = $1", &[&from_db])
+        .await?
+        .get(0);
+    let mut statements = vec![format!("ALTER DATABASE \"{}\" OWNER TO \"{}\";", to_db, owner)];
+    let grants = client
+        .query(
+            "SELECT CASE WHEN a.grantee = 0 THEN 'PUBLIC' ELSE pg_get_userbyid(a.grantee) END, a.privilege_type, a.is_grantable \
+             FROM pg_database d, aclexplode(d.datacl) a WHERE d.datname = $1",
+            &[&from_db],
+        )
+        .await?;
+    for row in grants {
+        let grantee: String = row.get(0);
+        let privilege: String = row.get(1);
+        statements.push(grant_statement(to_db, &grantee, &privilege, row.get(2)));
+    }
+    Ok(statements)
+}
+
+/// Promote a restored database to the production name
+///
+/// The live database is renamed aside rather than dropped. If the restored
+/// database cannot take over the production name, the live database is
+/// renamed back before the error is returned.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server holding both databases
+/// * `plan` - The promotion to carry out
+pub async fn promote(pg_config: &PostgresConfig, plan: &PromotePlan) -> Result<()> {
+    debug!("Promoting {} to {}", plan.restored_db, plan.live_db);
+    if plan.live_db == plan.restored_db {
+        return Err(anyhow!("{} is already the live database", plan.restored_db));
+    }
+    let client = pg_config.connect_to(MAINTENANCE_DB).await?;
+    if !database_exists(&client, &plan.restored_db).await? {
+        return Err(anyhow!("Restored database {} does not exist", plan.restored_db));
+    }
+    if database_exists(&client, &plan.retired_db).await? {
+        return Err(anyhow!("Database {} already exists, refusing to overwrite it", plan.retired_db));
+    }
+    let live_exists = database_exists(&client, &plan.live_db).await?;
+
+    // Read the live database's ownership before its name moves
+    let ownership = match (plan.transfer_ownership, live_exists) {
+        (true, true) => ownership_statements(&client, &plan.live_db, &plan.live_db).await?,
+        (true, false) => {
+            warn!("No live database {} to take the owner and grants from", plan.live_db);
+            Vec::new()
+        }
+        (false, _) => Vec::new(),
+    };
+
+    disconnect_sessions(&client, &plan.restored_db).await?;
+    if live_exists {
+        disconnect_sessions(&client, &plan.live_db).await?;
+        postgres::rename_database(&client, &plan.live_db, &plan.retired_db).await?;
+    }
+    if let Err(e) = postgres::rename_database(&client, &plan.restored_db, &plan.live_db).await {
+        if live_exists {
+            warn!("Putting {} back after failed promotion", plan.live_db);
+            postgres::rename_database(&client, &plan.retired_db, &plan.live_db).await?;
+        }
+        return Err(e);
+    }
+
+    for statement in &ownership {
+        client
+            .batch_execute(statement)
+            .await
+            .map_err(|e| anyhow!("Promoted {}, but transferring ownership failed: {}", plan.live_db, e))?;
+    }
+    info!("Promoted {} to {}, previous database kept as {}", plan.restored_db, plan.live_db, plan.retired_db);
+    Ok(())
+}
+
+/// A promotion recorded in the history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Promotion {
+    /// Server the promotion happened on, as `host:port`
+    #[serde(default)]
+    pub server: String,
+    /// Production name the restored database took over
+    pub live_db: String,
+    /// Name the promoted database had before the promotion
+    pub restored_db: String,
+    /// Name the previous live database was kept under
+    pub retired_db: String,
+    /// Whether ownership and grants were transferred
+    pub transfer_ownership: bool,
+    /// When the promotion happened
+    pub promoted_at: DateTime<Utc>,
+    /// When the promotion was rolled back, if it was
+    #[serde(default)]
+    pub rolled_back_at: Option<DateTime<Utc>>,
+}
+
+/// History of every promotion on the server, newest last
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromotionHistory {
+    pub promotions: Vec<Promotion>,
+}
+
+impl PromotionHistory {
+    /// Parse a history from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing promotion history ({} bytes)", bytes.len());
+        crate::migration::from_json(crate::migration::MetadataKind::Promotions, bytes)
+    }
+
+    /// Serialize the history to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing promotion history with {} entries", self.promotions.len());
+        crate::migration::to_json(self)
+    }
+
+    /// Record a completed promotion on `server`
+    pub fn record(&mut self, server: &str, plan: &PromotePlan, now: DateTime<Utc>) {
+        debug!("Recording promotion of {} to {} on {}", plan.restored_db, plan.live_db, server);
+        self.promotions.push(Promotion {
+            server: server.to_string(),
+            live_db: plan.live_db.clone(),
+            restored_db: plan.restored_db.clone(),
+            retired_db: plan.retired_db.clone(),
+            transfer_ownership: plan.transfer_ownership,
+            promoted_at: now,
+            rolled_back_at: None,
+        });
+    }
+
+    /// Find the promotion of `live_db` on `server` that a rollback would undo
+    ///
+    /// Only the latest promotion of a database can be rolled back, once, and
+    /// only within `window` of it happening. Databases of the same name on
+    /// other servers have their own history.
+    ///
+    /// # Arguments
+    ///
+    /// * `server` - Server holding the database, as `host:port`
+    /// * `live_db` - Production name of the promoted database
+    /// * `now` - Current time
+    /// * `window` - How long after a promotion it may still be rolled back
+    pub fn rollback_candidate(&self, server: &str, live_db: &str, now: DateTime<Utc>, window: Duration) -> Result<&Promotion> {
+        debug!("Finding promotion of {} on {} to roll back", live_db, server);
+        let promotion = self.promotions
+            .iter()
+            .rev()
+            .find(|promotion| promotion.server == server && promotion.live_db == live_db)
+            .ok_or_else(|| anyhow!("No promotion of {} on {} is recorded", live_db, server))?;
+        if let Some(rolled_back_at) = promotion.rolled_back_at {
+            return Err(anyhow!("The latest promotion of {} was already rolled back at {}", live_db, rolled_back_at));
+        }
+        if now - promotion.promoted_at > window {
+            return Err(anyhow!(
+                "{} was promoted at {}, outside the {} hour rollback window",
+                live_db, promotion.promoted_at, window.num_hours()
+            ));
+        }
+        Ok(promotion)
+    }
+
+    /// Mark the latest promotion of `live_db` on `server` as rolled back
+    pub fn mark_rolled_back(&mut self, server: &str, live_db: &str, now: DateTime<Utc>) {
+        debug!("Marking latest promotion of {} on {} as rolled back", live_db, server);
+        if let Some(promotion) = self.promotions.iter_mut().rev().find(|promotion| promotion.server == server && promotion.live_db == live_db) {
+            promotion.rolled_back_at = Some(now);
+        }
+    }
+}
+
+/// Load the promotion history from the bucket
+pub async fn load_history(browser: &mut SnapshotBrowser) -> Result<PromotionHistory> {
+    debug!("Loading promotion history from {}", PROMOTION_HISTORY_KEY);
+    match browser.store().await?.read_object(PROMOTION_HISTORY_KEY).await? {
+        Some(bytes) => PromotionHistory::from_json(&bytes),
+        None => Ok(PromotionHistory::default()),
+    }
+}
+
+/// Write the promotion history back to the bucket
+pub async fn save_history(browser: &mut SnapshotBrowser, history: &PromotionHistory) -> Result<()> {
+    debug!("Saving {} promotions to {}", history.promotions.len(), PROMOTION_HISTORY_KEY);
+    browser.store().await?.write_object(PROMOTION_HISTORY_KEY, history.to_json()?).await
+}
+
+/// Record a completed promotion on `server` in the bucket's history
+///
+/// The history is re-read from the bucket first so promotions recorded by
+/// other instances are kept.
+pub async fn record_promotion(browser: &mut SnapshotBrowser, server: &str, plan: &PromotePlan, now: DateTime<Utc>) -> Result<()> {
+    debug!("Recording promotion of {} on {} in the history", plan.live_db, server);
+    let mut history = load_history(browser).await?;
+    history.record(server, plan, now);
+    save_history(browser, &history).await
+}
+
+/// Swap the names of a promotion back
+///
+/// The promoted database gets its restored name back and the retired
+/// database takes the production name again. If the retired database cannot
+/// be renamed, the promoted one is renamed back before the error is returned.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server holding the databases
+/// * `promotion` - The promotion to undo
+pub async fn rollback(pg_config: &PostgresConfig, promotion: &Promotion) -> Result<()> {
+    debug!("Rolling back promotion of {} to {}", promotion.restored_db, promotion.live_db);
+    let client = pg_config.connect_to(MAINTENANCE_DB).await?;
+    if !database_exists(&client, &promotion.retired_db).await? {
+        return Err(anyhow!("Retired database {} no longer exists, nothing to roll back to", promotion.retired_db));
+    }
+    if database_exists(&client, &promotion.restored_db).await? {
+        return Err(anyhow!("Database {} already exists, refusing to overwrite it", promotion.restored_db));
+    }
+    if !database_exists(&client, &promotion.live_db).await? {
+        return Err(anyhow!("Live database {} does not exist", promotion.live_db));
+    }
+
+    disconnect_sessions(&client, &promotion.live_db).await?;
+    disconnect_sessions(&client, &promotion.retired_db).await?;
+    postgres::rename_database(&client, &promotion.live_db, &promotion.restored_db).await?;
+    if let Err(e) = postgres::rename_database(&client, &promotion.retired_db, &promotion.live_db).await {
+        warn!("Putting {} back after failed rollback", promotion.live_db);
+        postgres::rename_database(&client, &promotion.restored_db, &promotion.live_db).await?;
+        return Err(e);
+    }
+    info!("Rolled back promotion of {}, promoted database renamed back to {}", promotion.live_db, promotion.restored_db);
+    Ok(())
+}
+
+/// Roll back the latest promotion of a database and record it in the history
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket holding the history
+/// * `pg_config` - Connection settings of the server holding the databases
+/// * `live_db` - Production name of the promoted database
+/// * `window` - How long after a promotion it may still be rolled back
+/// * `now` - Current time
+///
+/// # Returns
+///
+/// The promotion that was rolled back
+pub async fn rollback_promotion(
+    browser: &mut SnapshotBrowser,
+    pg_config: &PostgresConfig,
+    live_db: &str,
+    window: Duration,
+    now: DateTime<Utc>,
+) -> Result<Promotion> {
+    debug!("Rolling back latest promotion of {}", live_db);
+    let mut history = load_history(browser).await?;
+    let server = pg_config.server();
+    let promotion = history.rollback_candidate(&server, live_db, now, window)?.clone();
+    rollback(pg_config, &promotion).await?;
+    history.mark_rolled_back(&server, live_db, now);
+    save_history(browser, &history)
+        .await
+        .map_err(|e| anyhow!("Rolled back {}, but recording the rollback failed: {}", live_db, e))?;
+    Ok(promotion)
+}
@@ -0,0 +1,154 @@
+// This module contains the provenance of restored databases for the Rustored application
+// After a PostgreSQL restore, the database comment records which snapshot it
+// came from, when and by whom, and a `rustored.provenance` table inside the
+// database keeps one row per restore. `rustored list` shows the comment, so a
+// database nobody remembers creating can be traced back to its snapshot.
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{debug, info, warn};
+use crate::gc::THROWAWAY_TAG;
+use crate::hooks::TemplateVars;
+use crate::ui::models::PostgresConfig;
+
+/// Start of the comment of a restored database that is not a throwaway
+pub const RESTORE_TAG: &str = "rustored:restore";
+
+/// Schema of the provenance table inside restored databases, left out of change summaries
+pub const PROVENANCE_SCHEMA: &str = "rustored";
+
+/// Where a restored database came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// Key of the restored snapshot, unknown for a database tagged before it was restored into
+    pub snapshot_key: Option<String>,
+    /// When the database was restored into, or created
+    pub restored_at: DateTime<Utc>,
+    /// Who ran the restore
+    pub operator: Option<String>,
+    /// Whether rustored named the database itself, so `gc` may drop it
+    pub throwaway: bool,
+}
+
+/// Who is restoring: `RUSTORED_OPERATOR`, else the login user
+pub fn operator() -> String {
+    debug!("Finding the restore operator");
+    ["RUSTORED_OPERATOR", "USER", "USERNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.trim().is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl Provenance {
+    /// The database comment recording the provenance
+    ///
+    /// The snapshot key comes last, since it is the only part that may hold spaces.
+    pub fn to_comment(&self) -> String {
+        debug!("Building provenance comment");
+        let mut comment = format!(
+            "{} created={}",
+            if self.throwaway { THROWAWAY_TAG } else { RESTORE_TAG },
+            self.restored_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        );
+        if let Some(operator) = &self.operator {
+            comment.push_str(&format!(" by={}", operator));
+        }
+        if let Some(key) = &self.snapshot_key {
+            comment.push_str(&format!(" snapshot={}", key));
+        }
+        comment
+    }
+
+    /// Parse a database comment, or `None` if rustored did not write it
+    pub fn from_comment(comment: &str) -> Option<Self> {
+        debug!("Parsing provenance comment: {}", comment);
+        let (throwaway, rest) = match (comment.strip_prefix(THROWAWAY_TAG), comment.strip_prefix(RESTORE_TAG)) {
+            (Some(rest), _) => (true, rest),
+            (None, Some(rest)) => (false, rest),
+            (None, None) => return None,
+        };
+        let (rest, snapshot_key) = match rest.split_once(" snapshot=") {
+            Some((rest, key)) => (rest, Some(key.to_string())),
+            None => (rest, None),
+        };
+        let (rest, operator) = match rest.split_once(" by=") {
+            Some((rest, operator)) => (rest, Some(operator.to_string())),
+            None => (rest, None),
+        };
+        let created = rest.trim().strip_prefix("created=")?;
+        let restored_at = DateTime::parse_from_rfc3339(created).ok()?.with_timezone(&Utc);
+        Some(Self { snapshot_key, restored_at, operator, throwaway })
+    }
+
+    /// One-line summary for `rustored list`
+    pub fn summary(&self) -> String {
+        debug!("Summarising provenance");
+        let mut summary = format!(
+            "{} {}",
+            self.snapshot_key.as_deref().unwrap_or("(restore)"),
+            self.restored_at.format("%Y-%m-%d %H:%M UTC"),
+        );
+        if let Some(operator) = &self.operator {
+            summary.push_str(&format!(" by {}", operator));
+        }
+        summary
+    }
+}
+
+/// Quote a string as a SQL literal
+pub(crate) fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Record where a restored database came from, in its comment and provenance table
+///
+/// A database rustored named itself keeps its throwaway tag, so `gc` can
+/// still drop it. Failures are logged rather than returned, since the restore
+/// itself succeeded.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `vars` - The restore's template variables, naming the database and snapshot
+pub async fn record(pg_config: &PostgresConfig, vars: &TemplateVars) {
+    debug!("Recording provenance of {}", vars.restored_db);
+    if let Err(e) = try_record(pg_config, vars).await {
+        warn!("Failed to record where {} was restored from: {}", vars.restored_db, e);
+    }
+}
+
+async fn try_record(pg_config: &PostgresConfig, vars: &TemplateVars) -> Result<()> {
+    debug!("Writing provenance of {}", vars.restored_db);
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    let existing: Option<String> = client
+        .query_opt("SELECT shobj_description(oid, 'pg_database') FROM pg_database WHERE datname = $1", &[&vars.restored_db])
+        .await?
+        .and_then(|row| row.get(0));
+    let provenance = Provenance {
+        snapshot_key: Some(vars.snapshot_key.clone()),
+        restored_at: Utc::now(),
+        operator: Some(operator()),
+        throwaway: existing.as_deref().and_then(Provenance::from_comment).is_some_and(|existing| existing.throwaway),
+    };
+    client
+        .batch_execute(&format!("COMMENT ON DATABASE \"{}\" IS {}", vars.restored_db.replace('"', "\"\""), quote_literal(&provenance.to_comment())))
+        .await?;
+
+    let restored = pg_config.connect_to(&vars.restored_db).await?;
+    restored
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema}; \
+             CREATE TABLE IF NOT EXISTS {schema}.provenance (\
+                 snapshot_key text NOT NULL, restored_at timestamptz NOT NULL, operator text NOT NULL, rustored_version text NOT NULL)",
+            schema = PROVENANCE_SCHEMA,
+        ))
+        .await?;
+    restored
+        .execute(
+            &format!("INSERT INTO {}.provenance VALUES ($1, $2::text::timestamptz, $3, $4)", PROVENANCE_SCHEMA),
+            &[&vars.snapshot_key, &provenance.restored_at.to_rfc3339(), &provenance.operator.as_deref().unwrap_or_default(), &env!("CARGO_PKG_VERSION")],
+        )
+        .await?;
+    info!("Recorded that {} was restored from {}", vars.restored_db, vars.snapshot_key);
+    Ok(())
+}
@@ -0,0 +1,505 @@
+// This module contains the Qdrant point loader for the Rustored application
+// Qdrant snapshots are newline-delimited JSON, one point per line in Qdrant's
+// own format (`{"id": 1, "vector": [...], "payload": {...}}`). Points are
+// upserted in batches, several at once. Qdrant rejects a whole batch when one
+// point is bad, so a rejected batch is retried a point at a time to find the
+// bad points, which go to a dead-letter file while the rest are loaded. An
+// upsert that fails with a server error or times out, as one does while
+// Qdrant is busy optimizing segments, is sent again after a backoff.
+//
+// A missing collection is created before the first batch. Its vector
+// parameters come from the `GET /collections/<name>` answer stored next to
+// the snapshot as `<snapshot key>.index.json`, like the index definitions of
+// Elasticsearch snapshots, or else from the vectors of the first point, with
+// the configured vector size and distance taking precedence over both.
+//
+// Upserts go over the REST API, or over the gRPC API when it is selected
+// and answers, which takes the same points in protobuf and loads large
+// collections faster. Collections are looked up and created over HTTP
+// either way.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use qdrant_client::qdrant::{NamedVectors, PointId, PointStruct, UpsertPointsBuilder, Vector, Vectors};
+use qdrant_client::{Payload, Qdrant, QdrantError};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::task::JoinSet;
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+use crate::es_index::sidecar_path;
+use crate::mapping::RestoreMapping;
+use crate::ui::models::{QdrantTransport, QdrantUploadConfig, TimeoutConfig, TlsConfig};
+
+/// A point Qdrant rejected, with the reason
+type Rejected = (Value, String);
+
+/// Times an upsert is sent while it fails with a server error or times out
+pub const MAX_UPSERT_ATTEMPTS: u32 = 4;
+
+/// Pause before the first resend
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Longest pause between resends
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// A connection to Qdrant, upserting over gRPC when it was opened with it
+///
+/// Clones share the HTTP clients and gRPC channel.
+#[derive(Clone)]
+pub struct QdrantConnection {
+    client: reqwest::Client,
+    /// Client without read or operation timeouts, for snapshot recovery
+    recovery_client: reqwest::Client,
+    pub(crate) host: String,
+    api_key: Option<String>,
+    grpc: Option<Arc<Qdrant>>,
+    upload: QdrantUploadConfig,
+}
+
+impl QdrantConnection {
+    /// A connection sending everything over HTTP with `client`, snapshot recoveries included
+    pub fn http(client: reqwest::Client, host: &str, api_key: Option<&str>) -> Self {
+        debug!("Creating Qdrant HTTP connection to {}", host);
+        Self {
+            recovery_client: client.clone(),
+            client,
+            host: host.trim_end_matches('/').to_string(),
+            api_key: api_key.map(String::from),
+            grpc: None,
+            upload: QdrantUploadConfig::default(),
+        }
+    }
+
+    /// Connect to Qdrant with the transport of `upload`
+    ///
+    /// The gRPC API is only used when it answers a health check. When it
+    /// does not, or the TLS settings hold a custom CA or client certificate,
+    /// which the gRPC client cannot take, points go over HTTP with a warning.
+    pub async fn connect(host: &str, api_key: Option<&str>, timeouts: &TimeoutConfig, tls: &TlsConfig, upload: &QdrantUploadConfig) -> Result<Self> {
+        debug!("Connecting to Qdrant at {} over {:?}", host, upload.transport);
+        // Recovering a snapshot answers only once the whole collection is loaded, so it waits as long as that takes
+        let unbounded = TimeoutConfig { read_secs: None, operation_secs: None, ..timeouts.clone() };
+        let mut connection = Self {
+            recovery_client: unbounded.tls_client(tls)?,
+            upload: upload.clone(),
+            ..Self::http(timeouts.tls_client(tls)?, host, api_key)
+        };
+        if upload.transport != QdrantTransport::Grpc {
+            return Ok(connection);
+        }
+        if tls != &TlsConfig::default() {
+            warn!("The Qdrant gRPC client takes no custom TLS settings, so points are upserted over HTTP");
+            return Ok(connection);
+        }
+        let url = upload.grpc_url(host)?;
+        let mut config = Qdrant::from_url(&url).api_key(api_key).skip_compatibility_check();
+        if let Some(connect) = timeouts.connect() {
+            config = config.connect_timeout(connect);
+        }
+        if let Some(timeout) = timeouts.operation().or(timeouts.read()) {
+            config = config.timeout(timeout);
+        }
+        let grpc = config.build().map_err(|e| anyhow!("Invalid Qdrant gRPC URL {}: {}", url, e))?;
+        match grpc.health_check().await {
+            Ok(_) => {
+                info!("Upserting points over gRPC at {}", url);
+                connection.grpc = Some(Arc::new(grpc));
+            }
+            Err(e) => warn!("Qdrant gRPC API at {} did not answer ({}), so points are upserted over HTTP", url, e),
+        }
+        Ok(connection)
+    }
+
+    /// Whether upserts go over gRPC
+    pub fn is_grpc(&self) -> bool {
+        debug!("Checking if Qdrant upserts go over gRPC");
+        self.grpc.is_some()
+    }
+
+    /// Build an HTTP request, adding the API key when one is set
+    pub(crate) fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.client.request(method, url))
+    }
+
+    /// Build an HTTP request without read or operation timeouts, for snapshot recovery
+    pub(crate) fn recovery_request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.recovery_client.request(method, url))
+    }
+
+    /// Add the API key to a request when one is set
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.header("api-key", key),
+            None => request,
+        }
+    }
+}
+
+/// Turn a point in Qdrant's JSON form into its gRPC form
+///
+/// IDs are unsigned integers or UUIDs; vectors are dense, or named, each
+/// dense, sparse (`{"indices", "values"}`) or a multi-vector.
+pub fn grpc_point(point: &Value) -> std::result::Result<PointStruct, String> {
+    debug!("Converting point {} for gRPC", point["id"]);
+    let id: PointId = match &point["id"] {
+        Value::Number(id) => id.as_u64().ok_or_else(|| format!("point ID {} is not an unsigned integer", id))?.into(),
+        Value::String(uuid) => uuid.as_str().into(),
+        id => return Err(format!("point ID {} is neither an unsigned integer nor a UUID", id)),
+    };
+    let vectors: Vectors = match &point["vector"] {
+        Value::Object(named) => {
+            let mut vectors = NamedVectors::default();
+            for (name, vector) in named {
+                vectors = vectors.add_vector(name.clone(), grpc_vector(vector).map_err(|e| format!("vector {}: {}", name, e))?);
+            }
+            vectors.into()
+        }
+        vector => grpc_vector(vector)?.into(),
+    };
+    let payload = match &point["payload"] {
+        Value::Object(payload) => Payload::from(payload.clone()),
+        Value::Null => Payload::new(),
+        _ => return Err("payload is not an object".to_string()),
+    };
+    Ok(PointStruct::new(id, vectors, payload))
+}
+
+/// One vector of a point in its gRPC form
+fn grpc_vector(vector: &Value) -> std::result::Result<Vector, String> {
+    debug!("Converting vector for gRPC");
+    let floats = |values: &Value| -> std::result::Result<Vec<f32>, String> {
+        values.as_array().ok_or("vector is not an array")?.iter().map(|value| value.as_f64().map(|value| value as f32).ok_or_else(|| format!("{} is not a number", value))).collect()
+    };
+    match vector {
+        Value::Array(rows) if rows.first().is_some_and(Value::is_array) => Ok(Vector::new_multi(rows.iter().map(floats).collect::<std::result::Result<Vec<_>, _>>()?)),
+        Value::Array(_) => Ok(Vector::new_dense(floats(vector)?)),
+        Value::Object(sparse) => {
+            let indices = sparse.get("indices").and_then(Value::as_array).ok_or("sparse vector has no indices")?;
+            let indices = indices.iter().map(|index| index.as_u64().and_then(|index| u32::try_from(index).ok()).ok_or_else(|| format!("{} is not a sparse index", index)))
+                .collect::<std::result::Result<Vec<u32>, _>>()?;
+            Ok(Vector::new_sparse(indices, floats(sparse.get("values").unwrap_or(&Value::Null))?))
+        }
+        _ => Err("point has no vector".to_string()),
+    }
+}
+
+/// Vector parameters stored next to a snapshot, if there are any
+///
+/// The sidecar holds the `GET /collections/<name>` answer, its
+/// `{"config": {"params": ...}}` part, or a `PUT /collections/<name>` body.
+pub fn read_collection_params(snapshot: &Path) -> Result<Option<Value>> {
+    debug!("Looking for collection parameters next to {:?}", snapshot);
+    let path = sidecar_path(snapshot);
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("Failed to read collection parameters {:?}: {}", path, e)),
+    };
+    let stored: Value = serde_json::from_slice(&bytes).with_context(|| format!("Collection parameters {:?} are not JSON", path))?;
+    let params = [&stored["result"]["config"]["params"], &stored["config"]["params"], &stored]
+        .into_iter()
+        .find(|params| params.get("vectors").is_some() || params.get("sparse_vectors").is_some())
+        .ok_or_else(|| anyhow!("Collection parameters {:?} hold no vectors", path))?;
+    Ok(Some(params.clone()))
+}
+
+/// Body of the `PUT /collections/<name>` request creating the collection of a snapshot
+///
+/// The stored parameters are taken as they are, or else the vectors are
+/// sampled from the first point: one per name, dense, multi or sparse, with
+/// Cosine distance. The configured size and distance replace those of the
+/// unnamed vector, or of every named dense vector.
+///
+/// # Arguments
+///
+/// * `stored` - Parameters read from the snapshot's sidecar
+/// * `upload` - The configured vector size and distance
+/// * `first` - The first point of the snapshot
+pub fn collection_body(stored: Option<&Value>, upload: &QdrantUploadConfig, first: &Value) -> Result<Value> {
+    debug!("Building collection creation body");
+    let distance = upload.distance_name()?;
+    let mut body = match stored {
+        Some(stored) => stored.clone(),
+        None => match sampled_params(&first["vector"]) {
+            Ok(params) => params,
+            Err(_) if upload.vector_size.is_some() => json!({}),
+            Err(e) => return Err(anyhow!("Cannot size the collection from the first point: {}; set the vector size", e)),
+        },
+    };
+    let dense = |params: &mut Value| {
+        if let Some(size) = upload.vector_size {
+            params["size"] = json!(size);
+        }
+        if let Some(distance) = distance {
+            params["distance"] = json!(distance);
+        }
+    };
+    match body.get_mut("vectors") {
+        Some(Value::Object(vectors)) if !vectors.contains_key("size") && !vectors.contains_key("distance") => vectors.values_mut().for_each(dense),
+        Some(vectors) => dense(vectors),
+        None if upload.vector_size.is_some() => {
+            body["vectors"] = json!({ "distance": distance.unwrap_or("Cosine") });
+            dense(&mut body["vectors"]);
+        }
+        None => {}
+    }
+    Ok(body)
+}
+
+/// Vector parameters of a collection holding points with `vector`
+fn sampled_params(vector: &Value) -> std::result::Result<Value, String> {
+    debug!("Sampling vector parameters of the first point");
+    let dense = |vector: &Value| -> std::result::Result<Value, String> {
+        match vector.as_array() {
+            Some(rows) if rows.first().is_some_and(Value::is_array) => {
+                Ok(json!({ "size": rows[0].as_array().map_or(0, Vec::len), "distance": "Cosine", "multivector_config": { "comparator": "max_sim" } }))
+            }
+            Some(values) if !values.is_empty() => Ok(json!({ "size": values.len(), "distance": "Cosine" })),
+            _ => Err("the point has no vector".to_string()),
+        }
+    };
+    let Value::Object(named) = vector else {
+        return Ok(json!({ "vectors": dense(vector)? }));
+    };
+    let (mut vectors, mut sparse) = (serde_json::Map::new(), serde_json::Map::new());
+    for (name, vector) in named {
+        if vector.is_object() {
+            sparse.insert(name.clone(), json!({}));
+        } else {
+            vectors.insert(name.clone(), dense(vector).map_err(|e| format!("vector {}: {}", name, e))?);
+        }
+    }
+    let mut params = json!({ "vectors": vectors });
+    if !sparse.is_empty() {
+        params["sparse_vectors"] = Value::Object(sparse);
+    }
+    Ok(params)
+}
+
+/// Create the collection unless it exists, from `stored` parameters or the first point
+async fn ensure_collection(connection: &QdrantConnection, collection_url: &str, stored: Option<&Value>, first: &Value) -> Result<()> {
+    debug!("Ensuring collection {} exists", collection_url);
+    let response = connection.request(reqwest::Method::GET, collection_url).send().await?;
+    if response.status().is_success() {
+        if stored.is_some() {
+            warn!("Collection {} already exists, so its stored parameters are not applied", collection_url);
+        }
+        return Ok(());
+    }
+    if response.status() != reqwest::StatusCode::NOT_FOUND {
+        return Err(anyhow!("Qdrant returned {} for {}", response.status(), collection_url));
+    }
+    let body = collection_body(stored, &connection.upload, first).with_context(|| format!("Collection {} does not exist", collection_url))?;
+    info!("Creating collection {} with vectors {}", collection_url, body["vectors"]);
+    let response = connection.request(reqwest::Method::PUT, collection_url).json(&body).send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to create collection {}: {} {}", collection_url, status, text));
+    }
+    Ok(())
+}
+
+/// What became of an upsert
+enum Upserted {
+    Loaded,
+    /// Qdrant refused the points, for the reason given
+    Rejected(String),
+    /// Qdrant failed or did not answer in time, and a later attempt may succeed
+    Failed(String),
+}
+
+/// Upsert points in one request
+///
+/// # Returns
+///
+/// What became of the points, and an error when the request failed in a way
+/// resending cannot fix
+async fn upsert(connection: &QdrantConnection, collection: &str, points: &[Value]) -> Result<Upserted> {
+    debug!("Upserting {} points", points.len());
+    if let Some(grpc) = &connection.grpc {
+        return upsert_grpc(grpc, collection, points).await;
+    }
+    let points_url = format!("{}/collections/{}/points?wait=true", connection.host, collection);
+    let response = match connection.request(reqwest::Method::PUT, &points_url).json(&json!({ "points": points })).send().await {
+        Ok(response) => response,
+        Err(e) if e.is_timeout() || e.is_connect() => return Ok(Upserted::Failed(format!("upsert to {} failed: {}", points_url, e))),
+        Err(e) => return Err(anyhow!("Upsert to {} failed: {}", points_url, e)),
+    };
+    let status = response.status();
+    if status.is_success() {
+        return Ok(Upserted::Loaded);
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    let reason = body["status"]["error"].as_str().map(String::from).unwrap_or_else(|| status.to_string());
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        Ok(Upserted::Failed(format!("Qdrant returned {} for an upsert: {}", status, reason)))
+    } else if status.is_client_error() {
+        Ok(Upserted::Rejected(reason))
+    } else {
+        Err(anyhow!("Qdrant returned {} for an upsert: {}", status, reason))
+    }
+}
+
+/// Upsert points in one gRPC request, with the outcome `upsert` gives
+async fn upsert_grpc(grpc: &Qdrant, collection: &str, points: &[Value]) -> Result<Upserted> {
+    debug!("Upserting {} points over gRPC", points.len());
+    let points = match points.iter().map(grpc_point).collect::<std::result::Result<Vec<_>, _>>() {
+        Ok(points) => points,
+        Err(reason) => return Ok(Upserted::Rejected(reason)),
+    };
+    match grpc.upsert_points(UpsertPointsBuilder::new(collection, points).wait(true)).await {
+        Ok(_) => Ok(Upserted::Loaded),
+        Err(QdrantError::ResponseError { status }) => match status.code() {
+            tonic::Code::InvalidArgument | tonic::Code::FailedPrecondition | tonic::Code::OutOfRange => Ok(Upserted::Rejected(status.message().to_string())),
+            tonic::Code::Unavailable | tonic::Code::DeadlineExceeded | tonic::Code::ResourceExhausted | tonic::Code::Aborted | tonic::Code::Internal => {
+                Ok(Upserted::Failed(format!("Qdrant gRPC upsert into {} failed: {}", collection, status)))
+            }
+            _ => Err(anyhow!("Qdrant gRPC upsert into {} failed: {}", collection, status)),
+        },
+        Err(e) => Err(anyhow!("Qdrant gRPC upsert into {} failed: {}", collection, e)),
+    }
+}
+
+/// Upsert points, sending them again with a growing pause while the upsert fails
+///
+/// # Returns
+///
+/// `Ok(Err(reason))` when Qdrant rejected the points, and an error when the
+/// upsert failed `MAX_UPSERT_ATTEMPTS` times or cannot succeed
+async fn upsert_with_retry(connection: &QdrantConnection, collection: &str, points: &[Value]) -> Result<std::result::Result<(), String>> {
+    debug!("Upserting {} points with retries", points.len());
+    let mut delay = BASE_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match upsert(connection, collection, points).await? {
+            Upserted::Loaded => return Ok(Ok(())),
+            Upserted::Rejected(reason) => return Ok(Err(reason)),
+            Upserted::Failed(reason) if attempt >= MAX_UPSERT_ATTEMPTS => {
+                return Err(anyhow!("{} (gave up after {} attempts)", reason, attempt));
+            }
+            Upserted::Failed(reason) => {
+                warn!("Upsert of {} points failed on attempt {} ({}), sending it again in {:?}", points.len(), attempt, reason, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Upsert a batch, retrying it a point at a time if Qdrant rejects it
+///
+/// # Returns
+///
+/// The number of points loaded and the points rejected, with the reasons
+async fn load_batch(connection: QdrantConnection, collection: String, batch: Vec<Value>) -> Result<(u64, Vec<Rejected>)> {
+    debug!("Loading batch of {} points", batch.len());
+    let reason = match upsert_with_retry(&connection, &collection, &batch).await? {
+        Ok(()) => return Ok((batch.len() as u64, Vec::new())),
+        Err(reason) => reason,
+    };
+    // A batch of one was rejected already, so it need not be sent again
+    if batch.len() == 1 {
+        return Ok((0, batch.into_iter().map(|point| (point, reason.clone())).collect()));
+    }
+    warn!("Qdrant rejected a batch of {} points ({}), retrying them one at a time", batch.len(), reason);
+    let (mut loaded, mut rejected) = (0, Vec::new());
+    for point in batch {
+        match upsert_with_retry(&connection, &collection, std::slice::from_ref(&point)).await? {
+            Ok(()) => loaded += 1,
+            Err(reason) => rejected.push((point, reason)),
+        }
+    }
+    Ok((loaded, rejected))
+}
+
+/// Number of points in an NDJSON snapshot, counting every non-blank line
+async fn count_points(path: &Path) -> Result<u64> {
+    debug!("Counting points in {:?}", path);
+    let file = tokio::fs::File::open(path).await.with_context(|| format!("Failed to open snapshot {:?}", path))?;
+    let mut lines = BufReader::new(file).lines();
+    let mut count = 0;
+    while let Some(line) = lines.next_line().await? {
+        if !line.trim().is_empty() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Stream an NDJSON snapshot of points into a collection
+///
+/// The collection is created if it does not exist, from the parameters
+/// stored next to the snapshot when there are any. Rejected points, and lines
+/// that are not JSON, are written to `dead_letters` and do not fail the load.
+/// A Qdrant-native snapshot is handed to `qdrant_snapshot::recover_snapshot`
+/// instead.
+///
+/// # Arguments
+///
+/// * `connection` - Qdrant, over HTTP or gRPC, carrying the target timeouts, batch size and concurrency
+/// * `collection` - Collection to load the points into
+/// * `path` - The snapshot file
+/// * `mapping` - Payload field renames applied to each point before it is sent
+/// * `progress` - Called with the share of the points loaded or dead-lettered, as batches finish
+/// * `dead_letters` - Receives the points that could not be loaded
+///
+/// # Returns
+///
+/// The number of points loaded, or held by a collection recovered from a native snapshot
+pub async fn upload_points(
+    connection: &QdrantConnection,
+    collection: &str,
+    path: &Path,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Uploading points from {:?} into {}", path, collection);
+    let collection_url = format!("{}/collections/{}", connection.host, collection);
+    let (batch_size, concurrency) = (connection.upload.batch_size(), connection.upload.concurrency());
+    let mut snapshot = NdjsonBatches::open(path, batch_size).await?;
+    if crate::qdrant_snapshot::is_native_snapshot(snapshot.path())? {
+        return crate::qdrant_snapshot::recover_snapshot(connection, collection, snapshot.path(), mapping, progress).await;
+    }
+    let stored = read_collection_params(path)?;
+    let total = count_points(snapshot.path()).await?.max(1);
+
+    let mut in_flight: JoinSet<Result<(u64, Vec<Rejected>)>> = JoinSet::new();
+    let (mut loaded, mut checked_collection) = (0u64, false);
+    // Only this task writes dead letters, so the file never interleaves
+    let mut record = |result: std::result::Result<Result<(u64, Vec<Rejected>)>, tokio::task::JoinError>, dead_letters: &mut DeadLetterWriter| -> Result<()> {
+        let (points, rejected) = result??;
+        for (point, reason) in rejected {
+            dead_letters.write(point, &reason)?;
+        }
+        loaded += points;
+        if let Some(progress) = progress {
+            progress((loaded + dead_letters.count()) as f32 / total as f32);
+        }
+        Ok(())
+    };
+
+    while let Some(batch) = snapshot.next_batch(dead_letters).await? {
+        let batch: Vec<Value> = batch.into_iter().map(|point| mapping.map_point(point)).collect();
+        if !checked_collection {
+            ensure_collection(connection, &collection_url, stored.as_ref(), &batch[0]).await?;
+            checked_collection = true;
+        }
+        while in_flight.len() >= concurrency {
+            if let Some(result) = in_flight.join_next().await {
+                record(result, dead_letters)?;
+            }
+        }
+        in_flight.spawn(load_batch(connection.clone(), collection.to_string(), batch));
+    }
+    while let Some(result) = in_flight.join_next().await {
+        record(result, dead_letters)?;
+    }
+    info!("Loaded {} points into {}, {} rejected", loaded, collection, dead_letters.count());
+    Ok(loaded)
+}
@@ -0,0 +1,147 @@
+// This module contains Qdrant-native snapshot recovery for the Rustored application
+// Besides point exports, Qdrant writes snapshots of its own: a tar archive of
+// a collection's segments, configuration and WAL, named
+// `<collection>-<id>-<date>.snapshot`. Such a file cannot be read point by
+// point; it is uploaded to the collection snapshot recovery API
+// (`POST /collections/<name>/snapshots/upload`), which creates the collection
+// from it, or replaces what an existing one holds. The snapshot is streamed
+// as a multipart form straight from disk, so snapshots larger than memory
+// can be restored, and the upload goes over HTTP whatever the transport.
+// Qdrant answers once the recovery is done, which for a large collection
+// takes longer than the read timeout, so the upload is sent without one.
+
+use anyhow::{anyhow, Context, Result};
+use futures_util::stream::{self, StreamExt};
+use log::{debug, info, warn};
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::bytes::Bytes;
+use tokio_util::io::ReaderStream;
+use crate::compression::Codec;
+use crate::mapping::RestoreMapping;
+use crate::qdrant_points::QdrantConnection;
+
+/// Extension of the snapshots Qdrant writes
+pub const NATIVE_SNAPSHOT_EXTENSION: &str = ".snapshot";
+
+/// Where a POSIX tar header holds its magic, and the magic itself
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+
+/// How often upload progress is reported
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Whether the snapshot stored under `key` is a Qdrant-native one, judged by its name
+///
+/// A compression extension after `.snapshot`, e.g. `.snapshot.zst`, is allowed.
+pub fn is_native_snapshot_key(key: &str) -> bool {
+    debug!("Checking if {} is a Qdrant-native snapshot", key);
+    let name = Codec::from_name(key).map_or(key, |codec| &key[..key.len() - codec.extension().len()]);
+    name.ends_with(NATIVE_SNAPSHOT_EXTENSION)
+}
+
+/// Whether a local, decompressed file is a Qdrant-native snapshot, judged by its tar header
+pub fn is_native_snapshot(path: &Path) -> Result<bool> {
+    debug!("Checking if {:?} is a Qdrant-native snapshot", path);
+    let mut header = Vec::with_capacity(TAR_MAGIC_OFFSET + TAR_MAGIC.len());
+    std::fs::File::open(path)
+        .with_context(|| format!("Failed to open snapshot {:?}", path))?
+        .take((TAR_MAGIC_OFFSET + TAR_MAGIC.len()) as u64)
+        .read_to_end(&mut header)?;
+    Ok(header.get(TAR_MAGIC_OFFSET..).is_some_and(|magic| magic == TAR_MAGIC))
+}
+
+/// Recover a collection from a Qdrant-native snapshot
+///
+/// The snapshot takes priority over any data the collection already holds,
+/// so the collection ends up as it was when the snapshot was taken. Payload
+/// field renames of `mapping` cannot be applied to a snapshot and are skipped
+/// with a warning.
+///
+/// # Arguments
+///
+/// * `connection` - Qdrant, carrying the target timeouts and API key
+/// * `collection` - Collection to recover
+/// * `path` - The decompressed snapshot file
+/// * `mapping` - Renames of the restore, checked for field renames
+/// * `progress` - Called with the share of the snapshot uploaded
+///
+/// # Returns
+///
+/// The number of points the collection holds once recovered
+pub async fn recover_snapshot(
+    connection: &QdrantConnection,
+    collection: &str,
+    path: &Path,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+) -> Result<u64> {
+    debug!("Recovering collection {} from native snapshot {:?}", collection, path);
+    if !mapping.fields.is_empty() {
+        warn!("Payload field renames are not applied to Qdrant-native snapshots");
+    }
+    if connection.is_grpc() {
+        info!("Qdrant-native snapshots are uploaded over HTTP");
+    }
+    let file = tokio::fs::File::open(path).await.with_context(|| format!("Failed to open snapshot {:?}", path))?;
+    let size = file.metadata().await?.len();
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("collection.snapshot");
+
+    // A multipart form of one `snapshot` field, streamed from disk
+    let boundary = format!("rustored-{:016x}", rand::random::<u64>());
+    let head = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"snapshot\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        boundary,
+        file_name.replace('"', "")
+    );
+    let tail = format!("\r\n--{}--\r\n", boundary);
+    let length = head.len() as u64 + size + tail.len() as u64;
+    let sent = Arc::new(AtomicU64::new(0));
+    let counter = sent.clone();
+    let body = stream::once(async move { Ok::<_, std::io::Error>(Bytes::from(head)) })
+        .chain(ReaderStream::new(file).map(move |chunk| {
+            chunk.inspect(|bytes| {
+                counter.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            })
+        }))
+        .chain(stream::once(async move { Ok(Bytes::from(tail)) }));
+
+    let url = format!("{}/collections/{}/snapshots/upload?wait=true&priority=snapshot", connection.host, collection);
+    info!("Uploading {} byte Qdrant snapshot to {}", size, url);
+    let request = connection
+        .recovery_request(reqwest::Method::POST, &url)
+        .header(reqwest::header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+        .header(reqwest::header::CONTENT_LENGTH, length)
+        .body(reqwest::Body::wrap_stream(body))
+        .send();
+    tokio::pin!(request);
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    let response = loop {
+        tokio::select! {
+            response = &mut request => break response.map_err(|e| anyhow!("Snapshot upload to {} failed: {}", url, e))?,
+            _ = ticker.tick() => {
+                if let Some(progress) = progress {
+                    progress(sent.load(Ordering::Relaxed) as f32 / size.max(1) as f32);
+                }
+            }
+        }
+    };
+    let status = response.status();
+    if !status.is_success() {
+        let body: Value = response.json().await.unwrap_or_default();
+        let reason = body["status"]["error"].as_str().map(String::from).unwrap_or_else(|| status.to_string());
+        return Err(anyhow!("Qdrant could not recover collection {} from the snapshot: {} {}", collection, status, reason));
+    }
+    if let Some(progress) = progress {
+        progress(1.0);
+    }
+
+    let info: Value = connection.request(reqwest::Method::GET, &format!("{}/collections/{}", connection.host, collection)).send().await?.json().await.unwrap_or_default();
+    let points = info["result"]["points_count"].as_u64().unwrap_or_default();
+    info!("Recovered collection {} with {} points from the snapshot", collection, points);
+    Ok(points)
+}
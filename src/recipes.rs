@@ -0,0 +1,208 @@
+// This module contains the built-in post-restore recipes for the Rustored application
+// Most restores of production data into development need the same clean-up:
+// passwords reset to a known value, email triggers switched off and audit
+// tables emptied. Rather than every team writing its own `post_sql`, these
+// recipes ship with the crate as SQL templates and are selected by name in a
+// preset, with `--recipe`, or in the restore confirmation popup.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How a parameter value is substituted into a recipe's SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    /// A schema, table or column name, placed inside double quotes
+    Identifier,
+    /// A string value, placed inside single quotes
+    Literal,
+}
+
+/// A `{{name}}` placeholder of a recipe and its default value
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipeParam {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub kind: ParamKind,
+    pub description: &'static str,
+}
+
+/// A post-restore SQL template bundled with the crate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [RecipeParam],
+    pub sql: &'static str,
+}
+
+/// Every built-in recipe
+pub const RECIPES: &[Recipe] = &[
+    Recipe {
+        name: "reset-passwords",
+        description: "Reset every user's password to a known development value (needs pgcrypto)",
+        params: &[
+            RecipeParam { name: "schema", default: "public", kind: ParamKind::Identifier, description: "Schema of the users table" },
+            RecipeParam { name: "table", default: "users", kind: ParamKind::Identifier, description: "Table holding the users" },
+            RecipeParam { name: "column", default: "password_hash", kind: ParamKind::Identifier, description: "Column holding the bcrypt password hash" },
+            RecipeParam { name: "password", default: "password", kind: ParamKind::Literal, description: "Password every user gets" },
+        ],
+        sql: include_str!("recipes/reset_passwords.sql"),
+    },
+    Recipe {
+        name: "disable-email-triggers",
+        description: "Disable triggers whose name or function matches a pattern, so restored data cannot email real users",
+        params: &[
+            RecipeParam { name: "pattern", default: "%mail%", kind: ParamKind::Literal, description: "ILIKE pattern of trigger or function names" },
+        ],
+        sql: include_str!("recipes/disable_email_triggers.sql"),
+    },
+    Recipe {
+        name: "truncate-audit-tables",
+        description: "Empty tables whose name matches a pattern, such as audit logs and history",
+        params: &[
+            RecipeParam { name: "pattern", default: "%audit%", kind: ParamKind::Literal, description: "ILIKE pattern of table names" },
+        ],
+        sql: include_str!("recipes/truncate_audit_tables.sql"),
+    },
+];
+
+/// Find a built-in recipe by name
+pub fn find(name: &str) -> Option<&'static Recipe> {
+    debug!("Finding recipe {}", name);
+    RECIPES.iter().find(|recipe| recipe.name == name)
+}
+
+impl Recipe {
+    /// Fill in the recipe's placeholders
+    ///
+    /// Parameters that are not given take their default. Identifiers may only
+    /// hold letters, digits and underscores, and single quotes in literals are
+    /// doubled, so a parameter cannot change what the SQL does.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Parameter values by name
+    ///
+    /// # Returns
+    ///
+    /// The SQL to run, or an error naming an unknown or invalid parameter
+    pub fn render(&self, params: &BTreeMap<String, String>) -> Result<String> {
+        debug!("Rendering recipe {}", self.name);
+        if let Some(unknown) = params.keys().find(|name| !self.params.iter().any(|param| param.name == name.as_str())) {
+            return Err(anyhow!("Recipe {} has no parameter '{}'", self.name, unknown));
+        }
+        let mut sql = self.sql.to_string();
+        for param in self.params {
+            let value = params.get(param.name).map(String::as_str).unwrap_or(param.default);
+            let value = match param.kind {
+                ParamKind::Identifier => {
+                    let valid = value.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    if !valid {
+                        return Err(anyhow!("Invalid {} '{}' for recipe {}: use letters, digits and underscores", param.name, value, self.name));
+                    }
+                    value.to_string()
+                }
+                ParamKind::Literal => {
+                    // Literals also appear inside the `$recipe$` quoted bodies of DO blocks
+                    if value.contains("$recipe$") {
+                        return Err(anyhow!("Invalid {} for recipe {}: '$recipe$' is not allowed", param.name, self.name));
+                    }
+                    value.replace('\'', "''")
+                }
+            };
+            sql = sql.replace(&format!("{{{{{}}}}}", param.name), &value);
+        }
+        Ok(sql)
+    }
+}
+
+/// A recipe chosen for a restore, with the parameters that differ from its defaults
+///
+/// In a presets file it is either the recipe's name or a table naming it:
+///
+/// ```toml
+/// recipes = ["disable-email-triggers", { name = "reset-passwords", password = "dev" }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RecipeRefToml", into = "RecipeRefToml")]
+pub struct RecipeRef {
+    pub name: String,
+    pub params: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RecipeRefToml {
+    Name(String),
+    Table {
+        name: String,
+        #[serde(flatten)]
+        params: BTreeMap<String, String>,
+    },
+}
+
+impl From<RecipeRefToml> for RecipeRef {
+    fn from(toml: RecipeRefToml) -> Self {
+        match toml {
+            RecipeRefToml::Name(name) => Self::new(&name),
+            RecipeRefToml::Table { name, params } => Self { name, params },
+        }
+    }
+}
+
+impl From<RecipeRef> for RecipeRefToml {
+    fn from(recipe: RecipeRef) -> Self {
+        match recipe.params.is_empty() {
+            true => Self::Name(recipe.name),
+            false => Self::Table { name: recipe.name, params: recipe.params },
+        }
+    }
+}
+
+impl RecipeRef {
+    /// A recipe with its default parameters
+    pub fn new(name: &str) -> Self {
+        debug!("Choosing recipe {}", name);
+        Self { name: name.to_string(), params: BTreeMap::new() }
+    }
+
+    /// Parse a `--recipe` argument: a name, optionally followed by `:name=value,name=value`
+    pub fn parse(arg: &str) -> Result<Self> {
+        debug!("Parsing recipe argument {}", arg);
+        let (name, params) = arg.split_once(':').unwrap_or((arg, ""));
+        let params = params
+            .split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').ok_or_else(|| anyhow!("Invalid recipe parameter '{}': use name=value", pair))?;
+                Ok((key.trim().to_string(), value.to_string()))
+            })
+            .collect::<Result<_>>()?;
+        let recipe = Self { name: name.trim().to_string(), params };
+        recipe.render()?;
+        Ok(recipe)
+    }
+
+    /// The SQL of the chosen recipe with its parameters filled in
+    pub fn render(&self) -> Result<String> {
+        debug!("Rendering chosen recipe {}", self.name);
+        let names: Vec<&str> = RECIPES.iter().map(|recipe| recipe.name).collect();
+        find(&self.name)
+            .ok_or_else(|| anyhow!("Unknown recipe '{}'; built-in recipes are {}", self.name, names.join(", ")))?
+            .render(&self.params)
+    }
+}
+
+/// Add a recipe with its default parameters, or remove it if it is already chosen
+pub fn toggle(recipes: &mut Vec<RecipeRef>, name: &str) {
+    debug!("Toggling recipe {}", name);
+    match recipes.iter().position(|recipe| recipe.name == name) {
+        Some(index) => {
+            recipes.remove(index);
+        }
+        None => recipes.push(RecipeRef::new(name)),
+    }
+}
@@ -0,0 +1,19 @@
+-- Disable triggers that send email so restored data cannot notify real users
+DO $recipe$
+DECLARE
+    trigger_row record;
+BEGIN
+    FOR trigger_row IN
+        SELECT t.tgname, c.relname, n.nspname
+        FROM pg_trigger t
+        JOIN pg_class c ON c.oid = t.tgrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_proc p ON p.oid = t.tgfoid
+        WHERE NOT t.tgisinternal
+          AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+          AND (t.tgname ILIKE '{{pattern}}' OR p.proname ILIKE '{{pattern}}')
+    LOOP
+        EXECUTE format('ALTER TABLE %I.%I DISABLE TRIGGER %I', trigger_row.nspname, trigger_row.relname, trigger_row.tgname);
+    END LOOP;
+END
+$recipe$;
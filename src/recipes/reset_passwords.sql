@@ -0,0 +1,3 @@
+-- Reset every user's password to a known development value
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+UPDATE "{{schema}}"."{{table}}" SET "{{column}}" = crypt('{{password}}', gen_salt('bf'));
@@ -0,0 +1,15 @@
+-- Empty audit and history tables, which are large and rarely needed outside production
+DO $recipe$
+DECLARE
+    table_row record;
+BEGIN
+    FOR table_row IN
+        SELECT schemaname, tablename
+        FROM pg_tables
+        WHERE schemaname NOT IN ('pg_catalog', 'information_schema')
+          AND tablename ILIKE '{{pattern}}'
+    LOOP
+        EXECUTE format('TRUNCATE TABLE %I.%I CASCADE', table_row.schemaname, table_row.tablename);
+    END LOOP;
+END
+$recipe$;
@@ -1,6 +1,129 @@
 use anyhow::Result;
+use std::fmt;
 use std::path::Path;
 use async_trait::async_trait;
+use log::debug;
+use crate::dead_letter::DeadLetterReport;
+
+/// What a restore will do to its target index, collection or database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImpactAction {
+    /// The target does not exist yet and will be created
+    Create,
+    /// Documents are written into the existing target, replacing any with the same ID
+    Append,
+    /// The existing database is dropped and created again empty
+    Recreate,
+    /// The objects in the snapshot are dropped from the existing database and reloaded
+    Clean,
+    /// A new index is loaded and the existing alias moved to it
+    Swap,
+    /// What the target holds is replaced by the snapshot's own copy of it
+    Replace,
+}
+
+impl fmt::Display for ImpactAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImpactAction::Create => write!(f, "create"),
+            ImpactAction::Append => write!(f, "append"),
+            ImpactAction::Recreate => write!(f, "recreate"),
+            ImpactAction::Clean => write!(f, "clean"),
+            ImpactAction::Swap => write!(f, "swap"),
+            ImpactAction::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+/// Result of a finished restore
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreOutcome {
+    /// Name of the restored database, index, or collection
+    pub name: String,
+    /// Records the target rejected, if any
+    pub dead_letters: Option<DeadLetterReport>,
+}
+
+/// Current state of a restore target, shown before restoring into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreImpact {
+    /// What the target is called, e.g. `index` or `collection`
+    pub kind: &'static str,
+    /// Name of the index or collection
+    pub name: String,
+    /// What the target holds, e.g. `documents` or `points`
+    pub unit: &'static str,
+    /// Whether the target exists
+    pub exists: bool,
+    /// Number of documents or points, if reported
+    pub count: Option<u64>,
+    /// Size on disk in bytes, if reported
+    pub size_bytes: Option<u64>,
+    /// What the restore does to the target if it exists
+    pub existing: ImpactAction,
+}
+
+impl RestoreImpact {
+    /// Impact of restoring into a target that does not exist
+    pub fn missing(kind: &'static str, name: &str, unit: &'static str) -> Self {
+        debug!("Recording missing {} {}", kind, name);
+        Self { kind, name: name.to_string(), unit, exists: false, count: None, size_bytes: None, existing: ImpactAction::Append }
+    }
+
+    /// The impact of restoring the snapshot stored under `key` into the target
+    ///
+    /// A Qdrant-native snapshot replaces what an existing collection holds
+    /// rather than adding points to it.
+    pub fn for_snapshot(mut self, key: &str) -> Self {
+        debug!("Adjusting impact on {} {} for snapshot {}", self.kind, self.name, key);
+        if self.kind == "collection" && self.unit == "points" && crate::qdrant_snapshot::is_native_snapshot_key(key) {
+            self.existing = ImpactAction::Replace;
+        }
+        self
+    }
+
+    /// What the restore will do to the target
+    pub fn action(&self) -> ImpactAction {
+        debug!("Deciding restore action for {} {}", self.kind, self.name);
+        if self.exists { self.existing } else { ImpactAction::Create }
+    }
+
+    /// Whether existing data is at risk, so the target name must be typed to confirm
+    ///
+    /// A target whose count is not reported is treated as holding data, and a
+    /// database restored over always needs its name typed.
+    pub fn needs_typed_confirmation(&self) -> bool {
+        debug!("Checking if restoring into {} {} needs confirmation", self.kind, self.name);
+        self.exists && (self.count != Some(0) || self.existing != ImpactAction::Append)
+    }
+
+    /// Lines describing the target and the restore's effect on it
+    pub fn lines(&self) -> Vec<String> {
+        debug!("Describing impact on {} {}", self.kind, self.name);
+        if !self.exists {
+            return vec![format!("{} {} does not exist and will be created", capitalize(self.kind), self.name)];
+        }
+        let count = self.count.map_or_else(|| format!("an unknown number of {}", self.unit), |count| format!("{} {}", count, self.unit));
+        let size = self.size_bytes
+            .map(|bytes| format!(", {}", humansize::format_size(bytes, humansize::DECIMAL)))
+            .unwrap_or_default();
+        let effect = match self.existing {
+            ImpactAction::Recreate => "Restore will drop it and restore into an empty database of the same name".to_string(),
+            ImpactAction::Clean => "Restore will drop and reload the objects in the snapshot; other objects are kept".to_string(),
+            ImpactAction::Swap => "Restore will load a new index and move the alias to it once every document is in; the current index is kept".to_string(),
+            ImpactAction::Replace => format!("Restore will replace its {} with the snapshot's; {} not in the snapshot are lost", self.unit, self.unit),
+            _ => format!("Restore will append to it; {} with the same ID are overwritten", self.unit),
+        };
+        vec![format!("{} {} holds {}{}", capitalize(self.kind), self.name, count, size), effect]
+    }
+}
+
+/// `index` to `Index`
+fn capitalize(word: &str) -> String {
+    debug!("Capitalizing {}", word);
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
 
 /// Trait for restore targets
 /// 
@@ -19,6 +142,8 @@ pub trait RestoreTarget {
     
     /// Restore a snapshot to this target
     /// 
+    /// Returns the name of the restored database, index, or collection.
+    /// 
     /// # Arguments
     /// * `snapshot_path` - Path to the snapshot file
     /// * `progress_callback` - Optional callback for reporting progress (0.0 to 1.0)
@@ -30,4 +155,19 @@ pub trait RestoreTarget {
     
     /// Test the connection to this target
     async fn test_connection(&self) -> Result<String>;
+
+    /// Look up what restoring into this target would change
+    ///
+    /// Targets that restore into a new database return `None`.
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        Ok(None)
+    }
+
+    /// Records the last restore could not load, and the dead-letter file they were written to
+    ///
+    /// Targets that load records in bulk report rejected records here after
+    /// `restore_snapshot` instead of failing; others return `None`.
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        None
+    }
 }
@@ -0,0 +1,186 @@
+// This module contains the restore history of the Rustored application
+// Every finished restore records how many bytes it restored and how long it
+// took in a JSON lines file beside the listing cache. The restore confirmation,
+// the restore plan and `restore-from-s3` estimate a restore's duration from the
+// throughput of past ones, preferring restores into the same target server,
+// where throughput depends most on the hardware, then of the same database.
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name of the history file inside the cache directory
+pub const HISTORY_FILE: &str = "restore-history.jsonl";
+
+/// Number of restores kept in the history
+const MAX_RECORDS: usize = 200;
+
+/// Number of recent restores an estimate is based on
+const ESTIMATE_SAMPLE: usize = 10;
+
+/// One finished restore
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreRecord {
+    /// Kind of restore target: `postgres`, `elasticsearch` or `qdrant`
+    pub datastore: String,
+    /// Database the snapshot was taken of, from its key
+    pub source_db: String,
+    /// Host of the restore target, empty in records from before targets were recorded
+    #[serde(default)]
+    pub target_host: String,
+    /// Size of the restored snapshot in bytes
+    pub bytes: u64,
+    /// How long the restore took, without the download
+    pub seconds: f64,
+    /// Unix timestamp of when the restore finished
+    pub finished_at: f64,
+}
+
+/// How long a restore is expected to take
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub duration: Duration,
+    /// Number of past restores the estimate is based on
+    pub samples: usize,
+    /// Target host the past restores went to, if the estimate is based on restores into it
+    pub target_host: Option<String>,
+}
+
+impl Estimate {
+    /// The estimate for the restore plan, e.g. `about 4m 10s (from 3 past restores into db.internal)`
+    pub fn describe(&self) -> String {
+        debug!("Describing estimate from {} restores", self.samples);
+        format!(
+            "about {} (from {} past restore{}{})",
+            format_duration(self.duration),
+            self.samples,
+            if self.samples == 1 { "" } else { "s" },
+            self.target_host.as_ref().map(|host| format!(" into {}", host)).unwrap_or_default(),
+        )
+    }
+}
+
+/// A duration to the nearest second, e.g. `45s`, `4m 10s` or `2h 5m`
+pub fn format_duration(duration: Duration) -> String {
+    debug!("Formatting duration {:?}", duration);
+    let seconds = duration.as_secs_f64().round() as u64;
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// Estimate how long restoring `bytes` takes from past restores
+///
+/// The most recent restores of `source_db` into `target_host` are used, or
+/// of any database into it when there are none. Without restores into the
+/// host, restores of `source_db` into any target of the same kind are used,
+/// then of any database into one. Their combined throughput is applied to `bytes`.
+///
+/// # Arguments
+///
+/// * `records` - Past restores, oldest first
+/// * `datastore` - Kind of restore target
+/// * `target_host` - Host of the restore target
+/// * `source_db` - Database the snapshot was taken of
+/// * `bytes` - Size of the snapshot
+///
+/// # Returns
+///
+/// The estimate, or `None` when no past restore tells
+pub fn estimate(records: &[RestoreRecord], datastore: &str, target_host: &str, source_db: &str, bytes: u64) -> Option<Estimate> {
+    debug!("Estimating restore of {} bytes of {} into {} {}", bytes, source_db, datastore, target_host);
+    let usable: Vec<&RestoreRecord> = records
+        .iter()
+        .filter(|record| record.datastore == datastore && record.bytes > 0 && record.seconds > 0.0)
+        .collect();
+    let on_target = |record: &&&RestoreRecord| !target_host.is_empty() && record.target_host == target_host;
+    let same_source = |record: &&&RestoreRecord| record.source_db == source_db;
+    let tiers: [(Vec<&RestoreRecord>, bool); 4] = [
+        (usable.iter().filter(on_target).filter(same_source).copied().collect(), true),
+        (usable.iter().filter(on_target).copied().collect(), true),
+        (usable.iter().filter(same_source).copied().collect(), false),
+        (usable.clone(), false),
+    ];
+    let (sample, on_target) = tiers.into_iter().find(|(sample, _)| !sample.is_empty())?;
+    let sample = &sample[sample.len().saturating_sub(ESTIMATE_SAMPLE)..];
+    let total_bytes: u64 = sample.iter().map(|record| record.bytes).sum();
+    let total_seconds: f64 = sample.iter().map(|record| record.seconds).sum();
+    Some(Estimate {
+        duration: Duration::from_secs_f64(bytes as f64 * total_seconds / total_bytes as f64),
+        samples: sample.len(),
+        target_host: on_target.then(|| target_host.to_string()),
+    })
+}
+
+/// History of finished restores kept on disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreHistory {
+    path: PathBuf,
+}
+
+impl RestoreHistory {
+    /// History in `dir`, which is created when the first restore is recorded
+    pub fn in_dir(dir: impl AsRef<Path>) -> Self {
+        let path = dir.as_ref().join(HISTORY_FILE);
+        debug!("Using restore history at {}", path.display());
+        Self { path }
+    }
+
+    /// Past restores, oldest first
+    ///
+    /// A missing history is empty, and unreadable lines are skipped, so the
+    /// plan can always be shown.
+    pub fn load(&self) -> Vec<RestoreRecord> {
+        debug!("Loading restore history from {}", self.path.display());
+        let Ok(contents) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipping unreadable restore history line in {}: {}", self.path.display(), e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Add a finished restore, keeping only the most recent ones
+    ///
+    /// The file is written beside its final name and renamed into place, so a
+    /// crash never leaves a half-written history.
+    pub fn record(&self, record: RestoreRecord) -> Result<()> {
+        debug!("Recording restore of {} into {}", record.source_db, record.datastore);
+        let mut records = self.load();
+        records.push(record);
+        let keep = &records[records.len().saturating_sub(MAX_RECORDS)..];
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create restore history directory {}", dir.display()))?;
+        }
+        let mut contents = String::new();
+        for record in keep {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        let partial = self.path.with_extension("jsonl.partial");
+        std::fs::write(&partial, contents)
+            .with_context(|| format!("Failed to write restore history {}", partial.display()))?;
+        std::fs::rename(&partial, &self.path)
+            .with_context(|| format!("Failed to replace restore history {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Estimate a restore from this history, see `estimate`
+    pub fn estimate(&self, datastore: &str, target_host: &str, source_db: &str, bytes: u64) -> Option<Estimate> {
+        debug!("Estimating restore from {}", self.path.display());
+        estimate(&self.load(), datastore, target_host, source_db, bytes)
+    }
+}
@@ -0,0 +1,166 @@
+// This module contains restore tool detection for the Rustored application
+// A downloaded PostgreSQL snapshot is not always a pg_dump archive: plain SQL
+// dumps need psql, and legacy formats need a wrapper script. The tool is chosen
+// from the first bytes of the decompressed file, so a misnamed snapshot still
+// goes to the right tool, and anything that is neither a pg_dump archive nor
+// SQL text goes to the `restore_command` template of its preset, if there is one.
+
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Magic bytes at the start of a pg_dump custom-format archive
+const CUSTOM_FORMAT_MAGIC: &[u8] = b"PGDMP";
+
+/// Offset of the `ustar` magic in the header of a tar file, as pg_dump's tar format writes
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Bytes read from the start of a file to recognise it
+const HEADER_LEN: usize = 4096;
+
+/// Program a PostgreSQL snapshot is restored with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreTool {
+    /// pg_restore, for custom, tar and directory-format archives
+    PgRestore,
+    /// psql, for plain SQL dumps
+    Psql,
+    /// A command built from a template such as `myrestore {file} {db}`
+    Custom(String),
+}
+
+impl fmt::Display for RestoreTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PgRestore => f.write_str("pg_restore"),
+            Self::Psql => f.write_str("psql"),
+            Self::Custom(template) => write!(f, "custom command `{}`", template),
+        }
+    }
+}
+
+impl RestoreTool {
+    /// Choose the tool for a file from its first bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The first bytes of the decompressed file
+    /// * `custom` - The `restore_command` template, used for unrecognised files
+    pub fn detect(header: &[u8], custom: Option<&str>) -> Self {
+        debug!("Detecting restore tool from {} header bytes", header.len());
+        if header.starts_with(CUSTOM_FORMAT_MAGIC) || header.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5) == Some(b"ustar") {
+            return Self::PgRestore;
+        }
+        if is_sql_text(header) {
+            return Self::Psql;
+        }
+        match custom {
+            Some(template) => Self::Custom(template.to_string()),
+            // pg_restore explains what it cannot read better than a guess would
+            None => Self::PgRestore,
+        }
+    }
+
+    /// Choose the tool for a file or unpacked dump directory on disk
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The decompressed file, or a directory-format dump
+    /// * `custom` - The `restore_command` template, used for unrecognised files
+    pub fn detect_file(path: &Path, custom: Option<&str>) -> Result<Self> {
+        debug!("Detecting restore tool for {}", path.display());
+        if path.is_dir() {
+            return Ok(Self::PgRestore);
+        }
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?
+            .take(HEADER_LEN as u64)
+            .read_to_end(&mut header)?;
+        Ok(Self::detect(&header, custom))
+    }
+
+    /// Describe the tool a snapshot will be restored with, before it is downloaded
+    ///
+    /// A file already on disk is recognised from its bytes; otherwise the key's
+    /// extension tells pg_dump archives and SQL apart, and any other name is
+    /// only recognised once downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the snapshot
+    /// * `path` - The snapshot on disk, if it is already there
+    /// * `custom` - The `restore_command` template of the snapshot's preset
+    pub fn expected(key: &str, path: Option<&Path>, custom: Option<&str>) -> String {
+        debug!("Predicting restore tool for {}", key);
+        let header = path.and_then(|path| {
+            let mut header = Vec::with_capacity(HEADER_LEN);
+            std::fs::File::open(path).ok()?.take(HEADER_LEN as u64).read_to_end(&mut header).ok()?;
+            Some(header)
+        });
+        // Compressed files are only recognised once decompressed
+        if let Some(header) = header.filter(|header| crate::compression::Codec::detect(header).is_none()) {
+            return Self::detect(&header, custom).to_string();
+        }
+        let name = crate::wal::archived_name(key);
+        if name.ends_with(".dump") || name.ends_with(crate::directory_dump::DIRECTORY_EXTENSION) {
+            return Self::PgRestore.to_string();
+        }
+        if name.ends_with(".sql") {
+            return Self::Psql.to_string();
+        }
+        match custom {
+            Some(template) => format!("pg_restore, psql or custom command `{}`, from the file's first bytes", template),
+            None => "pg_restore or psql, from the file's first bytes".to_string(),
+        }
+    }
+}
+
+/// Whether a file header looks like SQL text rather than a binary format
+fn is_sql_text(header: &[u8]) -> bool {
+    // A multi-byte character may be cut off at the end of the header
+    let text = match std::str::from_utf8(header) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&header[..e.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return false,
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    !text.is_empty() && !text.chars().any(|c| c.is_control() && !c.is_whitespace())
+}
+
+/// Build the command a `restore_command` template describes
+///
+/// The template is split on whitespace before the placeholders `{file}`,
+/// `{db}`, `{host}`, `{port}` and `{user}` are filled in, so values are passed
+/// as single arguments and never interpreted by a shell. The password is in
+/// `PGPASSWORD`, as for pg_restore.
+///
+/// # Arguments
+///
+/// * `template` - The template, e.g. `myrestore {file} {db}`
+/// * `file` - The decompressed snapshot file
+/// * `db` - Database to restore into
+/// * `host` - PostgreSQL server host
+/// * `port` - PostgreSQL server port
+/// * `username` - User to connect as, if set
+pub fn custom_command(template: &str, file: &str, db: &str, host: &str, port: u16, username: Option<&str>) -> Result<Command> {
+    debug!("Building custom restore command from {}", template);
+    let port = port.to_string();
+    let args: Vec<String> = template
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("{file}", file)
+                .replace("{db}", db)
+                .replace("{host}", host)
+                .replace("{port}", &port)
+                .replace("{user}", username.unwrap_or_default())
+        })
+        .collect();
+    let (program, args) = args.split_first().ok_or_else(|| anyhow!("restore_command is empty"))?;
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    Ok(cmd)
+}
@@ -0,0 +1,223 @@
+// This module contains retention pruning for the Rustored application
+// Retention rules such as "keep 7 daily, 4 weekly, 12 monthly" decide which
+// snapshots under a key prefix are kept: for each rule the newest snapshot of
+// each of the most recent days, ISO weeks or months is kept, and a snapshot
+// kept by any rule survives. Everything else under the prefix is pruned, except
+// snapshots under a legal hold and pre-change snapshots, which are never pruned.
+// A base backup is kept or pruned together with its chain of archived WAL.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use crate::holds::{HoldCatalog, CATALOG_KEY};
+use crate::manifest::{is_manifest_key, manifest_key};
+use crate::storage::SnapshotStore;
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::ui::models::BackupMetadata;
+
+/// Retention rules for the snapshots under a key prefix
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Key prefix the rules apply to, e.g. `postgres/orders-`
+    #[serde(default)]
+    pub prefix: String,
+    /// Days to keep the newest snapshot of
+    #[serde(default)]
+    pub daily: usize,
+    /// ISO weeks to keep the newest snapshot of
+    #[serde(default)]
+    pub weekly: usize,
+    /// Months to keep the newest snapshot of
+    #[serde(default)]
+    pub monthly: usize,
+}
+
+/// Schema of a `[[retention]]` table in the presets file
+pub(crate) const POLICY_SCHEMA: TableSchema = TableSchema {
+    name: "[[retention]]",
+    fields: &[
+        Field::optional("prefix", Kind::String),
+        Field::optional("daily", Kind::Integer { min: 0 }),
+        Field::optional("weekly", Kind::Integer { min: 0 }),
+        Field::optional("monthly", Kind::Integer { min: 0 }),
+    ],
+    requires: &[],
+    unique: None,
+};
+
+impl RetentionPolicy {
+    /// Fail if the policy would keep nothing, which would prune every snapshot under its prefix
+    pub fn validate(&self) -> Result<()> {
+        debug!("Validating retention policy for '{}'", self.prefix);
+        if self.daily == 0 && self.weekly == 0 && self.monthly == 0 {
+            return Err(anyhow!("Retention policy for '{}' keeps nothing; set daily, weekly or monthly", self.prefix));
+        }
+        Ok(())
+    }
+
+    /// One-line summary, e.g. `keep 7 daily, 4 weekly, 12 monthly under 'orders-'`
+    pub fn summary(&self) -> String {
+        debug!("Summarising retention policy for '{}'", self.prefix);
+        format!("keep {} daily, {} weekly, {} monthly under '{}'", self.daily, self.weekly, self.monthly, self.prefix)
+    }
+
+    /// Whether the policy covers a snapshot key
+    pub fn covers(&self, key: &str) -> bool {
+        debug!("Checking if retention policy for '{}' covers {}", self.prefix, key);
+        key.starts_with(&self.prefix)
+    }
+}
+
+/// A snapshot kept by a prune, with why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kept {
+    pub key: String,
+    /// The rules keeping it, e.g. `daily 2024-06-01` or `held`
+    pub reasons: Vec<String>,
+}
+
+/// What a prune keeps and deletes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrunePlan {
+    /// Kept snapshots, newest first
+    pub keep: Vec<Kept>,
+    /// Snapshots to delete, newest first
+    pub delete: Vec<BackupMetadata>,
+}
+
+/// Time a snapshot was taken, from its last-modified time
+fn taken(snapshot: &BackupMetadata) -> DateTime<Utc> {
+    debug!("Getting time {} was taken", snapshot.key);
+    DateTime::from_timestamp(snapshot.last_modified as i64, 0).unwrap_or_default()
+}
+
+/// Mark the newest snapshot of each of the `count` most recent periods as kept
+///
+/// `snapshots` must be sorted newest first; `period` names a snapshot's period.
+fn keep_newest_per_period(
+    snapshots: &[&BackupMetadata],
+    count: usize,
+    period: impl Fn(DateTime<Utc>) -> String,
+    reasons: &mut [Vec<String>],
+) {
+    debug!("Keeping the newest snapshot of {} periods", count);
+    let mut last: Option<String> = None;
+    let mut kept = 0;
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        if kept == count {
+            break;
+        }
+        let name = period(taken(snapshot));
+        if last.as_ref() != Some(&name) {
+            reasons[i].push(name.clone());
+            last = Some(name);
+            kept += 1;
+        }
+    }
+}
+
+/// Decide which snapshots each policy keeps and which it prunes
+///
+/// A snapshot is governed by the first policy whose prefix covers it, as
+/// presets are matched; snapshots no policy covers are left out of the plan.
+/// The WAL segments of a pruned base backup are pruned with it.
+///
+/// # Arguments
+///
+/// * `snapshots` - The snapshots to prune from
+/// * `policies` - Retention rules, in order of precedence
+/// * `holds` - Legal holds; held snapshots are always kept
+/// * `today` - Day holds are checked against
+pub fn plan_prune(snapshots: &[BackupMetadata], policies: &[RetentionPolicy], holds: &HoldCatalog, today: NaiveDate) -> Result<PrunePlan> {
+    debug!("Planning prune of {} snapshots with {} policies", snapshots.len(), policies.len());
+    let mut plan = PrunePlan::default();
+    let chains = crate::wal::group_chains(snapshots);
+    for (index, policy) in policies.iter().enumerate() {
+        policy.validate()?;
+        let mut covered: Vec<&BackupMetadata> = snapshots
+            .iter()
+            .filter(|snapshot| {
+                // WAL segments go with their base backup's chain, not kept per day
+                !snapshot.key.starts_with(".rustored/") && !snapshot.key.ends_with('/') && !is_manifest_key(&snapshot.key)
+                    && !crate::wal::is_wal_key(&snapshot.key) && !crate::es_index::is_index_definition_key(&snapshot.key)
+            })
+            .filter(|snapshot| policies.iter().position(|p| p.covers(&snapshot.key)) == Some(index))
+            .collect();
+        covered.sort_by(|a, b| b.last_modified.total_cmp(&a.last_modified).then_with(|| b.key.cmp(&a.key)));
+
+        let mut reasons = vec![Vec::new(); covered.len()];
+        keep_newest_per_period(&covered, policy.daily, |t| format!("daily {}", t.format("%Y-%m-%d")), &mut reasons);
+        keep_newest_per_period(&covered, policy.weekly, |t| {
+            let week = t.iso_week();
+            format!("weekly {}-W{:02}", week.year(), week.week())
+        }, &mut reasons);
+        keep_newest_per_period(&covered, policy.monthly, |t| format!("monthly {}", t.format("%Y-%m")), &mut reasons);
+
+        for (snapshot, mut reasons) in covered.into_iter().zip(reasons) {
+            // Without its base backup a WAL segment cannot be replayed, so a chain is held as a whole
+            let segments = chains.get(&snapshot.key).map_or(&[][..], |chain| &chain.segments[..]);
+            if holds.is_held(&snapshot.key, today) || segments.iter().any(|segment| holds.is_held(&segment.key, today)) {
+                reasons.push("held".to_string());
+            }
+            if snapshot.is_pinned() {
+                reasons.push("pre-change".to_string());
+            }
+            match reasons.is_empty() {
+                true => {
+                    plan.delete.push(snapshot.clone());
+                    plan.delete.extend(segments.iter().cloned());
+                }
+                false => plan.keep.push(Kept { key: snapshot.key.clone(), reasons }),
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// Apply retention policies to the snapshots under `prefix` in a store
+///
+/// Used by the schedule daemon after a backup. The hold catalog is read from
+/// the store and every deletion is checked against it. Manifests of pruned
+/// snapshots are deleted with them.
+///
+/// # Arguments
+///
+/// * `store` - Storage backend holding the snapshots
+/// * `prefix` - Key prefix to list snapshots under
+/// * `policies` - Retention rules, in order of precedence
+/// * `today` - Day holds are checked against
+/// * `dry_run` - Plan without deleting anything
+///
+/// # Returns
+///
+/// The plan, carried out unless `dry_run` is set
+pub async fn prune(
+    store: &dyn SnapshotStore,
+    prefix: &str,
+    policies: &[RetentionPolicy],
+    today: NaiveDate,
+    dry_run: bool,
+) -> Result<PrunePlan> {
+    debug!("Pruning snapshots under {} with {} policies", prefix, policies.len());
+    let snapshots = store.list_snapshots(prefix).await?;
+    let holds = match store.read_object(CATALOG_KEY).await? {
+        Some(bytes) => HoldCatalog::from_json(&bytes)?,
+        None => HoldCatalog::default(),
+    };
+    let plan = plan_prune(&snapshots, policies, &holds, today)?;
+    if dry_run {
+        return Ok(plan);
+    }
+    for snapshot in &plan.delete {
+        holds.ensure_deletable(&snapshot.key, today)?;
+        store.delete_object(&snapshot.key).await?;
+        info!("Pruned {}", snapshot.key);
+        for sidecar in [manifest_key(&snapshot.key), crate::es_index::index_definition_key(&snapshot.key)] {
+            if snapshots.iter().any(|object| object.key == sidecar) {
+                store.delete_object(&sidecar).await?;
+            }
+        }
+    }
+    Ok(plan)
+}
@@ -0,0 +1,249 @@
+// This module contains row filtering of PostgreSQL restores
+// pg_restore loads whole tables, so rows that must not reach another
+// environment, such as soft-deleted customers, are filtered on the way in. A
+// filter file names tables and a SQL predicate for each. The data of those
+// tables is left out of the main pg_restore run and loaded afterwards from
+// pg_restore's script output, with every `COPY` rewritten to go into a
+// temporary staging table first and only the rows passing the predicate
+// inserted into the real table. Indexes and constraints are created after the
+// filtered loads, so foreign keys are checked against the rows that were kept.
+//
+// ```toml
+// [[filter]]
+// table = "public.customers"
+// exclude_where = "deleted_at IS NOT NULL"
+//
+// [[filter]]
+// table = "audit.*"
+// where = "created_at > now() - interval '30 days'"
+// ```
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::toc::{pattern_matches, Relation, Toc, TocEntry};
+
+/// Table rows are staged in before the filtered insert, in the session's temporary schema
+const STAGING_TABLE: &str = "pg_temp.rustored_staged_rows";
+
+/// Rows of the tables matching `table` that are restored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowFilter {
+    /// Table pattern, as for `--include-table`: `schema.table`, `table` or `schema.*`
+    pub table: String,
+    /// Only rows matching this predicate are restored
+    #[serde(default, rename = "where")]
+    pub keep: Option<String>,
+    /// Rows matching this predicate are left out
+    #[serde(default)]
+    pub exclude_where: Option<String>,
+}
+
+impl RowFilter {
+    /// The `WHERE` condition of the rows restored
+    ///
+    /// Rows for which `exclude_where` is NULL are kept, as only rows it
+    /// matches are excluded.
+    pub fn predicate(&self) -> String {
+        debug!("Building row predicate for {}", self.table);
+        match (&self.keep, &self.exclude_where) {
+            (Some(keep), Some(exclude)) => format!("({}) AND NOT COALESCE(({}), false)", keep, exclude),
+            (Some(keep), None) => format!("({})", keep),
+            (None, Some(exclude)) => format!("NOT COALESCE(({}), false)", exclude),
+            (None, None) => "true".to_string(),
+        }
+    }
+}
+
+/// Schema of a `[[filter]]` table
+const FILTER_SCHEMA: TableSchema = TableSchema {
+    name: "[[filter]]",
+    fields: &[
+        Field::required("table", Kind::String),
+        Field::optional("where", Kind::String),
+        Field::optional("exclude_where", Kind::String),
+    ],
+    requires: &[],
+    // Only the first filter matching a table applies
+    unique: Some("table"),
+};
+
+/// Schema of the row filter file, checked before it is deserialized
+pub const SCHEMA: TableSchema = TableSchema {
+    name: "the row filter file",
+    fields: &[Field::optional("filter", Kind::Tables(&FILTER_SCHEMA))],
+    requires: &[],
+    unique: None,
+};
+
+/// Row filters of a PostgreSQL restore
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RowFilters {
+    /// The first filter whose pattern matches a table applies to it
+    #[serde(default, rename = "filter")]
+    pub filters: Vec<RowFilter>,
+}
+
+impl RowFilters {
+    /// Parse row filters from TOML
+    pub fn parse(contents: &str) -> Result<Self> {
+        debug!("Parsing row filters");
+        crate::config_schema::validate(contents, &SCHEMA, "row filters")?;
+        let filters: Self = toml::from_str(contents).map_err(|e| anyhow!("Invalid row filters: {}", e))?;
+        if let Some(filter) = filters.filters.iter().find(|filter| filter.keep.is_none() && filter.exclude_where.is_none()) {
+            return Err(anyhow!("Invalid row filter for {}: set where, exclude_where or both", filter.table));
+        }
+        Ok(filters)
+    }
+
+    /// Load row filters from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading row filters from {:?}", path);
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read row filters {}: {}", path.display(), e))?;
+        let filters = Self::parse(&contents).with_context(|| format!("Failed to load {}", path.display()))?;
+        info!("Loaded {} row filters from {}", filters.filters.len(), path.display());
+        Ok(filters)
+    }
+
+    /// The filter applying to a table, if any
+    pub fn find(&self, (schema, name): (&str, &str)) -> Option<&RowFilter> {
+        debug!("Finding row filter for {}.{}", schema, name);
+        self.filters.iter().find(|filter| pattern_matches(&filter.table, (schema, name)))
+    }
+
+    /// Split an archive's restore into the main use-list and the filtered table loads
+    ///
+    /// # Arguments
+    ///
+    /// * `toc` - Table of contents of the archive
+    /// * `include` - Patterns of the relations to restore, all if empty
+    /// * `exclude` - Patterns of the relations to leave out
+    ///
+    /// # Returns
+    ///
+    /// The use-list with the data of filtered tables commented out, and each
+    /// filtered table's data entry with its predicate, in archive order
+    pub fn plan(&self, toc: &Toc, include: &[String], exclude: &[String]) -> Result<(String, Vec<(TocEntry, String)>)> {
+        debug!("Planning row filtered restore with {} filters", self.filters.len());
+        let relations = toc.relations();
+        if let Some(filter) = self.filters.iter().find(|filter| !relations.iter().any(|(schema, name)| pattern_matches(&filter.table, (schema, name)))) {
+            return Err(anyhow!("Row filter for {} matches no table in the snapshot", filter.table));
+        }
+        let list = match include.is_empty() && exclude.is_empty() {
+            true => toc.lines.iter().map(|line| match line {
+                Ok(entry) => format!("{}\n", entry.line),
+                Err(comment) => format!("{}\n", comment),
+            }).collect(),
+            false => toc.use_list(include, exclude)?,
+        };
+        let staged: Vec<(TocEntry, String)> = toc
+            .entries()
+            .filter(|entry| entry.desc == "TABLE DATA")
+            .filter_map(|entry| {
+                let (schema, name): &Relation = entry.relation.as_ref()?;
+                if !crate::toc::is_selected(include, exclude, (schema, name)) {
+                    return None;
+                }
+                self.find((schema, name)).map(|filter| (entry.clone(), filter.predicate()))
+            })
+            .collect();
+        let list = list
+            .lines()
+            .map(|line| match staged.iter().any(|(entry, _)| entry.line == line) {
+                true => format!(";{}\n", line),
+                false => format!("{}\n", line),
+            })
+            .collect();
+        Ok((list, staged))
+    }
+}
+
+/// Rewrites the `COPY` blocks of a pg_restore data script into staged, filtered loads
+///
+/// Fed the script line by line, it turns `COPY t (a, b) FROM stdin;` into a
+/// staging table shaped like `t` and a `COPY` into it, and the `\.` ending the
+/// rows into an `INSERT ... SELECT` of the rows passing the predicate.
+#[derive(Debug)]
+pub struct StagedCopy {
+    predicate: String,
+    /// Target table and column list of the `COPY` being read, if inside one
+    copying: Option<(String, String)>,
+}
+
+impl StagedCopy {
+    /// Stage every `COPY` of a script and keep only rows matching `predicate`
+    pub fn new(predicate: &str) -> Self {
+        debug!("Staging COPY blocks with predicate {}", predicate);
+        Self { predicate: predicate.to_string(), copying: None }
+    }
+
+    /// Rewrite one line of the script, including its line ending
+    pub fn line(&mut self, line: &[u8]) -> Vec<u8> {
+        // No logging here: this runs for every row of the filtered tables
+        if let Some((table, columns)) = &self.copying {
+            if line.strip_suffix(b"\n").unwrap_or(line) != b"\\." {
+                return line.to_vec();
+            }
+            let select = columns.trim_start_matches('(').trim_end_matches(')');
+            let select = if select.is_empty() { "*" } else { select };
+            let insert = format!(
+                "\\.\nINSERT INTO {table} {columns} SELECT {select} FROM {staged} WHERE {predicate};\nDROP TABLE {staged};\n",
+                table = table, columns = columns, select = select, staged = STAGING_TABLE, predicate = self.predicate,
+            );
+            self.copying = None;
+            return insert.into_bytes();
+        }
+        let text = String::from_utf8_lossy(line);
+        let Some(copy) = text.trim_end().strip_prefix("COPY ").and_then(|rest| rest.strip_suffix(" FROM stdin;")) else {
+            return line.to_vec();
+        };
+        let (table, columns) = match copy.find(" (") {
+            Some(index) => (copy[..index].to_string(), copy[index + 1..].to_string()),
+            None => (copy.to_string(), String::new()),
+        };
+        let staged = format!(
+            "CREATE TEMP TABLE {staged} (LIKE {table} INCLUDING DEFAULTS);\nCOPY {staged} {columns} FROM stdin;\n",
+            staged = STAGING_TABLE, table = table, columns = columns,
+        );
+        self.copying = Some((table, columns));
+        staged.into_bytes()
+    }
+}
+
+/// Load one table's data from an archive, keeping only the rows passing `predicate`
+///
+/// pg_restore writes the entry's data as a script, which is rewritten by
+/// `StagedCopy` on its way into psql.
+///
+/// # Arguments
+///
+/// * `archive` - Custom-format file or directory-format dump
+/// * `entry` - The `TABLE DATA` entry of the table
+/// * `predicate` - `WHERE` condition of the rows kept
+/// * `rate` - Bytes per second the data is copied at, unlimited if `None`
+/// * `name` - Database to load into
+/// * `host` - PostgreSQL server host
+/// * `port` - PostgreSQL server port
+/// * `username` - User to connect as, if set
+#[allow(clippy::too_many_arguments)]
+pub async fn staged_load(
+    archive: &Path,
+    entry: &TocEntry,
+    predicate: &str,
+    rate: Option<u64>,
+    name: &str,
+    host: &str,
+    port: u16,
+    username: Option<&str>,
+) -> Result<()> {
+    debug!("Loading {} with predicate {}", entry.tag, predicate);
+    let list = format!("{}\n", entry.line);
+    crate::backup::load_data_script(archive, &list, Some(StagedCopy::new(predicate)), rate, name, host, port, username)
+        .await
+        .with_context(|| format!("Filtered load of {} failed", entry.tag))?;
+    info!("Loaded the rows of {} passing {}", entry.tag, predicate);
+    Ok(())
+}
@@ -0,0 +1,349 @@
+// This module contains the backup scheduler for the Rustored application
+// `rustored schedule` runs as a daemon that dumps and uploads databases on
+// cron schedules read from the presets file. A job whose previous run is still
+// going when it comes due again is skipped rather than started twice, and each
+// run can be delayed by a random jitter so jobs sharing a schedule do not all
+// hit the server at once. After a successful backup, the retention rules
+// covering the new snapshot prune its older siblings. The daemon writes the status of every job to a JSON
+// object in the snapshot bucket, which the TUI shows as the schedule view.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use log::{debug, info, warn};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+use crate::cancel::{cancellable, CancellationToken};
+use crate::encryption::ClientEncryption;
+use crate::retention::RetentionPolicy;
+use crate::storage::SnapshotStore;
+use crate::config_schema::{Field, Kind, TableSchema};
+use crate::ui::models::PostgresConfig;
+
+/// Key of the schedule status object in the snapshot bucket
+pub const STATUS_KEY: &str = ".rustored/schedule.json";
+
+/// A database backed up on a cron schedule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleJob {
+    /// Name of the database to back up
+    pub database: String,
+    /// Five-field cron expression, evaluated in UTC, e.g. `0 3 * * *`
+    pub cron: String,
+    /// Longest random delay added to each run, in seconds
+    #[serde(default)]
+    pub jitter_seconds: u64,
+    /// Label to embed in the snapshot keys, if any
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Number of parallel pg_dump workers; set to dump in directory format
+    #[serde(default)]
+    pub jobs: Option<u32>,
+}
+
+/// Schema of a `[[schedule]]` table in the presets file
+pub(crate) const JOB_SCHEMA: TableSchema = TableSchema {
+    name: "[[schedule]]",
+    fields: &[
+        Field::required("database", Kind::String),
+        Field::required("cron", Kind::String),
+        Field::optional("jitter_seconds", Kind::Integer { min: 0 }),
+        Field::optional("label", Kind::String),
+        Field::optional("jobs", Kind::Integer { min: 1 }),
+    ],
+    requires: &[],
+    unique: None,
+};
+
+impl ScheduleJob {
+    /// First time the job is due strictly after `after`
+    pub fn next_run(&self, after: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        debug!("Finding next run of {} ({}) after {}", self.database, self.cron, after);
+        Cron::new(&self.cron)
+            .parse()
+            .and_then(|cron| cron.find_next_occurrence(&after, false))
+            .map_err(|e| anyhow!("Invalid cron expression '{}' for {}: {}", self.cron, self.database, e))
+    }
+
+    /// Random delay before a run, up to `jitter_seconds`
+    pub fn jitter(&self) -> Duration {
+        debug!("Choosing jitter of up to {}s for {}", self.jitter_seconds, self.database);
+        Duration::from_secs(rand::thread_rng().gen_range(0..=self.jitter_seconds))
+    }
+}
+
+/// How the last run of a job ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastRun {
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    /// Key of the uploaded snapshot, or why the run failed
+    pub result: std::result::Result<String, String>,
+}
+
+/// Status of one scheduled job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub database: String,
+    pub cron: String,
+    /// When the job is next due
+    pub next_run: DateTime<Utc>,
+    /// When the running run started, if one is running
+    #[serde(default)]
+    pub running_since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_run: Option<LastRun>,
+    /// Runs skipped because the previous run was still going
+    #[serde(default)]
+    pub skipped: u64,
+}
+
+/// Status of every job of the schedule daemon
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    pub jobs: Vec<JobStatus>,
+    /// When the daemon last wrote the status
+    #[serde(default)]
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl ScheduleStatus {
+    /// Status of `jobs` before any has run, failing on an invalid cron expression
+    pub fn new(jobs: &[ScheduleJob], now: DateTime<Utc>) -> Result<Self> {
+        debug!("Creating schedule status for {} jobs", jobs.len());
+        let jobs = jobs
+            .iter()
+            .map(|job| {
+                Ok(JobStatus {
+                    database: job.database.clone(),
+                    cron: job.cron.clone(),
+                    next_run: job.next_run(now)?,
+                    running_since: None,
+                    last_run: None,
+                    skipped: 0,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { jobs, updated: Some(now) })
+    }
+
+    /// Parse a status from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing schedule status ({} bytes)", bytes.len());
+        crate::migration::from_json(crate::migration::MetadataKind::Schedule, bytes)
+    }
+
+    /// Serialize the status to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing schedule status with {} jobs", self.jobs.len());
+        crate::migration::to_json(self)
+    }
+
+    /// Indices of the jobs due at `now`
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<usize> {
+        debug!("Finding jobs due at {}", now);
+        self.jobs.iter().enumerate().filter(|(_, job)| job.next_run <= now).map(|(i, _)| i).collect()
+    }
+
+    /// Earliest time any job is next due
+    pub fn next_wake(&self) -> Option<DateTime<Utc>> {
+        debug!("Finding next wake of the schedule");
+        self.jobs.iter().map(|job| job.next_run).min()
+    }
+
+    /// Start a due run of a job, unless its previous run is still going
+    ///
+    /// Either way the job is next due at its first fire time after `now`, so
+    /// fire times missed while a run was going are not made up for.
+    ///
+    /// # Returns
+    ///
+    /// Whether the run should go ahead
+    pub fn start(&mut self, index: usize, job: &ScheduleJob, now: DateTime<Utc>) -> Result<bool> {
+        debug!("Starting run of {} at {}", job.database, now);
+        let status = self.jobs.get_mut(index).ok_or_else(|| anyhow!("No scheduled job {}", index))?;
+        status.next_run = job.next_run(now)?;
+        if let Some(since) = status.running_since {
+            warn!("Skipping run of {}: the run started at {} is still going", job.database, since);
+            status.skipped += 1;
+            return Ok(false);
+        }
+        status.running_since = Some(now);
+        Ok(true)
+    }
+
+    /// Record the end of a job's run
+    pub fn finish(&mut self, index: usize, result: &Result<String>, now: DateTime<Utc>) {
+        debug!("Finishing run of job {} at {}", index, now);
+        if let Some(status) = self.jobs.get_mut(index) {
+            let started = status.running_since.take().unwrap_or(now);
+            let result = match result {
+                Ok(key) => Ok(key.clone()),
+                Err(e) => Err(e.to_string()),
+            };
+            status.last_run = Some(LastRun { started, finished: now, result });
+        }
+    }
+
+    /// One line per job for display, e.g. in the TUI schedule view
+    pub fn lines(&self) -> Vec<String> {
+        debug!("Formatting schedule status lines");
+        let time = |t: DateTime<Utc>| t.format("%Y-%m-%d %H:%M UTC").to_string();
+        let mut lines: Vec<String> = self
+            .jobs
+            .iter()
+            .map(|job| {
+                let last = match (&job.running_since, &job.last_run) {
+                    (Some(since), _) => format!("running since {}", time(*since)),
+                    (None, Some(LastRun { started, result: Ok(_), .. })) => format!("last ok {}", time(*started)),
+                    (None, Some(LastRun { started, result: Err(e), .. })) => format!("last failed {}: {}", time(*started), e),
+                    (None, None) => "not run yet".to_string(),
+                };
+                let mut line = format!("{} [{}]: next {}, {}", job.database, job.cron, time(job.next_run), last);
+                if job.skipped > 0 {
+                    line.push_str(&format!(", {} skipped", job.skipped));
+                }
+                line
+            })
+            .collect();
+        if let Some(updated) = self.updated {
+            lines.push(format!("Updated {}", time(updated)));
+        }
+        lines
+    }
+}
+
+/// Read the schedule status the daemon last wrote to the bucket
+///
+/// # Returns
+///
+/// The status, or `None` if no daemon has written one
+pub async fn load_status(store: &dyn SnapshotStore) -> Result<Option<ScheduleStatus>> {
+    debug!("Loading schedule status from {}", STATUS_KEY);
+    store.read_object(STATUS_KEY).await?.map(|bytes| ScheduleStatus::from_json(&bytes)).transpose()
+}
+
+/// Write the status to the bucket, logging rather than failing so a flaky
+/// bucket never stops the schedule
+async fn save_status(store: &dyn SnapshotStore, status: &Mutex<ScheduleStatus>) {
+    debug!("Saving schedule status to {}", STATUS_KEY);
+    let json = {
+        let mut status = status.lock().await;
+        status.updated = Some(Utc::now());
+        status.to_json()
+    };
+    let saved = match json {
+        Ok(json) => store.write_object(STATUS_KEY, json).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        warn!("Failed to save schedule status: {}", e);
+    }
+}
+
+/// Prune with the retention rules covering a newly uploaded snapshot
+///
+/// Logs rather than fails, since the backup itself succeeded.
+async fn apply_retention(store: &dyn SnapshotStore, prefix: &str, retention: &[RetentionPolicy], key: &str) {
+    debug!("Applying retention after uploading {}", key);
+    if !retention.iter().any(|policy| policy.covers(key)) {
+        return;
+    }
+    match crate::retention::prune(store, prefix, retention, Utc::now().date_naive(), false).await {
+        Ok(plan) => info!("Retention kept {} and pruned {} snapshots after {}", plan.keep.len(), plan.delete.len(), key),
+        Err(e) => warn!("Retention after {} failed: {}", key, e),
+    }
+}
+
+/// Run scheduled backups until `cancel` is cancelled
+///
+/// Each due job runs on its own task after its jitter, so a slow dump does
+/// not hold up other databases. Cancelling stops the running dumps and waits
+/// for their status to be recorded.
+///
+/// # Arguments
+///
+/// * `store` - Storage backend to upload snapshots and the status to
+/// * `pg_config` - Connection settings of the server
+/// * `prefix` - Key prefix snapshots are uploaded under (may be empty)
+/// * `jobs` - The scheduled jobs
+/// * `retention` - Retention rules applied after each successful backup
+/// * `encryption` - Recipients to encrypt dumps for before upload
+/// * `cancel` - Stops the daemon
+pub async fn run_schedule(
+    store: Arc<dyn SnapshotStore>,
+    pg_config: PostgresConfig,
+    prefix: String,
+    jobs: Vec<ScheduleJob>,
+    retention: Vec<RetentionPolicy>,
+    encryption: ClientEncryption,
+    cancel: CancellationToken,
+) -> Result<()> {
+    debug!("Starting backup schedule with {} jobs", jobs.len());
+    if jobs.is_empty() {
+        return Err(anyhow!("No [[schedule]] or [[gc]] entries are defined in the presets file"));
+    }
+    for policy in &retention {
+        policy.validate()?;
+    }
+    let retention = Arc::new(retention);
+    let encryption = Arc::new(encryption);
+    let status = Arc::new(Mutex::new(ScheduleStatus::new(&jobs, Utc::now())?));
+    save_status(store.as_ref(), &status).await;
+    let mut running = JoinSet::new();
+
+    loop {
+        let wake = status.lock().await.next_wake().unwrap_or_else(Utc::now);
+        let delay = (wake - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+            // Reap finished runs so the set does not grow
+            Some(_) = running.join_next(), if !running.is_empty() => continue,
+        }
+
+        let now = Utc::now();
+        let due = status.lock().await.due(now);
+        for index in due {
+            let job = jobs[index].clone();
+            if !status.lock().await.start(index, &job, now)? {
+                continue;
+            }
+            let (store, pg_config, prefix, status, retention, encryption, cancel) = (
+                store.clone(),
+                pg_config.clone(),
+                prefix.clone(),
+                status.clone(),
+                retention.clone(),
+                encryption.clone(),
+                cancel.clone(),
+            );
+            running.spawn(async move {
+                let run = async {
+                    tokio::time::sleep(job.jitter()).await;
+                    info!("Running scheduled backup of {}", job.database);
+                    crate::backup::backup_database(store.as_ref(), &pg_config, &prefix, &job.database, job.label.as_deref(), job.jobs, &encryption, |_| {}).await
+                };
+                let result = cancellable(&cancel, &format!("Scheduled backup of {}", job.database), run).await;
+                match &result {
+                    Ok(key) => {
+                        info!("Scheduled backup of {} uploaded to {}", job.database, key);
+                        apply_retention(store.as_ref(), &prefix, &retention, key).await;
+                    }
+                    Err(e) => warn!("Scheduled backup of {} failed: {}", job.database, e),
+                }
+                status.lock().await.finish(index, &result, Utc::now());
+                save_status(store.as_ref(), &status).await;
+            });
+        }
+        save_status(store.as_ref(), &status).await;
+    }
+
+    info!("Stopping backup schedule, waiting for {} running backups", running.len());
+    while running.join_next().await.is_some() {}
+    Ok(())
+}
@@ -0,0 +1,277 @@
+// This module contains snapshot sharing for the Rustored application
+// `share export` writes a small descriptor of a snapshot: where it is, its
+// checksum and how it is encrypted, signed with a secret the teams share.
+// `share import` checks the signature and records the descriptor in the
+// importing bucket's shared catalog, and `restore-from-s3 --shared` restores
+// it from where it already is, so a restore point moves between teams without
+// its objects being copied.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use crate::encryption::Cipher;
+use crate::storage::{S3Store, SnapshotEncryption, SnapshotStore};
+use crate::table::{Align, Table};
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::BackupMetadata;
+
+/// Key of the shared catalog object in the snapshot bucket
+pub const SHARED_CATALOG_KEY: &str = ".rustored/shared.json";
+
+/// Format version of share descriptors, bumped if their fields change meaning
+pub const SHARE_VERSION: u32 = 1;
+
+/// Where a shared snapshot is and how to read it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareDescriptor {
+    /// Format version, see `SHARE_VERSION`
+    pub version: u32,
+    /// Storage backend holding the snapshot, e.g. `S3`
+    pub backend: String,
+    /// Bucket holding the snapshot
+    pub bucket: String,
+    /// Key of the snapshot
+    pub key: String,
+    /// Version of the snapshot on a versioned bucket, if a version was shared
+    #[serde(default)]
+    pub version_id: Option<String>,
+    /// Size of the snapshot in bytes
+    pub size: i64,
+    /// ETag of the snapshot when it was shared
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// Hex encoded SHA-256 checksum from the snapshot's backup manifest, if it has one
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Database the snapshot was taken from, from its backup manifest
+    #[serde(default)]
+    pub database: Option<String>,
+    /// How the snapshot is encrypted, e.g. `age; SSE-KMS (alias/backups)`, so the importer knows which key to ask for
+    #[serde(default)]
+    pub decryption: Option<String>,
+    /// When the snapshot was shared
+    pub exported: DateTime<Utc>,
+    /// Hex encoded HMAC-SHA256 of the other fields, keyed with the share secret
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl ShareDescriptor {
+    /// Parse a descriptor from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing share descriptor ({} bytes)", bytes.len());
+        serde_json::from_slice(bytes).context("Failed to parse share descriptor")
+    }
+
+    /// Serialize the descriptor to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing share descriptor of {}", self.key);
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// HMAC-SHA256 of every field but the signature
+    fn mac(&self, secret: &str) -> Result<Hmac<Sha256>> {
+        debug!("Computing share signature of {}", self.key);
+        if secret.is_empty() {
+            return Err(anyhow!("Set --share-secret or RUSTORED_SHARE_SECRET to sign and check shares"));
+        }
+        let unsigned = Self { signature: String::new(), ..self.clone() };
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+        mac.update(&serde_json::to_vec(&unsigned)?);
+        Ok(mac)
+    }
+
+    /// Sign the descriptor with the share secret
+    pub fn sign(&mut self, secret: &str) -> Result<()> {
+        debug!("Signing share descriptor of {}", self.key);
+        self.signature = hex::encode(self.mac(secret)?.finalize().into_bytes());
+        Ok(())
+    }
+
+    /// Fail unless the descriptor was signed with the share secret and is a version this build reads
+    pub fn verify(&self, secret: &str) -> Result<()> {
+        debug!("Verifying share descriptor of {}", self.key);
+        if self.version != SHARE_VERSION {
+            return Err(anyhow!("Share of {} has format version {}, this rustored reads version {}", self.key, self.version, SHARE_VERSION));
+        }
+        let signature = hex::decode(&self.signature).unwrap_or_default();
+        self.mac(secret)?
+            .verify_slice(&signature)
+            .map_err(|_| anyhow!("Share of {} is not signed with this share secret, or was changed after it was signed", self.key))
+    }
+}
+
+/// Catalog of the snapshots other teams shared with a bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SharedCatalog {
+    pub shares: Vec<ShareDescriptor>,
+}
+
+impl SharedCatalog {
+    /// Parse a catalog from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing shared catalog ({} bytes)", bytes.len());
+        crate::migration::from_json(crate::migration::MetadataKind::Shared, bytes)
+    }
+
+    /// Serialize the catalog to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing shared catalog with {} shares", self.shares.len());
+        crate::migration::to_json(self)
+    }
+
+    /// Record a share, replacing an earlier share of the same snapshot
+    ///
+    /// # Returns
+    ///
+    /// Whether an earlier share was replaced
+    pub fn register(&mut self, descriptor: ShareDescriptor) -> bool {
+        debug!("Registering share of {} in {}", descriptor.key, descriptor.bucket);
+        let earlier = self.shares.iter().position(|share| {
+            share.bucket == descriptor.bucket && share.key == descriptor.key && share.version_id == descriptor.version_id
+        });
+        match earlier {
+            Some(index) => {
+                self.shares[index] = descriptor;
+                true
+            }
+            None => {
+                self.shares.push(descriptor);
+                false
+            }
+        }
+    }
+
+    /// The shares as a table for `share list`
+    pub fn table(&self) -> Table {
+        debug!("Tabulating {} shares", self.shares.len());
+        let mut table = Table::new(&[("KEY", Align::Left), ("BUCKET", Align::Left), ("SIZE", Align::Right), ("SHARED", Align::Left), ("DECRYPTION", Align::Left)]);
+        for share in &self.shares {
+            let size = humansize::format_size(share.size.max(0) as u64, humansize::DECIMAL);
+            let shared = share.exported.format("%Y-%m-%d").to_string();
+            table.row(vec![share.key.as_str().into(), share.bucket.as_str().into(), size.into(), shared.into(), share.decryption.as_deref().unwrap_or_default().into()]);
+        }
+        table
+    }
+
+    /// The latest share of a snapshot key
+    pub fn find(&self, key: &str) -> Option<&ShareDescriptor> {
+        debug!("Finding share of {}", key);
+        self.shares.iter().rev().find(|share| share.key == key)
+    }
+}
+
+/// Read the bucket's shared catalog, empty if nothing was imported yet
+pub async fn load_catalog(store: &dyn SnapshotStore) -> Result<SharedCatalog> {
+    debug!("Loading shared catalog");
+    match store.read_object(SHARED_CATALOG_KEY).await? {
+        Some(bytes) => SharedCatalog::from_json(&bytes),
+        None => Ok(SharedCatalog::default()),
+    }
+}
+
+/// Describe and sign a snapshot in the browser's bucket for another team
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the bucket holding the snapshot
+/// * `key` - Key of the snapshot to share
+/// * `version_id` - Version to share on a versioned bucket, or the current one
+/// * `secret` - Secret shared with the importing team
+pub async fn export(browser: &mut SnapshotBrowser, key: &str, version_id: Option<&str>, secret: &str) -> Result<ShareDescriptor> {
+    debug!("Exporting share of {}", key);
+    let store = browser.store().await?;
+    // Sharing hands over a location, which only S3 buckets can be reached at by another team's store
+    if store.name() != "S3" {
+        return Err(anyhow!("Only S3 snapshots can be shared, not {} ones", store.name()));
+    }
+    let snapshot = browser.find_snapshot(key, version_id).await?;
+    let manifest = crate::manifest::read_manifest(store.as_ref(), key).await?;
+    let cipher = [Cipher::Age, Cipher::Gpg].into_iter().find(|cipher| key.ends_with(cipher.extension()));
+    let decryption = [
+        cipher.map(|cipher| cipher.to_string()),
+        store.encryption(key).await?.filter(|encryption| *encryption != SnapshotEncryption::None).map(|encryption| encryption.to_string()),
+    ];
+    let decryption: Vec<String> = decryption.into_iter().flatten().collect();
+    let mut descriptor = ShareDescriptor {
+        version: SHARE_VERSION,
+        backend: store.name().to_string(),
+        bucket: browser.s3_config.bucket.clone(),
+        key: snapshot.key.clone(),
+        version_id: snapshot.version_id.clone(),
+        size: snapshot.size,
+        etag: snapshot.etag.clone(),
+        sha256: manifest.as_ref().map(|manifest| manifest.sha256.clone()),
+        database: manifest.map(|manifest| manifest.database),
+        decryption: (!decryption.is_empty()).then(|| decryption.join("; ")),
+        exported: Utc::now(),
+        signature: String::new(),
+    };
+    descriptor.sign(secret)?;
+    info!("Exported share of {} in {}", descriptor.key, descriptor.bucket);
+    Ok(descriptor)
+}
+
+/// Check a share descriptor and record it in the browser's bucket's shared catalog
+///
+/// The catalog is re-read from the bucket first so shares imported by other
+/// instances are kept.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the importing bucket
+/// * `bytes` - The descriptor as `share export` wrote it
+/// * `secret` - Secret shared with the exporting team
+///
+/// # Returns
+///
+/// The descriptor, and whether it replaced an earlier share of the same snapshot
+pub async fn import(browser: &mut SnapshotBrowser, bytes: &[u8], secret: &str) -> Result<(ShareDescriptor, bool)> {
+    debug!("Importing share descriptor");
+    let descriptor = ShareDescriptor::from_json(bytes)?;
+    descriptor.verify(secret)?;
+    let store = browser.store().await?;
+    let mut catalog = load_catalog(store.as_ref()).await?;
+    let replaced = catalog.register(descriptor.clone());
+    store.write_object(SHARED_CATALOG_KEY, catalog.to_json()?).await?;
+    info!("Imported share of {} in {}", descriptor.key, descriptor.bucket);
+    Ok((descriptor, replaced))
+}
+
+/// Point the browser at the bucket of an imported share and look its snapshot up
+///
+/// The signature is checked again, since anyone who can write the catalog
+/// could change it, and a snapshot whose size or ETag changed since it was
+/// shared is refused. The browser keeps reading the shared bucket afterwards.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser of the importing bucket
+/// * `key` - Key of the shared snapshot
+/// * `secret` - Secret shared with the exporting team
+pub async fn open_shared(browser: &mut SnapshotBrowser, key: &str, secret: &str) -> Result<BackupMetadata> {
+    debug!("Opening shared snapshot {}", key);
+    let catalog = load_catalog(browser.store().await?.as_ref()).await?;
+    let descriptor = catalog.find(key).ok_or_else(|| anyhow!("{} has not been shared with this bucket; run share import first", key))?;
+    descriptor.verify(secret)?;
+    let store = S3Store {
+        client: browser.s3_config.create_client().await?,
+        bucket: descriptor.bucket.clone(),
+        sse_customer_key: browser.s3_config.customer_key()?,
+    };
+    let current = match &descriptor.version_id {
+        Some(version_id) => store.list_versions(key).await?.into_iter().find(|version| version.version_id.as_ref() == Some(version_id)),
+        None => store.list_snapshots(key).await?.into_iter().find(|snapshot| snapshot.key == key),
+    };
+    let current = current.ok_or_else(|| anyhow!("{} is no longer in {}", key, descriptor.bucket))?;
+    if current.size != descriptor.size || current.etag != descriptor.etag {
+        return Err(anyhow!("{} in {} has changed since it was shared on {}", key, descriptor.bucket, descriptor.exported.format("%Y-%m-%d")));
+    }
+    browser.set_store(Arc::new(store));
+    info!("Restoring {} shared from {}", key, descriptor.bucket);
+    Ok(current)
+}
@@ -0,0 +1,182 @@
+// This module contains AWS Signature Version 4 request signing
+// Amazon OpenSearch Service domains and OpenSearch Serverless collections
+// authenticate requests with SigV4 instead of basic auth: every request
+// carries an `Authorization` header with an HMAC over its method, path, query,
+// signed headers and a hash of its body, keyed from the secret key, the day,
+// the region and the service. Credentials come from the default AWS credential
+// chain, as for S3.
+
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_s3::config::ProvideCredentials;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::debug;
+use sha2::{Digest, Sha256};
+
+/// Service name of Amazon OpenSearch Service domains
+pub const SERVICE_DOMAIN: &str = "es";
+
+/// Service name of OpenSearch Serverless collections
+pub const SERVICE_SERVERLESS: &str = "aoss";
+
+/// AWS credentials a request is signed with
+#[derive(Clone, PartialEq)]
+pub struct SigningCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Session token of temporary credentials, such as an assumed role's
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for SigningCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"[MASKED]")
+            .field("session_token", &self.session_token.as_ref().map(|_| "[MASKED]"))
+            .finish()
+    }
+}
+
+/// Signs requests to one AWS service in one region
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigV4Signer {
+    pub region: String,
+    /// `es` for domains, `aoss` for serverless collections
+    pub service: String,
+    pub credentials: SigningCredentials,
+}
+
+impl SigV4Signer {
+    /// Signer with the given credentials
+    pub fn new(region: &str, service: &str, credentials: SigningCredentials) -> Self {
+        debug!("Creating SigV4 signer for {} in {}", service, region);
+        Self { region: region.to_string(), service: service.to_string(), credentials }
+    }
+
+    /// Signer with credentials from the default AWS credential chain
+    ///
+    /// The chain reads environment variables, `AWS_PROFILE` and shared config
+    /// files, web identity tokens, and the ECS or EC2 instance role.
+    pub async fn from_default_chain(region: &str, service: &str) -> Result<Self> {
+        debug!("Resolving AWS credentials to sign {} requests in {}", service, region);
+        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(aws_sdk_s3::config::Region::new(region.to_string()))
+            .load()
+            .await;
+        let provider = config
+            .credentials_provider()
+            .ok_or_else(|| anyhow!("No AWS credentials found to sign {} requests", service))?;
+        let credentials = provider
+            .provide_credentials()
+            .await
+            .context("Failed to resolve AWS credentials for SigV4 signing")?;
+        Ok(Self::new(region, service, SigningCredentials {
+            access_key_id: credentials.access_key_id().to_string(),
+            secret_access_key: credentials.secret_access_key().to_string(),
+            session_token: credentials.session_token().map(str::to_string),
+        }))
+    }
+
+    /// Add the SigV4 headers to a request, as signed at `now`
+    ///
+    /// The body must be in memory; streamed bodies cannot be hashed.
+    pub fn sign(&self, request: &mut reqwest::Request, now: DateTime<Utc>) -> Result<()> {
+        debug!("Signing {} {} for {}", request.method(), request.url().path(), self.service);
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let body: &[u8] = match request.body() {
+            Some(body) => body.as_bytes().ok_or_else(|| anyhow!("Cannot sign a streamed request body"))?,
+            None => &[],
+        };
+        let payload_hash = hex::encode(Sha256::digest(body));
+        let url = request.url();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        // Canonical headers, sorted by name
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| (uri_encode(&key, true), uri_encode(&value, true)))
+            .collect();
+        query.sort();
+        let canonical_query = query.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join("&");
+        // Services other than S3 sign the path encoded twice; the URL holds it encoded once
+        let canonical_path = match url.path() {
+            "" => "/".to_string(),
+            path => uri_encode(path, false),
+        };
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method(), canonical_path, canonical_query, canonical_headers, signed_headers, payload_hash,
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        );
+        let key = [date.as_str(), self.region.as_str(), self.service.as_str(), "aws4_request"]
+            .iter()
+            .try_fold(format!("AWS4{}", self.credentials.secret_access_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()))?;
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes())?);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.access_key_id, scope, signed_headers, signature,
+        );
+
+        for (name, value) in headers.into_iter().skip(1) {
+            request.headers_mut().insert(name, value.parse()?);
+        }
+        request.headers_mut().insert(reqwest::header::AUTHORIZATION, authorization.parse()?);
+        Ok(())
+    }
+}
+
+/// HMAC-SHA256 of `data` under `key`
+fn hmac(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Percent-encode everything but unreserved characters, and `/` unless `encode_slash`
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Send a request, signed first when a signer is given
+///
+/// # Arguments
+///
+/// * `client` - HTTP client, carrying the target timeouts
+/// * `signer` - Signs the request for an AWS service, if set
+/// * `request` - The request to send
+pub async fn send(client: &reqwest::Client, signer: Option<&SigV4Signer>, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+    let mut request = request.build()?;
+    debug!("Sending {} {}", request.method(), request.url());
+    if let Some(signer) = signer {
+        signer.sign(&mut request, Utc::now())?;
+    }
+    Ok(client.execute(request).await?)
+}
@@ -0,0 +1,398 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use log::{debug, info, warn};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Mutex;
+use tokio_util::io::StreamReader;
+use super::{PartReader, SnapshotStore};
+use crate::ui::models::{B2Config, BackupMetadata};
+
+/// Files at least this large are uploaded with the B2 large-file API
+pub const LARGE_FILE_THRESHOLD: u64 = 200 * 1024 * 1024;
+
+/// Number of file names requested per `b2_list_file_names` call
+const LIST_PAGE_SIZE: u32 = 1000;
+
+/// Session details returned by `b2_authorize_account`
+#[derive(Clone, Debug)]
+struct B2Auth {
+    token: String,
+    api_url: String,
+    download_url: String,
+    bucket_id: String,
+    part_size: u64,
+}
+
+/// Upload URL and token returned by `b2_get_upload_url` or `b2_get_upload_part_url`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadTarget {
+    upload_url: String,
+    authorization_token: String,
+}
+
+/// File entry returned by `b2_list_file_names`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct B2File {
+    file_name: String,
+    /// Changes with every upload of the same name, so it serves as the ETag
+    #[serde(default)]
+    file_id: Option<String>,
+    content_length: i64,
+    upload_timestamp: i64,
+    action: String,
+}
+
+/// Page of files returned by `b2_list_file_names`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileNames {
+    files: Vec<B2File>,
+    next_file_name: Option<String>,
+}
+
+/// Snapshot store using the native Backblaze B2 API
+///
+/// The S3-compatible endpoint trips over B2's token expiry and large-file
+/// handling, so this talks to B2 directly: expired auth tokens are renewed and
+/// the request retried once, downloads are ranged like every other backend,
+/// and big uploads go through the large-file API in `recommendedPartSize` parts.
+pub struct B2Store {
+    config: B2Config,
+    client: Client,
+    auth: Mutex<Option<B2Auth>>,
+}
+
+/// Percent-encode a file name for B2 headers and download URLs, keeping `/`
+pub fn encode_file_name(name: &str) -> String {
+    debug!("Encoding B2 file name: {}", name);
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Whether a B2 error response means the auth token must be renewed
+fn is_expired_auth(status: StatusCode, body: &str) -> bool {
+    status == StatusCode::UNAUTHORIZED && (body.contains("expired_auth_token") || body.contains("bad_auth_token"))
+}
+
+impl B2Store {
+    /// Create a store for the given settings; the account is authorized on first use
+    pub fn new(config: B2Config) -> Result<Self> {
+        debug!("Creating B2 snapshot store for bucket: {}", config.bucket);
+        config.verify_settings()?;
+        let client = config.timeouts.http_client()?;
+        Ok(Self { config, client, auth: Mutex::new(None) })
+    }
+
+    /// Exchange the application key for a fresh auth token
+    async fn authorize(&self) -> Result<B2Auth> {
+        debug!("Authorizing B2 account {}", self.config.key_id);
+        let response = self.client
+            .get(format!("{}/b2api/v2/b2_authorize_account", self.config.api_url()))
+            .basic_auth(&self.config.key_id, Some(&self.config.application_key))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to authorize B2 account: {}", e))?;
+        let body: Value = Self::check(response, "b2_authorize_account").await?.json().await?;
+
+        let token = body["authorizationToken"].as_str().unwrap_or_default().to_string();
+        let api_url = body["apiUrl"].as_str().unwrap_or_default().to_string();
+        let download_url = body["downloadUrl"].as_str().unwrap_or_default().to_string();
+        let part_size = body["recommendedPartSize"].as_u64().unwrap_or(100 * 1024 * 1024);
+
+        // Keys restricted to one bucket report it; otherwise look the bucket up by name
+        let bucket_id = match body["allowed"]["bucketId"].as_str() {
+            Some(id) => id.to_string(),
+            None => {
+                let response = self.client
+                    .post(format!("{}/b2api/v2/b2_list_buckets", api_url))
+                    .header("Authorization", &token)
+                    .json(&json!({ "accountId": body["accountId"], "bucketName": self.config.bucket }))
+                    .send()
+                    .await?;
+                let buckets: Value = Self::check(response, "b2_list_buckets").await?.json().await?;
+                buckets["buckets"][0]["bucketId"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("B2 bucket {} not found", self.config.bucket))?
+                    .to_string()
+            }
+        };
+        info!("Authorized B2 account for bucket {}", self.config.bucket);
+        Ok(B2Auth { token, api_url, download_url, bucket_id, part_size })
+    }
+
+    /// Current session, authorizing if there is none yet
+    async fn session(&self) -> Result<B2Auth> {
+        debug!("Getting B2 session");
+        let mut auth = self.auth.lock().await;
+        if auth.is_none() {
+            *auth = Some(self.authorize().await?);
+        }
+        Ok(auth.clone().expect("B2 session was just set"))
+    }
+
+    /// Drop the cached session so the next call authorizes again
+    async fn reset_session(&self) {
+        warn!("B2 auth token expired, re-authorizing");
+        *self.auth.lock().await = None;
+    }
+
+    /// Turn a B2 error response into an error carrying its code and message
+    async fn check(response: Response, op: &str) -> Result<Response> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        Err(anyhow!("B2 {} failed with {}: {}", op, status, body))
+    }
+
+    /// Send a request built from the current session, renewing the token once if it expired
+    ///
+    /// Responses other than 401 are returned unchecked so callers can handle
+    /// statuses such as 404 themselves.
+    async fn send<F>(&self, op: &str, build: F) -> Result<Response>
+    where
+        F: Fn(&B2Auth) -> RequestBuilder,
+    {
+        debug!("Sending B2 request: {}", op);
+        for attempt in 0..2 {
+            let auth = self.session().await?;
+            let response = build(&auth).send().await.map_err(|e| anyhow!("B2 {} failed: {}", op, e))?;
+            let status = response.status();
+            if status != StatusCode::UNAUTHORIZED {
+                return Ok(response);
+            }
+            let body = response.text().await.unwrap_or_default();
+            if attempt == 0 && is_expired_auth(status, &body) {
+                self.reset_session().await;
+                continue;
+            }
+            return Err(anyhow!("B2 {} failed with {}: {}", op, status, body));
+        }
+        Err(anyhow!("B2 {} failed: auth token rejected after renewal", op))
+    }
+
+    /// Call a JSON API operation such as `b2_list_file_names`
+    async fn call<T: DeserializeOwned>(&self, op: &str, body: Value) -> Result<T> {
+        debug!("Calling B2 API {}", op);
+        let response = self.send(op, |auth| {
+            self.client
+                .post(format!("{}/b2api/v2/{}", auth.api_url, op))
+                .header("Authorization", &auth.token)
+                .json(&body)
+        })
+        .await?;
+        Self::check(response, op).await?.json().await.with_context(|| format!("Invalid B2 {} response", op))
+    }
+
+    /// Upload a body to an upload URL with the given B2 headers
+    async fn post_upload(&self, target: &UploadTarget, headers: &[(&str, String)], body: Vec<u8>) -> Result<Value> {
+        debug!("Uploading {} bytes to B2", body.len());
+        let mut request = self.client
+            .post(&target.upload_url)
+            .header("Authorization", &target.authorization_token)
+            .header("Content-Length", body.len())
+            .header("X-Bz-Content-Sha1", hex::encode(Sha1::digest(&body)));
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let response = request.body(body).send().await.map_err(|e| anyhow!("B2 upload failed: {}", e))?;
+        Ok(Self::check(response, "upload").await?.json().await?)
+    }
+
+    /// Upload a whole object with `b2_upload_file`
+    async fn upload_small(&self, key: &str, body: Vec<u8>, label: Option<&str>) -> Result<()> {
+        debug!("Uploading {} to B2 in one request", key);
+        let auth = self.session().await?;
+        let target: UploadTarget = self.call("b2_get_upload_url", json!({ "bucketId": auth.bucket_id })).await?;
+        let mut headers = vec![
+            ("X-Bz-File-Name", encode_file_name(key)),
+            ("Content-Type", "b2/x-auto".to_string()),
+        ];
+        if let Some(label) = label {
+            headers.push(("X-Bz-Info-label", encode_file_name(label)));
+        }
+        self.post_upload(&target, &headers, body).await?;
+        Ok(())
+    }
+
+    /// Upload a file in parts with the B2 large-file API
+    async fn upload_large(&self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()> {
+        debug!("Uploading {:?} to B2 as a large file", local_path);
+        let auth = self.session().await?;
+        let mut file_info = json!({});
+        if let Some(label) = label {
+            file_info["label"] = json!(label);
+        }
+        let started: Value = self.call("b2_start_large_file", json!({
+            "bucketId": auth.bucket_id,
+            "fileName": key,
+            "contentType": "b2/x-auto",
+            "fileInfo": file_info,
+        }))
+        .await?;
+        let file_id = started["fileId"].as_str().ok_or_else(|| anyhow!("B2 did not return a large file ID"))?.to_string();
+
+        let result = self.upload_parts(local_path, &file_id, auth.part_size).await;
+        match result {
+            Ok(sha1s) => {
+                let _: Value = self.call("b2_finish_large_file", json!({ "fileId": file_id, "partSha1Array": sha1s })).await?;
+                Ok(())
+            }
+            Err(e) => {
+                // Unfinished large files are billed until cancelled
+                if let Err(cancel) = self.call::<Value>("b2_cancel_large_file", json!({ "fileId": file_id })).await {
+                    warn!("Failed to cancel B2 large file {}: {}", file_id, cancel);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Upload every part of a large file, returning the part checksums in order
+    async fn upload_parts(&self, local_path: &Path, file_id: &str, part_size: u64) -> Result<Vec<String>> {
+        debug!("Uploading parts of {:?} in {} byte parts", local_path, part_size);
+        let target: UploadTarget = self.call("b2_get_upload_part_url", json!({ "fileId": file_id })).await?;
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", local_path.display(), e))?;
+        let mut sha1s = Vec::new();
+        loop {
+            let mut part = Vec::with_capacity(part_size as usize);
+            (&mut file).take(part_size).read_to_end(&mut part).await?;
+            if part.is_empty() {
+                break;
+            }
+            let part_number = sha1s.len() + 1;
+            sha1s.push(hex::encode(Sha1::digest(&part)));
+            self.post_upload(&target, &[("X-Bz-Part-Number", part_number.to_string())], part).await?;
+        }
+        Ok(sha1s)
+    }
+
+    /// Send a download request for `key`, optionally limited to a byte range
+    ///
+    /// The response status is not checked.
+    async fn download(&self, key: &str, range: Option<(u64, u64)>) -> Result<Response> {
+        debug!("Downloading {} from B2, range {:?}", key, range);
+        let bucket = encode_file_name(&self.config.bucket);
+        let file = encode_file_name(key);
+        self.send("download", |auth| {
+            let request = self.client
+                .get(format!("{}/file/{}/{}", auth.download_url, bucket, file))
+                .header("Authorization", &auth.token);
+            match range {
+                Some((start, end)) => request.header("Range", format!("bytes={}-{}", start, end)),
+                None => request,
+            }
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for B2Store {
+    fn name(&self) -> &'static str {
+        "B2"
+    }
+
+    async fn list_snapshots(&self, prefix: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing B2 files with prefix: {}", prefix);
+        let auth = self.session().await?;
+        let mut snapshots = Vec::new();
+        let mut start: Option<String> = None;
+        loop {
+            let page: FileNames = self.call("b2_list_file_names", json!({
+                "bucketId": auth.bucket_id,
+                "prefix": prefix,
+                "startFileName": start,
+                "maxFileCount": LIST_PAGE_SIZE,
+            }))
+            .await?;
+            // Unfinished large files and hidden files are not snapshots
+            snapshots.extend(page.files.into_iter().filter(|f| f.action == "upload").map(|f| BackupMetadata {
+                key: f.file_name,
+                size: f.content_length,
+                last_modified: f.upload_timestamp as f64 / 1000.0,
+                etag: f.file_id,
+                storage_class: None,
+                version_id: None,
+            }));
+            match page.next_file_name {
+                Some(next) => start = Some(next),
+                None => break,
+            }
+        }
+        Ok(snapshots)
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader> {
+        debug!("Reading B2 file {} bytes {}-{}", key, start, end);
+        let response = Self::check(self.download(key, Some((start, end))).await?, "download").await?;
+        let stream = response.bytes_stream().map_err(std::io::Error::other);
+        Ok(Box::pin(StreamReader::new(stream)))
+    }
+
+    async fn upload_file(&self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()> {
+        debug!("Uploading {:?} to B2 key: {}", local_path, key);
+        let size = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", local_path.display(), e))?
+            .len();
+        if size >= LARGE_FILE_THRESHOLD {
+            return self.upload_large(local_path, key, label).await;
+        }
+        let body = tokio::fs::read(local_path).await?;
+        self.upload_small(key, body, label).await
+    }
+
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        debug!("Reading whole B2 file {}", key);
+        let response = self.download(key, None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(Self::check(response, "download").await?.bytes().await?.to_vec()))
+    }
+
+    async fn write_object(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        debug!("Writing {} bytes to B2 key: {}", body.len(), key);
+        self.upload_small(key, body, None).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        debug!("Deleting B2 file {}", key);
+        // Versions are deleted by ID, so look up the latest version of the name
+        let auth = self.session().await?;
+        let page: FileNames = self.call("b2_list_file_names", json!({
+            "bucketId": auth.bucket_id,
+            "startFileName": key,
+            "maxFileCount": 1,
+        }))
+        .await?;
+        let file = page.files
+            .into_iter()
+            .find(|f| f.file_name == key)
+            .ok_or_else(|| anyhow!("Failed to delete {}: no such file", key))?;
+        let file_id = file.file_id.ok_or_else(|| anyhow!("Failed to delete {}: B2 did not report its file ID", key))?;
+        let _: Value = self.call("b2_delete_file_version", json!({
+            "fileName": key,
+            "fileId": file_id,
+        }))
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use log::debug;
+use object_store::path::Path as ObjectPath;
+use object_store::{Attribute, Attributes, GetOptions, GetRange, ObjectStore, PutMultipartOpts, WriteMultipart};
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
+use super::{PartReader, SnapshotStore};
+use crate::ui::models::BackupMetadata;
+
+/// Size of the chunks streamed to the bucket when uploading a snapshot
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Snapshot store backed by an `object_store` client (GCS or Azure Blob)
+pub struct CloudStore {
+    pub name: &'static str,
+    pub client: Box<dyn ObjectStore>,
+}
+
+impl CloudStore {
+    /// Create a store named `name` for the given client
+    pub fn new(name: &'static str, client: impl ObjectStore) -> Self {
+        debug!("Creating {} snapshot store", name);
+        Self { name, client: Box::new(client) }
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for CloudStore {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn list_snapshots(&self, prefix: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing {} objects with prefix: {}", self.name, prefix);
+        // object_store lists whole path segments, so list the prefix's directory
+        // and filter on the raw prefix below
+        let dir = prefix.rsplit_once('/').map(|(dir, _)| dir).filter(|dir| !dir.is_empty());
+        let objects: Vec<_> = self.client
+            .list(dir.map(ObjectPath::from).as_ref())
+            .try_collect()
+            .await
+            .map_err(|e| anyhow!("Failed to list objects: {}", e))?;
+
+        let snapshots = objects
+            .into_iter()
+            .filter(|obj| obj.location.as_ref().starts_with(prefix))
+            .map(|obj| BackupMetadata {
+                key: obj.location.to_string(),
+                size: obj.size as i64,
+                last_modified: obj.last_modified.timestamp() as f64,
+                etag: obj.e_tag,
+                storage_class: None,
+                version_id: None,
+            })
+            .collect();
+        Ok(snapshots)
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader> {
+        debug!("Reading {} object {} bytes {}-{}", self.name, key, start, end);
+        let options = GetOptions {
+            range: Some(GetRange::Bounded(start as usize..end as usize + 1)),
+            ..Default::default()
+        };
+        let result = self.client
+            .get_opts(&ObjectPath::from(key), options)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", key, e))?;
+        let stream = result.into_stream().map_err(std::io::Error::other);
+        Ok(Box::pin(StreamReader::new(stream)))
+    }
+
+    async fn upload_file(&self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()> {
+        debug!("Uploading {:?} to {} key: {}", local_path, self.name, key);
+        let mut attributes = Attributes::new();
+        if let Some(label) = label {
+            attributes.insert(Attribute::Metadata("label".into()), label.to_string().into());
+        }
+        let options = PutMultipartOpts { attributes, ..Default::default() };
+        let upload = self.client
+            .put_multipart_opts(&ObjectPath::from(key), options)
+            .await
+            .map_err(|e| anyhow!("Failed to start upload of {}: {}", key, e))?;
+
+        // Stream the file in chunks rather than loading whole dumps into memory
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, UPLOAD_CHUNK_SIZE);
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", local_path.display(), e))?;
+        let mut buffer = vec![0; UPLOAD_CHUNK_SIZE];
+        loop {
+            let n = file.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            writer.wait_for_capacity(4).await.map_err(|e| anyhow!("Failed to upload {}: {}", key, e))?;
+            writer.write(&buffer[..n]);
+        }
+        writer.finish().await.map_err(|e| anyhow!("Failed to upload {}: {}", key, e))?;
+        Ok(())
+    }
+
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        debug!("Reading whole {} object {}", self.name, key);
+        let result = match self.client.get(&ObjectPath::from(key)).await {
+            Ok(result) => result,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", key, e)),
+        };
+        let bytes = result.bytes().await.map_err(|e| anyhow!("Failed to read {}: {}", key, e))?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    async fn write_object(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        debug!("Writing {} bytes to {} key: {}", body.len(), self.name, key);
+        self.client
+            .put(&ObjectPath::from(key), body.into())
+            .await
+            .map_err(|e| anyhow!("Failed to write {}: {}", key, e))?;
+        Ok(())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        debug!("Deleting {} object {}", self.name, key);
+        self.client
+            .delete(&ObjectPath::from(key))
+            .await
+            .map_err(|e| anyhow!("Failed to delete {}: {}", key, e))?;
+        Ok(())
+    }
+}
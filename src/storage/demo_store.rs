@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::stream;
+use log::debug;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tokio_util::io::StreamReader;
+use super::{PartReader, SnapshotStore};
+use crate::archive::{ArchiveStatus, ArchiveTier};
+use crate::ui::models::BackupMetadata;
+
+/// Bytes a demo read produces per second, so downloads take a few seconds to watch
+pub const DEMO_READ_RATE: u64 = 6 * 1024 * 1024;
+
+/// Size of each chunk a demo read yields before pausing
+const DEMO_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// How long a requested archive restore of a demo snapshot takes
+const DEMO_ARCHIVE_RESTORE_SECONDS: i64 = 30;
+
+/// Snapshot store holding synthetic snapshots in memory, for `--demo`
+///
+/// Snapshots have generated contents that are streamed at `DEMO_READ_RATE`.
+/// Uploads and catalog objects are kept in memory for the life of the process.
+pub struct DemoStore {
+    snapshots: Mutex<Vec<BackupMetadata>>,
+    objects: Mutex<BTreeMap<String, Vec<u8>>>,
+    /// When an archive restore was requested, by key
+    archive_requests: Mutex<BTreeMap<String, DateTime<Utc>>>,
+}
+
+impl DemoStore {
+    /// A store of synthetic snapshots under `prefix`, taken over the two weeks before `now`
+    pub fn new(prefix: &str, now: DateTime<Utc>) -> Self {
+        debug!("Creating demo store under {}", prefix);
+        Self {
+            snapshots: Mutex::new(demo_snapshots(prefix, now)),
+            objects: Mutex::new(BTreeMap::new()),
+            archive_requests: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn snapshot(&self, key: &str) -> Option<BackupMetadata> {
+        self.snapshots.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|snapshot| snapshot.key == key).cloned()
+    }
+}
+
+/// Synthetic snapshots: daily PostgreSQL dumps of a few databases, a pre-change
+/// backup, weekly cluster globals, Elasticsearch and Qdrant exports, and older
+/// dumps moved to archive storage classes
+pub fn demo_snapshots(prefix: &str, now: DateTime<Utc>) -> Vec<BackupMetadata> {
+    debug!("Generating demo snapshots under {}", prefix);
+    const MB: i64 = 1024 * 1024;
+    let midnight = now.date_naive().and_hms_opt(3, 0, 0).unwrap_or_default().and_utc();
+    let mut snapshots = Vec::new();
+    let mut add = |key: String, size: i64, taken: DateTime<Utc>, storage_class: &str| {
+        let etag = Some(format!("demo-{}", snapshots.len()));
+        snapshots.push(BackupMetadata {
+            key,
+            size,
+            last_modified: taken.timestamp() as f64,
+            etag,
+            storage_class: Some(storage_class.to_string()),
+            version_id: None,
+        });
+    };
+    for day in 0..14i64 {
+        let taken = midnight - Duration::days(day);
+        let storage_class = match day {
+            0..=9 => "STANDARD",
+            10..=11 => "GLACIER",
+            _ => "DEEP_ARCHIVE",
+        };
+        add(crate::backup::snapshot_key(prefix, "orders", taken), 40 * MB - day * MB, taken, storage_class);
+        add(crate::backup::snapshot_key(prefix, "billing", taken + Duration::minutes(5)), 18 * MB + (day % 3) * MB, taken + Duration::minutes(5), storage_class);
+        if day % 2 == 0 {
+            add(crate::backup::snapshot_key(prefix, "users", taken + Duration::minutes(10)), 6 * MB, taken + Duration::minutes(10), storage_class);
+        }
+        if day % 7 == 0 {
+            add(crate::globals::globals_key(prefix, taken - Duration::minutes(1)), 24 * 1024, taken - Duration::minutes(1), storage_class);
+        }
+    }
+    let recent = now - Duration::hours(2);
+    add(crate::backup::labeled_snapshot_key(prefix, "orders", crate::backup::PRE_CHANGE_LABEL, recent), 41 * MB, recent, "STANDARD");
+    let export = midnight + Duration::hours(1);
+    add(crate::backup::join_prefix(prefix, format!("products-{}.ndjson", export.format("%Y%m%dT%H%M%SZ"))), 12 * MB, export, "STANDARD");
+    add(crate::backup::join_prefix(prefix, format!("embeddings-{}.jsonl", export.format("%Y%m%dT%H%M%SZ"))), 16 * MB, export, "STANDARD");
+    snapshots.sort_by(|a, b| b.last_modified.total_cmp(&a.last_modified));
+    snapshots
+}
+
+/// Deterministic contents of a demo snapshot, so resumed downloads verify
+fn demo_bytes(start: u64, len: u64) -> Vec<u8> {
+    (start..start + len).map(|offset| (offset % 251) as u8).collect()
+}
+
+#[async_trait]
+impl SnapshotStore for DemoStore {
+    fn name(&self) -> &'static str {
+        "Demo"
+    }
+
+    async fn list_snapshots(&self, prefix: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing demo snapshots with prefix: {}", prefix);
+        let snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(snapshots.iter().filter(|snapshot| snapshot.key.starts_with(prefix)).cloned().collect())
+    }
+
+    async fn archive_status(&self, key: &str) -> Result<ArchiveStatus> {
+        debug!("Getting archive status of demo snapshot {}", key);
+        let archived = self.snapshot(key)
+            .and_then(|snapshot| snapshot.storage_class)
+            .is_some_and(|class| class == "GLACIER" || class == "DEEP_ARCHIVE");
+        if !archived {
+            return Ok(ArchiveStatus::NotArchived);
+        }
+        let requested = self.archive_requests.lock().unwrap_or_else(|e| e.into_inner()).get(key).copied();
+        Ok(match requested {
+            None => ArchiveStatus::Archived,
+            Some(at) if Utc::now() - at < Duration::seconds(DEMO_ARCHIVE_RESTORE_SECONDS) => ArchiveStatus::Restoring,
+            Some(at) => ArchiveStatus::Restored { expiry: Some((at + Duration::days(3)).to_rfc2822()) },
+        })
+    }
+
+    async fn request_archive_restore(&self, key: &str, _tier: ArchiveTier, _days: i32) -> Result<()> {
+        debug!("Requesting archive restore of demo snapshot {}", key);
+        self.archive_requests.lock().unwrap_or_else(|e| e.into_inner()).entry(key.to_string()).or_insert_with(Utc::now);
+        Ok(())
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader> {
+        debug!("Reading demo snapshot {} bytes {}-{}", key, start, end);
+        if let Some(body) = self.objects.lock().unwrap_or_else(|e| e.into_inner()).get(key) {
+            let end = (end as usize + 1).min(body.len());
+            let part = body.get(start as usize..end).unwrap_or_default().to_vec();
+            return Ok(Box::pin(std::io::Cursor::new(part)));
+        }
+        let snapshot = self.snapshot(key).ok_or_else(|| anyhow!("Demo snapshot {} not found", key))?;
+        let end = end.min(snapshot.size.max(1) as u64 - 1);
+        // Yield the part a chunk at a time, pausing so it arrives at the demo rate
+        let chunks = stream::unfold(start, move |offset| async move {
+            if offset > end {
+                return None;
+            }
+            let len = DEMO_CHUNK_SIZE.min(end + 1 - offset);
+            tokio::time::sleep(std::time::Duration::from_secs_f64(len as f64 / DEMO_READ_RATE as f64)).await;
+            Some((Ok::<_, std::io::Error>(std::io::Cursor::new(demo_bytes(offset, len))), offset + len))
+        });
+        Ok(Box::pin(StreamReader::new(chunks)))
+    }
+
+    async fn upload_file(&self, local_path: &Path, key: &str, _label: Option<&str>) -> Result<()> {
+        debug!("Uploading {:?} to demo store key: {}", local_path, key);
+        let body = tokio::fs::read(local_path).await?;
+        let mut snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        snapshots.retain(|snapshot| snapshot.key != key);
+        let etag = Some(format!("demo-upload-{}", snapshots.len()));
+        snapshots.insert(0, BackupMetadata {
+            key: key.to_string(),
+            size: body.len() as i64,
+            last_modified: Utc::now().timestamp() as f64,
+            etag,
+            storage_class: Some("STANDARD".to_string()),
+            version_id: None,
+        });
+        self.objects.lock().unwrap_or_else(|e| e.into_inner()).insert(key.to_string(), body);
+        Ok(())
+    }
+
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        debug!("Reading demo object {}", key);
+        Ok(self.objects.lock().unwrap_or_else(|e| e.into_inner()).get(key).cloned())
+    }
+
+    async fn write_object(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        debug!("Writing demo object {}", key);
+        self.objects.lock().unwrap_or_else(|e| e.into_inner()).insert(key.to_string(), body);
+        Ok(())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        debug!("Deleting demo object {}", key);
+        self.objects.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+        self.snapshots.lock().unwrap_or_else(|e| e.into_inner()).retain(|snapshot| snapshot.key != key);
+        Ok(())
+    }
+}
+
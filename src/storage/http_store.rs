@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use log::{debug, info};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, ETAG, LAST_MODIFIED, RANGE};
+use reqwest::{Client, Response, StatusCode, Url};
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
+use super::{PartReader, SnapshotStore};
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::{BackupMetadata, PopupState, TimeoutConfig};
+
+/// Snapshot store for a single file behind an HTTP(S) URL
+///
+/// Used by `restore --url` for a presigned S3 link or an artifact store
+/// download, when there is a link but no bucket credentials. The file is read
+/// with `Range` requests, so it downloads in the same verified, resumable
+/// parts as a snapshot in a bucket. The URL's query string often holds a
+/// signature, so only its scheme, host and path are ever logged.
+pub struct HttpStore {
+    url: Url,
+    client: Client,
+}
+
+impl HttpStore {
+    /// Create a store for the file at `url`
+    pub fn new(url: &str, timeouts: &TimeoutConfig) -> Result<Self> {
+        let url = Url::parse(url).map_err(|e| anyhow!("Invalid URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("Only http and https URLs can be restored from, not {}", url.scheme()));
+        }
+        let store = Self { url, client: timeouts.http_client()? };
+        debug!("Creating HTTP snapshot store for {}", store.display_url());
+        Ok(store)
+    }
+
+    /// The URL without its query string or credentials, for messages and logs
+    pub fn display_url(&self) -> String {
+        format!("{}://{}{}", self.url.scheme(), self.url.host_str().unwrap_or_default(), self.url.path())
+    }
+
+    /// Key the file is downloaded and logged under: the last segment of the URL's path
+    pub fn key(&self) -> String {
+        self.url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("download")
+            .to_string()
+    }
+
+    /// Request a byte range of the file, failing on anything but the range or the whole file
+    async fn get_range(&self, start: u64, end: u64) -> Result<Response> {
+        debug!("Requesting bytes {}-{} of {}", start, end, self.display_url());
+        let response = self
+            .client
+            .get(self.url.clone())
+            .header(RANGE, format!("bytes={}-{}", start, end))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to download {}: {}", self.display_url(), e.without_url()))?;
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT | StatusCode::OK => Ok(response),
+            status => Err(anyhow!("Downloading {} failed with {}", self.display_url(), status)),
+        }
+    }
+
+    /// Look up the size, ETag and modification time of the file
+    ///
+    /// Presigned links are only signed for `GET`, so the file is probed with a
+    /// one-byte range request rather than `HEAD`.
+    pub async fn probe(&self) -> Result<BackupMetadata> {
+        debug!("Probing {}", self.display_url());
+        let response = self.get_range(0, 0).await?;
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+        let size = match response.status() {
+            // `bytes 0-0/12345`
+            StatusCode::PARTIAL_CONTENT => header(CONTENT_RANGE).and_then(|range| range.rsplit_once('/')).and_then(|(_, size)| size.parse().ok()),
+            // Servers without range support send the whole file
+            _ => header(CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+        }
+        .ok_or_else(|| anyhow!("{} did not report the size of the file", self.display_url()))?;
+        let last_modified = header(LAST_MODIFIED)
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+            .map_or(0.0, |date| date.timestamp() as f64);
+        let snapshot = BackupMetadata {
+            key: self.key(),
+            size,
+            last_modified,
+            etag: header(ETAG).map(str::to_string),
+            storage_class: None,
+            version_id: None,
+        };
+        info!("{} is {} bytes", self.display_url(), snapshot.size);
+        Ok(snapshot)
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for HttpStore {
+    fn name(&self) -> &'static str {
+        "HTTP"
+    }
+
+    async fn list_snapshots(&self, _prefix: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing the file at {}", self.display_url());
+        Ok(vec![self.probe().await?])
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader> {
+        debug!("Reading {} bytes {}-{}", key, start, end);
+        let response = self.get_range(start, end).await?;
+        if response.status() == StatusCode::OK && start > 0 {
+            return Err(anyhow!(
+                "The server of {} ignores range requests, so it cannot be downloaded in parts; download it with curl and restore the file instead",
+                self.display_url()
+            ));
+        }
+        let stream = response.bytes_stream().map_err(std::io::Error::other);
+        // A whole file sent in place of the first part is cut to the part
+        Ok(Box::pin(StreamReader::new(stream).take(end - start + 1)))
+    }
+
+    async fn upload_file(&self, _local_path: &Path, key: &str, _label: Option<&str>) -> Result<()> {
+        Err(anyhow!("Cannot upload {}: a URL source is read-only", key))
+    }
+
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        // A link has no catalogs or manifests next to it
+        debug!("No object {} next to {}", key, self.display_url());
+        Ok(None)
+    }
+
+    async fn write_object(&self, key: &str, _body: Vec<u8>) -> Result<()> {
+        Err(anyhow!("Cannot write {}: a URL source is read-only", key))
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        Err(anyhow!("Cannot delete {}: a URL source is read-only", key))
+    }
+}
+
+/// Download the file at a URL the way snapshots are downloaded
+///
+/// The file is fetched in ranged parts with the browser's concurrency,
+/// progress board, cancellation and client-side decryption, and resumes from
+/// its verified parts if an earlier download of it was interrupted.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser to download with; its store is replaced
+/// * `url` - HTTP(S) URL of the file
+/// * `sha256` - Expected SHA-256 of the file as served, checked before it is decrypted
+///
+/// # Returns
+///
+/// Where the file was downloaded to
+pub async fn download_url(browser: &mut SnapshotBrowser, url: &str, sha256: Option<&str>) -> Result<String> {
+    let store = HttpStore::new(url, &browser.s3_config.timeouts)?;
+    debug!("Downloading {}", store.display_url());
+    let display_url = store.display_url();
+    let snapshot = store.probe().await?;
+    browser.set_store(std::sync::Arc::new(store));
+    let tmp_path = crate::download_ledger::temp_download_path(&snapshot.key);
+    if browser.fetch_snapshot(&snapshot, &tmp_path).await?.is_none() {
+        return Err(match &browser.popup_state {
+            PopupState::Error(message) => anyhow!("{}", message),
+            _ => anyhow!("Download of {} failed", display_url),
+        });
+    }
+    if let Some(expected) = sha256 {
+        let actual = crate::verification::file_checksum(&tmp_path)?;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(anyhow!("{} has SHA-256 {}, expected {}", display_url, actual, expected.trim()));
+        }
+        info!("{} matches its SHA-256", display_url);
+    }
+    browser.encryption.decrypt_in_place(&tmp_path).await.with_context(|| format!("Decrypting {} failed", display_url))?;
+    Ok(tmp_path.to_string_lossy().to_string())
+}
@@ -0,0 +1,149 @@
+mod s3_store;
+mod cloud_store;
+mod sftp_store;
+mod b2_store;
+mod demo_store;
+mod http_store;
+
+pub use s3_store::{explain_s3_error, S3Store, SseCustomerKey};
+pub use cloud_store::CloudStore;
+pub use sftp_store::SftpStore;
+pub use b2_store::{encode_file_name, B2Store, LARGE_FILE_THRESHOLD};
+pub use demo_store::{demo_snapshots, DemoStore, DEMO_READ_RATE};
+pub use http_store::{download_url, HttpStore};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::fmt;
+use std::path::Path;
+use std::pin::Pin;
+use tokio::io::AsyncRead;
+use crate::archive::{ArchiveStatus, ArchiveTier};
+use crate::ui::models::BackupMetadata;
+
+/// Reader over the bytes of one ranged part of a snapshot
+pub type PartReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Server-side encryption of a stored snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotEncryption {
+    /// Stored unencrypted
+    None,
+    /// Encrypted with keys managed by the storage service (SSE-S3)
+    ServiceManaged,
+    /// Encrypted with a KMS key; reading it needs decrypt permission on that key
+    Kms { key_id: Option<String> },
+    /// Encrypted with a customer-provided key (SSE-C) that must be sent with every read
+    CustomerKey,
+}
+
+impl fmt::Display for SnapshotEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotEncryption::None => write!(f, "none"),
+            SnapshotEncryption::ServiceManaged => write!(f, "SSE-S3"),
+            SnapshotEncryption::Kms { key_id: Some(key_id) } => write!(f, "SSE-KMS ({})", key_id),
+            SnapshotEncryption::Kms { key_id: None } => write!(f, "SSE-KMS (default key)"),
+            SnapshotEncryption::CustomerKey => write!(f, "SSE-C (customer-provided key)"),
+        }
+    }
+}
+
+/// Trait for snapshot storage backends
+///
+/// This trait defines the interface the snapshot browser uses to list, download
+/// and upload snapshots. Each storage backend (S3, GCS, Azure, B2, SFTP) should implement this trait.
+#[async_trait]
+pub trait SnapshotStore: Send + Sync {
+    /// Get the name of this storage backend
+    fn name(&self) -> &'static str;
+
+    /// List the snapshots stored under `prefix`
+    async fn list_snapshots(&self, prefix: &str) -> Result<Vec<BackupMetadata>>;
+
+    /// List the snapshots stored under `prefix` a page at a time
+    ///
+    /// `on_page` is called with each page as it arrives, so callers can show
+    /// results while a large listing is still running. Listing stops once
+    /// `max_keys` objects have been returned. Backends that list everything in
+    /// one request return it as a single page.
+    async fn list_snapshot_pages(
+        &self,
+        prefix: &str,
+        max_keys: Option<usize>,
+        on_page: &mut (dyn FnMut(Vec<BackupMetadata>) + Send),
+    ) -> Result<()> {
+        let mut snapshots = self.list_snapshots(prefix).await?;
+        if let Some(max_keys) = max_keys {
+            snapshots.truncate(max_keys);
+        }
+        on_page(snapshots);
+        Ok(())
+    }
+
+    /// List the snapshots under `prefix` whose keys sort after `start_after`
+    ///
+    /// Used to pick up new snapshots without re-listing the whole source.
+    /// Backends that cannot start a listing part way filter a full listing.
+    async fn list_snapshots_after(&self, prefix: &str, start_after: &str) -> Result<Vec<BackupMetadata>> {
+        let mut snapshots = self.list_snapshots(prefix).await?;
+        snapshots.retain(|snapshot| snapshot.key.as_str() > start_after);
+        Ok(snapshots)
+    }
+
+    /// Look up how a snapshot is encrypted at rest
+    ///
+    /// Backends that do not report encryption return `None`.
+    async fn encryption(&self, _key: &str) -> Result<Option<SnapshotEncryption>> {
+        Ok(None)
+    }
+
+    /// Look up whether a snapshot is archived and how far a restore of it has got
+    ///
+    /// Backends without archive storage classes return `NotArchived`.
+    async fn archive_status(&self, _key: &str) -> Result<ArchiveStatus> {
+        Ok(ArchiveStatus::NotArchived)
+    }
+
+    /// Ask for a temporary readable copy of an archived snapshot, kept for `days`
+    ///
+    /// Requesting a restore that is already running succeeds.
+    async fn request_archive_restore(&self, key: &str, _tier: ArchiveTier, _days: i32) -> Result<()> {
+        Err(anyhow!("{} is not archived: {} has no archive storage classes", key, self.name()))
+    }
+
+    /// List every stored version of `key`, newest first
+    ///
+    /// Each version carries its `version_id`. Backends without object
+    /// versioning return an error.
+    async fn list_versions(&self, key: &str) -> Result<Vec<BackupMetadata>> {
+        Err(anyhow!("{} does not keep versions of {}", self.name(), key))
+    }
+
+    /// Make a link that downloads `snapshot` without credentials until `expires_in` has passed
+    ///
+    /// Backends without presigned URLs return an error.
+    async fn presigned_url(&self, snapshot: &BackupMetadata, _expires_in: std::time::Duration) -> Result<String> {
+        Err(anyhow!("{} cannot make download links for {}", self.name(), snapshot.key))
+    }
+
+    /// Open a reader over the inclusive byte range `start..=end` of `key`
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader>;
+
+    /// Open a reader over the inclusive byte range `start..=end` of one version of `key`
+    async fn read_version_range(&self, key: &str, version_id: &str, _start: u64, _end: u64) -> Result<PartReader> {
+        Err(anyhow!("{} does not keep versions, cannot read version {} of {}", self.name(), version_id, key))
+    }
+
+    /// Upload a local file under `key`, storing `label` as object metadata
+    async fn upload_file(&self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()>;
+
+    /// Read a small object whole, returning `None` if it does not exist
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Write a small object whole, replacing any existing object
+    async fn write_object(&self, key: &str, body: Vec<u8>) -> Result<()>;
+
+    /// Delete the current object stored under `key`
+    async fn delete_object(&self, key: &str) -> Result<()>;
+}
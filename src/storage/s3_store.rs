@@ -0,0 +1,432 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{GlacierJobParameters, RestoreRequest, ServerSideEncryption, Tier};
+use aws_sdk_s3::Client as S3Client;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use log::debug;
+use md5::{Digest, Md5};
+use std::path::Path;
+use crate::archive::{ArchiveStatus, ArchiveTier};
+use super::{PartReader, SnapshotEncryption, SnapshotStore};
+use crate::ui::models::BackupMetadata;
+
+/// Largest page ListObjectsV2 returns
+const LIST_PAGE_SIZE: usize = 1000;
+
+/// Algorithm S3 supports for customer-provided keys
+const SSE_CUSTOMER_ALGORITHM: &str = "AES256";
+
+/// Customer-provided key (SSE-C) sent with reads and uploads of snapshots
+#[derive(Clone, PartialEq)]
+pub struct SseCustomerKey {
+    /// The 256-bit key, base64 encoded
+    pub key: String,
+    /// Base64 encoded MD5 of the raw key, which S3 uses to check the key arrived intact
+    pub key_md5: String,
+}
+
+impl std::fmt::Debug for SseCustomerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never log the key itself
+        f.debug_struct("SseCustomerKey").field("key_md5", &self.key_md5).finish()
+    }
+}
+
+impl SseCustomerKey {
+    /// Parse a base64 encoded 256-bit key
+    pub fn from_base64(key: &str) -> Result<Self> {
+        debug!("Parsing SSE-C customer key");
+        let raw = BASE64
+            .decode(key.trim())
+            .map_err(|e| anyhow!("SSE-C customer key is not valid base64: {}", e))?;
+        if raw.len() != 32 {
+            return Err(anyhow!("SSE-C customer key must be 32 bytes, got {}", raw.len()));
+        }
+        Ok(Self { key: key.trim().to_string(), key_md5: BASE64.encode(Md5::digest(&raw)) })
+    }
+}
+
+/// Snapshot store backed by an S3 bucket
+pub struct S3Store {
+    pub client: S3Client,
+    pub bucket: String,
+    /// Customer-provided key for SSE-C encrypted snapshots
+    pub sse_customer_key: Option<SseCustomerKey>,
+}
+
+/// Turn an S3 error about encryption into advice on fixing it
+///
+/// S3 reports missing KMS permissions and SSE-C key problems with generic
+/// `AccessDenied` and `InvalidRequest` errors, which say little about the cause
+/// on their own.
+///
+/// # Arguments
+///
+/// * `key` - Key of the object being read
+/// * `code` - S3 error code, e.g. `AccessDenied`
+/// * `message` - S3 error message
+///
+/// # Returns
+///
+/// An explanation, or `None` if the error is not about encryption
+pub fn explain_s3_error(key: &str, code: Option<&str>, message: Option<&str>) -> Option<String> {
+    debug!("Explaining S3 error for {}: {:?} {:?}", key, code, message);
+    let message = message.unwrap_or_default();
+    let lower = message.to_lowercase();
+    match code {
+        Some("AccessDenied") | Some("KMS.AccessDeniedException") if lower.contains("kms") => Some(format!(
+            "{} is encrypted with a KMS key these credentials cannot use. Grant kms:Decrypt on the key to them. ({})",
+            key, message
+        )),
+        Some("AccessDenied") if lower.contains("md5") || lower.contains("customer") => Some(format!(
+            "{} is encrypted with a different SSE-C customer key than the one configured. ({})",
+            key, message
+        )),
+        Some("InvalidRequest") | Some("InvalidArgument") if lower.contains("server side encryption") || lower.contains("encryption parameters") => Some(format!(
+            "{} is encrypted with a customer-provided key (SSE-C). Set --sse-customer-key to read it. ({})",
+            key, message
+        )),
+        Some("InvalidObjectState") => Some(format!(
+            "{} is in an archive storage class and must be restored before it can be downloaded. Select it in the TUI to request a restore. ({})",
+            key, message
+        )),
+        Some("KMS.DisabledException") | Some("KMS.KMSInvalidStateException") | Some("KMS.NotFoundException") => Some(format!(
+            "The KMS key {} is encrypted with is disabled, pending deletion or missing. ({})",
+            key, message
+        )),
+        _ => None,
+    }
+}
+
+/// Describe a failed S3 request, explaining encryption problems
+fn describe_error<E: ProvideErrorMetadata + std::error::Error + 'static, R: std::fmt::Debug>(
+    action: &str,
+    key: &str,
+    error: &SdkError<E, R>,
+) -> anyhow::Error {
+    debug!("Describing failed S3 {} of {}", action, key);
+    match explain_s3_error(key, error.code(), error.message()) {
+        Some(explanation) => anyhow!("Failed to {} {}: {}", action, key, explanation),
+        None => anyhow!("Failed to {} {}: {}", action, key, aws_sdk_s3::error::DisplayErrorContext(error)),
+    }
+}
+
+impl S3Store {
+    /// List objects under `prefix` a page at a time, starting after `start_after` when set
+    async fn list_pages_from(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        max_keys: Option<usize>,
+        on_page: &mut (dyn FnMut(Vec<BackupMetadata>) + Send),
+    ) -> Result<()> {
+        debug!("Listing S3 objects in pages, bucket: {}, prefix: {}, after: {:?}, max keys: {:?}", self.bucket, prefix, start_after, max_keys);
+        let mut listed = 0;
+        let mut continuation_token: Option<String> = None;
+        loop {
+            // Never ask for more keys than the cap leaves room for
+            let page_size = max_keys.map_or(LIST_PAGE_SIZE, |max| (max - listed).min(LIST_PAGE_SIZE));
+            let output = self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .max_keys(page_size as i32)
+                .set_start_after(start_after.map(str::to_string))
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(|e| anyhow!("Failed to list objects: {}", e))?;
+
+            let page: Vec<BackupMetadata> = output.contents
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|obj| {
+                    let key = obj.key?;
+                    Some(BackupMetadata {
+                        key,
+                        size: obj.size.unwrap_or(0),
+                        last_modified: obj.last_modified
+                            .map(|dt| dt.as_secs_f64())
+                            .unwrap_or_else(|| Utc::now().timestamp() as f64),
+                        etag: obj.e_tag,
+                        storage_class: obj.storage_class.map(|class| class.as_str().to_string()),
+                        version_id: None,
+                    })
+                })
+                .take(page_size)
+                .collect();
+            listed += page.len();
+            debug!("Listed page of {} objects ({} total)", page.len(), listed);
+            on_page(page);
+
+            if max_keys.is_some_and(|max| listed >= max) {
+                debug!("Reached the limit of {} keys", listed);
+                break;
+            }
+            match output.next_continuation_token {
+                Some(token) if output.is_truncated.unwrap_or(false) => continuation_token = Some(token),
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a byte range of `key`, or of one version of it, sending the SSE-C key when set
+    async fn read_object_range(&self, key: &str, version_id: Option<&str>, start: u64, end: u64) -> Result<PartReader> {
+        debug!("Reading S3 object {} (version {:?}) bytes {}-{}", key, version_id, start, end);
+        let request = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_version_id(version_id.map(str::to_string))
+            .range(format!("bytes={}-{}", start, end));
+        let Some(customer_key) = &self.sse_customer_key else {
+            let output = request.send().await.map_err(|e| describe_error("read", key, &e))?;
+            return Ok(Box::pin(output.body.into_async_read()));
+        };
+
+        let output = match request
+            .clone()
+            .sse_customer_algorithm(SSE_CUSTOMER_ALGORITHM)
+            .sse_customer_key(&customer_key.key)
+            .sse_customer_key_md5(&customer_key.key_md5)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            // S3 rejects customer keys for objects that were not stored with one,
+            // so buckets mixing SSE-C and other snapshots are read without it
+            Err(e) if e.code() == Some("InvalidRequest") || e.code() == Some("InvalidArgument") => {
+                debug!("{} is not SSE-C encrypted, reading it without the customer key", key);
+                request.send().await.map_err(|e| describe_error("read", key, &e))?
+            }
+            Err(e) => return Err(describe_error("read", key, &e)),
+        };
+        Ok(Box::pin(output.body.into_async_read()))
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for S3Store {
+    fn name(&self) -> &'static str {
+        "S3"
+    }
+
+    async fn list_snapshots(&self, prefix: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing S3 objects in bucket: {}, prefix: {}", self.bucket, prefix);
+        let mut snapshots = Vec::new();
+        self.list_snapshot_pages(prefix, None, &mut |page| snapshots.extend(page)).await?;
+        Ok(snapshots)
+    }
+
+    async fn list_snapshot_pages(
+        &self,
+        prefix: &str,
+        max_keys: Option<usize>,
+        on_page: &mut (dyn FnMut(Vec<BackupMetadata>) + Send),
+    ) -> Result<()> {
+        self.list_pages_from(prefix, None, max_keys, on_page).await
+    }
+
+    async fn list_snapshots_after(&self, prefix: &str, start_after: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing S3 objects in bucket: {}, prefix: {}, after: {}", self.bucket, prefix, start_after);
+        let mut snapshots = Vec::new();
+        self.list_pages_from(prefix, Some(start_after), None, &mut |page| snapshots.extend(page)).await?;
+        Ok(snapshots)
+    }
+
+    async fn encryption(&self, key: &str) -> Result<Option<SnapshotEncryption>> {
+        debug!("Looking up encryption of S3 object {}", key);
+        let mut request = self.client.head_object().bucket(&self.bucket).key(key);
+        if let Some(customer_key) = &self.sse_customer_key {
+            request = request
+                .sse_customer_algorithm(SSE_CUSTOMER_ALGORITHM)
+                .sse_customer_key(&customer_key.key)
+                .sse_customer_key_md5(&customer_key.key_md5);
+        }
+        let output = match request.send().await {
+            Ok(output) => output,
+            // HEAD responses have no body, so an SSE-C object read without its key is only a bare 400
+            Err(e) if self.sse_customer_key.is_none() && e.raw_response().is_some_and(|r| r.status().as_u16() == 400) => {
+                return Ok(Some(SnapshotEncryption::CustomerKey));
+            }
+            Err(e) => return Err(describe_error("inspect", key, &e)),
+        };
+        if output.sse_customer_algorithm().is_some() {
+            return Ok(Some(SnapshotEncryption::CustomerKey));
+        }
+        Ok(Some(match output.server_side_encryption() {
+            Some(ServerSideEncryption::AwsKms) | Some(ServerSideEncryption::AwsKmsDsse) => SnapshotEncryption::Kms {
+                key_id: output.ssekms_key_id().map(str::to_string),
+            },
+            Some(_) => SnapshotEncryption::ServiceManaged,
+            None => SnapshotEncryption::None,
+        }))
+    }
+
+    async fn list_versions(&self, key: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing versions of S3 object {}", key);
+        let mut versions = Vec::new();
+        let mut markers: (Option<String>, Option<String>) = (None, None);
+        loop {
+            let output = self.client
+                .list_object_versions()
+                .bucket(&self.bucket)
+                .prefix(key)
+                .set_key_marker(markers.0.take())
+                .set_version_id_marker(markers.1.take())
+                .send()
+                .await
+                .map_err(|e| describe_error("list versions of", key, &e))?;
+            // The prefix also matches longer keys, and delete markers have no data
+            versions.extend(output.versions().iter().filter(|v| v.key() == Some(key)).map(|v| BackupMetadata {
+                key: key.to_string(),
+                size: v.size().unwrap_or(0),
+                last_modified: v.last_modified().map(|dt| dt.as_secs_f64()).unwrap_or_default(),
+                etag: v.e_tag().map(str::to_string),
+                storage_class: v.storage_class().map(|class| class.as_str().to_string()),
+                version_id: v.version_id().map(str::to_string),
+            }));
+            if !output.is_truncated().unwrap_or(false) {
+                break;
+            }
+            markers = (output.next_key_marker().map(str::to_string), output.next_version_id_marker().map(str::to_string));
+        }
+        versions.sort_by(|a, b| b.last_modified.total_cmp(&a.last_modified));
+        debug!("Found {} versions of {}", versions.len(), key);
+        Ok(versions)
+    }
+
+    async fn archive_status(&self, key: &str) -> Result<ArchiveStatus> {
+        debug!("Looking up archive status of S3 object {}", key);
+        let mut request = self.client.head_object().bucket(&self.bucket).key(key);
+        if let Some(customer_key) = &self.sse_customer_key {
+            request = request
+                .sse_customer_algorithm(SSE_CUSTOMER_ALGORITHM)
+                .sse_customer_key(&customer_key.key)
+                .sse_customer_key_md5(&customer_key.key_md5);
+        }
+        let output = request.send().await.map_err(|e| describe_error("inspect", key, &e))?;
+        Ok(ArchiveStatus::from_restore_header(
+            output.storage_class().map(|class| class.as_str()),
+            output.restore(),
+        ))
+    }
+
+    async fn request_archive_restore(&self, key: &str, tier: ArchiveTier, days: i32) -> Result<()> {
+        debug!("Requesting {} restore of S3 object {} for {} days", tier, key, days);
+        let job = GlacierJobParameters::builder()
+            .tier(Tier::from(tier.as_str()))
+            .build()
+            .map_err(|e| anyhow!("Failed to build restore request for {}: {}", key, e))?;
+        let result = self.client
+            .restore_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .restore_request(RestoreRequest::builder().days(days).glacier_job_parameters(job).build())
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == Some("RestoreAlreadyInProgress") => {
+                debug!("A restore of {} is already running", key);
+                Ok(())
+            }
+            Err(e) => Err(describe_error("restore", key, &e)),
+        }
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader> {
+        self.read_object_range(key, None, start, end).await
+    }
+
+    async fn presigned_url(&self, snapshot: &BackupMetadata, expires_in: std::time::Duration) -> Result<String> {
+        debug!("Presigning a download of {} for {:?}", snapshot.key, expires_in);
+        // The SSE-C key would have to be sent as headers, which a link cannot carry
+        if self.sse_customer_key.is_some() {
+            return Err(anyhow!("{} is read with an SSE-C key, so it cannot be downloaded from a link", snapshot.key));
+        }
+        let config = aws_sdk_s3::presigning::PresigningConfig::expires_in(expires_in)
+            .map_err(|e| anyhow!("Invalid link duration: {}", e))?;
+        let request = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&snapshot.key)
+            .set_version_id(snapshot.version_id.clone())
+            .presigned(config)
+            .await
+            .map_err(|e| describe_error("presign", &snapshot.key, &e))?;
+        Ok(request.uri().to_string())
+    }
+
+    async fn read_version_range(&self, key: &str, version_id: &str, start: u64, end: u64) -> Result<PartReader> {
+        self.read_object_range(key, Some(version_id), start, end).await
+    }
+
+    async fn upload_file(&self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()> {
+        debug!("Uploading {:?} to S3 key: {}", local_path, key);
+        let body = ByteStream::from_path(local_path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", local_path.display(), e))?;
+        let mut request = self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(body);
+        if let Some(label) = label {
+            request = request.metadata("label", label);
+        }
+        if let Some(customer_key) = &self.sse_customer_key {
+            request = request
+                .sse_customer_algorithm(SSE_CUSTOMER_ALGORITHM)
+                .sse_customer_key(&customer_key.key)
+                .sse_customer_key_md5(&customer_key.key_md5);
+        }
+        request.send().await.map_err(|e| anyhow!("Failed to upload {}: {}", key, e))?;
+        Ok(())
+    }
+
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        debug!("Reading whole S3 object {}", key);
+        let output = match self.client.get_object().bucket(&self.bucket).key(key).send().await {
+            Ok(output) => output,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => return Err(describe_error("read", key, &e)),
+        };
+        let body = output.body
+            .collect()
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", key, e))?;
+        Ok(Some(body.into_bytes().to_vec()))
+    }
+
+    async fn write_object(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        debug!("Writing {} bytes to S3 key: {}", body.len(), key);
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to write {}: {}", key, e))?;
+        Ok(())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        debug!("Deleting S3 object {}", key);
+        // On a versioned bucket this adds a delete marker; older versions are kept
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| describe_error("delete", key, &e))?;
+        Ok(())
+    }
+}
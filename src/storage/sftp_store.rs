@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::debug;
+use ssh2::{ErrorCode, Session, Sftp};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use super::{PartReader, SnapshotStore};
+use crate::ui::models::BackupMetadata;
+
+/// SFTP status code for a missing file (`SSH_FX_NO_SUCH_FILE`)
+const SFTP_NO_SUCH_FILE: i32 = 2;
+
+/// Snapshot store backed by files on an SFTP server
+///
+/// libssh2 is blocking, so every operation runs on a blocking task over the
+/// shared session.
+pub struct SftpStore {
+    pub session: Session,
+}
+
+impl SftpStore {
+    /// Run `op` against an SFTP channel on a blocking task
+    async fn with_sftp<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Sftp) -> Result<T> + Send + 'static,
+    {
+        debug!("Opening SFTP channel");
+        let session = self.session.clone();
+        tokio::task::spawn_blocking(move || {
+            let sftp = session.sftp().map_err(|e| anyhow!("Failed to open SFTP channel: {}", e))?;
+            op(&sftp)
+        })
+        .await?
+    }
+}
+
+/// Directory to list for a key prefix, and the key prefix of its entries
+///
+/// Keys keep the form they were listed under, so `backups/db` lists
+/// `backups` and yields keys like `backups/db-20250101.dump`.
+fn list_dir(prefix: &str) -> (String, String) {
+    debug!("Finding SFTP directory for prefix: {}", prefix);
+    match prefix.rsplit_once('/') {
+        Some(("", _)) => ("/".to_string(), "/".to_string()),
+        Some((dir, _)) => (dir.to_string(), format!("{}/", dir)),
+        None => (".".to_string(), String::new()),
+    }
+}
+
+/// Whether an SFTP error means the file does not exist
+fn is_not_found(e: &ssh2::Error) -> bool {
+    e.code() == ErrorCode::SFTP(SFTP_NO_SUCH_FILE)
+}
+
+#[async_trait]
+impl SnapshotStore for SftpStore {
+    fn name(&self) -> &'static str {
+        "SFTP"
+    }
+
+    async fn list_snapshots(&self, prefix: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Listing SFTP files with prefix: {}", prefix);
+        let prefix = prefix.to_string();
+        self.with_sftp(move |sftp| {
+            let (dir, key_prefix) = list_dir(&prefix);
+            let entries = sftp
+                .readdir(Path::new(&dir))
+                .map_err(|e| anyhow!("Failed to list {}: {}", dir, e))?;
+            Ok(entries
+                .into_iter()
+                .filter(|(_, stat)| stat.is_file())
+                .filter_map(|(path, stat)| {
+                    let name = path.file_name()?.to_string_lossy().to_string();
+                    Some(BackupMetadata {
+                        key: format!("{}{}", key_prefix, name),
+                        size: stat.size.unwrap_or(0) as i64,
+                        last_modified: stat.mtime.unwrap_or(0) as f64,
+                        etag: None,
+                        storage_class: None,
+                        version_id: None,
+                    })
+                })
+                .filter(|snapshot| snapshot.key.starts_with(&prefix))
+                .collect())
+        })
+        .await
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> Result<PartReader> {
+        debug!("Reading SFTP file {} bytes {}-{}", key, start, end);
+        let key = key.to_string();
+        let bytes = self.with_sftp(move |sftp| {
+            let mut file = sftp.open(Path::new(&key)).map_err(|e| anyhow!("Failed to read {}: {}", key, e))?;
+            file.seek(SeekFrom::Start(start))?;
+            let mut bytes = Vec::with_capacity((end - start + 1) as usize);
+            file.take(end - start + 1).read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+        .await?;
+        Ok(Box::pin(Cursor::new(bytes)))
+    }
+
+    async fn upload_file(&self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()> {
+        // SFTP has no object metadata, so the label is only kept in the key
+        debug!("Uploading {:?} to SFTP path: {} (label {:?})", local_path, key, label);
+        let local_path = local_path.to_path_buf();
+        let key = key.to_string();
+        self.with_sftp(move |sftp| {
+            let mut local = std::fs::File::open(&local_path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", local_path.display(), e))?;
+            let mut remote = sftp.create(Path::new(&key)).map_err(|e| anyhow!("Failed to upload {}: {}", key, e))?;
+            std::io::copy(&mut local, &mut remote).map_err(|e| anyhow!("Failed to upload {}: {}", key, e))?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn read_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        debug!("Reading whole SFTP file {}", key);
+        let key = key.to_string();
+        self.with_sftp(move |sftp| {
+            let mut file = match sftp.open(Path::new(&key)) {
+                Ok(file) => file,
+                Err(e) if is_not_found(&e) => return Ok(None),
+                Err(e) => return Err(anyhow!("Failed to read {}: {}", key, e)),
+            };
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            Ok(Some(bytes))
+        })
+        .await
+    }
+
+    async fn write_object(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        debug!("Writing {} bytes to SFTP path: {}", body.len(), key);
+        let key = key.to_string();
+        self.with_sftp(move |sftp| {
+            // Create the parent directory, ignoring the error if it already exists
+            if let Some(parent) = PathBuf::from(&key).parent().filter(|p| !p.as_os_str().is_empty()) {
+                let _ = sftp.mkdir(parent, 0o755);
+            }
+            let mut file = sftp.create(Path::new(&key)).map_err(|e| anyhow!("Failed to write {}: {}", key, e))?;
+            file.write_all(&body)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        debug!("Deleting SFTP file {}", key);
+        let key = key.to_string();
+        self.with_sftp(move |sftp| {
+            sftp.unlink(Path::new(&key)).map_err(|e| anyhow!("Failed to delete {}: {}", key, e))
+        })
+        .await
+    }
+}
@@ -0,0 +1,295 @@
+// This module contains streaming restores for the Rustored application
+// Plain SQL dumps need no table of contents and no seeking, so instead of
+// downloading a multi-gigabyte file first, the object body is piped straight
+// into psql. The pipe gives backpressure for free: while psql is busy the copy
+// stops reading, so the download never runs ahead of the restore.
+//
+// Dumps compressed with gzip, zstd or lz4 are piped through the codec's tool on
+// the way. Their uncompressed size is unknown until the end, so progress counts
+// the lines handed to psql against a total estimated from how much of the
+// compressed object those lines took. When psql stops at a failing statement,
+// its error is reported with the line of the dump it stopped at.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, error, info, warn};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context as TaskContext, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use crate::compression::Codec;
+use crate::presets::{RestoreMode, RestoreOptions};
+use crate::storage::{PartReader, SnapshotStore};
+use crate::ui::models::postgres_config::PostgresConfig;
+use crate::ui::models::BackupMetadata;
+
+/// Bytes handed to psql at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Whether a snapshot is a plain SQL dump, which is restored by streaming it into psql
+///
+/// Dumps compressed with gzip, zstd or lz4 count, and are decompressed on the
+/// way. Globals dumps are plain SQL too, but are applied on their own before a
+/// restore.
+pub fn is_plain_sql(key: &str) -> bool {
+    debug!("Checking if {} is a plain SQL dump", key);
+    let name = Codec::from_name(key).and_then(|codec| key.strip_suffix(codec.extension())).unwrap_or(key);
+    name.ends_with(".sql") && !crate::globals::is_globals_key(name)
+}
+
+/// Estimate the lines of a dump from the `lines` found in its first `read` of `total` bytes
+///
+/// For a compressed dump the bytes are compressed ones, so the estimate
+/// assumes the rest of the dump compresses like what was read so far.
+pub fn estimated_lines(lines: u64, read: u64, total: u64) -> u64 {
+    if read == 0 {
+        return 0;
+    }
+    ((lines as u128 * total.max(read) as u128 / read as u128) as u64).max(lines)
+}
+
+/// Explain why psql stopped, from its stderr
+///
+/// psql names the failing statement's line as `psql:<stdin>:LINE: ERROR: ...`.
+/// Notices and warnings printed before the error are left out, and the
+/// `LINE`, `DETAIL` and `HINT` lines psql prints after it are kept.
+///
+/// # Arguments
+///
+/// * `stderr` - What psql wrote to stderr
+/// * `total_lines` - Estimated lines of the dump, or 0 if unknown
+pub fn psql_error(stderr: &str, total_lines: u64) -> String {
+    debug!("Reading psql error from {} bytes of output", stderr.len());
+    let lines: Vec<&str> = stderr.lines().collect();
+    let Some(start) = lines.iter().position(|line| line.contains("ERROR:") || line.contains("FATAL:")) else {
+        return format!("psql failed: {}", stderr.trim());
+    };
+    let details = lines[start + 1..].iter().take_while(|line| !line.starts_with("psql:")).map(|line| line.trim_end());
+    let located = lines[start].strip_prefix("psql:").and_then(|rest| {
+        let mut parts = rest.splitn(3, ':');
+        let (_source, line, message) = (parts.next()?, parts.next()?.parse::<u64>().ok()?, parts.next()?);
+        Some((line, message.trim()))
+    });
+    let mut error = match located {
+        Some((line, message)) if total_lines > 0 => format!("psql stopped at line {} of about {} in the dump: {}", line, total_lines, message),
+        Some((line, message)) => format!("psql stopped at line {} of the dump: {}", line, message),
+        None => format!("psql failed: {}", lines[start].trim()),
+    };
+    for detail in details.filter(|detail| !detail.is_empty()) {
+        error.push('\n');
+        error.push_str(detail);
+    }
+    error
+}
+
+/// Open the whole body of a snapshot, or of its version, as one stream
+pub async fn open_snapshot(store: &dyn SnapshotStore, snapshot: &BackupMetadata) -> Result<PartReader> {
+    debug!("Opening {} for a streaming restore", snapshot.key);
+    if snapshot.size <= 0 {
+        return Err(anyhow!("{} is empty, there is nothing to restore", snapshot.key));
+    }
+    let end = snapshot.size as u64 - 1;
+    match &snapshot.version_id {
+        Some(version_id) => store.read_version_range(&snapshot.key, version_id, 0, end).await,
+        None => store.read_range(&snapshot.key, 0, end).await,
+    }
+}
+
+/// A reader that reports the fraction of `total` bytes read so far
+pub struct ProgressReader<R> {
+    inner: R,
+    read: u64,
+    total: u64,
+    callback: Box<dyn Fn(f32) + Send + Sync>,
+}
+
+impl<R> ProgressReader<R> {
+    /// Wrap `inner`, which is expected to hold `total` bytes
+    pub fn new(inner: R, total: u64, callback: Box<dyn Fn(f32) + Send + Sync>) -> Self {
+        debug!("Tracking progress of a {} byte stream", total);
+        Self { inner, read: 0, total, callback }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+        if read > 0 {
+            self.read += read;
+            (self.callback)((self.read as f32 / self.total.max(1) as f32).min(1.0));
+        }
+        poll
+    }
+}
+
+/// A reader that counts the bytes read through it
+struct CountingReader<'a, R> {
+    inner: R,
+    read: &'a AtomicU64,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<'_, R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.read.fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+/// Hand SQL to psql, counting its lines and reporting the estimated share restored
+///
+/// # Arguments
+///
+/// * `sql` - The uncompressed dump
+/// * `psql` - psql's stdin
+/// * `read` - Bytes of the snapshot read so far, compressed if it is
+/// * `total` - Size of the snapshot
+/// * `lines` - Counts the lines handed to psql
+/// * `progress` - Called with the lines handed to psql over the estimated total
+async fn feed_psql<S: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    mut sql: S,
+    psql: &mut W,
+    read: &AtomicU64,
+    total: u64,
+    lines: &AtomicU64,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+) -> std::io::Result<u64> {
+    debug!("Handing plain SQL to psql");
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let n = sql.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        psql.write_all(&buf[..n]).await?;
+        copied += n as u64;
+        let newlines = buf[..n].iter().filter(|byte| **byte == b'\n').count() as u64;
+        let counted = lines.fetch_add(newlines, Ordering::Relaxed) + newlines;
+        if let Some(progress) = progress {
+            let estimate = estimated_lines(counted, read.load(Ordering::Relaxed), total);
+            progress((counted as f32 / estimate.max(1) as f32).min(1.0));
+        }
+    }
+    psql.flush().await?;
+    Ok(copied)
+}
+
+/// Restore a plain SQL dump read from a stream with psql
+///
+/// The dump goes into the target database or a new `<word>-restored` one, or
+/// into a recreated database when `options.mode` asks for it. A dump whose key
+/// ends in `.gz`, `.zst` or `.lz4` is decompressed with the codec's tool on the
+/// way. psql stops at the first failing statement. Options that only
+/// pg_restore understands are refused rather than silently ignored.
+///
+/// # Arguments
+///
+/// * `pg_config` - Connection settings of the server
+/// * `input` - The dump, e.g. from `open_snapshot`
+/// * `key` - The snapshot's key, whose extension tells its compression
+/// * `size` - Bytes in `input`
+/// * `options` - Restore options, as for an archive restore
+/// * `progress` - Called with the estimated share of the dump's lines restored
+///
+/// # Returns
+///
+/// The name of the database restored into
+pub async fn restore_sql_stream<R: AsyncRead + Unpin>(
+    pg_config: &PostgresConfig,
+    input: R,
+    key: &str,
+    size: u64,
+    options: &RestoreOptions,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+) -> Result<String> {
+    debug!("Starting plain SQL restore of {} from stream", key);
+    if !options.include_tables.is_empty() || !options.exclude_tables.is_empty() {
+        return Err(anyhow!("Restoring only some tables needs a pg_dump archive; a plain SQL dump is restored whole"));
+    }
+    if options.drop_indexes {
+        return Err(anyhow!("Leaving out indexes needs a pg_dump archive; a plain SQL dump is restored whole"));
+    }
+    if options.row_filters.is_some() {
+        return Err(anyhow!("Row filters need a pg_dump archive; a plain SQL dump is restored whole"));
+    }
+    if options.mode == RestoreMode::Clean {
+        return Err(anyhow!("A plain SQL dump cannot clean an existing database; restore it into a new or recreated one"));
+    }
+    if options.jobs.is_some() {
+        warn!("psql restores a plain SQL dump with one connection, ignoring the job count");
+    }
+    let client = pg_config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+    let name = crate::postgres::prepare_restore_database(
+        &client,
+        pg_config.db_name.as_deref(),
+        pg_config.target_db.as_deref(),
+        options.mode,
+    ).await?;
+    // Dropped with the database if the restore is cancelled part way
+    let partial = crate::postgres::PartialRestore::new(client, &name, options);
+
+    let codec = Codec::from_name(key);
+    let span = crate::telemetry::Span::start("psql").with("database", &name);
+    let result = crate::telemetry::instrument(span, async move {
+        let mut cmd = tokio::process::Command::new("psql");
+        cmd.args(["--no-psqlrc", "--quiet", "--set", "ON_ERROR_STOP=1", "--dbname"]).arg(&name);
+        crate::globals::connection_args(&mut cmd, pg_config);
+        cmd.env("PGSSLMODE", if pg_config.use_ssl { "require" } else { "disable" });
+        cmd.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).kill_on_drop(true);
+        let mut child = cmd.spawn().context("Failed to execute psql; install the PostgreSQL client tools to restore plain SQL dumps")?;
+
+        let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("psql input is not available"))?;
+        let (read, lines) = (AtomicU64::new(0), AtomicU64::new(0));
+        let mut input = CountingReader { inner: input, read: &read };
+        let (fed, decompressed) = match codec {
+            None => (feed_psql(&mut input, &mut stdin, &read, size, &lines, progress).await, Ok(())),
+            Some(codec) => {
+                info!("Decompressing {} dump {} on its way to psql", codec, key);
+                let mut decompressor = codec
+                    .decompress_command()
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .with_context(|| codec.missing_tool())?;
+                let compressed = decompressor.stdin.take().ok_or_else(|| anyhow!("{} input is not available", codec))?;
+                let sql = decompressor.stdout.take().ok_or_else(|| anyhow!("{} output is not available", codec))?;
+                let copy = async {
+                    // Closing the codec's stdin tells it the dump is complete
+                    let mut compressed = compressed;
+                    tokio::io::copy(&mut input, &mut compressed).await
+                };
+                let (copied, fed) = tokio::join!(copy, feed_psql(sql, &mut stdin, &read, size, &lines, progress));
+                let output = decompressor.wait_with_output().await.with_context(|| format!("Failed to wait for {}", codec))?;
+                let decompressed = match output.status.success() {
+                    true => copied.map(|_| ()).map_err(|e| e.to_string()),
+                    false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                };
+                (fed, decompressed)
+            }
+        };
+        // Closing stdin tells psql the dump is complete
+        drop(stdin);
+        let output = child.wait_with_output().await.context("Failed to wait for psql")?;
+        let lines = lines.load(Ordering::Relaxed);
+
+        // psql's own error explains a broken pipe better than the copy error does
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            error!("psql failed: {}", error_msg);
+            anyhow::bail!(psql_error(&error_msg, estimated_lines(lines, read.load(Ordering::Relaxed), size)));
+        }
+        // A dump cut short by a corrupt archive may still have run cleanly as far as it got
+        decompressed.map_err(|e| anyhow!("Failed to decompress {} after {} lines: {}", key, lines, e))?;
+        let copied = fed.map_err(|e| anyhow!("Failed to stream the dump to psql: {}", e))?;
+        info!("Restored {} lines ({} bytes) of plain SQL to database: {}", lines, copied, name);
+        Ok(name)
+    }).await;
+    partial.finish();
+    result
+}
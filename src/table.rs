@@ -0,0 +1,147 @@
+// This module contains the table renderer for the Rustored command line
+// List-style commands print their results as column-aligned tables with a
+// header row. Color is only used when writing to a terminal, and never when
+// `--no-color` or the `NO_COLOR` environment variable is set, so piped output
+// stays plain text that is easy to parse.
+
+use crossterm::style::{Color, Stylize};
+use log::debug;
+use std::io::IsTerminal;
+
+/// Horizontal alignment of a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// One value in a table, with an optional color
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    pub color: Option<Color>,
+}
+
+impl Cell {
+    /// Cell printed in the terminal's default color
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self { text: text.into(), color: None }
+    }
+
+    /// Cell printed in `color` when color is enabled
+    pub fn colored(text: impl Into<String>, color: Color) -> Self {
+        Self { text: text.into(), color: Some(color) }
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::plain(text)
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::plain(text)
+    }
+}
+
+/// Column-aligned table with a header row
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    headers: Vec<(String, Align)>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// Table with the given column headers and alignments
+    pub fn new(headers: &[(&str, Align)]) -> Self {
+        debug!("Creating table with {} columns", headers.len());
+        Self {
+            headers: headers.iter().map(|(header, align)| (header.to_string(), *align)).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row; missing cells are left blank and extra cells are dropped
+    pub fn row(&mut self, cells: Vec<Cell>) -> &mut Self {
+        self.rows.push(cells);
+        self
+    }
+
+    /// Whether the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Render the table, one line per row, each ending in a newline
+    ///
+    /// Columns are two spaces apart and padded to their widest value. Color
+    /// codes are added after padding so they never affect alignment.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Whether to bold the header and color cells that have a color
+    pub fn render(&self, color: bool) -> String {
+        debug!("Rendering table of {} rows, color: {}", self.rows.len(), color);
+        let widths: Vec<usize> = self.headers
+            .iter()
+            .enumerate()
+            .map(|(i, (header, _))| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.text.chars().count())
+                    .chain(std::iter::once(header.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut output = String::new();
+        let header: Vec<Cell> = self.headers.iter().map(|(header, _)| Cell::plain(header.as_str())).collect();
+        output.push_str(&self.render_line(&header, &widths, color, true));
+        for row in &self.rows {
+            output.push_str(&self.render_line(row, &widths, color, false));
+        }
+        output
+    }
+
+    /// Render one line of padded cells
+    fn render_line(&self, cells: &[Cell], widths: &[usize], color: bool, header: bool) -> String {
+        let blank = Cell::plain("");
+        let mut line = String::new();
+        for (i, ((_, align), width)) in self.headers.iter().zip(widths).enumerate() {
+            let cell = cells.get(i).unwrap_or(&blank);
+            let padded = match align {
+                Align::Left => format!("{:<width$}", cell.text, width = width),
+                Align::Right => format!("{:>width$}", cell.text, width = width),
+            };
+            let styled = match (color, header, cell.color) {
+                (true, true, _) => padded.bold().to_string(),
+                (true, false, Some(cell_color)) => padded.with(cell_color).to_string(),
+                _ => padded,
+            };
+            if i > 0 {
+                line.push_str("  ");
+            }
+            line.push_str(&styled);
+        }
+        // Padding after the last value only gets in the way of parsing
+        line.truncate(line.trim_end().len());
+        line.push('\n');
+        line
+    }
+}
+
+/// Whether command output should be colored
+///
+/// # Arguments
+///
+/// * `no_color` - Whether `--no-color` was given
+pub fn use_color(no_color: bool) -> bool {
+    debug!("Deciding whether to color output, --no-color: {}", no_color);
+    !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && std::io::stdout().is_terminal()
+}
@@ -0,0 +1,64 @@
+use crate::restore::RestoreTarget;
+use anyhow::Result;
+use async_trait::async_trait;
+use log::{debug, info};
+use std::path::Path;
+use std::time::Duration;
+
+/// Number of progress steps a demo restore reports
+const DEMO_RESTORE_STEPS: u32 = 40;
+
+/// Pause between two progress steps of a demo restore
+const DEMO_RESTORE_STEP: Duration = Duration::from_millis(100);
+
+/// Restore target for `--demo` that contacts no server
+///
+/// A restore reports progress for a few seconds and returns the name the
+/// real target would have restored into.
+pub struct DemoRestoreTarget {
+    /// Name of the target type it stands in for, e.g. `PostgreSQL`
+    pub name: &'static str,
+    /// Database, index or collection the restore reports as restored
+    pub restored: String,
+}
+
+#[async_trait]
+impl RestoreTarget for DemoRestoreTarget {
+    fn name(&self) -> &'static str {
+        debug!("Getting name for demo restore target");
+        self.name
+    }
+
+    fn is_configured(&self) -> bool {
+        debug!("Checking if demo target is configured");
+        true
+    }
+
+    fn required_fields(&self) -> Vec<&'static str> {
+        debug!("Getting required fields for demo target");
+        Vec::new()
+    }
+
+    async fn restore_snapshot(
+        &self,
+        snapshot_path: &Path,
+        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+    ) -> Result<String> {
+        debug!("Simulating {} restore of {:?}", self.name, snapshot_path);
+        for step in 0..=DEMO_RESTORE_STEPS {
+            if let Some(ref callback) = progress_callback {
+                callback(step as f32 / DEMO_RESTORE_STEPS as f32);
+            }
+            if step < DEMO_RESTORE_STEPS {
+                tokio::time::sleep(DEMO_RESTORE_STEP).await;
+            }
+        }
+        info!("Simulated restore to {} {}", self.name, self.restored);
+        Ok(self.restored.clone())
+    }
+
+    async fn test_connection(&self) -> Result<String> {
+        debug!("Testing demo target connection");
+        Ok(format!("Demo mode: no {} server is contacted", self.name))
+    }
+}
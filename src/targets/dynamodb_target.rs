@@ -0,0 +1,134 @@
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::restore::{ImpactAction, RestoreImpact, RestoreTarget};
+use crate::ui::models::dynamodb_config::DynamoDbConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{debug, info};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// DynamoDB restore target implementation
+///
+/// Writes NDJSON items into an existing table with BatchWriteItem, using the
+/// default AWS credential chain.
+pub struct DynamoDbRestoreTarget {
+    pub config: DynamoDbConfig,
+    /// Items the last restore could not write
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl DynamoDbRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: DynamoDbConfig) -> Self {
+        debug!("Creating DynamoDB restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
+
+    /// Connect to DynamoDB in the configured region
+    async fn client(&self) -> Result<aws_sdk_dynamodb::Client> {
+        debug!("Connecting to the configured DynamoDB");
+        if let Some(url) = &self.config.endpoint_url {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                return Err(anyhow!("Invalid DynamoDB endpoint URL: {}", url));
+            }
+        }
+        crate::dynamodb_items::connect(self.config.region.as_deref(), self.config.endpoint_url.as_deref(), &self.config.timeouts).await
+    }
+}
+
+#[async_trait]
+impl RestoreTarget for DynamoDbRestoreTarget {
+    fn name(&self) -> &'static str {
+        debug!("Getting name for DynamoDB restore target");
+        "DynamoDB"
+    }
+
+    fn is_configured(&self) -> bool {
+        debug!("Checking if DynamoDB target is configured");
+        self.config.table.is_some()
+    }
+
+    fn required_fields(&self) -> Vec<&'static str> {
+        debug!("Getting required fields for DynamoDB target");
+        vec!["table"]
+    }
+
+    async fn restore_snapshot(
+        &self,
+        snapshot_path: &Path,
+        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+    ) -> Result<String> {
+        let table = self.config.target_table().ok_or_else(|| anyhow!("DynamoDB table not specified"))?;
+        if let Some(ref callback) = progress_callback {
+            callback(0.0);
+        }
+
+        let client = self.client().await?;
+        let endpoint = crate::dynamodb_items::endpoint_name(&client, self.config.endpoint_url.as_deref());
+        debug!("Restoring to DynamoDB at {}, table {}", endpoint, table);
+        let path = dead_letter_path(Path::new("."), &table, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("dynamodb", &endpoint, &table, "items", path);
+        let result = crate::dynamodb_items::import_items(
+            &client,
+            &table,
+            snapshot_path,
+            &self.config.mapping,
+            progress_callback.as_deref(),
+            &mut dead_letters,
+        ).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(1.0);
+        }
+        match result {
+            Ok(_) => {
+                info!("Restored to DynamoDB table: {}", table);
+                Ok(table)
+            }
+            Err(e) => Err(anyhow!("Failed to restore to DynamoDB: {}", e)),
+        }
+    }
+
+    async fn test_connection(&self) -> Result<String> {
+        debug!("Testing connection to DynamoDB");
+        let client = self.client().await?;
+        let endpoint = crate::dynamodb_items::endpoint_name(&client, self.config.endpoint_url.as_deref());
+        // Describing the table checks the credentials and that the table exists
+        match self.config.target_table() {
+            Some(table) => match crate::dynamodb_items::table_state(&client, &table).await? {
+                Some(_) => Ok(format!("Successfully connected to DynamoDB at {}, table {} exists", endpoint, table)),
+                None => Err(anyhow!("Connected to DynamoDB at {}, but table {} does not exist", endpoint, table)),
+            },
+            None => {
+                client.list_tables().limit(1).send().await
+                    .map_err(|e| anyhow!("Failed to connect to DynamoDB at {}: {}", endpoint, aws_sdk_dynamodb::error::DisplayErrorContext(e)))?;
+                Ok(format!("Successfully connected to DynamoDB at {}", endpoint))
+            }
+        }
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the DynamoDB table");
+        let table = self.config.target_table().ok_or_else(|| anyhow!("DynamoDB table not specified"))?;
+        let client = self.client().await?;
+        let (count, size) = crate::dynamodb_items::table_state(&client, &table)
+            .await?
+            .ok_or_else(|| anyhow!("DynamoDB table {} does not exist; create it before restoring", table))?;
+        Ok(Some(RestoreImpact {
+            kind: "table",
+            name: table,
+            unit: "items",
+            exists: true,
+            count: Some(count),
+            size_bytes: Some(size),
+            // Items with the keys of existing ones replace them
+            existing: ImpactAction::Append,
+        }))
+    }
+
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        debug!("Getting items rejected by the last DynamoDB restore");
+        self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
@@ -1,13 +1,25 @@
-use crate::restore::RestoreTarget;
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::restore::{RestoreImpact, RestoreTarget};
 use crate::ui::models::elasticsearch_config::ElasticsearchConfig;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::{debug, info};
 use std::path::Path;
+use std::sync::Mutex;
 
 /// Elasticsearch restore target implementation
 pub struct ElasticsearchRestoreTarget {
     pub config: ElasticsearchConfig,
+    /// Documents the last restore rejected
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl ElasticsearchRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: ElasticsearchConfig) -> Self {
+        debug!("Creating Elasticsearch restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
 }
 
 #[async_trait]
@@ -36,20 +48,30 @@ impl RestoreTarget for ElasticsearchRestoreTarget {
     ) -> Result<String> {
         // Get Elasticsearch connection details
         let host = self.config.host.as_ref().ok_or_else(|| anyhow!("Elasticsearch host not specified"))?.clone();
-        let index = self.config.index.as_ref().ok_or_else(|| anyhow!("Elasticsearch index not specified"))?.clone();
+        let index = self.config.target_index().ok_or_else(|| anyhow!("Elasticsearch index not specified"))?;
 
         // Report initial progress
         if let Some(ref callback) = progress_callback {
             callback(0.0);
         }
 
-        // Call the Elasticsearch restore function
+        // Stream the snapshot in with the configured bulk settings
         debug!("Restoring to Elasticsearch at {}, index {}", host, index);
-        let result = crate::datastore::restore_to_elasticsearch(
+        let client = self.config.timeouts.tls_client(&self.config.tls)?;
+        let path = dead_letter_path(Path::new("."), &index, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("elasticsearch", &host, &index, "documents", path);
+        let result = crate::es_bulk::bulk_restore(
+            &client,
+            None,
             &host,
             &index,
-            snapshot_path.to_str().ok_or_else(|| anyhow!("Invalid snapshot path"))?,
+            snapshot_path,
+            &self.config.bulk,
+            &self.config.mapping,
+            progress_callback.as_deref(),
+            &mut dead_letters,
         ).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
 
         // Report completion progress
         if let Some(ref callback) = progress_callback {
@@ -59,7 +81,7 @@ impl RestoreTarget for ElasticsearchRestoreTarget {
         match result {
             Ok(_) => {
                 info!("Restored to Elasticsearch index: {}", index);
-                Ok(format!("Successfully restored to index: {}", index))
+                Ok(index)
             }
             Err(e) => Err(anyhow!("Failed to restore to Elasticsearch: {}", e)),
         }
@@ -80,16 +102,55 @@ impl RestoreTarget for ElasticsearchRestoreTarget {
             }
         };
         
-        // Simple connection test - in a real implementation, this would use the Elasticsearch client
-        // to check if the server is reachable
         debug!("Validating Elasticsearch host URL format");
-        if host.starts_with("http://") || host.starts_with("https://") {
-            // For now, just return success since we don't have a real implementation
-            debug!("Elasticsearch URL format is valid, connection test passed");
-            Ok(format!("Successfully connected to Elasticsearch at {}", host))
-        } else {
+        if !(host.starts_with("http://") || host.starts_with("https://")) {
             debug!("Invalid Elasticsearch host URL format: {}", host);
-            Err(anyhow!("Invalid Elasticsearch host URL: {}", host))
+            return Err(anyhow!("Invalid Elasticsearch host URL: {}", host));
+        }
+
+        // Ping the cluster root, bounded by the configured timeouts
+        let client = self.config.timeouts.tls_client(&self.config.tls)?;
+        let response = client.get(&host).send().await
+            .map_err(|e| anyhow!("Failed to connect to Elasticsearch at {}: {}", host, e))?;
+        if !response.status().is_success() {
+            debug!("Elasticsearch returned status {}", response.status());
+            return Err(anyhow!("Elasticsearch at {} returned {}", host, response.status()));
+        }
+        debug!("Elasticsearch connection test passed");
+        Ok(format!("Successfully connected to Elasticsearch at {}", host))
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the Elasticsearch index");
+        let host = self.config.host.as_ref().ok_or_else(|| anyhow!("Elasticsearch host not specified"))?;
+        let index = &self.config.target_index().ok_or_else(|| anyhow!("Elasticsearch index not specified"))?;
+        let (kind, existing) = self.config.bulk.target_kind();
+
+        let client = self.config.timeouts.tls_client(&self.config.tls)?;
+        let url = format!("{}/{}/_stats/docs,store", host.trim_end_matches('/'), index);
+        let response = client.get(&url).send().await
+            .map_err(|e| anyhow!("Failed to read stats of Elasticsearch index {}: {}", index, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Some(RestoreImpact::missing(kind, index, "documents")));
         }
+        if !response.status().is_success() {
+            return Err(anyhow!("Elasticsearch returned {} for the stats of index {}", response.status(), index));
+        }
+        let stats: serde_json::Value = response.json().await?;
+        let primaries = &stats["_all"]["primaries"];
+        Ok(Some(RestoreImpact {
+            kind,
+            name: index.clone(),
+            unit: "documents",
+            exists: true,
+            count: primaries["docs"]["count"].as_u64(),
+            size_bytes: primaries["store"]["size_in_bytes"].as_u64(),
+            existing,
+        }))
+    }
+
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        debug!("Getting documents rejected by the last Elasticsearch restore");
+        self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()).clone()
     }
 }
@@ -0,0 +1,128 @@
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::influx_lines::InfluxBucket;
+use crate::restore::{ImpactAction, RestoreImpact, RestoreTarget};
+use crate::ui::models::influxdb_config::InfluxDbConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{debug, info};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// InfluxDB restore target implementation
+///
+/// Writes line protocol into an InfluxDB 2.x bucket in chunks, creating the
+/// bucket first if it does not exist.
+pub struct InfluxDbRestoreTarget {
+    pub config: InfluxDbConfig,
+    /// Lines the last restore could not write
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl InfluxDbRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: InfluxDbConfig) -> Self {
+        debug!("Creating InfluxDB restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
+
+    /// The bucket to write to, with the host checked to be a URL
+    fn target(&self) -> Result<InfluxBucket> {
+        debug!("Getting InfluxDB bucket");
+        let host = self.config.host.as_ref().ok_or_else(|| anyhow!("InfluxDB host not specified"))?;
+        if !(host.starts_with("http://") || host.starts_with("https://")) {
+            return Err(anyhow!("Invalid InfluxDB host URL: {}", host));
+        }
+        let mut target = self.config.target().ok_or_else(|| anyhow!("InfluxDB organization and bucket not specified"))?;
+        target.host = host.trim_end_matches('/').to_string();
+        Ok(target)
+    }
+}
+
+#[async_trait]
+impl RestoreTarget for InfluxDbRestoreTarget {
+    fn name(&self) -> &'static str {
+        debug!("Getting name for InfluxDB restore target");
+        "InfluxDB"
+    }
+
+    fn is_configured(&self) -> bool {
+        debug!("Checking if InfluxDB target is configured");
+        self.config.host.is_some() && self.config.org.is_some() && self.config.bucket.is_some()
+    }
+
+    fn required_fields(&self) -> Vec<&'static str> {
+        debug!("Getting required fields for InfluxDB target");
+        vec!["host", "org", "bucket"]
+    }
+
+    async fn restore_snapshot(
+        &self,
+        snapshot_path: &Path,
+        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+    ) -> Result<String> {
+        let target = self.target()?;
+        if let Some(ref callback) = progress_callback {
+            callback(0.0);
+        }
+
+        debug!("Restoring to InfluxDB at {}, bucket {}", target.host, target.bucket);
+        let client = self.config.timeouts.http_client()?;
+        let path = dead_letter_path(Path::new("."), &target.bucket, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("influxdb", &target.host, &target.bucket, "points", path);
+        let result = crate::influx_lines::write_lines(&client, &target, snapshot_path, progress_callback.as_deref(), &mut dead_letters).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(1.0);
+        }
+        match result {
+            Ok(_) => {
+                info!("Restored to InfluxDB bucket: {}", target.bucket);
+                Ok(target.bucket)
+            }
+            Err(e) => Err(anyhow!("Failed to restore to InfluxDB: {}", e)),
+        }
+    }
+
+    async fn test_connection(&self) -> Result<String> {
+        debug!("Testing connection to InfluxDB");
+        let target = self.target()?;
+        let client = self.config.timeouts.http_client()?;
+        // The health check answers without a token, so looking up the bucket also checks it
+        let response = target.request(&client, reqwest::Method::GET, "/health").send().await
+            .map_err(|e| anyhow!("Failed to connect to InfluxDB at {}: {}", target.host, e))?;
+        if !response.status().is_success() {
+            debug!("InfluxDB returned status {} for /health", response.status());
+            return Err(anyhow!("InfluxDB at {} returned {}", target.host, response.status()));
+        }
+        match target.bucket_id(&client).await? {
+            Some(_) => Ok(format!("Successfully connected to InfluxDB at {}, bucket {} exists", target.host, target.bucket)),
+            None => Ok(format!("Successfully connected to InfluxDB at {}, bucket {} will be created", target.host, target.bucket)),
+        }
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the InfluxDB bucket");
+        let target = self.target()?;
+        let client = self.config.timeouts.http_client()?;
+        if target.bucket_id(&client).await?.is_none() {
+            return Ok(Some(RestoreImpact::missing("bucket", &target.bucket, "points")));
+        }
+        Ok(Some(RestoreImpact {
+            kind: "bucket",
+            name: target.bucket,
+            unit: "points",
+            exists: true,
+            // Counting a bucket's points means scanning all of it
+            count: None,
+            size_bytes: None,
+            // Points with the series and time of existing ones replace them
+            existing: ImpactAction::Append,
+        }))
+    }
+
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        debug!("Getting lines rejected by the last InfluxDB restore");
+        self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
@@ -0,0 +1,151 @@
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::restore::{ImpactAction, RestoreImpact, RestoreTarget};
+use crate::ui::models::meilisearch_config::MeilisearchConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{debug, info};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Meilisearch restore target implementation
+///
+/// Adds NDJSON documents to an index and waits for Meilisearch to process
+/// each batch's task, so progress follows indexing rather than uploading.
+pub struct MeilisearchRestoreTarget {
+    pub config: MeilisearchConfig,
+    /// Documents the last restore rejected
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl MeilisearchRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: MeilisearchConfig) -> Self {
+        debug!("Creating Meilisearch restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
+
+    /// The configured host, checked to be a URL
+    fn host(&self) -> Result<String> {
+        debug!("Getting Meilisearch host");
+        let host = self.config.host.as_ref().ok_or_else(|| anyhow!("Meilisearch host not specified"))?;
+        if !(host.starts_with("http://") || host.starts_with("https://")) {
+            return Err(anyhow!("Invalid Meilisearch host URL: {}", host));
+        }
+        Ok(host.trim_end_matches('/').to_string())
+    }
+
+    /// A GET request carrying the API key, if one is set
+    fn get(&self, client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+        let request = client.get(url);
+        match &self.config.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl RestoreTarget for MeilisearchRestoreTarget {
+    fn name(&self) -> &'static str {
+        debug!("Getting name for Meilisearch restore target");
+        "Meilisearch"
+    }
+
+    fn is_configured(&self) -> bool {
+        debug!("Checking if Meilisearch target is configured");
+        self.config.host.is_some() && self.config.index.is_some()
+    }
+
+    fn required_fields(&self) -> Vec<&'static str> {
+        debug!("Getting required fields for Meilisearch target");
+        vec!["host", "index"]
+    }
+
+    async fn restore_snapshot(
+        &self,
+        snapshot_path: &Path,
+        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+    ) -> Result<String> {
+        let host = self.host()?;
+        let index = self.config.target_index().ok_or_else(|| anyhow!("Meilisearch index not specified"))?;
+        if let Some(ref callback) = progress_callback {
+            callback(0.0);
+        }
+
+        debug!("Restoring to Meilisearch at {}, index {}", host, index);
+        let client = self.config.timeouts.http_client()?;
+        let path = dead_letter_path(Path::new("."), &index, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("meilisearch", &host, &index, "documents", path);
+        let result = crate::meili_documents::add_documents(
+            &client,
+            &host,
+            &index,
+            self.config.api_key.as_deref(),
+            self.config.primary_key.as_deref(),
+            snapshot_path,
+            &self.config.mapping,
+            progress_callback.as_deref(),
+            &mut dead_letters,
+        ).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(1.0);
+        }
+        match result {
+            Ok(_) => {
+                info!("Restored to Meilisearch index: {}", index);
+                Ok(index)
+            }
+            Err(e) => Err(anyhow!("Failed to restore to Meilisearch: {}", e)),
+        }
+    }
+
+    async fn test_connection(&self) -> Result<String> {
+        debug!("Testing connection to Meilisearch");
+        let host = self.host()?;
+        let client = self.config.timeouts.http_client()?;
+        // /health answers without a key, so listing an index also checks the key
+        for url in [format!("{}/health", host), format!("{}/indexes?limit=1", host)] {
+            let response = self.get(&client, &url).send().await
+                .map_err(|e| anyhow!("Failed to connect to Meilisearch at {}: {}", host, e))?;
+            if !response.status().is_success() {
+                debug!("Meilisearch returned status {} for {}", response.status(), url);
+                return Err(anyhow!("Meilisearch at {} returned {}", host, response.status()));
+            }
+        }
+        let api_key_info = if self.config.api_key.is_some() { " with API key" } else { "" };
+        Ok(format!("Successfully connected to Meilisearch at {}{}", host, api_key_info))
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the Meilisearch index");
+        let host = self.host()?;
+        let index = self.config.target_index().ok_or_else(|| anyhow!("Meilisearch index not specified"))?;
+
+        let client = self.config.timeouts.http_client()?;
+        let response = self.get(&client, &format!("{}/indexes/{}/stats", host, index)).send().await
+            .map_err(|e| anyhow!("Failed to read stats of Meilisearch index {}: {}", index, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Some(RestoreImpact::missing("index", &index, "documents")));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Meilisearch returned {} for the stats of index {}", response.status(), index));
+        }
+        let stats: serde_json::Value = response.json().await?;
+        Ok(Some(RestoreImpact {
+            kind: "index",
+            name: index,
+            unit: "documents",
+            exists: true,
+            count: stats["numberOfDocuments"].as_u64(),
+            size_bytes: stats["rawDocumentDbSize"].as_u64(),
+            existing: ImpactAction::Append,
+        }))
+    }
+
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        debug!("Getting documents rejected by the last Meilisearch restore");
+        self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
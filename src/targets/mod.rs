@@ -1,24 +1,51 @@
 mod postgres_target;
 mod elasticsearch_target;
 mod qdrant_target;
+mod opensearch_target;
+mod meilisearch_target;
+mod weaviate_target;
+mod milvus_target;
+mod dynamodb_target;
+mod influxdb_target;
+mod demo_target;
 
 pub use postgres_target::PostgresRestoreTarget;
 pub use elasticsearch_target::ElasticsearchRestoreTarget;
 pub use qdrant_target::QdrantRestoreTarget;
+pub use opensearch_target::OpenSearchRestoreTarget;
+pub use meilisearch_target::MeilisearchRestoreTarget;
+pub use weaviate_target::WeaviateRestoreTarget;
+pub use milvus_target::MilvusRestoreTarget;
+pub use dynamodb_target::DynamoDbRestoreTarget;
+pub use influxdb_target::InfluxDbRestoreTarget;
+pub use demo_target::DemoRestoreTarget;
 
 use crate::restore::RestoreTarget;
 use crate::datastore::RestoreTarget as RestoreTargetEnum;
 
 /// Factory function to create a restore target based on the target type
+#[allow(clippy::too_many_arguments)]
 pub fn create_restore_target(
     target_type: RestoreTargetEnum,
     pg_config: crate::ui::models::postgres_config::PostgresConfig,
     es_config: crate::ui::models::elasticsearch_config::ElasticsearchConfig,
     qdrant_config: crate::ui::models::qdrant_config::QdrantConfig,
+    os_config: crate::ui::models::opensearch_config::OpenSearchConfig,
+    meili_config: crate::ui::models::meilisearch_config::MeilisearchConfig,
+    weaviate_config: crate::ui::models::weaviate_config::WeaviateConfig,
+    milvus_config: crate::ui::models::milvus_config::MilvusConfig,
+    dynamodb_config: crate::ui::models::dynamodb_config::DynamoDbConfig,
+    influxdb_config: crate::ui::models::influxdb_config::InfluxDbConfig,
 ) -> Box<dyn RestoreTarget + Send + Sync> {
     match target_type {
-        RestoreTargetEnum::Postgres => Box::new(PostgresRestoreTarget { config: pg_config }),
-        RestoreTargetEnum::Elasticsearch => Box::new(ElasticsearchRestoreTarget { config: es_config }),
-        RestoreTargetEnum::Qdrant => Box::new(QdrantRestoreTarget { config: qdrant_config }),
+        RestoreTargetEnum::Postgres => Box::new(PostgresRestoreTarget { config: pg_config, options: Default::default() }),
+        RestoreTargetEnum::Elasticsearch => Box::new(ElasticsearchRestoreTarget::new(es_config)),
+        RestoreTargetEnum::Qdrant => Box::new(QdrantRestoreTarget::new(qdrant_config)),
+        RestoreTargetEnum::OpenSearch => Box::new(OpenSearchRestoreTarget::new(os_config)),
+        RestoreTargetEnum::Meilisearch => Box::new(MeilisearchRestoreTarget::new(meili_config)),
+        RestoreTargetEnum::Weaviate => Box::new(WeaviateRestoreTarget::new(weaviate_config)),
+        RestoreTargetEnum::Milvus => Box::new(MilvusRestoreTarget::new(milvus_config)),
+        RestoreTargetEnum::DynamoDb => Box::new(DynamoDbRestoreTarget::new(dynamodb_config)),
+        RestoreTargetEnum::InfluxDb => Box::new(InfluxDbRestoreTarget::new(influxdb_config)),
     }
 }
@@ -0,0 +1,155 @@
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::restore::{RestoreImpact, RestoreTarget};
+use crate::sigv4::send;
+use crate::ui::models::opensearch_config::OpenSearchConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{debug, info};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// OpenSearch restore target implementation
+///
+/// Loads documents with the same bulk loader as Elasticsearch, signing every
+/// request with SigV4 when a region is configured, as Amazon OpenSearch
+/// Service and OpenSearch Serverless require.
+pub struct OpenSearchRestoreTarget {
+    pub config: OpenSearchConfig,
+    /// Documents the last restore rejected
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl OpenSearchRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: OpenSearchConfig) -> Self {
+        debug!("Creating OpenSearch restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
+
+    /// The configured host, checked to be a URL
+    fn host(&self) -> Result<String> {
+        debug!("Getting OpenSearch host");
+        let host = self.config.host.as_ref().ok_or_else(|| anyhow!("OpenSearch host not specified"))?;
+        if !(host.starts_with("http://") || host.starts_with("https://")) {
+            return Err(anyhow!("Invalid OpenSearch host URL: {}", host));
+        }
+        Ok(host.trim_end_matches('/').to_string())
+    }
+}
+
+#[async_trait]
+impl RestoreTarget for OpenSearchRestoreTarget {
+    fn name(&self) -> &'static str {
+        debug!("Getting name for OpenSearch restore target");
+        "OpenSearch"
+    }
+
+    fn is_configured(&self) -> bool {
+        debug!("Checking if OpenSearch target is configured");
+        self.config.host.is_some() && self.config.index.is_some()
+    }
+
+    fn required_fields(&self) -> Vec<&'static str> {
+        debug!("Getting required fields for OpenSearch target");
+        vec!["host", "index"]
+    }
+
+    async fn restore_snapshot(
+        &self,
+        snapshot_path: &Path,
+        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+    ) -> Result<String> {
+        let host = self.host()?;
+        let index = self.config.target_index().ok_or_else(|| anyhow!("OpenSearch index not specified"))?;
+        if let Some(ref callback) = progress_callback {
+            callback(0.0);
+        }
+
+        debug!("Restoring to OpenSearch at {}, index {}", host, index);
+        let client = self.config.timeouts.http_client()?;
+        let signer = self.config.signer().await?;
+        let path = dead_letter_path(Path::new("."), &index, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("opensearch", &host, &index, "documents", path);
+        let result = crate::es_bulk::bulk_restore(
+            &client,
+            signer.as_ref(),
+            &host,
+            &index,
+            snapshot_path,
+            &self.config.bulk,
+            &self.config.mapping,
+            progress_callback.as_deref(),
+            &mut dead_letters,
+        ).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(1.0);
+        }
+        match result {
+            Ok(_) => {
+                info!("Restored to OpenSearch index: {}", index);
+                Ok(index)
+            }
+            Err(e) => Err(anyhow!("Failed to restore to OpenSearch: {}", e)),
+        }
+    }
+
+    async fn test_connection(&self) -> Result<String> {
+        debug!("Testing connection to OpenSearch");
+        let host = self.host()?;
+        let client = self.config.timeouts.http_client()?;
+        let signer = self.config.signer().await?;
+        // Serverless collections have no cluster root, so list the indices instead
+        let url = match self.config.service.as_str() {
+            crate::sigv4::SERVICE_SERVERLESS => format!("{}/_cat/indices", host),
+            _ => host.clone(),
+        };
+        let response = send(&client, signer.as_ref(), client.get(&url)).await
+            .map_err(|e| anyhow!("Failed to connect to OpenSearch at {}: {}", host, e))?;
+        if !response.status().is_success() {
+            debug!("OpenSearch returned status {}", response.status());
+            return Err(anyhow!("OpenSearch at {} returned {}", host, response.status()));
+        }
+        let signing = match signer {
+            Some(signer) => format!(" with SigV4 for {} in {}", signer.service, signer.region),
+            None => String::new(),
+        };
+        Ok(format!("Successfully connected to OpenSearch at {}{}", host, signing))
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the OpenSearch index");
+        let host = self.host()?;
+        let index = self.config.target_index().ok_or_else(|| anyhow!("OpenSearch index not specified"))?;
+        let (kind, existing) = self.config.bulk.target_kind();
+
+        let client = self.config.timeouts.http_client()?;
+        let signer = self.config.signer().await?;
+        let url = format!("{}/{}/_stats/docs,store", host, index);
+        let response = send(&client, signer.as_ref(), client.get(&url)).await
+            .map_err(|e| anyhow!("Failed to read stats of OpenSearch index {}: {}", index, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Some(RestoreImpact::missing(kind, &index, "documents")));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("OpenSearch returned {} for the stats of index {}", response.status(), index));
+        }
+        let stats: serde_json::Value = response.json().await?;
+        let primaries = &stats["_all"]["primaries"];
+        Ok(Some(RestoreImpact {
+            kind,
+            name: index,
+            unit: "documents",
+            exists: true,
+            count: primaries["docs"]["count"].as_u64(),
+            size_bytes: primaries["store"]["size_in_bytes"].as_u64(),
+            existing,
+        }))
+    }
+
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        debug!("Getting documents rejected by the last OpenSearch restore");
+        self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
@@ -1,4 +1,5 @@
-use crate::restore::RestoreTarget;
+use crate::presets::{RestoreMode, RestoreOptions};
+use crate::restore::{ImpactAction, RestoreImpact, RestoreTarget};
 use crate::ui::models::postgres_config::PostgresConfig;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -8,6 +9,24 @@ use std::path::Path;
 /// PostgreSQL restore target implementation
 pub struct PostgresRestoreTarget {
     pub config: PostgresConfig,
+    pub options: RestoreOptions,
+}
+
+impl PostgresRestoreTarget {
+    /// Number of tables and size in bytes of a database, or `None` if it does not exist
+    async fn database_state(&self, name: &str) -> Result<Option<(u64, u64)>> {
+        debug!("Reading table count and size of database {}", name);
+        let client = self.config.connect_to(crate::promote::MAINTENANCE_DB).await?;
+        let Some(row) = client.query_opt("SELECT pg_database_size(datname) FROM pg_database WHERE datname = $1", &[&name]).await? else {
+            return Ok(None);
+        };
+        let size: i64 = row.get(0);
+        let tables: i64 = self.config.connect_to(name).await?
+            .query_one("SELECT count(*) FROM pg_catalog.pg_tables WHERE schemaname NOT IN ('pg_catalog', 'information_schema')", &[])
+            .await?
+            .get(0);
+        Ok(Some((tables as u64, size as u64)))
+    }
 }
 
 #[async_trait]
@@ -58,6 +77,9 @@ impl RestoreTarget for PostgresRestoreTarget {
             password,
             use_ssl,
             snapshot_path.to_str().ok_or_else(|| anyhow!("Invalid snapshot path"))?,
+            self.config.db_name.as_deref(),
+            self.config.target_db.as_deref(),
+            self.options.clone(),
         ).await;
 
         // Report completion progress
@@ -68,7 +90,7 @@ impl RestoreTarget for PostgresRestoreTarget {
         match result {
             Ok(db_name) => {
                 info!("Restored to PostgreSQL database: {}", db_name);
-                Ok(format!("Successfully restored to database: {}", db_name))
+                Ok(db_name)
             }
             Err(e) => Err(anyhow!("Failed to restore to PostgreSQL: {}", e)),
         }
@@ -121,9 +143,9 @@ impl RestoreTarget for PostgresRestoreTarget {
             config.user(user);
         }
         
-        if let Some(_) = &password {
+        if let Some(pass) = &password {
             debug!("Setting PostgreSQL password: [MASKED]");
-            config.password(password.as_ref().unwrap());
+            config.password(pass);
         }
         
         // Try to connect to PostgreSQL
@@ -147,4 +169,26 @@ impl RestoreTarget for PostgresRestoreTarget {
             },
         }
     }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the database a PostgreSQL restore writes over");
+        let existing = match self.options.mode {
+            RestoreMode::New => return Ok(None),
+            RestoreMode::Recreate => ImpactAction::Recreate,
+            RestoreMode::Clean => ImpactAction::Clean,
+        };
+        let name = self.config.target_db.as_ref().or(self.config.db_name.as_ref())
+            .ok_or_else(|| anyhow!("PostgreSQL database not specified"))?;
+        let missing = RestoreImpact { existing, ..RestoreImpact::missing("database", name, "tables") };
+        // A database that cannot be looked up is treated as holding data, so it must still be confirmed
+        let (count, size_bytes) = match self.database_state(name).await {
+            Ok(Some((tables, size))) => (Some(tables), Some(size)),
+            Ok(None) => return Ok(Some(missing)),
+            Err(e) => {
+                debug!("Failed to look up database {}: {}", name, e);
+                (None, None)
+            }
+        };
+        Ok(Some(RestoreImpact { exists: true, count, size_bytes, ..missing }))
+    }
 }
@@ -1,13 +1,26 @@
-use crate::restore::RestoreTarget;
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::qdrant_points::QdrantConnection;
+use crate::restore::{ImpactAction, RestoreImpact, RestoreTarget};
 use crate::ui::models::qdrant_config::QdrantConfig;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::{debug, info};
 use std::path::Path;
+use std::sync::Mutex;
 
 /// Qdrant restore target implementation
 pub struct QdrantRestoreTarget {
     pub config: QdrantConfig,
+    /// Points the last restore rejected
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl QdrantRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: QdrantConfig) -> Self {
+        debug!("Creating Qdrant restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
 }
 
 #[async_trait]
@@ -36,7 +49,7 @@ impl RestoreTarget for QdrantRestoreTarget {
     ) -> Result<String> {
         // Get Qdrant connection details
         let host = self.config.host.as_ref().ok_or_else(|| anyhow!("Qdrant host not specified"))?.clone();
-        let collection = self.config.collection.as_ref().ok_or_else(|| anyhow!("Qdrant collection not specified"))?.clone();
+        let collection = self.config.target_collection().ok_or_else(|| anyhow!("Qdrant collection not specified"))?;
         let api_key = self.config.api_key.clone();
 
         // Report initial progress
@@ -44,14 +57,20 @@ impl RestoreTarget for QdrantRestoreTarget {
             callback(0.0);
         }
 
-        // Call the Qdrant restore function
+        // Upsert the points in batches
         debug!("Restoring to Qdrant at {}, collection {}", host, collection);
-        let result = crate::datastore::restore_to_qdrant(
-            &host,
+        let connection = QdrantConnection::connect(&host, api_key.as_deref(), &self.config.timeouts, &self.config.tls, &self.config.upload).await?;
+        let path = dead_letter_path(Path::new("."), &collection, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("qdrant", &host, &collection, "points", path);
+        let result = crate::qdrant_points::upload_points(
+            &connection,
             &collection,
-            api_key.as_deref(),
-            snapshot_path.to_str().ok_or_else(|| anyhow!("Invalid snapshot path"))?,
+            snapshot_path,
+            &self.config.mapping,
+            progress_callback.as_deref(),
+            &mut dead_letters,
         ).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
 
         // Report completion progress
         if let Some(ref callback) = progress_callback {
@@ -61,7 +80,7 @@ impl RestoreTarget for QdrantRestoreTarget {
         match result {
             Ok(_) => {
                 info!("Restored to Qdrant collection: {}", collection);
-                Ok(format!("Successfully restored to collection: {}", collection))
+                Ok(collection)
             }
             Err(e) => Err(anyhow!("Failed to restore to Qdrant: {}", e)),
         }
@@ -86,16 +105,56 @@ impl RestoreTarget for QdrantRestoreTarget {
         debug!("Qdrant API key provided: {}", has_api_key);
         let api_key_info = if has_api_key { " with API key" } else { "" };
         
-        // Simple connection test - in a real implementation, this would use the Qdrant client
-        // to check if the server is reachable
         debug!("Validating Qdrant host URL format");
-        if host.starts_with("http://") || host.starts_with("https://") {
-            // For now, just return success since we don't have a real implementation
-            debug!("Qdrant URL format is valid, connection test passed");
-            Ok(format!("Successfully connected to Qdrant at {}{}", host, api_key_info))
-        } else {
+        if !(host.starts_with("http://") || host.starts_with("https://")) {
             debug!("Invalid Qdrant host URL format: {}", host);
-            Err(anyhow!("Invalid Qdrant host URL: {}", host))
+            return Err(anyhow!("Invalid Qdrant host URL: {}", host));
+        }
+
+        // Ping the server root, bounded by the configured timeouts
+        let client = self.config.timeouts.tls_client(&self.config.tls)?;
+        let mut request = client.get(&host);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header("api-key", api_key);
+        }
+        let response = request.send().await
+            .map_err(|e| anyhow!("Failed to connect to Qdrant at {}: {}", host, e))?;
+        if !response.status().is_success() {
+            debug!("Qdrant returned status {}", response.status());
+            return Err(anyhow!("Qdrant at {} returned {}", host, response.status()));
+        }
+        debug!("Qdrant connection test passed");
+        Ok(format!("Successfully connected to Qdrant at {}{}", host, api_key_info))
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the Qdrant collection");
+        let host = self.config.host.as_ref().ok_or_else(|| anyhow!("Qdrant host not specified"))?;
+        let collection = &self.config.target_collection().ok_or_else(|| anyhow!("Qdrant collection not specified"))?;
+
+        let client = self.config.timeouts.tls_client(&self.config.tls)?;
+        let mut request = client.get(format!("{}/collections/{}", host.trim_end_matches('/'), collection));
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header("api-key", api_key);
+        }
+        let response = request.send().await
+            .map_err(|e| anyhow!("Failed to read Qdrant collection {}: {}", collection, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Some(RestoreImpact::missing("collection", collection, "points")));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Qdrant returned {} for collection {}", response.status(), collection));
         }
+        let info: serde_json::Value = response.json().await?;
+        // Qdrant does not report a collection's size on disk
+        Ok(Some(RestoreImpact {
+            kind: "collection",
+            name: collection.clone(),
+            unit: "points",
+            exists: true,
+            count: info["result"]["points_count"].as_u64(),
+            size_bytes: None,
+            existing: ImpactAction::Append,
+        }))
     }
 }
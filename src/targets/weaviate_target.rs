@@ -0,0 +1,152 @@
+use crate::dead_letter::{dead_letter_path, DeadLetterReport, DeadLetterWriter};
+use crate::restore::{ImpactAction, RestoreImpact, RestoreTarget};
+use crate::ui::models::weaviate_config::WeaviateConfig;
+use crate::weaviate_objects::request;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{debug, info};
+use serde_json::json;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Weaviate restore target implementation
+///
+/// Imports NDJSON objects with their vectors through the batch API, creating
+/// the class first if it does not exist.
+pub struct WeaviateRestoreTarget {
+    pub config: WeaviateConfig,
+    /// Objects the last restore rejected
+    dead_letters: Mutex<Option<DeadLetterReport>>,
+}
+
+impl WeaviateRestoreTarget {
+    /// Restore target for the given settings
+    pub fn new(config: WeaviateConfig) -> Self {
+        debug!("Creating Weaviate restore target");
+        Self { config, dead_letters: Mutex::new(None) }
+    }
+
+    /// The configured host, checked to be a URL
+    fn host(&self) -> Result<String> {
+        debug!("Getting Weaviate host");
+        let host = self.config.host.as_ref().ok_or_else(|| anyhow!("Weaviate host not specified"))?;
+        if !(host.starts_with("http://") || host.starts_with("https://")) {
+            return Err(anyhow!("Invalid Weaviate host URL: {}", host));
+        }
+        Ok(host.trim_end_matches('/').to_string())
+    }
+}
+
+#[async_trait]
+impl RestoreTarget for WeaviateRestoreTarget {
+    fn name(&self) -> &'static str {
+        debug!("Getting name for Weaviate restore target");
+        "Weaviate"
+    }
+
+    fn is_configured(&self) -> bool {
+        debug!("Checking if Weaviate target is configured");
+        self.config.host.is_some() && self.config.class.is_some()
+    }
+
+    fn required_fields(&self) -> Vec<&'static str> {
+        debug!("Getting required fields for Weaviate target");
+        vec!["host", "class"]
+    }
+
+    async fn restore_snapshot(
+        &self,
+        snapshot_path: &Path,
+        progress_callback: Option<Box<dyn Fn(f32) + Send + Sync>>,
+    ) -> Result<String> {
+        let host = self.host()?;
+        let class = self.config.target_class().ok_or_else(|| anyhow!("Weaviate class not specified"))?;
+        if let Some(ref callback) = progress_callback {
+            callback(0.0);
+        }
+
+        debug!("Restoring to Weaviate at {}, class {}", host, class);
+        let client = self.config.timeouts.http_client()?;
+        let path = dead_letter_path(Path::new("."), &class, chrono::Utc::now());
+        let mut dead_letters = DeadLetterWriter::new("weaviate", &host, &class, "objects", path);
+        let result = crate::weaviate_objects::import_objects(
+            &client,
+            &host,
+            &class,
+            &self.config.credentials(),
+            snapshot_path,
+            &self.config.mapping,
+            progress_callback.as_deref(),
+            &mut dead_letters,
+        ).await;
+        *self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()) = dead_letters.finish()?;
+
+        if let Some(ref callback) = progress_callback {
+            callback(1.0);
+        }
+        match result {
+            Ok(_) => {
+                info!("Restored to Weaviate class: {}", class);
+                Ok(class)
+            }
+            Err(e) => Err(anyhow!("Failed to restore to Weaviate: {}", e)),
+        }
+    }
+
+    async fn test_connection(&self) -> Result<String> {
+        debug!("Testing connection to Weaviate");
+        let host = self.host()?;
+        let client = self.config.timeouts.http_client()?;
+        let credentials = self.config.credentials();
+        let token = credentials.token(&client, &host).await?;
+        // The readiness check answers without credentials, so reading the schema also checks them
+        for url in [format!("{}/v1/.well-known/ready", host), format!("{}/v1/schema", host)] {
+            let response = request(&client, reqwest::Method::GET, &url, token.as_deref()).send().await
+                .map_err(|e| anyhow!("Failed to connect to Weaviate at {}: {}", host, e))?;
+            if !response.status().is_success() {
+                debug!("Weaviate returned status {} for {}", response.status(), url);
+                return Err(anyhow!("Weaviate at {} returned {}", host, response.status()));
+            }
+        }
+        Ok(format!("Successfully connected to Weaviate at {}{}", host, credentials.describe()))
+    }
+
+    async fn impact(&self) -> Result<Option<RestoreImpact>> {
+        debug!("Looking up the current state of the Weaviate class");
+        let host = self.host()?;
+        let class = self.config.target_class().ok_or_else(|| anyhow!("Weaviate class not specified"))?;
+
+        let client = self.config.timeouts.http_client()?;
+        let token = self.config.credentials().token(&client, &host).await?;
+        let response = request(&client, reqwest::Method::GET, &format!("{}/v1/schema/{}", host, class), token.as_deref()).send().await
+            .map_err(|e| anyhow!("Failed to read Weaviate class {}: {}", class, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Some(RestoreImpact::missing("class", &class, "objects")));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Weaviate returned {} for class {}", response.status(), class));
+        }
+        let query = json!({ "query": format!("{{ Aggregate {{ {} {{ meta {{ count }} }} }} }}", class) });
+        let response = request(&client, reqwest::Method::POST, &format!("{}/v1/graphql", host), token.as_deref())
+            .json(&query)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to count objects of Weaviate class {}: {}", class, e))?;
+        let counted: serde_json::Value = response.json().await.unwrap_or_default();
+        Ok(Some(RestoreImpact {
+            kind: "class",
+            name: class.clone(),
+            unit: "objects",
+            exists: true,
+            count: counted["data"]["Aggregate"][class.as_str()][0]["meta"]["count"].as_u64(),
+            size_bytes: None,
+            // Objects with the IDs of existing ones replace them
+            existing: ImpactAction::Append,
+        }))
+    }
+
+    fn dead_letters(&self) -> Option<DeadLetterReport> {
+        debug!("Getting objects rejected by the last Weaviate restore");
+        self.dead_letters.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
@@ -0,0 +1,402 @@
+// This module contains the OpenTelemetry and statsd exporters
+// Each phase of a restore (download, create_db, pg_restore, validation) is
+// recorded as a span. Phases started while a restore operation is open become
+// children of its span, so a restore shows up as one trace. Spans are sent to
+// an OTLP/HTTP collector as JSON when the operation ends, together with a
+// duration metric per phase, and each phase is also sent to statsd as a timer
+// and an ok/failed counter as soon as it ends. With no exporter configured,
+// spans cost next to nothing and are dropped.
+
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use serde_json::{json, Value};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Timeout of an export, so an unreachable collector cannot hold up exit
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where spans and metrics are sent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL, e.g. `http://localhost:4318`
+    pub otlp_endpoint: Option<String>,
+    /// Headers sent with every OTLP request, e.g. an API key
+    pub otlp_headers: Vec<(String, String)>,
+    /// statsd server address, e.g. `127.0.0.1:8125`
+    pub statsd: Option<String>,
+    /// `service.name` resource of the spans, and the statsd metric prefix
+    pub service_name: String,
+    /// W3C `traceparent` of a caller's trace that restores should join
+    pub traceparent: Option<String>,
+}
+
+impl TelemetryConfig {
+    /// Whether any exporter is configured
+    pub fn is_enabled(&self) -> bool {
+        self.otlp_endpoint.is_some() || self.statsd.is_some()
+    }
+}
+
+/// A finished span, ready to export
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanData {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(String, String)>,
+    /// Why the phase failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+impl SpanData {
+    /// How long the phase took
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+}
+
+/// Exporter state shared by all spans
+struct Telemetry {
+    config: TelemetryConfig,
+    /// Trace and span ID of the open operation, if any
+    current: Mutex<Option<(String, String)>>,
+    /// Finished spans not exported yet
+    finished: Mutex<Vec<SpanData>>,
+    /// Exports still in flight
+    exports: Mutex<Vec<JoinHandle<()>>>,
+    statsd: Option<std::net::UdpSocket>,
+}
+
+static TELEMETRY: OnceLock<Telemetry> = OnceLock::new();
+
+/// Exports the remaining spans when dropped at the end of `main`
+pub struct FlushGuard;
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        debug!("Flushing telemetry on exit");
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            tokio::task::block_in_place(|| handle.block_on(flush()));
+        }
+    }
+}
+
+/// Start exporting spans and metrics
+///
+/// Only the first call has an effect. Without an exporter in `config`
+/// nothing is recorded.
+///
+/// # Returns
+///
+/// A guard that exports what is left when it is dropped
+pub fn init(config: TelemetryConfig) -> Result<FlushGuard> {
+    debug!("Initializing telemetry: {:?}", config);
+    if !config.is_enabled() {
+        return Ok(FlushGuard);
+    }
+    let statsd = match &config.statsd {
+        Some(address) => {
+            let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+            socket.connect(address).map_err(|e| anyhow!("Invalid statsd address {}: {}", address, e))?;
+            socket.set_nonblocking(true)?;
+            Some(socket)
+        }
+        None => None,
+    };
+    let telemetry = Telemetry {
+        config,
+        current: Mutex::new(None),
+        finished: Mutex::new(Vec::new()),
+        exports: Mutex::new(Vec::new()),
+        statsd,
+    };
+    if TELEMETRY.set(telemetry).is_err() {
+        debug!("Telemetry already initialized");
+    }
+    Ok(FlushGuard)
+}
+
+/// Parse `KEY=VALUE,KEY=VALUE` headers, as in `OTEL_EXPORTER_OTLP_HEADERS`
+pub fn parse_headers(headers: &str) -> Result<Vec<(String, String)>> {
+    debug!("Parsing OTLP headers");
+    headers
+        .split(',')
+        .filter(|header| !header.trim().is_empty())
+        .map(|header| match header.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+            None => Err(anyhow!("Invalid OTLP header {}, expected KEY=VALUE", header)),
+        })
+        .collect()
+}
+
+/// Trace and parent span ID from a W3C `traceparent`, e.g. `00-<trace id>-<span id>-01`
+pub fn parse_traceparent(traceparent: &str) -> Option<(String, String)> {
+    debug!("Parsing traceparent {}", traceparent);
+    match traceparent.trim().split('-').collect::<Vec<_>>().as_slice() {
+        [_, trace_id, span_id, _]
+            if trace_id.len() == 32 && span_id.len() == 16
+                && trace_id.chars().chain(span_id.chars()).all(|c| c.is_ascii_hexdigit()) =>
+        {
+            Some((trace_id.to_lowercase(), span_id.to_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+/// A phase being timed, recorded when it ends
+///
+/// A span dropped without `end`, e.g. by a cancelled or failed `?`, is
+/// recorded as failed.
+#[must_use]
+pub struct Span {
+    data: Option<SpanData>,
+    /// Whether this span is the open operation's
+    operation: bool,
+}
+
+impl Span {
+    /// Start timing a phase, as a child of the open operation if there is one
+    pub fn start(name: &str) -> Self {
+        debug!("Starting span {}", name);
+        let Some(telemetry) = TELEMETRY.get() else {
+            return Self { data: None, operation: false };
+        };
+        let parent = telemetry.current.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let (trace_id, parent_span_id) = match parent {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => new_root(&telemetry.config),
+        };
+        Self { data: Some(new_span(name, trace_id, parent_span_id)), operation: false }
+    }
+
+    /// Start an operation, such as a restore, whose phases are recorded as its children
+    ///
+    /// An operation that is still open is replaced.
+    pub fn operation(name: &str) -> Self {
+        debug!("Starting operation span {}", name);
+        let Some(telemetry) = TELEMETRY.get() else {
+            return Self { data: None, operation: false };
+        };
+        let (trace_id, parent_span_id) = new_root(&telemetry.config);
+        let data = new_span(name, trace_id, parent_span_id);
+        *telemetry.current.lock().unwrap_or_else(|e| e.into_inner()) = Some((data.trace_id.clone(), data.span_id.clone()));
+        Self { data: Some(data), operation: true }
+    }
+
+    /// Add an attribute, e.g. the snapshot key
+    pub fn with(mut self, key: &str, value: impl ToString) -> Self {
+        if let Some(data) = &mut self.data {
+            data.attributes.push((key.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// End the phase with its outcome
+    pub fn end<T>(mut self, result: &Result<T>) {
+        debug!("Ending span, ok: {}", result.is_ok());
+        self.record(result.as_ref().err().map(|e| e.to_string()));
+    }
+
+    /// Record the span, then export the operation if this ends it
+    fn record(&mut self, error: Option<String>) {
+        let (Some(mut data), Some(telemetry)) = (self.data.take(), TELEMETRY.get()) else {
+            return;
+        };
+        data.end = SystemTime::now();
+        data.error = error;
+        send_statsd(telemetry, &data);
+        telemetry.finished.lock().unwrap_or_else(|e| e.into_inner()).push(data.clone());
+        if self.operation {
+            let mut current = telemetry.current.lock().unwrap_or_else(|e| e.into_inner());
+            if current.as_ref().is_some_and(|(_, span_id)| *span_id == data.span_id) {
+                *current = None;
+            }
+            drop(current);
+            spawn_export(telemetry);
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if self.data.is_some() {
+            self.record(Some("cancelled".to_string()));
+        }
+    }
+}
+
+/// Run a phase, timing it with `span`
+///
+/// # Returns
+///
+/// The phase's result, recorded as the span's outcome
+pub async fn instrument<T>(span: Span, phase: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    let result = phase.await;
+    span.end(&result);
+    result
+}
+
+/// Trace ID and parent span for a new trace, joining the configured `traceparent` if any
+fn new_root(config: &TelemetryConfig) -> (String, Option<String>) {
+    match config.traceparent.as_deref().and_then(parse_traceparent) {
+        Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+        None => (format!("{:032x}", rand::random::<u128>()), None),
+    }
+}
+
+fn new_span(name: &str, trace_id: String, parent_span_id: Option<String>) -> SpanData {
+    let now = SystemTime::now();
+    SpanData {
+        trace_id,
+        span_id: format!("{:016x}", rand::random::<u64>()),
+        parent_span_id,
+        name: name.to_string(),
+        start: now,
+        end: now,
+        attributes: Vec::new(),
+        error: None,
+    }
+}
+
+/// statsd lines for a finished span: a timer and an ok or failed counter
+pub fn statsd_lines(prefix: &str, span: &SpanData) -> Vec<String> {
+    debug!("Building statsd lines for span {}", span.name);
+    let outcome = if span.error.is_none() { "ok" } else { "failed" };
+    vec![
+        format!("{}.{}.duration:{}|ms", prefix, span.name, span.duration().as_millis()),
+        format!("{}.{}.{}:1|c", prefix, span.name, outcome),
+    ]
+}
+
+fn send_statsd(telemetry: &Telemetry, span: &SpanData) {
+    let Some(socket) = &telemetry.statsd else {
+        return;
+    };
+    // statsd is best effort, a lost packet only loses a data point
+    if let Err(e) = socket.send(statsd_lines(&telemetry.config.service_name, span).join("\n").as_bytes()) {
+        debug!("Failed to send statsd metrics: {}", e);
+    }
+}
+
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+fn attributes(pairs: &[(String, String)]) -> Vec<Value> {
+    pairs.iter().map(|(key, value)| json!({"key": key, "value": {"stringValue": value}})).collect()
+}
+
+fn resource(service_name: &str) -> Value {
+    json!({"attributes": attributes(&[("service.name".to_string(), service_name.to_string())])})
+}
+
+fn scope() -> Value {
+    json!({"name": "rustored", "version": env!("CARGO_PKG_VERSION")})
+}
+
+/// OTLP/JSON trace export request for finished spans
+pub fn traces_request(service_name: &str, spans: &[SpanData]) -> Value {
+    debug!("Building OTLP trace request for {} spans", spans.len());
+    let spans: Vec<Value> = spans.iter().map(|span| {
+        let mut value = json!({
+            "traceId": span.trace_id,
+            "spanId": span.span_id,
+            "name": span.name,
+            "kind": 1,
+            "startTimeUnixNano": nanos(span.start),
+            "endTimeUnixNano": nanos(span.end),
+            "attributes": attributes(&span.attributes),
+            "status": match &span.error {
+                None => json!({"code": 1}),
+                Some(error) => json!({"code": 2, "message": error}),
+            },
+        });
+        if let Some(parent) = &span.parent_span_id {
+            value["parentSpanId"] = json!(parent);
+        }
+        value
+    }).collect();
+    json!({"resourceSpans": [{"resource": resource(service_name), "scopeSpans": [{"scope": scope(), "spans": spans}]}]})
+}
+
+/// OTLP/JSON metrics export request with the duration of each finished span
+pub fn metrics_request(service_name: &str, spans: &[SpanData]) -> Value {
+    debug!("Building OTLP metrics request for {} spans", spans.len());
+    let points: Vec<Value> = spans.iter().map(|span| {
+        let outcome = if span.error.is_none() { "ok" } else { "failed" };
+        json!({
+            "asDouble": span.duration().as_secs_f64(),
+            "startTimeUnixNano": nanos(span.start),
+            "timeUnixNano": nanos(span.end),
+            "attributes": attributes(&[("phase".to_string(), span.name.clone()), ("outcome".to_string(), outcome.to_string())]),
+        })
+    }).collect();
+    let metric = json!({
+        "name": format!("{}.phase.duration", service_name),
+        "description": "Duration of each restore phase",
+        "unit": "s",
+        "gauge": {"dataPoints": points},
+    });
+    json!({"resourceMetrics": [{"resource": resource(service_name), "scopeMetrics": [{"scope": scope(), "metrics": [metric]}]}]})
+}
+
+/// Send finished spans and their metrics to the collector in the background
+fn spawn_export(telemetry: &'static Telemetry) {
+    let Some(endpoint) = telemetry.config.otlp_endpoint.clone() else {
+        telemetry.finished.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        return;
+    };
+    let spans = std::mem::take(&mut *telemetry.finished.lock().unwrap_or_else(|e| e.into_inner()));
+    if spans.is_empty() {
+        return;
+    }
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        debug!("No runtime to export {} spans on", spans.len());
+        return;
+    };
+    let export = handle.spawn(async move {
+        if let Err(e) = export(&telemetry.config, &endpoint, &spans).await {
+            warn!("Failed to export telemetry to {}: {}", endpoint, e);
+        }
+    });
+    telemetry.exports.lock().unwrap_or_else(|e| e.into_inner()).push(export);
+}
+
+async fn export(config: &TelemetryConfig, endpoint: &str, spans: &[SpanData]) -> Result<()> {
+    debug!("Exporting {} spans to {}", spans.len(), endpoint);
+    let client = reqwest::Client::builder().timeout(EXPORT_TIMEOUT).build()?;
+    let base = endpoint.trim_end_matches('/');
+    let requests = [
+        ("traces", traces_request(&config.service_name, spans)),
+        ("metrics", metrics_request(&config.service_name, spans)),
+    ];
+    for (signal, body) in requests {
+        let mut request = client.post(format!("{}/v1/{}", base, signal)).json(&body);
+        for (key, value) in &config.otlp_headers {
+            request = request.header(key, value);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("collector returned {} for {}", response.status(), signal));
+        }
+    }
+    Ok(())
+}
+
+/// Export every finished span and wait for exports in flight
+pub async fn flush() {
+    debug!("Flushing telemetry");
+    let Some(telemetry) = TELEMETRY.get() else {
+        return;
+    };
+    spawn_export(telemetry);
+    let exports = std::mem::take(&mut *telemetry.exports.lock().unwrap_or_else(|e| e.into_inner()));
+    for export in exports {
+        let _ = export.await;
+    }
+}
@@ -0,0 +1,520 @@
+// This module contains the table of contents of PostgreSQL archives, used to
+// restore only some of their tables. pg_restore can include tables with `-t`
+// but cannot exclude them, and `-t` leaves out their indexes and constraints,
+// so selective restores hand pg_restore a filtered `--use-list` instead. The
+// entries of `pg_restore --list` do not say which table an index, constraint
+// or comment belongs to, so that is read from the statements in the archive's
+// schema-only script.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Entry types that are relations, which can be chosen for a selective restore
+pub const RELATIONS: &[&str] = &["TABLE", "VIEW", "MATERIALIZED VIEW", "FOREIGN TABLE"];
+
+/// Entry types that hold the data or partition of the relation named by their tag
+const RELATION_PARTS: &[&str] = &["TABLE DATA", "MATERIALIZED VIEW DATA", "TABLE ATTACH"];
+
+/// Entry types of more than one word, each before any type it starts with
+const MULTI_WORD_TYPES: &[&str] = &[
+    "PUBLICATION TABLES IN SCHEMA",
+    "TEXT SEARCH CONFIGURATION",
+    "TEXT SEARCH DICTIONARY",
+    "MATERIALIZED VIEW DATA",
+    "FOREIGN DATA WRAPPER",
+    "TEXT SEARCH TEMPLATE",
+    "DATABASE PROPERTIES",
+    "PROCEDURAL LANGUAGE",
+    "SEQUENCE OWNED BY",
+    "PUBLICATION TABLE",
+    "TEXT SEARCH PARSER",
+    "MATERIALIZED VIEW",
+    "SUBSCRIPTION TABLE",
+    "CHECK CONSTRAINT",
+    "OPERATOR FAMILY",
+    "OPERATOR CLASS",
+    "SECURITY LABEL",
+    "ACCESS METHOD",
+    "EVENT TRIGGER",
+    "FK CONSTRAINT",
+    "FOREIGN TABLE",
+    "INDEX ATTACH",
+    "LARGE OBJECT",
+    "ROW SECURITY",
+    "SEQUENCE SET",
+    "TABLE ATTACH",
+    "USER MAPPING",
+    "DEFAULT ACL",
+    "SHELL TYPE",
+    "TABLE DATA",
+];
+
+/// A table, view or other relation, by schema and name
+pub type Relation = (String, String);
+
+/// One entry of `pg_restore --list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    /// The line as listed, handed back to pg_restore in the use-list
+    pub line: String,
+    /// Entry type, e.g. `TABLE DATA` or `FK CONSTRAINT`
+    pub desc: String,
+    /// Schema of the entry, `None` for objects outside a schema
+    pub schema: Option<String>,
+    /// Name of the entry as listed, e.g. `orders orders_pkey` for a constraint
+    pub tag: String,
+    /// Relation the entry belongs to, if any
+    pub relation: Option<Relation>,
+    /// Relation a foreign key entry references, which must be restored too
+    pub references: Option<Relation>,
+}
+
+/// Table of contents of a custom or directory-format archive
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Toc {
+    /// Comment lines of the listing and its entries, in listing order
+    pub lines: Vec<Result<TocEntry, String>>,
+}
+
+/// Whether a `--include-table`/`--exclude-table` pattern names a relation
+///
+/// `schema.name` names one relation, `name` a relation of that name in any
+/// schema and `schema.*` every relation in a schema.
+pub fn pattern_matches(pattern: &str, (schema, name): (&str, &str)) -> bool {
+    match pattern.split_once('.') {
+        Some((pattern_schema, "*")) => pattern_schema == schema,
+        Some((pattern_schema, pattern_name)) if pattern_schema == schema && pattern_name == name => true,
+        _ => pattern == name,
+    }
+}
+
+/// Whether a relation is restored with the given include and exclude patterns
+pub fn is_selected(include: &[String], exclude: &[String], relation: (&str, &str)) -> bool {
+    (include.is_empty() || include.iter().any(|pattern| pattern_matches(pattern, relation)))
+        && !exclude.iter().any(|pattern| pattern_matches(pattern, relation))
+}
+
+/// Read a possibly double-quoted, possibly schema-qualified identifier
+///
+/// # Returns
+///
+/// The parts of the name, unquoted, or nothing if `text` does not start with one
+fn qualified_name(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut chars = text.trim_start().chars().peekable();
+    loop {
+        let mut part = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match (c, chars.peek()) {
+                    ('"', Some('"')) => {
+                        part.push('"');
+                        chars.next();
+                    }
+                    ('"', _) => break,
+                    (c, _) => part.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                    break;
+                }
+                part.push(c);
+                chars.next();
+            }
+            if part.is_empty() {
+                break;
+            }
+        }
+        parts.push(part);
+        if chars.peek() != Some(&'.') {
+            break;
+        }
+        chars.next();
+    }
+    parts
+}
+
+/// Relation named right after `marker` in a statement, such as the table of `ALTER TABLE ONLY`
+fn relation_after(statement: &str, marker: &str) -> Option<Relation> {
+    let rest = &statement[statement.find(marker)? + marker.len()..];
+    let rest = rest.strip_prefix("ONLY ").unwrap_or(rest);
+    match qualified_name(rest).as_slice() {
+        [schema, name, ..] => Some((schema.clone(), name.clone())),
+        _ => None,
+    }
+}
+
+/// Relation a schema-only script statement of the given entry type belongs to
+fn statement_relation(desc: &str, statement: &str) -> Option<Relation> {
+    match desc {
+        "CONSTRAINT" | "FK CONSTRAINT" | "CHECK CONSTRAINT" | "DEFAULT" | "ROW SECURITY" => relation_after(statement, "ALTER TABLE "),
+        "INDEX" | "POLICY" | "TRIGGER" => relation_after(statement, " ON "),
+        "RULE" => relation_after(statement, " TO "),
+        "STATISTICS" => relation_after(statement, " FROM "),
+        "SEQUENCE OWNED BY" => relation_after(statement, " OWNED BY "),
+        "COMMENT" => relation_after(statement, "COMMENT ON COLUMN ")
+            .or_else(|| relation_after(statement, "COMMENT ON TABLE "))
+            // Constraints, triggers, policies and rules are named with the table after them
+            .or_else(|| {
+                ["CONSTRAINT ", "TRIGGER ", "POLICY ", "RULE "]
+                    .iter()
+                    .find_map(|kind| statement.strip_prefix("COMMENT ON ")?.strip_prefix(kind))
+                    .and_then(|rest| relation_after(rest, " ON "))
+            }),
+        "ACL" => relation_after(statement, " ON TABLE "),
+        _ => None,
+    }
+}
+
+/// Relations of the entries in a schema-only script, keyed by entry type, schema and name
+///
+/// Each entry of the script starts with a `-- Name: ...; Type: ...; Schema: ...` comment.
+fn script_relations(script: &str) -> HashMap<(String, String, String), (Relation, Option<Relation>)> {
+    debug!("Reading relations of {} bytes of schema script", script.len());
+    let mut relations = HashMap::new();
+    let mut current: Option<(String, String, String)> = None;
+    let mut statement = String::new();
+    let mut finish = |current: &mut Option<(String, String, String)>, statement: &mut String| {
+        if let Some(key) = current.take() {
+            if let Some(relation) = statement_relation(&key.0, statement) {
+                let references = (key.0 == "FK CONSTRAINT").then(|| relation_after(statement, " REFERENCES ")).flatten();
+                relations.entry(key).or_insert((relation, references));
+            }
+        }
+        statement.clear();
+    };
+    for line in script.lines() {
+        if let Some(header) = line.strip_prefix("-- Name: ") {
+            finish(&mut current, &mut statement);
+            let fields: Vec<&str> = header.split("; ").collect();
+            if let [name, desc, schema, ..] = fields.as_slice() {
+                if let (Some(desc), Some(schema)) = (desc.strip_prefix("Type: "), schema.strip_prefix("Schema: ")) {
+                    current = Some((desc.to_string(), schema.to_string(), name.to_string()));
+                }
+            }
+        } else if !line.starts_with("--") && !line.trim().is_empty() {
+            statement.push_str(line.trim());
+            statement.push(' ');
+        }
+    }
+    finish(&mut current, &mut statement);
+    relations
+}
+
+/// Parse one entry line of `pg_restore --list`, e.g. `216; 1259 16520 TABLE public orders owner`
+fn parse_entry(line: &str) -> Option<TocEntry> {
+    let (_, rest) = line.split_once("; ")?;
+    // Table OID and object OID come before the entry type
+    let rest = rest.splitn(3, ' ').nth(2)?;
+    let desc = MULTI_WORD_TYPES
+        .iter()
+        .find(|desc| rest.starts_with(&format!("{} ", desc)))
+        .map(|desc| desc.to_string())
+        .unwrap_or_else(|| rest.split(' ').next().unwrap_or_default().to_string());
+    let mut words: Vec<&str> = rest[desc.len()..].split_whitespace().collect();
+    let schema = (!words.is_empty()).then(|| words.remove(0)).filter(|schema| *schema != "-").map(str::to_string);
+    // The owner comes last, the name may have spaces
+    words.pop();
+    Some(TocEntry { line: line.to_string(), desc, schema, tag: words.join(" "), relation: None, references: None })
+}
+
+impl Toc {
+    /// Build the table of contents from `pg_restore --list` and the schema-only script
+    ///
+    /// # Arguments
+    ///
+    /// * `list` - Output of `pg_restore --list`
+    /// * `script` - Output of `pg_restore --schema-only --file -`
+    pub fn parse(list: &str, script: &str) -> Self {
+        debug!("Parsing table of contents of {} lines", list.lines().count());
+        let relations = script_relations(script);
+        let lines = list
+            .lines()
+            .map(|line| {
+                let entry = if line.trim_start().starts_with(';') { None } else { parse_entry(line) };
+                let Some(mut entry) = entry else {
+                    return Err(line.to_string());
+                };
+                let schema = entry.schema.clone().unwrap_or_else(|| "-".to_string());
+                if RELATIONS.contains(&entry.desc.as_str()) || RELATION_PARTS.contains(&entry.desc.as_str()) {
+                    entry.relation = entry.schema.clone().map(|schema| (schema, entry.tag.clone()));
+                } else if let Some((relation, references)) = relations.get(&(entry.desc.clone(), schema, entry.tag.clone())) {
+                    entry.relation = Some(relation.clone());
+                    entry.references = references.clone();
+                }
+                Ok(entry)
+            })
+            .collect();
+        Self { lines }
+    }
+
+    /// Read the table of contents of an archive with pg_restore
+    ///
+    /// The archive must be a custom-format file or a directory-format dump;
+    /// compressed and packed snapshots are prepared with `backup::restore_input`.
+    pub async fn read(archive: &Path) -> Result<Self> {
+        debug!("Reading table of contents of {:?}", archive);
+        let run = |args: &[&str]| {
+            let mut cmd = tokio::process::Command::new("pg_restore");
+            cmd.args(args).arg(archive).kill_on_drop(true);
+            cmd
+        };
+        let list = run(&["--list"]).output().await.context("Failed to execute pg_restore")?;
+        if !list.status.success() {
+            return Err(anyhow!(
+                "Cannot read the table of contents of {}, only custom and directory-format dumps have one: {}",
+                archive.display(),
+                String::from_utf8_lossy(&list.stderr).trim()
+            ));
+        }
+        let script = run(&["--schema-only", "--file", "-"]).output().await.context("Failed to execute pg_restore")?;
+        if !script.status.success() {
+            return Err(anyhow!("Failed to read the schema of {}: {}", archive.display(), String::from_utf8_lossy(&script.stderr).trim()));
+        }
+        Ok(Self::parse(&String::from_utf8_lossy(&list.stdout), &String::from_utf8_lossy(&script.stdout)))
+    }
+
+    /// Entries of the archive, without the comment lines
+    pub fn entries(&self) -> impl Iterator<Item = &TocEntry> {
+        self.lines.iter().filter_map(|line| line.as_ref().ok())
+    }
+
+    /// Tables, views and other relations in the archive, sorted by schema and name
+    pub fn relations(&self) -> Vec<Relation> {
+        let mut relations: Vec<Relation> = self
+            .entries()
+            .filter(|entry| RELATIONS.contains(&entry.desc.as_str()))
+            .filter_map(|entry| entry.relation.clone())
+            .collect();
+        relations.sort();
+        relations.dedup();
+        relations
+    }
+
+    /// Build a `--use-list` restoring only the selected relations
+    ///
+    /// Entries belonging to other relations are commented out, and so are
+    /// foreign keys referencing them; entries that belong to no relation, such
+    /// as schemas, functions and sequences, are always restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `include` - Patterns of the relations to restore, all if empty
+    /// * `exclude` - Patterns of the relations to leave out
+    ///
+    /// # Returns
+    ///
+    /// The list, or an error naming a pattern that matches no relation
+    pub fn use_list(&self, include: &[String], exclude: &[String]) -> Result<String> {
+        debug!("Building use-list including {:?} and excluding {:?}", include, exclude);
+        self.check_selection(include, exclude)?;
+        let mut list = String::new();
+        for line in &self.lines {
+            match line {
+                Ok(entry) if !is_restored(entry, include, exclude) => {
+                    list.push(';');
+                    list.push_str(&entry.line);
+                }
+                Ok(entry) => list.push_str(&entry.line),
+                Err(comment) => list.push_str(comment),
+            }
+            list.push('\n');
+        }
+        Ok(list)
+    }
+
+    /// Check that every pattern names a relation and that some relation is left to restore
+    fn check_selection(&self, include: &[String], exclude: &[String]) -> Result<()> {
+        debug!("Checking table selection including {:?} and excluding {:?}", include, exclude);
+        let relations = self.relations();
+        for (flag, pattern) in include.iter().map(|p| ("--include-table", p)).chain(exclude.iter().map(|p| ("--exclude-table", p))) {
+            if !relations.iter().any(|(schema, name)| pattern_matches(pattern, (schema, name))) {
+                return Err(anyhow!("{} {} matches no table in the snapshot", flag, pattern));
+            }
+        }
+        let selected = relations.iter().filter(|(schema, name)| is_selected(include, exclude, (schema, name))).count();
+        if selected == 0 {
+            return Err(anyhow!("The table selection leaves no table to restore"));
+        }
+        info!("Restoring {} of {} tables", selected, relations.len());
+        Ok(())
+    }
+
+    /// Describe the objects a restore would create, for a dry run
+    ///
+    /// # Arguments
+    ///
+    /// * `include` - Patterns of the relations to restore, all if empty
+    /// * `exclude` - Patterns of the relations to leave out
+    ///
+    /// # Returns
+    ///
+    /// A count of the objects of each type, then one line per object, e.g. `INDEX public.orders_customer_idx`
+    pub fn preview(&self, include: &[String], exclude: &[String]) -> Result<Vec<String>> {
+        debug!("Previewing restore including {:?} and excluding {:?}", include, exclude);
+        if !include.is_empty() || !exclude.is_empty() {
+            self.check_selection(include, exclude)?;
+        }
+        let (restored, left_out): (Vec<&TocEntry>, Vec<&TocEntry>) = self.entries().partition(|entry| is_restored(entry, include, exclude));
+        // Types in the order the archive restores them
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for entry in &restored {
+            match counts.iter_mut().find(|(desc, _)| *desc == entry.desc) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&entry.desc, 1)),
+            }
+        }
+        let counts: Vec<String> = counts.iter().map(|(desc, count)| format!("{} {}", count, desc)).collect();
+        let mut lines = vec![format!("{} objects: {}", restored.len(), counts.join(", "))];
+        if !left_out.is_empty() {
+            lines.push(format!("{} left out by the table selection", left_out.len()));
+        }
+        lines.extend(restored.iter().map(|entry| match &entry.schema {
+            Some(schema) => format!("{} {}.{}", entry.desc, schema, entry.tag),
+            None => format!("{} {}", entry.desc, entry.tag),
+        }));
+        Ok(lines)
+    }
+}
+
+/// Whether pg_restore restores an entry with the given include and exclude patterns
+///
+/// Entries of relations left out are not, and neither are foreign keys
+/// referencing them; entries that belong to no relation always are.
+fn is_restored(entry: &TocEntry, include: &[String], exclude: &[String]) -> bool {
+    !entry.relation.iter().chain(&entry.references).any(|(schema, name)| !is_selected(include, exclude, (schema, name)))
+}
+
+/// Preview the objects restoring a downloaded snapshot would create
+///
+/// Compressed and packed snapshots are prepared as for a restore. Nothing is
+/// read from or written to the target server.
+///
+/// # Arguments
+///
+/// * `path` - Downloaded snapshot
+/// * `include` - Patterns of the relations to restore, all if empty
+/// * `exclude` - Patterns of the relations to leave out
+pub async fn preview_snapshot(path: &str, include: &[String], exclude: &[String]) -> Result<Vec<String>> {
+    debug!("Previewing restore of {}", path);
+    let input = crate::backup::restore_input(path).await?;
+    Toc::read(&input.path).await?.preview(include, exclude)
+}
+
+/// A row of the table picker: a schema, or a table within the schema above it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRow {
+    pub schema: String,
+    /// Name of the relation, `None` for the schema's own row
+    pub name: Option<String>,
+    pub ticked: bool,
+}
+
+/// Schemas and tables of a downloaded snapshot, ticked for a selective restore
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSelection {
+    /// Downloaded snapshot the table of contents was read from
+    pub path: String,
+    pub rows: Vec<TableRow>,
+    /// Highlighted row
+    pub cursor: usize,
+}
+
+impl TableSelection {
+    /// Rows for the relations of a table of contents, ticked as the patterns select them
+    pub fn new(path: &str, relations: &[Relation], include: &[String], exclude: &[String]) -> Self {
+        debug!("Building table selection of {} relations", relations.len());
+        let mut rows: Vec<TableRow> = Vec::new();
+        for (schema, name) in relations {
+            if rows.last().is_none_or(|row| row.schema != *schema) {
+                rows.push(TableRow { schema: schema.clone(), name: None, ticked: false });
+            }
+            rows.push(TableRow { schema: schema.clone(), name: Some(name.clone()), ticked: is_selected(include, exclude, (schema, name)) });
+        }
+        let mut selection = Self { path: path.to_string(), rows, cursor: 0 };
+        selection.tick_schemas();
+        selection
+    }
+
+    /// Tick the schema rows whose tables are all ticked
+    fn tick_schemas(&mut self) {
+        for i in 0..self.rows.len() {
+            if self.rows[i].name.is_none() {
+                let schema = self.rows[i].schema.clone();
+                self.rows[i].ticked = self.rows.iter().filter(|row| row.schema == schema && row.name.is_some()).all(|row| row.ticked);
+            }
+        }
+    }
+
+    /// Move the highlight by `delta` rows
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self.cursor.saturating_add_signed(delta).min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Toggle the highlighted table, or every table of the highlighted schema
+    pub fn toggle(&mut self) {
+        let Some(row) = self.rows.get(self.cursor).cloned() else {
+            return;
+        };
+        let ticked = !row.ticked;
+        for other in &mut self.rows {
+            if other.schema == row.schema && (row.name.is_none() || other.name == row.name) {
+                other.ticked = ticked;
+            }
+        }
+        self.tick_schemas();
+    }
+
+    /// Tick every table, or none if all are ticked
+    pub fn toggle_all(&mut self) {
+        let ticked = !self.rows.iter().all(|row| row.ticked);
+        self.rows.iter_mut().for_each(|row| row.ticked = ticked);
+    }
+
+    /// Include patterns restoring the ticked tables, empty when all are ticked
+    ///
+    /// A fully ticked schema becomes a `schema.*` pattern.
+    pub fn include_patterns(&self) -> Vec<String> {
+        if self.rows.iter().all(|row| row.ticked) {
+            return Vec::new();
+        }
+        self.rows
+            .iter()
+            .filter(|row| row.ticked)
+            .filter(|row| row.name.is_none() || !self.rows.iter().any(|schema| schema.name.is_none() && schema.schema == row.schema && schema.ticked))
+            .map(|row| match &row.name {
+                None => format!("{}.*", row.schema),
+                Some(name) => format!("{}.{}", row.schema, name),
+            })
+            .collect()
+    }
+
+    /// Number of ticked tables and of all tables
+    pub fn counts(&self) -> (usize, usize) {
+        let tables = self.rows.iter().filter(|row| row.name.is_some());
+        (tables.clone().filter(|row| row.ticked).count(), tables.count())
+    }
+}
+
+/// Write a use-list for the selected tables next to the archive
+///
+/// # Returns
+///
+/// The temporary file holding the list, removed when dropped, or `None` when
+/// every table is restored
+pub async fn write_use_list(archive: &Path, include: &[String], exclude: &[String]) -> Result<Option<tempfile::NamedTempFile>> {
+    debug!("Writing use-list for {:?}", archive);
+    if include.is_empty() && exclude.is_empty() {
+        return Ok(None);
+    }
+    let list = Toc::read(archive).await?.use_list(include, exclude)?;
+    let dir = archive.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file = tempfile::Builder::new().suffix(".list").tempfile_in(dir)?;
+    std::fs::write(file.path(), list)?;
+    Ok(Some(file))
+}
@@ -1,29 +1,131 @@
 use log::debug;
+use std::future::Future;
 use std::time::Duration;
 use anyhow::Result;
-use crossterm::event::{self, Event};
+use crossterm::event::{self, Event, KeyCode, KeyEvent};
 use ratatui::backend::Backend;
 use ratatui::Terminal;
 
+use crate::cancel::CancellationToken;
 use crate::ui::rustored::RustoredApp;
 
+/// Run an operation, cancelling `token` if Esc is pressed before it finishes
+///
+/// The main loop does not read keys while an operation is awaited, so the
+/// keyboard is watched on a blocking thread for the duration.
+pub async fn cancel_on_escape<T>(token: &CancellationToken, operation: impl Future<Output = T>) -> T {
+    debug!("Watching for Esc while an operation runs");
+    let done = token.child_token();
+    let watcher = {
+        let token = token.clone();
+        let done = done.clone();
+        tokio::task::spawn_blocking(move || {
+            while !done.is_cancelled() {
+                match event::poll(Duration::from_millis(100)) {
+                    Ok(true) => {
+                        if let Ok(Event::Key(key)) = event::read() {
+                            if key.code == KeyCode::Esc {
+                                debug!("Esc pressed, cancelling operation");
+                                token.cancel();
+                            }
+                        }
+                    }
+                    Ok(false) => {}
+                    // No terminal to watch, e.g. in tests
+                    Err(_) => break,
+                }
+            }
+        })
+    };
+    let result = operation.await;
+    done.cancel();
+    let _ = watcher.await;
+    result
+}
+
+/// Next key for the main loop: from a replayed recording, or pressed within 100ms
+async fn next_key(app: &mut RustoredApp) -> Result<Option<KeyEvent>> {
+    if let Some(replay) = app.replay.as_mut() {
+        return Ok(replay.next_key().await);
+    }
+    if event::poll(Duration::from_millis(100))? {
+        if let Event::Key(key) = event::read()? {
+            return Ok(Some(key));
+        }
+    }
+    Ok(None)
+}
+
 /// Run the TUI application, delegating to RustoredApp
 pub async fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut RustoredApp) -> Result<Option<String>> {
+    // Show the last listing straight away, marked stale, while the live one loads
+    if app.snapshot_browser.show_cached_listing() {
+        app.snapshot_browser.refreshing = true;
+        terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
+    }
+
     // Initial load of snapshots
     if let Err(e) = app.snapshot_browser.load_snapshots().await {
         debug!("Failed to load snapshots: {}", e);
     }
 
+    // When the handling of the last key started, until its operation has ended
+    let mut handling: Option<std::time::Instant> = None;
     loop {
+        // A signal cancels everything, including the TUI itself
+        if app.cancel.is_cancelled() {
+            debug!("Cancelled by signal, leaving the TUI");
+            return Ok(None);
+        }
+        if app.replay.as_ref().is_some_and(|replay| replay.finished()) {
+            debug!("Replayed every recorded key, leaving the TUI");
+            return Ok(None);
+        }
+
+        // A long operation that ended waiting for the operator is signalled before the frame is drawn
+        if let Some(started) = handling.take() {
+            app.attention.operation_finished(&app.popup_state, started.elapsed());
+        }
+
         // Draw UI
         terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
 
         // Handle events
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                // Pass the key event to the app
-                if let Some(snapshot_path) = app.handle_key_event::<B>(key).await? {
-                    return Ok(Some(snapshot_path));
+        if let Some(key) = next_key(app).await? {
+            app.attention.acknowledge();
+            handling = Some(std::time::Instant::now());
+            // Pass the key event to the app
+            crate::ui::recording::before_key(app, &key);
+            let handled = app.handle_key_event::<B>(key).await;
+            crate::ui::recording::after_key(app);
+            if let Some(snapshot_path) = handled? {
+                // A downloaded snapshot is restored with the confirmed options
+                if snapshot_path != "quit" {
+                    // A local file or a download made to pick tables is the snapshot it was made of
+                    let downloaded = app.downloaded.as_ref().filter(|(_, path)| *path == snapshot_path).map(|(snapshot, _)| snapshot.clone());
+                    let selected = downloaded.or_else(|| app.snapshot_browser.snapshots.get(app.snapshot_browser.selected_index).cloned());
+                    if let Some(snapshot) = selected {
+                        if let Err(e) = app.restore_snapshot(&snapshot, terminal, &snapshot_path).await {
+                            debug!("Restore failed: {}", e);
+                            app.popup_state = crate::ui::models::PopupState::Error(format!("Restore failed: {}", e));
+                        }
+                        continue;
+                    }
+                }
+                return Ok(Some(snapshot_path));
+            }
+            // A confirmed bundle is downloaded and restored with its progress drawn
+            if let Some(members) = app.pending_bundle.take() {
+                if let Err(e) = app.restore_bundle(&members, terminal).await {
+                    debug!("Bundle restore failed: {}", e);
+                    app.popup_state = crate::ui::models::PopupState::Error(format!("Bundle restore failed: {}", e));
+                }
+            }
+            // Confirmed marked snapshots are restored one after another, each with its progress drawn
+            if let Some(snapshots) = app.pending_batch.take() {
+                if let Err(e) = crate::ui::batch::restore_batch(app, &snapshots, terminal).await {
+                    debug!("Batch restore failed: {}", e);
+                    app.popup_state = crate::ui::models::PopupState::Error(format!("Batch restore failed: {}", e));
                 }
             }
         }
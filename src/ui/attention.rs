@@ -0,0 +1,130 @@
+// This module contains the attention signal of the Rustored TUI
+// Downloads and restores can run for hours while the operator works in another
+// window. When one ends on a popup that waits for them, such as an error, the
+// typed confirmation of overwriting a target that holds data, or a production
+// approval, the TUI would otherwise sit there silently. So when an operation
+// that took long enough leaves the TUI waiting, the terminal bell rings once,
+// which most terminals and tmux turn into a tab or window alert, and the
+// status bar flashes until the next key is pressed.
+//
+// The bell is written from the main loop between frames, like the terminal
+// title, so it never lands in the middle of a frame's output.
+
+use crate::ui::models::PopupState;
+use log::{debug, info, warn};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Operations shorter than this end without a signal, as the operator is still watching
+pub const DEFAULT_ATTENTION_AFTER: Duration = Duration::from_secs(10);
+
+/// How long the status bar stays lit, and then unlit, while flashing
+const FLASH_PERIOD: Duration = Duration::from_millis(500);
+
+/// What a popup waits for from the operator, or `None` while an operation is still running
+pub fn waiting_for(popup: &PopupState) -> Option<&'static str> {
+    debug!("Checking whether the popup waits for input");
+    match popup {
+        PopupState::Hidden
+        | PopupState::Downloading(..)
+        | PopupState::Restoring(..)
+        | PopupState::RestoringBundle(..)
+        | PopupState::RestoringBatch(..)
+        | PopupState::TestingS3
+        | PopupState::TestingPg => None,
+        PopupState::Error(_) => Some("an error needs attention"),
+        PopupState::ConfirmImpact(..) => Some("confirm overwriting the target"),
+        PopupState::ConfirmProduction(..) => Some("production approval"),
+        PopupState::RestoreSummary(_)
+        | PopupState::Success(_)
+        | PopupState::TestS3Result(_)
+        | PopupState::TestPgResult(_)
+        | PopupState::PresignedLink(..)
+        | PopupState::GeneratedCommand(..)
+        | PopupState::ScheduleStatus(_) => Some("finished"),
+        PopupState::ConfirmRestore(_)
+        | PopupState::ConfirmArchiveRestore(..)
+        | PopupState::ConfirmCancel(..)
+        | PopupState::ConfirmPromote(_)
+        | PopupState::ConfirmDelete(..)
+        | PopupState::ConfirmBundleRestore(..)
+        | PopupState::ConfirmBatchRestore(..)
+        | PopupState::RestorePlan(..) => Some("confirmation"),
+        PopupState::SelectRecipes(..)
+        | PopupState::SelectServer(..)
+        | PopupState::SelectTables(..)
+        | PopupState::RestorePreview(..)
+        | PopupState::VersionHistory(..)
+        | PopupState::LocalFile(..)
+        | PopupState::RecoverTarget(..)
+        | PopupState::PresignDuration(..) => Some("input"),
+    }
+}
+
+/// Rings the bell and flashes the status bar when a long operation leaves the TUI waiting
+#[derive(Debug)]
+pub struct Attention {
+    /// Ring the terminal bell, off for `--no-bell` and when the TUI does not own a terminal
+    bell: bool,
+    /// How long an operation must have taken for its end to be signalled
+    after: Duration,
+    /// What the TUI waits for and when it started to, while the signal is on
+    pending: Option<(&'static str, Instant)>,
+}
+
+impl Default for Attention {
+    fn default() -> Self {
+        Self::new(false, DEFAULT_ATTENTION_AFTER)
+    }
+}
+
+impl Attention {
+    /// Signal operations that took at least `after`, ringing the bell if `bell` is set
+    pub fn new(bell: bool, after: Duration) -> Self {
+        debug!("Creating attention signal with bell {} after {:?}", bell, after);
+        Self { bell, after, pending: None }
+    }
+
+    /// Signal the end of an operation if it took long enough and left the TUI waiting
+    ///
+    /// # Arguments
+    ///
+    /// * `popup` - The popup shown once the operation ended
+    /// * `elapsed` - How long the operation took
+    ///
+    /// # Returns
+    ///
+    /// Whether the signal was raised
+    pub fn operation_finished(&mut self, popup: &PopupState, elapsed: Duration) -> bool {
+        debug!("Operation finished after {:?}", elapsed);
+        if elapsed < self.after {
+            return false;
+        }
+        let Some(reason) = waiting_for(popup) else {
+            return false;
+        };
+        info!("Waiting for the operator after {:?}: {}", elapsed, reason);
+        self.pending = Some((reason, Instant::now()));
+        if self.bell {
+            let mut stdout = std::io::stdout();
+            if let Err(e) = stdout.write_all(b"\x07").and_then(|_| stdout.flush()) {
+                warn!("Failed to ring the terminal bell: {}", e);
+            }
+        }
+        true
+    }
+
+    /// The operator pressed a key, so they have noticed
+    pub fn acknowledge(&mut self) {
+        if self.pending.take().is_some() {
+            debug!("Attention signal acknowledged");
+        }
+    }
+
+    /// What the TUI waits for, and whether the status bar is lit at `now`, while the signal is on
+    pub fn flash(&self, now: Instant) -> Option<(&'static str, bool)> {
+        let (reason, since) = self.pending?;
+        let periods = now.saturating_duration_since(since).as_millis() / FLASH_PERIOD.as_millis();
+        Some((reason, periods.is_multiple_of(2)))
+    }
+}
@@ -0,0 +1,208 @@
+// This module contains batch restores of marked snapshots in the Rustored TUI
+// `Space` marks snapshots in the list and `Q` restores them one after another,
+// each into its own database, index or collection so no restore overwrites the
+// one before it. Every item goes through the same download or stream and
+// restore as a single snapshot, with its own preset; a failed item is recorded
+// and the batch moves on, while Esc aborts the rest of it.
+
+use crate::presets::RestoreMode;
+use crate::restore::RestoreOutcome;
+use crate::ui::guard_rails::{self, GuardedAction};
+use crate::ui::models::{BackupMetadata, PopupState, RestoreTarget};
+use crate::ui::rustored::RustoredApp;
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+use ratatui::backend::Backend;
+use ratatui::Terminal;
+use std::future::Future;
+use std::time::Duration;
+
+/// Names the items of a batch are restored into, in order
+///
+/// Each item goes into the configured target database, index or collection
+/// with `-<n>` appended. `None` means a PostgreSQL restore without a target
+/// database, which creates a new, randomly named database for every item.
+///
+/// # Arguments
+///
+/// * `app` - The RustoredApp, holding the configured names
+/// * `count` - How many snapshots are in the batch
+pub fn target_names(app: &RustoredApp, count: usize) -> Vec<Option<String>> {
+    debug!("Naming targets of a batch of {} restores", count);
+    let base = match app.restore_target {
+        RestoreTarget::Postgres => app.pg_config.target_db.clone(),
+        RestoreTarget::Elasticsearch => app.es_config.index.clone(),
+        RestoreTarget::Qdrant => app.qdrant_config.collection.clone(),
+        RestoreTarget::OpenSearch => app.os_config.index.clone(),
+        RestoreTarget::Meilisearch => app.meili_config.index.clone(),
+        RestoreTarget::Weaviate => app.weaviate_config.class.clone(),
+        RestoreTarget::Milvus => app.milvus_config.collection.clone(),
+        RestoreTarget::DynamoDb => app.dynamodb_config.table.clone(),
+        RestoreTarget::InfluxDb => app.influxdb_config.bucket.clone(),
+    };
+    (1..=count).map(|n| base.as_ref().map(|base| format!("{}-{}", base, n))).collect()
+}
+
+/// Point the restore target at the name a batch item is restored into
+fn set_target_name(app: &mut RustoredApp, name: Option<String>) {
+    debug!("Setting batch restore target name to {:?}", name);
+    match app.restore_target {
+        RestoreTarget::Postgres => app.pg_config.target_db = name,
+        RestoreTarget::Elasticsearch => app.es_config.index = name,
+        RestoreTarget::Qdrant => app.qdrant_config.collection = name,
+        RestoreTarget::OpenSearch => app.os_config.index = name,
+        RestoreTarget::Meilisearch => app.meili_config.index = name,
+        RestoreTarget::Weaviate => app.weaviate_config.class = name,
+        RestoreTarget::Milvus => app.milvus_config.collection = name,
+        RestoreTarget::DynamoDb => app.dynamodb_config.table = name,
+        RestoreTarget::InfluxDb => app.influxdb_config.bucket = name,
+    }
+}
+
+/// Ask to restore the marked snapshots one after another
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+pub fn open(app: &mut RustoredApp) {
+    debug!("Opening batch restore confirmation");
+    let snapshots = app.snapshot_browser.marked_snapshots();
+    if snapshots.is_empty() {
+        app.popup_state = PopupState::Error("Mark the snapshots to restore with Space first".to_string());
+        return;
+    }
+    if let Some(base) = snapshots.iter().find(|snapshot| crate::wal::base_start_segment(&snapshot.key).is_some()) {
+        app.popup_state = PopupState::Error(format!("{} is a base backup; recover it with Enter on it instead", base.key));
+        return;
+    }
+    let lines = snapshots
+        .iter()
+        .zip(target_names(app, snapshots.len()))
+        .map(|(snapshot, name)| format!("{} -> {}", snapshot.key, name.as_deref().unwrap_or("a new database")))
+        .collect();
+    app.popup_state = PopupState::ConfirmBatchRestore(snapshots, lines);
+}
+
+/// Handle a key in the batch restore confirmation
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) -> Result<Option<String>> {
+    debug!("Handling batch restore confirmation key: {:?}", key);
+    let PopupState::ConfirmBatchRestore(snapshots, _) = &app.popup_state else {
+        return Ok(None);
+    };
+    match key.code {
+        KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+            let snapshots = snapshots.clone();
+            return guard_rails::confirm(app, GuardedAction::RestoreBatch(snapshots)).await;
+        }
+        KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => app.popup_state = PopupState::Hidden,
+        _ => {}
+    }
+    Ok(None)
+}
+
+/// Restore confirmed snapshots one after another, showing the progress of each
+///
+/// The configured target name and restore options are put back afterwards.
+/// The summary lists the outcome of every item.
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshots` - The confirmed snapshots, in the order they are restored
+/// * `terminal` - The terminal to draw progress on
+pub async fn restore_batch<B: Backend>(app: &mut RustoredApp, snapshots: &[BackupMetadata], terminal: &mut Terminal<B>) -> Result<()> {
+    debug!("Restoring batch of {} snapshots", snapshots.len());
+    let configured = (
+        app.pg_config.target_db.clone(),
+        app.es_config.index.clone(),
+        app.qdrant_config.collection.clone(),
+        app.os_config.index.clone(),
+        app.meili_config.index.clone(),
+        app.weaviate_config.class.clone(),
+        app.milvus_config.collection.clone(),
+        app.dynamodb_config.table.clone(),
+        app.influxdb_config.bucket.clone(),
+    );
+    let options = app.restore_options.clone();
+    let operation = app.begin_operation();
+    let mut outcomes: Vec<String> = Vec::new();
+    let mut restored = 0;
+    for (snapshot, name) in snapshots.iter().zip(target_names(app, snapshots.len())) {
+        if operation.is_cancelled() {
+            outcomes.push("Skipped, the batch was aborted".to_string());
+            continue;
+        }
+        app.apply_restore_preset(snapshot);
+        // Without a name to number, only new databases keep the restores apart
+        if name.is_none() {
+            app.restore_options.mode = RestoreMode::New;
+        }
+        set_target_name(app, name);
+        app.popup_state = PopupState::RestoringBatch(snapshots.to_vec(), outcomes.clone());
+        terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
+
+        let span = crate::telemetry::Span::operation("restore").with("key", &snapshot.key).with("target", format!("{:?}", app.restore_target).to_lowercase());
+        let result = restore_item(app, snapshot, terminal).await;
+        span.end(&result);
+        outcomes.push(match result {
+            Ok(outcome) => {
+                restored += 1;
+                match outcome.dead_letters {
+                    Some(report) => format!("Restored to {}, {}", outcome.name, report.summary()),
+                    None => format!("Restored to {}", outcome.name),
+                }
+            }
+            Err(e) => app.restore_error("Restore", &e),
+        });
+    }
+
+    (app.pg_config.target_db, app.es_config.index, app.qdrant_config.collection, app.os_config.index, app.meili_config.index, app.weaviate_config.class, app.milvus_config.collection, app.dynamodb_config.table, app.influxdb_config.bucket) = configured;
+    app.restore_options = options;
+    let mut lines = vec![format!("Restored {} of {} snapshots", restored, snapshots.len())];
+    lines.extend(snapshots.iter().zip(&outcomes).map(|(snapshot, outcome)| format!("{}: {}", snapshot.key, outcome)));
+    app.popup_state = PopupState::RestoreSummary(lines);
+    terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
+    Ok(())
+}
+
+/// Download or stream one item of a batch and restore it, redrawing as its progress moves
+async fn restore_item<B: Backend>(app: &mut RustoredApp, snapshot: &BackupMetadata, terminal: &mut Terminal<B>) -> Result<RestoreOutcome> {
+    debug!("Restoring batch item {}", snapshot.key);
+    let operation = app.operation.clone();
+    if app.streams_restore(snapshot) {
+        let input = app.open_restore_stream(snapshot).await?;
+        let app: &RustoredApp = app;
+        return draw_while(app, terminal, crate::ui::app::cancel_on_escape(&operation, app.run_streamed_restore(snapshot, input))).await?;
+    }
+    let tmp_path = crate::download_ledger::temp_download_path(&snapshot.key);
+    let download = app.snapshot_browser.download_snapshot(snapshot, &tmp_path);
+    let path = crate::ui::app::cancel_on_escape(&operation, download).await?
+        .ok_or_else(|| anyhow!("download failed: {:?}", app.snapshot_browser.popup_state))?;
+    let app: &RustoredApp = app;
+    draw_while(app, terminal, crate::ui::app::cancel_on_escape(&operation, app.run_restore(snapshot, std::path::Path::new(&path)))).await?
+}
+
+/// Await a restore, redrawing at most 10 times per second while its progress moves
+async fn draw_while<B: Backend, T>(app: &RustoredApp, terminal: &mut Terminal<B>, restore: impl Future<Output = T>) -> Result<T> {
+    debug!("Drawing batch progress while a restore runs");
+    let mut updates = app.progress.subscribe();
+    tokio::pin!(restore);
+    let mut ticker = tokio::time::interval(Duration::from_millis(100));
+    loop {
+        tokio::select! {
+            result = &mut restore => return Ok(result),
+            _ = ticker.tick() => {
+                if updates.has_changed().unwrap_or(false) {
+                    updates.borrow_and_update();
+                    terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
+                }
+            }
+        }
+    }
+}
@@ -1,23 +1,100 @@
 use anyhow::{Result, anyhow};
-use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
-use aws_sdk_s3::operation::get_object::GetObjectOutput;
-use aws_sdk_s3::Client as S3Client;
-
 use log::{debug, warn};
 use tokio::io::AsyncReadExt;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
-use std::fs::File;
-use std::io::Write;
-use crate::ui::models::{S3Config, PopupState, FocusField, BackupMetadata};
-use chrono::Utc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use futures_util::stream::{self, StreamExt};
+use crate::archive::{ArchiveStatus, ArchiveTier, ArchiveTracker, ARCHIVE_POLL_INTERVAL, DEFAULT_ARCHIVE_RESTORE_DAYS};
+use crate::cancel::{cancellable, CancellationToken, Cancelled};
+use crate::download_ledger::{DownloadLedger, DEFAULT_DOWNLOAD_CONCURRENCY, DEFAULT_PART_SIZE};
+use crate::encryption::ClientEncryption;
+use crate::holds::{HoldCatalog, CATALOG_KEY};
+use crate::listing_cache::{merge_listing, ListingCache};
+use crate::manifest::{is_manifest_key, manifest_key, Manifest, MANIFEST_SUFFIX};
+use crate::es_index::is_index_definition_key;
+use crate::progress::{Progress, ProgressBoard};
+use crate::verification::{VerificationCatalog, VERIFICATION_CATALOG_KEY};
+use crate::wal::{is_wal_key, WalChain};
+use crate::storage::{B2Store, CloudStore, S3Store, SftpStore, SnapshotEncryption, SnapshotStore};
+use crate::ui::models::{S3Config, GcsConfig, AzureConfig, B2Config, SftpConfig, PopupState, FocusField, BackupMetadata};
+
+/// Word typed to confirm deleting snapshots
+pub const DELETE_CONFIRMATION: &str = "delete";
 
 /// Component for S3 snapshot browsing
 pub struct SnapshotBrowser {
     // S3 Configuration
     pub s3_config: S3Config,
+
+    // GCS Configuration, used instead of S3 when set
+    pub gcs_config: Option<GcsConfig>,
+
+    // Azure Blob Configuration, used instead of S3 when set
+    pub azure_config: Option<AzureConfig>,
+
+    // Backblaze B2 Configuration, used instead of S3, GCS and Azure when set
+    pub b2_config: Option<B2Config>,
+
+    // SFTP Configuration, used instead of any bucket when set
+    pub sftp_config: Option<SftpConfig>,
     
-    // S3 Client
-    s3_client: Option<S3Client>,
+    // Storage backend client
+    store: Option<Arc<dyn SnapshotStore>>,
+
+    // Legal holds on the bucket, loaded with the snapshots
+    pub holds: HoldCatalog,
+
+    // Cached verification results, loaded with the snapshots
+    pub verifications: VerificationCatalog,
+
+    // Backup manifests by snapshot key, loaded with the snapshots
+    pub manifests: HashMap<String, Manifest>,
+
+    // Archived WAL files, kept out of the snapshot list
+    pub wal_files: Vec<BackupMetadata>,
+
+    // Base backups and the WAL archived after them, by base backup key
+    pub wal_chains: BTreeMap<String, WalChain>,
+
+    // Number of snapshot parts downloaded at once
+    pub download_concurrency: usize,
+
+    // Stop listing snapshots after this many keys, to bound huge buckets
+    pub max_keys: Option<usize>,
+
+    // Cancels downloads and uploads; replaced for each operation (see `RustoredApp::begin_operation`)
+    pub cancel: CancellationToken,
+
+    // Latest progress of downloads, read by the renderer
+    pub progress: ProgressBoard,
+
+    // Encryption of the snapshot awaiting restore confirmation, if the backend reports it
+    pub selected_encryption: Option<SnapshotEncryption>,
+
+    // age or GPG recipients of uploaded backups, and the age identity downloads are decrypted with
+    pub encryption: ClientEncryption,
+
+    // Archive status of snapshots looked up or restored from Glacier, shown in the list
+    pub archive: ArchiveTracker,
+
+    // Last complete listing of each source, shown while the live listing loads; None disables it
+    pub listing_cache: Option<ListingCache>,
+
+    // When the cached listing on show was taken, until the live listing replaces it
+    pub stale_since: Option<f64>,
+
+    // Whether a live listing is being loaded
+    pub refreshing: bool,
+
+    // When the snapshot list was last refreshed from the source
+    pub last_refreshed: Option<f64>,
+
+    // Keys of the snapshots marked in the list, to delete or restore them together
+    pub marked: BTreeSet<String>,
     
     // UI State
     pub focus: FocusField,
@@ -34,7 +111,28 @@ impl SnapshotBrowser {
         debug!("Creating new SnapshotBrowser with S3 config: {:?}", s3_config);
         let browser = Self {
             s3_config,
-            s3_client: None,
+            gcs_config: None,
+            azure_config: None,
+            b2_config: None,
+            sftp_config: None,
+            store: None,
+            holds: HoldCatalog::default(),
+            verifications: VerificationCatalog::default(),
+            manifests: HashMap::new(),
+            wal_files: Vec::new(),
+            wal_chains: BTreeMap::new(),
+            download_concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+            max_keys: None,
+            cancel: CancellationToken::new(),
+            progress: ProgressBoard::default(),
+            selected_encryption: None,
+            encryption: ClientEncryption::default(),
+            archive: ArchiveTracker::default(),
+            listing_cache: None,
+            stale_since: None,
+            refreshing: false,
+            last_refreshed: None,
+            marked: BTreeSet::new(),
             focus: FocusField::SnapshotList,
             input_mode: crate::ui::models::InputMode::Normal,
             input_buffer: String::new(),
@@ -46,196 +144,702 @@ impl SnapshotBrowser {
         browser
     }
 
-    /// Initialize the S3 client based on current settings
+    /// Bucket snapshots are read from, on whichever backend is active
+    ///
+    /// For SFTP this is the server host.
+    pub fn bucket(&self) -> &str {
+        debug!("Getting snapshot source bucket");
+        if let Some(sftp) = &self.sftp_config {
+            return &sftp.host;
+        }
+        if let Some(b2) = &self.b2_config {
+            return &b2.bucket;
+        }
+        match (&self.azure_config, &self.gcs_config) {
+            (Some(azure), _) => &azure.container,
+            (None, Some(gcs)) => &gcs.bucket,
+            (None, None) => &self.s3_config.bucket,
+        }
+    }
+
+    /// Key prefix snapshots are listed under, on whichever backend is active
+    pub fn prefix(&self) -> &str {
+        debug!("Getting snapshot source prefix");
+        if let Some(sftp) = &self.sftp_config {
+            return &sftp.prefix;
+        }
+        if let Some(b2) = &self.b2_config {
+            return &b2.prefix;
+        }
+        match (&self.azure_config, &self.gcs_config) {
+            (Some(azure), _) => &azure.prefix,
+            (None, Some(gcs)) => &gcs.prefix,
+            (None, None) => &self.s3_config.prefix,
+        }
+    }
+
+    /// Identifies the snapshot source, so each backend, bucket and prefix is cached separately
+    pub fn source(&self) -> String {
+        debug!("Getting snapshot source identifier");
+        let scheme = match (&self.sftp_config, &self.b2_config, &self.azure_config, &self.gcs_config) {
+            (Some(sftp), _, _, _) => format!("sftp://{}@", sftp.username),
+            (None, Some(_), _, _) => "b2://".to_string(),
+            (None, None, Some(azure), _) => format!("az://{}/", azure.account),
+            (None, None, None, Some(_)) => "gs://".to_string(),
+            (None, None, None, None) if self.s3_config.endpoint_url.is_empty() => "s3://".to_string(),
+            (None, None, None, None) => format!("s3+{}/", self.s3_config.endpoint_url),
+        };
+        format!("{}{}/{}", scheme, self.bucket(), self.prefix())
+    }
+
+    /// Show the cached listing of the current source until the live listing replaces it
+    ///
+    /// # Returns
+    ///
+    /// Whether a cached listing was found
+    pub fn show_cached_listing(&mut self) -> bool {
+        debug!("Showing cached snapshot listing");
+        let Some(listing) = self.listing_cache.as_ref().and_then(|cache| cache.load(&self.source())) else {
+            return false;
+        };
+        debug!("Showing {} cached snapshots from {}", listing.snapshots.len(), listing.saved_at);
+        self.set_snapshots(listing.snapshots);
+        self.stale_since = Some(listing.saved_at);
+        true
+    }
+
+    /// Use an already constructed storage backend instead of building one from the settings
+    pub fn set_store(&mut self, store: Arc<dyn SnapshotStore>) {
+        debug!("Setting {} storage backend", store.name());
+        self.store = Some(store);
+    }
+
+    /// Storage backend in use, initializing it from the settings if needed
+    pub async fn store(&mut self) -> Result<Arc<dyn SnapshotStore>> {
+        debug!("Getting storage backend");
+        if self.store.is_none() {
+            self.init_client().await?;
+        }
+        self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))
+    }
+
+    /// Initialize the storage client based on current settings
     pub async fn init_client(&mut self) -> Result<()> {
-        debug!("Initializing S3 client with config: {:?}", self.s3_config);
-        match self.s3_config.create_client() {
-            Ok(client) => {
-                debug!("Successfully created S3 client");
-                self.s3_client = Some(client);
+        debug!("Initializing storage client, gcs: {}, azure: {}, sftp: {}", self.gcs_config.is_some(), self.azure_config.is_some(), self.sftp_config.is_some());
+        if let Some(sftp) = self.sftp_config.clone() {
+            // Connecting blocks on the network, so keep it off the async runtime
+            let session = tokio::task::spawn_blocking(move || sftp.connect()).await?;
+            let session = session.map_err(|e| anyhow!("Failed to create storage client: {}", e))?;
+            self.store = Some(Arc::new(SftpStore { session }));
+            return Ok(());
+        }
+        if let Some(b2) = self.b2_config.clone() {
+            let store = B2Store::new(b2).map_err(|e| anyhow!("Failed to create storage client: {}", e))?;
+            self.store = Some(Arc::new(store));
+            return Ok(());
+        }
+        let store: Result<Arc<dyn SnapshotStore>> = match (&self.azure_config, &self.gcs_config) {
+            (Some(azure), _) => azure.create_client().map(|client| Arc::new(CloudStore::new("Azure", client)) as Arc<dyn SnapshotStore>),
+            (None, Some(gcs)) => gcs.create_client().map(|client| Arc::new(CloudStore::new("GCS", client)) as Arc<dyn SnapshotStore>),
+            (None, None) => match self.s3_config.customer_key() {
+                Ok(sse_customer_key) => self.s3_config.create_client().await.map(|client| {
+                    Arc::new(S3Store { client, bucket: self.s3_config.bucket.clone(), sse_customer_key }) as Arc<dyn SnapshotStore>
+                }),
+                Err(e) => Err(e),
+            },
+        };
+        match store {
+            Ok(store) => {
+                debug!("Successfully created {} client", store.name());
+                self.store = Some(store);
                 Ok(())
             },
             Err(e) => {
-                warn!("Failed to create S3 client: {}", e);
-                Err(anyhow!("Failed to create S3 client: {}", e))
+                warn!("Failed to create storage client: {}", e);
+                Err(anyhow!("Failed to create storage client: {}", e))
             }
         }
     }
 
-    /// Load snapshots from S3
+    /// Load snapshots from S3, GCS, Azure, B2 or SFTP
+    ///
+    /// A complete listing replaces any stale cached one on show and is saved
+    /// to the listing cache.
     pub async fn load_snapshots(&mut self) -> Result<()> {
-        debug!("Loading snapshots from S3 bucket: {}, prefix: {}", self.s3_config.bucket, self.s3_config.prefix);
-        debug!("Loading snapshots from S3");
-        
+        debug!("Loading snapshots from bucket: {}, prefix: {}", self.bucket(), self.prefix());
+        self.refreshing = true;
+        let result = self.list_snapshots().await;
+        self.refreshing = false;
+        result?;
+
+        self.stale_since = None;
+        self.last_refreshed = Some(chrono::Utc::now().timestamp() as f64);
+        self.save_listing_cache();
+        Ok(())
+    }
+
+    /// Pick up snapshots added since the last refresh without re-listing the whole source
+    ///
+    /// Only keys sorting after the last listed key are fetched, which finds
+    /// new timestamped snapshots but not deleted or replaced ones, so a full
+    /// listing is loaded instead when nothing has been listed yet or a stale
+    /// cached listing is on show.
+    ///
+    /// # Returns
+    ///
+    /// How many snapshots were added or changed
+    pub async fn refresh_snapshots(&mut self) -> Result<usize> {
+        debug!("Refreshing snapshots from {}", self.source());
+        let last_key = self.snapshots.iter().map(|snapshot| snapshot.key.clone()).max();
+        let (Some(last_key), Some(_), None) = (last_key, self.last_refreshed, self.stale_since) else {
+            self.load_snapshots().await?;
+            return Ok(self.snapshots.len());
+        };
+
+        let store = self.store().await?;
+        let prefix = self.prefix().to_string();
+        self.refreshing = true;
+        let listed = store.list_snapshots_after(&prefix, &last_key).await;
+        self.refreshing = false;
+
+        let listed = listed?;
+        self.load_manifests(&listed).await;
+        let listed = listed.into_iter().filter(|object| !is_manifest_key(&object.key) && !is_index_definition_key(&object.key)).collect();
+        let mut snapshots = self.snapshots.clone();
+        snapshots.extend(self.wal_files.iter().cloned());
+        let changed = merge_listing(&mut snapshots, listed);
+        debug!("{} snapshots added since the last refresh", changed);
+        if changed > 0 {
+            self.set_snapshots(snapshots);
+            self.save_listing_cache();
+        }
+        self.last_refreshed = Some(chrono::Utc::now().timestamp() as f64);
+        Ok(changed)
+    }
+
+    /// Save the snapshot list to the listing cache, if enabled
+    fn save_listing_cache(&self) {
+        debug!("Saving snapshot listing cache");
+        if let Some(cache) = &self.listing_cache {
+            // The cache only speeds up the next start, so failing to save it is not fatal
+            if let Err(e) = cache.save(&self.source(), &self.snapshots) {
+                warn!("Failed to cache snapshot listing: {}", e);
+            }
+        }
+    }
+
+    /// List the snapshots of the current source into the snapshot list
+    async fn list_snapshots(&mut self) -> Result<()> {
+        debug!("Listing snapshots from {}", self.source());
+
         // Initialize client if needed
-        if self.s3_client.is_none() {
-            debug!("S3 client not initialized, initializing now");
+        if self.store.is_none() {
+            debug!("Storage client not initialized, initializing now");
             self.init_client().await?;
         }
         
-        if let Some(client) = &self.s3_client {
-            debug!("Using S3 client to list objects in bucket: {}", self.s3_config.bucket);
-            
-            let list_objects_result = client
-                .list_objects_v2()
-                .bucket(&self.s3_config.bucket)
-                .prefix(&self.s3_config.prefix)
-                .send()
-                .await;
-                
-            match list_objects_result {
-                Ok(output) => {
-                    debug!("Successfully listed objects, parsing results");
-                    self.parse_s3_output(output);
-                    Ok(())
-                }
-                Err(e) => {
-                    debug!("Failed to list objects: {}", e);
-                    Err(anyhow!("Failed to list objects: {}", e))
+        let store = self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        let prefix = self.prefix().to_string();
+
+        // Holds only pin snapshots in the list, so an unreadable catalog is not fatal here.
+        // Load them first so pages are sorted correctly as they arrive.
+        if let Err(e) = self.load_holds().await {
+            warn!("Failed to load hold catalog: {}", e);
+        }
+        if let Err(e) = self.load_verifications().await {
+            warn!("Failed to load verification catalog: {}", e);
+        }
+
+        // Populate the list page by page so large buckets fill in while listing
+        let mut listed = Vec::new();
+        let max_keys = self.max_keys;
+        store.list_snapshot_pages(&prefix, max_keys, &mut |page| {
+            listed.extend(page);
+            self.set_snapshots(listed.clone());
+        }).await?;
+        self.manifests.clear();
+        self.load_manifests(&listed).await;
+        Ok(())
+    }
+
+    /// Read the manifests among listed objects, keyed by the key of their snapshot
+    ///
+    /// Up to `download_concurrency` manifests are read at once. Manifests only
+    /// add detail to the list, so one that cannot be read is logged and skipped.
+    async fn load_manifests(&mut self, listed: &[BackupMetadata]) {
+        debug!("Loading manifests among {} listed objects", listed.len());
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        let loaded: Vec<(String, Result<Option<Vec<u8>>>)> = stream::iter(listed.iter().filter(|object| is_manifest_key(&object.key)))
+            .map(|object| {
+                let store = store.clone();
+                async move { (object.key.clone(), store.read_object(&object.key).await) }
+            })
+            .buffer_unordered(self.download_concurrency.max(1))
+            .collect()
+            .await;
+        for (key, bytes) in loaded {
+            let snapshot_key = key.strip_suffix(MANIFEST_SUFFIX).unwrap_or(&key).to_string();
+            match bytes.and_then(|bytes| bytes.map(|bytes| Manifest::from_json(&bytes)).transpose()) {
+                Ok(Some(manifest)) => {
+                    self.manifests.insert(snapshot_key, manifest);
                 }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read manifest {}: {}", key, e),
             }
-        } else {
-            debug!("S3 client not available");
-            Err(anyhow!("S3 client not initialized"))
         }
+        debug!("Loaded {} manifests", self.manifests.len());
     }
 
-    /// Parse S3 output and populate snapshots
-    fn parse_s3_output(&mut self, output: ListObjectsV2Output) {
-        debug!("Parsing S3 output to populate snapshots list");
-        self.snapshots.clear();
-        self.selected_index = 0;
+    /// Look up the encryption of a snapshot for display before restoring it
+    ///
+    /// A failed lookup is logged rather than returned, since the download
+    /// reports the same problem with more context.
+    pub async fn load_encryption(&mut self, key: &str) {
+        debug!("Loading encryption of snapshot {}", key);
+        self.selected_encryption = match self.store().await {
+            Ok(store) => store.encryption(key).await.unwrap_or_else(|e| {
+                warn!("Failed to look up encryption of {}: {}", key, e);
+                None
+            }),
+            Err(e) => {
+                warn!("Failed to look up encryption of {}: {}", key, e);
+                None
+            }
+        };
+    }
+
+    /// List every stored version of a snapshot, newest first
+    pub async fn load_versions(&mut self, key: &str) -> Result<Vec<BackupMetadata>> {
+        debug!("Loading versions of snapshot {}", key);
+        let store = self.store().await?;
+        store.list_versions(key).await
+    }
 
-        if let Some(contents) = output.contents {
-            for obj in contents {
-                // Skip directory-like objects (ones that end with /)
-                if let Some(key) = &obj.key {
-                    if key.ends_with('/') {
-                        continue;
-                    }
-                    
-                    // Only include objects that match our prefix and have content
-                    if key.starts_with(&self.s3_config.prefix) {
-                        let metadata = BackupMetadata {
-                            key: key.clone(),
-                            size: obj.size.unwrap_or(0),
-                            last_modified: obj.last_modified
-                                .map(|dt| dt.as_secs_f64())
-                                .unwrap_or_else(|| Utc::now().timestamp() as f64),
-                        };
-                        self.snapshots.push(metadata);
-                    }
+    /// Find a snapshot by key, or one version of it, for restoring from the command line
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the snapshot
+    /// * `version_id` - Version to use instead of the current one
+    pub async fn find_snapshot(&mut self, key: &str, version_id: Option<&str>) -> Result<BackupMetadata> {
+        debug!("Finding snapshot {} (version {:?})", key, version_id);
+        if let Some(version_id) = version_id {
+            return self.load_versions(key).await?
+                .into_iter()
+                .find(|version| version.version_id.as_deref() == Some(version_id))
+                .ok_or_else(|| anyhow!("Version {} of {} not found", version_id, key));
+        }
+        self.load_snapshots().await?;
+        self.snapshots
+            .iter()
+            .find(|snapshot| snapshot.key == key)
+            .cloned()
+            .ok_or_else(|| anyhow!("Snapshot {} not found", key))
+    }
+
+    /// Look up whether a snapshot is archived, recording the result for the snapshot list
+    pub async fn load_archive_status(&mut self, key: &str) -> Result<ArchiveStatus> {
+        debug!("Loading archive status of snapshot {}", key);
+        let store = self.store().await?;
+        let status = store.archive_status(key).await?;
+        self.archive.set(key, status.clone());
+        Ok(status)
+    }
+
+    /// Request a restore of an archived snapshot and poll in the background until it is readable
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the archived snapshot
+    /// * `tier` - Retrieval tier to restore it with
+    /// * `cancel` - Token that stops polling
+    pub async fn request_archive_restore(&mut self, key: &str, tier: ArchiveTier, cancel: CancellationToken) -> Result<()> {
+        debug!("Requesting {} archive restore of snapshot {}", tier, key);
+        let store = self.store().await?;
+        store.request_archive_restore(key, tier, DEFAULT_ARCHIVE_RESTORE_DAYS).await?;
+        self.archive.set(key, ArchiveStatus::Restoring);
+        self.archive.watch(store, key, ARCHIVE_POLL_INTERVAL, cancel);
+        Ok(())
+    }
+
+    /// Find the newest snapshot under `prefix` taken at or before `as_of`
+    ///
+    /// The listing is reloaded first so the choice reflects the source now.
+    pub async fn find_snapshot_as_of(&mut self, prefix: &str, as_of: chrono::DateTime<chrono::Utc>) -> Result<BackupMetadata> {
+        debug!("Finding snapshot under {} as of {}", prefix, as_of);
+        self.load_snapshots().await?;
+        crate::as_of::newest_as_of(&self.snapshots, prefix, as_of)
+            .cloned()
+            .ok_or_else(|| anyhow!("No snapshot under '{}' was taken at or before {}", prefix, as_of.format("%Y-%m-%d %H:%M:%S UTC")))
+    }
+
+    /// Load the legal hold catalog from the bucket
+    pub async fn load_holds(&mut self) -> Result<()> {
+        debug!("Loading hold catalog from {}", CATALOG_KEY);
+        if self.store.is_none() {
+            self.init_client().await?;
+        }
+        let store = self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        self.holds = match store.read_object(CATALOG_KEY).await? {
+            Some(bytes) => HoldCatalog::from_json(&bytes)?,
+            None => HoldCatalog::default(),
+        };
+        debug!("Loaded {} holds", self.holds.holds.len());
+        Ok(())
+    }
+
+    /// Write the legal hold catalog back to the bucket
+    pub async fn save_holds(&mut self) -> Result<()> {
+        debug!("Saving {} holds to {}", self.holds.holds.len(), CATALOG_KEY);
+        if self.store.is_none() {
+            self.init_client().await?;
+        }
+        let store = self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        store.write_object(CATALOG_KEY, self.holds.to_json()?).await
+    }
+
+    /// Load the verification catalog from the bucket
+    pub async fn load_verifications(&mut self) -> Result<()> {
+        debug!("Loading verification catalog from {}", VERIFICATION_CATALOG_KEY);
+        if self.store.is_none() {
+            self.init_client().await?;
+        }
+        let store = self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        self.verifications = match store.read_object(VERIFICATION_CATALOG_KEY).await? {
+            Some(bytes) => VerificationCatalog::from_json(&bytes)?,
+            None => VerificationCatalog::default(),
+        };
+        debug!("Loaded {} verification results", self.verifications.snapshots.len());
+        Ok(())
+    }
+
+    /// Write the verification catalog back to the bucket
+    pub async fn save_verifications(&mut self) -> Result<()> {
+        debug!("Saving {} verification results to {}", self.verifications.snapshots.len(), VERIFICATION_CATALOG_KEY);
+        if self.store.is_none() {
+            self.init_client().await?;
+        }
+        let store = self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        store.write_object(VERIFICATION_CATALOG_KEY, self.verifications.to_json()?).await
+    }
+
+    /// Mark the selected snapshot, or unmark it if it is marked
+    pub fn toggle_mark(&mut self) {
+        debug!("Toggling deletion mark of selected snapshot {}", self.selected_index);
+        if let Some(snapshot) = self.snapshots.get(self.selected_index) {
+            if !self.marked.remove(&snapshot.key) {
+                self.marked.insert(snapshot.key.clone());
+            }
+        }
+    }
+
+    /// Snapshots a delete applies to: the marked ones, or the selected one if none are marked
+    pub fn deletion_candidates(&self) -> Vec<BackupMetadata> {
+        debug!("Collecting snapshots to delete from {} marked", self.marked.len());
+        if self.marked.is_empty() {
+            return self.snapshots.get(self.selected_index).cloned().into_iter().collect();
+        }
+        self.marked_snapshots()
+    }
+
+    /// The marked snapshots, in the order they are listed
+    pub fn marked_snapshots(&self) -> Vec<BackupMetadata> {
+        debug!("Collecting {} marked snapshots", self.marked.len());
+        self.snapshots
+            .iter()
+            .filter(|snapshot| self.marked.contains(&snapshot.key))
+            .cloned()
+            .collect()
+    }
+
+    /// Delete snapshots from the source and drop them from the list
+    ///
+    /// The hold catalog is re-read from the bucket first, and nothing is
+    /// deleted if any of the snapshots is under an active legal hold. If a
+    /// delete fails part way, the snapshots already deleted are still dropped
+    /// from the list.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshots` - Snapshots to delete
+    /// * `today` - Date holds are checked against
+    pub async fn delete_snapshots(&mut self, snapshots: &[BackupMetadata], today: chrono::NaiveDate) -> Result<()> {
+        debug!("Deleting {} snapshots", snapshots.len());
+        self.load_holds().await?;
+        for snapshot in snapshots {
+            self.holds.ensure_deletable(&snapshot.key, today)?;
+        }
+
+        let store = self.store().await?;
+        let mut result = Ok(());
+        let mut deleted = BTreeSet::new();
+        for snapshot in snapshots {
+            if let Err(e) = store.delete_object(&snapshot.key).await {
+                result = Err(e);
+                break;
+            }
+            deleted.insert(snapshot.key.clone());
+            // A manifest left behind only describes a snapshot that is gone
+            if self.manifests.remove(&snapshot.key).is_some() {
+                if let Err(e) = store.delete_object(&manifest_key(&snapshot.key)).await {
+                    warn!("Failed to delete manifest of {}: {}", snapshot.key, e);
                 }
             }
         }
+
+        let remaining: Vec<BackupMetadata> = self.snapshots
+            .iter()
+            .filter(|snapshot| !deleted.contains(&snapshot.key))
+            .cloned()
+            .collect();
+        self.marked.retain(|key| !deleted.contains(key));
+        let selected_index = self.selected_index.min(remaining.len().saturating_sub(1));
+        self.set_snapshots(remaining);
+        self.selected_index = selected_index;
+        self.save_listing_cache();
+        result
+    }
+
+    /// Whether a snapshot is pinned to the top of the list, either as a
+    /// pre-change snapshot or by an active legal hold
+    pub fn is_pinned(&self, snapshot: &BackupMetadata) -> bool {
+        self.is_pinned_on(snapshot, chrono::Utc::now().date_naive())
+    }
+
+    /// Whether a snapshot is pinned, judging holds as of `today`
+    fn is_pinned_on(&self, snapshot: &BackupMetadata, today: chrono::NaiveDate) -> bool {
+        debug!("Checking if snapshot {} is pinned or held on {}", snapshot.key, today);
+        snapshot.is_pinned() || self.holds.is_held(&snapshot.key, today)
+    }
+
+    /// Populate the snapshot list from listed objects
+    fn set_snapshots(&mut self, objects: Vec<BackupMetadata>) {
+        debug!("Populating snapshots list from {} objects", objects.len());
+        let prefix = self.prefix().to_string();
+        self.selected_index = 0;
+
+        // WAL files are shown grouped with their base backup rather than one per row
+        self.wal_chains = crate::wal::group_chains(&objects);
+        let (wal_files, objects): (Vec<BackupMetadata>, Vec<BackupMetadata>) = objects.into_iter().partition(|obj| is_wal_key(&obj.key));
+        self.wal_files = wal_files;
+
+        // Skip directory-like objects, Rustored's own `.rustored/` files and anything outside our prefix
+        let mut snapshots: Vec<BackupMetadata> = objects
+            .into_iter()
+            .filter(|obj| {
+                !obj.key.ends_with('/')
+                    && !obj.key.starts_with(".rustored/")
+                    && !is_manifest_key(&obj.key)
+                    && !is_index_definition_key(&obj.key)
+                    && obj.key.starts_with(&prefix)
+            })
+            .collect();
         
-        // Sort by most recent first
-        self.snapshots.sort_by(|a, b| b.last_modified.partial_cmp(&a.last_modified).unwrap_or(std::cmp::Ordering::Equal));
+        // Sort pinned and held snapshots first, then by most recent first. The
+        // pinned flag is worked out once per snapshot against a single date, so
+        // a hold expiring mid-sort cannot leave the order inconsistent
+        let today = chrono::Utc::now().date_naive();
+        snapshots.sort_by(|a, b| b.last_modified.partial_cmp(&a.last_modified).unwrap_or(std::cmp::Ordering::Equal));
+        snapshots.sort_by_cached_key(|snapshot| !self.is_pinned_on(snapshot, today));
+        self.snapshots = snapshots;
         
         debug!("Loaded {} snapshots", self.snapshots.len());
     }
 
     /// Download snapshot to a local file
+    ///
+    /// The snapshot is fetched in ranged parts, `download_concurrency` at a
+    /// time. Each completed part is recorded in a sidecar ledger (see
+    /// `DownloadLedger`), so an interrupted download to the same path resumes
+    /// by re-verifying the parts already on disk. Cancelling `cancel` stops the
+    /// download with a `Cancelled` error and keeps the ledger for resuming.
+    /// A snapshot encrypted with age or GPG is decrypted once downloaded, and
+    /// its Elasticsearch index definition, if any, is downloaded next to it.
     pub async fn download_snapshot(
         &mut self,
         snapshot: &BackupMetadata,
         tmp_path: &Path,
     ) -> Result<Option<String>> {
         debug!("Downloading snapshot: {} to path: {:?}", snapshot.key, tmp_path);
-        if let Some(client) = &self.s3_client {
-            self.popup_state = PopupState::Downloading(snapshot.clone(), 0.0, 0.0);
-            
-            // Set popup state for download
-            self.popup_state = PopupState::Downloading(snapshot.clone(), 0.0, 0.0);
-            
-            match client
-                .get_object()
-                .bucket(&self.s3_config.bucket)
-                .key(&snapshot.key)
-                .send()
-                .await
-            {
-                Ok(output) => {
-                    debug!("Download started for {}", snapshot.key);
-                    
-                    // Ensure parent directory exists
-                    if let Some(parent) = tmp_path.parent() {
-                        std::fs::create_dir_all(parent)?;
-                    }
-                    
-                    // Save to file
-                    if let Ok(file) = File::create(tmp_path) {
-                        let tmp_path_str = tmp_path.to_string_lossy().to_string();
-                        if let Err(e) = self.save_stream_to_file(output, snapshot, file).await {
-                            warn!("Error saving file: {}", e);
-                            self.popup_state = PopupState::Error(format!("Download failed: {}", e));
-                            // Set error popup state
-                            return Ok(None);
-                        }
-                        return Ok(Some(tmp_path_str));
-                    } else {
-                        debug!("Could not create file at {:?}", tmp_path);
-                        return Ok(None);
-                    }
-                }
-                Err(e) => {
-                    debug!("Failed to download snapshot {}: {}", snapshot.key, e);
-                    return Ok(None);
-                }
+        let span = crate::telemetry::Span::start("download").with("key", &snapshot.key).with("bytes", snapshot.size);
+        crate::telemetry::instrument(span, async move {
+            let Some(path) = self.fetch_snapshot(snapshot, tmp_path).await? else {
+                return Ok(None);
+            };
+            if let Err(e) = self.encryption.decrypt_in_place(tmp_path).await {
+                self.popup_state = PopupState::Error(format!("Decrypting {} failed: {}", snapshot.key, e));
+                return Err(e);
+            }
+            if let Some(store) = self.store.as_deref() {
+                crate::es_index::fetch_index_definition(store, &snapshot.key, tmp_path).await?;
             }
-        } else {
-            debug!("Download attempted but S3 client not initialized");
+            Ok(Some(path))
+        }).await
+    }
+
+    /// Download snapshot to a local file as it is stored, without decrypting it
+    ///
+    /// See `download_snapshot`; verification checksums the stored bytes.
+    pub async fn fetch_snapshot(
+        &mut self,
+        snapshot: &BackupMetadata,
+        tmp_path: &Path,
+    ) -> Result<Option<String>> {
+        debug!("Fetching snapshot: {} to path: {:?}", snapshot.key, tmp_path);
+        if self.store.is_none() {
+            debug!("Download attempted but storage client not initialized");
             return Ok(None);
         }
+
+        // Set popup state for download
+        self.popup_state = PopupState::Downloading(snapshot.clone(), 0.0, 0.0);
+
+        // Ensure parent directory exists
+        if let Some(parent) = tmp_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Pick up where a previous attempt left off, if its parts still verify
+        let size = snapshot.size.max(0) as u64;
+        let mut ledger = DownloadLedger::resume_or_new(
+            tmp_path,
+            &snapshot.key,
+            size,
+            snapshot.last_modified,
+            snapshot.etag.as_deref(),
+            DEFAULT_PART_SIZE,
+        );
+
+        // Open without truncating so verified parts are kept
+        let file = match OpenOptions::new().create(true).write(true).truncate(false).open(tmp_path) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Could not create file at {:?}: {}", tmp_path, e);
+                return Ok(None);
+            }
+        };
+        file.set_len(size)?;
+
+        let result = self.download_parts(snapshot, &mut ledger, file, tmp_path).await;
+        self.progress.finish(&format!("Download of {}", snapshot.key));
+        if let Err(e) = result {
+            // Verified parts stay in the ledger, so a cancelled download resumes later
+            if crate::cancel::is_cancelled(&e) {
+                self.popup_state = PopupState::Error(e.to_string());
+                return Err(e);
+            }
+            warn!("Error saving file: {}", e);
+            self.popup_state = PopupState::Error(format!("Download failed: {}", e));
+            return Ok(None);
+        }
+
+        // The ledger is only needed while the download is incomplete
+        DownloadLedger::remove(tmp_path)?;
+        Ok(Some(tmp_path.to_string_lossy().to_string()))
+    }
+
+    /// Upload a local file to the snapshot bucket under the given key
+    ///
+    /// # Arguments
+    ///
+    /// * `local_path` - Path of the file to upload
+    /// * `key` - Object key to upload to
+    /// * `label` - Optional label stored as `label` metadata on the object
+    pub async fn upload_snapshot(&mut self, local_path: &Path, key: &str, label: Option<&str>) -> Result<()> {
+        debug!("Uploading {:?} to key: {}", local_path, key);
+        if self.store.is_none() {
+            self.init_client().await?;
+        }
+        let store = self.store.as_ref().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        cancellable(&self.cancel, &format!("Upload of {}", key), store.upload_file(local_path, key, label)).await?;
+
+        debug!("Upload complete: {}", key);
+        Ok(())
     }
 
-    /// Save stream to file with progress updates
-    async fn save_stream_to_file(
+    /// Download all missing parts into the file with progress updates
+    ///
+    /// Up to `download_concurrency` parts are fetched at once. Each part is
+    /// buffered in memory and hashed by its worker, then written at its offset
+    /// and recorded in the ledger by this task, so the file only ever has one
+    /// writer and the ledger never gets ahead of the file.
+    async fn download_parts(
         &mut self,
-        output: GetObjectOutput,
         snapshot: &BackupMetadata,
+        ledger: &mut DownloadLedger,
         mut file: File,
+        tmp_path: &Path,
     ) -> Result<()> {
-        debug!("Saving stream to file for snapshot: {}, size: {} bytes", snapshot.key, snapshot.size);
-        let mut body = output.body.into_async_read();
-        let size = snapshot.size as f64;
-        let mut downloaded: usize = 0;
-        let mut buffer = [0; 1024 * 64]; // 64KB buffer
+        debug!("Downloading {} parts for snapshot: {}, size: {} bytes", ledger.part_count(), snapshot.key, snapshot.size);
+        let store = self.store.clone().ok_or_else(|| anyhow!("Storage client not initialized"))?;
+        let cancel = self.cancel.clone();
+        let reporter = self.progress.reporter(&format!("Download of {}", snapshot.key));
+        let size = ledger.size as f64;
+        let concurrency = self.download_concurrency.max(1);
         let start_time = std::time::Instant::now();
-        let mut last_update = std::time::Instant::now();
-        
+
+        // Bytes received across all parts, including parts still in flight
+        let received = Arc::new(AtomicU64::new(ledger.completed_bytes()));
+
+        // Parts recorded in the ledger were verified before resuming
+        let missing: Vec<(u64, (u64, u64))> = (0..ledger.part_count())
+            .filter(|&index| !ledger.is_part_complete(index))
+            .map(|index| (index, ledger.part_range(index)))
+            .collect();
+        debug!("Fetching {} missing parts with concurrency {}", missing.len(), concurrency);
+
+        let mut parts = stream::iter(missing)
+            .map(|(index, (start, end))| {
+                let store = store.clone();
+                let received = received.clone();
+                let reporter = reporter.clone();
+                let key = snapshot.key.clone();
+                let version_id = snapshot.version_id.clone();
+                async move {
+                    let data = fetch_part(store.as_ref(), &key, version_id.as_deref(), index, start, end, &received, &reporter).await?;
+                    Ok::<_, anyhow::Error>((index, start, data))
+                }
+            })
+            .buffer_unordered(concurrency);
+
+        // Update progress at most 10 times per second
+        let mut ticker = tokio::time::interval(std::time::Duration::from_millis(100));
         loop {
-            match body.read(&mut buffer).await {
-                Ok(0) => break, // EOF
-                Ok(n) => {
-                    // Write to file
-                    file.write_all(&buffer[0..n])?;
-                    downloaded += n;
-                    
-                    // Update progress at most 10 times per second
-                    let now = std::time::Instant::now();
-                    if now.duration_since(last_update).as_millis() > 100 {
-                        last_update = now;
-                        let elapsed = now.duration_since(start_time).as_secs_f64();
-                        let rate = if elapsed > 0.0 { downloaded as f64 / elapsed } else { 0.0 };
-                        let progress = downloaded as f64 / size;
-                        
-                        // Update popup state
-                        self.popup_state = PopupState::Downloading(snapshot.clone(), progress as f32, rate);
-                        
-                        // Check for user cancel
-                        if let PopupState::ConfirmCancel(_, _, _) = self.popup_state {
-                            return Err(anyhow!("Download cancelled by user"));
-                        }
-                    }
+            tokio::select! {
+                // Check for cancellation before taking another part
+                biased;
+                _ = cancel.cancelled() => {
+                    return Err(Cancelled::error(format!("Download of {}", snapshot.key)));
+                }
+                part = parts.next() => {
+                    let Some(part) = part else { break };
+                    let (index, start, data) = part?;
+                    file.seek(SeekFrom::Start(start))?;
+                    file.write_all(&data)?;
+
+                    // Flush before recording so the ledger never gets ahead of the file
+                    file.flush()?;
+                    ledger.record_part(index, DownloadLedger::checksum(&data));
+                    ledger.save(tmp_path)?;
+                }
+                _ = ticker.tick() => {
+                    let downloaded = received.load(Ordering::Relaxed);
+                    let elapsed = start_time.elapsed().as_secs_f64();
+                    let rate = if elapsed > 0.0 { downloaded as f64 / elapsed } else { 0.0 };
+                    let progress = if size > 0.0 { downloaded as f64 / size } else { 1.0 };
+
+                    // Update popup state
+                    self.popup_state = PopupState::Downloading(snapshot.clone(), progress as f32, rate);
+                    reporter.report(Progress { fraction: progress as f32, rate });
                 }
-                Err(e) => return Err(anyhow!("Error reading from S3: {}", e)),
             }
         }
-        
+
         debug!("Download complete: {}", snapshot.key);
         self.popup_state = PopupState::Success(format!("Download complete: {}", snapshot.key));
-        
+
         Ok(())
     }
 
@@ -243,3 +847,58 @@ impl SnapshotBrowser {
 
     // Key handling has been moved to RustoredApp
 }
+
+/// Fetch one part of a snapshot into memory
+///
+/// # Arguments
+///
+/// * `store` - Storage backend to read from
+/// * `key` - Snapshot key
+/// * `version_id` - Version of the snapshot to read, or `None` for the current one
+/// * `index` - Part index, used in error messages
+/// * `start` - First byte of the part
+/// * `end` - Last byte of the part (inclusive)
+/// * `received` - Counter of received bytes shared by all parts, for progress
+/// * `reporter` - The download's job, whose share of the transfer budget paces the reads
+///
+/// # Returns
+///
+/// The bytes of the part, which are exactly `end - start + 1` long
+#[allow(clippy::too_many_arguments)]
+async fn fetch_part(
+    store: &dyn SnapshotStore,
+    key: &str,
+    version_id: Option<&str>,
+    index: u64,
+    start: u64,
+    end: u64,
+    received: &AtomicU64,
+    reporter: &crate::progress::ProgressReporter,
+) -> Result<Vec<u8>> {
+    debug!("Fetching part {} of {} (version {:?}, bytes {}-{})", index, key, version_id, start, end);
+    let expected = end - start + 1;
+    let body = match version_id {
+        Some(version_id) => store.read_version_range(key, version_id, start, end).await,
+        None => store.read_range(key, start, end).await,
+    };
+    let mut body = body.map_err(|e| anyhow!("Failed to download part {} of {}: {}", index, key, e))?;
+    let mut data = Vec::with_capacity(expected as usize);
+    let mut buffer = [0; 1024 * 64]; // 64KB buffer
+    loop {
+        match body.read(&mut buffer).await {
+            Ok(0) => break, // End of part
+            Ok(n) => {
+                data.extend_from_slice(&buffer[0..n]);
+                received.fetch_add(n as u64, Ordering::Relaxed);
+                reporter.transfer(n as u64).await;
+            }
+            Err(e) => return Err(anyhow!("Error reading from {}: {}", store.name(), e)),
+        }
+    }
+
+    // A short part would otherwise be recorded as complete
+    if data.len() as u64 != expected {
+        return Err(anyhow!("Part {} of {} was truncated ({} of {} bytes)", index, key, data.len(), expected));
+    }
+    Ok(data)
+}
@@ -0,0 +1,161 @@
+// This module contains bundle restores in the Rustored TUI
+// A bundle is a PostgreSQL snapshot with the Elasticsearch or Qdrant snapshots
+// taken alongside it. Its members are all downloaded first, then restored
+// concurrently inside one maintenance window, each with its own progress.
+
+use crate::bundle::BundleMember;
+use crate::cancel::cancellable;
+use crate::ui::models::{PopupState, RestoreTarget};
+use crate::ui::rustored::RustoredApp;
+use anyhow::{anyhow, Result};
+use log::debug;
+use ratatui::backend::Backend;
+use ratatui::Terminal;
+
+impl RustoredApp {
+    /// Describe a bundle restore for its confirmation: where each member goes and what it changes
+    ///
+    /// A failed impact lookup is logged rather than returned, as for a single restore.
+    ///
+    /// # Arguments
+    ///
+    /// * `members` - The bundle's snapshots, PostgreSQL first
+    pub async fn bundle_lines(&self, members: &[BundleMember]) -> Vec<String> {
+        debug!("Describing bundle restore of {} snapshots", members.len());
+        let mut lines = Vec::new();
+        let mut impacts = Vec::new();
+        for member in members {
+            let name = match member.target {
+                RestoreTarget::Postgres => self.pg_config.db_name.clone(),
+                RestoreTarget::Elasticsearch => self.es_config.target_index(),
+                RestoreTarget::Qdrant => self.qdrant_config.target_collection(),
+                RestoreTarget::OpenSearch => self.os_config.target_index(),
+                RestoreTarget::Meilisearch => self.meili_config.target_index(),
+                RestoreTarget::Weaviate => self.weaviate_config.target_class(),
+                RestoreTarget::Milvus => self.milvus_config.target_collection(),
+                RestoreTarget::DynamoDb => self.dynamodb_config.target_table(),
+                RestoreTarget::InfluxDb => self.influxdb_config.target_bucket(),
+            };
+            lines.push(format!("{}: {} -> {}", member.label(), member.snapshot.key, name.as_deref().unwrap_or("(not configured)")));
+            match self.restore_target_for(&member.target).impact().await {
+                Ok(impact) => impacts.extend(impact.map(|impact| impact.for_snapshot(&member.snapshot.key).lines()).unwrap_or_default()),
+                Err(e) => debug!("Failed to look up restore impact of {}: {}", member.snapshot.key, e),
+            }
+        }
+        lines.extend(impacts);
+        lines
+    }
+
+    /// Restore downloaded bundle members to their targets at once, without touching the UI
+    ///
+    /// Every target must be configured before anything is restored. The
+    /// restores run concurrently inside one maintenance window, each reporting
+    /// progress as `Restore of <key>`. The preset's post-restore SQL and hook
+    /// run after the PostgreSQL restore. A failed member fails the bundle,
+    /// though members already restored stay restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `members` - The bundle's snapshots, PostgreSQL first
+    /// * `paths` - The downloaded file of each member, in the same order
+    ///
+    /// # Returns
+    ///
+    /// The outcome of each member's restore, in the same order
+    pub async fn run_bundle_restore(&self, members: &[BundleMember], paths: &[std::path::PathBuf]) -> Result<Vec<crate::restore::RestoreOutcome>> {
+        debug!("Running bundle restore of {} snapshots", members.len());
+        let targets: Vec<_> = members.iter().map(|member| self.restore_target_for(&member.target)).collect();
+        if let Some((member, target)) = members.iter().zip(&targets).find(|(_, target)| !target.is_configured()) {
+            let required = target.required_fields().join(", ");
+            return Err(anyhow!("{} target not properly configured. Required fields: {}", member.label(), required));
+        }
+
+        let options = &self.restore_options;
+        let window = self.pg_config.db_name.clone().unwrap_or_default();
+        let operation = format!("Restore of bundle {}", members.first().map(|member| member.snapshot.key.as_str()).unwrap_or_default());
+        // Members that finish first keep showing as done until the whole bundle is
+        let reporters: Vec<_> = members.iter().map(|member| self.progress.reporter(&format!("Restore of {}", member.snapshot.key))).collect();
+        let restores = members.iter().zip(&targets).zip(paths).zip(&reporters).map(|(((member, target), path), reporter)| async move {
+            if member.target == RestoreTarget::Postgres {
+                let live = self.pg_config.db_name.as_deref().unwrap_or_default();
+                options.run_pre_hook(&self.hook_vars(&member.target, live, &member.snapshot.key)).await?;
+            }
+            let restored = target.restore_snapshot(path, Some(reporter.callback())).await?;
+            if member.target == RestoreTarget::Postgres {
+                let vars = self.hook_vars(&member.target, &restored, &member.snapshot.key);
+                crate::provenance::record(&self.pg_config, &vars).await;
+                options.run_post_sql(&self.pg_config, &vars).await?;
+                options.run_post_hook(&vars).await?;
+            }
+            Ok(crate::restore::RestoreOutcome { name: restored, dead_letters: target.dead_letters() })
+        });
+        let result = self.maintenance_hooks
+            .run(&window, cancellable(&self.operation, &operation, futures_util::future::try_join_all(restores)))
+            .await;
+        reporters.iter().for_each(|reporter| reporter.finish());
+        if result.as_ref().is_err_and(crate::cancel::is_cancelled) {
+            crate::postgres::finish_partial_drops().await;
+        }
+        result
+    }
+
+    /// Download and restore a bundle from the TUI, showing the progress of every member
+    ///
+    /// # Arguments
+    ///
+    /// * `members` - The confirmed bundle's snapshots, PostgreSQL first
+    /// * `terminal` - The terminal to draw progress on
+    pub async fn restore_bundle<B: Backend>(&mut self, members: &[BundleMember], terminal: &mut Terminal<B>) -> Result<()> {
+        debug!("Restoring bundle of {} snapshots", members.len());
+        use std::time::Duration;
+        let operation = self.begin_operation();
+        let mut paths = Vec::new();
+        for member in members {
+            self.popup_state = PopupState::RestoringBundle(members.to_vec(), paths.len());
+            terminal.draw(|f| crate::ui::renderer::ui::<B>(f, self))?;
+            let tmp_path = crate::download_ledger::temp_download_path(&member.snapshot.key);
+            let download = self.snapshot_browser.download_snapshot(&member.snapshot, &tmp_path);
+            match crate::ui::app::cancel_on_escape(&operation, download).await? {
+                Some(path) => paths.push(std::path::PathBuf::from(path)),
+                None => {
+                    self.popup_state = PopupState::Error(format!("Download of {} failed: {:?}", member.snapshot.key, self.snapshot_browser.popup_state));
+                    return Ok(());
+                }
+            }
+        }
+
+        self.popup_state = PopupState::RestoringBundle(members.to_vec(), paths.len());
+        terminal.draw(|f| crate::ui::renderer::ui::<B>(f, self))?;
+        let mut updates = self.progress.subscribe();
+        let result = {
+            let app: &Self = self;
+            let restore = crate::ui::app::cancel_on_escape(&operation, app.run_bundle_restore(members, &paths));
+            tokio::pin!(restore);
+            let mut ticker = tokio::time::interval(Duration::from_millis(100));
+            loop {
+                tokio::select! {
+                    result = &mut restore => break result,
+                    _ = ticker.tick() => {
+                        if updates.has_changed().unwrap_or(false) {
+                            updates.borrow_and_update();
+                            terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
+                        }
+                    }
+                }
+            }
+        };
+        self.popup_state = match result {
+            Ok(outcomes) => {
+                let mut lines = Vec::new();
+                for (member, outcome) in members.iter().zip(outcomes) {
+                    lines.push(format!("{}: restored {} to {}", member.label(), member.snapshot.key, outcome.name));
+                    lines.extend(outcome.dead_letters.map(|report| report.summary()));
+                }
+                PopupState::RestoreSummary(lines)
+            }
+            Err(e) => PopupState::Error(self.restore_error("Bundle restore", &e)),
+        };
+        terminal.draw(|f| crate::ui::renderer::ui::<B>(f, self))?;
+        Ok(())
+    }
+}
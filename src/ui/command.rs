@@ -0,0 +1,380 @@
+use log::debug;
+use std::io::{IsTerminal, Write};
+
+use crate::presets::RestoreMode;
+use crate::ui::models::{BackupMetadata, RestoreTarget};
+use crate::ui::rustored::RustoredApp;
+
+/// Quote an argument for a POSIX shell, leaving plain words unquoted
+pub fn shell_quote(arg: &str) -> String {
+    debug!("Quoting shell argument");
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Command line builder that skips unset values
+struct Args(Vec<String>);
+
+impl Args {
+    /// Add a flag with a value, quoting the value
+    fn value(&mut self, flag: &str, value: &str) {
+        self.0.push(format!("{} {}", flag, shell_quote(value)));
+    }
+
+    /// Add a flag with a value only if the value is set and non-empty
+    fn opt(&mut self, flag: &str, value: Option<&str>) {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            self.value(flag, value);
+        }
+    }
+
+    /// Add a flag whose secret value is read from an environment variable
+    fn secret(&mut self, flag: &str, value: Option<&str>, env: &str) {
+        if value.is_some_and(|v| !v.is_empty()) {
+            self.0.push(format!("{} \"${}\"", flag, env));
+        }
+    }
+}
+
+/// Build the non-interactive command equivalent to restoring `snapshot` with the current selections
+///
+/// Secrets are never written out; the command references the environment
+/// variable each secret flag falls back to instead (`"$PG_PASSWORD"`).
+///
+/// # Arguments
+///
+/// * `app` - Application state holding the source, target and restore option selections
+/// * `snapshot` - The snapshot to restore
+///
+/// # Returns
+///
+/// A single shell command line using `restore-from-s3`, or `restore` for a local file
+pub fn restore_command(app: &RustoredApp, snapshot: &BackupMetadata) -> String {
+    debug!("Building restore command for snapshot: {}", snapshot.key);
+    if crate::ui::local_file::is_local(app, snapshot) {
+        return local_restore_command(app, snapshot);
+    }
+    let browser = &app.snapshot_browser;
+    let mut args = Args(vec!["rustored".to_string()]);
+
+    // Snapshot source, in the same priority order the browser uses
+    if let Some(sftp) = &browser.sftp_config {
+        args.value("--sftp-host", &sftp.host);
+        args.value("--sftp-port", &sftp.port.to_string());
+        args.opt("--sftp-username", Some(&sftp.username));
+        args.opt("--sftp-private-key", sftp.private_key_path.as_deref());
+        args.secret("--sftp-private-key-passphrase", sftp.private_key_passphrase.as_deref(), "SFTP_PRIVATE_KEY_PASSPHRASE");
+        args.secret("--sftp-password", sftp.password.as_deref(), "SFTP_PASSWORD");
+        args.value("--sftp-prefix", &sftp.prefix);
+    } else if let Some(b2) = &browser.b2_config {
+        args.value("--b2-bucket", &b2.bucket);
+        args.value("--b2-prefix", &b2.prefix);
+        args.opt("--b2-key-id", Some(&b2.key_id));
+        args.secret("--b2-application-key", Some(&b2.application_key), "B2_APPLICATION_KEY");
+    } else if let Some(azure) = &browser.azure_config {
+        args.value("--azure-account", &azure.account);
+        args.opt("--azure-container", Some(&azure.container));
+        args.value("--azure-prefix", &azure.prefix);
+        args.secret("--azure-access-key", Some(&azure.access_key), "AZURE_STORAGE_ACCESS_KEY");
+        args.secret("--azure-sas-token", Some(&azure.sas_token), "AZURE_STORAGE_SAS_TOKEN");
+    } else if let Some(gcs) = &browser.gcs_config {
+        args.value("--gcs-bucket", &gcs.bucket);
+        args.value("--gcs-prefix", &gcs.prefix);
+        args.opt("--gcs-service-account-path", gcs.service_account_path.as_deref());
+        args.secret("--gcs-service-account-key", gcs.service_account_key.as_deref(), "GCS_SERVICE_ACCOUNT_KEY");
+    } else {
+        let s3 = &browser.s3_config;
+        args.opt("--bucket", Some(&s3.bucket));
+        args.opt("--region", Some(&s3.region));
+        args.value("--prefix", &s3.prefix);
+        args.opt("--endpoint-url", Some(&s3.endpoint_url));
+        args.opt("--access-key-id", Some(&s3.access_key_id));
+        args.secret("--secret-access-key", Some(&s3.secret_access_key), "S3_SECRET_ACCESS_KEY");
+        args.secret("--sse-customer-key", Some(&s3.sse_customer_key), "S3_SSE_CUSTOMER_KEY");
+    }
+
+    // Restore target connection
+    let target = match app.restore_target {
+        RestoreTarget::Postgres => {
+            let pg = &app.pg_config;
+            args.opt("--host", pg.host.as_deref());
+            args.opt("--port", pg.port.map(|p| p.to_string()).as_deref());
+            args.opt("--username", pg.username.as_deref());
+            args.secret("--password", pg.password.as_deref(), "PG_PASSWORD");
+            if pg.use_ssl {
+                args.0.push("--use-ssl".to_string());
+            }
+            args.opt("--db-name", pg.db_name.as_deref());
+            args.opt("--target-db", pg.target_db.as_deref());
+            "postgres"
+        }
+        RestoreTarget::Elasticsearch => {
+            args.opt("--es-host", app.es_config.host.as_deref());
+            args.opt("--es-index", app.es_config.index.as_deref());
+            tls_settings(&mut args, &app.es_config.tls);
+            "elasticsearch"
+        }
+        RestoreTarget::Qdrant => {
+            args.opt("--es-host", app.qdrant_config.host.as_deref());
+            args.opt("--es-index", app.qdrant_config.collection.as_deref());
+            args.secret("--qdrant-api-key", app.qdrant_config.api_key.as_deref(), "QDRANT_API_KEY");
+            tls_settings(&mut args, &app.qdrant_config.tls);
+            "qdrant"
+        }
+        RestoreTarget::OpenSearch => {
+            args.opt("--opensearch-host", app.os_config.host.as_deref());
+            args.opt("--opensearch-index", app.os_config.index.as_deref());
+            opensearch_signing(&mut args, app);
+            "opensearch"
+        }
+        RestoreTarget::Meilisearch => {
+            args.opt("--meili-host", app.meili_config.host.as_deref());
+            args.opt("--meili-index", app.meili_config.index.as_deref());
+            meilisearch_auth(&mut args, app);
+            "meilisearch"
+        }
+        RestoreTarget::Weaviate => {
+            args.opt("--weaviate-host", app.weaviate_config.host.as_deref());
+            args.opt("--weaviate-class", app.weaviate_config.class.as_deref());
+            weaviate_auth(&mut args, app);
+            "weaviate"
+        }
+        RestoreTarget::Milvus => {
+            args.opt("--milvus-host", app.milvus_config.host.as_deref());
+            args.opt("--milvus-collection", app.milvus_config.collection.as_deref());
+            milvus_settings(&mut args, app);
+            "milvus"
+        }
+        RestoreTarget::DynamoDb => {
+            args.opt("--dynamodb-table", app.dynamodb_config.table.as_deref());
+            args.opt("--dynamodb-endpoint-url", app.dynamodb_config.endpoint_url.as_deref());
+            dynamodb_region(&mut args, app);
+            "dynamodb"
+        }
+        RestoreTarget::InfluxDb => {
+            args.opt("--influx-host", app.influxdb_config.host.as_deref());
+            args.opt("--influx-bucket", app.influxdb_config.bucket.as_deref());
+            influxdb_settings(&mut args, app);
+            "influxdb"
+        }
+    };
+
+    // The preset supplies masking and hooks; overrides made in the
+    // confirmation popup are spelled out on top of it
+    let preset = match &app.restore_preset {
+        Some(_) => app.restore_presets.find(&snapshot.key).map(|p| p.options.clone()).unwrap_or_default(),
+        None => Default::default(),
+    };
+    if let (Some(_), Some(path)) = (&app.restore_preset, &app.restore_presets.source) {
+        args.value("--presets-file", &path.to_string_lossy());
+    }
+    args.value("restore-from-s3", &snapshot.key);
+    args.value("--target", target);
+    if let Some(version_id) = &snapshot.version_id {
+        args.value("--version-id", version_id);
+    }
+    if app.restore_preset.is_none() {
+        args.0.push("--no-preset".to_string());
+    }
+    if app.restore_options.jobs != preset.jobs {
+        args.value("--jobs", &app.restore_options.jobs.unwrap_or(1).to_string());
+    }
+    match (app.restore_options.drop_indexes, preset.drop_indexes) {
+        (true, false) => args.0.push("--drop-indexes".to_string()),
+        (false, true) => args.0.push("--drop-indexes=false".to_string()),
+        _ => {}
+    }
+    match (app.restore_options.compare_live, preset.compare_live) {
+        (true, false) => args.0.push("--compare".to_string()),
+        (false, true) => args.0.push("--compare=false".to_string()),
+        _ => {}
+    }
+    match (app.restore_options.globals, preset.globals) {
+        (true, false) => args.0.push("--globals".to_string()),
+        (false, true) => args.0.push("--globals=false".to_string()),
+        _ => {}
+    }
+    match (app.restore_options.drop_on_cancel, preset.drop_on_cancel) {
+        (true, false) => args.0.push("--drop-on-cancel".to_string()),
+        (false, true) => args.0.push("--drop-on-cancel=false".to_string()),
+        _ => {}
+    }
+    match (app.restore_options.gentle, preset.gentle) {
+        (true, false) => args.0.push("--gentle".to_string()),
+        (false, true) => args.0.push("--gentle=false".to_string()),
+        _ => {}
+    }
+    if app.restore_options.mode != RestoreMode::New {
+        args.value("--into-existing", &app.restore_options.mode.to_string());
+    }
+    for table in &app.restore_options.include_tables {
+        args.value("--include-table", table);
+    }
+    for table in &app.restore_options.exclude_tables {
+        args.value("--exclude-table", table);
+    }
+    if app.restore_options.recipes != preset.recipes {
+        if app.restore_options.recipes.is_empty() {
+            args.value("--recipe", "none");
+        }
+        for recipe in &app.restore_options.recipes {
+            let params: Vec<String> = recipe.params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            match params.is_empty() {
+                true => args.value("--recipe", &recipe.name),
+                false => args.value("--recipe", &format!("{}:{}", recipe.name, params.join(","))),
+            }
+        }
+    }
+
+    args.0.join(" ")
+}
+
+/// Build the `restore` command for a local file, which takes the preset matching the destination name
+fn local_restore_command(app: &RustoredApp, snapshot: &BackupMetadata) -> String {
+    debug!("Building local restore command for {}", snapshot.key);
+    let mut args = Args(vec!["rustored".to_string()]);
+    let source_db = crate::hooks::source_db_from_key(&snapshot.key);
+    let (target, name) = match app.restore_target {
+        RestoreTarget::Postgres => {
+            let pg = &app.pg_config;
+            args.opt("--host", pg.host.as_deref());
+            args.opt("--port", pg.port.map(|p| p.to_string()).as_deref());
+            args.opt("--username", pg.username.as_deref());
+            args.secret("--password", pg.password.as_deref(), "PG_PASSWORD");
+            if pg.use_ssl {
+                args.0.push("--use-ssl".to_string());
+            }
+            ("postgres", pg.target_db.clone().unwrap_or(source_db))
+        }
+        RestoreTarget::Elasticsearch => {
+            tls_settings(&mut args, &app.es_config.tls);
+            ("elasticsearch", app.es_config.target_index().unwrap_or(source_db))
+        }
+        RestoreTarget::Qdrant => {
+            tls_settings(&mut args, &app.qdrant_config.tls);
+            ("qdrant", app.qdrant_config.target_collection().unwrap_or(source_db))
+        }
+        RestoreTarget::OpenSearch => {
+            opensearch_signing(&mut args, app);
+            ("opensearch", app.os_config.target_index().unwrap_or(source_db))
+        }
+        RestoreTarget::Meilisearch => {
+            meilisearch_auth(&mut args, app);
+            ("meilisearch", app.meili_config.target_index().unwrap_or(source_db))
+        }
+        RestoreTarget::Weaviate => {
+            weaviate_auth(&mut args, app);
+            ("weaviate", app.weaviate_config.target_class().unwrap_or(source_db))
+        }
+        RestoreTarget::Milvus => {
+            milvus_settings(&mut args, app);
+            ("milvus", app.milvus_config.target_collection().unwrap_or(source_db))
+        }
+        RestoreTarget::DynamoDb => {
+            dynamodb_region(&mut args, app);
+            ("dynamodb", app.dynamodb_config.target_table().unwrap_or(source_db))
+        }
+        RestoreTarget::InfluxDb => {
+            influxdb_settings(&mut args, app);
+            ("influxdb", app.influxdb_config.target_bucket().unwrap_or(source_db))
+        }
+    };
+    args.value("restore", &name);
+    args.0.push(shell_quote(&snapshot.key));
+    args.value("--target", target);
+    match app.restore_target {
+        RestoreTarget::Postgres => {}
+        RestoreTarget::Elasticsearch => args.opt("--es-host", app.es_config.host.as_deref()),
+        RestoreTarget::Qdrant => {
+            args.opt("--es-host", app.qdrant_config.host.as_deref());
+            args.secret("--qdrant-api-key", app.qdrant_config.api_key.as_deref(), "QDRANT_API_KEY");
+        }
+        RestoreTarget::OpenSearch => args.opt("--es-host", app.os_config.host.as_deref()),
+        RestoreTarget::Meilisearch => args.opt("--es-host", app.meili_config.host.as_deref()),
+        RestoreTarget::Weaviate => args.opt("--es-host", app.weaviate_config.host.as_deref()),
+        RestoreTarget::Milvus => args.opt("--es-host", app.milvus_config.host.as_deref()),
+        RestoreTarget::DynamoDb => args.opt("--es-host", app.dynamodb_config.endpoint_url.as_deref()),
+        RestoreTarget::InfluxDb => args.opt("--es-host", app.influxdb_config.host.as_deref()),
+    }
+    args.0.join(" ")
+}
+
+/// Add the CA bundle, client certificate and skip-verify toggle of the Elasticsearch or Qdrant target
+fn tls_settings(args: &mut Args, tls: &crate::ui::models::TlsConfig) {
+    debug!("Adding TLS arguments");
+    args.opt("--es-ca-cert", tls.ca_cert_path.as_deref());
+    args.opt("--es-client-cert", tls.client_cert_path.as_deref());
+    args.opt("--es-client-key", tls.client_key_path.as_deref());
+    if tls.insecure_skip_verify {
+        args.0.push("--es-insecure-skip-verify".to_string());
+    }
+}
+
+/// Add the SigV4 region and service of the OpenSearch target, leaving out the default service
+fn opensearch_signing(args: &mut Args, app: &RustoredApp) {
+    debug!("Adding OpenSearch signing arguments");
+    args.opt("--opensearch-region", app.os_config.region.as_deref());
+    if app.os_config.service != crate::sigv4::SERVICE_DOMAIN {
+        args.value("--opensearch-service", &app.os_config.service);
+    }
+}
+
+/// Add the Meilisearch API key, kept out of the command line, and primary key
+fn meilisearch_auth(args: &mut Args, app: &RustoredApp) {
+    debug!("Adding Meilisearch key arguments");
+    args.secret("--meili-api-key", app.meili_config.api_key.as_deref(), "MEILI_API_KEY");
+    args.opt("--meili-primary-key", app.meili_config.primary_key.as_deref());
+}
+
+/// Add the Weaviate API key and OIDC client secret, kept out of the command line, and OIDC scope
+fn weaviate_auth(args: &mut Args, app: &RustoredApp) {
+    debug!("Adding Weaviate credential arguments");
+    args.secret("--weaviate-api-key", app.weaviate_config.api_key.as_deref(), "WEAVIATE_API_KEY");
+    args.secret("--weaviate-oidc-client-secret", app.weaviate_config.oidc_client_secret.as_deref(), "WEAVIATE_OIDC_CLIENT_SECRET");
+    args.opt("--weaviate-oidc-scope", app.weaviate_config.oidc_scope.as_deref());
+}
+
+/// Add the Milvus token, kept out of the command line, database, vector field and metric
+fn milvus_settings(args: &mut Args, app: &RustoredApp) {
+    debug!("Adding Milvus setting arguments");
+    args.secret("--milvus-token", app.milvus_config.token.as_deref(), "MILVUS_TOKEN");
+    args.opt("--milvus-db", app.milvus_config.db_name.as_deref());
+    args.opt("--milvus-vector-field", app.milvus_config.vector_field.as_deref());
+    args.opt("--milvus-metric", app.milvus_config.metric.as_deref());
+}
+
+/// Add the AWS region of the DynamoDB table
+fn dynamodb_region(args: &mut Args, app: &RustoredApp) {
+    debug!("Adding DynamoDB region argument");
+    args.opt("--dynamodb-region", app.dynamodb_config.region.as_deref());
+}
+
+/// Add the InfluxDB organization, token, kept out of the command line, and precision
+fn influxdb_settings(args: &mut Args, app: &RustoredApp) {
+    debug!("Adding InfluxDB setting arguments");
+    args.opt("--influx-org", app.influxdb_config.org.as_deref());
+    args.secret("--influx-token", app.influxdb_config.token.as_deref(), "INFLUX_TOKEN");
+    args.opt("--influx-precision", app.influxdb_config.precision.as_deref());
+}
+
+/// Copy text to the terminal's clipboard with an OSC 52 escape sequence
+///
+/// Most modern terminals (and tmux with `set-clipboard on`) honour this, even
+/// over SSH. It fails when stdout is not a terminal. A terminal that ignores
+/// the sequence cannot be told apart from one that honours it.
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    debug!("Copying {} bytes to the clipboard", text.len());
+    use base64::Engine;
+    let mut stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return Err(std::io::Error::other("stdout is not a terminal"));
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
@@ -0,0 +1,84 @@
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Line},
+    widgets::{Block, Borders, Paragraph, Table, Row, Cell},
+    Frame,
+};
+
+use log::debug;
+use crate::ui::models::{AzureConfig, InputMode};
+use crate::ui::rustored::RustoredApp;
+
+/// Render Azure Blob settings section
+///
+/// Shown in place of the S3 settings when snapshots are read from Azure.
+pub fn render_azure_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area: Rect) {
+    debug!("Starting to render Azure settings in area: {:?}", area);
+    let azure_settings_block = Block::default()
+        .title("Azure Blob Settings")
+        .borders(Borders::ALL);
+    f.render_widget(azure_settings_block, area);
+
+    let Some(config) = &app.snapshot_browser.azure_config else {
+        debug!("No Azure config set, nothing to render");
+        return;
+    };
+
+    // As per TDD rule #10, navigation help text should be at the bottom
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(area);
+
+    // Create a row for each field, masking secrets unless they are being edited
+    let rows: Vec<Row> = AzureConfig::focus_fields()
+        .iter()
+        .map(|field| {
+            let is_focused = app.focus == *field;
+            let is_editing = is_focused && app.input_mode == InputMode::Editing;
+            let value = config.get_field_display(*field, is_editing, &app.input_buffer);
+            let value_style = if is_editing {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else if is_focused {
+                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let label = field.to_string().replace("Azure ", "");
+            Row::new(vec![
+                Cell::from(label).style(Style::default().fg(Color::Blue)),
+                Cell::from(value).style(value_style),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [Constraint::Percentage(30), Constraint::Percentage(70)]
+    )
+    .column_spacing(1)
+    .header(Row::new(vec![
+        Cell::from(Span::styled("Setting", Style::default().add_modifier(Modifier::BOLD))),
+        Cell::from(Span::styled("Value", Style::default().add_modifier(Modifier::BOLD)))
+    ]));
+    f.render_widget(table, chunks[0]);
+
+    // Create the help legend and place it at the bottom as per TDD rule #10
+    let mut help_items = vec![
+        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate "),
+    ];
+    if config.verify_settings().is_ok() {
+        help_items.push(Span::styled("[t]", Style::default().fg(Color::Yellow)));
+        help_items.push(Span::raw(" Test Connection "));
+    }
+    let help_legend = Paragraph::new(Line::from(help_items))
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Left);
+    f.render_widget(help_legend, chunks[1]);
+
+    debug!("Finished rendering Azure settings");
+}
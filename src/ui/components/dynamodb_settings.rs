@@ -0,0 +1,130 @@
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Line},
+    widgets::{Block, Borders, Paragraph, Table, Row, Cell},
+    Frame,
+};
+
+use log::debug;
+use crate::ui::models::{FocusField, InputMode};
+use crate::ui::rustored::RustoredApp;
+
+/// Render DynamoDB settings component
+/// 
+/// This function is responsible for rendering the DynamoDB settings UI component.
+/// It displays all DynamoDB connection parameters and highlights the currently focused field.
+/// Credentials come from the default AWS credential chain, so none are shown.
+/// 
+/// # Arguments
+/// 
+/// * `f` - A mutable reference to the frame for rendering
+/// * `app` - A reference to the application state
+/// * `area` - The area in which to render the component
+pub fn render_dynamodb_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area: Rect) {
+    // Log the start of rendering DynamoDB settings
+    debug!("Starting to render DynamoDB settings in area: {:?}", area);
+    // Log the rendering of DynamoDB settings
+    debug!("Rendering DynamoDB settings in area: {:?}", area);
+
+    // Create a block for the DynamoDB settings
+    let block = Block::default()
+        .title(" DynamoDB Settings ")
+        .borders(Borders::ALL)
+        .style(Style::default());
+
+    // Create a layout for the settings fields
+    // As per TDD rule #10, navigation help text should be at the bottom
+    let inner_area = block.inner(area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Settings fields (use all remaining space)
+            Constraint::Length(1), // Spacer
+            Constraint::Length(1), // Help text at the bottom (TDD rule #10)
+        ])
+        .split(inner_area);
+    
+    debug!("Created DynamoDB settings layout with navigation help at the bottom (TDD rule #10)");
+
+    // Help text will be rendered at the bottom as per TDD rule #10
+
+    // Prepare the table rows for DynamoDB settings
+    let mut rows = Vec::new();
+
+    // Define the fields to display
+    let fields = [
+        ("Table", app.dynamodb_config.get_field_value(FocusField::DynamoDbTable), FocusField::DynamoDbTable),
+        ("Region", app.dynamodb_config.get_field_value(FocusField::DynamoDbRegion), FocusField::DynamoDbRegion),
+        ("Endpoint URL", app.dynamodb_config.get_field_value(FocusField::DynamoDbEndpointUrl), FocusField::DynamoDbEndpointUrl),
+    ];
+
+    // Create a row for each field
+    for (label, value, field) in &fields {
+        // Determine if this field is focused
+        let is_focused = app.focus == *field;
+        
+        // Style for the label
+        let label_style = Style::default().fg(Color::Blue);
+        
+        // Style for the value - highlight if focused
+        let value_style = if is_focused {
+            if app.input_mode == InputMode::Editing {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            }
+        } else {
+            Style::default().fg(Color::White)
+        };
+        
+        // Create the row with styled cells
+        let row = Row::new(vec![
+            Cell::from(label.to_string()).style(label_style),
+            Cell::from(value.clone()).style(value_style),
+        ]);
+        
+        rows.push(row);
+    }
+
+    // Create and render the table
+    // Create the table with rows and column widths
+    let table = Table::new(
+        rows,
+        [Constraint::Percentage(30), Constraint::Percentage(70)]
+    )
+    .column_spacing(1)
+    .style(Style::default())
+    .header(Row::new(vec![
+        Cell::from(Span::styled("Setting", Style::default().add_modifier(Modifier::BOLD))),
+        Cell::from(Span::styled("Value", Style::default().add_modifier(Modifier::BOLD)))
+    ]));
+
+    // Render the block first to create the border
+    f.render_widget(block, area);
+    // Then render the table inside the block's inner area
+    f.render_widget(table, chunks[0]);
+    
+    // Render the help text at the bottom as per TDD rule #10
+    // Always show navigation help (using the same format as S3 settings)
+    let mut help_items = vec![
+        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate "),
+    ];
+    
+    // Show test connection option (using [t] consistently across all components)
+    help_items.push(Span::styled("[t]", Style::default().fg(Color::Yellow)));
+    help_items.push(Span::raw(" Test Connection "));
+    
+    let help_text = Line::from(help_items);
+    
+    let help = Paragraph::new(help_text)
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Left);
+    
+    debug!("Rendering navigation help text at the bottom of DynamoDB settings (TDD rule #10)");
+    f.render_widget(help, chunks[2]);
+    
+    debug!("Finished rendering DynamoDB settings");
+}
@@ -105,11 +105,11 @@ pub fn render_elasticsearch_settings<B: Backend>(f: &mut Frame, app: &RustoredAp
     f.render_widget(table, chunks[0]);
     
     // Render the help text at the bottom as per TDD rule #10
-    let mut help_items = Vec::new();
-    
     // Always show navigation help (using the same format as S3 settings)
-    help_items.push(Span::styled("↑↓", Style::default().fg(Color::Yellow)));
-    help_items.push(Span::raw(" Navigate "));
+    let mut help_items = vec![
+        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate "),
+    ];
     
     // Show test connection option (using [t] consistently across all components)
     help_items.push(Span::styled("[t]", Style::default().fg(Color::Yellow)));
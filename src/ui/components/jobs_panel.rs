@@ -0,0 +1,58 @@
+use ratatui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use log::debug;
+
+use crate::bandwidth::format_rate;
+use crate::progress::Progress;
+use crate::ui::rustored::RustoredApp;
+
+/// Lines of the jobs panel: each running job's progress and rate, and its share of the transfer budget
+///
+/// # Arguments
+///
+/// * `jobs` - The running jobs, as on the progress board
+/// * `fair_share` - Bytes per second each transferring job gets, when the budget is limited
+pub fn job_lines(jobs: &[(String, Progress)], fair_share: Option<f64>) -> Vec<String> {
+    debug!("Describing {} running jobs", jobs.len());
+    jobs.iter()
+        .map(|(job, progress)| {
+            let mut line = format!("{}: {:.1}%", job, progress.fraction * 100.0);
+            if progress.rate > 0.0 {
+                line.push_str(&format!(" at {}", format_rate(progress.rate)));
+                if let Some(share) = fair_share {
+                    line.push_str(&format!(" (share {})", format_rate(share)));
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+/// Title of the jobs panel, with the combined rate and the limit it shares
+pub fn jobs_title(total_rate: f64, limit: Option<u64>) -> String {
+    debug!("Building jobs panel title");
+    match limit {
+        Some(limit) => format!("Jobs ({} of {} limit)", format_rate(total_rate), format_rate(limit as f64)),
+        None => format!("Jobs ({})", format_rate(total_rate)),
+    }
+}
+
+/// Render the running jobs below the snapshot list
+pub fn render_jobs_panel<B: Backend>(f: &mut Frame, app: &RustoredApp, area: Rect) {
+    debug!("Rendering jobs panel in area: {:?}", area);
+    let budget = app.progress.budget();
+    let lines: Vec<Line> = job_lines(&app.progress.jobs(), budget.fair_share())
+        .into_iter()
+        .map(|line| Line::from(vec![Span::raw(line)]))
+        .collect();
+    let title = jobs_title(app.progress.total_rate(), budget.limit());
+    let panel = Paragraph::new(lines)
+        .block(Block::default().title(title).borders(Borders::ALL).style(Style::default().fg(Color::Cyan)));
+    f.render_widget(panel, area);
+}
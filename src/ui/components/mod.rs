@@ -1,17 +1,33 @@
 // Import all component modules
 pub mod s3_settings;
+pub mod azure_settings;
 pub mod restore_target;
 pub mod snapshot_list;
 pub mod popups;
 pub mod postgres_settings;
 pub mod elasticsearch_settings;
 pub mod qdrant_settings;
+pub mod opensearch_settings;
+pub mod meilisearch_settings;
+pub mod weaviate_settings;
+pub mod milvus_settings;
+pub mod dynamodb_settings;
+pub mod influxdb_settings;
+pub mod jobs_panel;
 
 // Re-export all components for easier imports
 pub use s3_settings::*;
+pub use azure_settings::*;
 pub use restore_target::*;
 pub use snapshot_list::*;
 pub use popups::*;
 pub use postgres_settings::*;
 pub use elasticsearch_settings::*;
 pub use qdrant_settings::*;
+pub use opensearch_settings::*;
+pub use meilisearch_settings::*;
+pub use weaviate_settings::*;
+pub use milvus_settings::*;
+pub use dynamodb_settings::*;
+pub use influxdb_settings::*;
+pub use jobs_panel::*;
@@ -0,0 +1,131 @@
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Line},
+    widgets::{Block, Borders, Paragraph, Table, Row, Cell},
+    Frame,
+};
+
+use log::debug;
+use crate::ui::models::{FocusField, InputMode};
+use crate::ui::rustored::RustoredApp;
+
+/// Render OpenSearch settings component
+/// 
+/// This function is responsible for rendering the OpenSearch settings UI component.
+/// It displays all OpenSearch connection parameters and highlights the currently focused field.
+/// Requests are signed with SigV4 when an AWS region is set.
+/// 
+/// # Arguments
+/// 
+/// * `f` - A mutable reference to the frame for rendering
+/// * `app` - A reference to the application state
+/// * `area` - The area in which to render the component
+pub fn render_opensearch_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area: Rect) {
+    // Log the start of rendering OpenSearch settings
+    debug!("Starting to render OpenSearch settings in area: {:?}", area);
+    // Log the rendering of OpenSearch settings
+    debug!("Rendering OpenSearch settings in area: {:?}", area);
+
+    // Create a block for the OpenSearch settings
+    let block = Block::default()
+        .title(" OpenSearch Settings ")
+        .borders(Borders::ALL)
+        .style(Style::default());
+
+    // Create a layout for the settings fields
+    // As per TDD rule #10, navigation help text should be at the bottom
+    let inner_area = block.inner(area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Settings fields (use all remaining space)
+            Constraint::Length(1), // Spacer
+            Constraint::Length(1), // Help text at the bottom (TDD rule #10)
+        ])
+        .split(inner_area);
+    
+    debug!("Created OpenSearch settings layout with navigation help at the bottom (TDD rule #10)");
+
+    // Help text will be rendered at the bottom as per TDD rule #10
+
+    // Prepare the table rows for OpenSearch settings
+    let mut rows = Vec::new();
+
+    // Define the fields to display
+    let fields = [
+        ("Host", app.os_config.get_field_value(FocusField::OsHost), FocusField::OsHost),
+        ("Index", app.os_config.get_field_value(FocusField::OsIndex), FocusField::OsIndex),
+        ("AWS Region", app.os_config.get_field_value(FocusField::OsRegion), FocusField::OsRegion),
+        ("Service", app.os_config.get_field_value(FocusField::OsService), FocusField::OsService),
+    ];
+
+    // Create a row for each field
+    for (label, value, field) in &fields {
+        // Determine if this field is focused
+        let is_focused = app.focus == *field;
+        
+        // Style for the label
+        let label_style = Style::default().fg(Color::Blue);
+        
+        // Style for the value - highlight if focused
+        let value_style = if is_focused {
+            if app.input_mode == InputMode::Editing {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            }
+        } else {
+            Style::default().fg(Color::White)
+        };
+        
+        // Create the row with styled cells
+        let row = Row::new(vec![
+            Cell::from(label.to_string()).style(label_style),
+            Cell::from(value.clone()).style(value_style),
+        ]);
+        
+        rows.push(row);
+    }
+
+    // Create and render the table
+    // Create the table with rows and column widths
+    let table = Table::new(
+        rows,
+        [Constraint::Percentage(30), Constraint::Percentage(70)]
+    )
+    .column_spacing(1)
+    .style(Style::default())
+    .header(Row::new(vec![
+        Cell::from(Span::styled("Setting", Style::default().add_modifier(Modifier::BOLD))),
+        Cell::from(Span::styled("Value", Style::default().add_modifier(Modifier::BOLD)))
+    ]));
+
+    // Render the block first to create the border
+    f.render_widget(block, area);
+    // Then render the table inside the block's inner area
+    f.render_widget(table, chunks[0]);
+    
+    // Render the help text at the bottom as per TDD rule #10
+    // Always show navigation help (using the same format as S3 settings)
+    let mut help_items = vec![
+        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate "),
+    ];
+    
+    // Show test connection option (using [t] consistently across all components)
+    help_items.push(Span::styled("[t]", Style::default().fg(Color::Yellow)));
+    help_items.push(Span::raw(" Test Connection "));
+    
+    let help_text = Line::from(help_items);
+    
+    let help = Paragraph::new(help_text)
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Left);
+    
+    debug!("Rendering navigation help text at the bottom of OpenSearch settings (TDD rule #10)");
+    f.render_widget(help, chunks[2]);
+    
+    debug!("Finished rendering OpenSearch settings");
+}
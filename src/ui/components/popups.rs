@@ -8,6 +8,7 @@ use ratatui::{
 };
 use log::debug;
 
+use crate::download_ledger::{temp_download_path, DownloadLedger};
 use crate::ui::layouts::centered_rect;
 use crate::ui::models::PopupState;
 use crate::ui::rustored::RustoredApp;
@@ -19,19 +20,214 @@ pub fn render_popups<B: Backend>(f: &mut Frame, app: &RustoredApp) {
     match &app.popup_state {
         PopupState::ConfirmRestore(snapshot) => {
             debug!("Rendering confirm restore popup for snapshot: {}", snapshot.key);
-            let area = centered_rect(60, 5, f.size());
+            // What the restore will change in an existing Elasticsearch index or Qdrant collection
+            let impact: Vec<String> = app.restore_impact.as_ref().map(|impact| impact.lines()).unwrap_or_default();
+            // How the snapshot was taken, from its backup manifest
+            let manifest: Vec<String> = app.snapshot_browser.manifests.get(&snapshot.key).map(|manifest| manifest.lines()).unwrap_or_default();
+            // Index, collection and field renames from the restore mapping
+            let mapping = app.mapping_lines();
+            let area = centered_rect(60, 12 + (impact.len() + manifest.len() + mapping.len()) as u16, f.size());
             debug!("Popup area: {:?}", area);
             // Clear the area where the popup will be rendered
             f.render_widget(ratatui::widgets::Clear, area);
+            let preset = match &app.restore_preset {
+                Some(pattern) => format!("Preset: {}", pattern),
+                None => "Preset: none".to_string(),
+            };
+            // Mention a partial download that confirming will pick up again
+            let resume = DownloadLedger::resumable_bytes(
+                &temp_download_path(&snapshot.key),
+                &snapshot.key,
+                snapshot.size.max(0) as u64,
+                snapshot.last_modified,
+                snapshot.etag.as_deref(),
+            )
+            .filter(|&done| done > 0 && snapshot.size > 0)
+            .map(|done| format!("Resumes partial download at {:.0}%", done as f64 * 100.0 / snapshot.size as f64))
+            .unwrap_or_default();
+            let encryption = app.snapshot_browser.selected_encryption
+                .as_ref()
+                .map(|encryption| format!("Encryption: {}", encryption))
+                .unwrap_or_default();
+            // Server of the profile the restore goes to, when it lists several
+            let server = match (&app.pg_server, app.restore_target == crate::ui::models::RestoreTarget::Postgres) {
+                (Some(name), true) => format!("Server: {} ({})", name, app.pg_config.host.as_deref().unwrap_or_default()),
+                _ => String::new(),
+            };
+            let title = match &snapshot.version_id {
+                Some(version_id) => format!("Restore snapshot: {} (version {})", snapshot.key, version_id),
+                None => format!("Restore snapshot: {}", snapshot.key),
+            };
+            let mut text = vec![
+                Line::from(vec![Span::raw(title)]),
+                Line::from(vec![Span::raw(encryption)]),
+                Line::from(vec![Span::styled(server, Style::default().fg(Color::Cyan))]),
+            ];
+            text.extend(manifest.into_iter().map(|line| Line::from(vec![Span::raw(line)])));
+            text.extend([
+                Line::from(vec![Span::styled(preset, Style::default().fg(Color::Cyan))]),
+                Line::from(vec![Span::raw(app.restore_options.summary())]),
+                Line::from(vec![Span::raw(app.estimate_line(snapshot))]),
+                Line::from(vec![Span::styled(resume, Style::default().fg(Color::Yellow))]),
+            ]);
+            text.extend(mapping.into_iter().map(|line| Line::from(vec![Span::styled(line, Style::default().fg(Color::Cyan))])));
+            text.extend(impact.into_iter().map(|line| Line::from(vec![Span::styled(line, Style::default().fg(Color::Yellow))])));
+            text.push(Line::from(vec![Span::raw("[+/-] jobs  [i] drop indexes  [c] compare  [l] gentle  [r] recipes  [t] tables  [v] preview  [e] existing db  [s] server  [p] preset  [g] command")]));
+            text.push(Line::from(vec![Span::raw("Press 'y' to confirm, 'n' to cancel")]));
+            let popup = Paragraph::new(text)
+            .block(Block::default().title("Confirm Restore").borders(Borders::ALL))
+            .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+            debug!("Finished rendering confirm restore popup");
+        }
+        PopupState::SelectRecipes(_, selected) => {
+            let area = centered_rect(70, crate::recipes::RECIPES.len() as u16 + 5, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = crate::recipes::RECIPES.iter().enumerate().map(|(i, recipe)| {
+                let chosen = app.restore_options.recipes.iter().any(|chosen| chosen.name == recipe.name);
+                let line = format!(
+                    "{} [{}] {:<24} {}",
+                    if i == *selected { ">" } else { " " },
+                    if chosen { "x" } else { " " },
+                    recipe.name,
+                    recipe.description,
+                );
+                let style = if i == *selected { Style::default().fg(Color::Yellow) } else { Style::default() };
+                Line::from(vec![Span::styled(line, style)])
+            }).collect();
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw("[space] toggle  Enter/Esc back; recipes run after the restore, in the order chosen")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Post-Restore Recipes").borders(Borders::ALL));
+            f.render_widget(popup, area);
+        }
+        PopupState::SelectServer(snapshot, selected) => {
+            let area = centered_rect(70, app.pg_servers.len() as u16 + 5, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = app.pg_servers.iter().enumerate().map(|(i, server)| {
+                let line = format!("{} {}", if i == *selected { ">" } else { " " }, server.label());
+                let style = match (i == *selected, server.environment.as_deref().is_some_and(crate::profiles::is_production)) {
+                    (true, _) => Style::default().fg(Color::Yellow),
+                    (false, true) => Style::default().fg(Color::Red),
+                    (false, false) => Style::default(),
+                };
+                Line::from(vec![Span::styled(line, style)])
+            }).collect();
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw("Enter restore onto this server  Esc keep the current settings")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title(format!("Restore {} onto", snapshot.key)).borders(Borders::ALL));
+            f.render_widget(popup, area);
+        }
+        PopupState::SelectTables(snapshot, selection) => {
+            let visible = selection.rows.len().clamp(1, 16);
+            let area = centered_rect(70, visible as u16 + 5, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            // Scroll so the highlighted row stays in view
+            let offset = selection.cursor.saturating_sub(visible - 1);
+            let mut text: Vec<Line> = selection.rows.iter().enumerate().skip(offset).take(visible).map(|(i, row)| {
+                let line = format!(
+                    "{} [{}] {}",
+                    if i == selection.cursor { ">" } else { " " },
+                    if row.ticked { "x" } else { " " },
+                    match &row.name {
+                        None => format!("{}.*", row.schema),
+                        Some(name) => format!("    {}", name),
+                    },
+                );
+                let style = match (i == selection.cursor, &row.name) {
+                    (true, _) => Style::default().fg(Color::Yellow),
+                    (false, None) => Style::default().fg(Color::Cyan),
+                    (false, Some(_)) => Style::default(),
+                };
+                Line::from(vec![Span::styled(line, style)])
+            }).collect();
+            let (ticked, total) = selection.counts();
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw(format!("{} of {} tables  [space] toggle  [a] all  Enter apply  Esc back", ticked, total))]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title(format!("Tables of {}", snapshot.key)).borders(Borders::ALL));
+            f.render_widget(popup, area);
+        }
+        PopupState::RestorePreview(snapshot, lines, scroll) => {
+            let visible = lines.len().saturating_sub(1).clamp(1, 16);
+            let area = centered_rect(70, visible as u16 + 6, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            // The counts stay at the top while the objects scroll
+            let (summary, objects) = lines.split_first().map_or(("", &[][..]), |(summary, objects)| (summary.as_str(), objects));
+            let mut text = vec![Line::from(vec![Span::styled(summary, Style::default().fg(Color::Cyan))])];
+            text.extend(objects.iter().skip(*scroll).take(visible).map(|line| Line::from(vec![Span::raw(line.as_str())])));
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw("Nothing is restored until confirmed  ↑↓ scroll  Enter/Esc back")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title(format!("Restore Preview of {}", snapshot.key)).borders(Borders::ALL));
+            f.render_widget(popup, area);
+        }
+        PopupState::RestorePlan(_, lines) => {
+            let area = centered_rect(70, lines.len() as u16 + 5, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = lines.iter().map(|line| {
+                let (label, value) = line.split_once(": ").unwrap_or(("", line.as_str()));
+                // Too little free disk for the download stands out
+                let style = if value.contains("but only") { Style::default().fg(Color::Red) } else { Style::default() };
+                Line::from(vec![Span::styled(format!("{:>11} ", label), Style::default().fg(Color::Cyan)), Span::styled(value, style)])
+            }).collect();
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::styled("[ Run plan ]", Style::default().fg(Color::Yellow)), Span::raw("  Enter/y to run, Esc to change the restore")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Restore Plan").borders(Borders::ALL));
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmArchiveRestore(snapshot, tier) => {
+            let area = centered_rect(60, 8, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let class = snapshot.storage_class.as_deref().unwrap_or_default();
             let popup = Paragraph::new(vec![
-                Line::from(vec![Span::raw(format!("Restore snapshot: {}", snapshot.key))]),
+                Line::from(vec![Span::raw(format!("{} is archived in {}", snapshot.key, class))]),
+                Line::from(vec![Span::raw("It must be restored from the archive before it can be downloaded")]),
+                Line::from(vec![Span::styled(
+                    format!("Tier: {} (typically {})", tier, tier.typical_wait(class)),
+                    Style::default().fg(Color::Cyan),
+                )]),
                 Line::from(vec![]),
-                Line::from(vec![Span::raw("Press 'y' to confirm, 'n' to cancel")]),
+                Line::from(vec![Span::raw("[t] tier  Press 'y' to request the restore, 'n' to cancel")]),
             ])
-            .block(Block::default().title("Confirm Restore").borders(Borders::ALL))
+            .block(Block::default().title("Archived Snapshot").borders(Borders::ALL).style(Style::default().fg(Color::Yellow)))
             .alignment(Alignment::Center);
             f.render_widget(popup, area);
-            debug!("Finished rendering confirm restore popup");
+        }
+        PopupState::VersionHistory(key, versions, selected) => {
+            let visible = versions.len().min(14);
+            let area = centered_rect(80, visible as u16 + 4, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            // Scroll so the selected version stays in view
+            let offset = selected.saturating_sub(visible.saturating_sub(1));
+            let mut text: Vec<Line> = versions.iter().enumerate().skip(offset).take(visible).map(|(i, version)| {
+                let date = chrono::DateTime::from_timestamp(version.last_modified as i64, 0).unwrap_or_default();
+                let line = format!(
+                    "{} {}  {:>10.2} MB  {:<12} {}{}",
+                    if i == *selected { ">" } else { " " },
+                    date.format("%Y-%m-%d %H:%M:%S"),
+                    version.size as f64 / 1024.0 / 1024.0,
+                    version.storage_class.as_deref().unwrap_or_default(),
+                    version.version_id.as_deref().unwrap_or_default(),
+                    if i == 0 { " (current)" } else { "" },
+                );
+                let style = if i == *selected { Style::default().fg(Color::Green) } else { Style::default() };
+                Line::from(vec![Span::styled(line, style)])
+            }).collect();
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw("Up/Down to select, Enter to restore this version, Esc to close")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title(format!("Versions of {}", key)).borders(Borders::ALL));
+            f.render_widget(popup, area);
         }
         PopupState::Downloading(snapshot, progress, rate) => {
             let area = centered_rect(60, 5, f.size());
@@ -63,6 +259,274 @@ pub fn render_popups<B: Backend>(f: &mut Frame, app: &RustoredApp) {
             .alignment(Alignment::Center);
             f.render_widget(popup, area);
         }
+        PopupState::GeneratedCommand(command, copied) => {
+            let area = centered_rect(80, 14, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let popup = Paragraph::new(vec![
+                clipboard_status(copied, "command"),
+                Line::from(vec![]),
+                Line::from(vec![Span::styled(command.as_str(), Style::default().fg(Color::Cyan))]),
+                Line::from(vec![]),
+                Line::from(vec![Span::raw("Secrets are read from the referenced environment variables.")]),
+                Line::from(vec![Span::raw("Press Esc or Enter to close")]),
+            ])
+            .block(Block::default().title("Equivalent Command").borders(Borders::ALL))
+            .wrap(ratatui::widgets::Wrap { trim: false });
+            f.render_widget(popup, area);
+        }
+        PopupState::PresignDuration(snapshot, typed) => {
+            let area = centered_rect(60, 7, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let popup = Paragraph::new(vec![
+                Line::from(vec![Span::raw(format!("Download link to {}", snapshot.key))]),
+                Line::from(vec![Span::raw("Valid for: "), Span::styled(format!("{}_", typed), Style::default().fg(Color::Yellow))]),
+                Line::from(vec![]),
+                Line::from(vec![Span::raw("e.g. 30m, 12h or 7d (at most 7d)  [Enter] create  [Esc] cancel")]),
+            ])
+            .block(Block::default().title("Download Link").borders(Borders::ALL))
+            .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::LocalFile(typed, candidates) => {
+            let shown = candidates.len().min(8);
+            let area = centered_rect(70, shown as u16 + 6, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text = vec![Line::from(vec![Span::raw("Path: "), Span::styled(format!("{}_", typed), Style::default().fg(Color::Yellow))])];
+            text.extend(candidates.iter().take(shown).map(|name| Line::from(vec![Span::styled(name.as_str(), Style::default().fg(Color::Cyan))])));
+            if candidates.len() > shown {
+                text.push(Line::from(vec![Span::raw(format!("... and {} more", candidates.len() - shown))]));
+            }
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw("[Tab] complete  [Enter] restore this file  [Esc] cancel")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Restore Local File").borders(Borders::ALL));
+            f.render_widget(popup, area);
+        }
+        PopupState::RecoverTarget(snapshot, window, typed) => {
+            let area = centered_rect(70, 8, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let popup = Paragraph::new(vec![
+                Line::from(vec![Span::raw(format!("Recover {} on a scratch instance", snapshot.key))]),
+                Line::from(vec![Span::styled(format!("Recoverable: {} (UTC)", window), Style::default().fg(Color::Cyan))]),
+                Line::from(vec![Span::raw("Recover to: "), Span::styled(format!("{}_", typed), Style::default().fg(Color::Yellow))]),
+                Line::from(vec![]),
+                Line::from(vec![Span::raw(format!("Listens on localhost:{}  [Enter] recover  [Esc] cancel or abort", crate::pitr::DEFAULT_SCRATCH_PORT))]),
+            ])
+            .block(Block::default().title("Point-in-Time Recovery").borders(Borders::ALL))
+            .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::PresignedLink(key, url, expires_at, copied) => {
+            let area = centered_rect(80, 16, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let popup = Paragraph::new(vec![
+                clipboard_status(copied, "link"),
+                Line::from(vec![]),
+                Line::from(vec![Span::raw(format!("Anyone with this link can download {} until {}:", key, expires_at))]),
+                Line::from(vec![]),
+                Line::from(vec![Span::styled(url.as_str(), Style::default().fg(Color::Cyan))]),
+                Line::from(vec![]),
+                Line::from(vec![Span::raw("Press Esc or Enter to close")]),
+            ])
+            .block(Block::default().title("Download Link").borders(Borders::ALL))
+            .wrap(ratatui::widgets::Wrap { trim: false });
+            f.render_widget(popup, area);
+        }
+        PopupState::RestoreSummary(lines) => {
+            let height = (lines.len() as u16 + 4).min(20);
+            let area = centered_rect(70, height, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = lines.iter().map(|line| Line::from(vec![Span::raw(line.as_str())])).collect();
+            if let Some(plan) = &app.promote_plan {
+                text.push(Line::from(vec![Span::styled(
+                    format!("Press 'P' to promote {} to {}", plan.restored_db, plan.live_db),
+                    Style::default().fg(Color::Yellow),
+                )]));
+            }
+            text.push(Line::from(vec![Span::raw("Press Esc or Enter to close")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Restore Summary").borders(Borders::ALL).style(Style::default().fg(Color::Green)))
+                .wrap(ratatui::widgets::Wrap { trim: false });
+            f.render_widget(popup, area);
+        }
+        PopupState::ScheduleStatus(lines) => {
+            let height = (lines.len() as u16 + 4).min(20);
+            let area = centered_rect(80, height, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = lines.iter().map(|line| Line::from(vec![Span::raw(line.as_str())])).collect();
+            text.push(Line::from(vec![Span::raw("Press Esc or Enter to close")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Backup Schedule").borders(Borders::ALL).style(Style::default().fg(Color::Cyan)))
+                .wrap(ratatui::widgets::Wrap { trim: false });
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmPromote(plan) => {
+            let area = centered_rect(70, 10, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = plan.steps().into_iter().map(|step| Line::from(vec![Span::raw(step)])).collect();
+            let ownership = if plan.transfer_ownership { "on" } else { "off" };
+            text.push(Line::from(vec![Span::raw(format!("[o] transfer owner and grants: {}", ownership))]));
+            text.push(Line::from(vec![Span::raw("Press 'y' to promote, 'n' to cancel")]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Confirm Promote").borders(Borders::ALL).style(Style::default().fg(Color::Yellow)))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmImpact(snapshot, typed) => {
+            let area = centered_rect(70, 9, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text = vec![Line::from(vec![Span::raw(format!("Restore {}", snapshot.key))])];
+            if let Some(impact) = &app.restore_impact {
+                text.extend(impact.lines().into_iter().map(|line| Line::from(vec![Span::raw(line)])));
+                text.push(Line::from(vec![]));
+                text.push(Line::from(vec![Span::raw(format!(
+                    "Type the {} name ({}) and press Enter to restore, Esc to cancel",
+                    impact.kind, impact.name
+                ))]));
+            }
+            text.push(Line::from(vec![Span::styled(format!("> {}", typed), Style::default().fg(Color::Cyan))]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Confirm Restore Into Existing Data").borders(Borders::ALL).style(Style::default().fg(Color::Red)))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmProduction(action, typed) => {
+            let area = centered_rect(70, 8, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let environment = app.environment.as_deref().unwrap_or_default();
+            let text = vec![
+                Line::from(vec![Span::raw(action.describe())]),
+                Line::from(vec![Span::raw(format!("The settings point at {}", crate::profiles::banner(environment, &app.s3_config.region)))]),
+                Line::from(vec![]),
+                Line::from(vec![Span::raw(format!("Type the environment name ({}) and press Enter to go ahead, Esc to cancel", environment))]),
+                Line::from(vec![Span::styled(format!("> {}", typed), Style::default().fg(Color::Cyan))]),
+            ];
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Confirm In Production").borders(Borders::ALL).style(Style::default().fg(Color::Red)))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmDelete(snapshots, typed) => {
+            let visible = snapshots.len().min(8);
+            let area = centered_rect(70, visible as u16 + 6, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = snapshots.iter().take(visible).map(|snapshot| Line::from(vec![Span::raw(snapshot.key.as_str())])).collect();
+            if snapshots.len() > visible {
+                text.push(Line::from(vec![Span::raw(format!("and {} more", snapshots.len() - visible))]));
+            }
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw(format!(
+                "Type '{}' and press Enter to delete {} snapshot(s), Esc to cancel",
+                crate::ui::browser::DELETE_CONFIRMATION, snapshots.len()
+            ))]));
+            text.push(Line::from(vec![Span::styled(format!("> {}", typed), Style::default().fg(Color::Cyan))]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Confirm Delete").borders(Borders::ALL).style(Style::default().fg(Color::Red)))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmBundleRestore(members, lines, typed) => {
+            let area = centered_rect(70, lines.len() as u16 + 8, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            // Where each member goes, then what the restore changes in existing indices and collections
+            let mut text: Vec<Line> = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| match i < members.len() {
+                    true => Line::from(vec![Span::raw(line.as_str())]),
+                    false => Line::from(vec![Span::styled(line.as_str(), Style::default().fg(Color::Yellow))]),
+                })
+                .collect();
+            text.push(Line::from(vec![Span::raw(app.restore_options.summary())]));
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw(format!(
+                "Type '{}' and press Enter to restore {} snapshots together, Esc to cancel",
+                crate::bundle::BUNDLE_CONFIRMATION, members.len()
+            ))]));
+            text.push(Line::from(vec![Span::styled(format!("> {}", typed), Style::default().fg(Color::Cyan))]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Confirm Bundle Restore").borders(Borders::ALL))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::RestoringBundle(members, downloaded) => {
+            let area = centered_rect(70, members.len() as u16 * 2 + 3, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            // One bar per member, following its download and then its restore
+            let mut text = Vec::new();
+            for (i, member) in members.iter().enumerate() {
+                let key = &member.snapshot.key;
+                let (stage, fraction) = match (app.progress.latest(&format!("Restore of {}", key)), app.progress.latest(&format!("Download of {}", key))) {
+                    (Some(restore), _) => ("restoring", restore.fraction),
+                    (None, _) if i < *downloaded => ("downloaded", 1.0),
+                    (None, Some(download)) => ("downloading", download.fraction),
+                    (None, None) => ("waiting", 0.0),
+                };
+                let filled = (30.0 * fraction.clamp(0.0, 1.0)) as usize;
+                text.push(Line::from(vec![Span::raw(format!("{}: {} ({})", member.label(), key, stage))]));
+                text.push(Line::from(vec![Span::raw(format!("[{}{}] {:.1}%", "=".repeat(filled), " ".repeat(30 - filled), fraction * 100.0))]));
+            }
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Restoring Bundle").borders(Borders::ALL))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::ConfirmBatchRestore(snapshots, lines) => {
+            let area = centered_rect(70, lines.len() as u16 + 6, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            let mut text: Vec<Line> = lines.iter().map(|line| Line::from(vec![Span::raw(line.as_str())])).collect();
+            text.push(Line::from(vec![]));
+            text.push(Line::from(vec![Span::raw(format!(
+                "Restore {} snapshots one after another, each with its own preset? (y/n)",
+                snapshots.len()
+            ))]));
+            let popup = Paragraph::new(text)
+                .block(Block::default().title("Confirm Batch Restore").borders(Borders::ALL))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
+        PopupState::RestoringBatch(snapshots, outcomes) => {
+            let area = centered_rect(70, snapshots.len() as u16 + 4, f.size());
+            // Clear the area where the popup will be rendered
+            f.render_widget(ratatui::widgets::Clear, area);
+            // The outcome of each finished snapshot, and a bar following the download and restore of the current one
+            let mut text = Vec::new();
+            for (i, snapshot) in snapshots.iter().enumerate() {
+                let key = &snapshot.key;
+                match outcomes.get(i) {
+                    Some(outcome) => text.push(Line::from(vec![Span::raw(format!("{}: {}", key, outcome))])),
+                    None if i == outcomes.len() => {
+                        let (stage, fraction) = match (app.progress.latest(&format!("Restore of {}", key)), app.progress.latest(&format!("Download of {}", key))) {
+                            (Some(restore), _) => ("restoring", restore.fraction),
+                            (None, Some(download)) => ("downloading", download.fraction),
+                            (None, None) => ("starting", 0.0),
+                        };
+                        let filled = (30.0 * fraction.clamp(0.0, 1.0)) as usize;
+                        text.push(Line::from(vec![Span::styled(format!("{}: {}", key, stage), Style::default().fg(Color::Yellow))]));
+                        text.push(Line::from(vec![Span::raw(format!("[{}{}] {:.1}%", "=".repeat(filled), " ".repeat(30 - filled), fraction * 100.0))]));
+                    }
+                    None => text.push(Line::from(vec![Span::raw(format!("{}: waiting", key))])),
+                }
+            }
+            let popup = Paragraph::new(text)
+                .block(Block::default().title(format!("Restoring Batch ({} of {})", (outcomes.len() + 1).min(snapshots.len()), snapshots.len())).borders(Borders::ALL))
+                .alignment(Alignment::Center);
+            f.render_widget(popup, area);
+        }
         PopupState::Error(message) => {
             let area = centered_rect(60, 5, f.size());
             // Clear the area where the popup will be rendered
@@ -126,12 +590,14 @@ pub fn render_popups<B: Backend>(f: &mut Frame, app: &RustoredApp) {
             f.render_widget(popup, area);
         }
         PopupState::Restoring(snapshot, progress) => {
-            let area = centered_rect(60, 5, f.size());
+            let area = centered_rect(60, 6, f.size());
             // Clear the area where the popup will be rendered
             f.render_widget(ratatui::widgets::Clear, area);
 
-            // Create a progress bar
-            let progress_value = progress; // Store in a local variable
+            // Create a progress bar from the latest reported progress
+            let progress_value = app.progress
+                .latest(&format!("Restore of {}", snapshot.key))
+                .map_or(*progress, |latest| latest.fraction);
             let _progress_percent = (progress_value * 100.0) as u16;
             let progress_bar_width = 50;
             let filled_width = (progress_bar_width as f32 * progress_value) as usize;
@@ -148,6 +614,7 @@ pub fn render_popups<B: Backend>(f: &mut Frame, app: &RustoredApp) {
                 Line::from(vec![Span::raw(format!("Restoring: {}", snapshot.key))]),
                 Line::from(vec![]),
                 Line::from(vec![Span::raw(progress_bar)]),
+                Line::from(vec![Span::raw("Press Esc to abort")]),
             ])
             .block(Block::default().title("Restoring").borders(Borders::ALL))
             .alignment(Alignment::Center);
@@ -156,3 +623,18 @@ pub fn render_popups<B: Backend>(f: &mut Frame, app: &RustoredApp) {
         PopupState::Hidden => {}
     }
 }
+
+/// Whether `what` reached the clipboard, and what to do when it did not
+fn clipboard_status(copied: &Result<(), String>, what: &str) -> Line<'static> {
+    debug!("Describing clipboard copy of the {}", what);
+    match copied {
+        Ok(()) => Line::from(vec![Span::styled(
+            format!("Sent to the clipboard; if it did not arrive, select the {} below", what),
+            Style::default().fg(Color::Green),
+        )]),
+        Err(e) => Line::from(vec![Span::styled(
+            format!("Copy failed ({}), select the {} below", e, what),
+            Style::default().fg(Color::Yellow),
+        )]),
+    }
+}
@@ -76,6 +76,10 @@ pub fn render_postgres_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, ar
     
     // Add remaining fields
     fields.push(("Database", app.pg_config.db_name.clone().unwrap_or_default(), FocusField::PgDbName));
+    let target_db = app.pg_config.target_db.clone().unwrap_or_else(|| "(new <word>-restored)".to_string());
+    fields.push(("Target DB", target_db, FocusField::PgTargetDb));
+    let restore_jobs = app.pg_config.restore_jobs.map_or_else(|| "(preset or 1)".to_string(), |jobs| jobs.to_string());
+    fields.push(("Restore Jobs", restore_jobs, FocusField::PgRestoreJobs));
     fields.push(("Use SSL", if app.pg_config.use_ssl { "Yes" } else { "No" }.to_string(), FocusField::PgDbName));
     
     debug!("Applied password masking for PostgreSQL password field (TDD rule #12)");
@@ -126,15 +130,19 @@ pub fn render_postgres_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, ar
     f.render_widget(table, chunks[0]);
     
     // Render the help text at the bottom as per TDD rule #10
-    let mut help_items = Vec::new();
-    
     // Always show navigation help (using the same format as S3 settings)
-    help_items.push(Span::styled("↑↓", Style::default().fg(Color::Yellow)));
-    help_items.push(Span::raw(" Navigate "));
+    let mut help_items = vec![
+        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate "),
+    ];
     
     // Show test connection option (using [t] consistently across all components)
     help_items.push(Span::styled("[t]", Style::default().fg(Color::Yellow)));
     help_items.push(Span::raw(" Test Connection "));
+
+    // Show the backup now action for taking a pre-change snapshot
+    help_items.push(Span::styled("[b]", Style::default().fg(Color::Yellow)));
+    help_items.push(Span::raw(" Backup Now "));
     
     let help_text = Line::from(help_items);
     
@@ -76,6 +76,10 @@ pub fn render_qdrant_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area
     
     debug!("Applied masking for Qdrant API key field (TDD rule #12)");
 
+    // Vector parameters of a collection the restore creates, blank to use the stored or sampled ones
+    fields.push(("Vector Size", app.qdrant_config.get_field_value(FocusField::QdrantVectorSize), FocusField::QdrantVectorSize));
+    fields.push(("Distance", app.qdrant_config.get_field_value(FocusField::QdrantDistance), FocusField::QdrantDistance));
+
     // Create a row for each field
     for (label, value, field) in &fields {
         // Determine if this field is focused
@@ -123,11 +127,11 @@ pub fn render_qdrant_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area
     f.render_widget(table, chunks[0]);
     
     // Render the help text at the bottom as per TDD rule #10
-    let mut help_items = Vec::new();
-    
     // Always show navigation help (using the same format as S3 settings)
-    help_items.push(Span::styled("↑↓", Style::default().fg(Color::Yellow)));
-    help_items.push(Span::raw(" Navigate "));
+    let mut help_items = vec![
+        Span::styled("↑↓", Style::default().fg(Color::Yellow)),
+        Span::raw(" Navigate "),
+    ];
     
     // Show test connection option (using [t] consistently across all components)
     help_items.push(Span::styled("[t]", Style::default().fg(Color::Yellow)));
@@ -45,13 +45,19 @@ pub fn render_restore_target<B: Backend>(f: &mut Frame, app: &RustoredApp, area:
 
     // Create list items for restore targets with numeric prefixes as per TDD rule #11
     // Each target will be on its own line as requested
-    let restore_target_names = vec!["1. PostgreSQL", "2. Elasticsearch", "3. Qdrant"];
+    let restore_target_names = vec!["1. PostgreSQL", "2. Elasticsearch", "3. Qdrant", "4. OpenSearch", "5. Meilisearch", "6. Weaviate", "7. Milvus", "8. DynamoDB", "9. InfluxDB"];
     debug!("Created restore targets with numeric prefixes: {:?}", restore_target_names);
     
     let restore_target_index = match app.restore_target {
         RestoreTarget::Postgres => 0,
         RestoreTarget::Elasticsearch => 1,
         RestoreTarget::Qdrant => 2,
+        RestoreTarget::OpenSearch => 3,
+        RestoreTarget::Meilisearch => 4,
+        RestoreTarget::Weaviate => 5,
+        RestoreTarget::Milvus => 6,
+        RestoreTarget::DynamoDb => 7,
+        RestoreTarget::InfluxDb => 8,
     };
     debug!("Current restore target index: {}", restore_target_index);
 
@@ -81,7 +87,7 @@ pub fn render_restore_target<B: Backend>(f: &mut Frame, app: &RustoredApp, area:
     // Add help text at the bottom of the restore target section as per TDD rule #10
     let help_text = Line::from(vec![
         Span::styled("Press ", Style::default()),
-        Span::styled("1-3", Style::default().fg(Color::Yellow)),
+        Span::styled("1-9", Style::default().fg(Color::Yellow)),
         Span::styled(" to select restore target type", Style::default()),
     ]);
     
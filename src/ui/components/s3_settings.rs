@@ -76,9 +76,11 @@ pub fn render_s3_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area: Re
     };
     fields.push(("Endpoint URL", endpoint_value, FocusField::EndpointUrl));
     
-    // Access Key ID field
+    // Access Key ID field, noting the credential chain is used when no keys are set
     let access_key_value = if app.focus == FocusField::AccessKeyId && app.input_mode == InputMode::Editing {
         app.input_buffer.clone()
+    } else if !app.s3_config.has_static_credentials() && app.s3_config.access_key_id.is_empty() {
+        format!("({})", app.s3_config.credentials_source())
     } else {
         app.s3_config.access_key_id.clone()
     };
@@ -139,10 +141,9 @@ pub fn render_s3_settings<B: Backend>(f: &mut Frame, app: &RustoredApp, area: Re
     
     debug!("Rendered S3 settings using table layout");
 
-    // Only show S3 connection test option if required fields are set
-    let has_required_fields = !app.s3_config.bucket.is_empty() &&
-                            !app.s3_config.access_key_id.is_empty() &&
-                            !app.s3_config.secret_access_key.is_empty();
+    // Only show S3 connection test option if required fields are set; credentials
+    // may come from the default AWS credential chain instead of access keys
+    let has_required_fields = !app.s3_config.bucket.is_empty();
 
     // Create help legend text
     let mut help_items = Vec::new();
@@ -8,6 +8,7 @@ use ratatui::{
 use chrono::{DateTime, Utc};
 use log::debug;
 
+use crate::archive::ArchiveStatus;
 use crate::ui::models::FocusField;
 use crate::ui::rustored::RustoredApp;
 
@@ -21,8 +22,31 @@ pub fn render_snapshot_list<B: Backend>(f: &mut Frame, app: &RustoredApp, area:
     } else {
         Style::default()
     };
+    let browser = &app.snapshot_browser;
+    let title = match (&browser.sftp_config, &browser.azure_config, &browser.gcs_config) {
+        (Some(sftp), _, _) => format!("Snapshots (sftp://{}@{}:{}/{})", sftp.username, sftp.host, sftp.port, sftp.prefix),
+        (None, _, _) if browser.b2_config.is_some() => format!("Snapshots (b2://{}/{})", browser.bucket(), browser.prefix()),
+        (None, Some(azure), _) => format!("Snapshots (az://{}/{}/{})", azure.account, azure.container, azure.prefix),
+        (None, None, Some(gcs)) => format!("Snapshots (gs://{}/{})", gcs.bucket, gcs.prefix),
+        (None, None, None) => "Snapshots".to_string(),
+    };
+    // A cached listing is shown until the live listing replaces it
+    let title = match (browser.stale_since, browser.refreshing) {
+        (Some(saved_at), refreshing) => {
+            let saved: DateTime<Utc> = DateTime::from_timestamp(saved_at as i64, 0).unwrap_or_default();
+            let state = if refreshing { "refreshing…" } else { "refresh failed" };
+            format!("{} - stale from {}, {}", title, saved.format("%Y-%m-%d %H:%M"), state)
+        }
+        (None, _) => match browser.last_refreshed {
+            Some(refreshed) => {
+                let refreshed: DateTime<Utc> = DateTime::from_timestamp(refreshed as i64, 0).unwrap_or_default();
+                format!("{} - last refreshed {}", title, refreshed.format("%Y-%m-%d %H:%M:%S"))
+            }
+            None => title,
+        },
+    };
     let snapshot_block = Block::default()
-        .title("Snapshots")
+        .title(title)
         .borders(Borders::ALL)
         .style(snapshot_style);
 
@@ -42,17 +66,54 @@ pub fn render_snapshot_list<B: Backend>(f: &mut Frame, app: &RustoredApp, area:
             let full_path = &snapshot.key;
             debug!("Using full S3 path: {}", full_path);
             
-            // Apply style to the selected row
+            // Apply style to the selected row, highlighting pinned pre-change and held snapshots
+            let pinned = app.snapshot_browser.is_pinned(snapshot);
+            let marked = app.snapshot_browser.marked.contains(&snapshot.key);
             let style = if i == app.snapshot_browser.selected_index {
                 Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            } else if marked {
+                Style::default().fg(Color::Magenta)
+            } else if pinned {
+                Style::default().fg(Color::Cyan)
             } else {
                 Style::default()
             };
+            // Marked for deletion, pinned, or neither
+            let path_text = match (marked, pinned) {
+                (true, _) => format!("x {}", full_path),
+                (false, true) => format!("* {}", full_path),
+                (false, false) => full_path.to_string(),
+            };
             
+            // Badge from the cached verification results, red when a check failed
+            let badge = app.snapshot_browser.verifications.badge(snapshot);
+            let badge_style = match app.snapshot_browser.verifications.get(snapshot) {
+                Some(v) if !v.is_ok() && i != app.snapshot_browser.selected_index => style.fg(Color::Red),
+                _ => style,
+            };
+
+            // Storage class, with the progress of any archive restore seen this session
+            let class = match (&snapshot.storage_class, app.snapshot_browser.archive.status(&snapshot.key)) {
+                (Some(class), Some(status)) if status != ArchiveStatus::NotArchived && status != ArchiveStatus::Archived => {
+                    format!("{} ({})", class, status)
+                }
+                (Some(class), _) => class.clone(),
+                (None, _) => String::new(),
+            };
+
+            // Source database and pg_dump version from the backup manifest, or the WAL archived after a base backup
+            let source = match app.snapshot_browser.wal_chains.get(&snapshot.key) {
+                Some(chain) => chain.summary(),
+                None => app.snapshot_browser.manifests.get(&snapshot.key).map(|manifest| manifest.source()).unwrap_or_default(),
+            };
+
             Row::new(vec![
-                Cell::from(full_path.to_string()).style(style),
+                Cell::from(path_text).style(style),
                 Cell::from(formatted_size).style(style),
                 Cell::from(formatted_date).style(style),
+                Cell::from(class).style(style),
+                Cell::from(badge).style(badge_style),
+                Cell::from(source).style(style),
             ])
         })
         .collect();
@@ -63,6 +124,9 @@ pub fn render_snapshot_list<B: Backend>(f: &mut Frame, app: &RustoredApp, area:
         Cell::from("S3 Path").style(header_style),
         Cell::from("Size").style(header_style),
         Cell::from("Last Modified").style(header_style),
+        Cell::from("Class").style(header_style),
+        Cell::from("Verified").style(header_style),
+        Cell::from("Source").style(header_style),
     ]);
     debug!("Created header row with S3 Path column");
     
@@ -71,9 +135,12 @@ pub fn render_snapshot_list<B: Backend>(f: &mut Frame, app: &RustoredApp, area:
     table_rows.extend(rows);
     
     let table = Table::new(table_rows, &[
-            Constraint::Percentage(50),  // S3 Path takes 50% of the width
-            Constraint::Percentage(15),  // Size takes 15% of the width
-            Constraint::Percentage(35),  // Date takes 35% of the width
+            Constraint::Percentage(32),  // S3 Path takes 32% of the width
+            Constraint::Percentage(10),  // Size takes 10% of the width
+            Constraint::Percentage(18),  // Date takes 18% of the width
+            Constraint::Percentage(10),  // Storage class takes 10% of the width
+            Constraint::Percentage(12),  // Verification badge takes 12% of the width
+            Constraint::Percentage(18),  // Manifest source takes 18% of the width
         ])
         .block(snapshot_block)
         .column_spacing(1);
@@ -0,0 +1,119 @@
+// This module contains the guard rails of the Rustored TUI for production
+// When the settings point at a production environment, restoring, deleting
+// snapshots, restoring a bundle or a batch of marked snapshots and promoting a
+// restored database each ask for the environment's name to be typed after their
+// own confirmation.
+
+use crate::bundle::BundleMember;
+use crate::promote::PromotePlan;
+use crate::ui::models::{BackupMetadata, PopupState};
+use crate::ui::rustored::RustoredApp;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+
+/// A destructive action, held back until the environment's name is typed
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardedAction {
+    Restore(BackupMetadata),
+    Delete(Vec<BackupMetadata>),
+    RestoreBundle(Vec<BundleMember>),
+    RestoreBatch(Vec<BackupMetadata>),
+    Promote(PromotePlan),
+}
+
+impl GuardedAction {
+    /// What the action does, for the confirmation popup
+    pub fn describe(&self) -> String {
+        debug!("Describing guarded action");
+        match self {
+            GuardedAction::Restore(snapshot) => format!("Restore {}", snapshot.key),
+            GuardedAction::Delete(snapshots) => format!("Delete {} snapshot(s)", snapshots.len()),
+            GuardedAction::RestoreBundle(members) => format!("Restore {} snapshots together", members.len()),
+            GuardedAction::RestoreBatch(snapshots) => format!("Restore {} snapshots one after another", snapshots.len()),
+            GuardedAction::Promote(plan) => format!("Promote {} to {}", plan.restored_db, plan.live_db),
+        }
+    }
+}
+
+/// Run a confirmed destructive action, or ask for the environment's name first in production
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `action` - The action that was confirmed
+///
+/// # Returns
+///
+/// The downloaded file's path when a restore was started, which the main loop restores
+pub async fn confirm(app: &mut RustoredApp, action: GuardedAction) -> Result<Option<String>> {
+    debug!("Confirmed action: {}", action.describe());
+    if app.environment.as_deref().is_some_and(crate::profiles::is_production) {
+        app.popup_state = PopupState::ConfirmProduction(action, String::new());
+        return Ok(None);
+    }
+    perform(app, action).await
+}
+
+/// Handle a key in the production confirmation
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+///
+/// # Returns
+///
+/// The downloaded file's path when a restore was started, which the main loop restores
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) -> Result<Option<String>> {
+    debug!("Handling production confirmation key: {:?}", key);
+    let PopupState::ConfirmProduction(action, typed) = &mut app.popup_state else {
+        return Ok(None);
+    };
+    match key.code {
+        KeyCode::Enter if Some(typed.as_str()) == app.environment.as_deref() => {
+            let action = action.clone();
+            return perform(app, action).await;
+        }
+        KeyCode::Backspace => {
+            typed.pop();
+        }
+        KeyCode::Char(c) => typed.push(c),
+        KeyCode::Esc => app.popup_state = PopupState::Hidden,
+        _ => {}
+    }
+    Ok(None)
+}
+
+async fn perform(app: &mut RustoredApp, action: GuardedAction) -> Result<Option<String>> {
+    debug!("Performing action: {}", action.describe());
+    match action {
+        GuardedAction::Restore(snapshot) => return crate::ui::restore_plan::start_download(app, snapshot).await,
+        GuardedAction::Delete(snapshots) => {
+            app.popup_state = match app.snapshot_browser.delete_snapshots(&snapshots, chrono::Utc::now().date_naive()).await {
+                Ok(()) => PopupState::Success(format!("Deleted {} snapshot(s)", snapshots.len())),
+                Err(e) => PopupState::Error(format!("Delete failed: {}", e)),
+            };
+        }
+        GuardedAction::RestoreBundle(members) => {
+            // The main loop restores it, since it can draw the progress
+            app.pending_bundle = Some(members);
+            app.popup_state = PopupState::Hidden;
+        }
+        GuardedAction::RestoreBatch(snapshots) => {
+            // The main loop restores it, since it can draw the progress
+            app.pending_batch = Some(snapshots);
+            app.popup_state = PopupState::Hidden;
+        }
+        GuardedAction::Promote(plan) => {
+            app.popup_state = match app.promote(&plan).await {
+                Ok(()) => {
+                    app.promote_plan = None;
+                    PopupState::Success(format!("Promoted {} to {}, previous database kept as {}", plan.restored_db, plan.live_db, plan.retired_db))
+                }
+                Err(e) => PopupState::Error(format!("Promotion failed: {}", e)),
+            };
+        }
+    }
+    Ok(None)
+}
@@ -1,7 +1,8 @@
 // This module contains key handling logic for the Rustored application
 // It processes keyboard events and updates application state accordingly
 
-use crate::ui::models::{PopupState, InputMode, FocusField, RestoreTarget};
+use crate::ui::models::{PopupState, InputMode, FocusField, RestoreTarget, AzureConfig, OpenSearchConfig, MeilisearchConfig, WeaviateConfig, MilvusConfig, DynamoDbConfig, InfluxDbConfig};
+use crate::ui::guard_rails::{self, GuardedAction};
 use crate::ui::rustored::RustoredApp;
 use crossterm::event::{KeyCode, KeyEvent};
 use anyhow::Result;
@@ -25,10 +26,136 @@ pub async fn handle_popup_events(app: &mut RustoredApp, key: KeyEvent) -> Result
     match &app.popup_state {
         PopupState::ConfirmRestore(snapshot) => {
             match key.code {
+                // Nothing runs before the plan has been reviewed
                 KeyCode::Char('y') | KeyCode::Char('Y') => {
-                    // Download the snapshot
-                    let tmp_path = std::env::temp_dir().join(format!("rustored_snapshot_{}", snapshot.key.replace("/", "_")));
-                    return app.snapshot_browser.download_snapshot(snapshot, &tmp_path).await;
+                    let snapshot = snapshot.clone();
+                    crate::ui::restore_plan::open(app, snapshot);
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    app.popup_state = PopupState::Hidden;
+                }
+                // Override the preset for this restore only
+                KeyCode::Char('+') => {
+                    app.restore_options.jobs = Some(app.restore_options.jobs.unwrap_or(1) + 1);
+                }
+                KeyCode::Char('-') => {
+                    app.restore_options.jobs = match app.restore_options.jobs {
+                        Some(jobs) if jobs > 2 => Some(jobs - 1),
+                        _ => None,
+                    };
+                }
+                KeyCode::Char('i') => {
+                    app.restore_options.drop_indexes = !app.restore_options.drop_indexes;
+                }
+                KeyCode::Char('c') => {
+                    app.restore_options.compare_live = !app.restore_options.compare_live;
+                }
+                KeyCode::Char('l') if app.restore_target == RestoreTarget::Postgres => {
+                    app.restore_options.gentle = !app.restore_options.gentle;
+                }
+                KeyCode::Char('g') => {
+                    let snapshot = snapshot.clone();
+                    show_restore_command(app, &snapshot);
+                }
+                KeyCode::Char('s') => crate::ui::server_picker::open(app),
+                KeyCode::Char('r') if app.restore_target == RestoreTarget::Postgres => {
+                    app.popup_state = PopupState::SelectRecipes(snapshot.clone(), 0);
+                }
+                KeyCode::Char('t') if app.restore_target == RestoreTarget::Postgres => {
+                    let snapshot = snapshot.clone();
+                    crate::ui::table_picker::open(app, snapshot).await;
+                }
+                KeyCode::Char('v') if app.restore_target == RestoreTarget::Postgres => {
+                    let snapshot = snapshot.clone();
+                    crate::ui::restore_preview::open(app, snapshot).await;
+                }
+                // New database, drop and recreate the existing one, or clean it
+                KeyCode::Char('e') if app.restore_target == RestoreTarget::Postgres => {
+                    app.restore_options.mode = app.restore_options.mode.next();
+                    app.load_restore_impact().await;
+                }
+                KeyCode::Char('p') => {
+                    if app.restore_preset.is_some() {
+                        app.restore_options = Default::default();
+                        app.apply_restore_defaults();
+                        app.restore_preset = None;
+                    } else {
+                        let snapshot = snapshot.clone();
+                        app.apply_restore_preset(&snapshot);
+                    }
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        PopupState::SelectRecipes(snapshot, selected) => {
+            match key.code {
+                KeyCode::Up => {
+                    app.popup_state = PopupState::SelectRecipes(snapshot.clone(), selected.saturating_sub(1));
+                }
+                KeyCode::Down => {
+                    let selected = (*selected + 1).min(crate::recipes::RECIPES.len() - 1);
+                    app.popup_state = PopupState::SelectRecipes(snapshot.clone(), selected);
+                }
+                KeyCode::Char(' ') => {
+                    crate::recipes::toggle(&mut app.restore_options.recipes, crate::recipes::RECIPES[*selected].name);
+                }
+                KeyCode::Enter | KeyCode::Esc => {
+                    app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        PopupState::SelectServer(..) => {
+            crate::ui::server_picker::handle_key(app, key).await;
+            return Ok(None);
+        }
+        PopupState::SelectTables(..) => {
+            crate::ui::table_picker::handle_key(app, key);
+            return Ok(None);
+        }
+        PopupState::RestorePreview(..) => {
+            crate::ui::restore_preview::handle_key(app, key);
+            return Ok(None);
+        }
+        PopupState::RestorePlan(..) => {
+            return crate::ui::restore_plan::handle_key(app, key).await;
+        }
+        PopupState::ConfirmProduction(..) => {
+            return guard_rails::handle_key(app, key).await;
+        }
+        PopupState::PresignDuration(..) | PopupState::PresignedLink(..) => {
+            crate::ui::presign::handle_key(app, key).await;
+            return Ok(None);
+        }
+        PopupState::RecoverTarget(..) => {
+            crate::ui::pitr::handle_key(app, key).await;
+            return Ok(None);
+        }
+        PopupState::LocalFile(..) => {
+            crate::ui::local_file::handle_key(app, key).await;
+            return Ok(None);
+        }
+        PopupState::ConfirmBatchRestore(..) => {
+            return crate::ui::batch::handle_key(app, key).await;
+        }
+        PopupState::ConfirmArchiveRestore(snapshot, tier) => {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    let (snapshot, tier) = (snapshot.clone(), *tier);
+                    // Polling outlives this popup, so it gets its own operation token
+                    let operation = app.begin_operation();
+                    app.popup_state = match app.snapshot_browser.request_archive_restore(&snapshot.key, tier, operation).await {
+                        Ok(()) => PopupState::Success(format!(
+                            "Requested a {} restore of {}; the snapshot list shows when it can be downloaded",
+                            tier, snapshot.key
+                        )),
+                        Err(e) => PopupState::Error(format!("Archive restore request failed: {}", e)),
+                    };
+                }
+                KeyCode::Char('t') => {
+                    app.popup_state = PopupState::ConfirmArchiveRestore(snapshot.clone(), tier.next());
                 }
                 KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
                     app.popup_state = PopupState::Hidden;
@@ -37,9 +164,35 @@ pub async fn handle_popup_events(app: &mut RustoredApp, key: KeyEvent) -> Result
             }
             return Ok(None);
         }
+        PopupState::VersionHistory(key_name, versions, selected) => {
+            match key.code {
+                KeyCode::Up => {
+                    app.popup_state = PopupState::VersionHistory(key_name.clone(), versions.clone(), selected.saturating_sub(1));
+                }
+                KeyCode::Down => {
+                    let selected = (*selected + 1).min(versions.len().saturating_sub(1));
+                    app.popup_state = PopupState::VersionHistory(key_name.clone(), versions.clone(), selected);
+                }
+                KeyCode::Enter => {
+                    if let Some(version) = versions.get(*selected).cloned() {
+                        // Encryption is only looked up for the current version
+                        app.snapshot_browser.selected_encryption = None;
+                        app.apply_restore_preset(&version);
+                        app.popup_state = PopupState::ConfirmRestore(version);
+                        app.load_restore_impact().await;
+                    }
+                }
+                KeyCode::Esc => {
+                    app.popup_state = PopupState::Hidden;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
         PopupState::ConfirmCancel(_, _, _) => {
             match key.code {
                 KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    app.operation.cancel();
                     app.popup_state = PopupState::Error("Download cancelled".to_string());
                 }
                 KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
@@ -61,7 +214,95 @@ pub async fn handle_popup_events(app: &mut RustoredApp, key: KeyEvent) -> Result
             }
             return Ok(None);
         }
-        PopupState::Error(_) | PopupState::Success(_) => {
+        PopupState::RestoreSummary(_) if key.code == KeyCode::Char('P') => {
+            if let Some(plan) = app.promote_plan.clone() {
+                app.popup_state = PopupState::ConfirmPromote(plan);
+            }
+            return Ok(None);
+        }
+        PopupState::ConfirmPromote(plan) => {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    let plan = plan.clone();
+                    return guard_rails::confirm(app, GuardedAction::Promote(plan)).await;
+                }
+                KeyCode::Char('o') => {
+                    let mut plan = plan.clone();
+                    plan.transfer_ownership = !plan.transfer_ownership;
+                    app.popup_state = PopupState::ConfirmPromote(plan);
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    app.popup_state = PopupState::Hidden;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        PopupState::ConfirmImpact(snapshot, typed) => {
+            let target_name = app.restore_impact.as_ref().map(|impact| impact.name.as_str()).unwrap_or_default();
+            match key.code {
+                KeyCode::Enter if typed == target_name => {
+                    let snapshot = snapshot.clone();
+                    return guard_rails::confirm(app, GuardedAction::Restore(snapshot)).await;
+                }
+                KeyCode::Backspace => {
+                    let mut typed = typed.clone();
+                    typed.pop();
+                    app.popup_state = PopupState::ConfirmImpact(snapshot.clone(), typed);
+                }
+                KeyCode::Char(c) => {
+                    app.popup_state = PopupState::ConfirmImpact(snapshot.clone(), format!("{}{}", typed, c));
+                }
+                KeyCode::Esc => {
+                    app.popup_state = PopupState::Hidden;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        PopupState::ConfirmDelete(snapshots, typed) => {
+            match key.code {
+                KeyCode::Enter if typed == crate::ui::browser::DELETE_CONFIRMATION => {
+                    let snapshots = snapshots.clone();
+                    return guard_rails::confirm(app, GuardedAction::Delete(snapshots)).await;
+                }
+                KeyCode::Backspace => {
+                    let mut typed = typed.clone();
+                    typed.pop();
+                    app.popup_state = PopupState::ConfirmDelete(snapshots.clone(), typed);
+                }
+                KeyCode::Char(c) => {
+                    app.popup_state = PopupState::ConfirmDelete(snapshots.clone(), format!("{}{}", typed, c));
+                }
+                KeyCode::Esc => {
+                    app.popup_state = PopupState::Hidden;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        PopupState::ConfirmBundleRestore(members, lines, typed) => {
+            match key.code {
+                KeyCode::Enter if typed == crate::bundle::BUNDLE_CONFIRMATION => {
+                    let members = members.clone();
+                    return guard_rails::confirm(app, GuardedAction::RestoreBundle(members)).await;
+                }
+                KeyCode::Backspace => {
+                    let mut typed = typed.clone();
+                    typed.pop();
+                    app.popup_state = PopupState::ConfirmBundleRestore(members.clone(), lines.clone(), typed);
+                }
+                KeyCode::Char(c) => {
+                    app.popup_state = PopupState::ConfirmBundleRestore(members.clone(), lines.clone(), format!("{}{}", typed, c));
+                }
+                KeyCode::Esc => {
+                    app.popup_state = PopupState::Hidden;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        PopupState::Error(_) | PopupState::Success(_) | PopupState::GeneratedCommand(..) | PopupState::RestoreSummary(_) | PopupState::ScheduleStatus(_) => {
             if key.code == KeyCode::Esc || key.code == KeyCode::Enter {
                 app.popup_state = PopupState::Hidden;
             }
@@ -85,6 +326,22 @@ pub async fn handle_popup_events(app: &mut RustoredApp, key: KeyEvent) -> Result
     Ok(None)
 }
 
+/// Show the non-interactive command for restoring a snapshot and copy it to the clipboard
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The snapshot the command restores
+fn show_restore_command(app: &mut RustoredApp, snapshot: &crate::ui::models::BackupMetadata) {
+    debug!("Showing restore command for snapshot: {}", snapshot.key);
+    let command = crate::ui::command::restore_command(app, snapshot);
+    let copied = crate::ui::command::copy_to_clipboard(&command).map_err(|e| e.to_string());
+    if let Err(e) = &copied {
+        debug!("Failed to copy command to clipboard: {}", e);
+    }
+    app.popup_state = PopupState::GeneratedCommand(command, copied);
+}
+
 /// Handle editing mode key events
 ///
 /// This function processes key events when in editing mode
@@ -149,6 +406,9 @@ pub async fn handle_editing_mode(app: &mut RustoredApp, key: KeyEvent) -> Result
                         app.pg_config.db_name = Some(app.input_buffer.clone());
                     }
                 }
+                FocusField::PgTargetDb | FocusField::PgRestoreJobs => {
+                    app.pg_config.set_field_value(app.focus, app.input_buffer.clone());
+                }
                 FocusField::EsHost => {
                     if let Some(host) = &mut app.es_config.host {
                         *host = app.input_buffer.clone();
@@ -170,12 +430,36 @@ pub async fn handle_editing_mode(app: &mut RustoredApp, key: KeyEvent) -> Result
                         app.qdrant_config.api_key = Some(app.input_buffer.clone());
                     }
                 }
+                field @ (FocusField::QdrantVectorSize | FocusField::QdrantDistance) => app.qdrant_config.set_field_value(field, app.input_buffer.clone()),
+                field if OpenSearchConfig::contains_field(field) => {
+                    app.os_config.set_field_value(field, app.input_buffer.clone());
+                }
+                field if MeilisearchConfig::contains_field(field) => {
+                    app.meili_config.set_field_value(field, app.input_buffer.clone());
+                }
+                field if WeaviateConfig::contains_field(field) => {
+                    app.weaviate_config.set_field_value(field, app.input_buffer.clone());
+                }
+                field if MilvusConfig::contains_field(field) => {
+                    app.milvus_config.set_field_value(field, app.input_buffer.clone());
+                }
+                field if DynamoDbConfig::contains_field(field) => {
+                    app.dynamodb_config.set_field_value(field, app.input_buffer.clone());
+                }
+                field if InfluxDbConfig::contains_field(field) => {
+                    app.influxdb_config.set_field_value(field, app.input_buffer.clone());
+                }
+                field if AzureConfig::contains_field(field) => {
+                    if let Some(azure) = app.snapshot_browser.azure_config.as_mut() {
+                        azure.set_field_value(field, app.input_buffer.clone());
+                    }
+                }
                 _ => {}
             }
             app.input_mode = InputMode::Normal;
 
-            // Update S3 client with new settings if S3 settings were changed
-            if matches!(app.focus,
+            // Update storage client with new settings if S3 or Azure settings were changed
+            let s3_changed = matches!(app.focus,
                 FocusField::Bucket |
                 FocusField::Region |
                 FocusField::Prefix |
@@ -183,8 +467,12 @@ pub async fn handle_editing_mode(app: &mut RustoredApp, key: KeyEvent) -> Result
                 FocusField::AccessKeyId |
                 FocusField::SecretAccessKey |
                 FocusField::PathStyle
-            ) {
+            );
+            if s3_changed {
                 app.snapshot_browser.s3_config = app.s3_config.clone();
+            }
+            // The demo store stays in place whatever the settings say
+            if (s3_changed || AzureConfig::contains_field(app.focus)) && !app.demo {
                 let _ = app.snapshot_browser.init_client().await;
 
                 // Reload snapshots with new settings
@@ -233,7 +521,13 @@ pub async fn handle_normal_mode(app: &mut RustoredApp, key: KeyEvent) -> Result<
             return Ok(Some("quit".to_string()));
         }
         KeyCode::Char('r') => {
-            // Reload snapshots
+            // Pick up new snapshots
+            if let Err(e) = app.snapshot_browser.refresh_snapshots().await {
+                debug!("Failed to refresh snapshots: {}", e);
+            }
+        }
+        KeyCode::Char('R') => {
+            // Re-list everything, dropping deleted snapshots
             if let Err(e) = app.snapshot_browser.load_snapshots().await {
                 debug!("Failed to load snapshots: {}", e);
             }
@@ -258,6 +552,20 @@ pub async fn handle_normal_mode(app: &mut RustoredApp, key: KeyEvent) -> Result<
                 }
             }
 
+            // Test Azure connection by listing the container when focus is on Azure settings
+            if AzureConfig::contains_field(app.focus) {
+                app.popup_state = match app.snapshot_browser.load_snapshots().await {
+                    Ok(()) => PopupState::TestS3Result(format!(
+                        "Successfully connected to Azure!\nSnapshots found: {}",
+                        app.snapshot_browser.snapshots.len()
+                    )),
+                    Err(e) => {
+                        debug!("Azure connection test failed: {}", e);
+                        PopupState::Error(format!("Failed to connect to Azure: {}", e))
+                    }
+                };
+            }
+
             // Test PostgreSQL connection when focus is on PostgreSQL settings window
             if matches!(app.focus,
                 FocusField::PgHost |
@@ -265,7 +573,9 @@ pub async fn handle_normal_mode(app: &mut RustoredApp, key: KeyEvent) -> Result<
                 FocusField::PgUsername |
                 FocusField::PgPassword |
                 FocusField::PgSsl |
-                FocusField::PgDbName
+                FocusField::PgDbName |
+                FocusField::PgTargetDb |
+                FocusField::PgRestoreJobs
             ) {
                 // Only test if required fields are set
                 if app.pg_config.host.is_some() &&
@@ -281,16 +591,149 @@ pub async fn handle_normal_mode(app: &mut RustoredApp, key: KeyEvent) -> Result<
                 }
             }
         }
+        KeyCode::Char('b') if app.restore_target == RestoreTarget::Postgres => {
+            // Back up the configured PostgreSQL database before risky changes
+            let operation = app.begin_operation();
+            app.popup_state = match crate::ui::app::cancel_on_escape(&operation, app.backup_now()).await {
+                Ok(key) => PopupState::Success(format!("Backed up to {}", key)),
+                Err(e) => {
+                    debug!("Backup now failed: {}", e);
+                    PopupState::Error(format!("Backup failed: {}", e))
+                }
+            };
+        }
+        KeyCode::Char('S') => {
+            // Show the next and last run of each job the schedule daemon last reported
+            let status = match app.snapshot_browser.store().await {
+                Ok(store) => crate::schedule::load_status(store.as_ref()).await,
+                Err(e) => Err(e),
+            };
+            app.popup_state = match status {
+                Ok(Some(status)) => PopupState::ScheduleStatus(status.lines()),
+                Ok(None) => PopupState::Error("No schedule status found, is `rustored schedule` running?".to_string()),
+                Err(e) => PopupState::Error(format!("Failed to load schedule status: {}", e)),
+            };
+        }
+        KeyCode::Char('v') if app.focus == FocusField::SnapshotList => {
+            // Verify the selected snapshot and cache the result for its badge
+            if let Some(snapshot) = app.snapshot_browser.snapshots.get(app.snapshot_browser.selected_index).cloned() {
+                let operation = app.begin_operation();
+                let verify = crate::verification::verify_snapshot(&mut app.snapshot_browser, &snapshot, chrono::Utc::now());
+                let result = crate::ui::app::cancel_on_escape(&operation, verify).await;
+                app.popup_state = match result {
+                    Ok(v) if v.is_ok() => PopupState::Success(format!("Verified {}", snapshot.key)),
+                    Ok(v) => PopupState::Error(format!(
+                        "{} failed verification (checksum ok: {}, archive ok: {:?})",
+                        snapshot.key, v.checksum_ok, v.archive_ok
+                    )),
+                    Err(e) => {
+                        debug!("Verification failed: {}", e);
+                        PopupState::Error(format!("Verification failed: {}", e))
+                    }
+                };
+            }
+        }
+        KeyCode::Char('h') if app.focus == FocusField::SnapshotList => {
+            // Show the version history of the selected snapshot to restore an older version
+            if let Some(snapshot) = app.snapshot_browser.snapshots.get(app.snapshot_browser.selected_index).cloned() {
+                app.popup_state = match app.snapshot_browser.load_versions(&snapshot.key).await {
+                    Ok(versions) if versions.is_empty() => PopupState::Error(format!("No versions of {} found", snapshot.key)),
+                    Ok(versions) => PopupState::VersionHistory(snapshot.key, versions, 0),
+                    Err(e) => PopupState::Error(format!("Failed to list versions: {}", e)),
+                };
+            }
+        }
+        KeyCode::Char('B') if app.focus == FocusField::SnapshotList => {
+            // Restore the bundle the selected snapshot belongs to, every member at once
+            if let Some(snapshot) = app.snapshot_browser.snapshots.get(app.snapshot_browser.selected_index).cloned() {
+                let members = match crate::bundle::find_bundle(&app.snapshot_browser.manifests, &snapshot.key) {
+                    Some(manifest) => crate::bundle::resolve_members(manifest, &app.snapshot_browser.snapshots),
+                    None => Err(anyhow::anyhow!("{} is not part of a bundle", snapshot.key)),
+                };
+                app.popup_state = match members {
+                    Ok(members) => {
+                        app.apply_restore_preset(&members[0].snapshot);
+                        let lines = app.bundle_lines(&members).await;
+                        PopupState::ConfirmBundleRestore(members, lines, String::new())
+                    }
+                    Err(e) => PopupState::Error(e.to_string()),
+                };
+            }
+        }
+        KeyCode::Char(' ') if app.focus == FocusField::SnapshotList => {
+            // Mark the selected snapshot so several can be deleted or restored at once
+            app.snapshot_browser.toggle_mark();
+        }
+        KeyCode::Char('d') if app.focus == FocusField::SnapshotList => {
+            // Delete the marked snapshots, or the selected one, once the deletion is typed out
+            let snapshots = app.snapshot_browser.deletion_candidates();
+            if !snapshots.is_empty() {
+                app.popup_state = PopupState::ConfirmDelete(snapshots, String::new());
+            }
+        }
+        KeyCode::Char('g') if app.focus == FocusField::SnapshotList => {
+            // Show the command that restores the selected snapshot without the TUI
+            if let Some(snapshot) = app.snapshot_browser.snapshots.get(app.snapshot_browser.selected_index).cloned() {
+                app.apply_restore_preset(&snapshot);
+                show_restore_command(app, &snapshot);
+            }
+        }
+        KeyCode::Char('u') if app.focus == FocusField::SnapshotList => crate::ui::presign::open(app),
+        KeyCode::Char('Q') if app.focus == FocusField::SnapshotList => crate::ui::batch::open(app),
+        KeyCode::Char('L') => crate::ui::local_file::open(app),
         KeyCode::Tab => handle_tab_navigation(app),
         KeyCode::Up => handle_up_navigation(app),
         KeyCode::Down => handle_down_navigation(app),
-        KeyCode::Enter => handle_enter_key(app),
+        KeyCode::Enter => {
+            handle_enter_key(app);
+            // Archived snapshots cannot be downloaded until they are restored
+            if let PopupState::ConfirmRestore(snapshot) = &app.popup_state {
+                if snapshot.storage_class.as_deref().is_some_and(crate::archive::is_archived_class) {
+                    let snapshot = snapshot.clone();
+                    check_archive_status(app, snapshot).await;
+                }
+            }
+            // Profiles listing several servers ask which one to restore onto first
+            crate::ui::server_picker::open(app);
+            // Show how the snapshot is encrypted and what the restore will change before it is downloaded
+            if let PopupState::ConfirmRestore(snapshot) = &app.popup_state {
+                let key = snapshot.key.clone();
+                app.snapshot_browser.load_encryption(&key).await;
+                app.load_restore_impact().await;
+            }
+        }
         _ => {}
     }
 
     Ok(None)
 }
 
+/// Offer to restore an archived snapshot instead of downloading it
+///
+/// Leaves the restore confirmation in place once the snapshot is readable.
+/// A failed lookup is logged, since the download reports the same problem.
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The selected snapshot, in an archive storage class
+async fn check_archive_status(app: &mut RustoredApp, snapshot: crate::ui::models::BackupMetadata) {
+    debug!("Checking archive status of selected snapshot: {}", snapshot.key);
+    match app.snapshot_browser.load_archive_status(&snapshot.key).await {
+        Ok(crate::archive::ArchiveStatus::Archived) => {
+            app.popup_state = PopupState::ConfirmArchiveRestore(snapshot, Default::default());
+        }
+        Ok(crate::archive::ArchiveStatus::Restoring) => {
+            app.popup_state = PopupState::Error(format!(
+                "{} is still being restored from the archive, try again once the snapshot list shows it restored",
+                snapshot.key
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => debug!("Failed to look up archive status of {}: {}", snapshot.key, e),
+    }
+}
+
 /// Handle Tab key navigation
 ///
 /// This function processes Tab key presses to navigate between main UI sections
@@ -310,14 +753,13 @@ fn handle_tab_navigation(app: &mut RustoredApp) {
         FocusField::EndpointUrl |
         FocusField::AccessKeyId |
         FocusField::SecretAccessKey |
-        FocusField::PathStyle => {
-            // Move to restore target settings
-            match app.restore_target {
-                RestoreTarget::Postgres => FocusField::PgHost,
-                RestoreTarget::Elasticsearch => FocusField::EsHost,
-                RestoreTarget::Qdrant => FocusField::QdrantApiKey,
-            }
-        }
+        FocusField::PathStyle |
+        FocusField::AzureAccount |
+        FocusField::AzureContainer |
+        FocusField::AzurePrefix |
+        FocusField::AzureAccessKey |
+        // Move to restore target settings
+        FocusField::AzureSasToken => app.restore_target.first_focus_field(),
         // Restore Target settings - move to Snapshot List
         FocusField::PgHost |
         FocusField::PgPort |
@@ -325,10 +767,20 @@ fn handle_tab_navigation(app: &mut RustoredApp) {
         FocusField::PgPassword |
         FocusField::PgSsl |
         FocusField::PgDbName |
+        FocusField::PgTargetDb |
+        FocusField::PgRestoreJobs |
         FocusField::EsHost |
         FocusField::EsIndex |
-        FocusField::QdrantApiKey => FocusField::SnapshotList,
-        // Snapshot list - move back to S3 Settings
+        FocusField::QdrantApiKey | FocusField::QdrantVectorSize | FocusField::QdrantDistance |
+        FocusField::OsHost |
+        FocusField::OsIndex |
+        FocusField::OsRegion |
+        FocusField::OsService => FocusField::SnapshotList,
+        field if MeilisearchConfig::contains_field(field) || WeaviateConfig::contains_field(field) => FocusField::SnapshotList,
+        field if MilvusConfig::contains_field(field) || DynamoDbConfig::contains_field(field) => FocusField::SnapshotList,
+        field if InfluxDbConfig::contains_field(field) => FocusField::SnapshotList,
+        // Snapshot list - move back to S3 (or Azure) Settings
+        FocusField::SnapshotList if app.snapshot_browser.azure_config.is_some() => FocusField::AzureAccount,
         FocusField::SnapshotList => FocusField::Bucket,
         // Default case
         _ => FocusField::Bucket,
@@ -337,6 +789,56 @@ fn handle_tab_navigation(app: &mut RustoredApp) {
     debug!("New focus after Tab navigation: {:?}", app.focus);
 }
 
+/// The fields of the settings panel holding the focus, in display order
+///
+/// # Arguments
+///
+/// * `app` - A reference to the RustoredApp
+fn panel_fields(app: &RustoredApp) -> &'static [FocusField] {
+    debug!("Getting fields of the panel holding {:?}", app.focus);
+    match app.focus {
+        // S3 Settings fields
+        FocusField::Bucket |
+        FocusField::Region |
+        FocusField::Prefix |
+        FocusField::EndpointUrl |
+        FocusField::AccessKeyId |
+        FocusField::SecretAccessKey |
+        FocusField::PathStyle => crate::ui::models::S3Config::focus_fields(),
+
+        // PostgreSQL Settings fields
+        FocusField::PgHost |
+        FocusField::PgPort |
+        FocusField::PgUsername |
+        FocusField::PgPassword |
+        FocusField::PgSsl |
+        FocusField::PgDbName |
+        FocusField::PgTargetDb |
+        FocusField::PgRestoreJobs => crate::ui::models::PostgresConfig::focus_fields(),
+
+        // Elasticsearch Settings fields
+        FocusField::EsHost |
+        FocusField::EsIndex => crate::ui::models::ElasticsearchConfig::focus_fields(),
+
+        // Qdrant Settings fields
+        FocusField::QdrantApiKey | FocusField::QdrantVectorSize | FocusField::QdrantDistance => crate::ui::models::QdrantConfig::focus_fields(),
+
+        // OpenSearch, Meilisearch, Weaviate, Milvus, DynamoDB and InfluxDB Settings fields
+        field if OpenSearchConfig::contains_field(field) => OpenSearchConfig::focus_fields(),
+        field if MeilisearchConfig::contains_field(field) => MeilisearchConfig::focus_fields(),
+        field if WeaviateConfig::contains_field(field) => WeaviateConfig::focus_fields(),
+        field if MilvusConfig::contains_field(field) => MilvusConfig::focus_fields(),
+        field if DynamoDbConfig::contains_field(field) => DynamoDbConfig::focus_fields(),
+        field if InfluxDbConfig::contains_field(field) => InfluxDbConfig::focus_fields(),
+
+        // Azure Settings fields
+        field if AzureConfig::contains_field(field) => AzureConfig::focus_fields(),
+
+        // Default case
+        _ => &[],
+    }
+}
+
 /// Handle Up key navigation
 ///
 /// This function processes Up key presses to navigate within UI sections
@@ -361,34 +863,7 @@ fn handle_up_navigation(app: &mut RustoredApp) {
         }
         _ => {
             // Navigate within settings panels
-            let focus_fields = match app.focus {
-                // S3 Settings fields
-                FocusField::Bucket |
-                FocusField::Region |
-                FocusField::Prefix |
-                FocusField::EndpointUrl |
-                FocusField::AccessKeyId |
-                FocusField::SecretAccessKey |
-                FocusField::PathStyle => crate::ui::models::S3Config::focus_fields(),
-
-                // PostgreSQL Settings fields
-                FocusField::PgHost |
-                FocusField::PgPort |
-                FocusField::PgUsername |
-                FocusField::PgPassword |
-                FocusField::PgSsl |
-                FocusField::PgDbName => crate::ui::models::PostgresConfig::focus_fields(),
-
-                // Elasticsearch Settings fields
-                FocusField::EsHost |
-                FocusField::EsIndex => crate::ui::models::ElasticsearchConfig::focus_fields(),
-
-                // Qdrant Settings fields
-                FocusField::QdrantApiKey => crate::ui::models::QdrantConfig::focus_fields(),
-
-                // Default case
-                _ => &[],
-            };
+            let focus_fields = panel_fields(app);
 
             if !focus_fields.is_empty() {
                 // Find current index
@@ -424,34 +899,7 @@ fn handle_down_navigation(app: &mut RustoredApp) {
         }
         _ => {
             // Navigate within settings panels
-            let focus_fields = match app.focus {
-                // S3 Settings fields
-                FocusField::Bucket |
-                FocusField::Region |
-                FocusField::Prefix |
-                FocusField::EndpointUrl |
-                FocusField::AccessKeyId |
-                FocusField::SecretAccessKey |
-                FocusField::PathStyle => crate::ui::models::S3Config::focus_fields(),
-
-                // PostgreSQL Settings fields
-                FocusField::PgHost |
-                FocusField::PgPort |
-                FocusField::PgUsername |
-                FocusField::PgPassword |
-                FocusField::PgSsl |
-                FocusField::PgDbName => crate::ui::models::PostgresConfig::focus_fields(),
-
-                // Elasticsearch Settings fields
-                FocusField::EsHost |
-                FocusField::EsIndex => crate::ui::models::ElasticsearchConfig::focus_fields(),
-
-                // Qdrant Settings fields
-                FocusField::QdrantApiKey => crate::ui::models::QdrantConfig::focus_fields(),
-
-                // Default case
-                _ => &[],
-            };
+            let focus_fields = panel_fields(app);
 
             if !focus_fields.is_empty() {
                 // Find current index
@@ -478,11 +926,14 @@ fn handle_enter_key(app: &mut RustoredApp) {
 
     match app.focus {
         FocusField::SnapshotList => {
-            // Select a snapshot for restoration
-            // Select a snapshot for restoration if one is available
+            // Select a snapshot for restoration if one is available; base backups are recovered to a point in time
             if !app.snapshot_browser.snapshots.is_empty() {
-                let snapshot = &app.snapshot_browser.snapshots[app.snapshot_browser.selected_index];
-                app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+                let snapshot = app.snapshot_browser.snapshots[app.snapshot_browser.selected_index].clone();
+                if app.snapshot_browser.wal_chains.contains_key(&snapshot.key) {
+                    return crate::ui::pitr::open(app, snapshot);
+                }
+                app.apply_restore_preset(&snapshot);
+                app.popup_state = PopupState::ConfirmRestore(snapshot);
             }
         }
         _ => {
@@ -507,6 +958,8 @@ fn handle_enter_key(app: &mut RustoredApp) {
                 FocusField::PgPassword => app.pg_config.password.clone().unwrap_or_default(),
                 FocusField::PgSsl => app.pg_config.use_ssl.to_string(),
                 FocusField::PgDbName => app.pg_config.db_name.clone().unwrap_or_default(),
+                FocusField::PgTargetDb => app.pg_config.target_db.clone().unwrap_or_default(),
+                FocusField::PgRestoreJobs => app.pg_config.get_field_value(FocusField::PgRestoreJobs),
 
                 // Elasticsearch Settings fields
                 FocusField::EsHost => app.es_config.host.clone().unwrap_or_default(),
@@ -514,6 +967,21 @@ fn handle_enter_key(app: &mut RustoredApp) {
 
                 // Qdrant Settings fields
                 FocusField::QdrantApiKey => app.qdrant_config.api_key.clone().unwrap_or_default(),
+                field @ (FocusField::QdrantVectorSize | FocusField::QdrantDistance) => app.qdrant_config.get_field_value(field),
+
+                // OpenSearch, Meilisearch, Weaviate, Milvus, DynamoDB and InfluxDB Settings fields
+                field if OpenSearchConfig::contains_field(field) => app.os_config.get_field_value(field),
+                field if MeilisearchConfig::contains_field(field) => app.meili_config.get_field_value(field),
+                field if WeaviateConfig::contains_field(field) => app.weaviate_config.get_field_value(field),
+                field if MilvusConfig::contains_field(field) => app.milvus_config.get_field_value(field),
+                field if DynamoDbConfig::contains_field(field) => app.dynamodb_config.get_field_value(field),
+                field if InfluxDbConfig::contains_field(field) => app.influxdb_config.get_field_value(field),
+
+                // Azure Settings fields
+                field if AzureConfig::contains_field(field) => app.snapshot_browser.azure_config
+                    .as_ref()
+                    .map(|azure| azure.get_field_value(field))
+                    .unwrap_or_default(),
 
                 // Default case
                 _ => String::new(),
@@ -0,0 +1,144 @@
+// This module contains restores of local files in the Rustored TUI
+// `L` asks for the path of a dump already on disk, completing it with Tab like
+// a shell. The file then goes through the same confirmation, plan and progress
+// as a snapshot from the bucket, but is never downloaded, decrypted or deleted:
+// it is handed to the restore as if it had just been downloaded.
+
+use crate::ui::models::{BackupMetadata, PopupState};
+use crate::ui::rustored::RustoredApp;
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+use std::path::PathBuf;
+
+/// Expand a leading `~` to the home directory
+fn expand_home(typed: &str) -> PathBuf {
+    match (typed.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => PathBuf::from(format!("{}{}", home.to_string_lossy(), rest)),
+        _ => PathBuf::from(typed),
+    }
+}
+
+/// Complete a typed path as far as the entries it could name agree
+///
+/// Directories are completed with a trailing `/`, and hidden entries are only
+/// offered once a `.` is typed.
+///
+/// # Arguments
+///
+/// * `typed` - The path as typed so far
+///
+/// # Returns
+///
+/// The completed path, and the names of the entries it could still be, sorted
+pub fn complete_path(typed: &str) -> (String, Vec<String>) {
+    debug!("Completing path: {}", typed);
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(index) => typed.split_at(index + 1),
+        None => ("", typed),
+    };
+    let listed = match dir.is_empty() {
+        true => PathBuf::from("."),
+        false => expand_home(dir),
+    };
+    let mut names: Vec<String> = std::fs::read_dir(&listed)
+        .map(|entries| entries.flatten().filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.path().is_dir();
+            (name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')))
+                .then(|| if is_dir { format!("{}/", name) } else { name })
+        }).collect())
+        .unwrap_or_default();
+    names.sort();
+    let common = match names.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.as_str(), |common, name| {
+            let length = common.char_indices().zip(name.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(name.len()), |((index, _), _)| index);
+            &common[..length]
+        }),
+        None => prefix,
+    };
+    (format!("{}{}", dir, common), names)
+}
+
+/// The snapshot standing for a local file, keyed by its path
+///
+/// # Arguments
+///
+/// * `typed` - The path as typed, `~` meaning the home directory
+pub fn local_snapshot(typed: &str) -> Result<BackupMetadata> {
+    debug!("Reading local file to restore: {}", typed);
+    let path = expand_home(typed.trim());
+    let metadata = std::fs::metadata(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+    if !metadata.is_file() {
+        return Err(anyhow!("{} is not a file", path.display()));
+    }
+    let last_modified = metadata.modified().ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0.0, |modified| modified.as_secs_f64());
+    Ok(BackupMetadata::new(path.to_string_lossy(), metadata.len() as i64, last_modified))
+}
+
+/// Whether a confirmed snapshot is a local file rather than one in the bucket
+pub fn is_local(app: &RustoredApp, snapshot: &BackupMetadata) -> bool {
+    debug!("Checking if {} is a local file", snapshot.key);
+    app.downloaded_snapshot(snapshot).is_some_and(|path| path == snapshot.key)
+}
+
+/// Ask for the path of a local file to restore
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+pub fn open(app: &mut RustoredApp) {
+    debug!("Opening local file prompt");
+    app.popup_state = PopupState::LocalFile(String::new(), Vec::new());
+}
+
+/// Confirm restoring the typed file, as Enter on a snapshot does
+async fn select(app: &mut RustoredApp, typed: &str) -> Result<()> {
+    debug!("Selecting local file {} for restore", typed);
+    let snapshot = local_snapshot(typed)?;
+    if crate::wal::base_start_segment(&snapshot.key).is_some() {
+        return Err(anyhow!("{} is a base backup; recover it from the bucket with Enter on it", snapshot.key));
+    }
+    app.downloaded = Some((snapshot.clone(), snapshot.key.clone()));
+    app.apply_restore_preset(&snapshot);
+    app.snapshot_browser.selected_encryption = None;
+    app.popup_state = PopupState::ConfirmRestore(snapshot);
+    app.load_restore_impact().await;
+    Ok(())
+}
+
+/// Handle a key in the local file prompt
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) {
+    debug!("Handling local file key: {:?}", key);
+    let PopupState::LocalFile(typed, candidates) = &mut app.popup_state else {
+        return;
+    };
+    match key.code {
+        KeyCode::Tab => (*typed, *candidates) = complete_path(typed),
+        KeyCode::Enter => {
+            let typed = typed.clone();
+            if let Err(e) = select(app, &typed).await {
+                app.popup_state = PopupState::Error(format!("Cannot restore {}: {}", typed, e));
+            }
+        }
+        KeyCode::Backspace => {
+            typed.pop();
+            candidates.clear();
+        }
+        KeyCode::Char(c) => {
+            typed.push(c);
+            candidates.clear();
+        }
+        KeyCode::Esc => app.popup_state = PopupState::Hidden,
+        _ => {}
+    }
+}
@@ -1,9 +1,23 @@
 // UI module for the postgres manager
 pub mod models;
 pub mod browser;
+pub mod command;
 pub mod renderer;
 pub mod rustored;
 pub mod layouts;
 pub mod components;
 pub mod app;
 pub mod key_handler;
+pub mod terminal_status;
+pub mod attention;
+pub mod recording;
+pub mod table_picker;
+pub mod restore_preview;
+pub mod restore_plan;
+pub mod presign;
+pub mod pitr;
+pub mod local_file;
+pub mod batch;
+pub mod bundle_restore;
+pub mod guard_rails;
+pub mod server_picker;
@@ -13,6 +13,29 @@ pub enum RestoreTarget {
     Postgres,
     Elasticsearch,
     Qdrant,
+    OpenSearch,
+    Meilisearch,
+    Weaviate,
+    Milvus,
+    DynamoDb,
+    InfluxDb,
+}
+
+impl From<crate::datastore::RestoreTarget> for RestoreTarget {
+    fn from(target: crate::datastore::RestoreTarget) -> Self {
+        use crate::datastore::RestoreTarget as Kind;
+        match target {
+            Kind::Postgres => RestoreTarget::Postgres,
+            Kind::Elasticsearch => RestoreTarget::Elasticsearch,
+            Kind::Qdrant => RestoreTarget::Qdrant,
+            Kind::OpenSearch => RestoreTarget::OpenSearch,
+            Kind::Meilisearch => RestoreTarget::Meilisearch,
+            Kind::Weaviate => RestoreTarget::Weaviate,
+            Kind::Milvus => RestoreTarget::Milvus,
+            Kind::DynamoDb => RestoreTarget::DynamoDb,
+            Kind::InfluxDb => RestoreTarget::InfluxDb,
+        }
+    }
 }
 
 impl RestoreTarget {
@@ -22,6 +45,12 @@ impl RestoreTarget {
             RestoreTarget::Postgres => postgres_config::PostgresConfig::focus_fields(),
             RestoreTarget::Elasticsearch => elasticsearch_config::ElasticsearchConfig::focus_fields(),
             RestoreTarget::Qdrant => qdrant_config::QdrantConfig::focus_fields(),
+            RestoreTarget::OpenSearch => opensearch_config::OpenSearchConfig::focus_fields(),
+            RestoreTarget::Meilisearch => meilisearch_config::MeilisearchConfig::focus_fields(),
+            RestoreTarget::Weaviate => weaviate_config::WeaviateConfig::focus_fields(),
+            RestoreTarget::Milvus => milvus_config::MilvusConfig::focus_fields(),
+            RestoreTarget::DynamoDb => dynamodb_config::DynamoDbConfig::focus_fields(),
+            RestoreTarget::InfluxDb => influxdb_config::InfluxDbConfig::focus_fields(),
         }
     }
     
@@ -31,6 +60,12 @@ impl RestoreTarget {
             RestoreTarget::Postgres => FocusField::PgHost,
             RestoreTarget::Elasticsearch => FocusField::EsHost,
             RestoreTarget::Qdrant => FocusField::QdrantApiKey,
+            RestoreTarget::OpenSearch => FocusField::OsHost,
+            RestoreTarget::Meilisearch => FocusField::MeiliHost,
+            RestoreTarget::Weaviate => FocusField::WeaviateHost,
+            RestoreTarget::Milvus => FocusField::MilvusHost,
+            RestoreTarget::DynamoDb => FocusField::DynamoDbTable,
+            RestoreTarget::InfluxDb => FocusField::InfluxHost,
         }
     }
 }
@@ -41,6 +76,34 @@ pub mod elasticsearch_config;
 pub use elasticsearch_config::ElasticsearchConfig;
 pub mod qdrant_config;
 pub use qdrant_config::QdrantConfig;
+pub mod opensearch_config;
+pub use opensearch_config::OpenSearchConfig;
+pub mod meilisearch_config;
+pub use meilisearch_config::MeilisearchConfig;
+pub mod weaviate_config;
+pub use weaviate_config::WeaviateConfig;
+pub mod milvus_config;
+pub use milvus_config::MilvusConfig;
+pub mod dynamodb_config;
+pub use dynamodb_config::DynamoDbConfig;
+pub mod influxdb_config;
+pub use influxdb_config::InfluxDbConfig;
+pub mod timeout_config;
+pub use timeout_config::TimeoutConfig;
+pub mod tls_config;
+pub use tls_config::TlsConfig;
+pub mod bulk_config;
+pub use bulk_config::BulkConfig;
+pub mod qdrant_upload_config;
+pub use qdrant_upload_config::{QdrantTransport, QdrantUploadConfig};
+pub mod gcs_config;
+pub use gcs_config::GcsConfig;
+pub mod azure_config;
+pub use azure_config::AzureConfig;
+pub mod b2_config;
+pub use b2_config::B2Config;
+pub mod sftp_config;
+pub use sftp_config::SftpConfig;
 
 /// Input mode for the UI
 #[derive(Debug, PartialEq)]
@@ -50,11 +113,34 @@ pub enum InputMode {
 }
 
 /// Metadata for a backup
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct BackupMetadata {
     pub key: String,
     pub size: i64,
     pub last_modified: f64,
+    /// ETag or backend version id, used to detect a replaced object when resuming a download
+    pub etag: Option<String>,
+    /// Storage class reported by the backend, e.g. `GLACIER`, when it has one
+    pub storage_class: Option<String>,
+    /// Object version, set for older versions picked from a versioned bucket's history
+    pub version_id: Option<String>,
+}
+
+impl BackupMetadata {
+    /// Metadata of an object with no ETag, storage class or version
+    pub fn new(key: impl Into<String>, size: i64, last_modified: f64) -> Self {
+        let key = key.into();
+        log::debug!("Creating backup metadata for {}", key);
+        Self { key, size, last_modified, etag: None, storage_class: None, version_id: None }
+    }
+
+    /// Whether this snapshot was taken with the "backup now" action and
+    /// should be pinned to the top of the snapshot list
+    pub fn is_pinned(&self) -> bool {
+        log::debug!("Checking if snapshot {} is pinned", self.key);
+        let label = format!("-{}-", crate::backup::PRE_CHANGE_LABEL);
+        self.key.rsplit('/').next().is_some_and(|name| name.contains(&label))
+    }
 }
 
 /// State of the popup
@@ -62,6 +148,13 @@ pub struct BackupMetadata {
 pub enum PopupState {
     Hidden,
     ConfirmRestore(BackupMetadata),
+    ConfirmArchiveRestore(BackupMetadata, crate::archive::ArchiveTier), // Archived snapshot, retrieval tier to restore it with
+    SelectRecipes(BackupMetadata, usize), // Snapshot being confirmed, and the highlighted built-in recipe
+    SelectServer(BackupMetadata, usize), // Snapshot being confirmed, and the highlighted server of the profile
+    SelectTables(BackupMetadata, crate::toc::TableSelection), // Snapshot being confirmed, and its tables ticked for restoring
+    RestorePreview(BackupMetadata, Vec<String>, usize), // Snapshot being confirmed, the objects restoring it would create, and the first line shown
+    RestorePlan(BackupMetadata, Vec<String>), // Confirmed snapshot, and everything restoring it will do
+    VersionHistory(String, Vec<BackupMetadata>, usize), // Versions of a key, newest first, and the selected one
     Downloading(BackupMetadata, f32, f64),
     ConfirmCancel(BackupMetadata, f32, f64),
     Restoring(BackupMetadata, f32),  // Snapshot being restored, progress percentage
@@ -69,6 +162,21 @@ pub enum PopupState {
     TestS3Result(String),            // Result of S3 connection test
     TestingPg,                       // Testing PostgreSQL connection in progress
     TestPgResult(String),            // Result of PostgreSQL connection test
+    GeneratedCommand(String, Result<(), String>), // Non-interactive equivalent of the current selections, and whether copying it failed
+    LocalFile(String, Vec<String>), // Path of a local dump typed so far, and the entries Tab could complete it to
+    RecoverTarget(BackupMetadata, String, String), // Base backup, its recoverable window, and the target time typed so far
+    PresignDuration(BackupMetadata, String), // Snapshot to link to, and how long the link stays valid as typed so far
+    PresignedLink(String, String, String, Result<(), String>), // Linked snapshot's key, the presigned URL, when it expires, and whether copying it failed
+    RestoreSummary(Vec<String>),     // Restore result and how it differs from the live database
+    ConfirmPromote(crate::promote::PromotePlan), // Promotion of the restored database awaiting confirmation
+    ConfirmDelete(Vec<BackupMetadata>, String), // Snapshots to delete and the confirmation typed so far
+    ConfirmImpact(BackupMetadata, String), // Restore into a target holding data, and the target name typed so far
+    ConfirmProduction(crate::ui::guard_rails::GuardedAction, String), // Destructive action in production, and the environment name typed so far
+    ConfirmBundleRestore(Vec<crate::bundle::BundleMember>, Vec<String>, String), // Bundle members, what restoring them does, and the confirmation typed so far
+    RestoringBundle(Vec<crate::bundle::BundleMember>, usize), // Bundle members being downloaded and restored, and how many are downloaded
+    ConfirmBatchRestore(Vec<BackupMetadata>, Vec<String>), // Marked snapshots, and where each one is restored to
+    RestoringBatch(Vec<BackupMetadata>, Vec<String>), // Snapshots restored one after another, and the outcome of each finished one
+    ScheduleStatus(Vec<String>),     // Next and last run of each job of the schedule daemon
     Error(String),
     Success(String),
 }
@@ -92,11 +200,49 @@ pub enum FocusField {
     PgPassword,      // Alt+r
     PgSsl,          // Alt+t
     PgDbName,        // Alt+y
+    PgTargetDb,
+    PgRestoreJobs,
     SnapshotList,
     RestoreTarget,
     EsHost,
     EsIndex,
     QdrantApiKey,
+    QdrantVectorSize,
+    QdrantDistance,
+    OsHost,
+    OsIndex,
+    OsRegion,
+    OsService,
+    MeiliHost,
+    MeiliIndex,
+    MeiliApiKey,
+    MeiliPrimaryKey,
+    WeaviateHost,
+    WeaviateClass,
+    WeaviateApiKey,
+    WeaviateOidcSecret,
+    WeaviateOidcScope,
+    MilvusHost,
+    MilvusCollection,
+    MilvusToken,
+    MilvusDatabase,
+    MilvusVectorField,
+    MilvusMetric,
+    DynamoDbTable,
+    DynamoDbRegion,
+    DynamoDbEndpointUrl,
+    InfluxHost,
+    InfluxOrg,
+    InfluxBucket,
+    InfluxToken,
+    InfluxPrecision,
+
+    // Azure Blob Settings, shown instead of S3 when Azure is the source
+    AzureAccount,
+    AzureContainer,
+    AzurePrefix,
+    AzureAccessKey,
+    AzureSasToken,
 }
 
 impl fmt::Display for FocusField {
@@ -120,11 +266,74 @@ impl fmt::Display for FocusField {
             FocusField::PgPassword => write!(f, "PostgreSQL Password"),
             FocusField::PgSsl => write!(f, "PostgreSQL SSL"),
             FocusField::PgDbName => write!(f, "PostgreSQL Database"),
+            FocusField::PgTargetDb => write!(f, "PostgreSQL Target Database"),
+            FocusField::PgRestoreJobs => write!(f, "PostgreSQL Restore Jobs"),
             // Elasticsearch Settings (30-39)
             FocusField::EsHost => write!(f, "Elasticsearch/Qdrant Host"),
             FocusField::EsIndex => write!(f, "Index/Collection"),
             // Qdrant Settings (40-49)
             FocusField::QdrantApiKey => write!(f, "Qdrant API Key"),
+            FocusField::QdrantVectorSize => write!(f, "Qdrant Vector Size"),
+            FocusField::QdrantDistance => write!(f, "Qdrant Distance"),
+            // OpenSearch Settings
+            FocusField::OsHost => write!(f, "OpenSearch Host"),
+            FocusField::OsIndex => write!(f, "OpenSearch Index"),
+            FocusField::OsRegion => write!(f, "OpenSearch AWS Region"),
+            FocusField::OsService => write!(f, "OpenSearch SigV4 Service"),
+            // Meilisearch Settings
+            FocusField::MeiliHost => write!(f, "Meilisearch Host"),
+            FocusField::MeiliIndex => write!(f, "Meilisearch Index"),
+            FocusField::MeiliApiKey => write!(f, "Meilisearch API Key"),
+            FocusField::MeiliPrimaryKey => write!(f, "Meilisearch Primary Key"),
+            // Weaviate Settings
+            FocusField::WeaviateHost => write!(f, "Weaviate Host"),
+            FocusField::WeaviateClass => write!(f, "Weaviate Class"),
+            FocusField::WeaviateApiKey => write!(f, "Weaviate API Key"),
+            FocusField::WeaviateOidcSecret => write!(f, "Weaviate OIDC Client Secret"),
+            FocusField::WeaviateOidcScope => write!(f, "Weaviate OIDC Scope"),
+            // Milvus Settings
+            FocusField::MilvusHost => write!(f, "Milvus Host"),
+            FocusField::MilvusCollection => write!(f, "Milvus Collection"),
+            FocusField::MilvusToken => write!(f, "Milvus Token"),
+            FocusField::MilvusDatabase => write!(f, "Milvus Database"),
+            FocusField::MilvusVectorField => write!(f, "Milvus Vector Field"),
+            FocusField::MilvusMetric => write!(f, "Milvus Index Metric"),
+            // DynamoDB Settings
+            FocusField::DynamoDbTable => write!(f, "DynamoDB Table"),
+            FocusField::DynamoDbRegion => write!(f, "DynamoDB Region"),
+            FocusField::DynamoDbEndpointUrl => write!(f, "DynamoDB Endpoint URL"),
+            // InfluxDB Settings
+            FocusField::InfluxHost => write!(f, "InfluxDB Host"),
+            FocusField::InfluxOrg => write!(f, "InfluxDB Organization"),
+            FocusField::InfluxBucket => write!(f, "InfluxDB Bucket"),
+            FocusField::InfluxToken => write!(f, "InfluxDB Token"),
+            FocusField::InfluxPrecision => write!(f, "InfluxDB Precision"),
+            // Azure Blob Settings
+            FocusField::AzureAccount => write!(f, "Azure Storage Account"),
+            FocusField::AzureContainer => write!(f, "Azure Container"),
+            FocusField::AzurePrefix => write!(f, "Azure Prefix"),
+            FocusField::AzureAccessKey => write!(f, "Azure Access Key"),
+            FocusField::AzureSasToken => write!(f, "Azure SAS Token"),
         }
     }
 }
+
+impl FocusField {
+    /// Whether the field holds a credential, which session recordings redact
+    pub fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            FocusField::AccessKeyId
+                | FocusField::SecretAccessKey
+                | FocusField::PgPassword
+                | FocusField::QdrantApiKey
+                | FocusField::MeiliApiKey
+                | FocusField::WeaviateApiKey
+                | FocusField::WeaviateOidcSecret
+                | FocusField::MilvusToken
+                | FocusField::InfluxToken
+                | FocusField::AzureAccessKey
+                | FocusField::AzureSasToken
+        )
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use object_store::azure::{AzureConfigKey, MicrosoftAzure, MicrosoftAzureBuilder};
+
+/// Configuration for an Azure Blob Storage snapshot source
+///
+/// Either a storage account access key or a SAS token is used to authenticate;
+/// when both are empty the client falls back to the Azure credential chain
+/// (environment, managed identity or the Azure CLI).
+#[derive(Clone, Debug, Default)]
+pub struct AzureConfig {
+    pub account: String,
+    pub container: String,
+    pub prefix: String,
+    pub access_key: String,
+    pub sas_token: String,
+    pub timeouts: super::TimeoutConfig,
+}
+
+impl AzureConfig {
+    /// Get all focus fields for Azure settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for AzureConfig");
+        use super::FocusField;
+        &[
+            FocusField::AzureAccount,
+            FocusField::AzureContainer,
+            FocusField::AzurePrefix,
+            FocusField::AzureAccessKey,
+            FocusField::AzureSasToken,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::AzureAccount => self.account.clone(),
+            FocusField::AzureContainer => self.container.clone(),
+            FocusField::AzurePrefix => self.prefix.clone(),
+            FocusField::AzureAccessKey => self.access_key.clone(),
+            FocusField::AzureSasToken => self.sas_token.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field {:?} to new value", field);
+        use super::FocusField;
+        match field {
+            FocusField::AzureAccount => self.account = value,
+            FocusField::AzureContainer => self.container = value,
+            FocusField::AzurePrefix => self.prefix = value,
+            FocusField::AzureAccessKey => self.access_key = value,
+            FocusField::AzureSasToken => self.sas_token = value,
+            _ => {},
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to AzureConfig", field);
+        use super::FocusField;
+        matches!(field,
+            FocusField::AzureAccount |
+            FocusField::AzureContainer |
+            FocusField::AzurePrefix |
+            FocusField::AzureAccessKey |
+            FocusField::AzureSasToken
+        )
+    }
+
+    /// Get the display value for a field, hiding secrets unless being edited
+    pub fn get_field_display(&self, field: super::FocusField, is_editing: bool, input_buffer: &str) -> String {
+        debug!("Getting display value for {:?}, is_editing: {}", field, is_editing);
+        use super::FocusField;
+        if is_editing {
+            return input_buffer.to_string();
+        }
+        match field {
+            FocusField::AzureAccessKey | FocusField::AzureSasToken => {
+                let value = self.get_field_value(field);
+                if value.is_empty() { String::new() } else { String::from("[hidden]") }
+            }
+            _ => self.get_field_value(field),
+        }
+    }
+
+    /// Verify Azure settings are valid
+    pub fn verify_settings(&self) -> Result<()> {
+        debug!("Verifying Azure settings for account: {}, container: {}", self.account, self.container);
+        if self.account.is_empty() {
+            return Err(anyhow!("Azure storage account is required"));
+        }
+        if self.container.is_empty() {
+            return Err(anyhow!("Azure container is required"));
+        }
+        Ok(())
+    }
+
+    /// Initialize an Azure Blob client with current settings
+    pub fn create_client(&self) -> Result<MicrosoftAzure> {
+        debug!("Creating Azure client for account: {}, container: {}", self.account, self.container);
+        self.verify_settings()?;
+
+        let mut builder = MicrosoftAzureBuilder::new()
+            .with_account(&self.account)
+            .with_container_name(&self.container)
+            .with_client_options(self.timeouts.to_object_store());
+
+        // An access key wins over a SAS token when both are given
+        if !self.access_key.is_empty() {
+            builder = builder.with_access_key(&self.access_key);
+        } else if !self.sas_token.is_empty() {
+            builder = builder.with_config(AzureConfigKey::SasKey, self.sas_token.trim_start_matches('?'));
+        }
+
+        builder.build().map_err(|e| anyhow!("Failed to create Azure client: {}", e))
+    }
+}
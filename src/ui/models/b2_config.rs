@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+
+/// URL of the Backblaze B2 native API used to authorize an account
+pub const DEFAULT_B2_API_URL: &str = "https://api.backblazeb2.com";
+
+/// Configuration for a Backblaze B2 snapshot source using the native B2 API
+///
+/// An application key ID and key are exchanged for an auth token on first use;
+/// the token is refreshed automatically when B2 reports it expired.
+#[derive(Clone, Debug, Default)]
+pub struct B2Config {
+    pub bucket: String,
+    pub prefix: String,
+    pub key_id: String,
+    pub application_key: String,
+    /// Authorization endpoint, `DEFAULT_B2_API_URL` when empty
+    pub api_url: String,
+    pub timeouts: super::TimeoutConfig,
+}
+
+impl B2Config {
+    /// Verify B2 settings are valid
+    pub fn verify_settings(&self) -> Result<()> {
+        debug!("Verifying B2 settings for bucket: {}", self.bucket);
+        if self.bucket.is_empty() {
+            return Err(anyhow!("B2 bucket name is required"));
+        }
+        if self.key_id.is_empty() || self.application_key.is_empty() {
+            return Err(anyhow!("B2 application key ID and key are required"));
+        }
+        Ok(())
+    }
+
+    /// Authorization endpoint to use
+    pub fn api_url(&self) -> &str {
+        debug!("Getting B2 API URL");
+        if self.api_url.is_empty() { DEFAULT_B2_API_URL } else { &self.api_url }
+    }
+}
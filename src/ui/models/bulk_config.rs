@@ -0,0 +1,65 @@
+/// Bulk loading and index creation settings for Elasticsearch restores
+use log::debug;
+
+/// Default number of documents sent in one `_bulk` request
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// Default number of `_bulk` requests in flight at once
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Batch size, concurrency and refresh handling of a bulk restore, and the shards of the index it creates
+///
+/// Large indices load many times faster with several bulk requests in flight
+/// and refreshes turned off, so both are the default. The index's own refresh
+/// interval is put back once the load ends, whether or not it succeeded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkConfig {
+    pub batch_size: usize,
+    pub concurrency: usize,
+    /// Set `refresh_interval` to `-1` during the load
+    pub disable_refresh: bool,
+    /// Primary shards of an index created by the restore, instead of the index definition's
+    pub shards: Option<u32>,
+    /// Replicas of an index created by the restore, instead of the index definition's
+    pub replicas: Option<u32>,
+    /// Treat the index name as an alias, load a new timestamped index and move the alias to it
+    pub alias_swap: bool,
+}
+
+impl Default for BulkConfig {
+    fn default() -> Self {
+        debug!("Creating default BulkConfig instance");
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            disable_refresh: true,
+            shards: None,
+            replicas: None,
+            alias_swap: false,
+        }
+    }
+}
+
+impl BulkConfig {
+    /// Build a bulk config, falling back to the defaults for unset values
+    ///
+    /// A batch size or concurrency of 0 is raised to 1.
+    pub fn new(batch_size: Option<usize>, concurrency: Option<usize>, keep_refresh: bool) -> Self {
+        debug!("Creating BulkConfig: batch_size={:?}, concurrency={:?}, keep_refresh={}", batch_size, concurrency, keep_refresh);
+        Self {
+            batch_size: batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+            concurrency: concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+            disable_refresh: !keep_refresh,
+            ..Self::default()
+        }
+    }
+
+    /// What a restore with these settings calls its target, and does to it if it exists
+    pub fn target_kind(&self) -> (&'static str, crate::restore::ImpactAction) {
+        debug!("Getting target kind of bulk restore, alias_swap={}", self.alias_swap);
+        match self.alias_swap {
+            true => ("alias", crate::restore::ImpactAction::Swap),
+            false => ("index", crate::restore::ImpactAction::Append),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use log::debug;
+
+/// Configuration for DynamoDB restore target
+///
+/// Credentials come from the default AWS credential chain.
+#[derive(Clone, Debug, Default)]
+pub struct DynamoDbConfig {
+    pub table: Option<String>,
+    /// AWS region of the table, the region of the AWS credential chain when unset
+    pub region: Option<String>,
+    /// Endpoint to use instead of AWS's, e.g. DynamoDB Local
+    pub endpoint_url: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
+}
+
+impl DynamoDbConfig {
+    /// The table a restore writes to: the configured table, renamed by the mapping
+    pub fn target_table(&self) -> Option<String> {
+        debug!("Getting target table for DynamoDB restore");
+        self.table.as_deref().map(|table| self.mapping.map_name(table))
+    }
+
+    /// Get all focus fields for DynamoDB settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for DynamoDB settings");
+        use super::FocusField;
+        &[
+            FocusField::DynamoDbTable,
+            FocusField::DynamoDbRegion,
+            FocusField::DynamoDbEndpointUrl,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for DynamoDB field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::DynamoDbTable => self.table.clone().unwrap_or_default(),
+            FocusField::DynamoDbRegion => self.region.clone().unwrap_or_default(),
+            FocusField::DynamoDbEndpointUrl => self.endpoint_url.clone().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    ///
+    /// An empty region or endpoint URL clears it.
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field value for DynamoDB field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::DynamoDbTable => self.table = Some(value),
+            FocusField::DynamoDbRegion => self.region = (!value.is_empty()).then_some(value),
+            FocusField::DynamoDbEndpointUrl => self.endpoint_url = (!value.is_empty()).then_some(value),
+            _ => debug!("Ignoring attempt to set unrelated field: {:?}", field),
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to DynamoDB config", field);
+        Self::focus_fields().contains(&field)
+    }
+}
@@ -5,9 +5,21 @@ use log::debug;
 pub struct ElasticsearchConfig {
     pub host: Option<String>,
     pub index: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    pub bulk: super::BulkConfig,
+    /// CA bundle, client certificate and verification for HTTPS hosts
+    pub tls: super::TlsConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
 }
 
 impl ElasticsearchConfig {
+    /// The index a restore loads into: the configured index, renamed by the mapping
+    pub fn target_index(&self) -> Option<String> {
+        debug!("Getting target index for Elasticsearch restore");
+        self.index.as_deref().map(|index| self.mapping.map_name(index))
+    }
+
     /// Get all focus fields for Elasticsearch settings
     pub fn focus_fields() -> &'static [super::FocusField] {
         debug!("Getting focus fields for Elasticsearch settings");
@@ -0,0 +1,50 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use object_store::gcp::{GoogleCloudStorage, GoogleCloudStorageBuilder};
+
+/// Configuration for a Google Cloud Storage snapshot source
+///
+/// Credentials are taken from a service-account JSON file or key when one is
+/// given; otherwise Application Default Credentials are used
+/// (`GOOGLE_APPLICATION_CREDENTIALS`, the gcloud well-known file, or the
+/// instance metadata server).
+#[derive(Clone, Debug, Default)]
+pub struct GcsConfig {
+    pub bucket: String,
+    pub prefix: String,
+    pub service_account_path: Option<String>,
+    pub service_account_key: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+}
+
+impl GcsConfig {
+    /// Verify GCS settings are valid
+    pub fn verify_settings(&self) -> Result<()> {
+        debug!("Verifying GCS settings for bucket: {}", self.bucket);
+        if self.bucket.is_empty() {
+            return Err(anyhow!("GCS bucket name is required"));
+        }
+        Ok(())
+    }
+
+    /// Initialize a GCS client with current settings
+    pub fn create_client(&self) -> Result<GoogleCloudStorage> {
+        debug!("Creating GCS client for bucket: {}", self.bucket);
+        self.verify_settings()?;
+
+        let mut builder = GoogleCloudStorageBuilder::new()
+            .with_bucket_name(&self.bucket)
+            .with_client_options(self.timeouts.to_object_store());
+
+        // Explicit service-account credentials win over ADC
+        if let Some(path) = self.service_account_path.as_ref().filter(|p| !p.is_empty()) {
+            builder = builder.with_service_account_path(path);
+        } else if let Some(key) = self.service_account_key.as_ref().filter(|k| !k.is_empty()) {
+            builder = builder.with_service_account_key(key);
+        } else if let Ok(path) = std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
+            builder = builder.with_application_credentials(path);
+        }
+
+        builder.build().map_err(|e| anyhow!("Failed to create GCS client: {}", e))
+    }
+}
@@ -0,0 +1,88 @@
+use log::debug;
+
+/// Configuration for InfluxDB restore target
+#[derive(Clone, Debug, Default)]
+pub struct InfluxDbConfig {
+    pub host: Option<String>,
+    /// Organization owning the bucket
+    pub org: Option<String>,
+    pub bucket: Option<String>,
+    /// API token with write access to the bucket
+    pub token: Option<String>,
+    /// Timestamp precision of the points, `ns` when unset
+    pub precision: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
+}
+
+impl InfluxDbConfig {
+    /// The bucket a restore writes to: the configured bucket, renamed by the mapping
+    pub fn target_bucket(&self) -> Option<String> {
+        debug!("Getting target bucket for InfluxDB restore");
+        self.bucket.as_deref().map(|bucket| self.mapping.map_name(bucket))
+    }
+
+    /// The bucket, organization and token a restore writes with
+    ///
+    /// `None` until the host, organization and bucket are set.
+    pub fn target(&self) -> Option<crate::influx_lines::InfluxBucket> {
+        debug!("Getting InfluxDB write target");
+        Some(crate::influx_lines::InfluxBucket {
+            host: self.host.clone()?,
+            org: self.org.clone()?,
+            bucket: self.target_bucket()?,
+            token: self.token.clone(),
+            precision: self.precision.clone().unwrap_or_else(|| crate::influx_lines::DEFAULT_PRECISION.to_string()),
+        })
+    }
+
+    /// Get all focus fields for InfluxDB settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for InfluxDB settings");
+        use super::FocusField;
+        &[
+            FocusField::InfluxHost,
+            FocusField::InfluxOrg,
+            FocusField::InfluxBucket,
+            FocusField::InfluxToken,
+            FocusField::InfluxPrecision,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for InfluxDB field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::InfluxHost => self.host.clone().unwrap_or_default(),
+            FocusField::InfluxOrg => self.org.clone().unwrap_or_default(),
+            FocusField::InfluxBucket => self.bucket.clone().unwrap_or_default(),
+            FocusField::InfluxToken => self.token.clone().unwrap_or_default(),
+            FocusField::InfluxPrecision => self.precision.clone().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    ///
+    /// An empty token or precision clears it.
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field value for InfluxDB field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::InfluxHost => self.host = Some(value),
+            FocusField::InfluxOrg => self.org = Some(value),
+            FocusField::InfluxBucket => self.bucket = Some(value),
+            FocusField::InfluxToken => self.token = (!value.is_empty()).then_some(value),
+            FocusField::InfluxPrecision => self.precision = (!value.is_empty()).then(|| value.to_lowercase()),
+            _ => debug!("Ignoring attempt to set unrelated field: {:?}", field),
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to InfluxDB config", field);
+        Self::focus_fields().contains(&field)
+    }
+}
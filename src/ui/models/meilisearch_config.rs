@@ -0,0 +1,69 @@
+use log::debug;
+
+/// Configuration for Meilisearch restore target
+#[derive(Clone, Debug, Default)]
+pub struct MeilisearchConfig {
+    pub host: Option<String>,
+    pub index: Option<String>,
+    pub api_key: Option<String>,
+    /// Primary key of a new index; detected from the documents when unset
+    pub primary_key: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
+}
+
+impl MeilisearchConfig {
+    /// The index a restore loads into: the configured index, renamed by the mapping
+    pub fn target_index(&self) -> Option<String> {
+        debug!("Getting target index for Meilisearch restore");
+        self.index.as_deref().map(|index| self.mapping.map_name(index))
+    }
+
+    /// Get all focus fields for Meilisearch settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for Meilisearch settings");
+        use super::FocusField;
+        &[
+            FocusField::MeiliHost,
+            FocusField::MeiliIndex,
+            FocusField::MeiliApiKey,
+            FocusField::MeiliPrimaryKey,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for Meilisearch field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::MeiliHost => self.host.clone().unwrap_or_default(),
+            FocusField::MeiliIndex => self.index.clone().unwrap_or_default(),
+            FocusField::MeiliApiKey => self.api_key.clone().unwrap_or_default(),
+            FocusField::MeiliPrimaryKey => self.primary_key.clone().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    ///
+    /// An empty API key or primary key clears it.
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field value for Meilisearch field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::MeiliHost => self.host = Some(value),
+            FocusField::MeiliIndex => self.index = Some(value),
+            FocusField::MeiliApiKey => self.api_key = (!value.is_empty()).then_some(value),
+            FocusField::MeiliPrimaryKey => self.primary_key = (!value.is_empty()).then_some(value),
+            _ => debug!("Ignoring attempt to set unrelated field: {:?}", field),
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to Meilisearch config", field);
+        use super::FocusField;
+        matches!(field, FocusField::MeiliHost | FocusField::MeiliIndex | FocusField::MeiliApiKey | FocusField::MeiliPrimaryKey)
+    }
+}
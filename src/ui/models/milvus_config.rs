@@ -0,0 +1,93 @@
+use log::debug;
+
+/// Configuration for Milvus restore target
+#[derive(Clone, Debug, Default)]
+pub struct MilvusConfig {
+    pub host: Option<String>,
+    pub collection: Option<String>,
+    /// API key or `user:password`
+    pub token: Option<String>,
+    /// Database holding the collection, the default database when unset
+    pub db_name: Option<String>,
+    /// Field holding each row's vector, `vector` when unset
+    pub vector_field: Option<String>,
+    /// Metric of the vector index of a new collection, `COSINE` when unset
+    pub metric: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
+}
+
+impl MilvusConfig {
+    /// The collection a restore inserts into: the configured collection, renamed by the mapping and made a valid collection name
+    pub fn target_collection(&self) -> Option<String> {
+        debug!("Getting target collection for Milvus restore");
+        self.collection.as_deref().map(|collection| crate::milvus_rows::collection_name(&self.mapping.map_name(collection)))
+    }
+
+    /// The field holding each row's vector
+    pub fn vector_field(&self) -> &str {
+        debug!("Getting Milvus vector field");
+        self.vector_field.as_deref().unwrap_or(crate::milvus_rows::DEFAULT_VECTOR_FIELD)
+    }
+
+    /// The metric a new collection's vector index uses
+    pub fn metric(&self) -> &str {
+        debug!("Getting Milvus index metric");
+        self.metric.as_deref().unwrap_or(crate::milvus_rows::DEFAULT_METRIC)
+    }
+
+    /// Get all focus fields for Milvus settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for Milvus settings");
+        use super::FocusField;
+        &[
+            FocusField::MilvusHost,
+            FocusField::MilvusCollection,
+            FocusField::MilvusToken,
+            FocusField::MilvusDatabase,
+            FocusField::MilvusVectorField,
+            FocusField::MilvusMetric,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for Milvus field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::MilvusHost => self.host.clone().unwrap_or_default(),
+            FocusField::MilvusCollection => self.collection.clone().unwrap_or_default(),
+            FocusField::MilvusToken => self.token.clone().unwrap_or_default(),
+            FocusField::MilvusDatabase => self.db_name.clone().unwrap_or_default(),
+            FocusField::MilvusVectorField => self.vector_field().to_string(),
+            FocusField::MilvusMetric => self.metric().to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    ///
+    /// An empty token or database clears it, and an empty vector field or
+    /// metric goes back to the default. Metrics are upper-cased, as Milvus
+    /// names them.
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field value for Milvus field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::MilvusHost => self.host = Some(value),
+            FocusField::MilvusCollection => self.collection = Some(value),
+            FocusField::MilvusToken => self.token = (!value.is_empty()).then_some(value),
+            FocusField::MilvusDatabase => self.db_name = (!value.is_empty()).then_some(value),
+            FocusField::MilvusVectorField => self.vector_field = (!value.is_empty()).then_some(value),
+            FocusField::MilvusMetric => self.metric = (!value.is_empty()).then(|| value.to_uppercase()),
+            _ => debug!("Ignoring attempt to set unrelated field: {:?}", field),
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to Milvus config", field);
+        Self::focus_fields().contains(&field)
+    }
+}
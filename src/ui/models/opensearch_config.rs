@@ -0,0 +1,98 @@
+use anyhow::Result;
+use log::debug;
+use crate::sigv4::SigV4Signer;
+
+/// Configuration for OpenSearch restore target
+#[derive(Clone, Debug)]
+pub struct OpenSearchConfig {
+    pub host: Option<String>,
+    pub index: Option<String>,
+    /// AWS region requests are signed for; unsigned when unset, as for self-managed clusters
+    pub region: Option<String>,
+    /// SigV4 service name: `es` for domains, `aoss` for serverless collections
+    pub service: String,
+    pub timeouts: super::TimeoutConfig,
+    pub bulk: super::BulkConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
+}
+
+impl Default for OpenSearchConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            index: None,
+            region: None,
+            service: crate::sigv4::SERVICE_DOMAIN.to_string(),
+            timeouts: Default::default(),
+            bulk: Default::default(),
+            mapping: Default::default(),
+        }
+    }
+}
+
+impl OpenSearchConfig {
+    /// The index a restore loads into: the configured index, renamed by the mapping
+    pub fn target_index(&self) -> Option<String> {
+        debug!("Getting target index for OpenSearch restore");
+        self.index.as_deref().map(|index| self.mapping.map_name(index))
+    }
+
+    /// Signer for the cluster's requests, if a region is set
+    ///
+    /// Credentials come from the default AWS credential chain.
+    pub async fn signer(&self) -> Result<Option<SigV4Signer>> {
+        debug!("Getting SigV4 signer for OpenSearch in {:?}", self.region);
+        match self.region.as_deref().filter(|region| !region.is_empty()) {
+            Some(region) => Ok(Some(SigV4Signer::from_default_chain(region, &self.service).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get all focus fields for OpenSearch settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for OpenSearch settings");
+        use super::FocusField;
+        &[
+            FocusField::OsHost,
+            FocusField::OsIndex,
+            FocusField::OsRegion,
+            FocusField::OsService,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for OpenSearch field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::OsHost => self.host.clone().unwrap_or_default(),
+            FocusField::OsIndex => self.index.clone().unwrap_or_default(),
+            FocusField::OsRegion => self.region.clone().unwrap_or_default(),
+            FocusField::OsService => self.service.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    ///
+    /// An empty region turns signing off.
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field value for OpenSearch field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::OsHost => self.host = Some(value),
+            FocusField::OsIndex => self.index = Some(value),
+            FocusField::OsRegion => self.region = (!value.is_empty()).then_some(value),
+            FocusField::OsService => self.service = value,
+            _ => debug!("Ignoring attempt to set unrelated field: {:?}", field),
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to OpenSearch config", field);
+        use super::FocusField;
+        matches!(field, FocusField::OsHost | FocusField::OsIndex | FocusField::OsRegion | FocusField::OsService)
+    }
+}
@@ -13,6 +13,10 @@ pub struct PostgresConfig {
     pub password: Option<String>,
     pub use_ssl: bool,
     pub db_name: Option<String>,
+    /// Database to restore snapshots into, a new `<word>-restored` one when unset
+    pub target_db: Option<String>,
+    /// Parallel pg_restore jobs for restores whose preset sets none
+    pub restore_jobs: Option<u32>,
 }
 
 impl PostgresConfig {
@@ -27,6 +31,8 @@ impl PostgresConfig {
             FocusField::PgPassword,
             FocusField::PgSsl,
             FocusField::PgDbName,
+            FocusField::PgTargetDb,
+            FocusField::PgRestoreJobs,
         ]
     }
 
@@ -41,6 +47,8 @@ impl PostgresConfig {
             FocusField::PgPassword => self.password.clone().unwrap_or_default(),
             FocusField::PgSsl => self.use_ssl.to_string(),
             FocusField::PgDbName => self.db_name.clone().unwrap_or_default(),
+            FocusField::PgTargetDb => self.target_db.clone().unwrap_or_default(),
+            FocusField::PgRestoreJobs => self.restore_jobs.map(|jobs| jobs.to_string()).unwrap_or_default(),
             _ => String::new(),
         }
     }
@@ -56,6 +64,10 @@ impl PostgresConfig {
             FocusField::PgPassword => self.password = Some(value),
             FocusField::PgSsl => self.use_ssl = matches!(value.as_str(), "true" | "1"),
             FocusField::PgDbName => self.db_name = Some(value),
+            // Clearing the field goes back to a random name
+            FocusField::PgTargetDb => self.target_db = Some(value).filter(|name| !name.is_empty()),
+            // Clearing the field, or zero, leaves the job count to the preset
+            FocusField::PgRestoreJobs => self.restore_jobs = value.trim().parse().ok().filter(|jobs| *jobs > 0),
             _ => {},
         }
     }
@@ -70,10 +82,67 @@ impl PostgresConfig {
             FocusField::PgUsername |
             FocusField::PgPassword | 
             FocusField::PgSsl |
-            FocusField::PgDbName
+            FocusField::PgDbName |
+            FocusField::PgTargetDb |
+            FocusField::PgRestoreJobs
         )
     }
+
+    /// Name of the database to restore a snapshot into
+    ///
+    /// The chosen target database when set, otherwise a new random
+    /// `<word>-restored` name.
+    pub fn restore_db_name(&self) -> Result<String> {
+        debug!("Getting database name to restore into");
+        let name = self.target_db.clone().unwrap_or_else(postgres::new_restored_db_name);
+        postgres::check_db_name(&name)?;
+        Ok(name)
+    }
     
+    /// The server as `host:port`, with PostgreSQL's defaults filled in
+    pub fn server(&self) -> String {
+        debug!("Describing PostgreSQL server");
+        format!("{}:{}", self.host.as_deref().unwrap_or("localhost"), self.port.unwrap_or(5432))
+    }
+
+    /// Connect to a database on this server
+    ///
+    /// # Arguments
+    ///
+    /// * `db_name` - Database to connect to, which may differ from `self.db_name`
+    pub async fn connect_to(&self, db_name: &str) -> Result<tokio_postgres::Client> {
+        debug!("Connecting to PostgreSQL database: {}", db_name);
+        let mut config = PgConfig::new();
+        config.host(self.host.as_deref().unwrap_or("localhost"));
+        config.port(self.port.unwrap_or(5432));
+        config.dbname(db_name);
+        if let Some(user) = &self.username {
+            config.user(user);
+        }
+        if let Some(password) = &self.password {
+            config.password(password);
+        }
+
+        if self.use_ssl {
+            postgres::connect_ssl(&config, false, None).await
+        } else {
+            postgres::connect_no_ssl(&config).await
+        }
+    }
+
+    /// Run a batch of SQL statements against a database on this server
+    ///
+    /// # Arguments
+    ///
+    /// * `db_name` - Database to connect to, which may differ from `self.db_name`
+    /// * `sql` - Statements to run, separated by semicolons
+    pub async fn execute_sql(&self, db_name: &str, sql: &str) -> Result<()> {
+        debug!("Executing SQL against PostgreSQL database: {}", db_name);
+        let client = self.connect_to(db_name).await?;
+        client.batch_execute(sql).await
+            .map_err(|e| anyhow!("SQL failed against {}: {}", db_name, e))
+    }
+
     /// Test PostgreSQL connection and return a client if successful
     pub async fn test_connection(&self, popup_state_setter: impl FnOnce(PopupState)) -> Result<Option<tokio_postgres::Client>> {
         // Validate PostgreSQL settings
@@ -100,7 +169,7 @@ impl PostgresConfig {
         config.host(self.host.as_ref().unwrap());
         config.port(self.port.unwrap());
         config.user(self.username.as_ref().unwrap());
-        config.password(&self.password.as_ref().unwrap_or(&String::new()));
+        config.password(self.password.as_ref().unwrap_or(&String::new()));
         
         let result = if self.use_ssl {
             postgres::connect_ssl(&config, false, None).await
@@ -6,9 +6,22 @@ pub struct QdrantConfig {
     pub host: Option<String>,
     pub collection: Option<String>,
     pub api_key: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    /// CA bundle, client certificate and verification for HTTPS hosts
+    pub tls: super::TlsConfig,
+    /// HTTP or gRPC upserts
+    pub upload: super::QdrantUploadConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
 }
 
 impl QdrantConfig {
+    /// The collection a restore loads into: the configured collection, renamed by the mapping
+    pub fn target_collection(&self) -> Option<String> {
+        debug!("Getting target collection for Qdrant restore");
+        self.collection.as_deref().map(|collection| self.mapping.map_name(collection))
+    }
+
     /// Get all focus fields for Qdrant settings
     pub fn focus_fields() -> &'static [super::FocusField] {
         debug!("Getting focus fields for Qdrant settings");
@@ -17,6 +30,8 @@ impl QdrantConfig {
             FocusField::EsHost,     // Reusing EsHost for Qdrant host
             FocusField::EsIndex,    // Reusing EsIndex for collection
             FocusField::QdrantApiKey,
+            FocusField::QdrantVectorSize,
+            FocusField::QdrantDistance,
         ]
     }
 
@@ -28,6 +43,8 @@ impl QdrantConfig {
             FocusField::EsHost => self.host.clone().unwrap_or_default(),
             FocusField::EsIndex => self.collection.clone().unwrap_or_default(),
             FocusField::QdrantApiKey => self.api_key.clone().unwrap_or_default(),
+            FocusField::QdrantVectorSize => self.upload.vector_size.map(|size| size.to_string()).unwrap_or_default(),
+            FocusField::QdrantDistance => self.upload.distance.clone().unwrap_or_default(),
             _ => String::new(),
         };
         // Mask sensitive information in logs
@@ -57,6 +74,15 @@ impl QdrantConfig {
                 debug!("Setting Qdrant API key to: [MASKED]");
                 self.api_key = Some(value);
             },
+            FocusField::QdrantVectorSize => {
+                debug!("Setting Qdrant vector size to: {}", value);
+                // Anything but a size, e.g. an empty field, falls back to the stored or sampled one
+                self.upload.vector_size = value.trim().parse().ok();
+            },
+            FocusField::QdrantDistance => {
+                debug!("Setting Qdrant distance to: {}", value);
+                self.upload.distance = (!value.trim().is_empty()).then(|| value.trim().to_string());
+            },
             _ => {
                 debug!("Ignoring attempt to set unrelated field: {:?}", field);
             },
@@ -70,7 +96,9 @@ impl QdrantConfig {
         let result = matches!(field, 
             FocusField::EsHost | 
             FocusField::EsIndex |
-            FocusField::QdrantApiKey
+            FocusField::QdrantApiKey |
+            FocusField::QdrantVectorSize |
+            FocusField::QdrantDistance
         );
         debug!("Field {:?} belongs to Qdrant config: {}", field, result);
         result
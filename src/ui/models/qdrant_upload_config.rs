@@ -0,0 +1,83 @@
+/// Transport and loading settings for Qdrant restores
+use log::debug;
+
+/// Default number of points upserted in one request
+pub const DEFAULT_POINT_BATCH_SIZE: usize = 256;
+
+/// Default number of upserts in flight at once
+pub const DEFAULT_POINT_CONCURRENCY: usize = 4;
+
+/// Distance functions Qdrant compares vectors with, in its own spelling
+pub const DISTANCES: &[&str] = &["Cosine", "Euclid", "Dot", "Manhattan"];
+
+/// Port of Qdrant's gRPC API when none is configured
+pub const DEFAULT_GRPC_PORT: u16 = 6334;
+
+/// API points are upserted through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum QdrantTransport {
+    /// The REST API, on the host's own port
+    #[default]
+    Http,
+    /// The gRPC API, on the same host at the gRPC port, falling back to HTTP when it cannot be reached
+    Grpc,
+}
+
+/// How a Qdrant restore sends its points, and sizes the collection it creates
+///
+/// The gRPC API takes the same upserts in protobuf over one HTTP/2
+/// connection, which loads large collections considerably faster than JSON
+/// over HTTP/1.1. Collections are still looked up and created over HTTP.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QdrantUploadConfig {
+    pub transport: QdrantTransport,
+    /// gRPC port, instead of 6334
+    pub grpc_port: Option<u16>,
+    /// Size of the vectors of a collection the restore creates, instead of the stored or sampled one
+    pub vector_size: Option<u64>,
+    /// Distance of the vectors of a collection the restore creates, instead of the stored one or Cosine
+    pub distance: Option<String>,
+    /// Points per upsert, instead of 256
+    pub batch_size: Option<usize>,
+    /// Upserts in flight at once, instead of 4
+    pub concurrency: Option<usize>,
+}
+
+impl QdrantUploadConfig {
+    /// URL of the gRPC API of the Qdrant at `host`
+    ///
+    /// The host's scheme and address are kept and its port replaced, so
+    /// `http://qdrant:6333` becomes `http://qdrant:6334`.
+    pub fn grpc_url(&self, host: &str) -> anyhow::Result<String> {
+        debug!("Building Qdrant gRPC URL of {}", host);
+        let mut url = reqwest::Url::parse(host).map_err(|e| anyhow::anyhow!("Invalid Qdrant host URL {}: {}", host, e))?;
+        url.set_port(Some(self.grpc_port.unwrap_or(DEFAULT_GRPC_PORT)))
+            .map_err(|_| anyhow::anyhow!("Qdrant host URL {} cannot have a port", host))?;
+        Ok(url.as_str().trim_end_matches('/').to_string())
+    }
+
+    /// Points per upsert, at least 1
+    pub fn batch_size(&self) -> usize {
+        debug!("Getting Qdrant batch size {:?}", self.batch_size);
+        self.batch_size.unwrap_or(DEFAULT_POINT_BATCH_SIZE).max(1)
+    }
+
+    /// Upserts in flight at once, at least 1
+    pub fn concurrency(&self) -> usize {
+        debug!("Getting Qdrant upsert concurrency {:?}", self.concurrency);
+        self.concurrency.unwrap_or(DEFAULT_POINT_CONCURRENCY).max(1)
+    }
+
+    /// The configured distance in Qdrant's spelling, matched case-insensitively
+    pub fn distance_name(&self) -> anyhow::Result<Option<&'static str>> {
+        debug!("Resolving Qdrant distance {:?}", self.distance);
+        let Some(distance) = self.distance.as_deref().map(str::trim).filter(|distance| !distance.is_empty()) else {
+            return Ok(None);
+        };
+        DISTANCES
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(distance) || (**name == "Euclid" && distance.eq_ignore_ascii_case("euclidean")))
+            .map(|name| Some(*name))
+            .ok_or_else(|| anyhow::anyhow!("Unknown Qdrant distance {}; use one of {}", distance, DISTANCES.join(", ")))
+    }
+}
@@ -13,8 +13,11 @@ pub struct S3Config {
     pub access_key_id: String,
     pub secret_access_key: String,
     pub path_style: bool,
+    /// Base64 encoded SSE-C customer key, empty when snapshots do not use one
+    pub sse_customer_key: String,
     pub error_message: Option<String>,
     pub test_s3_button: bool,
+    pub timeouts: super::TimeoutConfig,
 }
 
 impl Default for S3Config {
@@ -28,8 +31,10 @@ impl Default for S3Config {
             access_key_id: String::new(),
             secret_access_key: String::new(),
             path_style: false,
+            sse_customer_key: String::new(),
             error_message: None,
             test_s3_button: false,
+            timeouts: super::TimeoutConfig::default(),
         }
     }
 }
@@ -118,23 +123,56 @@ impl S3Config {
         Ok(())
     }
 
+    /// Whether explicit access keys are set, overriding the default AWS credential chain
+    pub fn has_static_credentials(&self) -> bool {
+        log::debug!("Checking for static S3 credentials");
+        !self.access_key_id.is_empty() && !self.secret_access_key.is_empty()
+    }
+
+    /// Describe where S3 credentials come from, for display
+    pub fn credentials_source(&self) -> &'static str {
+        log::debug!("Describing S3 credentials source");
+        if self.has_static_credentials() {
+            "access keys"
+        } else {
+            "default AWS credential chain"
+        }
+    }
+
+    /// Parse the SSE-C customer key, if one is set
+    pub fn customer_key(&self) -> Result<Option<crate::storage::SseCustomerKey>> {
+        log::debug!("Parsing S3 SSE-C customer key");
+        if self.sse_customer_key.is_empty() {
+            return Ok(None);
+        }
+        crate::storage::SseCustomerKey::from_base64(&self.sse_customer_key).map(Some)
+    }
+
     /// Initialize S3 client with current settings
-    pub fn create_client(&self) -> Result<S3Client> {
+    ///
+    /// Without explicit access keys, credentials come from the default AWS
+    /// credential chain: environment variables, `AWS_PROFILE` and shared
+    /// config files (including SSO sessions), web identity tokens, and the
+    /// ECS or EC2 instance role. Credentials are resolved on first use.
+    pub async fn create_client(&self) -> Result<S3Client> {
         log::debug!("Creating S3 client with endpoint: {}, region: {}", self.endpoint_url, self.region);
         self.verify_settings()?;
 
-        let mut config_builder = aws_sdk_s3::config::Builder::new()
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
             .region(aws_sdk_s3::config::Region::new(self.region.clone()));
 
-        // Only add credentials if they are provided
-        if !self.access_key_id.is_empty() && !self.secret_access_key.is_empty() {
+        // Explicit keys override the default credential chain
+        if self.has_static_credentials() {
             let credentials = Credentials::new(
                 &self.access_key_id,
                 &self.secret_access_key,
                 None, None, "rustored"
             );
-            config_builder = config_builder.credentials_provider(credentials);
+            loader = loader.credentials_provider(credentials);
         }
+        log::debug!("Using {} for S3 credentials", self.credentials_source());
+        let sdk_config = loader.load().await;
+        let mut config_builder = aws_sdk_s3::config::Builder::from(&sdk_config);
 
         if !self.endpoint_url.is_empty() {
             let endpoint_url = if !self.endpoint_url.starts_with("http") {
@@ -150,8 +188,8 @@ impl S3Config {
             config_builder = config_builder.force_path_style(true);
         }
 
-        // Add behavior version which is required by AWS SDK
-        config_builder = config_builder.behavior_version(aws_sdk_s3::config::BehaviorVersion::latest());
+        // Apply connect/read/operation timeouts so unreachable endpoints fail fast
+        config_builder = config_builder.timeout_config(self.timeouts.to_aws());
 
         let config = config_builder.build();
         log::debug!("S3 client configuration built successfully");
@@ -161,7 +199,7 @@ impl S3Config {
     /// Test S3 connection and return success or error
     pub async fn test_connection(&self, popup_state_setter: impl FnOnce(PopupState)) -> Result<()> {
         log::debug!("Testing S3 connection to bucket: {}", self.bucket);
-        let client = match self.create_client() {
+        let client = match self.create_client().await {
             Ok(client) => client,
             Err(e) => {
                 let error_msg = format!("Failed to initialize S3 client: {}", e);
@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use ssh2::Session;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+
+/// Configuration for an SFTP snapshot source
+///
+/// Authentication uses the private key when one is given, then the password,
+/// and finally any keys offered by a running ssh-agent.
+#[derive(Clone, Debug, Default)]
+pub struct SftpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: Option<String>,
+    pub private_key_path: Option<String>,
+    pub private_key_passphrase: Option<String>,
+    pub prefix: String,
+    pub timeouts: super::TimeoutConfig,
+}
+
+impl SftpConfig {
+    /// Verify SFTP settings are valid
+    pub fn verify_settings(&self) -> Result<()> {
+        debug!("Verifying SFTP settings for host: {}", self.host);
+        if self.host.is_empty() {
+            return Err(anyhow!("SFTP host is required"));
+        }
+        if self.username.is_empty() {
+            return Err(anyhow!("SFTP username is required"));
+        }
+        Ok(())
+    }
+
+    /// Open an authenticated SSH session with current settings
+    ///
+    /// This blocks while connecting, so call it from a blocking task.
+    pub fn connect(&self) -> Result<Session> {
+        debug!("Connecting to SFTP server {}:{} as {}", self.host, self.port, self.username);
+        self.verify_settings()?;
+
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve {}", self.host))?
+            .next()
+            .ok_or_else(|| anyhow!("No address found for {}", self.host))?;
+        let tcp = match self.timeouts.connect() {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        }
+        .with_context(|| format!("Failed to connect to {}:{}", self.host, self.port))?;
+        tcp.set_read_timeout(self.timeouts.read())?;
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        if let Some(timeout) = self.timeouts.read() {
+            session.set_timeout(timeout.as_millis() as u32);
+        }
+        session.handshake().context("SSH handshake failed")?;
+
+        if let Some(path) = self.private_key_path.as_ref().filter(|p| !p.is_empty()) {
+            session
+                .userauth_pubkey_file(&self.username, None, Path::new(path), self.private_key_passphrase.as_deref())
+                .with_context(|| format!("Key authentication with {} failed", path))?;
+        } else if let Some(password) = self.password.as_ref().filter(|p| !p.is_empty()) {
+            session
+                .userauth_password(&self.username, password)
+                .context("Password authentication failed")?;
+        } else {
+            session.userauth_agent(&self.username).context("ssh-agent authentication failed")?;
+        }
+
+        if !session.authenticated() {
+            return Err(anyhow!("SFTP authentication failed for {}", self.username));
+        }
+        Ok(session)
+    }
+}
@@ -0,0 +1,127 @@
+/// Timeout settings shared by the S3, Elasticsearch and Qdrant clients
+use log::debug;
+use std::time::Duration;
+
+/// Default time allowed to establish a connection
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Default time allowed between reads on an open connection
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+
+/// Connect, read and whole-operation timeouts in seconds
+///
+/// `None` disables the corresponding timeout. Without a connect timeout a
+/// black-holed endpoint would hang connection tests indefinitely, so one is
+/// set by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeoutConfig {
+    pub connect_secs: Option<u64>,
+    pub read_secs: Option<u64>,
+    pub operation_secs: Option<u64>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        debug!("Creating default TimeoutConfig instance");
+        Self {
+            connect_secs: Some(DEFAULT_CONNECT_TIMEOUT_SECS),
+            read_secs: Some(DEFAULT_READ_TIMEOUT_SECS),
+            operation_secs: None,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    /// Build a timeout config, falling back to the defaults for unset values
+    pub fn new(connect_secs: Option<u64>, read_secs: Option<u64>, operation_secs: Option<u64>) -> Self {
+        debug!("Creating TimeoutConfig: connect={:?}, read={:?}, operation={:?}", connect_secs, read_secs, operation_secs);
+        let defaults = Self::default();
+        Self {
+            connect_secs: connect_secs.or(defaults.connect_secs),
+            read_secs: read_secs.or(defaults.read_secs),
+            operation_secs: operation_secs.or(defaults.operation_secs),
+        }
+    }
+
+    /// Load timeouts from `<PREFIX>_CONNECT_TIMEOUT`, `<PREFIX>_READ_TIMEOUT`
+    /// and `<PREFIX>_OPERATION_TIMEOUT` environment variables
+    pub fn from_env(prefix: &str) -> Self {
+        debug!("Loading timeouts from environment with prefix {}", prefix);
+        let read = |name: &str| {
+            std::env::var(format!("{}_{}", prefix, name)).ok().and_then(|v| v.trim().parse().ok())
+        };
+        Self::new(read("CONNECT_TIMEOUT"), read("READ_TIMEOUT"), read("OPERATION_TIMEOUT"))
+    }
+
+    /// Connect timeout as a Duration
+    pub fn connect(&self) -> Option<Duration> {
+        debug!("Getting connect timeout");
+        self.connect_secs.map(Duration::from_secs)
+    }
+
+    /// Read timeout as a Duration
+    pub fn read(&self) -> Option<Duration> {
+        debug!("Getting read timeout");
+        self.read_secs.map(Duration::from_secs)
+    }
+
+    /// Whole-operation timeout as a Duration
+    pub fn operation(&self) -> Option<Duration> {
+        debug!("Getting operation timeout");
+        self.operation_secs.map(Duration::from_secs)
+    }
+
+    /// Build the AWS SDK timeout configuration
+    pub fn to_aws(&self) -> aws_sdk_s3::config::timeout::TimeoutConfig {
+        debug!("Building AWS SDK timeout config");
+        let mut builder = aws_sdk_s3::config::timeout::TimeoutConfig::builder();
+        if let Some(connect) = self.connect() {
+            builder = builder.connect_timeout(connect);
+        }
+        if let Some(read) = self.read() {
+            builder = builder.read_timeout(read);
+        }
+        if let Some(operation) = self.operation() {
+            builder = builder.operation_timeout(operation);
+        }
+        builder.build()
+    }
+
+    /// Build object_store client options for the GCS client
+    ///
+    /// object_store has no separate read timeout, so the operation timeout is
+    /// used when set and the read timeout otherwise.
+    pub fn to_object_store(&self) -> object_store::ClientOptions {
+        debug!("Building object_store client options");
+        let mut options = object_store::ClientOptions::new();
+        if let Some(connect) = self.connect() {
+            options = options.with_connect_timeout(connect);
+        }
+        if let Some(timeout) = self.operation().or(self.read()) {
+            options = options.with_timeout(timeout);
+        }
+        options
+    }
+
+    /// Build an HTTP client for Elasticsearch and Qdrant using these timeouts
+    pub fn http_client(&self) -> anyhow::Result<reqwest::Client> {
+        debug!("Building HTTP client with default TLS settings");
+        self.tls_client(&super::TlsConfig::default())
+    }
+
+    /// Build an HTTP client using these timeouts and the given TLS settings
+    pub fn tls_client(&self, tls: &super::TlsConfig) -> anyhow::Result<reqwest::Client> {
+        debug!("Building HTTP client with timeouts: {:?}", self);
+        let mut builder = tls.apply(reqwest::Client::builder())?;
+        if let Some(connect) = self.connect() {
+            builder = builder.connect_timeout(connect);
+        }
+        if let Some(read) = self.read() {
+            builder = builder.read_timeout(read);
+        }
+        if let Some(operation) = self.operation() {
+            builder = builder.timeout(operation);
+        }
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,57 @@
+/// TLS settings for HTTPS connections to Elasticsearch and Qdrant
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+
+/// Custom CA bundle, client certificate and verification of a target's certificate
+///
+/// These play the part `--root-cert-path` and `--verify-ssl` play for
+/// Postgres. With no settings the system trust store is used and the server
+/// certificate is verified.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsConfig {
+    /// PEM file of CA certificates trusted besides the system ones
+    pub ca_cert_path: Option<String>,
+    /// PEM file of the client certificate presented to the server
+    pub client_cert_path: Option<String>,
+    /// PEM file of the client certificate's PKCS#8 private key
+    pub client_key_path: Option<String>,
+    /// Accept any server certificate, e.g. a self-signed one
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsConfig {
+    /// Apply these settings to an HTTP client builder
+    ///
+    /// Fails when a certificate or key file cannot be read or parsed, so a
+    /// mistyped path is reported before any request is sent.
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder> {
+        debug!("Applying TLS settings: {:?}", self);
+        if let Some(path) = &self.ca_cert_path {
+            let pem = std::fs::read(path).with_context(|| format!("Failed to read CA certificates {}", path))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| anyhow!("Invalid CA certificates in {}: {}", path, e))?;
+            if certificates.is_empty() {
+                return Err(anyhow!("No CA certificates found in {}", path));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        match (&self.client_cert_path, &self.client_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                let cert = std::fs::read(cert_path).with_context(|| format!("Failed to read client certificate {}", cert_path))?;
+                let key = std::fs::read(key_path).with_context(|| format!("Failed to read client key {}", key_path))?;
+                let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key)
+                    .map_err(|e| anyhow!("Invalid client certificate {} or key {}: {}", cert_path, key_path, e))?;
+                builder = builder.identity(identity);
+            }
+            (Some(cert_path), None) => return Err(anyhow!("Client certificate {} given without a client key", cert_path)),
+            (None, Some(key_path)) => return Err(anyhow!("Client key {} given without a client certificate", key_path)),
+            (None, None) => {}
+        }
+        if self.insecure_skip_verify {
+            warn!("Server certificates are not verified");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+}
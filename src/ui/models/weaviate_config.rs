@@ -0,0 +1,83 @@
+use log::debug;
+
+/// Configuration for Weaviate restore target
+#[derive(Clone, Debug, Default)]
+pub struct WeaviateConfig {
+    pub host: Option<String>,
+    pub class: Option<String>,
+    pub api_key: Option<String>,
+    /// OIDC client secret, used for the client credentials grant without an API key
+    pub oidc_client_secret: Option<String>,
+    /// Scopes requested with the OIDC token
+    pub oidc_scope: Option<String>,
+    pub timeouts: super::TimeoutConfig,
+    /// Renames applied while restoring, from `--mapping-file`
+    pub mapping: crate::mapping::RestoreMapping,
+}
+
+impl WeaviateConfig {
+    /// The class a restore imports into: the configured class, renamed by the mapping and made a valid class name
+    pub fn target_class(&self) -> Option<String> {
+        debug!("Getting target class for Weaviate restore");
+        self.class.as_deref().map(|class| crate::weaviate_objects::class_name(&self.mapping.map_name(class)))
+    }
+
+    /// The API key or OIDC client credentials requests authenticate with
+    pub fn credentials(&self) -> crate::weaviate_objects::WeaviateCredentials {
+        debug!("Getting Weaviate credentials");
+        crate::weaviate_objects::WeaviateCredentials {
+            api_key: self.api_key.clone(),
+            oidc_client_secret: self.oidc_client_secret.clone(),
+            oidc_scope: self.oidc_scope.clone(),
+        }
+    }
+
+    /// Get all focus fields for Weaviate settings
+    pub fn focus_fields() -> &'static [super::FocusField] {
+        debug!("Getting focus fields for Weaviate settings");
+        use super::FocusField;
+        &[
+            FocusField::WeaviateHost,
+            FocusField::WeaviateClass,
+            FocusField::WeaviateApiKey,
+            FocusField::WeaviateOidcSecret,
+            FocusField::WeaviateOidcScope,
+        ]
+    }
+
+    /// Get the field value for a given focus field
+    pub fn get_field_value(&self, field: super::FocusField) -> String {
+        debug!("Getting field value for Weaviate field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::WeaviateHost => self.host.clone().unwrap_or_default(),
+            FocusField::WeaviateClass => self.class.clone().unwrap_or_default(),
+            FocusField::WeaviateApiKey => self.api_key.clone().unwrap_or_default(),
+            FocusField::WeaviateOidcSecret => self.oidc_client_secret.clone().unwrap_or_default(),
+            FocusField::WeaviateOidcScope => self.oidc_scope.clone().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Set a field value from a string
+    ///
+    /// An empty API key, OIDC secret or scope clears it.
+    pub fn set_field_value(&mut self, field: super::FocusField, value: String) {
+        debug!("Setting field value for Weaviate field: {:?}", field);
+        use super::FocusField;
+        match field {
+            FocusField::WeaviateHost => self.host = Some(value),
+            FocusField::WeaviateClass => self.class = Some(value),
+            FocusField::WeaviateApiKey => self.api_key = (!value.is_empty()).then_some(value),
+            FocusField::WeaviateOidcSecret => self.oidc_client_secret = (!value.is_empty()).then_some(value),
+            FocusField::WeaviateOidcScope => self.oidc_scope = (!value.is_empty()).then_some(value),
+            _ => debug!("Ignoring attempt to set unrelated field: {:?}", field),
+        }
+    }
+
+    /// Check if a focus field belongs to this config
+    pub fn contains_field(field: super::FocusField) -> bool {
+        debug!("Checking if field {:?} belongs to Weaviate config", field);
+        Self::focus_fields().contains(&field)
+    }
+}
@@ -0,0 +1,70 @@
+// This module contains point-in-time recovery in the Rustored TUI
+// Enter on a base backup asks for the time to recover to instead of starting
+// a restore, showing the window its archived WAL covers. The recovery runs on
+// a scratch instance (see `crate::pitr`) and Esc aborts it, stopping the
+// instance if it was already replaying.
+
+use crate::pitr::{self, RecoveryWindow, DEFAULT_SCRATCH_PORT};
+use crate::ui::models::{BackupMetadata, PopupState};
+use crate::ui::rustored::RustoredApp;
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+
+/// Ask for the time to recover a base backup to, starting at the latest one
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The base backup selected in the snapshot list
+pub fn open(app: &mut RustoredApp, snapshot: BackupMetadata) {
+    debug!("Opening recovery target prompt for {}", snapshot.key);
+    let window = app.snapshot_browser.wal_chains.get(&snapshot.key).map(RecoveryWindow::of);
+    app.popup_state = match window {
+        Some(Ok(window)) => PopupState::RecoverTarget(snapshot, window.to_string(), pitr::format_time(window.latest)),
+        Some(Err(e)) => PopupState::Error(format!("Cannot recover {}: {}", snapshot.key, e)),
+        None => PopupState::Error(format!("{} has no archived WAL to recover with", snapshot.key)),
+    };
+}
+
+/// Recover a base backup to the typed time on a scratch instance
+async fn recover(app: &mut RustoredApp, snapshot: &BackupMetadata, typed: &str) -> Result<String> {
+    debug!("Recovering {} to {} from the TUI", snapshot.key, typed);
+    let target = pitr::parse_target_time(typed)?;
+    let chain = app.snapshot_browser.wal_chains.get(&snapshot.key).cloned()
+        .ok_or_else(|| anyhow!("{} has no archived WAL to recover with", snapshot.key))?;
+    RecoveryWindow::of(&chain)?.check(target)?;
+    let data_dir = pitr::default_data_dir(target);
+    let operation = app.begin_operation();
+    let recovery = crate::cancel::cancellable(&operation, "Recovery", pitr::recover(&mut app.snapshot_browser, &chain, target, &data_dir, DEFAULT_SCRATCH_PORT));
+    Ok(crate::ui::app::cancel_on_escape(&operation, recovery).await?.summary())
+}
+
+/// Handle a key in the recovery target prompt
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) {
+    debug!("Handling recovery target key: {:?}", key);
+    let PopupState::RecoverTarget(snapshot, _, typed) = &mut app.popup_state else {
+        return;
+    };
+    match key.code {
+        KeyCode::Enter => {
+            let (snapshot, typed) = (snapshot.clone(), typed.clone());
+            app.popup_state = match recover(app, &snapshot, &typed).await {
+                Ok(summary) => PopupState::Success(summary),
+                Err(e) if crate::cancel::is_cancelled(&e) => PopupState::Error(format!("Recovery of {} aborted, the scratch instance was stopped", snapshot.key)),
+                Err(e) => PopupState::Error(format!("Recovery of {} failed: {}", snapshot.key, e)),
+            };
+        }
+        KeyCode::Backspace => {
+            typed.pop();
+        }
+        KeyCode::Char(c) => typed.push(c),
+        KeyCode::Esc => app.popup_state = PopupState::Hidden,
+        _ => {}
+    }
+}
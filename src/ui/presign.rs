@@ -0,0 +1,107 @@
+// This module contains the download links of the Rustored TUI
+// `u` on a snapshot asks how long a link should stay valid, then makes a
+// presigned GET URL for it and copies it to the clipboard, so a snapshot can be
+// handed to someone without sharing bucket credentials.
+
+use crate::ui::models::{BackupMetadata, PopupState};
+use crate::ui::rustored::RustoredApp;
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use log::{debug, info};
+use std::time::Duration;
+
+/// How long a link stays valid unless another duration is typed
+pub const DEFAULT_LINK_DURATION: &str = "24h";
+
+/// Longest a link can stay valid; S3 refuses longer presigned URLs
+pub const MAX_LINK_DURATION: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Parse a link duration such as `30m`, `12h` or `7d`; a bare number is hours
+///
+/// # Arguments
+///
+/// * `value` - The duration as typed
+///
+/// # Returns
+///
+/// The duration, between a minute and `MAX_LINK_DURATION`
+pub fn parse_link_duration(value: &str) -> Result<Duration> {
+    debug!("Parsing link duration: {}", value);
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "h"),
+    };
+    let seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        _ => return Err(anyhow!("Unknown unit in {:?}, use m, h or d, e.g. 12h", value)),
+    };
+    let number: u64 = number.parse().map_err(|_| anyhow!("{:?} is not a duration, e.g. 30m, 12h or 7d", value))?;
+    let duration = Duration::from_secs(number * seconds);
+    if duration < Duration::from_secs(60) || duration > MAX_LINK_DURATION {
+        return Err(anyhow!("A link must stay valid for between a minute and 7 days, not {}", value));
+    }
+    Ok(duration)
+}
+
+/// Ask how long a link to the selected snapshot should stay valid
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+pub fn open(app: &mut RustoredApp) {
+    debug!("Opening link duration prompt");
+    if let Some(snapshot) = app.snapshot_browser.snapshots.get(app.snapshot_browser.selected_index).cloned() {
+        app.popup_state = PopupState::PresignDuration(snapshot, DEFAULT_LINK_DURATION.to_string());
+    }
+}
+
+/// Handle a key in the link duration prompt or the link it made
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) {
+    debug!("Handling download link key: {:?}", key);
+    let PopupState::PresignDuration(snapshot, typed) = &mut app.popup_state else {
+        // The finished link closes like the other messages
+        if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+            app.popup_state = PopupState::Hidden;
+        }
+        return;
+    };
+    match key.code {
+        KeyCode::Enter => {
+            let (snapshot, typed) = (snapshot.clone(), typed.clone());
+            app.popup_state = match create_link(app, &snapshot, &typed).await {
+                Ok(popup) => popup,
+                Err(e) => PopupState::Error(format!("Could not make a link to {}: {}", snapshot.key, e)),
+            };
+        }
+        KeyCode::Backspace => {
+            typed.pop();
+        }
+        KeyCode::Char(c) => typed.push(c),
+        KeyCode::Esc => app.popup_state = PopupState::Hidden,
+        _ => {}
+    }
+}
+
+/// Presign a download of `snapshot` and copy the link to the clipboard
+async fn create_link(app: &mut RustoredApp, snapshot: &BackupMetadata, typed: &str) -> Result<PopupState> {
+    debug!("Creating a {} link to {}", typed, snapshot.key);
+    let expires_in = parse_link_duration(typed)?;
+    let url = app.snapshot_browser.store().await?.presigned_url(snapshot, expires_in).await?;
+    let expires_at = chrono::Utc::now() + chrono::Duration::from_std(expires_in)?;
+    let expires_at = expires_at.format("%Y-%m-%d %H:%M UTC").to_string();
+    // The link itself is a credential, so it is never logged
+    info!("Made a download link to {} valid until {}", snapshot.key, expires_at);
+    let copied = crate::ui::command::copy_to_clipboard(&url).map_err(|e| e.to_string());
+    if let Err(e) = &copied {
+        debug!("Failed to copy link to clipboard: {}", e);
+    }
+    Ok(PopupState::PresignedLink(snapshot.key.clone(), url, expires_at, copied))
+}
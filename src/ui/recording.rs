@@ -0,0 +1,363 @@
+// This module contains TUI session recording and replay for the Rustored application
+// `--record-session <file>` writes each key handled by the TUI, and the state
+// of the UI after it, as JSON lines. Characters typed into credential fields
+// are redacted, and the state is only the focus, input mode, popup, restore
+// target and selection, never a settings value. `rustored replay <file>`
+// feeds the keys back into a demo-mode TUI at the recorded pace and reports
+// every key after which the state differs, so a bug report can carry a
+// recording that reproduces it.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::ui::models::{InputMode, PopupState};
+use crate::ui::rustored::RustoredApp;
+
+/// Version of the recording format, written in the first line of every recording
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// Recorded in place of a character typed into a credential field
+pub const REDACTED_KEY: &str = "<redacted>";
+
+/// Typed in place of a redacted character when a recording is replayed
+const REDACTED_REPLAY: char = '*';
+
+/// One line of a session recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// First line, describing the recording
+    Start {
+        version: u32,
+        rustored: String,
+        /// When recording started, which dates the demo snapshots on replay
+        recorded_at: DateTime<Utc>,
+        /// Whether the session was recorded in demo mode
+        demo: bool,
+    },
+    /// A key handled by the TUI, `at_ms` after recording started
+    Key { at_ms: u64, key: String },
+    /// State of the UI after the key before it was handled
+    State { at_ms: u64, state: SessionState },
+}
+
+/// The parts of the UI state a replay is checked against
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub focus: String,
+    pub input_mode: String,
+    /// Name of the popup shown, without its contents
+    pub popup: String,
+    pub target: String,
+    /// Index of the selected snapshot
+    pub selected: usize,
+}
+
+impl SessionState {
+    /// Capture the state of an app
+    pub fn of(app: &RustoredApp) -> Self {
+        debug!("Capturing session state");
+        Self {
+            focus: format!("{:?}", app.focus),
+            input_mode: format!("{:?}", app.input_mode),
+            popup: popup_name(&app.popup_state),
+            target: format!("{:?}", app.restore_target),
+            selected: app.snapshot_browser.selected_index,
+        }
+    }
+
+    /// Describe how `actual` differs from this expected state, e.g. `popup ConfirmRestore -> Error`
+    pub fn differences(&self, actual: &SessionState) -> Vec<String> {
+        debug!("Comparing session states");
+        let fields = [
+            ("focus", &self.focus, &actual.focus),
+            ("input mode", &self.input_mode, &actual.input_mode),
+            ("popup", &self.popup, &actual.popup),
+            ("target", &self.target, &actual.target),
+        ];
+        let mut differences: Vec<String> = fields
+            .iter()
+            .filter(|(_, expected, actual)| expected != actual)
+            .map(|(name, expected, actual)| format!("{} {} -> {}", name, expected, actual))
+            .collect();
+        if self.selected != actual.selected {
+            differences.push(format!("selected {} -> {}", self.selected, actual.selected));
+        }
+        differences
+    }
+}
+
+/// Name of a popup without the snapshot, message or input it holds
+fn popup_name(popup: &PopupState) -> String {
+    let debug = format!("{:?}", popup);
+    debug.split(['(', ' ', '{']).next().unwrap_or_default().to_string()
+}
+
+/// Encode a key as text, e.g. `j`, `Enter`, `Ctrl+z` or `Alt+Up`
+pub fn encode_key(key: &KeyEvent) -> String {
+    debug!("Encoding key {:?}", key);
+    let mut text = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        text.push_str("Ctrl+");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        text.push_str("Alt+");
+    }
+    // A character already says whether Shift was held
+    if key.modifiers.contains(KeyModifiers::SHIFT) && !matches!(key.code, KeyCode::Char(_)) {
+        text.push_str("Shift+");
+    }
+    match key.code {
+        KeyCode::Char(c) => text.push(c),
+        KeyCode::F(n) => text.push_str(&format!("F{}", n)),
+        code => text.push_str(&format!("{:?}", code)),
+    }
+    text
+}
+
+/// Decode a key written by `encode_key`
+pub fn decode_key(text: &str) -> Result<KeyEvent> {
+    debug!("Decoding key {}", text);
+    if text == REDACTED_KEY {
+        return Ok(KeyEvent::new(KeyCode::Char(REDACTED_REPLAY), KeyModifiers::NONE));
+    }
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text;
+    loop {
+        if let Some(after) = rest.strip_prefix("Ctrl+").filter(|after| !after.is_empty()) {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("Alt+").filter(|after| !after.is_empty()) {
+            modifiers |= KeyModifiers::ALT;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("Shift+").filter(|after| !after.is_empty()) {
+            modifiers |= KeyModifiers::SHIFT;
+            rest = after;
+        } else {
+            break;
+        }
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest {
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Tab" => KeyCode::Tab,
+            "BackTab" => KeyCode::BackTab,
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Insert" => KeyCode::Insert,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            other => match other.strip_prefix('F').and_then(|n| n.parse().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => return Err(anyhow!("Unknown key '{}' in recording", text)),
+            },
+        },
+    };
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+/// Writes a session recording as the TUI handles keys
+pub struct SessionRecorder {
+    file: Mutex<std::fs::File>,
+    started: Instant,
+}
+
+impl SessionRecorder {
+    /// Start a recording in a new file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to write, replaced if it exists
+    /// * `demo` - Whether the session runs in demo mode
+    /// * `now` - Time recording starts
+    pub fn create(path: &Path, demo: bool, now: DateTime<Utc>) -> Result<Self> {
+        debug!("Recording TUI session to {:?}", path);
+        let file = std::fs::File::create(path).with_context(|| format!("Failed to create session recording {}", path.display()))?;
+        let recorder = Self { file: Mutex::new(file), started: Instant::now() };
+        recorder.write(&SessionEvent::Start {
+            version: SESSION_FORMAT_VERSION,
+            rustored: env!("CARGO_PKG_VERSION").to_string(),
+            recorded_at: now,
+            demo,
+        });
+        info!("Recording TUI session to {}", path.display());
+        Ok(recorder)
+    }
+
+    /// Record a key about to be handled, redacted if it types into a credential field
+    pub fn record_key(&self, key: &KeyEvent, app: &RustoredApp) {
+        debug!("Recording key {:?}", key);
+        let secret = app.input_mode == InputMode::Editing
+            && app.popup_state == PopupState::Hidden
+            && app.focus.is_secret()
+            && matches!(key.code, KeyCode::Char(_));
+        let key = match secret {
+            true => REDACTED_KEY.to_string(),
+            false => encode_key(key),
+        };
+        self.write(&SessionEvent::Key { at_ms: self.elapsed_ms(), key });
+    }
+
+    /// Record the state of the UI after a key was handled
+    pub fn record_state(&self, app: &RustoredApp) {
+        debug!("Recording session state");
+        self.write(&SessionEvent::State { at_ms: self.elapsed_ms(), state: SessionState::of(app) });
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Append one event, flushed so a crash still leaves the recording readable
+    ///
+    /// A failed write is logged rather than ending the session being recorded.
+    fn write(&self, event: &SessionEvent) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let written = serde_json::to_string(event)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| writeln!(file, "{}", line).and_then(|_| file.flush()).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            warn!("Failed to write session recording: {}", e);
+        }
+    }
+}
+
+/// A recording being replayed into the TUI
+pub struct SessionReplay {
+    /// When recording started, which the demo snapshots are dated back from
+    pub recorded_at: DateTime<Utc>,
+    /// Keys still to replay, with when they were pressed and the state recorded after them
+    keys: std::collections::VecDeque<(u64, String, Option<SessionState>)>,
+    /// State expected after the key being handled
+    expected: Option<(usize, String, SessionState)>,
+    /// Number of keys replayed so far
+    pub replayed: usize,
+    /// Keys after which the state differed from the recording, described
+    pub divergences: Vec<String>,
+    /// Multiplier of the recorded pace, 2.0 replays twice as fast
+    speed: f64,
+    started: Instant,
+}
+
+impl SessionReplay {
+    /// Parse a recording
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The recording, one JSON event per line
+    /// * `speed` - Multiplier of the recorded pace; 0 replays without pauses
+    pub fn parse(reader: impl BufRead, speed: f64) -> Result<Self> {
+        debug!("Parsing session recording at {}x speed", speed);
+        let mut recorded_at = None;
+        let mut keys = std::collections::VecDeque::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: SessionEvent = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid event on line {} of the recording", number + 1))?;
+            match event {
+                SessionEvent::Start { version, recorded_at: at, .. } => {
+                    if version > SESSION_FORMAT_VERSION {
+                        return Err(anyhow!("Recording format {} is newer than this rustored understands ({})", version, SESSION_FORMAT_VERSION));
+                    }
+                    recorded_at = Some(at);
+                }
+                SessionEvent::Key { at_ms, key } => {
+                    decode_key(&key).with_context(|| format!("Invalid key on line {} of the recording", number + 1))?;
+                    keys.push_back((at_ms, key, None));
+                }
+                SessionEvent::State { state, .. } => {
+                    if let Some(last) = keys.back_mut() {
+                        last.2 = Some(state);
+                    }
+                }
+            }
+        }
+        let recorded_at = recorded_at.ok_or_else(|| anyhow!("The recording has no start event"))?;
+        Ok(Self { recorded_at, keys, expected: None, replayed: 0, divergences: Vec::new(), speed, started: Instant::now() })
+    }
+
+    /// Open and parse a recording file
+    pub fn load(path: &Path, speed: f64) -> Result<Self> {
+        debug!("Loading session recording {:?}", path);
+        let file = std::fs::File::open(path).with_context(|| format!("Failed to open session recording {}", path.display()))?;
+        Self::parse(std::io::BufReader::new(file), speed)
+    }
+
+    /// Whether every key has been replayed
+    pub fn finished(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Wait until the next key is due at the replay speed and return it
+    pub async fn next_key(&mut self) -> Option<KeyEvent> {
+        debug!("Replaying next key, {} left", self.keys.len());
+        let (at_ms, text, state) = self.keys.pop_front()?;
+        if self.speed > 0.0 {
+            let due = Duration::from_secs_f64(at_ms as f64 / 1000.0 / self.speed);
+            if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        self.replayed += 1;
+        let key = decode_key(&text).ok()?;
+        self.expected = state.map(|state| (self.replayed, text, state));
+        Some(key)
+    }
+
+    /// Compare the state after the replayed key with the recorded one
+    pub fn check(&mut self, actual: &SessionState) {
+        debug!("Checking replayed session state");
+        let Some((number, key, expected)) = self.expected.take() else {
+            return;
+        };
+        let differences = expected.differences(actual);
+        if !differences.is_empty() {
+            self.divergences.push(format!("key {} ({}): {}", number, key, differences.join(", ")));
+        }
+    }
+
+    /// Outcome of the replay, e.g. `Replayed 42 keys, state matched the recording`
+    pub fn summary(&self) -> String {
+        debug!("Summarising replay");
+        match self.divergences.len() {
+            0 => format!("Replayed {} keys, state matched the recording", self.replayed),
+            n => format!("Replayed {} keys, state differed from the recording after {}", self.replayed, n),
+        }
+    }
+}
+
+/// Record a key about to be handled, when the session is recorded
+pub fn before_key(app: &RustoredApp, key: &KeyEvent) {
+    if let Some(recorder) = &app.recorder {
+        recorder.record_key(key, app);
+    }
+}
+
+/// Record the state after a key, or check it against the replayed recording
+pub fn after_key(app: &mut RustoredApp) {
+    let state = SessionState::of(app);
+    if let Some(recorder) = &app.recorder {
+        recorder.record_state(app);
+    }
+    if let Some(replay) = app.replay.as_mut() {
+        replay.check(&state);
+    }
+}
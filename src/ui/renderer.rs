@@ -10,7 +10,7 @@ use ratatui::{
 use log::debug;
 use crate::ui::models::{RestoreTarget, PopupState};
 use crate::ui::rustored::RustoredApp;
-use crate::ui::components::{popups, postgres_settings, elasticsearch_settings, qdrant_settings, s3_settings, snapshot_list, restore_target};
+use crate::ui::components::{popups, postgres_settings, elasticsearch_settings, qdrant_settings, opensearch_settings, meilisearch_settings, weaviate_settings, milvus_settings, dynamodb_settings, influxdb_settings, s3_settings, azure_settings, snapshot_list, restore_target, jobs_panel};
 
 /// Helper function to create a centered rect using up certain percentage of the available rect
 /// 
@@ -60,6 +60,21 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     centered
 }
 
+/// Render the banner naming the environment the settings point at
+///
+/// Production is white on red, any other environment black on cyan.
+fn render_environment_banner(f: &mut Frame, environment: &str, region: &str, area: Rect) {
+    debug!("Rendering environment banner for {}", environment);
+    let style = match crate::profiles::is_production(environment) {
+        true => Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+        false => Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+    };
+    let banner = Paragraph::new(crate::profiles::banner(environment, region))
+        .style(style)
+        .alignment(Alignment::Center);
+    f.render_widget(banner, area);
+}
+
 /// Render the UI
 /// 
 /// This function is the main entry point for rendering the entire UI.
@@ -68,13 +83,27 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 /// # Arguments
 /// 
 /// * `f` - A mutable reference to the frame for rendering
-/// * `app` - A mutable reference to the application state
-pub fn ui<B: Backend>(f: &mut Frame, app: &mut RustoredApp) {
+/// * `app` - A reference to the application state
+pub fn ui<B: Backend>(f: &mut Frame, app: &RustoredApp) {
     debug!("Starting UI rendering cycle");
     debug!("Current focus: {:?}, input mode: {:?}", app.focus, app.input_mode);
     // Log the start of UI rendering
     debug!("Starting UI rendering");
+
+    // Nothing of this frame has been written yet, so the title cannot split its output
+    if let Some(status) = &app.terminal_status {
+        status.update(&app.progress.jobs());
+    }
     
+    // Keep a row above the title for the environment banner when one is set
+    let mut area = f.size();
+    if let Some(environment) = &app.environment {
+        let banner_area = Rect { height: 1.min(area.height), ..area };
+        render_environment_banner(f, environment, &app.s3_config.region, banner_area);
+        area.y += banner_area.height;
+        area.height -= banner_area.height;
+    }
+
     // Create the layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -83,7 +112,7 @@ pub fn ui<B: Backend>(f: &mut Frame, app: &mut RustoredApp) {
             Constraint::Min(0),     // Main content
             Constraint::Length(1),  // Status bar
         ])
-        .split(f.size());
+        .split(area);
 
     // Render title
     let title = Paragraph::new(Line::from(vec![
@@ -99,7 +128,7 @@ pub fn ui<B: Backend>(f: &mut Frame, app: &mut RustoredApp) {
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(10), // Top row (S3 settings, restore target selection, specific settings)
+            Constraint::Length(13), // Top row (S3 settings, restore target selection, specific settings)
             Constraint::Min(0),     // Bottom row (snapshot list) - takes remaining space
         ])
         .split(chunks[1]);
@@ -114,9 +143,13 @@ pub fn ui<B: Backend>(f: &mut Frame, app: &mut RustoredApp) {
         ])
         .split(main_chunks[0]);
 
-    // Render S3 settings on the left of the top row
-    debug!("Rendering S3 settings on the left of the top row");
-    s3_settings::render_s3_settings::<B>(f, app, top_row[0]);
+    // Render snapshot source settings (S3, or Azure when configured) on the left of the top row
+    debug!("Rendering snapshot source settings on the left of the top row");
+    if app.snapshot_browser.azure_config.is_some() {
+        azure_settings::render_azure_settings::<B>(f, app, top_row[0]);
+    } else {
+        s3_settings::render_s3_settings::<B>(f, app, top_row[0]);
+    }
     
     // Render restore target selection tabs in the middle of the top row
     debug!("Rendering restore target selection in the middle of the top row");
@@ -139,17 +172,64 @@ pub fn ui<B: Backend>(f: &mut Frame, app: &mut RustoredApp) {
             debug!("Rendering Qdrant settings panel");
             qdrant_settings::render_qdrant_settings::<B>(f, app, top_row[2]);
         },
+        RestoreTarget::OpenSearch => {
+            debug!("Rendering OpenSearch settings panel");
+            opensearch_settings::render_opensearch_settings::<B>(f, app, top_row[2]);
+        },
+        RestoreTarget::Meilisearch => {
+            debug!("Rendering Meilisearch settings panel");
+            meilisearch_settings::render_meilisearch_settings::<B>(f, app, top_row[2]);
+        },
+        RestoreTarget::Weaviate => {
+            debug!("Rendering Weaviate settings panel");
+            weaviate_settings::render_weaviate_settings::<B>(f, app, top_row[2]);
+        },
+        RestoreTarget::Milvus => {
+            debug!("Rendering Milvus settings panel");
+            milvus_settings::render_milvus_settings::<B>(f, app, top_row[2]);
+        },
+        RestoreTarget::DynamoDb => {
+            debug!("Rendering DynamoDB settings panel");
+            dynamodb_settings::render_dynamodb_settings::<B>(f, app, top_row[2]);
+        },
+        RestoreTarget::InfluxDb => {
+            debug!("Rendering InfluxDB settings panel");
+            influxdb_settings::render_influxdb_settings::<B>(f, app, top_row[2]);
+        },
     };
     
-    // For now, render snapshot list taking up the entire bottom row
-    // Later, when a snapshot is selected, we'll split this row to show the restore window
+    // Running jobs take the bottom of the row, below the snapshot list
+    let jobs = app.progress.jobs().len() as u16;
+    let bottom_row = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),                                    // Snapshot list
+            Constraint::Length(if jobs > 0 { jobs + 2 } else { 0 }), // Jobs panel
+        ])
+        .split(main_chunks[1]);
     debug!("Rendering snapshot list in the bottom row");
-    snapshot_list::render_snapshot_list::<B>(f, app, main_chunks[1]);
+    snapshot_list::render_snapshot_list::<B>(f, app, bottom_row[0]);
+    if jobs > 0 {
+        jobs_panel::render_jobs_panel::<B>(f, app, bottom_row[1]);
+    }
 
     // Render status bar
-    let status = format!("Press 'q' to quit | Tab to switch focus | 1-3 to change restore target | Current focus: {:?}", app.focus);
+    // The status bar flashes while a long operation waits for the operator
+    let (status, style) = match app.attention.flash(std::time::Instant::now()) {
+        Some((reason, lit)) => (
+            format!("Rustored is waiting: {}", reason),
+            match lit {
+                true => Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+                false => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            },
+        ),
+        None => (
+            format!("Press 'q' to quit | Tab to switch focus | 1-9 to change restore target | Current focus: {:?}", app.focus),
+            Style::default().fg(Color::White),
+        ),
+    };
     let status_bar = Paragraph::new(status)
-        .style(Style::default().fg(Color::White))
+        .style(style)
         .alignment(Alignment::Center);
     
     f.render_widget(status_bar, chunks[2]);
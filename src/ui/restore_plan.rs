@@ -0,0 +1,279 @@
+// This module contains the restore plan of the Rustored TUI
+// Confirming a restore shows one consolidated plan before anything runs: the
+// snapshot, where it goes, the options and hooks, how long past restores
+// suggest it will take and how much local disk the download needs. Nothing is
+// downloaded or restored until the plan is run, so every restore gets the
+// same plan-then-apply review.
+
+use crate::hooks::RestoreHook;
+use crate::presets::RestoreMode;
+use crate::restore_tool::RestoreTool;
+use crate::ui::guard_rails::{self, GuardedAction};
+use crate::ui::models::{BackupMetadata, PopupState, RestoreTarget};
+use crate::ui::rustored::RustoredApp;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+use std::path::Path;
+
+/// Show the plan of restoring a confirmed snapshot
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The snapshot being confirmed
+pub fn open(app: &mut RustoredApp, snapshot: BackupMetadata) {
+    debug!("Opening restore plan for snapshot: {}", snapshot.key);
+    let lines = plan_lines(app, &snapshot);
+    app.popup_state = PopupState::RestorePlan(snapshot, lines);
+}
+
+/// Describe everything restoring a snapshot will do, one `Label: value` line each
+///
+/// # Arguments
+///
+/// * `app` - The app holding the target settings and restore options
+/// * `snapshot` - The snapshot being confirmed
+pub fn plan_lines(app: &RustoredApp, snapshot: &BackupMetadata) -> Vec<String> {
+    debug!("Building restore plan for {}", snapshot.key);
+    let size = snapshot.size.max(0) as u64;
+    let local = crate::ui::local_file::is_local(app, snapshot);
+    let mut lines = vec![match &snapshot.version_id {
+        _ if local => format!("Source: local file {}", snapshot.key),
+        Some(version_id) => format!("Source: {} (version {})", snapshot.key, version_id),
+        None => format!("Source: {}", snapshot.key),
+    }];
+    let taken = chrono::DateTime::from_timestamp(snapshot.last_modified as i64, 0)
+        .map(|taken| format!(", taken {}", taken.format("%Y-%m-%d %H:%M UTC")))
+        .unwrap_or_default();
+    lines.push(format!("Size: {}{}", humansize::format_size(size, humansize::DECIMAL), taken));
+    if let Some(encryption) = &app.snapshot_browser.selected_encryption {
+        lines.push(format!("Encryption: {}", encryption));
+    }
+    lines.push(format!("Target: {}", target(app)));
+    lines.extend(app.restore_impact.iter().flat_map(|impact| impact.lines()));
+    lines.push(format!("Preset: {}", app.restore_preset.as_deref().unwrap_or("none")));
+    lines.push(format!("Options: {}", app.restore_options.summary()));
+    if app.restore_target == RestoreTarget::Postgres {
+        lines.push(format!("Tool: {}", tool(app, snapshot)));
+    }
+    lines.push(format!("Hooks: {}", hooks(app)));
+    lines.push(app.estimate_line(snapshot));
+    lines.push(format!("Disk: {}", disk(app, snapshot, size)));
+    lines
+}
+
+/// Where the restore goes, e.g. `postgres db.internal:5432, new <word>-restored database`
+fn target(app: &RustoredApp) -> String {
+    debug!("Describing restore target for the plan");
+    let unset = || "(not configured)".to_string();
+    match app.restore_target {
+        RestoreTarget::Postgres => {
+            let config = &app.pg_config;
+            let server = config.server();
+            let existing = config.target_db.clone().or_else(|| config.db_name.clone()).unwrap_or_else(unset);
+            let database = match app.restore_options.mode {
+                RestoreMode::New => match &config.target_db {
+                    Some(name) => format!("new database {}", name),
+                    None => format!("new <word>{} database", crate::gc::RESTORED_SUFFIX),
+                },
+                RestoreMode::Recreate => format!("drop and recreate {}", existing),
+                RestoreMode::Clean => format!("clean and restore over {}", existing),
+            };
+            format!("postgres {}, {}", server, database)
+        }
+        RestoreTarget::Elasticsearch => format!(
+            "elasticsearch {}, index {}",
+            app.es_config.host.clone().unwrap_or_else(unset),
+            app.es_config.target_index().unwrap_or_else(unset),
+        ),
+        RestoreTarget::Qdrant => format!(
+            "qdrant {}, collection {}",
+            app.qdrant_config.host.clone().unwrap_or_else(unset),
+            app.qdrant_config.target_collection().unwrap_or_else(unset),
+        ),
+        RestoreTarget::OpenSearch => format!(
+            "opensearch {}, index {}{}",
+            app.os_config.host.clone().unwrap_or_else(unset),
+            app.os_config.target_index().unwrap_or_else(unset),
+            app.os_config.region.as_ref().map(|region| format!(", signed for {} in {}", app.os_config.service, region)).unwrap_or_default(),
+        ),
+        RestoreTarget::Meilisearch => format!(
+            "meilisearch {}, index {}",
+            app.meili_config.host.clone().unwrap_or_else(unset),
+            app.meili_config.target_index().unwrap_or_else(unset),
+        ),
+        RestoreTarget::Weaviate => format!(
+            "weaviate {}, class {}",
+            app.weaviate_config.host.clone().unwrap_or_else(unset),
+            app.weaviate_config.target_class().unwrap_or_else(unset),
+        ),
+        RestoreTarget::Milvus => format!(
+            "milvus {}, collection {}",
+            app.milvus_config.host.clone().unwrap_or_else(unset),
+            app.milvus_config.target_collection().unwrap_or_else(unset),
+        ),
+        RestoreTarget::DynamoDb => format!(
+            "dynamodb {}, table {}",
+            app.dynamodb_config.endpoint_url.clone().or_else(|| app.dynamodb_config.region.clone()).unwrap_or_else(|| "default region".to_string()),
+            app.dynamodb_config.target_table().unwrap_or_else(unset),
+        ),
+        RestoreTarget::InfluxDb => format!(
+            "influxdb {}, bucket {}",
+            app.influxdb_config.host.clone().unwrap_or_else(unset),
+            app.influxdb_config.target_bucket().unwrap_or_else(unset),
+        ),
+    }
+}
+
+/// The program the PostgreSQL restore runs, e.g. `psql`
+fn tool(app: &RustoredApp, snapshot: &BackupMetadata) -> String {
+    debug!("Describing restore tool for the plan");
+    if app.streams_restore(snapshot) {
+        return RestoreTool::Psql.to_string();
+    }
+    let downloaded = app.downloaded_snapshot(snapshot);
+    RestoreTool::expected(&snapshot.key, downloaded.as_deref().map(Path::new), app.restore_options.restore_command.as_deref())
+}
+
+/// The hooks and SQL the restore runs, in the order they run
+fn hooks(app: &RustoredApp) -> String {
+    debug!("Describing restore hooks for the plan");
+    let describe = |hook: &RestoreHook| match hook {
+        RestoreHook::Url(url) => url.clone(),
+        RestoreHook::Command(command) => command.clone(),
+    };
+    let options = &app.restore_options;
+    let postgres = app.restore_target == RestoreTarget::Postgres;
+    let recipes: Vec<&str> = options.recipes.iter().map(|recipe| recipe.name.as_str()).collect();
+    let hooks: Vec<String> = [
+        app.maintenance_hooks.enter.as_ref().map(|hook| format!("maintenance on {}", describe(hook))),
+        options.pre_hook.as_ref().map(|hook| format!("pre {}", hook)),
+        (postgres && !recipes.is_empty()).then(|| format!("recipes {}", recipes.join(","))),
+        options.post_sql.as_ref().filter(|_| postgres).map(|_| "post SQL".to_string()),
+        options.post_hook.as_ref().map(|hook| format!("post {}", hook)),
+        app.maintenance_hooks.exit.as_ref().map(|hook| format!("maintenance off {}", describe(hook))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    match hooks.is_empty() {
+        true => "none".to_string(),
+        false => hooks.join("; "),
+    }
+}
+
+/// Local disk the download needs, and whether there is room for it
+fn disk(app: &RustoredApp, snapshot: &BackupMetadata, size: u64) -> String {
+    debug!("Describing disk needed for {}", snapshot.key);
+    if crate::ui::local_file::is_local(app, snapshot) {
+        return "none, read from the local file".to_string();
+    }
+    if app.streams_restore(snapshot) {
+        return "none, streamed from the bucket into psql".to_string();
+    }
+    if app.downloaded_snapshot(snapshot).is_some() {
+        return "none, already downloaded".to_string();
+    }
+    let path = crate::download_ledger::temp_download_path(&snapshot.key);
+    let done = crate::download_ledger::DownloadLedger::resumable_bytes(&path, &snapshot.key, size, snapshot.last_modified, snapshot.etag.as_deref())
+        .unwrap_or_default();
+    let needed = size.saturating_sub(done);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let format = |bytes| humansize::format_size(bytes, humansize::DECIMAL);
+    match free_space(dir) {
+        Some(free) if free < needed => format!("{} in {}, but only {} free", format(needed), dir.display(), format(free)),
+        Some(free) => format!("{} in {} ({} free)", format(needed), dir.display(), format(free)),
+        None => format!("{} in {}", format(needed), dir.display()),
+    }
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `dir`
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn free_space(dir: &Path) -> Option<u64> {
+    debug!("Checking free space in {}", dir.display());
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs only writes to the zeroed struct, and the path is NUL-terminated
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// Handle a key in the restore plan
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+///
+/// # Returns
+///
+/// The downloaded file's path when the restore was started, which the main loop restores
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) -> Result<Option<String>> {
+    debug!("Handling restore plan key: {:?}", key);
+    let PopupState::RestorePlan(snapshot, _) = &app.popup_state else {
+        return Ok(None);
+    };
+    let snapshot = snapshot.clone();
+    match key.code {
+        // Run plan
+        KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+            // Existing data in the target must be confirmed by typing its name
+            if app.restore_impact.as_ref().is_some_and(|impact| impact.needs_typed_confirmation()) {
+                app.popup_state = PopupState::ConfirmImpact(snapshot, String::new());
+                return Ok(None);
+            }
+            return guard_rails::confirm(app, GuardedAction::Restore(snapshot)).await;
+        }
+        KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+            app.popup_state = PopupState::ConfirmRestore(snapshot);
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+/// Download a confirmed snapshot, resuming any previous partial download of it
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The snapshot to download
+///
+/// # Returns
+///
+/// The downloaded file's path, which the main loop restores
+pub(crate) async fn start_download(app: &mut RustoredApp, snapshot: BackupMetadata) -> Result<Option<String>> {
+    debug!("Starting download of snapshot: {}", snapshot.key);
+    let tmp_path = crate::download_ledger::temp_download_path(&snapshot.key);
+    let operation = app.begin_operation();
+    // The restore's span stays open until the main loop has restored the download
+    let span = crate::telemetry::Span::operation("restore").with("key", &snapshot.key).with("target", format!("{:?}", app.restore_target).to_lowercase());
+    // Picking tables already downloaded the snapshot, and plain SQL dumps are streamed, which the empty path tells the main loop
+    let result = match app.downloaded_snapshot(&snapshot) {
+        Some(path) => Ok(Some(path)),
+        None if app.streams_restore(&snapshot) => Ok(Some(String::new())),
+        None => crate::ui::app::cancel_on_escape(&operation, app.snapshot_browser.download_snapshot(&snapshot, &tmp_path)).await,
+    };
+    match &result {
+        Ok(Some(_)) => app.restore_span = Some(span),
+        Ok(None) => span.end::<()>(&Err(anyhow::anyhow!("download did not start"))),
+        Err(_) => span.end(&result),
+    }
+    match result {
+        Err(e) if crate::cancel::is_cancelled(&e) => {
+            app.popup_state = PopupState::Error(format!("{}, press Enter on the snapshot again to resume", e));
+            Ok(None)
+        }
+        result => result,
+    }
+}
@@ -0,0 +1,57 @@
+// This module contains the restore preview of the Rustored TUI
+// From the restore confirmation of a PostgreSQL snapshot, `v` downloads the
+// snapshot and lists the objects restoring it would create, from its table of
+// contents, without contacting the server. The download is kept so confirming
+// the restore does not fetch it again.
+
+use crate::ui::models::{BackupMetadata, PopupState};
+use crate::ui::rustored::RustoredApp;
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+
+/// Download a snapshot if needed and show what restoring it would create
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The snapshot being confirmed
+pub async fn open(app: &mut RustoredApp, snapshot: BackupMetadata) {
+    debug!("Opening restore preview for snapshot: {}", snapshot.key);
+    let preview = match crate::ui::table_picker::download_once(app, &snapshot).await {
+        Ok(path) => {
+            let options = &app.restore_options;
+            crate::toc::preview_snapshot(&path, &options.include_tables, &options.exclude_tables).await
+        }
+        Err(e) => Err(e),
+    };
+    app.popup_state = match preview {
+        Ok(lines) => PopupState::RestorePreview(snapshot, lines, 0),
+        Err(e) if crate::cancel::is_cancelled(&e) => PopupState::ConfirmRestore(snapshot),
+        Err(e) => PopupState::Error(format!("Previewing the restore of {} failed: {}", snapshot.key, e)),
+    };
+}
+
+/// Handle a key in the restore preview
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub fn handle_key(app: &mut RustoredApp, key: KeyEvent) {
+    debug!("Handling restore preview key: {:?}", key);
+    let PopupState::RestorePreview(snapshot, lines, scroll) = &mut app.popup_state else {
+        return;
+    };
+    // The first line, the counts, does not scroll
+    let last = lines.len().saturating_sub(2);
+    match key.code {
+        KeyCode::Up => *scroll = scroll.saturating_sub(1),
+        KeyCode::Down => *scroll = (*scroll + 1).min(last),
+        KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+        KeyCode::PageDown => *scroll = (*scroll + 10).min(last),
+        KeyCode::Enter | KeyCode::Esc => {
+            app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+        }
+        _ => {}
+    }
+}
@@ -1,10 +1,22 @@
-use crate::ui::models::{S3Config, PostgresConfig, ElasticsearchConfig, QdrantConfig, PopupState, InputMode, FocusField, RestoreTarget, BackupMetadata};
+use crate::ui::models::{S3Config, PostgresConfig, ElasticsearchConfig, QdrantConfig, OpenSearchConfig, MeilisearchConfig, WeaviateConfig, MilvusConfig, DynamoDbConfig, InfluxDbConfig, TimeoutConfig, TlsConfig, BulkConfig, QdrantUploadConfig, PopupState, InputMode, FocusField, RestoreTarget, BackupMetadata};
 use crate::ui::browser::SnapshotBrowser;
 use crate::ui::key_handler;
+use crate::cancel::{cancellable, CancellationToken};
+use crate::hooks::MaintenanceHooks;
+use crate::progress::ProgressBoard;
+use crate::ui::terminal_status::TerminalStatus;
+use crate::ui::attention::Attention;
+use crate::ui::recording::{SessionRecorder, SessionReplay};
+use crate::presets::{RestoreOptions, RestorePresets};
+use crate::profiles::TargetServer;
+use crate::promote::PromotePlan;
+use crate::mapping::RestoreMapping;
+use crate::bundle::BundleMember;
+use crate::restore::RestoreImpact;
 use ratatui::backend::Backend;
 use ratatui::Terminal;
 use anyhow::{Result, anyhow};
-use log::debug;
+use log::{debug, warn};
 
 /// Main application state struct
 /// 
@@ -16,11 +28,65 @@ pub struct RustoredApp {
     pub pg_config: PostgresConfig,
     pub es_config: ElasticsearchConfig,
     pub qdrant_config: QdrantConfig,
+    pub os_config: OpenSearchConfig,
+    pub meili_config: MeilisearchConfig,
+    pub weaviate_config: WeaviateConfig,
+    pub milvus_config: MilvusConfig,
+    pub dynamodb_config: DynamoDbConfig,
+    pub influxdb_config: InfluxDbConfig,
     pub restore_target: RestoreTarget,
     pub popup_state: PopupState,
     pub input_mode: InputMode,
     pub input_buffer: String,
     pub focus: FocusField,
+    pub maintenance_hooks: MaintenanceHooks,
+    pub restore_presets: RestorePresets,
+    /// Options for the restore being confirmed, seeded from the matching preset
+    pub restore_options: RestoreOptions,
+    /// Pattern of the preset that seeded `restore_options`, if any
+    pub restore_preset: Option<String>,
+    /// Hook run before every restore whose preset sets none, from `--pre-restore-hook`
+    pub pre_restore_hook: Option<String>,
+    /// Hook run after every restore whose preset sets none, from `--post-restore-hook`
+    pub post_restore_hook: Option<String>,
+    /// Row filter file of every PostgreSQL restore whose preset sets none, from `--row-filter-file`
+    pub row_filter_file: Option<String>,
+    /// Promotion offered for the last PostgreSQL restore, if any
+    pub promote_plan: Option<PromotePlan>,
+    /// Current state of the Elasticsearch index or Qdrant collection the confirmed restore writes to
+    pub restore_impact: Option<RestoreImpact>,
+    /// Snapshot downloaded to pick its tables, and where, restored without downloading it again
+    pub downloaded: Option<(BackupMetadata, String)>,
+    /// Durations of past restores, which the restore plan estimates from
+    pub restore_history: Option<crate::restore_history::RestoreHistory>,
+    /// Span of the restore in progress, from its download until the summary
+    pub restore_span: Option<crate::telemetry::Span>,
+    /// Cancelled on SIGINT or SIGTERM, which cancels every operation
+    pub cancel: CancellationToken,
+    /// Cancels the operation in progress, a child of `cancel`
+    pub operation: CancellationToken,
+    /// Latest progress of running downloads and restores, shared with the snapshot browser
+    pub progress: ProgressBoard,
+    /// Bundle confirmed for restoring, picked up by the main loop, which can draw progress
+    pub pending_bundle: Option<Vec<BundleMember>>,
+    /// Marked snapshots confirmed for restoring one after another, picked up by the main loop
+    pub pending_batch: Option<Vec<BackupMetadata>>,
+    /// Terminal title and tmux status updated with progress, when the TUI owns a terminal
+    pub terminal_status: Option<TerminalStatus>,
+    /// Bell and status bar flash when a long operation ends waiting for the operator
+    pub attention: Attention,
+    /// Environment the settings point at, shown as a banner; production asks for its name before destructive actions
+    pub environment: Option<String>,
+    /// PostgreSQL servers of the profile, one of which is picked before each restore
+    pub pg_servers: Vec<TargetServer>,
+    /// Name of the server picked last, whose settings are in `pg_config`
+    pub pg_server: Option<String>,
+    /// Synthetic snapshots and simulated restores from `--demo`, with no server contacted
+    pub demo: bool,
+    /// Writes each handled key and the state after it, with `--record-session`
+    pub recorder: Option<SessionRecorder>,
+    /// Recording whose keys are handled instead of the keyboard's, for `replay`
+    pub replay: Option<SessionReplay>,
 }
 
 impl RustoredApp {
@@ -33,6 +99,7 @@ impl RustoredApp {
     /// # Returns
     /// 
     /// A new RustoredApp instance with the provided configuration
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         bucket: &Option<String>,
         region: &Option<String>,
@@ -68,8 +135,10 @@ impl RustoredApp {
             access_key_id: access_key_id.clone().unwrap_or_default(),
             secret_access_key: secret_access_key.clone().unwrap_or_default(),
             path_style,
+            sse_customer_key: String::new(),
             error_message: None,
             test_s3_button: false,
+            timeouts: TimeoutConfig::default(),
         };
         
         // Create PostgreSQL configuration
@@ -80,13 +149,18 @@ impl RustoredApp {
             password: password.clone(),
             use_ssl,
             db_name: db_name.clone(),
-            ..Default::default()
+            target_db: None,
+            restore_jobs: None,
         };
         
         // Create Elasticsearch configuration
         let es_config = ElasticsearchConfig {
             host: es_host.clone(),
             index: es_index.clone(),
+            timeouts: TimeoutConfig::default(),
+            bulk: BulkConfig::default(),
+            tls: TlsConfig::default(),
+            mapping: RestoreMapping::default(),
         };
         
         // Create Qdrant configuration
@@ -94,24 +168,149 @@ impl RustoredApp {
             host: es_host.clone(),
             collection: es_index.clone(),
             api_key: qdrant_api_key.clone(),
+            timeouts: TimeoutConfig::default(),
+            tls: TlsConfig::default(),
+            upload: QdrantUploadConfig::default(),
+            mapping: RestoreMapping::default(),
         };
         
         // Create snapshot browser with S3 configuration
         let snapshot_browser = SnapshotBrowser::new(s3_config.clone());
+        let cancel = CancellationToken::new();
         
         // Create and return the RustoredApp instance
         RustoredApp {
-            snapshot_browser,
             s3_config,
             pg_config,
             es_config,
             qdrant_config,
+            os_config: OpenSearchConfig::default(),
+            meili_config: MeilisearchConfig::default(),
+            weaviate_config: WeaviateConfig::default(),
+            milvus_config: MilvusConfig::default(),
+            dynamodb_config: DynamoDbConfig::default(),
+            influxdb_config: InfluxDbConfig::default(),
             restore_target: RestoreTarget::Postgres,
             popup_state: PopupState::Hidden,
             input_mode: InputMode::Normal,
             input_buffer: String::new(),
             focus: FocusField::SnapshotList,
+            maintenance_hooks: MaintenanceHooks::default(),
+            restore_presets: RestorePresets::default(),
+            restore_options: RestoreOptions::default(),
+            restore_preset: None,
+            pre_restore_hook: None,
+            post_restore_hook: None,
+            row_filter_file: None,
+            promote_plan: None,
+            restore_impact: None,
+            restore_history: None,
+            downloaded: None,
+            restore_span: None,
+            operation: cancel.child_token(),
+            cancel,
+            progress: snapshot_browser.progress.clone(),
+            pending_bundle: None,
+            pending_batch: None,
+            terminal_status: None,
+            attention: Attention::default(),
+            environment: None,
+            pg_servers: Vec::new(),
+            pg_server: None,
+            demo: false,
+            recorder: None,
+            replay: None,
+            snapshot_browser,
+        }
+    }
+
+    /// Start a new cancellable operation
+    ///
+    /// A cancelled token stays cancelled, so every download, upload, dump,
+    /// restore or verification gets a fresh child of `cancel`, shared with the
+    /// snapshot browser.
+    ///
+    /// # Returns
+    ///
+    /// The token that cancels the new operation
+    pub fn begin_operation(&mut self) -> CancellationToken {
+        debug!("Beginning a new cancellable operation");
+        self.operation = self.cancel.child_token();
+        self.snapshot_browser.cancel = self.operation.clone();
+        self.operation.clone()
+    }
+
+    /// Path of a snapshot already downloaded to pick its tables, if it is still there
+    pub fn downloaded_snapshot(&self, snapshot: &BackupMetadata) -> Option<String> {
+        debug!("Looking for a download of snapshot: {}", snapshot.key);
+        self.downloaded
+            .as_ref()
+            .filter(|(downloaded, path)| downloaded == snapshot && std::path::Path::new(path).exists())
+            .map(|(_, path)| path.clone())
+    }
+
+    /// Apply client timeouts to the S3 and restore target configurations
+    ///
+    /// # Arguments
+    ///
+    /// * `s3` - Timeouts for the S3, GCS, Azure, B2 or SFTP client
+    /// * `target` - Timeouts for the Elasticsearch, OpenSearch, Meilisearch, Weaviate, Milvus, DynamoDB, InfluxDB and Qdrant clients
+    pub fn set_timeouts(&mut self, s3: TimeoutConfig, target: TimeoutConfig) {
+        debug!("Setting timeouts: s3={:?}, target={:?}", s3, target);
+        self.s3_config.timeouts = s3.clone();
+        if let Some(gcs) = self.snapshot_browser.gcs_config.as_mut() {
+            gcs.timeouts = s3.clone();
+        }
+        if let Some(azure) = self.snapshot_browser.azure_config.as_mut() {
+            azure.timeouts = s3.clone();
         }
+        if let Some(b2) = self.snapshot_browser.b2_config.as_mut() {
+            b2.timeouts = s3.clone();
+        }
+        if let Some(sftp) = self.snapshot_browser.sftp_config.as_mut() {
+            sftp.timeouts = s3.clone();
+        }
+        self.snapshot_browser.s3_config.timeouts = s3;
+        self.es_config.timeouts = target.clone();
+        self.os_config.timeouts = target.clone();
+        self.meili_config.timeouts = target.clone();
+        self.weaviate_config.timeouts = target.clone();
+        self.milvus_config.timeouts = target.clone();
+        self.dynamodb_config.timeouts = target.clone();
+        self.influxdb_config.timeouts = target.clone();
+        self.qdrant_config.timeouts = target;
+    }
+
+    /// Apply a restore mapping to the Elasticsearch, OpenSearch, Meilisearch, Weaviate, Milvus, DynamoDB, InfluxDB and Qdrant restores
+    pub fn set_restore_mapping(&mut self, mapping: RestoreMapping) {
+        debug!("Setting restore mapping: {:?}", mapping);
+        self.es_config.mapping = mapping.clone();
+        self.os_config.mapping = mapping.clone();
+        self.meili_config.mapping = mapping.clone();
+        self.weaviate_config.mapping = mapping.clone();
+        self.milvus_config.mapping = mapping.clone();
+        self.dynamodb_config.mapping = mapping.clone();
+        self.influxdb_config.mapping = mapping.clone();
+        self.qdrant_config.mapping = mapping;
+    }
+
+    /// Lines describing how the mapping changes a restore to the current target, for confirmation
+    ///
+    /// PostgreSQL restores are not mapped, so they have none.
+    pub fn mapping_lines(&self) -> Vec<String> {
+        debug!("Getting restore mapping lines for {:?}", self.restore_target);
+        let (mapping, name) = match self.restore_target {
+            RestoreTarget::Postgres => return Vec::new(),
+            RestoreTarget::Elasticsearch => (&self.es_config.mapping, &self.es_config.index),
+            RestoreTarget::Qdrant => (&self.qdrant_config.mapping, &self.qdrant_config.collection),
+            RestoreTarget::OpenSearch => (&self.os_config.mapping, &self.os_config.index),
+            RestoreTarget::Meilisearch => (&self.meili_config.mapping, &self.meili_config.index),
+            RestoreTarget::Weaviate => (&self.weaviate_config.mapping, &self.weaviate_config.class),
+            RestoreTarget::Milvus => (&self.milvus_config.mapping, &self.milvus_config.collection),
+            RestoreTarget::DynamoDb => (&self.dynamodb_config.mapping, &self.dynamodb_config.table),
+            RestoreTarget::InfluxDb => (&self.influxdb_config.mapping, &self.influxdb_config.bucket),
+        };
+        mapping.lines(name.as_deref().unwrap_or_default())
     }
 
     /// Run the application loop
@@ -178,17 +377,176 @@ impl RustoredApp {
     /// A boxed trait object implementing the RestoreTarget trait
     pub fn get_current_restore_target(&self) -> Box<dyn crate::restore::RestoreTarget + Send + Sync> {
         debug!("Getting current restore target for type: {:?}", self.restore_target);
-        match self.restore_target {
+        self.restore_target_for(&self.restore_target)
+    }
+
+    /// Get the restore target of a given type, configured from the settings panels
+    pub fn restore_target_for(&self, target: &RestoreTarget) -> Box<dyn crate::restore::RestoreTarget + Send + Sync> {
+        debug!("Getting restore target for type: {:?}", target);
+        if self.demo {
+            let (name, restored) = match target {
+                RestoreTarget::Postgres => ("PostgreSQL", self.pg_config.target_db.clone().unwrap_or_else(crate::postgres::new_restored_db_name)),
+                RestoreTarget::Elasticsearch => ("Elasticsearch", self.es_config.target_index().unwrap_or_default()),
+                RestoreTarget::Qdrant => ("Qdrant", self.qdrant_config.target_collection().unwrap_or_default()),
+                RestoreTarget::OpenSearch => ("OpenSearch", self.os_config.target_index().unwrap_or_default()),
+                RestoreTarget::Meilisearch => ("Meilisearch", self.meili_config.target_index().unwrap_or_default()),
+                RestoreTarget::Weaviate => ("Weaviate", self.weaviate_config.target_class().unwrap_or_default()),
+                RestoreTarget::Milvus => ("Milvus", self.milvus_config.target_collection().unwrap_or_default()),
+                RestoreTarget::DynamoDb => ("DynamoDB", self.dynamodb_config.target_table().unwrap_or_default()),
+                RestoreTarget::InfluxDb => ("InfluxDB", self.influxdb_config.target_bucket().unwrap_or_default()),
+            };
+            return Box::new(crate::targets::DemoRestoreTarget { name, restored });
+        }
+        match target {
             RestoreTarget::Postgres => Box::new(crate::targets::PostgresRestoreTarget {
                 config: self.pg_config.clone(),
+                options: self.restore_options.clone(),
             }),
-            RestoreTarget::Elasticsearch => Box::new(crate::targets::ElasticsearchRestoreTarget {
-                config: self.es_config.clone(),
-            }),
-            RestoreTarget::Qdrant => Box::new(crate::targets::QdrantRestoreTarget {
-                config: self.qdrant_config.clone(),
-            }),
+            RestoreTarget::Elasticsearch => Box::new(crate::targets::ElasticsearchRestoreTarget::new(self.es_config.clone())),
+            RestoreTarget::Qdrant => Box::new(crate::targets::QdrantRestoreTarget::new(self.qdrant_config.clone())),
+            RestoreTarget::OpenSearch => Box::new(crate::targets::OpenSearchRestoreTarget::new(self.os_config.clone())),
+            RestoreTarget::Meilisearch => Box::new(crate::targets::MeilisearchRestoreTarget::new(self.meili_config.clone())),
+            RestoreTarget::Weaviate => Box::new(crate::targets::WeaviateRestoreTarget::new(self.weaviate_config.clone())),
+            RestoreTarget::Milvus => Box::new(crate::targets::MilvusRestoreTarget::new(self.milvus_config.clone())),
+            RestoreTarget::DynamoDb => Box::new(crate::targets::DynamoDbRestoreTarget::new(self.dynamodb_config.clone())),
+            RestoreTarget::InfluxDb => Box::new(crate::targets::InfluxDbRestoreTarget::new(self.influxdb_config.clone())),
+        }
+    }
+
+    /// Seed the restore options from the preset matching a snapshot
+    ///
+    /// Called when the restore confirmation popup is opened, so the popup
+    /// shows the preset that will be applied and lets the user override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The snapshot about to be restored
+    pub fn apply_restore_preset(&mut self, snapshot: &BackupMetadata) {
+        debug!("Applying restore preset for snapshot: {}", snapshot.key);
+        match self.restore_presets.find(&snapshot.key) {
+            Some(preset) => {
+                self.restore_options = preset.options.clone();
+                self.restore_preset = Some(preset.pattern.clone());
+            }
+            None => {
+                self.restore_options = RestoreOptions::default();
+                self.restore_preset = None;
+            }
         }
+        self.apply_restore_defaults();
+    }
+
+    /// Fill the restore options the preset left unset from the command line defaults
+    pub fn apply_restore_defaults(&mut self) {
+        debug!("Applying restore defaults");
+        let options = &mut self.restore_options;
+        options.jobs = options.jobs.or(self.pg_config.restore_jobs);
+        options.pre_hook = options.pre_hook.take().or_else(|| self.pre_restore_hook.clone());
+        options.post_hook = options.post_hook.take().or_else(|| self.post_restore_hook.clone());
+        options.row_filters = options.row_filters.take().or_else(|| self.row_filter_file.clone());
+    }
+
+    /// Template variables for hooks of a restore of `key` to `target`, named `name`
+    pub(crate) fn hook_vars(&self, target: &RestoreTarget, name: &str, key: &str) -> crate::hooks::TemplateVars {
+        debug!("Building hook variables for {} of {}", name, key);
+        let (datastore, host) = match target {
+            RestoreTarget::Postgres => ("postgres", &self.pg_config.host),
+            RestoreTarget::Elasticsearch => ("elasticsearch", &self.es_config.host),
+            RestoreTarget::Qdrant => ("qdrant", &self.qdrant_config.host),
+            RestoreTarget::OpenSearch => ("opensearch", &self.os_config.host),
+            RestoreTarget::Meilisearch => ("meilisearch", &self.meili_config.host),
+            RestoreTarget::Weaviate => ("weaviate", &self.weaviate_config.host),
+            RestoreTarget::Milvus => ("milvus", &self.milvus_config.host),
+            RestoreTarget::DynamoDb => ("dynamodb", &self.dynamodb_config.endpoint_url),
+            RestoreTarget::InfluxDb => ("influxdb", &self.influxdb_config.host),
+        };
+        crate::hooks::TemplateVars::new(name, key).with_target(datastore, host.as_deref().unwrap_or_default())
+    }
+
+    /// Dump the configured PostgreSQL database and upload it as a `pre-change` snapshot
+    ///
+    /// This backs the one-keystroke "backup now" action, meant to be used right
+    /// before manual surgery on a database. The uploaded snapshot is pinned to
+    /// the top of the snapshot list once the list is reloaded.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the S3 key of the uploaded snapshot
+    pub async fn backup_now(&mut self) -> Result<String> {
+        debug!("Starting backup now for PostgreSQL database: {:?}", self.pg_config.db_name);
+        let db_name = self.pg_config.db_name.clone()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| anyhow!("PostgreSQL database name is required for backup"))?;
+        let host = self.pg_config.host.clone().unwrap_or_else(|| "localhost".to_string());
+        let port = self.pg_config.port.unwrap_or(5432);
+
+        // Dump into a temporary file that is removed once uploaded
+        let dump_file = tempfile::NamedTempFile::new()?;
+        let dump_path = dump_file.path().to_string_lossy().to_string();
+        let started = chrono::Utc::now();
+        if self.demo {
+            // There is no server to dump in demo mode
+            std::fs::write(&dump_path, crate::demo::DEMO_DUMP)?;
+        } else {
+            let dump = crate::backup::dump_database(
+                &db_name,
+                &dump_path,
+                &host,
+                port,
+                self.pg_config.username.as_deref(),
+                self.pg_config.password.as_deref(),
+                self.pg_config.use_ssl,
+                true,
+                None,
+            );
+            cancellable(&self.operation, &format!("Dump of {}", db_name), dump).await?;
+        }
+
+        // Upload with the label embedded in the key so it can be pinned
+        let label = crate::backup::PRE_CHANGE_LABEL;
+        let finished = chrono::Utc::now();
+        let key = crate::backup::labeled_snapshot_key(self.snapshot_browser.prefix(), &db_name, label, finished);
+        let encrypted = self.snapshot_browser.encryption.encrypt_file(dump_file.path()).await?;
+        let (upload, key) = match &encrypted {
+            Some(encrypted) => (encrypted.path(), self.snapshot_browser.encryption.encrypted_key(&key)),
+            None => (dump_file.path(), key),
+        };
+        self.snapshot_browser.upload_snapshot(upload, &key, Some(label)).await?;
+        let store = self.snapshot_browser.store().await?;
+        crate::manifest::record_backup(store.as_ref(), &key, &db_name, upload, started, finished).await;
+
+        // Reload so the new snapshot shows up pinned at the top
+        self.snapshot_browser.load_snapshots().await?;
+        self.snapshot_browser.selected_index = 0;
+        debug!("Backup now completed: {}", key);
+        Ok(key)
+    }
+
+    /// Download and apply the cluster globals snapshot matching a PostgreSQL snapshot
+    ///
+    /// Run before the database dump is restored, so the roles its owners and
+    /// grants name exist on the target server.
+    ///
+    /// # Returns
+    ///
+    /// The key of the applied globals snapshot and the unexpected errors psql reported
+    pub async fn apply_cluster_globals(&mut self, snapshot: &BackupMetadata) -> Result<(String, Vec<String>)> {
+        debug!("Applying cluster globals before restoring {}", snapshot.key);
+        if self.snapshot_browser.snapshots.is_empty() {
+            self.snapshot_browser.load_snapshots().await?;
+        }
+        let globals = crate::globals::find_globals(&self.snapshot_browser.snapshots, snapshot)
+            .cloned()
+            .ok_or_else(|| anyhow!("No globals snapshot found; upload one with backup-globals"))?;
+        let tmp_path = crate::download_ledger::temp_download_path(&globals.key);
+        let download = self.snapshot_browser.download_snapshot(&globals, &tmp_path);
+        let path = cancellable(&self.operation, &format!("Download of {}", globals.key), download).await?
+            .ok_or_else(|| anyhow!("Download of {} failed", globals.key))?;
+        let applied = crate::globals::apply_globals(&self.pg_config, std::path::Path::new(&path)).await;
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("Failed to remove downloaded globals {}: {}", path, e);
+        }
+        Ok((globals.key, applied?))
     }
 
     /// Restore a snapshot from a downloaded file
@@ -197,7 +555,7 @@ impl RustoredApp {
     /// 
     /// * `snapshot` - The snapshot metadata
     /// * `terminal` - A mutable reference to the terminal
-    /// * `file_path` - The path to the downloaded snapshot file
+    /// * `file_path` - The path to the downloaded snapshot file, or empty when it is streamed from the bucket
     /// 
     /// # Returns
     /// 
@@ -206,61 +564,106 @@ impl RustoredApp {
         debug!("Starting restore of snapshot: {:?} from file: {}", snapshot, file_path);
         debug!("Using restore target: {:?}", self.restore_target);
         use std::path::Path;
-        use std::sync::atomic::{AtomicBool, Ordering};
-        use std::sync::Arc;
-        use tokio::time::sleep;
         use std::time::Duration;
         
-        // Create the appropriate restore target based on the selected target type
+        // Check if the target is properly configured before showing progress
         let restore_target = self.get_current_restore_target();
-        
-        // Check if the target is properly configured
         if !restore_target.is_configured() {
             let required = restore_target.required_fields().join(", ");
             return Err(anyhow!("Restore target not properly configured. Required fields: {}", required));
         }
         
+        // A plain SQL dump was not downloaded, it is read from the bucket as it is restored, or from a local file
+        let stream: Result<Option<Box<dyn tokio::io::AsyncRead + Unpin + Send>>> = match (file_path.is_empty(), self.streams_restore(snapshot)) {
+            (true, _) => self.open_restore_stream(snapshot).await.map(|stream| Some(Box::new(stream) as _)),
+            (false, true) => tokio::fs::File::open(file_path).await.map(|file| Some(Box::new(file) as _)).map_err(Into::into),
+            (false, false) => Ok(None),
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                if let Some(span) = self.restore_span.take() {
+                    span.end::<()>(&Err(anyhow!("Opening the snapshot failed: {}", e)));
+                }
+                return Err(e);
+            }
+        };
+
         // Update UI to show initial progress
         self.popup_state = PopupState::Restoring(snapshot.clone(), 0.0);
         terminal.draw(|f| crate::ui::renderer::ui::<B>(f, self))?;
         
-        // Create a cancellation flag that can be shared between threads
-        let cancelled = Arc::new(AtomicBool::new(false));
-        let cancelled_clone = cancelled.clone();
-        
-        // Spawn a task to update the progress UI
-        let progress_handle = tokio::spawn(async move {
-            let mut progress = 0.0;
-            while progress < 100.0 && !cancelled.load(Ordering::SeqCst) {
-                progress += 1.0;
-                if progress > 100.0 {
-                    progress = 100.0;
+        // Perform the actual restore operation, cancelled with Esc
+        let operation = self.begin_operation();
+        let span = self.restore_span.take().unwrap_or_else(|| crate::telemetry::Span::operation("restore"));
+        let mut globals = None;
+        if self.restore_options.globals && self.restore_target == RestoreTarget::Postgres {
+            match crate::ui::app::cancel_on_escape(&operation, self.apply_cluster_globals(snapshot)).await {
+                Ok((key, errors)) => globals = Some(crate::globals::summary(&key, &errors)),
+                Err(e) => {
+                    span.end::<()>(&Err(anyhow!("Applying cluster globals failed: {}", e)));
+                    self.popup_state = PopupState::Error(format!("Applying cluster globals failed: {}", e));
+                    terminal.draw(|f| crate::ui::renderer::ui::<B>(f, self))?;
+                    return Ok(());
                 }
-                sleep(Duration::from_millis(50)).await;
             }
-            progress
-        });
-        
-        // Perform the actual restore operation
-        debug!("Calling restore_snapshot on target");
-        let file_path = Path::new(file_path);
-        let restore_result = restore_target.restore_snapshot(file_path, None).await;
-        
-        // Cancel the progress task
-        cancelled_clone.store(true, Ordering::SeqCst);
-        
-        // Wait for the progress task to complete
-        let _ = progress_handle.await;
+        }
+        let mut updates = self.progress.subscribe();
+        let restore_result = {
+            // Rendering only reads the app, so it can run alongside the restore
+            let app: &Self = self;
+            let restore = crate::ui::app::cancel_on_escape(&operation, async {
+                match stream {
+                    Some(stream) => app.run_streamed_restore(snapshot, stream).await,
+                    None => app.run_restore(snapshot, Path::new(file_path)).await,
+                }
+            });
+            tokio::pin!(restore);
+
+            // Redraw at most 10 times per second, and only when progress moved;
+            // the board keeps just the latest value, so a fast restore cannot back up the UI
+            let mut ticker = tokio::time::interval(Duration::from_millis(100));
+            loop {
+                tokio::select! {
+                    result = &mut restore => break result,
+                    _ = ticker.tick() => {
+                        if updates.has_changed().unwrap_or(false) {
+                            updates.borrow_and_update();
+                            terminal.draw(|f| crate::ui::renderer::ui::<B>(f, app))?;
+                        }
+                    }
+                }
+            }
+        };
         
         // Update UI based on restore result
         match restore_result {
-            Ok(result) => {
+            Ok(outcome) => {
+                let result = outcome.name;
                 debug!("Restore completed successfully: {}", result);
-                self.popup_state = PopupState::Success(format!("Restored to {}", result));
+                // A restored PostgreSQL database can be promoted from the summary
+                self.promote_plan = (self.restore_target == RestoreTarget::Postgres).then(|| {
+                    PromotePlan::new(&crate::hooks::source_db_from_key(&snapshot.key), &result, chrono::Utc::now())
+                });
+                // Rejected records are reported in the summary rather than as a failure
+                let mut lines = vec![format!("Restored to {}", result)];
+                lines.extend(globals);
+                lines.extend(outcome.dead_letters.map(|report| report.summary()));
+                self.popup_state = match self.change_summary(snapshot, &result).await {
+                    Ok(Some(summary)) => {
+                        lines.extend(summary.lines());
+                        PopupState::RestoreSummary(lines)
+                    }
+                    Ok(None) if self.promote_plan.is_some() || lines.len() > 1 => PopupState::RestoreSummary(lines),
+                    Ok(None) => PopupState::Success(format!("Restored to {}", result)),
+                    Err(e) => PopupState::Error(format!("Restored to {}, but comparing with the live database failed: {}", result, e)),
+                };
+                span.end(&Ok(()));
             }
             Err(e) => {
                 debug!("Restore failed: {}", e);
-                self.popup_state = PopupState::Error(format!("Restore failed: {}", e));
+                span.end::<()>(&Err(anyhow!("{}", e)));
+                self.popup_state = PopupState::Error(self.restore_error("Restore", &e));
             }
         }
         
@@ -269,4 +672,235 @@ impl RustoredApp {
         
         Ok(())
     }
+
+    /// Restore a downloaded snapshot to the selected target without touching the UI
+    ///
+    /// Runs the restore and the preset's post-restore SQL and hook inside the
+    /// maintenance window. Used by the TUI and the `restore-from-s3` command.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The snapshot being restored
+    /// * `file_path` - The path to the downloaded snapshot file
+    ///
+    /// # Returns
+    ///
+    /// The name of the restored database, index, or collection, and the
+    /// records the target rejected, if any
+    pub async fn run_restore(&self, snapshot: &BackupMetadata, file_path: &std::path::Path) -> Result<crate::restore::RestoreOutcome> {
+        debug!("Running restore of {} from {:?}", snapshot.key, file_path);
+        let restore_target = self.get_current_restore_target();
+        if !restore_target.is_configured() {
+            let required = restore_target.required_fields().join(", ");
+            return Err(anyhow!("Restore target not properly configured. Required fields: {}", required));
+        }
+
+        let name = self.restore_in_window(snapshot, |progress| restore_target.restore_snapshot(file_path, Some(progress))).await?;
+        Ok(crate::restore::RestoreOutcome { name, dead_letters: restore_target.dead_letters() })
+    }
+
+    /// Whether a snapshot is streamed from the bucket into its restore instead of downloaded first
+    pub fn streams_restore(&self, snapshot: &BackupMetadata) -> bool {
+        debug!("Checking if {} is restored as a stream", snapshot.key);
+        self.restore_target == RestoreTarget::Postgres && crate::stream_restore::is_plain_sql(&snapshot.key)
+    }
+
+    /// Open a snapshot in the bucket for `run_streamed_restore`
+    pub async fn open_restore_stream(&mut self, snapshot: &BackupMetadata) -> Result<crate::storage::PartReader> {
+        debug!("Opening restore stream of {}", snapshot.key);
+        let store = self.snapshot_browser.store().await?;
+        crate::stream_restore::open_snapshot(store.as_ref(), snapshot).await
+    }
+
+    /// Restore a plain SQL dump into PostgreSQL as it is read from the bucket or a local file
+    ///
+    /// Like `run_restore`, but nothing is written to disk; progress follows
+    /// the lines psql has been handed, decompressing gzip, zstd or lz4 dumps on the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The snapshot being restored
+    /// * `input` - The snapshot's body, from `open_restore_stream` or the local file
+    pub async fn run_streamed_restore<R: tokio::io::AsyncRead + Unpin>(&self, snapshot: &BackupMetadata, input: R) -> Result<crate::restore::RestoreOutcome> {
+        debug!("Running streamed restore of {}", snapshot.key);
+        let restore_target = self.get_current_restore_target();
+        if self.restore_target != RestoreTarget::Postgres || !restore_target.is_configured() {
+            return Err(anyhow!("Streamed restores need a configured PostgreSQL target. Required fields: {}", restore_target.required_fields().join(", ")));
+        }
+        let name = self.restore_in_window(snapshot, |progress| async move {
+            let size = snapshot.size.max(0) as u64;
+            crate::stream_restore::restore_sql_stream(&self.pg_config, input, &snapshot.key, size, &self.restore_options, Some(progress.as_ref())).await
+        }).await?;
+        Ok(crate::restore::RestoreOutcome { name, dead_letters: None })
+    }
+
+    /// Download a snapshot and restore it, or stream it when it is a plain SQL dump
+    pub async fn restore_from_bucket(&mut self, snapshot: &BackupMetadata) -> Result<crate::restore::RestoreOutcome> {
+        debug!("Restoring {} from the bucket", snapshot.key);
+        if self.streams_restore(snapshot) {
+            let input = self.open_restore_stream(snapshot).await?;
+            return self.run_streamed_restore(snapshot, input).await;
+        }
+        let tmp_path = crate::download_ledger::temp_download_path(&snapshot.key);
+        let path = self.snapshot_browser.download_snapshot(snapshot, &tmp_path).await?
+            .ok_or_else(|| anyhow!("Download of {} failed: {:?}", snapshot.key, self.snapshot_browser.popup_state))?;
+        self.run_restore(snapshot, std::path::Path::new(&path)).await
+    }
+
+    /// Run a restore and the preset's hooks and post-restore SQL inside the maintenance window
+    ///
+    /// `restore` is handed the progress callback and returns the restored name.
+    async fn restore_in_window<F, Fut>(&self, snapshot: &BackupMetadata, restore: F) -> Result<String>
+    where
+        F: FnOnce(Box<dyn Fn(f32) + Send + Sync>) -> Fut,
+        Fut: std::future::Future<Output = Result<String>>,
+    {
+        debug!("Restoring {} inside the maintenance window", snapshot.key);
+        let target_name = match self.restore_target {
+            RestoreTarget::Postgres => self.pg_config.db_name.clone(),
+            RestoreTarget::Elasticsearch => self.es_config.target_index(),
+            RestoreTarget::Qdrant => self.qdrant_config.target_collection(),
+            RestoreTarget::OpenSearch => self.os_config.target_index(),
+            RestoreTarget::Meilisearch => self.meili_config.target_index(),
+            RestoreTarget::Weaviate => self.weaviate_config.target_class(),
+            RestoreTarget::Milvus => self.milvus_config.target_collection(),
+            RestoreTarget::DynamoDb => self.dynamodb_config.target_table(),
+            RestoreTarget::InfluxDb => self.influxdb_config.target_bucket(),
+        }.unwrap_or_default();
+        // The post-restore hook runs inside the maintenance window, before the application is let back in
+        let options = &self.restore_options;
+        let pg_config = (self.restore_target == RestoreTarget::Postgres).then_some(&self.pg_config);
+        // Cancelling inside the window still runs the maintenance off hook
+        let operation = format!("Restore of {}", snapshot.key);
+        let reporter = self.progress.reporter(&operation);
+        let result = self.maintenance_hooks
+            .run(&target_name, cancellable(&self.operation, &operation, async {
+                // The restored database is only named once it exists, so the pre hook gets the target
+                options.run_pre_hook(&self.hook_vars(&self.restore_target, &target_name, &snapshot.key)).await?;
+                let started = std::time::Instant::now();
+                let restored = restore(reporter.callback()).await?;
+                let vars = self.hook_vars(&self.restore_target, &restored, &snapshot.key);
+                self.record_restore(&vars, snapshot, started.elapsed());
+                if let Some(pg_config) = pg_config {
+                    crate::provenance::record(pg_config, &vars).await;
+                    options.run_post_sql(pg_config, &vars).await?;
+                }
+                options.run_post_hook(&vars).await?;
+                Ok(restored)
+            }))
+            .await;
+        reporter.finish();
+        if result.as_ref().is_err_and(crate::cancel::is_cancelled) {
+            crate::postgres::finish_partial_drops().await;
+        }
+        result
+    }
+
+    /// How long restoring a snapshot into the current target is expected to take, from the restore history
+    pub fn restore_estimate(&self, snapshot: &BackupMetadata) -> Option<crate::restore_history::Estimate> {
+        debug!("Estimating restore of {}", snapshot.key);
+        let history = self.restore_history.as_ref()?;
+        let vars = self.hook_vars(&self.restore_target, "", &snapshot.key);
+        history.estimate(&vars.datastore, &vars.target_host, &vars.source_db, snapshot.size.max(0) as u64)
+    }
+
+    /// The estimated duration line of the restore confirmation, plan and `restore-from-s3`
+    pub fn estimate_line(&self, snapshot: &BackupMetadata) -> String {
+        debug!("Describing estimated duration of {}", snapshot.key);
+        format!(
+            "Duration: {}",
+            self.restore_estimate(snapshot).map_or_else(|| "unknown, no past restores to go by".to_string(), |estimate| estimate.describe()),
+        )
+    }
+
+    /// Add a finished restore to the history the restore plan estimates from
+    fn record_restore(&self, vars: &crate::hooks::TemplateVars, snapshot: &BackupMetadata, elapsed: std::time::Duration) {
+        debug!("Recording restore of {} in {:?}", snapshot.key, elapsed);
+        let Some(history) = &self.restore_history else { return };
+        let record = crate::restore_history::RestoreRecord {
+            datastore: vars.datastore.clone(),
+            source_db: vars.source_db.clone(),
+            target_host: vars.target_host.clone(),
+            bytes: snapshot.size.max(0) as u64,
+            seconds: elapsed.as_secs_f64(),
+            finished_at: chrono::Utc::now().timestamp() as f64,
+        };
+        if let Err(e) = history.record(record) {
+            warn!("Failed to record the restore in the restore history: {}", e);
+        }
+    }
+
+    /// Message for a failed restore, saying what became of the database when it was aborted
+    pub(crate) fn restore_error(&self, restore: &str, error: &anyhow::Error) -> String {
+        debug!("Describing failed restore: {}", error);
+        if !crate::cancel::is_cancelled(error) {
+            return format!("{} failed: {}", restore, error);
+        }
+        match self.restore_target {
+            RestoreTarget::Postgres if self.restore_options.drop_on_cancel && self.restore_options.mode != crate::presets::RestoreMode::Clean => {
+                format!("{} aborted, the partially restored database was dropped", restore)
+            }
+            RestoreTarget::Postgres => format!("{} aborted, the partially restored database was left in place", restore),
+            _ => format!("{} aborted", restore),
+        }
+    }
+
+    /// Look up the current state of the restore target for the restore confirmation
+    ///
+    /// A failed lookup is logged rather than returned; the restore itself
+    /// reports an unreachable target with more context.
+    pub async fn load_restore_impact(&mut self) {
+        debug!("Loading restore impact for {:?}", self.restore_target);
+        self.restore_impact = match self.get_current_restore_target().impact().await {
+            Ok(impact) => match &self.popup_state {
+                PopupState::ConfirmRestore(snapshot) => impact.map(|impact| impact.for_snapshot(&snapshot.key)),
+                _ => impact,
+            },
+            Err(e) => {
+                debug!("Failed to look up restore impact: {}", e);
+                None
+            }
+        };
+    }
+
+    /// Compare a restored PostgreSQL database with the live one, if the restore options ask for it
+    ///
+    /// The live database is the one the snapshot was taken from, derived from
+    /// the snapshot key (see `source_db_from_key`).
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The restored snapshot
+    /// * `restored` - Name of the restored database
+    ///
+    /// # Returns
+    ///
+    /// The summary, or `None` when no comparison was requested or the target is not PostgreSQL
+    pub async fn change_summary(&self, snapshot: &BackupMetadata, restored: &str) -> Result<Option<crate::compare::ChangeSummary>> {
+        debug!("Checking whether to compare {} with the live database", restored);
+        if !self.restore_options.compare_live || self.restore_target != RestoreTarget::Postgres {
+            return Ok(None);
+        }
+        let live_db = crate::hooks::source_db_from_key(&snapshot.key);
+        crate::compare::compare_with_live(&self.pg_config, &live_db, restored).await.map(Some)
+    }
+
+    /// Promote a restored PostgreSQL database inside the maintenance window
+    ///
+    /// Renaming the live database disconnects its sessions, so the
+    /// maintenance hooks run around the promotion as they do around a restore.
+    /// The promotion is then recorded in the bucket so it can be rolled back.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - The confirmed promotion
+    pub async fn promote(&mut self, plan: &PromotePlan) -> Result<()> {
+        debug!("Promoting {} to {}", plan.restored_db, plan.live_db);
+        self.maintenance_hooks
+            .run(&plan.live_db, crate::promote::promote(&self.pg_config, plan))
+            .await?;
+        crate::promote::record_promotion(&mut self.snapshot_browser, &self.pg_config.server(), plan, chrono::Utc::now())
+            .await
+            .map_err(|e| anyhow!("Promoted {}, but recording the promotion for rollback failed: {}", plan.live_db, e))
+    }
 }
@@ -0,0 +1,87 @@
+// This module contains the server picker of the Rustored TUI
+// When the connection profile lists several PostgreSQL servers, choosing a
+// snapshot to restore onto PostgreSQL first asks which server to restore it
+// onto, and `s` in the restore confirmation asks again. Picking a server copies
+// its settings into the PostgreSQL panel and its environment into the banner,
+// so the production guard follows the server the restore goes to.
+
+use crate::profiles::TargetServer;
+use crate::ui::models::{PopupState, RestoreTarget};
+use crate::ui::rustored::RustoredApp;
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+
+/// Ask which server the snapshot being confirmed is restored onto
+///
+/// Does nothing unless a PostgreSQL restore is being confirmed and the profile lists servers.
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+pub fn open(app: &mut RustoredApp) {
+    debug!("Opening server picker");
+    let PopupState::ConfirmRestore(snapshot) = &app.popup_state else {
+        return;
+    };
+    if app.restore_target != RestoreTarget::Postgres || app.pg_servers.is_empty() {
+        return;
+    }
+    // Start on the server picked last
+    let selected = app.pg_server
+        .as_ref()
+        .and_then(|name| app.pg_servers.iter().position(|server| &server.name == name))
+        .unwrap_or(0);
+    app.popup_state = PopupState::SelectServer(snapshot.clone(), selected);
+}
+
+/// Restore onto a server from now on
+///
+/// Settings the server leaves out keep their current values.
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `server` - The picked server
+pub fn apply(app: &mut RustoredApp, server: &TargetServer) {
+    debug!("Restoring onto server {} at {}", server.name, server.host);
+    app.pg_config.host = Some(server.host.clone());
+    app.pg_config.port = server.port.or(app.pg_config.port);
+    app.pg_config.username = server.username.clone().or_else(|| app.pg_config.username.clone());
+    app.pg_config.use_ssl = server.use_ssl.unwrap_or(app.pg_config.use_ssl);
+    app.pg_config.target_db = server.target_db.clone();
+    app.environment = server.environment.clone();
+    app.pg_server = Some(server.name.clone());
+}
+
+/// Handle a key in the server picker
+///
+/// Enter restores onto the highlighted server and Esc keeps the current
+/// settings; both go on to the restore confirmation.
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub async fn handle_key(app: &mut RustoredApp, key: KeyEvent) {
+    debug!("Handling server picker key: {:?}", key);
+    let PopupState::SelectServer(snapshot, selected) = &mut app.popup_state else {
+        return;
+    };
+    match key.code {
+        KeyCode::Up => *selected = selected.saturating_sub(1),
+        KeyCode::Down => *selected = (*selected + 1).min(app.pg_servers.len().saturating_sub(1)),
+        KeyCode::Enter | KeyCode::Esc => {
+            let (snapshot, selected) = (snapshot.clone(), *selected);
+            if key.code == KeyCode::Enter {
+                if let Some(server) = app.pg_servers.get(selected).cloned() {
+                    apply(app, &server);
+                }
+            }
+            app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+            // Show how the snapshot is encrypted and what the restore will change on this server
+            app.snapshot_browser.load_encryption(&snapshot.key).await;
+            app.load_restore_impact().await;
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,89 @@
+// This module contains the table picker of the Rustored TUI
+// From the restore confirmation of a PostgreSQL snapshot, `t` downloads the
+// snapshot, reads its table of contents and lists its schemas and tables to
+// tick. The ticked tables become the restore's include patterns, and the
+// download is kept so confirming the restore does not fetch it again.
+
+use crate::toc::{TableSelection, Toc};
+use crate::ui::models::{BackupMetadata, PopupState};
+use crate::ui::rustored::RustoredApp;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use log::debug;
+
+/// Download a snapshot if needed and show its tables to pick from
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `snapshot` - The snapshot being confirmed
+pub async fn open(app: &mut RustoredApp, snapshot: BackupMetadata) {
+    debug!("Opening table picker for snapshot: {}", snapshot.key);
+    app.popup_state = match read_tables(app, &snapshot).await {
+        Ok(selection) => PopupState::SelectTables(snapshot, selection),
+        Err(e) if crate::cancel::is_cancelled(&e) => PopupState::ConfirmRestore(snapshot),
+        Err(e) => PopupState::Error(format!("Reading the tables of {} failed: {}", snapshot.key, e)),
+    };
+}
+
+/// Download a snapshot being confirmed, unless it already is, and keep it for the restore
+///
+/// # Returns
+///
+/// Where the snapshot was downloaded to
+pub(crate) async fn download_once(app: &mut RustoredApp, snapshot: &BackupMetadata) -> Result<String> {
+    debug!("Downloading snapshot for its table of contents: {}", snapshot.key);
+    if let Some(path) = app.downloaded_snapshot(snapshot) {
+        return Ok(path);
+    }
+    let tmp_path = crate::download_ledger::temp_download_path(&snapshot.key);
+    let operation = app.begin_operation();
+    let download = app.snapshot_browser.download_snapshot(snapshot, &tmp_path);
+    let path = crate::ui::app::cancel_on_escape(&operation, download)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("the snapshot could not be downloaded"))?;
+    app.downloaded = Some((snapshot.clone(), path.clone()));
+    Ok(path)
+}
+
+async fn read_tables(app: &mut RustoredApp, snapshot: &BackupMetadata) -> Result<TableSelection> {
+    debug!("Reading tables of snapshot: {}", snapshot.key);
+    let path = download_once(app, snapshot).await?;
+    let input = crate::backup::restore_input(&path).await?;
+    let toc = Toc::read(&input.path).await?;
+    let options = &app.restore_options;
+    Ok(TableSelection::new(&path, &toc.relations(), &options.include_tables, &options.exclude_tables))
+}
+
+/// Handle a key in the table picker
+///
+/// # Arguments
+///
+/// * `app` - A mutable reference to the RustoredApp
+/// * `key` - The key event to process
+pub fn handle_key(app: &mut RustoredApp, key: KeyEvent) {
+    debug!("Handling table picker key: {:?}", key);
+    let PopupState::SelectTables(snapshot, selection) = &mut app.popup_state else {
+        return;
+    };
+    match key.code {
+        KeyCode::Up => selection.move_cursor(-1),
+        KeyCode::Down => selection.move_cursor(1),
+        KeyCode::PageUp => selection.move_cursor(-10),
+        KeyCode::PageDown => selection.move_cursor(10),
+        KeyCode::Char(' ') => selection.toggle(),
+        KeyCode::Char('a') => selection.toggle_all(),
+        KeyCode::Enter => {
+            if selection.counts().0 == 0 {
+                return;
+            }
+            app.restore_options.include_tables = selection.include_patterns();
+            app.restore_options.exclude_tables.clear();
+            app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+        }
+        KeyCode::Esc => {
+            app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+        }
+        _ => {}
+    }
+}
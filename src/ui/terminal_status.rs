@@ -0,0 +1,128 @@
+// This module contains the terminal title and tmux status integration for the Rustored TUI
+// Restores and downloads can run for hours, and the pane running them is often
+// in the background. The terminal title, and optionally a tmux window option,
+// show the running operation and its progress, e.g. "rustored: restoring
+// orders 42%", so it can be followed from the tab or status bar.
+//
+// The title is written from the renderer, just before a frame is flushed, so
+// its escape sequence can never land in the middle of the frame's output.
+
+use crossterm::{execute, terminal::SetTitle};
+use log::{debug, warn};
+use std::io::Write;
+use std::sync::Mutex;
+use crate::progress::Progress;
+
+/// tmux window option holding the status, for use as `#{@rustored_status}` in a status format
+pub const TMUX_STATUS_OPTION: &str = "@rustored_status";
+
+/// Title shown while no operation is running
+const IDLE_TITLE: &str = "rustored";
+
+/// Describe a progress job for the title, e.g. `restoring orders` for `Restore of backups/orders-20250101T000000Z.dump`
+pub fn describe_job(job: &str) -> String {
+    debug!("Describing job {} for the terminal title", job);
+    let Some((action, subject)) = job.split_once(" of ") else {
+        return job.to_lowercase();
+    };
+    let verb = match action {
+        "Restore" => "restoring",
+        "Download" => "downloading",
+        "Backup" => "backing up",
+        "Dump" => "dumping",
+        "Upload" => "uploading",
+        other => return format!("{} {}", other.to_lowercase(), subject),
+    };
+    match subject.strip_prefix("bundle ") {
+        Some(key) => format!("{} bundle {}", verb, crate::hooks::source_db_from_key(key)),
+        None => format!("{} {}", verb, crate::hooks::source_db_from_key(subject)),
+    }
+}
+
+/// Title for the running jobs: the first job and its progress, and how many others are running
+pub fn status_title(jobs: &[(String, Progress)]) -> String {
+    debug!("Building terminal title for {} jobs", jobs.len());
+    let Some((job, progress)) = jobs.first() else {
+        return IDLE_TITLE.to_string();
+    };
+    let percent = (progress.fraction * 100.0).round() as u32;
+    let mut title = format!("{}: {} {}%", IDLE_TITLE, describe_job(job), percent);
+    if jobs.len() > 1 {
+        title.push_str(&format!(" (+{} more)", jobs.len() - 1));
+    }
+    title
+}
+
+/// Writes the status to the terminal title, and to tmux when enabled
+#[derive(Debug)]
+pub struct TerminalStatus {
+    /// Also set `@rustored_status` on the tmux window running the TUI
+    tmux: bool,
+    /// Last title written, so unchanged titles are not rewritten every frame
+    last: Mutex<Option<String>>,
+}
+
+impl TerminalStatus {
+    /// Create the status writer; tmux is only updated when running inside tmux
+    pub fn new(tmux: bool) -> Self {
+        debug!("Creating terminal status with tmux {}", tmux);
+        let tmux = tmux && std::env::var_os("TMUX").is_some();
+        Self { tmux, last: Mutex::new(None) }
+    }
+
+    /// Save the terminal's current title so `end` can put it back
+    pub fn begin(&self) {
+        debug!("Saving the terminal title");
+        // XTWINOPS push title; terminals without a title stack ignore it
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(b"\x1b[22;0t").and_then(|_| stdout.flush());
+    }
+
+    /// Show the progress of the running jobs, if the title changed
+    pub fn update(&self, jobs: &[(String, Progress)]) {
+        let title = status_title(jobs);
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        if last.as_deref() == Some(title.as_str()) {
+            return;
+        }
+        debug!("Updating terminal title to {}", title);
+        if let Err(e) = execute!(std::io::stdout(), SetTitle(&title)) {
+            warn!("Failed to set the terminal title: {}", e);
+        }
+        if self.tmux {
+            set_tmux_status(Some(&title));
+        }
+        *last = Some(title);
+    }
+
+    /// Restore the title saved by `begin` and clear the tmux status
+    pub fn end(&self) {
+        debug!("Restoring the terminal title");
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(b"\x1b[23;0t").and_then(|_| stdout.flush());
+        if self.tmux {
+            set_tmux_status(None);
+        }
+    }
+}
+
+/// Set or unset the status option on the tmux window of this pane
+///
+/// tmux runs on its own thread so a slow server never stalls a frame.
+fn set_tmux_status(status: Option<&str>) {
+    debug!("Setting tmux status to {:?}", status);
+    let mut cmd = std::process::Command::new("tmux");
+    cmd.args(["set-option", "-q", "-w"]);
+    if let Some(pane) = std::env::var_os("TMUX_PANE") {
+        cmd.arg("-t").arg(pane);
+    }
+    match status {
+        Some(status) => cmd.args([TMUX_STATUS_OPTION, status]),
+        None => cmd.args(["-u", TMUX_STATUS_OPTION]),
+    };
+    std::thread::spawn(move || {
+        if let Err(e) = cmd.stdin(std::process::Stdio::null()).stdout(std::process::Stdio::null()).stderr(std::process::Stdio::null()).status() {
+            warn!("Failed to set the tmux status: {}", e);
+        }
+    });
+}
@@ -0,0 +1,377 @@
+// This module contains the snapshot verification cache for the Rustored application
+// Verifying a snapshot downloads it, checksums the whole file and, for
+// PostgreSQL custom-format dumps, checks that `pg_restore --list` can read the
+// archive. The checksum is compared with the one in the snapshot's backup
+// manifest when it has one. Results are cached in a JSON catalog object in the
+// snapshot bucket so every rustored instance can show which restore points are
+// known-good without downloading them again. A quick check reads only the head
+// of the snapshot, for CI runs that cannot download whole dumps.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use tokio::io::AsyncReadExt;
+use crate::table::{Align, Cell, Table};
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::BackupMetadata;
+
+/// Key of the verification catalog object in the snapshot bucket
+pub const VERIFICATION_CATALOG_KEY: &str = ".rustored/verifications.json";
+
+/// Bytes read from the start of a snapshot by a quick check, enough for the table of contents of most archives
+pub const QUICK_VERIFY_BYTES: u64 = 1024 * 1024;
+
+/// Magic bytes at the start of a PostgreSQL custom-format archive
+const PG_ARCHIVE_MAGIC: &[u8] = b"PGDMP";
+
+/// Cached result of verifying one snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verification {
+    /// Size of the snapshot when it was verified
+    pub size: i64,
+    /// Last modified timestamp of the snapshot, used to detect a replaced object
+    pub last_modified: f64,
+    /// Hex encoded SHA-256 checksum from the backup manifest, or recorded the first time the snapshot was verified
+    pub sha256: String,
+    /// Whether the downloaded snapshot matched `sha256`
+    pub checksum_ok: bool,
+    /// Whether `sha256` came from the snapshot's backup manifest
+    #[serde(default)]
+    pub manifest_checked: bool,
+    /// Whether `pg_restore --list` could read the archive, `None` for snapshots that are not PostgreSQL dumps
+    pub archive_ok: Option<bool>,
+    /// When the snapshot was last verified
+    pub verified_at: DateTime<Utc>,
+}
+
+impl Verification {
+    /// Whether every check that ran passed
+    pub fn is_ok(&self) -> bool {
+        debug!("Checking if verification passed");
+        self.checksum_ok && self.archive_ok != Some(false)
+    }
+
+    /// Whether this result still describes a snapshot (same size and last modified time)
+    pub fn matches(&self, snapshot: &BackupMetadata) -> bool {
+        debug!("Checking if verification matches {}", snapshot.key);
+        self.size == snapshot.size && self.last_modified == snapshot.last_modified
+    }
+
+    /// The checks that ran and their results, e.g. `checksum matches manifest`
+    pub fn checks(&self) -> Vec<String> {
+        debug!("Describing verification checks");
+        let source = if self.manifest_checked { "manifest" } else { "first verification" };
+        let mut checks = vec![match self.checksum_ok {
+            true => format!("checksum matches {}", source),
+            false => format!("checksum differs from {}", source),
+        }];
+        checks.extend(self.archive_ok.map(|ok| match ok {
+            true => "pg_restore --list ok".to_string(),
+            false => "pg_restore --list failed".to_string(),
+        }));
+        checks
+    }
+}
+
+/// Pass/fail result of verifying one snapshot, as printed by `verify`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub key: String,
+    pub passed: bool,
+    /// Whether only the head of the snapshot was read
+    pub quick: bool,
+    /// The checks that ran and their results
+    pub checks: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Report of a full verification
+    pub fn from_verification(key: &str, verification: &Verification) -> Self {
+        debug!("Building verify report for {}", key);
+        Self { key: key.to_string(), passed: verification.is_ok(), quick: false, checks: verification.checks() }
+    }
+
+    /// Report of a snapshot that could not be verified at all
+    pub fn from_error(key: &str, error: &anyhow::Error) -> Self {
+        debug!("Building failed verify report for {}", key);
+        Self { key: key.to_string(), passed: false, quick: false, checks: vec![format!("could not verify: {}", error)] }
+    }
+
+    /// Line for the CI log, e.g. `PASS postgres/orders.dump: checksum matches manifest, pg_restore --list ok`
+    pub fn line(&self) -> String {
+        debug!("Formatting verify report line for {}", self.key);
+        let status = if self.passed { "PASS" } else { "FAIL" };
+        format!("{} {}: {}", status, self.key, self.checks.join(", "))
+    }
+}
+
+/// Summary line of a `verify` run, e.g. `3 snapshots verified: 2 passed, 1 failed`
+pub fn summarize(reports: &[VerifyReport]) -> String {
+    debug!("Summarizing {} verify reports", reports.len());
+    let passed = reports.iter().filter(|report| report.passed).count();
+    format!("{} snapshots verified: {} passed, {} failed", reports.len(), passed, reports.len() - passed)
+}
+
+/// The verify reports as a table, one row per snapshot
+pub fn table(reports: &[VerifyReport]) -> Table {
+    debug!("Tabulating {} verify reports", reports.len());
+    let mut table = Table::new(&[("RESULT", Align::Left), ("KEY", Align::Left), ("CHECKS", Align::Left)]);
+    for report in reports {
+        let result = match report.passed {
+            true => Cell::colored("pass", crossterm::style::Color::Green),
+            false => Cell::colored("fail", crossterm::style::Color::Red),
+        };
+        table.row(vec![result, report.key.as_str().into(), report.checks.join(", ").into()]);
+    }
+    table
+}
+
+/// Catalog of verification results for a bucket, keyed by snapshot key
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerificationCatalog {
+    pub snapshots: BTreeMap<String, Verification>,
+}
+
+impl VerificationCatalog {
+    /// Parse a catalog from its JSON form
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing verification catalog ({} bytes)", bytes.len());
+        crate::migration::from_json(crate::migration::MetadataKind::Verifications, bytes)
+    }
+
+    /// Serialize the catalog to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        debug!("Serializing verification catalog with {} snapshots", self.snapshots.len());
+        crate::migration::to_json(self)
+    }
+
+    /// Cached result for a snapshot, ignoring results for a replaced object
+    pub fn get(&self, snapshot: &BackupMetadata) -> Option<&Verification> {
+        debug!("Looking up verification of {}", snapshot.key);
+        self.snapshots.get(&snapshot.key).filter(|v| v.matches(snapshot))
+    }
+
+    /// Record the result of verifying a snapshot
+    ///
+    /// The checksum in the snapshot's backup manifest is the expected
+    /// checksum. Without a manifest, the checksum from the first verification
+    /// of an object is kept as the expected checksum, so a later download that
+    /// differs fails the check.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The verified snapshot
+    /// * `sha256` - Checksum of the downloaded file
+    /// * `manifest_sha256` - Checksum from the snapshot's backup manifest, if it has one
+    /// * `archive_ok` - Result of the archive check, if one ran
+    /// * `now` - Time of the verification
+    ///
+    /// # Returns
+    ///
+    /// The recorded verification
+    pub fn record(
+        &mut self,
+        snapshot: &BackupMetadata,
+        sha256: &str,
+        manifest_sha256: Option<&str>,
+        archive_ok: Option<bool>,
+        now: DateTime<Utc>,
+    ) -> &Verification {
+        debug!("Recording verification of {}", snapshot.key);
+        let expected = match manifest_sha256 {
+            Some(manifest_sha256) => manifest_sha256.to_string(),
+            None => self.get(snapshot).map(|v| v.sha256.clone()).unwrap_or_else(|| sha256.to_string()),
+        };
+        let verification = Verification {
+            size: snapshot.size,
+            last_modified: snapshot.last_modified,
+            checksum_ok: expected == sha256,
+            manifest_checked: manifest_sha256.is_some(),
+            sha256: expected,
+            archive_ok,
+            verified_at: now,
+        };
+        if !verification.is_ok() {
+            warn!("Snapshot {} failed verification: {:?}", snapshot.key, verification);
+        }
+        self.snapshots.insert(snapshot.key.clone(), verification);
+        &self.snapshots[&snapshot.key]
+    }
+
+    /// Short badge for the snapshot list, e.g. `OK 2025-03-01`
+    pub fn badge(&self, snapshot: &BackupMetadata) -> String {
+        debug!("Building verification badge for {}", snapshot.key);
+        match self.get(snapshot) {
+            Some(v) if v.is_ok() => format!("OK {}", v.verified_at.format("%Y-%m-%d")),
+            Some(v) => format!("FAILED {}", v.verified_at.format("%Y-%m-%d")),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// Whether a snapshot is a PostgreSQL custom-format dump that `pg_restore --list` can check
+pub fn is_pg_archive(key: &str) -> bool {
+    debug!("Checking if {} is a PostgreSQL archive", key);
+    key.ends_with(".dump")
+}
+
+/// Hex encoded SHA-256 checksum of a whole file
+pub fn file_checksum(path: &Path) -> Result<String> {
+    debug!("Computing checksum of {:?}", path);
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 1024 * 64]; // 64KB buffer
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[0..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Check that `pg_restore --list` can read the table of contents of an archive
+///
+/// Packed directory-format dumps are unpacked and their directory is listed. Base backups are
+/// checked by listing their `base.tar.gz` instead.
+pub fn check_pg_archive(path: &Path) -> Result<()> {
+    debug!("Listing archive {:?} with pg_restore", path);
+    let unpacked = match crate::directory_dump::is_tar_file(path)? {
+        true => Some(crate::directory_dump::unpack_blocking(path)?),
+        false => None,
+    };
+    // A base backup holds the cluster's files rather than a pg_restore archive
+    if let Some(dir) = unpacked.as_ref().filter(|dir| crate::wal::is_base_backup_dir(dir.path())) {
+        let output = Command::new("tar").arg("--list").arg("--gzip").arg("--file").arg(dir.path().join("base.tar.gz"))
+            .output()
+            .context("Failed to execute tar")?;
+        if !output.status.success() {
+            return Err(anyhow!("base backup is unreadable: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        return Ok(());
+    }
+    let output = Command::new("pg_restore")
+        .arg("--list")
+        .arg(unpacked.as_ref().map_or(path, |dir| dir.path()))
+        .output()
+        .context("Failed to execute pg_restore")?;
+    if !output.status.success() {
+        return Err(anyhow!("pg_restore --list failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Download a snapshot, verify it and cache the result in the bucket's catalog
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket
+/// * `snapshot` - The snapshot to verify
+/// * `now` - Time recorded for the verification
+///
+/// # Returns
+///
+/// The recorded verification. Failed checks are recorded, not returned as errors;
+/// an error means the snapshot could not be verified at all.
+pub async fn verify_snapshot(
+    browser: &mut SnapshotBrowser,
+    snapshot: &BackupMetadata,
+    now: DateTime<Utc>,
+) -> Result<Verification> {
+    debug!("Verifying snapshot {}", snapshot.key);
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("snapshot");
+    browser.fetch_snapshot(snapshot, &path).await?
+        .ok_or_else(|| anyhow!("Download of {} failed: {:?}", snapshot.key, browser.popup_state))?;
+
+    let sha256 = file_checksum(&path)?;
+    // Manifests are loaded with the snapshot list
+    let manifest = browser.manifests.get(&snapshot.key).cloned();
+    let plain_key = crate::encryption::plain_key(&snapshot.key);
+    let archive_ok = if is_pg_archive(plain_key) || plain_key.ends_with(crate::directory_dump::DIRECTORY_EXTENSION) {
+        // The checksum covers the stored ciphertext, the archive check its plaintext
+        let decrypted = browser.encryption.decrypt_file(&path).await?;
+        let result = check_pg_archive(decrypted.as_ref().map_or(path.as_path(), |file| file.path()));
+        if let Err(e) = &result {
+            warn!("Archive check of {} failed: {}", snapshot.key, e);
+        }
+        Some(result.is_ok())
+    } else {
+        None
+    };
+
+    // Re-read the catalog so results recorded by other instances are kept
+    browser.load_verifications().await?;
+    let manifest_sha256 = manifest.as_ref().map(|m| m.sha256.as_str());
+    let verification = browser.verifications.record(snapshot, &sha256, manifest_sha256, archive_ok, now).clone();
+    browser.save_verifications().await?;
+    info!("Verified {}: {}", snapshot.key, browser.verifications.badge(snapshot));
+    Ok(verification)
+}
+
+/// Check a snapshot by reading only its head, without downloading it
+///
+/// The size is compared with the backup manifest, and the head of a
+/// PostgreSQL dump must start with the custom-format magic and hold a table of
+/// contents that `pg_restore --list` can read. The checksum is not checked and
+/// the result is not cached in the catalog.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket
+/// * `snapshot` - The snapshot to check
+///
+/// # Returns
+///
+/// The report of the checks; an error means the snapshot could not be read at all
+pub async fn quick_verify_snapshot(browser: &mut SnapshotBrowser, snapshot: &BackupMetadata) -> Result<VerifyReport> {
+    debug!("Quick verifying snapshot {}", snapshot.key);
+    let mut checks = Vec::new();
+    let mut passed = true;
+    match browser.manifests.get(&snapshot.key).cloned() {
+        Some(manifest) if manifest.size == snapshot.size as u64 => checks.push("size matches manifest".to_string()),
+        Some(manifest) => {
+            passed = false;
+            checks.push(format!("size differs from manifest ({} bytes, expected {})", snapshot.size, manifest.size));
+        }
+        None => {}
+    }
+
+    if is_pg_archive(&snapshot.key) && snapshot.size > 0 {
+        let store = browser.store().await?;
+        let end = QUICK_VERIFY_BYTES.min(snapshot.size as u64) - 1;
+        let mut head = Vec::new();
+        store.read_range(&snapshot.key, 0, end).await?.read_to_end(&mut head).await
+            .with_context(|| format!("Failed to read the head of {}", snapshot.key))?;
+        if head.starts_with(PG_ARCHIVE_MAGIC) {
+            checks.push("custom-format header ok".to_string());
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("head.dump");
+            std::fs::write(&path, &head)?;
+            match check_pg_archive(&path) {
+                Ok(()) => checks.push("pg_restore --list ok".to_string()),
+                Err(e) => {
+                    passed = false;
+                    warn!("Archive check of the head of {} failed: {}", snapshot.key, e);
+                    checks.push("pg_restore --list failed".to_string());
+                }
+            }
+        } else {
+            passed = false;
+            checks.push("not a pg_dump custom-format archive".to_string());
+        }
+    }
+    if checks.is_empty() {
+        checks.push("no manifest or archive to check".to_string());
+    }
+    info!("Quick verified {}: {}", snapshot.key, if passed { "passed" } else { "failed" });
+    Ok(VerifyReport { key: snapshot.key.clone(), passed, quick: true, checks })
+}
@@ -0,0 +1,394 @@
+// This module contains WAL archiving for the Rustored application
+// A logical dump copies every row each time it runs. With WAL archiving the
+// server hands each finished write-ahead log segment to `rustored wal push`
+// (its `archive_command`), so only the changes since the last base backup are
+// uploaded, and `rustored wal fetch` (its `restore_command`) reads them back
+// during recovery. Base backups are taken with pg_basebackup and stored under
+// `<prefix>/base/`, segments under `<prefix>/wal/`. The snapshot browser groups
+// the segments with the base backup they follow instead of listing each one.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Stdio;
+use crate::compression::Compression;
+use crate::manifest::Manifest;
+use crate::ui::browser::SnapshotBrowser;
+use crate::ui::models::{BackupMetadata, PostgresConfig};
+
+/// Directory under the prefix holding archived WAL files
+pub const WAL_DIR: &str = "wal";
+
+/// Directory under the prefix holding base backups
+pub const BASE_DIR: &str = "base";
+
+/// Extension of a base backup, a tar of pg_basebackup's tar-format output
+pub const BASE_EXTENSION: &str = ".base.tar";
+
+/// Size of a WAL segment, as in a cluster initialised with the default `--wal-segsize`
+pub const WAL_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Number of segments in each 4 GB of WAL, the last part of a segment name
+const SEGMENTS_PER_ID: u64 = 0x1_0000_0000 / WAL_SEGMENT_SIZE;
+
+/// Name of the database recorded in base backup manifests, which hold the whole cluster
+const BASE_NAME: &str = "cluster";
+
+/// Key of an archived WAL file, e.g. `postgres/wal/000000010000000000000003`
+pub fn wal_key(prefix: &str, file_name: &str) -> String {
+    debug!("Building WAL key of {} under {}", file_name, prefix);
+    crate::backup::join_prefix(prefix, format!("{}/{}", WAL_DIR, file_name))
+}
+
+/// Whether an object key names an archived WAL file rather than a snapshot
+pub fn is_wal_key(key: &str) -> bool {
+    debug!("Checking whether {} is an archived WAL file", key);
+    key.rsplit('/').nth(1) == Some(WAL_DIR)
+}
+
+/// Key of a base backup whose WAL starts in `start_segment`
+///
+/// The start segment is part of the key so WAL can be grouped with the base
+/// backup without reading anything but the listing.
+pub fn base_key(prefix: &str, timestamp: DateTime<Utc>, start_segment: &str) -> String {
+    debug!("Building base backup key under {} starting at {}", prefix, start_segment);
+    let file_name = format!("{}-{}{}", timestamp.format("%Y%m%dT%H%M%SZ"), start_segment, BASE_EXTENSION);
+    crate::backup::join_prefix(prefix, format!("{}/{}", BASE_DIR, file_name))
+}
+
+/// WAL segment a base backup starts in, if the key names a base backup
+pub fn base_start_segment(key: &str) -> Option<&str> {
+    debug!("Reading start segment of base backup {}", key);
+    let key = crate::encryption::plain_key(key);
+    if key.rsplit('/').nth(1) != Some(BASE_DIR) {
+        return None;
+    }
+    let name = key.rsplit('/').next()?.strip_suffix(BASE_EXTENSION)?;
+    let segment = name.rsplit('-').next()?;
+    parse_segment(segment).map(|_| segment)
+}
+
+/// Name of the WAL segment holding `lsn` on `timeline`, e.g. `000000010000000000000003`
+pub fn segment_name(timeline: u32, lsn: u64) -> String {
+    debug!("Building WAL segment name for {:X} on timeline {}", lsn, timeline);
+    let segment = lsn / WAL_SEGMENT_SIZE;
+    format!("{:08X}{:08X}{:08X}", timeline, segment / SEGMENTS_PER_ID, segment % SEGMENTS_PER_ID)
+}
+
+/// Timeline and number of a WAL segment from its name
+///
+/// Timeline history, backup history and partial files are not segments and give `None`.
+pub fn parse_segment(name: &str) -> Option<(u32, u64)> {
+    if name.len() != 24 || !name.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let timeline = u32::from_str_radix(&name[0..8], 16).ok()?;
+    let high = u64::from_str_radix(&name[8..16], 16).ok()?;
+    let low = u64::from_str_radix(&name[16..24], 16).ok()?;
+    (low < SEGMENTS_PER_ID).then_some((timeline, high * SEGMENTS_PER_ID + low))
+}
+
+/// Name of an archived WAL file, without the compression and encryption extensions added on push
+pub fn archived_name(key: &str) -> &str {
+    let name = crate::encryption::plain_key(key).rsplit('/').next().unwrap_or(key);
+    [".gz", ".zst", ".lz4"].iter().find_map(|extension| name.strip_suffix(extension)).unwrap_or(name)
+}
+
+/// A base backup and the WAL segments archived after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalChain {
+    pub base: BackupMetadata,
+    /// Segments from the base backup's start up to the next base backup, oldest first
+    pub segments: Vec<BackupMetadata>,
+    /// Names of segments missing between the start and the newest segment
+    pub missing: Vec<String>,
+}
+
+impl WalChain {
+    /// Total size of the base backup and its segments in bytes
+    pub fn size(&self) -> i64 {
+        self.base.size + self.segments.iter().map(|segment| segment.size).sum::<i64>()
+    }
+
+    /// Newest segment recovery can replay, the end of the usable chain
+    ///
+    /// Replay stops at the first missing segment, so with a gap this is the segment before it.
+    pub fn last_segment(&self) -> Option<&str> {
+        debug!("Finding last usable segment after {}", self.base.key);
+        let first_missing = self.missing.first().and_then(|name| parse_segment(name)).map(|(_, number)| number);
+        self.segments
+            .iter()
+            .map(|segment| archived_name(&segment.key))
+            .rfind(|name| match (parse_segment(name), first_missing) {
+                (Some((_, number)), Some(missing)) => number < missing,
+                (_, _) => true,
+            })
+    }
+
+    /// Short description for the snapshot list, e.g. `base + 12 WAL (201 MB)`
+    pub fn summary(&self) -> String {
+        debug!("Summarising WAL chain of {}", self.base.key);
+        let size = humansize::format_size(self.size().max(0) as u64, humansize::DECIMAL);
+        match self.missing.len() {
+            0 => format!("base + {} WAL ({})", self.segments.len(), size),
+            missing => format!("base + {} WAL ({}), {} missing", self.segments.len(), size, missing),
+        }
+    }
+}
+
+/// Group archived WAL segments with the base backups they follow
+///
+/// Each segment belongs to the newest base backup under the same prefix that
+/// starts at or before it. Segments older than every base backup cannot be
+/// replayed and are left out.
+///
+/// # Returns
+///
+/// The chains keyed by the base backup's key
+pub fn group_chains(objects: &[BackupMetadata]) -> BTreeMap<String, WalChain> {
+    debug!("Grouping WAL chains among {} objects", objects.len());
+    // Base backups and their WAL share the part of the key before `base/` or `wal/`
+    let root = |key: &str| key.rsplitn(3, '/').nth(2).unwrap_or("").to_string();
+    let mut bases: BTreeMap<String, Vec<(u64, &BackupMetadata)>> = BTreeMap::new();
+    for object in objects {
+        if let Some((_, number)) = base_start_segment(&object.key).and_then(parse_segment) {
+            bases.entry(root(&object.key)).or_default().push((number, object));
+        }
+    }
+    let mut chains: BTreeMap<String, (u64, WalChain)> = BTreeMap::new();
+    for list in bases.values_mut() {
+        list.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.key.cmp(&b.1.key)));
+        for (number, base) in list.iter() {
+            let chain = WalChain { base: (*base).clone(), segments: Vec::new(), missing: Vec::new() };
+            chains.insert(base.key.clone(), (*number, chain));
+        }
+    }
+
+    let mut segments: Vec<(u64, u32, &BackupMetadata)> = objects
+        .iter()
+        .filter(|object| is_wal_key(&object.key))
+        .filter_map(|object| parse_segment(archived_name(&object.key)).map(|(timeline, number)| (number, timeline, object)))
+        .collect();
+    segments.sort_by_key(|(number, timeline, _)| (*number, *timeline));
+    for (number, _, segment) in segments {
+        let Some(list) = bases.get(&root(&segment.key)) else { continue };
+        let Some((_, base)) = list.iter().rev().find(|(start, _)| *start <= number) else { continue };
+        if let Some((_, chain)) = chains.get_mut(&base.key) {
+            chain.segments.push(segment.clone());
+        }
+    }
+
+    chains
+        .into_values()
+        .map(|(start, mut chain)| {
+            let timeline = base_start_segment(&chain.base.key).and_then(parse_segment).map_or(1, |(timeline, _)| timeline);
+            let numbers: Vec<u64> = chain.segments.iter().filter_map(|segment| parse_segment(archived_name(&segment.key))).map(|(_, number)| number).collect();
+            if let Some(&newest) = numbers.last() {
+                chain.missing = (start..=newest)
+                    .filter(|number| numbers.binary_search(number).is_err())
+                    .map(|number| segment_name(timeline, number * WAL_SEGMENT_SIZE))
+                    .collect();
+            }
+            (chain.base.key.clone(), chain)
+        })
+        .collect()
+}
+
+/// Find the archived copy of a WAL file, whatever extensions it was stored with
+async fn find_archived(browser: &mut SnapshotBrowser, file_name: &str) -> Result<Option<BackupMetadata>> {
+    debug!("Looking up archived WAL file {}", file_name);
+    let store = browser.store().await?;
+    let key = wal_key(browser.prefix(), file_name);
+    Ok(store.list_snapshots(&key).await?.into_iter().find(|object| archived_name(&object.key) == file_name))
+}
+
+/// Reject file names that are not a single path component
+fn check_file_name(file_name: &str) -> Result<()> {
+    if file_name.is_empty() || file_name.contains('/') || file_name == "." || file_name == ".." {
+        return Err(anyhow!("Invalid WAL file name '{}'", file_name));
+    }
+    Ok(())
+}
+
+/// Archive a WAL file, as PostgreSQL's `archive_command`
+///
+/// A file that is already archived with the same contents is accepted again,
+/// since the server retries a push whose success it did not see. One with
+/// different contents is refused rather than overwritten.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket
+/// * `path` - The WAL file to archive, `%p` in `archive_command`
+/// * `compression` - Codec to compress the file with before upload
+///
+/// # Returns
+///
+/// The key the file is archived under
+pub async fn push(browser: &mut SnapshotBrowser, path: &Path, compression: Option<Compression>) -> Result<String> {
+    debug!("Archiving WAL file {:?}", path);
+    let file_name = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| anyhow!("Invalid WAL file path {}", path.display()))?;
+    check_file_name(file_name)?;
+
+    if let Some(existing) = find_archived(browser, file_name).await? {
+        let dir = tempfile::TempDir::new()?;
+        let archived = dir.path().join(file_name);
+        download(browser, &existing, &archived).await?;
+        let same = std::fs::read(&archived)? == std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if same {
+            info!("{} is already archived as {}", file_name, existing.key);
+            return Ok(existing.key);
+        }
+        return Err(anyhow!("{} is already archived as {} with different contents; refusing to overwrite it", file_name, existing.key));
+    }
+
+    let compressed = match compression {
+        Some(compression) => {
+            let file = tempfile::NamedTempFile::new()?;
+            let input = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+            let output = compression.spawn(Stdio::from(input), file.path())?.wait_with_output().await?;
+            if !output.status.success() {
+                return Err(anyhow!("Compressing {} failed: {}", file_name, String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            Some((file, compression.codec.extension()))
+        }
+        None => None,
+    };
+    let (upload, extension) = match &compressed {
+        Some((file, extension)) => (file.path(), *extension),
+        None => (path, ""),
+    };
+    let encrypted = browser.encryption.encrypt_file(upload).await?;
+    let key = browser.encryption.encrypted_key(&wal_key(browser.prefix(), &format!("{}{}", file_name, extension)));
+    let upload = encrypted.as_ref().map_or(upload, |file| file.path());
+    browser.store().await?.upload_file(upload, &key, None).await?;
+    info!("Archived {} as {}", file_name, key);
+    Ok(key)
+}
+
+/// Download an archived WAL file, decrypted and decompressed, to `output`
+async fn download(browser: &mut SnapshotBrowser, object: &BackupMetadata, output: &Path) -> Result<()> {
+    debug!("Downloading archived WAL file {} to {:?}", object.key, output);
+    let dir = tempfile::TempDir::new()?;
+    let downloaded = dir.path().join("segment");
+    browser
+        .download_snapshot(object, &downloaded)
+        .await?
+        .ok_or_else(|| anyhow!("Download of {} failed: {:?}", object.key, browser.popup_state))?;
+    let decompressed = crate::compression::decompress_file(&downloaded).await?;
+    let source = decompressed.as_ref().map_or(downloaded.as_path(), |file| file.path());
+    std::fs::copy(source, output).with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok(())
+}
+
+/// Restore an archived WAL file, as PostgreSQL's `restore_command`
+///
+/// Recovery asks for files until one is missing, so a file that was never
+/// archived is an error the server expects rather than a failure.
+///
+/// # Arguments
+///
+/// * `browser` - Snapshot browser connected to the bucket
+/// * `file_name` - Name of the WAL file, `%f` in `restore_command`
+/// * `output` - Where to write it, `%p` in `restore_command`
+pub async fn fetch(browser: &mut SnapshotBrowser, file_name: &str, output: &Path) -> Result<String> {
+    debug!("Restoring archived WAL file {} to {:?}", file_name, output);
+    check_file_name(file_name)?;
+    let object = find_archived(browser, file_name)
+        .await?
+        .ok_or_else(|| anyhow!("{} is not archived under {}", file_name, browser.prefix()))?;
+    download(browser, &object, output).await?;
+    info!("Restored {} from {}", file_name, object.key);
+    Ok(object.key)
+}
+
+/// WAL range in pg_basebackup's `backup_manifest`
+#[derive(Debug, Deserialize)]
+struct WalRange {
+    #[serde(rename = "Timeline")]
+    timeline: u32,
+    #[serde(rename = "Start-LSN")]
+    start_lsn: String,
+}
+
+/// The parts of pg_basebackup's `backup_manifest` needed to name the backup
+#[derive(Debug, Deserialize)]
+struct BackupManifest {
+    #[serde(rename = "WAL-Ranges")]
+    wal_ranges: Vec<WalRange>,
+}
+
+/// WAL segment a base backup starts in, from the `backup_manifest` pg_basebackup writes
+pub fn start_segment(backup_manifest: &[u8]) -> Result<String> {
+    debug!("Reading start segment from backup manifest ({} bytes)", backup_manifest.len());
+    let manifest: BackupManifest = serde_json::from_slice(backup_manifest).context("Failed to parse backup_manifest")?;
+    let range = manifest.wal_ranges.first().ok_or_else(|| anyhow!("backup_manifest has no WAL range"))?;
+    Ok(segment_name(range.timeline, crate::chains::parse_lsn(&range.start_lsn)?))
+}
+
+/// Take a base backup with pg_basebackup and upload it under `<prefix>/base/`
+///
+/// The backup carries no WAL of its own (`--wal-method none`); the segments
+/// written while it runs reach the bucket through `archive_command`, so WAL
+/// archiving must already be set up. Like a dump, the backup is encrypted for
+/// the configured recipients before upload and gets a manifest.
+///
+/// # Returns
+///
+/// The key of the uploaded base backup
+pub async fn base_backup(browser: &mut SnapshotBrowser, pg_config: &PostgresConfig) -> Result<String> {
+    debug!("Taking base backup under {}", browser.prefix());
+    let work = tempfile::TempDir::new()?;
+    let output = work.path().join("backup");
+    let started = Utc::now();
+    let mut cmd = tokio::process::Command::new("pg_basebackup");
+    cmd.arg("--pgdata").arg(&output)
+        .args(["--format", "tar", "--gzip", "--wal-method", "none", "--checkpoint", "fast", "--label", "rustored", "--no-password"]);
+    crate::globals::connection_args(&mut cmd, pg_config);
+    let result = cmd
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to execute pg_basebackup; install the PostgreSQL client tools to take base backups")?;
+    if !result.status.success() {
+        return Err(anyhow!("pg_basebackup failed: {}", String::from_utf8_lossy(&result.stderr).trim()));
+    }
+
+    let manifest = std::fs::read(output.join("backup_manifest")).context("pg_basebackup wrote no backup_manifest; PostgreSQL 13 or newer is needed")?;
+    let finished = Utc::now();
+    let key = base_key(browser.prefix(), finished, &start_segment(&manifest)?);
+    let packed = work.path().join("base.tar");
+    crate::directory_dump::pack_directory(&output, &packed).await?;
+
+    let encrypted = browser.encryption.encrypt_file(&packed).await?;
+    let (upload, key) = match &encrypted {
+        Some(encrypted) => (encrypted.path(), browser.encryption.encrypted_key(&key)),
+        None => (packed.as_path(), key),
+    };
+    info!("Uploading base backup to {}", key);
+    let store = browser.store().await?;
+    store.upload_file(upload, &key, None).await?;
+
+    // The backup is usable without its manifest, so a failure is only logged
+    match Manifest::for_dump(&key, BASE_NAME, upload, started, finished) {
+        Ok(mut manifest) => {
+            manifest.format = "basebackup".to_string();
+            manifest.pg_dump_version = None;
+            if let Err(e) = crate::manifest::write_manifest(store.as_ref(), &manifest).await {
+                warn!("Failed to write manifest of {}: {}", key, e);
+            }
+        }
+        Err(e) => warn!("Failed to build manifest of {}: {}", key, e),
+    }
+    Ok(key)
+}
+
+/// Whether an unpacked tar snapshot is a base backup rather than a directory-format dump
+pub fn is_base_backup_dir(dir: &Path) -> bool {
+    debug!("Checking whether {:?} holds a base backup", dir);
+    dir.join("backup_manifest").exists() || dir.join("base.tar.gz").exists()
+}
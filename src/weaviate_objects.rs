@@ -0,0 +1,261 @@
+// This module contains the Weaviate object loader for the Rustored application
+// Weaviate snapshots are newline-delimited JSON, one object per line, as
+// returned by listing a class's objects with their vectors: an `id`, the
+// object's `properties` and its `vector`, or named `vectors`. Lines without
+// `properties` are taken as bare documents whose other fields are the
+// properties. Objects are sent through the batch API, which answers with a
+// result per object, so only the objects Weaviate rejected go to a
+// dead-letter file with its error, and the rest of the batch is kept.
+//
+// A missing class is created without a vectorizer, since the objects carry
+// their vectors, and Weaviate's auto-schema adds the properties as they
+// arrive. Requests carry an API key, or an OIDC token obtained with the client
+// credentials grant from the issuer Weaviate advertises. OIDC tokens expire,
+// often within the hour a large restore takes, so a batch refused with 401
+// fetches a new token and is sent once more.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use crate::dead_letter::{DeadLetterWriter, NdjsonBatches};
+use crate::mapping::RestoreMapping;
+
+/// Objects sent in one batch request
+pub const OBJECT_BATCH_SIZE: usize = 100;
+
+/// How a restore authenticates to Weaviate
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeaviateCredentials {
+    /// API key, sent as a bearer token
+    pub api_key: Option<String>,
+    /// OIDC client secret for the client credentials grant, used without an API key
+    pub oidc_client_secret: Option<String>,
+    /// Scopes requested with the OIDC token, if the issuer needs any
+    pub oidc_scope: Option<String>,
+}
+
+impl WeaviateCredentials {
+    /// How the credentials authenticate, for messages
+    pub fn describe(&self) -> &'static str {
+        debug!("Describing Weaviate credentials");
+        match (&self.api_key, &self.oidc_client_secret) {
+            (Some(_), _) => " with API key",
+            (None, Some(_)) => " with OIDC client credentials",
+            (None, None) => "",
+        }
+    }
+
+    /// The bearer token to send: the API key, or a fresh OIDC access token
+    ///
+    /// # Returns
+    ///
+    /// `None` when Weaviate is used without authentication
+    pub async fn token(&self, client: &reqwest::Client, host: &str) -> Result<Option<String>> {
+        debug!("Getting Weaviate bearer token");
+        if let Some(key) = &self.api_key {
+            return Ok(Some(key.clone()));
+        }
+        let Some(secret) = &self.oidc_client_secret else {
+            return Ok(None);
+        };
+        // Weaviate names the issuer and the client ID its tokens are checked against
+        let response = client.get(format!("{}/v1/.well-known/openid-configuration", host)).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Weaviate at {} has no OIDC configuration ({}); use an API key", host, response.status()));
+        }
+        let weaviate: Value = response.json().await?;
+        let (Some(href), Some(client_id)) = (weaviate["href"].as_str(), weaviate["clientId"].as_str()) else {
+            return Err(anyhow!("Weaviate at {} returned an incomplete OIDC configuration", host));
+        };
+        let discovery: Value = client.get(href).send().await?.error_for_status()?.json().await
+            .with_context(|| format!("Failed to read the OIDC discovery document at {}", href))?;
+        let token_endpoint = discovery["token_endpoint"].as_str()
+            .ok_or_else(|| anyhow!("The OIDC issuer at {} names no token endpoint", href))?;
+        let mut form = vec![("grant_type", "client_credentials"), ("client_id", client_id), ("client_secret", secret.as_str())];
+        if let Some(scope) = &self.oidc_scope {
+            form.push(("scope", scope));
+        }
+        let response = client.post(token_endpoint).form(&form).send().await?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        if !status.is_success() {
+            let reason = body["error_description"].as_str().or(body["error"].as_str()).map(String::from).unwrap_or_else(|| status.to_string());
+            return Err(anyhow!("The OIDC issuer refused the client credentials of {}: {}", client_id, reason));
+        }
+        info!("Obtained an OIDC token for Weaviate client {}", client_id);
+        body["access_token"].as_str().map(|token| Some(token.to_string()))
+            .ok_or_else(|| anyhow!("The OIDC issuer returned no access token"))
+    }
+}
+
+/// Build a request, adding the bearer token when there is one
+pub fn request(client: &reqwest::Client, method: reqwest::Method, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client.request(method, url);
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+/// The Weaviate class a name restores into
+///
+/// Class names start with a capital letter and hold only letters, digits and
+/// underscores, so `movies-restored` becomes `Movies_restored`.
+pub fn class_name(name: &str) -> String {
+    debug!("Building Weaviate class name from {}", name);
+    let mut chars = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' });
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => first.to_ascii_uppercase().to_string() + &chars.collect::<String>(),
+        Some(first) => format!("C{}{}", first, chars.collect::<String>()),
+        None => String::new(),
+    }
+}
+
+/// Turn a snapshot line into an object for the batch API
+///
+/// The object is put in `class`, whatever class it was exported from, and
+/// `mapping` renames its properties.
+///
+/// # Returns
+///
+/// The object, or why the line cannot be one
+pub fn weaviate_object(line: Value, class: &str, mapping: &RestoreMapping) -> std::result::Result<Value, String> {
+    debug!("Building Weaviate object for class {}", class);
+    let Value::Object(mut fields) = line else {
+        return Err("objects must be JSON objects".to_string());
+    };
+    let mut object = Map::new();
+    object.insert("class".to_string(), json!(class));
+    for key in ["id", "vector", "vectors", "tenant"] {
+        if let Some(value) = fields.remove(key).filter(|value| !value.is_null()) {
+            object.insert(key.to_string(), value);
+        }
+    }
+    let properties = match fields.remove("properties") {
+        Some(properties @ Value::Object(_)) => properties,
+        Some(_) => return Err("properties must be a JSON object".to_string()),
+        // A bare document: the fields left are the properties
+        None => {
+            fields.remove("class");
+            Value::Object(fields)
+        }
+    };
+    object.insert("properties".to_string(), mapping.map_document(properties));
+    Ok(Value::Object(object))
+}
+
+/// Why Weaviate rejected an object of a batch, from its result in the batch response
+pub fn object_error(result: &Value) -> Option<String> {
+    let errors = result["result"]["errors"]["error"].as_array()?;
+    let messages: Vec<&str> = errors.iter().filter_map(|error| error["message"].as_str()).collect();
+    match messages.is_empty() {
+        true => (!errors.is_empty()).then(|| "rejected by Weaviate".to_string()),
+        false => Some(messages.join("; ")),
+    }
+}
+
+/// Make sure the class exists, creating it without a vectorizer if it does not
+async fn ensure_class(client: &reqwest::Client, host: &str, class: &str, token: Option<&str>) -> Result<()> {
+    debug!("Ensuring Weaviate class {} exists", class);
+    let response = request(client, reqwest::Method::GET, &format!("{}/v1/schema/{}", host, class), token).send().await?;
+    match response.status() {
+        status if status.is_success() => return Ok(()),
+        reqwest::StatusCode::NOT_FOUND => {}
+        status => return Err(anyhow!("Weaviate returned {} for class {}", status, class)),
+    }
+    info!("Creating Weaviate class {}", class);
+    let response = request(client, reqwest::Method::POST, &format!("{}/v1/schema", host), token)
+        .json(&json!({ "class": class, "vectorizer": "none" }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        let reason = body["error"][0]["message"].as_str().map(String::from).unwrap_or_else(|| status.to_string());
+        return Err(anyhow!("Failed to create Weaviate class {}: {}", class, reason));
+    }
+    Ok(())
+}
+
+/// Stream an NDJSON snapshot of objects into a class
+///
+/// The class is created if it does not exist. Objects Weaviate rejects, and
+/// lines that are not objects, are written to `dead_letters` and do not fail
+/// the load.
+///
+/// # Arguments
+///
+/// * `client` - HTTP client, carrying the target timeouts
+/// * `host` - Weaviate URL
+/// * `class` - Class to import the objects into
+/// * `credentials` - API key or OIDC client credentials
+/// * `path` - The snapshot file
+/// * `mapping` - Property renames applied to each object before it is sent
+/// * `progress` - Called with the share of the file imported
+/// * `dead_letters` - Receives the objects that could not be imported
+///
+/// # Returns
+///
+/// The number of objects imported
+#[allow(clippy::too_many_arguments)]
+pub async fn import_objects(
+    client: &reqwest::Client,
+    host: &str,
+    class: &str,
+    credentials: &WeaviateCredentials,
+    path: &Path,
+    mapping: &RestoreMapping,
+    progress: Option<&(dyn Fn(f32) + Send + Sync)>,
+    dead_letters: &mut DeadLetterWriter,
+) -> Result<u64> {
+    debug!("Importing objects from {:?} into {}", path, class);
+    let host = host.trim_end_matches('/');
+    let mut snapshot = NdjsonBatches::open(path, OBJECT_BATCH_SIZE).await?;
+
+    let mut token = credentials.token(client, host).await?;
+    ensure_class(client, host, class, token.as_deref()).await?;
+    let url = format!("{}/v1/batch/objects", host);
+    let mut imported = 0u64;
+    while let Some(records) = snapshot.next_batch(dead_letters).await? {
+        let mut batch = Vec::with_capacity(records.len());
+        for record in records {
+            match weaviate_object(record.clone(), class, mapping) {
+                Ok(object) => batch.push(object),
+                Err(reason) => dead_letters.write(record, &reason)?,
+            }
+        }
+        if !batch.is_empty() {
+            let body = json!({ "objects": batch });
+            let mut response = request(client, reqwest::Method::POST, &url, token.as_deref()).json(&body).send().await
+                .map_err(|e| anyhow!("Importing objects into {} failed: {}", class, e))?;
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED && credentials.oidc_client_secret.is_some() {
+                info!("Weaviate refused the OIDC token, fetching a new one");
+                token = credentials.token(client, host).await?;
+                response = request(client, reqwest::Method::POST, &url, token.as_deref()).json(&body).send().await
+                    .map_err(|e| anyhow!("Importing objects into {} failed: {}", class, e))?;
+            }
+            let status = response.status();
+            if !status.is_success() {
+                let body: Value = response.json().await.unwrap_or_default();
+                let reason = body["error"][0]["message"].as_str().map(String::from).unwrap_or_else(|| status.to_string());
+                return Err(anyhow!("Weaviate refused a batch for {}: {}", class, reason));
+            }
+            let results: Vec<Value> = response.json().await?;
+            for (i, object) in batch.into_iter().enumerate() {
+                match results.get(i).and_then(object_error) {
+                    Some(reason) => {
+                        warn!("Weaviate rejected object {}: {}", object["id"], reason);
+                        dead_letters.write(object, &reason)?;
+                    }
+                    None => imported += 1,
+                }
+            }
+        }
+        if let Some(progress) = progress {
+            progress(snapshot.progress());
+        }
+    }
+    info!("Imported {} objects into {}, {} rejected", imported, class, dead_letters.count());
+    Ok(imported)
+}
@@ -0,0 +1,138 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustored::archive::{is_archived_class, wait_until_retrievable, ArchiveStatus, ArchiveTier};
+use rustored::cancel::CancellationToken;
+use rustored::storage::S3Store;
+use rustored::ui::models::{BackupMetadata, S3Config, FocusField, PopupState};
+use rustored::ui::rustored::RustoredApp;
+use std::time::Duration;
+use wiremock::matchers::{method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// S3 store talking to the mock server
+async fn mock_store(server: &MockServer) -> S3Store {
+    let config = S3Config {
+        bucket: "dumps".to_string(),
+        region: "us-east-1".to_string(),
+        endpoint_url: server.uri(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        path_style: true,
+        ..Default::default()
+    };
+    S3Store { client: config.create_client().await.unwrap(), bucket: "dumps".to_string(), sse_customer_key: None }
+}
+
+#[test]
+fn test_archive_status_from_restore_header() {
+    assert!(is_archived_class("GLACIER"));
+    assert!(is_archived_class("DEEP_ARCHIVE"));
+    // Instant Retrieval objects are readable as they are
+    assert!(!is_archived_class("GLACIER_IR"));
+
+    assert_eq!(ArchiveStatus::from_restore_header(Some("STANDARD"), None), ArchiveStatus::NotArchived);
+    assert_eq!(ArchiveStatus::from_restore_header(None, Some("ongoing-request=\"true\"")), ArchiveStatus::NotArchived);
+    assert_eq!(ArchiveStatus::from_restore_header(Some("GLACIER"), None), ArchiveStatus::Archived);
+    assert_eq!(ArchiveStatus::from_restore_header(Some("GLACIER"), Some("ongoing-request=\"true\"")), ArchiveStatus::Restoring);
+    assert_eq!(
+        ArchiveStatus::from_restore_header(Some("DEEP_ARCHIVE"), Some("ongoing-request=\"false\", expiry-date=\"Fri, 21 Dec 2012 00:00:00 GMT\"")),
+        ArchiveStatus::Restored { expiry: Some("Fri, 21 Dec 2012 00:00:00 GMT".to_string()) }
+    );
+}
+
+#[test]
+fn test_archive_tiers() {
+    assert_eq!(ArchiveTier::default(), ArchiveTier::Standard);
+    assert_eq!(ArchiveTier::Standard.next().next().next(), ArchiveTier::Standard);
+    assert_eq!("bulk".parse::<ArchiveTier>().unwrap(), ArchiveTier::Bulk);
+    assert!("fast".parse::<ArchiveTier>().is_err());
+    assert_eq!(ArchiveTier::Expedited.typical_wait("DEEP_ARCHIVE"), "not available");
+}
+
+#[tokio::test]
+async fn test_wait_until_retrievable_polls_until_restored() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("x-amz-storage-class", "GLACIER")
+            .insert_header("x-amz-restore", "ongoing-request=\"true\""))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("x-amz-storage-class", "GLACIER")
+            .insert_header("x-amz-restore", "ongoing-request=\"false\", expiry-date=\"Fri, 21 Dec 2012 00:00:00 GMT\""))
+        .mount(&server)
+        .await;
+
+    let store = mock_store(&server).await;
+    let status = wait_until_retrievable(&store, "pg/a.dump", Duration::from_millis(10), &CancellationToken::new()).await.unwrap();
+    assert!(status.is_retrievable());
+
+    // Polling stops when cancelled
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("x-amz-storage-class", "GLACIER")
+            .insert_header("x-amz-restore", "ongoing-request=\"true\""))
+        .mount(&server)
+        .await;
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let error = wait_until_retrievable(&mock_store(&server).await, "pg/a.dump", Duration::from_secs(60), &cancel).await.unwrap_err();
+    assert!(rustored::cancel::is_cancelled(&error));
+}
+
+#[tokio::test]
+async fn test_selecting_archived_snapshot_offers_restore() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-amz-storage-class", "GLACIER"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(query_param("restore", ""))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut app = RustoredApp::new(
+        &Some("dumps".to_string()),
+        &Some("us-east-1".to_string()),
+        &Some("pg/".to_string()),
+        &Some(server.uri()),
+        &Some("key".to_string()),
+        &Some("secret".to_string()),
+        true,
+        &None,
+        &None,
+        &None,
+        &None,
+        false,
+        &None,
+        &None,
+        &None,
+        &None,
+    );
+    app.snapshot_browser.snapshots = vec![BackupMetadata { storage_class: Some("GLACIER".to_string()), ..BackupMetadata::new("pg/a.dump", 10, 0.0) }];
+    app.focus = FocusField::SnapshotList;
+
+    // Enter offers an archive restore instead of a download
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    assert_eq!(app.popup_state, PopupState::ConfirmArchiveRestore(app.snapshot_browser.snapshots[0].clone(), ArchiveTier::Standard));
+
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE)).await;
+    assert!(matches!(app.popup_state, PopupState::ConfirmArchiveRestore(_, ArchiveTier::Bulk)));
+
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE)).await;
+    assert!(matches!(app.popup_state, PopupState::Success(_)), "{:?}", app.popup_state);
+    assert_eq!(app.snapshot_browser.archive.status("pg/a.dump"), Some(ArchiveStatus::Restoring));
+    app.operation.cancel();
+
+    // Snapshots on the standard storage classes go straight to the restore confirmation
+    app.popup_state = PopupState::Hidden;
+    app.snapshot_browser.snapshots[0].storage_class = Some("STANDARD".to_string());
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    assert!(matches!(app.popup_state, PopupState::ConfirmRestore(_)));
+}
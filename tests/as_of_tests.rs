@@ -0,0 +1,56 @@
+use chrono::{TimeZone, Utc};
+use rustored::as_of::{newest_as_of, parse_as_of};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{BackupMetadata, S3Config};
+use std::sync::Arc;
+
+fn snapshot(key: &str, last_modified: &str) -> BackupMetadata {
+    BackupMetadata::new(key, 4, parse_as_of(last_modified).unwrap().timestamp() as f64)
+}
+
+#[test]
+fn test_parse_as_of_formats() {
+    let three_am = Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap();
+    assert_eq!(parse_as_of("2024-06-01 03:00").unwrap(), three_am);
+    assert_eq!(parse_as_of("2024-06-01T03:00:00").unwrap(), three_am);
+    assert_eq!(parse_as_of("2024-06-01T05:00:00+02:00").unwrap(), three_am);
+    // A date alone covers the whole day
+    assert_eq!(parse_as_of("2024-06-01").unwrap(), Utc.with_ymd_and_hms(2024, 6, 1, 23, 59, 59).unwrap());
+    assert!(parse_as_of("June 1").is_err());
+    assert!(parse_as_of("2024-13-01 03:00").is_err());
+}
+
+#[test]
+fn test_newest_as_of_picks_latest_at_or_before() {
+    let snapshots = vec![
+        snapshot("pg/orders-0531.dump", "2024-05-31 03:00"),
+        snapshot("pg/orders-0601.dump", "2024-06-01 03:00"),
+        snapshot("pg/orders-0602.dump", "2024-06-02 03:00"),
+        snapshot("pg/users-0601.dump", "2024-06-01 04:00"),
+    ];
+    let pick = |prefix: &str, at: &str| newest_as_of(&snapshots, prefix, parse_as_of(at).unwrap()).map(|s| s.key.as_str());
+
+    // Exactly at the snapshot time counts
+    assert_eq!(pick("pg/orders-", "2024-06-01 03:00"), Some("pg/orders-0601.dump"));
+    assert_eq!(pick("pg/orders-", "2024-06-01 02:59"), Some("pg/orders-0531.dump"));
+    assert_eq!(pick("pg/", "2024-06-01 12:00"), Some("pg/users-0601.dump"));
+    assert_eq!(pick("pg/orders-", "2024-05-30"), None);
+    assert_eq!(pick("pg/accounts-", "2024-06-30"), None);
+}
+
+#[tokio::test]
+async fn test_browser_finds_snapshot_as_of() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let dump = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(dump.path(), b"dump").unwrap();
+    store.upload_file(dump.path(), "postgres/orders.dump", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store);
+    let found = browser.find_snapshot_as_of("postgres/orders", Utc::now()).await.unwrap();
+    assert_eq!(found.key, "postgres/orders.dump");
+
+    let err = browser.find_snapshot_as_of("postgres/orders", parse_as_of("2000-01-01").unwrap()).await.unwrap_err();
+    assert!(err.to_string().contains("No snapshot under 'postgres/orders'"));
+}
@@ -0,0 +1,41 @@
+use rustored::ui::attention::{waiting_for, Attention};
+use rustored::ui::models::{BackupMetadata, PopupState};
+use std::time::{Duration, Instant};
+
+/// A snapshot of the orders database
+fn snapshot() -> BackupMetadata {
+    BackupMetadata::new("backups/orders-20250101T000000Z.dump", 1024, 0.0)
+}
+
+#[test]
+fn test_waiting_for_ignores_running_operations() {
+    assert_eq!(waiting_for(&PopupState::Hidden), None);
+    assert_eq!(waiting_for(&PopupState::Restoring(snapshot(), 0.5)), None);
+    assert_eq!(waiting_for(&PopupState::Error("Restore failed".to_string())), Some("an error needs attention"));
+    assert_eq!(waiting_for(&PopupState::ConfirmImpact(snapshot(), String::new())), Some("confirm overwriting the target"));
+    assert_eq!(waiting_for(&PopupState::RestoreSummary(Vec::new())), Some("finished"));
+}
+
+#[test]
+fn test_only_long_operations_are_signalled() {
+    let mut attention = Attention::new(false, Duration::from_secs(10));
+    let error = PopupState::Error("Restore failed".to_string());
+    assert!(!attention.operation_finished(&error, Duration::from_secs(2)));
+    assert!(!attention.operation_finished(&PopupState::Hidden, Duration::from_secs(60)));
+    assert_eq!(attention.flash(Instant::now()), None);
+
+    assert!(attention.operation_finished(&error, Duration::from_secs(60)));
+    assert_eq!(attention.flash(Instant::now()).map(|(reason, _)| reason), Some("an error needs attention"));
+    attention.acknowledge();
+    assert_eq!(attention.flash(Instant::now()), None);
+}
+
+#[test]
+fn test_status_bar_flashes_on_and_off() {
+    let mut attention = Attention::default();
+    attention.operation_finished(&PopupState::Success("Restored orders".to_string()), Duration::from_secs(3600));
+    let now = Instant::now();
+    assert_eq!(attention.flash(now), Some(("finished", true)));
+    assert_eq!(attention.flash(now + Duration::from_millis(600)), Some(("finished", false)));
+    assert_eq!(attention.flash(now + Duration::from_millis(1100)), Some(("finished", true)));
+}
@@ -0,0 +1,115 @@
+use rustored::storage::{encode_file_name, B2Store, SnapshotStore};
+use rustored::ui::models::B2Config;
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn b2_config(server: &MockServer) -> B2Config {
+    B2Config {
+        bucket: "dumps".to_string(),
+        prefix: "pg/".to_string(),
+        key_id: "key-id".to_string(),
+        application_key: "secret".to_string(),
+        api_url: server.uri(),
+        ..Default::default()
+    }
+}
+
+/// Mount an authorize endpoint that hands out `token` for a bucket-restricted key
+async fn mount_authorize(server: &MockServer, token: &str, times: u64) {
+    Mock::given(method("GET"))
+        .and(path("/b2api/v2/b2_authorize_account"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "accountId": "acct",
+            "authorizationToken": token,
+            "apiUrl": server.uri(),
+            "downloadUrl": server.uri(),
+            "recommendedPartSize": 100,
+            "allowed": { "bucketId": "bucket-1" },
+        })))
+        .up_to_n_times(times)
+        .mount(server)
+        .await;
+}
+
+#[test]
+fn test_b2_config_and_file_name_encoding() {
+    assert!(B2Config::default().verify_settings().is_err());
+    assert_eq!(B2Config::default().api_url(), "https://api.backblazeb2.com");
+    assert_eq!(encode_file_name("pg/orders 2025+1.dump"), "pg/orders%202025%2B1.dump");
+}
+
+#[tokio::test]
+async fn test_b2_lists_pages_and_skips_unfinished_files() {
+    let server = MockServer::start().await;
+    mount_authorize(&server, "token-1", 1).await;
+    Mock::given(method("POST"))
+        .and(path("/b2api/v2/b2_list_file_names"))
+        .and(body_partial_json(json!({ "startFileName": null })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "files": [
+                { "fileName": "pg/a.dump", "contentLength": 10, "uploadTimestamp": 1_700_000_000_000i64, "action": "upload" },
+                { "fileName": "pg/b.dump", "contentLength": 0, "uploadTimestamp": 1_700_000_000_000i64, "action": "start" },
+            ],
+            "nextFileName": "pg/c.dump",
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/b2api/v2/b2_list_file_names"))
+        .and(body_partial_json(json!({ "startFileName": "pg/c.dump" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "files": [{ "fileName": "pg/c.dump", "contentLength": 20, "uploadTimestamp": 1_700_000_001_000i64, "action": "upload" }],
+            "nextFileName": null,
+        })))
+        .mount(&server)
+        .await;
+
+    let store = B2Store::new(b2_config(&server)).unwrap();
+    let snapshots = store.list_snapshots("pg/").await.unwrap();
+    let keys: Vec<_> = snapshots.iter().map(|s| s.key.as_str()).collect();
+    assert_eq!(keys, vec!["pg/a.dump", "pg/c.dump"]);
+    assert_eq!(snapshots[1].last_modified, 1_700_000_001.0);
+}
+
+#[tokio::test]
+async fn test_b2_renews_expired_token_for_ranged_download() {
+    let server = MockServer::start().await;
+    mount_authorize(&server, "token-1", 1).await;
+    mount_authorize(&server, "token-2", 1).await;
+    Mock::given(method("GET"))
+        .and(path("/file/dumps/pg/a.dump"))
+        .and(header("Authorization", "token-1"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "status": 401, "code": "expired_auth_token", "message": "Authorization token has expired",
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/file/dumps/pg/a.dump"))
+        .and(header("Authorization", "token-2"))
+        .and(header("Range", "bytes=2-5"))
+        .respond_with(ResponseTemplate::new(206).set_body_bytes(b"2345".to_vec()))
+        .mount(&server)
+        .await;
+
+    let store = B2Store::new(b2_config(&server)).unwrap();
+    let mut body = Vec::new();
+    store.read_range("pg/a.dump", 2, 5).await.unwrap().read_to_end(&mut body).await.unwrap();
+    assert_eq!(body, b"2345");
+}
+
+#[tokio::test]
+async fn test_b2_read_object_missing_returns_none() {
+    let server = MockServer::start().await;
+    mount_authorize(&server, "token-1", 1).await;
+    Mock::given(method("GET"))
+        .and(path("/file/dumps/.rustored/holds.json"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "status": 404, "code": "not_found" })))
+        .mount(&server)
+        .await;
+
+    let store = B2Store::new(b2_config(&server)).unwrap();
+    assert_eq!(store.read_object(".rustored/holds.json").await.unwrap(), None);
+}
@@ -0,0 +1,113 @@
+use chrono::{TimeZone, Utc};
+use rustored::backup::{copy_with_progress, labeled_snapshot_key, snapshot_key, PRE_CHANGE_LABEL, PROGRESS_INTERVAL};
+use rustored::ui::models::{BackupMetadata, PostgresConfig};
+use tokio::io::AsyncReadExt;
+
+// Fixed timestamp used for key generation (2025-01-01 12:00:00 UTC)
+fn test_timestamp() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn test_labeled_snapshot_key_joins_prefix() {
+    // Prefix without a trailing slash gets one inserted
+    let key = labeled_snapshot_key("backups", "mydb", PRE_CHANGE_LABEL, test_timestamp());
+    assert_eq!(key, "backups/mydb-pre-change-20250101T120000Z.dump");
+
+    // Prefix with a trailing slash is used as-is
+    let key = labeled_snapshot_key("backups/", "mydb", PRE_CHANGE_LABEL, test_timestamp());
+    assert_eq!(key, "backups/mydb-pre-change-20250101T120000Z.dump");
+
+    // Empty prefix puts the snapshot at the bucket root
+    let key = labeled_snapshot_key("", "mydb", PRE_CHANGE_LABEL, test_timestamp());
+    assert_eq!(key, "mydb-pre-change-20250101T120000Z.dump");
+}
+
+#[test]
+fn test_snapshot_key_names_database_and_time() {
+    let key = snapshot_key("backups", "mydb", test_timestamp());
+    assert_eq!(key, "backups/mydb-20250101T120000Z.dump");
+
+    let key = snapshot_key("", "mydb", test_timestamp());
+    assert_eq!(key, "mydb-20250101T120000Z.dump");
+
+    // The database can be read back from the key, as for other dumps
+    assert_eq!(rustored::hooks::source_db_from_key(&key), "mydb");
+}
+
+#[tokio::test]
+async fn test_copy_with_progress_reports_growing_size() {
+    let data = vec![7u8; (PROGRESS_INTERVAL * 2 + 10) as usize];
+    let mut output = Vec::new();
+    let mut reports = Vec::new();
+    let copied = copy_with_progress(data.as_slice(), &mut output, |bytes| reports.push(bytes)).await.unwrap();
+
+    assert_eq!(copied, data.len() as u64);
+    assert_eq!(output, data);
+    // Reports grow and end with the total
+    assert!(reports.len() >= 3);
+    assert!(reports.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(reports.last(), Some(&copied));
+}
+
+#[test]
+fn test_pre_change_snapshots_are_pinned() {
+    // Snapshots created by backup now are pinned
+    let pinned = BackupMetadata::new(labeled_snapshot_key("backups", "mydb", PRE_CHANGE_LABEL, test_timestamp()), 1024, 0.0);
+    assert!(pinned.is_pinned());
+
+    // Regular snapshots, and prefixes that merely contain the label, are not
+    let regular = BackupMetadata::new("pre-change-archive/mydb-20250101.dump", 1024, 0.0);
+    assert!(!regular.is_pinned());
+}
+
+#[tokio::test]
+async fn test_dump_stream_reports_pg_dump_failure() {
+    // Nothing listens on port 1, so pg_dump exits with an error
+    let pg_config = PostgresConfig {
+        host: Some("127.0.0.1".to_string()),
+        port: Some(1),
+        username: Some("postgres".to_string()),
+        password: None,
+        use_ssl: false,
+        db_name: Some("mydb".to_string()),
+        target_db: None,
+        restore_jobs: None,
+    };
+    let Ok(mut stream) = rustored::backup::dump_stream(&pg_config, "mydb", true) else {
+        // pg_dump is not installed here
+        return;
+    };
+
+    // The failure surfaces at the end of the stream instead of an empty dump
+    let mut dump = Vec::new();
+    let err = stream.read_to_end(&mut dump).await.unwrap_err();
+    assert!(err.to_string().contains("pg_dump failed"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_load_data_script_reports_psql_failing_partway() {
+    use std::os::unix::fs::PermissionsExt;
+    // A pg_restore that writes far more than a pipe holds, and a psql that gives up after a few lines
+    let bin = tempfile::tempdir().unwrap();
+    for (tool, script) in [
+        ("pg_restore", "#!/bin/sh\nyes 'INSERT INTO orders VALUES (1);'\n"),
+        ("psql", "#!/bin/sh\nhead -n 10 > /dev/null\necho 'ERROR:  syntax error at or near \"WHERE\"' >&2\nexit 3\n"),
+    ] {
+        let path = bin.path().join(tool);
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = format!("{}:{}", bin.path().display(), std::env::var("PATH").unwrap_or_default());
+    std::env::set_var("PATH", path);
+
+    let archive = bin.path().join("orders.dump");
+    let load = rustored::backup::load_data_script(&archive, "", None, None, "orders", "127.0.0.1", 1, None);
+    let error = tokio::time::timeout(std::time::Duration::from_secs(30), load)
+        .await
+        .expect("the load hung after psql exited")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("psql failed loading table data") && error.contains("syntax error"), "{}", error);
+}
@@ -0,0 +1,76 @@
+use rustored::bandwidth::{format_rate, parse_rate, TransferBudget};
+use rustored::progress::{Progress, ProgressBoard};
+use rustored::ui::components::jobs_panel::{job_lines, jobs_title};
+use std::time::{Duration, Instant};
+
+const MB: u64 = 1024 * 1024;
+
+#[test]
+fn test_rates_parse_with_units() {
+    assert_eq!(parse_rate("50M").unwrap(), 50 * MB);
+    assert_eq!(parse_rate("50MB/s").unwrap(), 50 * MB);
+    assert_eq!(parse_rate("1.5G").unwrap(), 1536 * MB);
+    assert_eq!(parse_rate("800k").unwrap(), 800 * 1024);
+    assert_eq!(parse_rate("4096").unwrap(), 4096);
+    assert!(parse_rate("50X").unwrap_err().to_string().contains("Unknown unit"));
+    assert!(parse_rate("fast").is_err());
+    assert!(parse_rate("0").is_err());
+    assert_eq!(format_rate(12.5 * MB as f64), "12.5 MB/s");
+}
+
+#[tokio::test]
+async fn test_budget_is_split_between_transferring_jobs() {
+    let budget = TransferBudget::default();
+    budget.take("Download of pg/a.dump", 10 * MB).await;
+    assert_eq!(budget.active_jobs(), 0, "without a limit nothing is tracked");
+    assert_eq!(budget.fair_share(), None);
+
+    budget.set_limit(Some(1000));
+    assert_eq!(budget.fair_share(), Some(1000.0));
+    budget.take("Download of pg/a.dump", 0).await;
+    budget.take("Download of pg/b.dump", 0).await;
+    assert_eq!(budget.active_jobs(), 2);
+    assert_eq!(budget.fair_share(), Some(500.0));
+    budget.release("Download of pg/a.dump");
+    assert_eq!(budget.fair_share(), Some(1000.0), "a finished job's share goes back to the others");
+}
+
+#[tokio::test]
+async fn test_jobs_wait_for_their_share() {
+    let budget = TransferBudget::default();
+    budget.set_limit(Some(MB));
+    budget.take("Download of pg/a.dump", 0).await;
+
+    // With two jobs transferring, a fifth of the limit takes as long as two fifths alone
+    let started = Instant::now();
+    budget.take("Download of pg/b.dump", MB / 5).await;
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(350), "waited only {:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "waited {:?}", elapsed);
+}
+
+#[tokio::test]
+async fn test_finished_progress_jobs_release_their_share() {
+    let board = ProgressBoard::default();
+    board.budget().set_limit(Some(MB));
+    let download = board.reporter("Download of pg/a.dump");
+    download.transfer(1).await;
+    assert_eq!(board.budget().active_jobs(), 1);
+    download.finish();
+    assert_eq!(board.budget().active_jobs(), 0);
+}
+
+#[test]
+fn test_jobs_panel_shows_each_rate_and_the_total() {
+    let jobs = vec![
+        ("Download of pg/a.dump".to_string(), Progress { fraction: 0.25, rate: 2.0 * MB as f64 }),
+        ("Restore of pg/b.dump".to_string(), Progress { fraction: 0.5, rate: 0.0 }),
+    ];
+    assert_eq!(job_lines(&jobs, Some(2.5 * MB as f64)), vec![
+        "Download of pg/a.dump: 25.0% at 2.0 MB/s (share 2.5 MB/s)".to_string(),
+        "Restore of pg/b.dump: 50.0%".to_string(),
+    ]);
+    assert_eq!(job_lines(&jobs[..1], None), vec!["Download of pg/a.dump: 25.0% at 2.0 MB/s".to_string()]);
+    assert_eq!(jobs_title(2.0 * MB as f64, Some(5 * MB)), "Jobs (2.0 MB/s of 5.0 MB/s limit)");
+    assert_eq!(jobs_title(2.0 * MB as f64, None), "Jobs (2.0 MB/s)");
+}
@@ -0,0 +1,109 @@
+use chrono::{TimeZone, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustored::storage::{DemoStore, SnapshotStore};
+use rustored::ui::batch::{restore_batch, target_names};
+use rustored::ui::guard_rails::GuardedAction;
+use rustored::ui::models::{BackupMetadata, FocusField, PopupState, RestoreTarget};
+use rustored::ui::rustored::RustoredApp;
+use std::sync::Arc;
+
+fn app() -> RustoredApp {
+    let none = None;
+    let mut app = RustoredApp::new(&none, &none, &none, &none, &none, &none, false, &Some("localhost".to_string()), &Some(1), &none, &none, false, &Some("orders".to_string()), &none, &none, &none);
+    app.focus = FocusField::SnapshotList;
+    app
+}
+
+fn snapshot(key: &str) -> BackupMetadata {
+    BackupMetadata::new(key, 5, 0.0)
+}
+
+async fn press(app: &mut RustoredApp, code: KeyCode) {
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(code, KeyModifiers::NONE)).await;
+}
+
+#[test]
+fn test_batch_items_get_numbered_target_names() {
+    let mut app = app();
+    assert_eq!(target_names(&app, 2), vec![None, None], "without a target database every item gets a new one");
+    app.pg_config.target_db = Some("scratch".to_string());
+    assert_eq!(target_names(&app, 2), vec![Some("scratch-1".to_string()), Some("scratch-2".to_string())]);
+    app.restore_target = RestoreTarget::Qdrant;
+    app.qdrant_config.collection = Some("embeddings".to_string());
+    assert_eq!(target_names(&app, 1), vec![Some("embeddings-1".to_string())]);
+}
+
+#[tokio::test]
+async fn test_marked_snapshots_are_confirmed_as_a_batch() {
+    let mut app = app();
+    app.snapshot_browser.snapshots = vec![snapshot("pg/orders-1.dump"), snapshot("pg/orders-2.dump"), snapshot("pg/orders-3.dump")];
+
+    press(&mut app, KeyCode::Char('Q')).await;
+    assert_eq!(app.popup_state, PopupState::Error("Mark the snapshots to restore with Space first".to_string()));
+    app.popup_state = PopupState::Hidden;
+
+    press(&mut app, KeyCode::Char(' ')).await;
+    app.snapshot_browser.selected_index = 2;
+    press(&mut app, KeyCode::Char(' ')).await;
+    app.pg_config.target_db = Some("scratch".to_string());
+    press(&mut app, KeyCode::Char('Q')).await;
+    let PopupState::ConfirmBatchRestore(snapshots, lines) = &app.popup_state else {
+        panic!("expected the batch confirmation, got {:?}", app.popup_state);
+    };
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(lines, &vec!["pg/orders-1.dump -> scratch-1".to_string(), "pg/orders-3.dump -> scratch-2".to_string()]);
+
+    press(&mut app, KeyCode::Enter).await;
+    assert_eq!(app.popup_state, PopupState::Hidden);
+    assert_eq!(app.pending_batch.as_ref().map(Vec::len), Some(2));
+
+    app.pending_batch = None;
+    app.environment = Some("production".to_string());
+    press(&mut app, KeyCode::Char('Q')).await;
+    press(&mut app, KeyCode::Char('y')).await;
+    assert!(matches!(&app.popup_state, PopupState::ConfirmProduction(GuardedAction::RestoreBatch(snapshots), _) if snapshots.len() == 2));
+    assert!(app.pending_batch.is_none());
+}
+
+#[tokio::test]
+async fn test_batch_moves_on_after_a_failed_item_and_reports_every_one() {
+    let mut app = app();
+    let store = DemoStore::new("pg", Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap());
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), b"PGDMP").unwrap();
+    store.upload_file(file.path(), "pg/orders-1.dump", None).await.unwrap();
+    store.upload_file(file.path(), "pg/orders-2.dump", None).await.unwrap();
+    let snapshots: Vec<_> = store.list_snapshots("pg/").await.unwrap().into_iter().filter(|s| s.key.starts_with("pg/orders-") && s.size == 5).collect();
+    app.snapshot_browser.set_store(Arc::new(store));
+    app.pg_config.target_db = Some("scratch".to_string());
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+
+    restore_batch(&mut app, &snapshots, &mut terminal).await.unwrap();
+    let PopupState::RestoreSummary(lines) = &app.popup_state else {
+        panic!("expected the batch summary, got {:?}", app.popup_state);
+    };
+    assert_eq!(lines[0], "Restored 0 of 2 snapshots");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with(&format!("{}: Restore failed", snapshots[0].key)), "{}", lines[1]);
+    assert!(lines[2].starts_with(&format!("{}: Restore failed", snapshots[1].key)), "{}", lines[2]);
+    assert_eq!(app.pg_config.target_db.as_deref(), Some("scratch"), "the configured target database is put back");
+}
+
+#[tokio::test]
+async fn test_aborted_batch_skips_the_rest() {
+    let mut app = app();
+    let snapshots = vec![snapshot("pg/orders-1.dump"), snapshot("pg/orders-2.dump")];
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+    // Every operation started from here on is cancelled before it starts
+    app.cancel.cancel();
+
+    restore_batch(&mut app, &snapshots, &mut terminal).await.unwrap();
+    let PopupState::RestoreSummary(lines) = &app.popup_state else {
+        panic!("expected the batch summary, got {:?}", app.popup_state);
+    };
+    assert_eq!(lines, &vec![
+        "Restored 0 of 2 snapshots".to_string(),
+        "pg/orders-1.dump: Skipped, the batch was aborted".to_string(),
+        "pg/orders-2.dump: Skipped, the batch was aborted".to_string(),
+    ]);
+}
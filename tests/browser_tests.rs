@@ -21,6 +21,7 @@ fn test_s3_config() {
         path_style: false,
         error_message: None,
         test_s3_button: false,
+        ..Default::default()
     };
 
     assert_debug_snapshot!(s3_config);
@@ -73,6 +74,7 @@ fn test_s3_config_get_field_value() {
         path_style: true,
         error_message: None,
         test_s3_button: false,
+        ..Default::default()
     };
     
     // Test getting field values
@@ -100,6 +102,7 @@ fn test_s3_config_set_field_value() {
         path_style: false,
         error_message: None,
         test_s3_button: false,
+        ..Default::default()
     };
     
     // Test setting field values
@@ -118,7 +121,7 @@ fn test_s3_config_set_field_value() {
     assert_eq!(s3_config.endpoint_url, "https://new-endpoint.com");
     assert_eq!(s3_config.access_key_id, "new-access-key");
     assert_eq!(s3_config.secret_access_key, "new-secret-key");
-    assert_eq!(s3_config.path_style, true);
+    assert!(s3_config.path_style);
     
     // Test setting a non-S3 field (should have no effect)
     s3_config.set_field_value(FocusField::PgHost, "should-not-change-anything".to_string());
@@ -134,6 +137,8 @@ fn test_postgres_config() {
         password: Some("password".to_string()),
         use_ssl: false,
         db_name: Some("postgres".to_string()),
+        target_db: None,
+        restore_jobs: None,
     };
 
     assert_debug_snapshot!(pg_config);
@@ -145,7 +150,7 @@ fn test_postgres_config_focus_fields() {
     let fields = PostgresConfig::focus_fields();
     
     // Verify we have the expected number of fields
-    assert_eq!(fields.len(), 6);
+    assert_eq!(fields.len(), 8);
     
     // Verify all expected fields are present
     assert!(fields.contains(&FocusField::PgHost));
@@ -154,6 +159,8 @@ fn test_postgres_config_focus_fields() {
     assert!(fields.contains(&FocusField::PgPassword));
     assert!(fields.contains(&FocusField::PgSsl));
     assert!(fields.contains(&FocusField::PgDbName));
+    assert!(fields.contains(&FocusField::PgTargetDb));
+    assert!(fields.contains(&FocusField::PgRestoreJobs));
 }
 
 #[test]
@@ -165,6 +172,8 @@ fn test_postgres_config_contains_field() {
     assert!(PostgresConfig::contains_field(FocusField::PgPassword));
     assert!(PostgresConfig::contains_field(FocusField::PgSsl));
     assert!(PostgresConfig::contains_field(FocusField::PgDbName));
+    assert!(PostgresConfig::contains_field(FocusField::PgTargetDb));
+    assert!(PostgresConfig::contains_field(FocusField::PgRestoreJobs));
     
     // Test that it correctly rejects non-PostgreSQL fields
     assert!(!PostgresConfig::contains_field(FocusField::Bucket));
@@ -181,6 +190,8 @@ fn test_postgres_config_get_field_value() {
         password: Some("password".to_string()),
         use_ssl: true,
         db_name: Some("postgres".to_string()),
+        target_db: None,
+        restore_jobs: None,
     };
     
     // Test getting field values
@@ -202,6 +213,8 @@ fn test_postgres_config_get_field_value() {
         password: None,
         use_ssl: false,
         db_name: None,
+        target_db: None,
+        restore_jobs: None,
     };
     
     assert_eq!(empty_pg_config.get_field_value(FocusField::PgHost), "");
@@ -221,6 +234,8 @@ fn test_postgres_config_set_field_value() {
         password: None,
         use_ssl: false,
         db_name: None,
+        target_db: None,
+        restore_jobs: None,
     };
     
     // Test setting field values
@@ -236,7 +251,7 @@ fn test_postgres_config_set_field_value() {
     assert_eq!(pg_config.port, Some(5433));
     assert_eq!(pg_config.username, Some("new-user".to_string()));
     assert_eq!(pg_config.password, Some("new-password".to_string()));
-    assert_eq!(pg_config.use_ssl, true);
+    assert!(pg_config.use_ssl);
     assert_eq!(pg_config.db_name, Some("new-database".to_string()));
     
     // Test setting a non-PostgreSQL field (should have no effect)
@@ -246,6 +261,34 @@ fn test_postgres_config_set_field_value() {
     // Test with invalid port value
     pg_config.set_field_value(FocusField::PgPort, "not-a-number".to_string());
     assert_eq!(pg_config.port, None); // Should be None when parse fails
+
+    // Clearing the target database goes back to a random name
+    pg_config.set_field_value(FocusField::PgTargetDb, "orders_staging".to_string());
+    assert_eq!(pg_config.get_field_value(FocusField::PgTargetDb), "orders_staging");
+    pg_config.set_field_value(FocusField::PgTargetDb, String::new());
+    assert_eq!(pg_config.target_db, None);
+
+    // Restore jobs must be a positive number, and clearing them leaves them to the preset
+    pg_config.set_field_value(FocusField::PgRestoreJobs, "6".to_string());
+    assert_eq!(pg_config.restore_jobs, Some(6));
+    assert_eq!(pg_config.get_field_value(FocusField::PgRestoreJobs), "6");
+    pg_config.set_field_value(FocusField::PgRestoreJobs, "0".to_string());
+    assert_eq!(pg_config.restore_jobs, None);
+}
+
+#[test]
+fn test_postgres_config_restore_db_name() {
+    let mut pg_config = PostgresConfig::default();
+    assert!(pg_config.restore_db_name().unwrap().ends_with("-restored"));
+
+    pg_config.target_db = Some("orders_staging".to_string());
+    assert_eq!(pg_config.restore_db_name().unwrap(), "orders_staging");
+
+    for bad in ["orders\"; DROP DATABASE live; --", &"x".repeat(64)] {
+        pg_config.target_db = Some(bad.to_string());
+        assert!(pg_config.restore_db_name().is_err(), "{}", bad);
+    }
+    assert!(rustored::postgres::check_db_name(&"x".repeat(63)).is_ok());
 }
 
 #[test]
@@ -253,6 +296,7 @@ fn test_elasticsearch_config() {
     let es_config = ElasticsearchConfig {
         host: Some("http://localhost:9200".to_string()),
         index: Some("test-index".to_string()),
+        ..Default::default()
     };
 
     assert_debug_snapshot!(es_config);
@@ -288,6 +332,7 @@ fn test_elasticsearch_config_get_field_value() {
     let es_config = ElasticsearchConfig {
         host: Some("http://localhost:9200".to_string()),
         index: Some("test-index".to_string()),
+        ..Default::default()
     };
     
     // Test getting field values
@@ -301,6 +346,7 @@ fn test_elasticsearch_config_get_field_value() {
     let empty_es_config = ElasticsearchConfig {
         host: None,
         index: None,
+        ..Default::default()
     };
     
     assert_eq!(empty_es_config.get_field_value(FocusField::EsHost), "");
@@ -312,6 +358,7 @@ fn test_elasticsearch_config_set_field_value() {
     let mut es_config = ElasticsearchConfig {
         host: None,
         index: None,
+        ..Default::default()
     };
     
     // Test setting field values
@@ -333,6 +380,7 @@ fn test_qdrant_config() {
         host: Some("http://localhost:6333".to_string()),
         collection: Some("test-collection".to_string()),
         api_key: Some("test-api-key".to_string()),
+        ..Default::default()
     };
 
     assert_debug_snapshot!(qdrant_config);
@@ -344,12 +392,14 @@ fn test_qdrant_config_focus_fields() {
     let fields = QdrantConfig::focus_fields();
     
     // Verify we have the expected number of fields
-    assert_eq!(fields.len(), 3);
+    assert_eq!(fields.len(), 5);
     
     // Verify all expected fields are present
     assert!(fields.contains(&FocusField::EsHost)); // Reused for Qdrant host
     assert!(fields.contains(&FocusField::EsIndex)); // Reused for collection
     assert!(fields.contains(&FocusField::QdrantApiKey));
+    assert!(fields.contains(&FocusField::QdrantVectorSize));
+    assert!(fields.contains(&FocusField::QdrantDistance));
 }
 
 #[test]
@@ -370,6 +420,7 @@ fn test_qdrant_config_get_field_value() {
         host: Some("http://localhost:6333".to_string()),
         collection: Some("test-collection".to_string()),
         api_key: Some("test-api-key".to_string()),
+        ..Default::default()
     };
     
     // Test getting field values
@@ -385,6 +436,7 @@ fn test_qdrant_config_get_field_value() {
         host: None,
         collection: None,
         api_key: None,
+        ..Default::default()
     };
     
     assert_eq!(empty_qdrant_config.get_field_value(FocusField::EsHost), "");
@@ -398,6 +450,7 @@ fn test_qdrant_config_set_field_value() {
         host: None,
         collection: None,
         api_key: None,
+        ..Default::default()
     };
     
     // Test setting field values
@@ -409,6 +462,14 @@ fn test_qdrant_config_set_field_value() {
     assert_eq!(qdrant_config.host, Some("http://new-host:6333".to_string()));
     assert_eq!(qdrant_config.collection, Some("new-collection".to_string()));
     assert_eq!(qdrant_config.api_key, Some("new-api-key".to_string()));
+
+    // Vector parameters land in the upload settings, and clearing them falls back to the stored ones
+    qdrant_config.set_field_value(FocusField::QdrantVectorSize, "384".to_string());
+    qdrant_config.set_field_value(FocusField::QdrantDistance, "dot".to_string());
+    assert_eq!(qdrant_config.upload.vector_size, Some(384));
+    assert_eq!(qdrant_config.get_field_value(FocusField::QdrantDistance), "dot");
+    qdrant_config.set_field_value(FocusField::QdrantVectorSize, String::new());
+    assert_eq!(qdrant_config.upload.vector_size, None);
     
     // Test setting a non-Qdrant field (should have no effect)
     qdrant_config.set_field_value(FocusField::Bucket, "should-not-change-anything".to_string());
@@ -418,11 +479,7 @@ fn test_qdrant_config_set_field_value() {
 #[test]
 fn test_backup_metadata() {
     let datetime = create_test_aws_datetime();
-    let backup = BackupMetadata {
-        key: "test-snapshot-1.sql.gz".to_string(),
-        size: 1024 * 1024 * 10, // 10 MB
-        last_modified: datetime,
-    };
+    let backup = BackupMetadata::new("test-snapshot-1.sql.gz", 1024 * 1024 * 10, datetime); // 10 MB
 
     assert_debug_snapshot!(backup);
 }
@@ -430,11 +487,7 @@ fn test_backup_metadata() {
 #[test]
 fn test_popup_states() {
     let datetime = create_test_aws_datetime();
-    let backup = BackupMetadata {
-        key: "test-snapshot-1.sql.gz".to_string(),
-        size: 1024 * 1024 * 10, // 10 MB
-        last_modified: datetime,
-    };
+    let backup = BackupMetadata::new("test-snapshot-1.sql.gz", 1024 * 1024 * 10, datetime); // 10 MB
 
     let hidden = PopupState::Hidden;
     let confirm_restore = PopupState::ConfirmRestore(backup.clone());
@@ -478,9 +531,9 @@ fn test_restore_target_focus_fields() {
     let qdrant_fields = RestoreTarget::Qdrant.focus_fields();
     
     // Verify we have the expected number of fields for each target
-    assert_eq!(postgres_fields.len(), 6);
+    assert_eq!(postgres_fields.len(), 8);
     assert_eq!(elasticsearch_fields.len(), 2);
-    assert_eq!(qdrant_fields.len(), 3);
+    assert_eq!(qdrant_fields.len(), 5);
     
     // Verify first field for each target
     assert_eq!(RestoreTarget::Postgres.first_focus_field(), FocusField::PgHost);
@@ -510,3 +563,17 @@ fn test_input_modes() {
     assert_debug_snapshot!("input_mode_normal", InputMode::Normal);
     assert_debug_snapshot!("input_mode_editing", InputMode::Editing);
 }
+
+#[test]
+fn test_timeout_config_defaults() {
+    use rustored::ui::models::TimeoutConfig;
+
+    // Unset values fall back to the defaults, explicit values win
+    let timeouts = TimeoutConfig::new(Some(3), None, Some(120));
+    assert_eq!(timeouts.connect_secs, Some(3));
+    assert_eq!(timeouts.read_secs, TimeoutConfig::default().read_secs);
+    assert_eq!(timeouts.operation(), Some(std::time::Duration::from_secs(120)));
+
+    // A connect timeout is always set by default so dead endpoints fail fast
+    assert!(TimeoutConfig::default().connect().is_some());
+}
@@ -0,0 +1,118 @@
+use chrono::{TimeZone, Utc};
+use rustored::bundle::{declare_bundle, find_bundle, resolve_members, Bundle};
+use rustored::manifest::{read_manifest, write_manifest, Manifest};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::models::{BackupMetadata, RestoreTarget};
+use rustored::ui::rustored::RustoredApp;
+use std::collections::HashMap;
+
+fn manifest(key: &str, day: u32, bundle: Option<Bundle>) -> Manifest {
+    Manifest {
+        key: key.to_string(),
+        sha256: "00".to_string(),
+        size: 4,
+        format: "custom".to_string(),
+        pg_dump_version: None,
+        database: "orders".to_string(),
+        started: Utc.with_ymd_and_hms(2024, 6, day, 3, 0, 0).unwrap(),
+        duration_seconds: 1.0,
+        bundle,
+    }
+}
+
+fn bundle(elasticsearch: &str, qdrant: &str) -> Bundle {
+    Bundle { elasticsearch: Some(elasticsearch.to_string()), qdrant: Some(qdrant.to_string()) }
+}
+
+fn snapshot(key: &str) -> BackupMetadata {
+    BackupMetadata::new(key, 4, 0.0)
+}
+
+fn manifests(list: Vec<Manifest>) -> HashMap<String, Manifest> {
+    list.into_iter().map(|manifest| (manifest.key.clone(), manifest)).collect()
+}
+
+#[test]
+fn test_find_bundle_from_any_member() {
+    let manifests = manifests(vec![
+        manifest("pg/orders-1.dump", 1, Some(bundle("es/orders-1.json", "qdrant/shared.json"))),
+        manifest("pg/orders-2.dump", 2, Some(bundle("es/orders-2.json", "qdrant/shared.json"))),
+        manifest("pg/users.dump", 2, None),
+    ]);
+    let anchor = |key: &str| find_bundle(&manifests, key).map(|manifest| manifest.key.as_str());
+    assert_eq!(anchor("pg/orders-1.dump"), Some("pg/orders-1.dump"));
+    assert_eq!(anchor("es/orders-1.json"), Some("pg/orders-1.dump"));
+    // A snapshot named by two bundles belongs to the newer one
+    assert_eq!(anchor("qdrant/shared.json"), Some("pg/orders-2.dump"));
+    assert_eq!(anchor("pg/users.dump"), None);
+    assert_eq!(anchor("es/other.json"), None);
+}
+
+#[test]
+fn test_resolve_members_in_target_order() {
+    let anchor = manifest("pg/orders.dump", 1, Some(bundle("es/orders.json", "qdrant/orders.json")));
+    let snapshots = vec![snapshot("qdrant/orders.json"), snapshot("pg/orders.dump"), snapshot("es/orders.json")];
+    let members = resolve_members(&anchor, &snapshots).unwrap();
+    let resolved: Vec<(RestoreTarget, &str)> = members.iter().map(|member| (member.target.clone(), member.snapshot.key.as_str())).collect();
+    assert_eq!(
+        resolved,
+        vec![
+            (RestoreTarget::Postgres, "pg/orders.dump"),
+            (RestoreTarget::Elasticsearch, "es/orders.json"),
+            (RestoreTarget::Qdrant, "qdrant/orders.json"),
+        ]
+    );
+    assert_eq!(members[2].label(), "Qdrant");
+
+    let err = resolve_members(&anchor, &snapshots[..2]).unwrap_err();
+    assert!(err.to_string().contains("es/orders.json"));
+}
+
+#[test]
+fn test_manifest_bundle_is_optional_and_shown() {
+    let plain = manifest("pg/orders.dump", 1, None);
+    let json = String::from_utf8(plain.to_json().unwrap()).unwrap();
+    assert!(!json.contains("bundle"));
+    assert_eq!(Manifest::from_json(json.as_bytes()).unwrap(), plain);
+
+    let bundled = manifest("pg/orders.dump", 1, Some(Bundle { elasticsearch: Some("es/orders.json".to_string()), qdrant: None }));
+    assert_eq!(bundled.lines().last().unwrap(), "Bundled with es/orders.json");
+}
+
+#[tokio::test]
+async fn test_declare_bundle_updates_manifest() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    for key in ["pg/orders.dump", "es/orders.json", "qdrant/orders.json"] {
+        store.write_object(key, b"data".to_vec()).await.unwrap();
+    }
+
+    let err = declare_bundle(&store, "pg/orders.dump", bundle("es/orders.json", "qdrant/orders.json")).await.unwrap_err();
+    assert!(err.to_string().contains("has no manifest"));
+
+    write_manifest(&store, &manifest("pg/orders.dump", 1, None)).await.unwrap();
+    let err = declare_bundle(&store, "pg/orders.dump", bundle("es/missing.json", "qdrant/orders.json")).await.unwrap_err();
+    assert!(err.to_string().contains("es/missing.json not found"));
+
+    declare_bundle(&store, "pg/orders.dump", bundle("es/orders.json", "qdrant/orders.json")).await.unwrap();
+    let stored = read_manifest(&store, "pg/orders.dump").await.unwrap().unwrap();
+    assert_eq!(stored.bundle, Some(bundle("es/orders.json", "qdrant/orders.json")));
+
+    // Declaring an empty bundle removes it
+    declare_bundle(&store, "pg/orders.dump", Bundle::default()).await.unwrap();
+    assert_eq!(read_manifest(&store, "pg/orders.dump").await.unwrap().unwrap().bundle, None);
+}
+
+#[tokio::test]
+async fn test_bundle_restore_needs_every_target_configured() {
+    let none = None;
+    let app = RustoredApp::new(
+        &none, &none, &none, &none, &none, &none, false,
+        &none, &None, &none, &none, false, &none,
+        &Some("http://localhost:9200".to_string()), &Some("orders".to_string()), &none,
+    );
+    let anchor = manifest("pg/orders.dump", 1, Some(Bundle { elasticsearch: Some("es/orders.json".to_string()), qdrant: None }));
+    let members = resolve_members(&anchor, &[snapshot("pg/orders.dump"), snapshot("es/orders.json")]).unwrap();
+
+    let err = app.run_bundle_restore(&members, &["a".into(), "b".into()]).await.unwrap_err();
+    assert!(err.to_string().starts_with("PostgreSQL target not properly configured"), "{}", err);
+}
@@ -0,0 +1,96 @@
+use rustored::cancel::{cancellable, is_cancelled, CancellationToken};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{PopupState, S3Config};
+use rustored::ui::rustored::RustoredApp;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_cancellable_stops_operation() {
+    let token = CancellationToken::new();
+    let result = cancellable(&token, "Sleep", async { Ok(1) }).await;
+    assert_eq!(result.unwrap(), 1);
+
+    // A cancelled token stops the operation with a recognisable error
+    token.cancel();
+    let err = cancellable(&token, "Sleep", async {
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        Ok(())
+    })
+    .await
+    .unwrap_err();
+    assert!(is_cancelled(&err));
+    assert_eq!(err.to_string(), "Sleep cancelled");
+    assert!(!is_cancelled(&anyhow::anyhow!("Sleep failed")));
+}
+
+#[test]
+fn test_operations_get_fresh_tokens() {
+    let none = None;
+    let mut app = RustoredApp::new(
+        &none, &none, &none, &none, &none, &none, false,
+        &none, &None, &none, &none, false, &none, &none, &none, &none,
+    );
+    let first = app.begin_operation();
+    first.cancel();
+    assert!(app.snapshot_browser.cancel.is_cancelled());
+
+    // The next operation is not affected by the cancelled one
+    let second = app.begin_operation();
+    assert!(!second.is_cancelled());
+    assert!(!app.snapshot_browser.cancel.is_cancelled());
+
+    // A signal cancels whatever is running
+    app.cancel.cancel();
+    assert!(second.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_cancelled_download_resumes() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source.dump");
+    std::fs::write(&source, vec![7u8; 4096]).unwrap();
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    store.upload_file(&source, "postgres/db.dump", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store);
+    browser.load_snapshots().await.unwrap();
+    let snapshot = browser.snapshots[0].clone();
+    let target = dir.path().join("db.dump");
+
+    browser.cancel.cancel();
+    let err = browser.download_snapshot(&snapshot, &target).await.unwrap_err();
+    assert!(is_cancelled(&err));
+    assert_eq!(browser.popup_state, PopupState::Error("Download of postgres/db.dump cancelled".to_string()));
+
+    // A new token lets the same download run to completion
+    browser.cancel = CancellationToken::new();
+    let path = browser.download_snapshot(&snapshot, &target).await.unwrap();
+    assert!(path.is_some());
+    assert_eq!(std::fs::read(&target).unwrap(), vec![7u8; 4096]);
+}
+
+#[tokio::test]
+async fn test_aborted_restore_says_what_became_of_the_database() {
+    let none = None;
+    let mut app = RustoredApp::new(
+        &none, &none, &none, &none, &none, &none, false,
+        &none, &None, &none, &none, false, &none, &none, &none, &none,
+    );
+    app.pg_config.host = Some("localhost".to_string());
+    app.pg_config.port = Some(1);
+    app.pg_config.db_name = Some("orders".to_string());
+    let snapshot = rustored::ui::models::BackupMetadata::new("postgres/orders.dump", 1024, 0.0);
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+    // Every operation started from here on is cancelled before it connects
+    app.cancel.cancel();
+
+    app.restore_snapshot(&snapshot, &mut terminal, "orders.dump").await.unwrap();
+    assert_eq!(app.popup_state, PopupState::Error("Restore aborted, the partially restored database was left in place".to_string()));
+
+    app.restore_options.drop_on_cancel = true;
+    app.restore_snapshot(&snapshot, &mut terminal, "orders.dump").await.unwrap();
+    assert_eq!(app.popup_state, PopupState::Error("Restore aborted, the partially restored database was dropped".to_string()));
+    assert!(app.restore_options.summary().ends_with("drop if cancelled"));
+}
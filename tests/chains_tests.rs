@@ -0,0 +1,87 @@
+use rustored::chains::{assemble_chains, parse_lsn, table, verify_chains, ChainManifest, LinkCheck};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::S3Config;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+fn link(backup: &str, parent: Option<&str>, start: u64, end: u64) -> LinkCheck {
+    LinkCheck {
+        backup: backup.to_string(),
+        parent: parent.map(str::to_string),
+        lsn_range: Some((start, end)),
+        problems: Vec::new(),
+    }
+}
+
+#[test]
+fn test_parse_lsn() {
+    assert_eq!(parse_lsn("0/3000028").unwrap(), 0x3000028);
+    assert_eq!(parse_lsn("16/B374D848").unwrap(), (0x16 << 32) | 0xB374D848);
+    assert!(parse_lsn("3000028").is_err());
+    assert!(parse_lsn("0/zz").is_err());
+}
+
+#[test]
+fn test_assemble_chains_reports_gaps_and_missing_parents() {
+    let links = vec![
+        link("base", None, 0, 100),
+        link("incr-1", Some("base"), 100, 200),
+        link("incr-2", Some("incr-1"), 250, 300),
+        link("orphan", Some("gone"), 0, 10),
+    ];
+    let reports = assemble_chains(&links);
+    assert_eq!(reports.len(), 2);
+
+    let main = reports.iter().find(|r| r.tip == "incr-2").unwrap();
+    assert_eq!(main.backups, vec!["incr-2", "incr-1", "base"]);
+    assert_eq!(main.problems, vec!["WAL gap between incr-1 and incr-2"]);
+
+    let orphan = reports.iter().find(|r| r.tip == "orphan").unwrap();
+    assert_eq!(orphan.problems, vec!["parent gone of orphan has no manifest"]);
+
+    let rendered = table(&reports).render(false);
+    assert!(rendered.starts_with("STATE   TIP     BACKUPS  PROBLEMS\n"), "{}", rendered);
+    assert!(rendered.contains("broken  incr-2        3  WAL gap between incr-1 and incr-2\n"), "{}", rendered);
+}
+
+/// Upload a backup with contents `body` and its manifest
+async fn put_link(store: &CloudStore, backup: &str, body: &[u8], parent: Option<&str>, start: &str, end: &str) {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), body).unwrap();
+    store.upload_file(file.path(), backup, None).await.unwrap();
+    let manifest = ChainManifest {
+        backup: backup.to_string(),
+        parent: parent.map(str::to_string),
+        start_lsn: start.to_string(),
+        end_lsn: end.to_string(),
+        sha256: Some(hex::encode(Sha256::digest(body))),
+    };
+    store.write_object(&ChainManifest::manifest_key(backup), serde_json::to_vec(&manifest).unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_verify_chains_finds_corrupt_link() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    put_link(&store, "pg/orders-base.tar", b"base", None, "0/1000000", "0/2000000").await;
+    put_link(&store, "pg/orders-incr1.tar", b"incr1", Some("pg/orders-base.tar"), "0/2000000", "0/3000000").await;
+    put_link(&store, "pg/users-base.tar", b"users", None, "0/1000000", "0/2000000").await;
+    put_link(&store, "pg/users-incr1.tar", b"incr1", Some("pg/users-base.tar"), "0/2000000", "0/3000000").await;
+
+    // Corrupt the users base backup after its manifest was written
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), b"usres").unwrap();
+    store.upload_file(file.path(), "pg/users-base.tar", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "pg/".to_string(), ..Default::default() });
+    browser.set_store(store);
+    let reports = verify_chains(&mut browser, 2).await.unwrap();
+    assert_eq!(reports.len(), 2);
+
+    // Broken chains are reported first
+    assert_eq!(reports[0].tip, "pg/users-incr1.tar");
+    assert!(!reports[0].is_ok());
+    assert!(reports[0].problems[0].contains("pg/users-base.tar checksum"));
+    assert_eq!(reports[1].tip, "pg/orders-incr1.tar");
+    assert!(reports[1].is_ok());
+}
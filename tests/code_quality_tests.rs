@@ -177,7 +177,7 @@ fn test_function_logging() {
             }
             
             // Count braces to track function body
-            if let Some(_) = current_fn {
+            if current_fn.is_some() {
                 brace_count += line.matches('{').count() as i32;
                 brace_count -= line.matches('}').count() as i32;
                 
@@ -188,10 +188,11 @@ fn test_function_logging() {
                 
                 // Function ended
                 if brace_count == 0 && line.contains('}') {
-                    if !has_logging && current_fn.is_some() {
-                        functions_without_logging.insert(current_fn.unwrap());
+                    if let Some(name) = current_fn.take() {
+                        if !has_logging {
+                            functions_without_logging.insert(name);
+                        }
                     }
-                    current_fn = None;
                 }
             }
         }
@@ -272,7 +273,7 @@ fn test_file_size_limits() {
                     large_files.push(file_name.to_string_lossy().to_string());
                 }
             }
-            warning.push_str("\n");
+            warning.push('\n');
         }
         
         // Process files exceeding hard limit
@@ -292,7 +293,7 @@ fn test_file_size_limits() {
                     large_files.push(file_name.to_string_lossy().to_string());
                 }
             }
-            warning.push_str("\n");
+            warning.push('\n');
         }
         
         warning.push_str("\nRefactoring Recommendations:\n");
@@ -0,0 +1,31 @@
+use rustored::compare::{ChangeSummary, DatabaseStats};
+
+fn stats(tables: &[(&str, i64)]) -> DatabaseStats {
+    DatabaseStats { tables: tables.iter().map(|(t, n)| (t.to_string(), *n)).collect() }
+}
+
+#[test]
+fn test_compare_reports_added_removed_and_changed_tables() {
+    let live = stats(&[("public.orders", 1200), ("public.users", 50), ("public.audit", 9)]);
+    let restored = stats(&[("public.orders", 1100), ("public.users", 50), ("public.coupons", 3)]);
+    let summary = ChangeSummary::compare("orders", "apple-restored", Some(&live), &restored);
+
+    assert_eq!(summary.added, vec![("public.coupons".to_string(), 3)]);
+    assert_eq!(summary.removed, vec![("public.audit".to_string(), 9)]);
+    assert_eq!(summary.changed.len(), 1);
+    assert_eq!(summary.unchanged, 1);
+    assert_eq!(summary.lines(), vec![
+        "apple-restored vs live orders: 1 added, 1 removed, 1 changed, 1 unchanged tables",
+        "+ public.coupons (3 rows)",
+        "- public.audit (9 rows)",
+        "~ public.orders: 1200 -> 1100 rows (-100)",
+    ]);
+}
+
+#[test]
+fn test_compare_without_live_database() {
+    let restored = stats(&[("public.orders", 1)]);
+    let summary = ChangeSummary::compare("orders", "apple-restored", None, &restored);
+    assert!(!summary.live_exists);
+    assert_eq!(summary.lines(), vec!["No live database named orders to compare apple-restored with"]);
+}
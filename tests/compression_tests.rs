@@ -0,0 +1,85 @@
+use rustored::compression::{decompress_file, detect_file, Codec, Compression};
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_bulk::bulk_restore;
+use rustored::mapping::RestoreMapping;
+use rustored::ui::models::BulkConfig;
+use std::process::Stdio;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Compress `contents` into `output` with gzip, as a dump would be
+async fn gzip(contents: &[u8], output: &std::path::Path) {
+    let input = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(input.path(), contents).unwrap();
+    let compression = Compression::new(Codec::Gzip, Some(6)).unwrap();
+    let child = compression.spawn(Stdio::from(std::fs::File::open(input.path()).unwrap()), output).unwrap();
+    assert!(child.wait_with_output().await.unwrap().status.success());
+}
+
+#[test]
+fn test_detect_codec_from_magic_bytes() {
+    assert_eq!(Codec::detect(&[0x1f, 0x8b, 0x08, 0x00]), Some(Codec::Gzip));
+    assert_eq!(Codec::detect(&[0x28, 0xb5, 0x2f, 0xfd]), Some(Codec::Zstd));
+    assert_eq!(Codec::detect(&[0x04, 0x22, 0x4d, 0x18]), Some(Codec::Lz4));
+    assert_eq!(Codec::detect(b"PGDMP"), None);
+    assert_eq!(Codec::detect(&[0x1f]), None);
+}
+
+#[test]
+fn test_codec_from_file_name() {
+    assert_eq!(Codec::from_name("postgres/orders.sql.gz"), Some(Codec::Gzip));
+    assert_eq!(Codec::from_name("postgres/orders.sql.zst"), Some(Codec::Zstd));
+    assert_eq!(Codec::from_name("postgres/orders.sql.lz4"), Some(Codec::Lz4));
+    assert_eq!(Codec::from_name("postgres/orders.sql"), None);
+    assert_eq!(Codec::from_name("postgres/orders.gzip"), None);
+}
+
+#[test]
+fn test_compression_levels_and_output_path() {
+    let zstd = Compression::new(Codec::Zstd, Some(19)).unwrap();
+    assert_eq!(zstd.output_path("orders.sql"), "orders.sql.zst");
+    assert_eq!(zstd.output_path("orders.sql.zst"), "orders.sql.zst");
+    let err = Compression::new(Codec::Gzip, Some(12)).unwrap_err();
+    assert_eq!(err.to_string(), "gzip compression level must be 1 to 9, not 12");
+    assert!(Compression::new(Codec::Lz4, None).is_ok());
+}
+
+#[tokio::test]
+async fn test_decompress_file_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let compressed = dir.path().join("orders.sql.gz");
+    gzip(b"select 1;\n", &compressed).await;
+    assert_eq!(detect_file(&compressed).unwrap(), Some(Codec::Gzip));
+
+    let decompressed = decompress_file(&compressed).await.unwrap().unwrap();
+    assert_eq!(std::fs::read(decompressed.path()).unwrap(), b"select 1;\n");
+    // The copy is written next to the snapshot
+    assert_eq!(decompressed.path().parent(), Some(dir.path()));
+
+    // Uncompressed files are read as they are
+    let plain = dir.path().join("orders.sql");
+    std::fs::write(&plain, b"select 1;\n").unwrap();
+    assert!(decompress_file(&plain).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_bulk_restore_reads_compressed_snapshot() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[{"index":{"status":201}}]}"#))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("orders.json.gz");
+    gzip(b"{\"_id\":\"1\",\"_source\":{\"level\":\"warn\"}}\n", &snapshot).await;
+    let mut dead_letters =
+        DeadLetterWriter::new("elasticsearch", "http://es", "orders", "documents", dir.path().join("orders.dead-letter.ndjson"));
+    let bulk = BulkConfig { disable_refresh: false, ..Default::default() };
+
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", &snapshot, &bulk, &RestoreMapping::default(), None, &mut dead_letters)
+        .await
+        .unwrap();
+    assert_eq!(indexed, 1);
+}
@@ -0,0 +1,148 @@
+use rustored::config_schema::check;
+use rustored::gc::GcSchedule;
+use rustored::mapping::RestoreMapping;
+use rustored::presets::{RestoreMode, RestoreOptions, RestorePreset, RestorePresets, SCHEMA};
+use rustored::prepare::TargetProfile;
+use rustored::profiles::{ConnectionProfile, TargetServer};
+use rustored::recipes::RecipeRef;
+use rustored::retention::RetentionPolicy;
+use rustored::schedule::ScheduleJob;
+
+const TYPOS: &str = r#"[[preset]]
+pattern = "orders*"
+drop_indexs = true
+jobs = "many"
+masking_ruleset = "gdpr"
+recipes = ["truncate-audit-tables", 3]
+
+[[preset]]
+pattern = "orders*"
+
+[[schedule]]
+database = "orders"
+jitter_second = 60
+
+[[target]]
+name = "staging"
+settings = { work_mem = 64 }
+
+[retention]
+daily = 7
+"#;
+
+#[test]
+fn test_presets_report_every_problem_with_its_position() {
+    let issues: Vec<String> = check(TYPOS, &SCHEMA).iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(issues, vec![
+        "line 3, column 1: unknown key `drop_indexs` in [[preset]], did you mean `drop_indexes`?",
+        "line 4, column 8: `jobs` in [[preset]] must be an integer, found a string",
+        "line 5, column 1: `masking_ruleset` in [[preset]] has no effect without `post_hook`",
+        "line 6, column 37: recipes in [[preset]] must be names or tables, found an integer",
+        "line 8, column 1: [[preset]] with pattern = \"orders*\" repeats the one on line 1, which is always used instead",
+        "line 11, column 1: [[schedule]] is missing the required key `cron`",
+        "line 13, column 1: unknown key `jitter_second` in [[schedule]], did you mean `jitter_seconds`?",
+        "line 17, column 25: `settings.work_mem` in [[target]] must be a string, found an integer",
+        "line 19, column 1: [[retention]] must be an array of tables, found a table",
+    ]);
+    let error = RestorePresets::parse(TYPOS).unwrap_err().to_string();
+    assert!(error.starts_with("Invalid restore presets:\n  line 3, column 1"));
+}
+
+#[test]
+fn test_unrelated_keys_list_the_expected_ones() {
+    let issues = check("[[schedule]]\ndatabase = \"orders\"\ncron = \"0 3 * * *\"\nwhen = \"nightly\"\n", &SCHEMA);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].line, 4);
+    assert!(issues[0].message.contains("expected one of `database`, `cron`, `jitter_seconds`, `label`, `jobs`"));
+    assert!(check("[[preset]]\npattern = \"x\"\njobs = 0\n", &SCHEMA)[0].message.contains("at least 1"));
+}
+
+#[test]
+fn test_syntax_errors_are_left_to_the_parser() {
+    assert!(check("[[preset]\npattern = ", &SCHEMA).is_empty());
+    assert!(RestorePresets::parse("[[preset]\npattern = ").unwrap_err().to_string().contains("line 1"));
+}
+
+#[test]
+fn test_schema_accepts_everything_the_presets_serialize_to() {
+    let presets = RestorePresets {
+        presets: vec![RestorePreset {
+            pattern: "orders*".to_string(),
+            options: RestoreOptions {
+                jobs: Some(8),
+                drop_indexes: true,
+                masking_ruleset: Some("gdpr".to_string()),
+                pre_hook: Some("/usr/local/bin/pause-traffic".to_string()),
+                post_hook: Some("https://hooks.internal/mask".to_string()),
+                post_sql: Some("ANALYZE;".to_string()),
+                recipes: vec![RecipeRef::new("truncate-audit-tables"), RecipeRef::parse("reset-passwords:password=dev").unwrap()],
+                compare_live: true,
+                globals: true,
+                drop_on_cancel: true,
+                restore_command: Some("legacy-restore {file} {db}".to_string()),
+                row_filters: Some("filters/orders.toml".to_string()),
+                gentle: true,
+                section_pause_seconds: Some(60),
+                copy_rate: Some("20M".to_string()),
+                // Not part of the file
+                mode: RestoreMode::New,
+                include_tables: Vec::new(),
+                exclude_tables: Vec::new(),
+            },
+        }],
+        targets: vec![TargetProfile {
+            name: "staging".to_string(),
+            roles: vec!["app".to_string()],
+            extensions: vec!["pg_trgm".to_string()],
+            settings: [("work_mem".to_string(), "64MB".to_string())].into(),
+        }],
+        schedules: vec![ScheduleJob {
+            database: "orders".to_string(),
+            cron: "0 3 * * *".to_string(),
+            jitter_seconds: 60,
+            label: Some("nightly".to_string()),
+            jobs: Some(4),
+        }],
+        gc: vec![GcSchedule { cron: "0 4 * * *".to_string(), older_than: "7d".to_string() }],
+        retention: vec![RetentionPolicy { prefix: "postgres/orders-".to_string(), daily: 7, weekly: 4, monthly: 12 }],
+        profiles: vec![ConnectionProfile {
+            name: "staging".to_string(),
+            environment: Some("staging".to_string()),
+            host: Some("db.staging".to_string()),
+            port: Some(5433),
+            username: Some("restore".to_string()),
+            db_name: Some("orders".to_string()),
+            use_ssl: Some(true),
+            target_db: Some("orders_staging".to_string()),
+            bucket: Some("acme-backups".to_string()),
+            region: Some("us-east-1".to_string()),
+            prefix: Some("postgres".to_string()),
+            endpoint_url: Some("https://s3.internal".to_string()),
+            access_key_id: Some("AKIA".to_string()),
+            path_style: Some(false),
+            servers: vec![TargetServer {
+                name: "staging-b".to_string(),
+                environment: Some("qa".to_string()),
+                host: "db-b.staging".to_string(),
+                port: Some(6432),
+                username: Some("restore".to_string()),
+                use_ssl: Some(false),
+                target_db: Some("orders_b".to_string()),
+            }],
+        }],
+        naming_template: Some("{db}-{timestamp}.dump".to_string()),
+        source: None,
+    };
+    let contents = toml::to_string(&presets).unwrap();
+    assert_eq!(check(&contents, &SCHEMA), Vec::new(), "schema is missing keys of:\n{}", contents);
+    assert_eq!(RestorePresets::parse(&contents).unwrap(), presets);
+}
+
+#[test]
+fn test_mapping_reports_misspelled_and_shadowed_rules() {
+    let mapping = "[[name]]\nfrom = \"prod-*\"\nto = \"staging-*\"\n\n[[name]]\nfrom = \"prod-*\"\nto = \"dev-*\"\n\n[[field]]\nfrm = \"a\"\nto = \"b\"\n";
+    let error = RestoreMapping::parse(mapping).unwrap_err().to_string();
+    assert!(error.contains("line 5, column 1: [[name]] with from = \"prod-*\" repeats the one on line 1"), "{}", error);
+    assert!(error.contains("line 9, column 1: [[field]] is missing the required key `from`"), "{}", error);
+    assert!(error.contains("line 10, column 1: unknown key `frm` in [[field]], did you mean `from`?"), "{}", error);
+}
@@ -0,0 +1,267 @@
+use rustored::mapping::RestoreMapping;
+use rustored::dead_letter::{dead_letter_path, read_dead_letters, retry_dead_letters, DeadLetterWriter, NdjsonBatches, RetryTargets};
+use rustored::qdrant_points::{upload_points, QdrantConnection};
+use serde_json::json;
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn qdrant_dead_letters(dir: &tempfile::TempDir, host: &str) -> DeadLetterWriter {
+    DeadLetterWriter::new("qdrant", host, "products", "points", dir.path().join("products.dead-letter.ndjson"))
+}
+
+/// Snapshot file holding the given NDJSON lines
+fn snapshot(lines: &[serde_json::Value]) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let text: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    std::fs::write(file.path(), text.join("\n") + "\n").unwrap();
+    file
+}
+
+#[test]
+fn test_dead_letter_path_is_timestamped() {
+    let started = chrono::DateTime::parse_from_rfc3339("2024-06-01T03:00:00Z").unwrap().with_timezone(&chrono::Utc);
+    let path = dead_letter_path(std::path::Path::new("/tmp"), "orders", started);
+    assert_eq!(path, std::path::PathBuf::from("/tmp/orders-20240601T030000Z.dead-letter.ndjson"));
+}
+
+#[test]
+fn test_writer_round_trips_and_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = DeadLetterWriter::new("elasticsearch", "http://es:9200", "orders", "documents", dir.path().join("orders.ndjson"));
+    writer.write(json!({"n": 1}), "mapper_parsing_exception: bad").unwrap();
+    writer.write(json!("not json"), "invalid JSON: expected value").unwrap();
+    let report = writer.finish().unwrap().unwrap();
+
+    assert_eq!(report.count, 2);
+    assert_eq!(
+        report.summary(),
+        format!(
+            "2 documents rejected, written to {0}; fix the cause and run `rustored retry-failed {0}`",
+            report.path.display()
+        )
+    );
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 2);
+    assert_eq!((letters[0].target.as_str(), letters[0].host.as_str()), ("elasticsearch", "http://es:9200"));
+    assert_eq!(letters[0].record, json!({"n": 1}));
+    assert_eq!(letters[1].reason, "invalid JSON: expected value");
+}
+
+#[test]
+fn test_writer_without_rejections_writes_no_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let writer = qdrant_dead_letters(&dir, "http://qdrant");
+    assert_eq!(writer.finish().unwrap(), None);
+    assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+}
+
+#[tokio::test]
+async fn test_snapshot_batches_dead_letter_invalid_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "{\"n\":1}\n\nnot json\n{\"n\":2}\n{\"n\":3}\n").unwrap();
+    let mut dead_letters = qdrant_dead_letters(&dir, "http://qdrant");
+    let mut snapshot = NdjsonBatches::open(file.path(), 2).await.unwrap();
+
+    let first = snapshot.next_batch(&mut dead_letters).await.unwrap();
+    assert_eq!(first, Some(vec![json!({"n": 1}), json!({"n": 2})]));
+    let second = snapshot.next_batch(&mut dead_letters).await.unwrap();
+    assert_eq!(second, Some(vec![json!({"n": 3})]));
+    assert_eq!(snapshot.next_batch(&mut dead_letters).await.unwrap(), None);
+    assert_eq!(snapshot.progress(), 1.0);
+
+    let report = dead_letters.finish().unwrap().unwrap();
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].record, json!("not json"));
+    assert!(letters[0].reason.starts_with("invalid JSON: "));
+}
+
+#[tokio::test]
+async fn test_snapshot_lines_skip_blank_lines_unparsed() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "cpu value=1\n   \ncpu value=2\n").unwrap();
+    let mut snapshot = NdjsonBatches::open(file.path(), 10).await.unwrap();
+
+    let lines = snapshot.next_lines().await.unwrap();
+    assert_eq!(lines, Some(vec!["cpu value=1".to_string(), "cpu value=2".to_string()]));
+    assert_eq!(snapshot.next_lines().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_upload_points_retries_rejected_batch_point_by_point() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/collections/products")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products"))
+        .and(body_json(json!({"vectors": {"size": 2, "distance": "Cosine"}})))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let bad = json!({"id": 2, "vector": [0.1]});
+    let rejection = json!({"status": {"error": "Wrong input: Vector dimension error: expected dim: 2, got 1"}});
+    // The whole batch and the bad point on its own are rejected
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .and(header("api-key", "secret"))
+        .and(body_json(json!({"points": [{"id": 1, "vector": [0.1, 0.2]}, bad, {"id": 3, "vector": [0.3, 0.4]}]})))
+        .respond_with(ResponseTemplate::new(400).set_body_json(&rejection))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .and(body_json(json!({"points": [bad]})))
+        .respond_with(ResponseTemplate::new(400).set_body_json(&rejection))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let file = snapshot(&[json!({"id": 1, "vector": [0.1, 0.2]}), bad.clone(), json!({"id": 3, "vector": [0.3, 0.4]})]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut dead_letters = qdrant_dead_letters(&dir, &server.uri());
+    let loaded = upload_points(&QdrantConnection::http(reqwest::Client::new(), &server.uri(), Some("secret")), "products", file.path(), &RestoreMapping::default(), None, &mut dead_letters)
+        .await
+        .unwrap();
+
+    assert_eq!(loaded, 2);
+    let report = dead_letters.finish().unwrap().unwrap();
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].record, bad);
+    assert!(letters[0].reason.contains("Vector dimension error"));
+}
+
+#[tokio::test]
+async fn test_upload_points_fails_on_server_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/collections/products")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let file = snapshot(&[json!({"id": 1, "vector": [0.1, 0.2]})]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut dead_letters = qdrant_dead_letters(&dir, &server.uri());
+    let err = upload_points(&QdrantConnection::http(reqwest::Client::new(), &server.uri(), None), "products", file.path(), &RestoreMapping::default(), None, &mut dead_letters)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("503"));
+    assert_eq!(dead_letters.count(), 0);
+}
+
+#[tokio::test]
+async fn test_retry_dead_letters_loads_recorded_points() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/collections/products")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .and(body_json(json!({"points": [{"id": 2, "vector": [0.1, 0.2]}]})))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // The file names a host that is not running; the retry is pointed at the mock instead
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = qdrant_dead_letters(&dir, "http://127.0.0.1:9");
+    writer.write(json!({"id": 2, "vector": [0.1, 0.2]}), "Wrong input").unwrap();
+    let report = writer.finish().unwrap().unwrap();
+
+    let outcome = retry_dead_letters(&report.path, Some(&server.uri()), &RetryTargets::default()).await.unwrap();
+    assert_eq!((outcome.retried, outcome.loaded()), (1, 1));
+    assert_eq!(outcome.dead_letters, None);
+}
+
+#[tokio::test]
+async fn test_retry_dead_letters_rejects_empty_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("empty.dead-letter.ndjson");
+    std::fs::write(&path, "").unwrap();
+    let err = retry_dead_letters(&path, None, &RetryTargets::default()).await.unwrap_err();
+    assert!(err.to_string().contains("holds no records"));
+}
+
+#[tokio::test]
+async fn test_retry_dead_letters_uses_the_recorded_targets_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/indexes/movies"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"uid": "movies", "primaryKey": "movie_id"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/indexes/movies/documents"))
+        .and(header("authorization", "Bearer meili-secret"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(json!({"taskUid": 1})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/tasks/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"uid": 1, "status": "succeeded"})))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = DeadLetterWriter::new("meilisearch", &server.uri(), "movies", "documents", dir.path().join("movies.dead-letter.ndjson"));
+    writer.write(json!({"movie_id": 1, "title": "Movie 1"}), "invalid_document_fields").unwrap();
+    let report = writer.finish().unwrap().unwrap();
+
+    // A profile with keys for several targets sends each target only its own
+    let mut targets = RetryTargets::default();
+    targets.qdrant.api_key = Some("qdrant-secret".to_string());
+    targets.meilisearch.api_key = Some("meili-secret".to_string());
+    let outcome = retry_dead_letters(&report.path, None, &targets).await.unwrap();
+    assert_eq!((outcome.retried, outcome.loaded()), (1, 1));
+}
+
+#[tokio::test]
+async fn test_retry_weaviate_dead_letters_with_weaviate_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/v1/schema/Articles")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/batch/objects"))
+        .and(header("authorization", "Bearer weaviate-secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"id": "00000000-0000-0000-0000-000000000001", "result": {}}])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = DeadLetterWriter::new("weaviate", &server.uri(), "Articles", "objects", dir.path().join("articles.dead-letter.ndjson"));
+    writer.write(json!({"id": "00000000-0000-0000-0000-000000000001", "properties": {"title": "x"}}), "invalid text property").unwrap();
+    let report = writer.finish().unwrap().unwrap();
+
+    let mut targets = RetryTargets::default();
+    targets.meilisearch.api_key = Some("meili-secret".to_string());
+    targets.weaviate.api_key = Some("weaviate-secret".to_string());
+    let outcome = retry_dead_letters(&report.path, None, &targets).await.unwrap();
+    assert_eq!((outcome.retried, outcome.loaded()), (1, 1));
+}
+
+#[tokio::test]
+async fn test_retry_influxdb_dead_letters_never_sends_other_keys() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2/buckets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"buckets": [{"id": "4567", "name": "metrics"}]})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).and(path("/api/v2/write")).respond_with(ResponseTemplate::new(204)).expect(1).mount(&server).await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = DeadLetterWriter::new("influxdb", &server.uri(), "metrics", "points", dir.path().join("metrics.dead-letter.ndjson"));
+    writer.write(json!("cpu,host=a usage=0.5 1700000000"), "field type conflict").unwrap();
+    let report = writer.finish().unwrap().unwrap();
+
+    let mut targets = RetryTargets::default();
+    targets.qdrant.api_key = Some("qdrant-secret".to_string());
+    targets.influxdb.org = Some("acme".to_string());
+    let outcome = retry_dead_letters(&report.path, None, &targets).await.unwrap();
+    assert_eq!(outcome.loaded(), 1);
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|request| !request.headers.contains_key("authorization")));
+}
@@ -0,0 +1,91 @@
+use chrono::{TimeZone, Utc};
+use rustored::archive::{ArchiveStatus, ArchiveTier};
+use rustored::globals::is_globals_key;
+use rustored::storage::{demo_snapshots, DemoStore, SnapshotStore};
+use rustored::ui::models::RestoreTarget as TargetKind;
+use rustored::ui::rustored::RustoredApp;
+use tokio::io::AsyncReadExt;
+
+fn now() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn test_demo_snapshots_cover_every_kind() {
+    let snapshots = demo_snapshots("postgres", now());
+    assert!(snapshots.iter().all(|snapshot| snapshot.key.starts_with("postgres/")));
+    assert!(snapshots.windows(2).all(|pair| pair[0].last_modified >= pair[1].last_modified));
+    assert!(snapshots[0].key.contains("orders"), "the pre-change backup is the newest");
+    assert_eq!(snapshots.iter().filter(|snapshot| is_globals_key(&snapshot.key)).count(), 2);
+    assert!(snapshots.iter().any(|snapshot| snapshot.key.ends_with(".ndjson")));
+    assert!(snapshots.iter().any(|snapshot| snapshot.key.ends_with(".jsonl")));
+    for class in ["STANDARD", "GLACIER", "DEEP_ARCHIVE"] {
+        assert!(snapshots.iter().any(|snapshot| snapshot.storage_class.as_deref() == Some(class)), "no {} snapshot", class);
+    }
+}
+
+#[tokio::test]
+async fn test_demo_store_reads_deterministic_ranges() {
+    let store = DemoStore::new("postgres", now());
+    let key = store.list_snapshots("postgres/").await.unwrap()[0].key.clone();
+    let mut body = Vec::new();
+    store.read_range(&key, 1000, 1999).await.unwrap().read_to_end(&mut body).await.unwrap();
+    assert_eq!(body.len(), 1000);
+    assert_eq!(body[0], (1000 % 251) as u8);
+    let mut again = Vec::new();
+    store.read_range(&key, 1000, 1999).await.unwrap().read_to_end(&mut again).await.unwrap();
+    assert_eq!(body, again);
+    assert!(store.read_range("postgres/missing.dump", 0, 10).await.is_err());
+}
+
+#[tokio::test]
+async fn test_demo_store_simulates_archive_restores() {
+    let store = DemoStore::new("postgres", now());
+    let snapshots = store.list_snapshots("postgres/").await.unwrap();
+    let archived = snapshots.iter().find(|snapshot| snapshot.storage_class.as_deref() == Some("GLACIER")).unwrap();
+    let standard = snapshots.iter().find(|snapshot| snapshot.storage_class.as_deref() == Some("STANDARD")).unwrap();
+    assert_eq!(store.archive_status(&standard.key).await.unwrap(), ArchiveStatus::NotArchived);
+    assert_eq!(store.archive_status(&archived.key).await.unwrap(), ArchiveStatus::Archived);
+    store.request_archive_restore(&archived.key, ArchiveTier::Standard, 1).await.unwrap();
+    assert_eq!(store.archive_status(&archived.key).await.unwrap(), ArchiveStatus::Restoring);
+}
+
+#[tokio::test]
+async fn test_demo_store_keeps_uploads_in_memory() {
+    let store = DemoStore::new("postgres", now());
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), b"demo dump").unwrap();
+    store.upload_file(file.path(), "postgres/mine.dump", None).await.unwrap();
+    let listed = store.list_snapshots("postgres/").await.unwrap();
+    assert_eq!(listed[0].key, "postgres/mine.dump");
+    assert_eq!(listed[0].size, 9);
+    let mut body = Vec::new();
+    store.read_range("postgres/mine.dump", 5, 100).await.unwrap().read_to_end(&mut body).await.unwrap();
+    assert_eq!(body, b"dump");
+
+    store.write_object(".rustored/holds.json", b"{}".to_vec()).await.unwrap();
+    assert_eq!(store.read_object(".rustored/holds.json").await.unwrap().as_deref(), Some(&b"{}"[..]));
+    store.delete_object("postgres/mine.dump").await.unwrap();
+    assert!(store.list_snapshots("postgres/").await.unwrap().iter().all(|snapshot| snapshot.key != "postgres/mine.dump"));
+}
+
+#[tokio::test]
+async fn test_demo_app_restores_to_simulated_targets() {
+    let mut app = RustoredApp::new(
+        &None, &None, &None, &None, &None, &None, false,
+        &None, &None, &None, &None, false, &None,
+        &None, &None, &None,
+    );
+    rustored::demo::enable(&mut app, now());
+    assert!(app.demo);
+    assert_eq!(app.s3_config.bucket, rustored::demo::DEMO_BUCKET);
+
+    let target = app.get_current_restore_target();
+    assert!(target.is_configured());
+    assert!(target.test_connection().await.unwrap().contains("Demo mode"));
+
+    app.restore_target = TargetKind::Elasticsearch;
+    let target = app.get_current_restore_target();
+    let restored = target.restore_snapshot(std::path::Path::new("unused"), None).await.unwrap();
+    assert_eq!(restored, "products");
+}
@@ -0,0 +1,44 @@
+use rustored::directory_dump::{default_jobs, directory_key, is_tar_file, pack_directory, unpack, DIRECTORY_EXTENSION};
+use rustored::hooks::source_db_from_key;
+
+#[test]
+fn test_directory_key_replaces_dump_extension() {
+    assert_eq!(directory_key("postgres/orders-20250101T120000Z.dump"), "postgres/orders-20250101T120000Z.dir.tar");
+    assert_eq!(directory_key("orders-latest"), format!("orders-latest{}", DIRECTORY_EXTENSION));
+    assert_eq!(source_db_from_key("postgres/orders-20250101T120000Z.dir.tar"), "orders");
+}
+
+#[test]
+fn test_default_jobs_is_at_least_one() {
+    assert!(default_jobs() >= 1);
+}
+
+#[tokio::test]
+async fn test_pack_and_unpack_round_trip() {
+    let work = tempfile::tempdir().unwrap();
+    let dump = work.path().join("orders");
+    std::fs::create_dir(&dump).unwrap();
+    std::fs::write(dump.join("toc.dat"), b"PGDMP toc").unwrap();
+    std::fs::write(dump.join("3342.dat.gz"), b"table data").unwrap();
+
+    let tar = work.path().join("orders.dir.tar");
+    pack_directory(&dump, &tar).await.unwrap();
+    assert!(is_tar_file(&tar).unwrap());
+    assert!(!is_tar_file(&dump.join("toc.dat")).unwrap());
+
+    let unpacked = unpack(&tar).await.unwrap();
+    assert!(unpacked.path().starts_with(work.path()), "unpacked next to the snapshot");
+    assert_eq!(std::fs::read(unpacked.path().join("toc.dat")).unwrap(), b"PGDMP toc");
+    assert_eq!(std::fs::read(unpacked.path().join("3342.dat.gz")).unwrap(), b"table data");
+}
+
+#[tokio::test]
+async fn test_unpack_fails_on_a_broken_tar() {
+    let work = tempfile::tempdir().unwrap();
+    let path = work.path().join("broken.dir.tar");
+    let mut header = vec![0u8; 512];
+    header[257..262].copy_from_slice(b"ustar");
+    std::fs::write(&path, &header[..300]).unwrap();
+    assert!(is_tar_file(&path).unwrap());
+    assert!(unpack(&path).await.is_err());
+}
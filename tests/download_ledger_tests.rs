@@ -0,0 +1,108 @@
+use rustored::download_ledger::DownloadLedger;
+
+#[test]
+fn test_part_ranges_cover_object() {
+    // 10 bytes split into 4 byte parts gives 3 parts, the last one short
+    let ledger = DownloadLedger::new("backups/db.dump", 10, 0.0, 4);
+    assert_eq!(ledger.part_count(), 3);
+    assert_eq!(ledger.part_range(0), (0, 3));
+    assert_eq!(ledger.part_range(1), (4, 7));
+    assert_eq!(ledger.part_range(2), (8, 9));
+}
+
+#[test]
+fn test_resume_verifies_parts_on_disk() {
+    // Write a partial download with two completed parts
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.dump");
+    std::fs::write(&path, b"aaaabbbbcc").unwrap();
+
+    let mut ledger = DownloadLedger::new("backups/db.dump", 10, 1.0, 4);
+    ledger.record_part(0, DownloadLedger::checksum(b"aaaa"));
+    ledger.record_part(1, DownloadLedger::checksum(b"bbbb"));
+    ledger.save(&path).unwrap();
+
+    // Corrupt the second part after it was recorded
+    std::fs::write(&path, b"aaaaXbbbcc").unwrap();
+
+    // Only the intact part survives resuming
+    let resumed = DownloadLedger::resume_or_new(&path, "backups/db.dump", 10, 1.0, None, 4);
+    assert!(resumed.is_part_complete(0));
+    assert!(!resumed.is_part_complete(1));
+    assert_eq!(resumed.completed_bytes(), 4);
+}
+
+#[test]
+fn test_resume_discards_ledger_for_changed_object() {
+    // A ledger for an older version of the object must not be reused
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.dump");
+    std::fs::write(&path, b"aaaa").unwrap();
+
+    let mut ledger = DownloadLedger::new("backups/db.dump", 4, 1.0, 4);
+    ledger.record_part(0, DownloadLedger::checksum(b"aaaa"));
+    ledger.save(&path).unwrap();
+
+    let resumed = DownloadLedger::resume_or_new(&path, "backups/db.dump", 4, 2.0, None, 4);
+    assert!(resumed.parts.is_empty());
+
+    // Removing the ledger cleans up the sidecar file
+    DownloadLedger::remove(&path).unwrap();
+    assert!(!DownloadLedger::ledger_path(&path).exists());
+}
+
+#[test]
+fn test_resume_requires_matching_etag() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.dump");
+    std::fs::write(&path, b"aaaabbbbcc").unwrap();
+
+    let mut ledger = DownloadLedger::resume_or_new(&path, "backups/db.dump", 10, 1.0, Some("\"v1\""), 4);
+    ledger.record_part(0, DownloadLedger::checksum(b"aaaa"));
+    ledger.save(&path).unwrap();
+
+    // Same etag resumes from the recorded byte offset
+    assert_eq!(DownloadLedger::resumable_bytes(&path, "backups/db.dump", 10, 1.0, Some("\"v1\"")), Some(4));
+    assert!(DownloadLedger::resume_or_new(&path, "backups/db.dump", 10, 1.0, Some("\"v1\""), 4).is_part_complete(0));
+
+    // An object re-uploaded with the same size and timestamp is caught by its etag
+    assert_eq!(DownloadLedger::resumable_bytes(&path, "backups/db.dump", 10, 1.0, Some("\"v2\"")), None);
+    let restarted = DownloadLedger::resume_or_new(&path, "backups/db.dump", 10, 1.0, Some("\"v2\""), 4);
+    assert!(restarted.parts.is_empty());
+    assert_eq!(restarted.etag.as_deref(), Some("\"v2\""));
+
+    // Discarding removes both the partial file and its ledger
+    DownloadLedger::discard(&path).unwrap();
+    assert!(!path.exists());
+    assert!(!DownloadLedger::ledger_path(&path).exists());
+}
+
+#[tokio::test]
+async fn test_parallel_download_assembles_parts_in_order() {
+    use rustored::download_ledger::DEFAULT_PART_SIZE;
+    use rustored::storage::{CloudStore, SnapshotStore};
+    use rustored::ui::browser::SnapshotBrowser;
+    use rustored::ui::models::S3Config;
+    use std::sync::Arc;
+
+    // Two full parts and a short third one, each with distinct content
+    let size = (DEFAULT_PART_SIZE * 2 + 1234) as usize;
+    let content: Vec<u8> = (0..size).map(|i| (i / 7 % 251) as u8).collect();
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source.dump");
+    std::fs::write(&source, &content).unwrap();
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    store.upload_file(&source, "postgres/db.dump", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store);
+    browser.download_concurrency = 3;
+    browser.load_snapshots().await.unwrap();
+    let snapshot = browser.snapshots[0].clone();
+
+    let target = dir.path().join("db.dump");
+    let path = browser.download_snapshot(&snapshot, &target).await.unwrap();
+    assert_eq!(path.as_deref(), Some(target.to_string_lossy().as_ref()));
+    assert_eq!(std::fs::read(&target).unwrap(), content);
+    assert!(!DownloadLedger::ledger_path(&target).exists());
+}
@@ -0,0 +1,148 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use rustored::dead_letter::{read_dead_letters, DeadLetterWriter};
+use rustored::dynamodb_items::{dynamodb_item, import_items, item_json};
+use rustored::mapping::RestoreMapping;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_string_contains, header, method};
+use wiremock::{Mock, MockBuilder, MockServer, ResponseTemplate};
+
+/// DynamoDB client sending its requests to a mock server
+fn client(server: &MockServer) -> aws_sdk_dynamodb::Client {
+    let config = aws_sdk_dynamodb::Config::builder()
+        .behavior_version(aws_sdk_dynamodb::config::BehaviorVersion::latest())
+        .region(aws_sdk_dynamodb::config::Region::new("us-east-1"))
+        .endpoint_url(server.uri())
+        .credentials_provider(aws_sdk_dynamodb::config::Credentials::new("AKID", "secret", None, None, "test"))
+        .build();
+    aws_sdk_dynamodb::Client::from_conf(config)
+}
+
+/// A DynamoDB answer to the operation named by the `X-Amz-Target` header
+fn operation(name: &str) -> MockBuilder {
+    Mock::given(method("POST")).and(header("x-amz-target", format!("DynamoDB_20120810.{}", name).as_str()))
+}
+
+/// A JSON body as DynamoDB sends it
+fn answer(status: u16, body: Value) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(body.to_string(), "application/x-amz-json-1.0")
+}
+
+/// Mount a `movies` table on the mock server
+async fn table(server: &MockServer) {
+    operation("DescribeTable")
+        .respond_with(answer(200, json!({"Table": {"TableName": "movies", "ItemCount": 3, "TableSizeBytes": 120}})))
+        .mount(server)
+        .await;
+}
+
+/// Snapshot of an export line and a plain document
+fn snapshot() -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let lines = [
+        json!({"Item": {"id": {"N": "1"}, "title": {"S": "Alien"}}}).to_string(),
+        json!({"id": 2, "title": "Aliens"}).to_string(),
+    ];
+    std::fs::write(file.path(), lines.join("\n") + "\n").unwrap();
+    file
+}
+
+/// Dead-letter writer for the `movies` table, writing into `dir`
+fn dead_letters(dir: &tempfile::TempDir) -> DeadLetterWriter {
+    DeadLetterWriter::new("dynamodb", "dynamodb.us-east-1", "movies", "items", dir.path().join("movies.dead-letter.ndjson"))
+}
+
+#[test]
+fn test_export_lines_keep_their_types_and_plain_documents_are_typed_from_json() {
+    let exported = json!({"Item": {"id": {"N": "7"}, "tags": {"SS": ["a", "b"]}, "cast": {"L": [{"M": {"name": {"S": "Ripley"}}}]}, "gone": {"NULL": true}}});
+    let item = dynamodb_item(exported.clone(), &RestoreMapping::default()).unwrap();
+    assert_eq!(item["id"], AttributeValue::N("7".to_string()));
+    assert_eq!(item["tags"], AttributeValue::Ss(vec!["a".to_string(), "b".to_string()]));
+    // Export lines survive the trip through a dead-letter file
+    assert_eq!(item_json(&item), exported);
+
+    let plain = dynamodb_item(json!({"id": 7, "title": "Alien", "seen": true, "cast": ["Ripley"]}), &RestoreMapping::default()).unwrap();
+    assert_eq!(plain["id"], AttributeValue::N("7".to_string()));
+    assert_eq!(plain["title"], AttributeValue::S("Alien".to_string()));
+    assert_eq!(plain["seen"], AttributeValue::Bool(true));
+    assert_eq!(plain["cast"], AttributeValue::L(vec![AttributeValue::S("Ripley".to_string())]));
+
+    assert!(dynamodb_item(json!({"Item": {"id": {"X": "1"}}}), &RestoreMapping::default()).is_err());
+    assert!(dynamodb_item(json!([1, 2]), &RestoreMapping::default()).is_err());
+}
+
+#[tokio::test]
+async fn test_import_items_resends_unprocessed_items() {
+    let server = MockServer::start().await;
+    table(&server).await;
+    // The first batch is throttled part way, leaving the second item unwritten
+    operation("BatchWriteItem")
+        .respond_with(answer(200, json!({"UnprocessedItems": {"movies": [{"PutRequest": {"Item": {"id": {"N": "2"}, "title": {"S": "Aliens"}}}}]}})))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    operation("BatchWriteItem")
+        .and(body_string_contains("Aliens"))
+        .respond_with(answer(200, json!({"UnprocessedItems": {}})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let file = snapshot();
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let callback = move |fraction: f32| reported.lock().unwrap().push(fraction);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let written = import_items(&client(&server), "movies", file.path(), &RestoreMapping::default(), Some(&callback), &mut rejected)
+        .await
+        .unwrap();
+
+    assert_eq!(written, 2);
+    assert_eq!(progress.lock().unwrap().last().copied(), Some(1.0));
+    assert!(rejected.finish().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_refused_batch_is_written_item_by_item() {
+    let server = MockServer::start().await;
+    table(&server).await;
+    operation("BatchWriteItem")
+        .respond_with(answer(400, json!({"__type": "com.amazonaws.dynamodb.v20120810#ValidationException", "message": "Missing the key id"})))
+        .mount(&server)
+        .await;
+    operation("PutItem").and(body_string_contains("Aliens")).respond_with(answer(200, json!({}))).mount(&server).await;
+    operation("PutItem")
+        .respond_with(answer(400, json!({"__type": "com.amazonaws.dynamodb.v20120810#ValidationException", "message": "Missing the key id"})))
+        .mount(&server)
+        .await;
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let lines = [json!({"title": "Alien"}).to_string(), json!({"id": 2, "title": "Aliens"}).to_string()];
+    std::fs::write(file.path(), lines.join("\n")).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let written = import_items(&client(&server), "movies", file.path(), &RestoreMapping::default(), None, &mut rejected).await.unwrap();
+
+    assert_eq!(written, 1);
+    let report = rejected.finish().unwrap().unwrap();
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 1);
+    // Dead letters are export lines, so a retry keeps the attribute types
+    assert_eq!(letters[0].record, json!({"Item": {"title": {"S": "Alien"}}}));
+    assert_eq!(letters[0].reason, "Missing the key id");
+}
+
+#[tokio::test]
+async fn test_import_items_needs_an_existing_table() {
+    let server = MockServer::start().await;
+    operation("DescribeTable")
+        .respond_with(answer(400, json!({"__type": "com.amazonaws.dynamodb.v20120810#ResourceNotFoundException", "message": "Requested resource not found"})))
+        .mount(&server)
+        .await;
+    let file = snapshot();
+    let dir = tempfile::tempdir().unwrap();
+    let error = import_items(&client(&server), "movies", file.path(), &RestoreMapping::default(), None, &mut dead_letters(&dir)).await.unwrap_err();
+    assert!(error.to_string().contains("does not exist"));
+}
@@ -0,0 +1,77 @@
+use rustored::encryption::{detect_file, plain_key, Cipher, ClientEncryption};
+
+#[test]
+fn test_detect_cipher_from_header() {
+    assert_eq!(Cipher::detect(b"age-encryption.org/v1\n-> X25519 abc"), Some(Cipher::Age));
+    assert_eq!(Cipher::detect(b"-----BEGIN AGE ENCRYPTED FILE-----\n"), Some(Cipher::Age));
+    assert_eq!(Cipher::detect(b"-----BEGIN PGP MESSAGE-----\n"), Some(Cipher::Gpg));
+    assert_eq!(Cipher::detect(&[0x85, 0x02, 0x0c]), Some(Cipher::Gpg));
+    assert_eq!(Cipher::detect(&[0xc1, 0x4c, 0x03]), Some(Cipher::Gpg));
+    assert_eq!(Cipher::detect(b"PGDMP"), None);
+    assert_eq!(Cipher::detect(&[0x1f, 0x8b]), None);
+}
+
+#[test]
+fn test_encrypted_and_plain_keys() {
+    let age = ClientEncryption::new(vec!["age1example".to_string()], vec![], None).unwrap();
+    assert_eq!(age.cipher(), Some(Cipher::Age));
+    assert_eq!(age.encrypted_key("backups/orders-20250101T000000Z.dump"), "backups/orders-20250101T000000Z.dump.age");
+    assert_eq!(ClientEncryption::default().encrypted_key("orders.dump"), "orders.dump");
+
+    assert_eq!(plain_key("backups/orders.dump.age"), "backups/orders.dump");
+    assert_eq!(plain_key("orders.dump.gpg"), "orders.dump");
+    assert_eq!(plain_key("orders.dump"), "orders.dump");
+}
+
+#[test]
+fn test_age_and_gpg_recipients_conflict() {
+    let err = ClientEncryption::new(vec!["age1example".to_string()], vec!["ops@example.com".to_string()], None).unwrap_err();
+    assert_eq!(err.to_string(), "Backups can be encrypted for age or GPG recipients, not both");
+}
+
+#[tokio::test]
+async fn test_plaintext_is_left_alone_and_age_needs_an_identity() {
+    let dir = tempfile::tempdir().unwrap();
+    let plain = dir.path().join("orders.dump");
+    std::fs::write(&plain, b"PGDMP plain archive").unwrap();
+    let encryption = ClientEncryption::default();
+    assert!(encryption.encrypt_file(&plain).await.unwrap().is_none());
+    assert_eq!(encryption.decrypt_in_place(&plain).await.unwrap(), None);
+    assert_eq!(std::fs::read(&plain).unwrap(), b"PGDMP plain archive");
+
+    let encrypted = dir.path().join("orders.dump.age");
+    std::fs::write(&encrypted, b"age-encryption.org/v1\n").unwrap();
+    let err = encryption.decrypt_file(&encrypted).await.unwrap_err();
+    assert!(err.to_string().contains("pass --age-identity"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_gpg_round_trip() {
+    if std::process::Command::new("gpg").arg("--version").output().is_err() {
+        eprintln!("gpg is not installed, skipping");
+        return;
+    }
+    // A throwaway keyring; no other test in this file runs gpg
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("GNUPGHOME", home.path());
+    let generated = std::process::Command::new("gpg")
+        .args(["--batch", "--passphrase", "", "--quick-gen-key", "Rustored Test <test@example.com>", "default", "default", "never"])
+        .output()
+        .unwrap();
+    assert!(generated.status.success(), "{}", String::from_utf8_lossy(&generated.stderr));
+
+    let dir = tempfile::tempdir().unwrap();
+    let plain = dir.path().join("orders.dump");
+    std::fs::write(&plain, b"PGDMP secret rows").unwrap();
+    let encryption = ClientEncryption::new(vec![], vec!["test@example.com".to_string()], None).unwrap();
+    let encrypted = encryption.encrypt_file(&plain).await.unwrap().unwrap();
+    assert_eq!(detect_file(encrypted.path()).unwrap(), Some(Cipher::Gpg));
+    assert!(!std::fs::read(encrypted.path()).unwrap().windows(6).any(|w| w == b"secret"));
+
+    // Downloads are decrypted in place
+    let download = dir.path().join("orders.dump.gpg");
+    std::fs::copy(encrypted.path(), &download).unwrap();
+    assert_eq!(encryption.decrypt_in_place(&download).await.unwrap(), Some(Cipher::Gpg));
+    assert_eq!(std::fs::read(&download).unwrap(), b"PGDMP secret rows");
+    let _ = std::process::Command::new("gpgconf").args(["--kill", "gpg-agent"]).output();
+}
@@ -0,0 +1,82 @@
+use chrono::TimeZone;
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_alias::alias_index_name;
+use rustored::es_bulk::bulk_restore;
+use rustored::mapping::RestoreMapping;
+use rustored::ui::models::BulkConfig;
+use serde_json::json;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Server where alias `orders` points at `orders-old` and a new index takes any bulk request
+async fn aliased_server(count: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_alias/orders"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"orders-old": {"aliases": {"orders": {}}}})))
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD")).and(path_regex(r"^/orders-\d{14}$")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("PUT")).and(path_regex(r"^/orders-\d{14}$")).respond_with(ResponseTemplate::new(200)).expect(1).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[]}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).and(path_regex(r"^/orders-\d{14}/_refresh$")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/orders-\d{14}/_count$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"count": count})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).and(path("/_aliases")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    server
+}
+
+/// Alias-swap restore of two documents into `orders` on `server`
+async fn restore(server: &MockServer) -> anyhow::Result<u64> {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("orders.ndjson");
+    std::fs::write(&snapshot, "{\"sku\":\"A-1\"}\n{\"sku\":\"A-2\"}\n").unwrap();
+    let bulk = BulkConfig { alias_swap: true, ..BulkConfig::new(None, None, true) };
+    let mut dead_letters = DeadLetterWriter::new("elasticsearch", "http://es", "orders", "documents", dir.path().join("orders.dead-letter.ndjson"));
+    bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", &snapshot, &bulk, &RestoreMapping::default(), None, &mut dead_letters).await
+}
+
+#[tokio::test]
+async fn test_alias_moves_to_loaded_index() {
+    let started = chrono::Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap();
+    assert_eq!(alias_index_name("orders", started), "orders-20240601030000");
+
+    let server = aliased_server(2).await;
+    assert_eq!(restore(&server).await.unwrap(), 2);
+
+    let requests = server.received_requests().await.unwrap();
+    let swap = requests.iter().find(|request| request.url.path() == "/_aliases").expect("alias was not moved");
+    let body: serde_json::Value = serde_json::from_slice(&swap.body).unwrap();
+    let actions = body["actions"].as_array().unwrap();
+    assert_eq!(actions[0], json!({"remove": {"index": "orders-old", "alias": "orders"}}));
+    let loaded = actions[1]["add"]["index"].as_str().unwrap();
+    assert!(loaded.starts_with("orders-") && loaded != "orders-old");
+    assert_eq!(actions[1]["add"]["alias"], "orders");
+}
+
+#[tokio::test]
+async fn test_alias_stays_when_document_count_differs() {
+    let server = aliased_server(1).await;
+    let error = restore(&server).await.unwrap_err().to_string();
+    assert!(error.contains("holds 1 documents but 2 were indexed"), "{}", error);
+    assert!(error.contains("still points at orders-old"), "{}", error);
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(!requests.iter().any(|request| request.url.path() == "/_aliases"));
+}
+
+#[tokio::test]
+async fn test_alias_swap_refuses_an_index_name() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/_alias/orders")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    let error = restore(&server).await.unwrap_err().to_string();
+    assert!(error.contains("orders is an index, not an alias"), "{}", error);
+}
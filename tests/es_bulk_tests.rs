@@ -0,0 +1,256 @@
+use rustored::mapping::RestoreMapping;
+use rustored::dead_letter::{read_dead_letters, DeadLetterWriter};
+use rustored::es_bulk::{bulk_body, bulk_restore, MAX_BULK_ATTEMPTS};
+use rustored::ui::models::BulkConfig;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_json, body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Snapshot of `count` documents, alternating search hits and bare documents
+fn snapshot(count: usize) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let lines: Vec<String> = (0..count)
+        .map(|i| match i % 2 {
+            0 => format!(r#"{{"_id":"{}","_source":{{"n":{}}}}}"#, i, i),
+            _ => format!(r#"{{"n":{}}}"#, i),
+        })
+        .collect();
+    std::fs::write(file.path(), lines.join("\n") + "\n").unwrap();
+    file
+}
+
+/// Dead-letter writer for the `orders` index, writing into `dir`
+fn dead_letters(dir: &tempfile::TempDir) -> DeadLetterWriter {
+    DeadLetterWriter::new("elasticsearch", "http://es", "orders", "documents", dir.path().join("orders.dead-letter.ndjson"))
+}
+
+/// Index with a 5s refresh interval that accepts every bulk request
+async fn mock_index(server: &MockServer, bulk_status: &str) {
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(200)).mount(server).await;
+    Mock::given(method("GET"))
+        .and(path("/orders/_settings/index.refresh_interval"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"orders":{"settings":{"index":{"refresh_interval":"5s"}}}}"#))
+        .mount(server)
+        .await;
+    Mock::given(method("PUT")).and(path("/orders/_settings")).respond_with(ResponseTemplate::new(200)).mount(server).await;
+    Mock::given(method("POST")).and(path("/orders/_refresh")).respond_with(ResponseTemplate::new(200)).mount(server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(bulk_status))
+        .mount(server)
+        .await;
+}
+
+/// Refresh intervals set on the index, in order
+async fn refresh_intervals(server: &MockServer) -> Vec<serde_json::Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.method.as_str() == "PUT" && request.url.path() == "/orders/_settings")
+        .map(|request| request.body_json::<serde_json::Value>().unwrap()["index"]["refresh_interval"].clone())
+        .collect()
+}
+
+#[test]
+fn test_bulk_body_keeps_hit_ids() {
+    let documents = vec![serde_json::json!({"_id": "7", "_source": {"n": 7}}), serde_json::json!({"n": 8})];
+    let body = bulk_body("orders", &documents, "index");
+    assert_eq!(
+        body,
+        "{\"index\":{\"_id\":\"7\",\"_index\":\"orders\"}}\n{\"n\":7}\n{\"index\":{\"_index\":\"orders\"}}\n{\"n\":8}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_bulk_restore_batches_and_restores_refresh_interval() {
+    let server = MockServer::start().await;
+    mock_index(&server, r#"{"errors":false,"items":[]}"#).await;
+    let file = snapshot(25);
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let callback = move |fraction: f32| reported.lock().unwrap().push(fraction);
+
+    let bulk = BulkConfig::new(Some(10), Some(2), false);
+    let client = reqwest::Client::new();
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let indexed = bulk_restore(&client, None, &server.uri(), "orders", file.path(), &bulk, &RestoreMapping::default(), Some(&callback), &mut rejected).await.unwrap();
+
+    assert_eq!(indexed, 25);
+    // Nothing was rejected, so no dead-letter file is written
+    assert_eq!(rejected.finish().unwrap(), None);
+    assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    let bulks = server.received_requests().await.unwrap().iter().filter(|r| r.url.path() == "/_bulk").count();
+    assert_eq!(bulks, 3);
+    // Refreshes are off during the load and the old interval is put back after
+    assert_eq!(refresh_intervals(&server).await, vec![serde_json::json!("-1"), serde_json::json!("5s")]);
+    assert_eq!(progress.lock().unwrap().last().copied(), Some(1.0));
+}
+
+#[tokio::test]
+async fn test_bulk_restore_dead_letters_rejected_documents() {
+    let server = MockServer::start().await;
+    mock_index(
+        &server,
+        r#"{"errors":true,"items":[{"index":{"status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse field [n]"}}}]}"#,
+    )
+    .await;
+    let file = snapshot(3);
+    std::fs::write(file.path(), std::fs::read_to_string(file.path()).unwrap() + "not json\n").unwrap();
+
+    let client = reqwest::Client::new();
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let indexed = bulk_restore(&client, None, &server.uri(), "orders", file.path(), &BulkConfig::default(), &RestoreMapping::default(), None, &mut rejected).await.unwrap();
+    assert_eq!(indexed, 2);
+
+    let report = rejected.finish().unwrap().unwrap();
+    assert_eq!(report.count, 2);
+    assert!(report.summary().starts_with("2 documents rejected"));
+    let letters = read_dead_letters(&report.path).unwrap();
+    // The unparseable line is dead-lettered as it is read, before the batch is sent
+    assert_eq!(letters[0].record, serde_json::json!("not json"));
+    assert!(letters[0].reason.starts_with("invalid JSON"));
+    assert_eq!(letters[1].record, serde_json::json!({"_id": "0", "_source": {"n": 0}}));
+    assert_eq!(letters[1].reason, "mapper_parsing_exception: failed to parse field [n]");
+    assert_eq!(letters[1].name, "orders");
+    assert_eq!(refresh_intervals(&server).await, vec![serde_json::json!("-1"), serde_json::json!("5s")]);
+}
+
+#[tokio::test]
+async fn test_bulk_restore_creates_missing_index_and_can_keep_refresh() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("PUT")).and(path("/orders")).respond_with(ResponseTemplate::new(200)).expect(1).mount(&server).await;
+    Mock::given(method("PUT"))
+        .and(path("/orders/_settings"))
+        .and(body_json(serde_json::json!({"index": {"refresh_interval": "-1"}})))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[]}"#))
+        .mount(&server)
+        .await;
+    let file = snapshot(2);
+
+    let bulk = BulkConfig::new(None, None, true);
+    let dir = tempfile::tempdir().unwrap();
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", file.path(), &bulk, &RestoreMapping::default(), None, &mut dead_letters(&dir)).await.unwrap();
+    assert_eq!(indexed, 2);
+}
+
+#[test]
+fn test_bulk_config_defaults_and_floors() {
+    assert_eq!(BulkConfig::new(None, None, false), BulkConfig::default());
+    let bulk = BulkConfig::new(Some(0), Some(0), true);
+    assert_eq!((bulk.batch_size, bulk.concurrency, bulk.disable_refresh), (1, 1, false));
+}
+
+#[tokio::test]
+async fn test_bulk_restore_resends_documents_the_cluster_is_too_busy_for() {
+    let server = MockServer::start().await;
+    // The whole first request is turned away, then one item of the second
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(body_string_contains(r#"{"n":1}"#))
+        .and(body_string_contains(r#"{"n":2}"#))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":429,"error":{"type":"es_rejected_execution_exception","reason":"rejected execution"}}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse field [n]"}}}]}"#,
+        ))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_index(&server, r#"{"errors":false,"items":[]}"#).await;
+    let file = snapshot(3);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", file.path(), &BulkConfig::default(), &RestoreMapping::default(), None, &mut rejected).await.unwrap();
+
+    assert_eq!(indexed, 2);
+    let bulks: Vec<String> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/_bulk")
+        .map(|request| String::from_utf8_lossy(&request.body).to_string())
+        .collect();
+    assert_eq!(bulks.len(), 3);
+    // Only the document turned away as too busy is sent again
+    assert_eq!(bulks[2], "{\"index\":{\"_index\":\"orders\"}}\n{\"n\":1}\n");
+    let report = rejected.finish().unwrap().unwrap();
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].reason, "mapper_parsing_exception: failed to parse field [n]");
+}
+
+#[tokio::test]
+async fn test_bulk_restore_dead_letters_documents_still_turned_away() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .expect(u64::from(MAX_BULK_ATTEMPTS))
+        .mount(&server)
+        .await;
+    mock_index(&server, r#"{"errors":false,"items":[]}"#).await;
+    let file = snapshot(2);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", file.path(), &BulkConfig::default(), &RestoreMapping::default(), None, &mut rejected).await.unwrap();
+
+    assert_eq!(indexed, 0);
+    let report = rejected.finish().unwrap().unwrap();
+    assert_eq!(report.count, 2);
+    assert!(read_dead_letters(&report.path).unwrap()[0].reason.starts_with("still turned away after 5 attempts: cluster answered 429"));
+}
+
+#[tokio::test]
+async fn test_bulk_restore_resends_only_documents_with_ids_after_gateway_errors() {
+    let server = MockServer::start().await;
+    // The proxy times out, though the cluster may have indexed the request
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(504).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_index(&server, r#"{"errors":false,"items":[]}"#).await;
+    let file = snapshot(2);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", file.path(), &BulkConfig::default(), &RestoreMapping::default(), None, &mut rejected).await.unwrap();
+
+    assert_eq!(indexed, 1);
+    let bulks: Vec<String> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/_bulk")
+        .map(|request| String::from_utf8_lossy(&request.body).to_string())
+        .collect();
+    assert_eq!(bulks[1], "{\"index\":{\"_id\":\"0\",\"_index\":\"orders\"}}\n{\"n\":0}\n");
+    let report = rejected.finish().unwrap().unwrap();
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].record, serde_json::json!({"n": 1}));
+    assert!(letters[0].reason.starts_with("cluster answered 504"), "{}", letters[0].reason);
+}
@@ -0,0 +1,122 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_bulk::bulk_restore;
+use rustored::es_data_stream::{is_data_stream_dump, template_body};
+use rustored::es_index::{sidecar_path, IndexDefinition};
+use rustored::mapping::RestoreMapping;
+use rustored::ui::models::BulkConfig;
+use serde_json::json;
+use wiremock::matchers::{body_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// The `GET /_index_template/logs-app` answer of a data stream template
+fn exported() -> serde_json::Value {
+    json!({
+        "index_templates": [{
+            "name": "logs-app",
+            "index_template": {
+                "index_patterns": ["logs-app*"],
+                "data_stream": {"hidden": false, "allow_custom_routing": false},
+                "priority": 200,
+                "template": {
+                    "settings": {"index": {"number_of_shards": "2", "lifecycle": {"name": "logs"}}},
+                    "mappings": {"properties": {"@timestamp": {"type": "date"}}}
+                }
+            }
+        }]
+    })
+}
+
+/// Bulk request bodies the server received, in order
+async fn bulks(server: &MockServer) -> Vec<String> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/_bulk")
+        .map(|request| String::from_utf8_lossy(&request.body).to_string())
+        .collect()
+}
+
+#[test]
+fn test_stored_template_is_narrowed_to_the_restored_stream() {
+    let definition = IndexDefinition::from_json(exported().to_string().as_bytes()).unwrap();
+    assert!(definition.is_data_stream());
+    assert!(!IndexDefinition::from_json(br#"{"settings": {}}"#).unwrap().is_data_stream());
+
+    let bulk = BulkConfig { replicas: Some(0), ..BulkConfig::default() };
+    assert_eq!(
+        template_body(&definition, "logs-restored", &bulk),
+        json!({
+            "index_patterns": ["logs-restored"],
+            "data_stream": {"hidden": false, "allow_custom_routing": false},
+            "priority": 200,
+            "template": {
+                "settings": {"index": {"number_of_shards": "2", "number_of_replicas": "0", "lifecycle": {"name": "logs"}}},
+                "mappings": {"properties": {"@timestamp": {"type": "date"}}}
+            }
+        })
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("logs.ndjson");
+    std::fs::write(&dump, "\n{\"_index\":\".ds-logs-app-2024.06.01-000001\",\"_id\":\"1\",\"_source\":{}}\n").unwrap();
+    assert!(is_data_stream_dump(&dump).unwrap());
+    std::fs::write(&dump, "{\"_index\":\"orders\",\"_id\":\"1\",\"_source\":{}}\n").unwrap();
+    assert!(!is_data_stream_dump(&dump).unwrap());
+}
+
+#[tokio::test]
+async fn test_data_stream_dump_creates_stream_from_its_template() {
+    let server = MockServer::start().await;
+    let definition = IndexDefinition::from_json(exported().to_string().as_bytes()).unwrap();
+    let bulk = BulkConfig::default();
+    Mock::given(method("HEAD")).and(path("/_index_template/logs-app")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("PUT"))
+        .and(path("/_index_template/logs-app"))
+        .and(body_json(template_body(&definition, "logs-app", &bulk)))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).and(path("/_data_stream/logs-app")).respond_with(ResponseTemplate::new(200)).expect(1).mount(&server).await;
+    Mock::given(method("PUT")).and(path("/logs-app/_settings")).respond_with(ResponseTemplate::new(200)).expect(0).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[]}"#))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("logs-app.ndjson");
+    std::fs::write(&snapshot, "{\"_index\":\".ds-logs-app-2024.06.01-000001\",\"_id\":\"1\",\"_source\":{\"@timestamp\":\"2024-06-01T00:00:00Z\"}}\n").unwrap();
+    std::fs::write(sidecar_path(&snapshot), exported().to_string()).unwrap();
+
+    let mut dead_letters = DeadLetterWriter::new("elasticsearch", "http://es", "logs-app", "documents", dir.path().join("logs.dead-letter.ndjson"));
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "logs-app", &snapshot, &bulk, &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+    assert_eq!(indexed, 1);
+    assert_eq!(bulks(&server).await, vec!["{\"create\":{\"_id\":\"1\",\"_index\":\"logs-app\"}}\n{\"@timestamp\":\"2024-06-01T00:00:00Z\"}\n"]);
+}
+
+#[tokio::test]
+async fn test_existing_data_stream_is_written_with_create() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD")).and(path("/logs")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/_data_stream/logs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data_streams": [{"name": "logs"}]})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET")).and(path("/logs/_settings/index.refresh_interval")).respond_with(ResponseTemplate::new(200)).expect(0).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[]}"#))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("logs.ndjson");
+    std::fs::write(&snapshot, "{\"@timestamp\":\"2024-06-01T00:00:00Z\"}\n").unwrap();
+
+    let mut dead_letters = DeadLetterWriter::new("elasticsearch", "http://es", "logs", "documents", dir.path().join("logs.dead-letter.ndjson"));
+    bulk_restore(&reqwest::Client::new(), None, &server.uri(), "logs", &snapshot, &BulkConfig::default(), &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+    assert_eq!(bulks(&server).await, vec!["{\"create\":{\"_index\":\"logs\"}}\n{\"@timestamp\":\"2024-06-01T00:00:00Z\"}\n"]);
+}
@@ -0,0 +1,101 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_bulk::bulk_restore;
+use rustored::es_index::{fetch_index_definition, sidecar_path, IndexDefinition};
+use rustored::mapping::RestoreMapping;
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::models::BulkConfig;
+use serde_json::json;
+use wiremock::matchers::{body_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// The `GET /orders` answer of an index with two shards and a keyword field
+fn exported() -> serde_json::Value {
+    json!({
+        "orders": {
+            "aliases": {},
+            "mappings": {"properties": {"sku": {"type": "keyword"}}},
+            "settings": {"index": {
+                "number_of_shards": "2",
+                "number_of_replicas": "1",
+                "uuid": "pgDXAo4cRm6Y2Lx5YSXC8A",
+                "creation_date": "1717200000000",
+                "provided_name": "orders",
+                "version": {"created": "8130099"},
+                "analysis": {"analyzer": {"folded": {"tokenizer": "standard", "filter": ["asciifolding"]}}}
+            }}
+        }
+    })
+}
+
+#[test]
+fn test_definition_drops_read_only_settings_and_takes_overrides() {
+    let definition = IndexDefinition::from_json(exported().to_string().as_bytes()).unwrap();
+    assert_eq!(
+        definition.clone().with_overrides(Some(1), Some(0)).create_body(),
+        json!({
+            "mappings": {"properties": {"sku": {"type": "keyword"}}},
+            "settings": {"index": {
+                "number_of_shards": "1",
+                "number_of_replicas": "0",
+                "analysis": {"analyzer": {"folded": {"tokenizer": "standard", "filter": ["asciifolding"]}}}
+            }}
+        })
+    );
+
+    // The bare form and flat setting names are taken as well
+    let flat = IndexDefinition::from_json(br#"{"settings": {"index.number_of_shards": 3, "index.uuid": "x"}}"#).unwrap();
+    assert_eq!(flat.clone().create_body(), json!({"settings": {"index.number_of_shards": 3}}));
+    assert_eq!(flat.with_overrides(None, Some(2)).create_body(), json!({"settings": {"index.number_of_shards": 3, "index": {"number_of_replicas": "2"}}}));
+    assert!(IndexDefinition::default().is_empty());
+    assert!(IndexDefinition::from_json(br#"{"a": 1, "b": 2}"#).is_err());
+}
+
+#[tokio::test]
+async fn test_definition_is_downloaded_with_the_snapshot_and_replaced() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    store.write_object("es/orders.ndjson.index.json", exported().to_string().into_bytes()).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let local = dir.path().join("download");
+
+    assert!(fetch_index_definition(&store, "es/orders.ndjson", &local).await.unwrap());
+    assert!(IndexDefinition::read_sidecar(&local).unwrap().unwrap().mappings.is_some());
+
+    // A later download of a snapshot without a definition leaves none behind
+    assert!(!fetch_index_definition(&store, "es/users.ndjson", &local).await.unwrap());
+    assert!(!sidecar_path(&local).exists());
+    assert_eq!(IndexDefinition::read_sidecar(&local).unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_bulk_restore_creates_index_from_definition() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("PUT"))
+        .and(path("/orders"))
+        .and(body_json(json!({
+            "mappings": {"properties": {"sku": {"type": "keyword"}}},
+            "settings": {"index": {
+                "number_of_shards": "2",
+                "number_of_replicas": "0",
+                "analysis": {"analyzer": {"folded": {"tokenizer": "standard", "filter": ["asciifolding"]}}}
+            }}
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[]}"#))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("orders.ndjson");
+    std::fs::write(&snapshot, "{\"sku\":\"A-1\"}\n").unwrap();
+    std::fs::write(sidecar_path(&snapshot), exported().to_string()).unwrap();
+
+    let bulk = BulkConfig { replicas: Some(0), ..BulkConfig::new(None, None, true) };
+    let mut dead_letters = DeadLetterWriter::new("elasticsearch", "http://es", "orders", "documents", dir.path().join("orders.dead-letter.ndjson"));
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", &snapshot, &bulk, &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+    assert_eq!(indexed, 1);
+}
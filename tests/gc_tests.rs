@@ -0,0 +1,55 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rustored::gc::{parse_age, parse_throwaway_comment, throwaway_comment, GcPlan, GcSchedule, Throwaway};
+use rustored::presets::RestorePresets;
+
+fn at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, day, hour, 0, 0).unwrap()
+}
+
+#[test]
+fn test_ages_accept_units_and_default_to_days() {
+    assert_eq!(parse_age("7d").unwrap(), Duration::days(7));
+    assert_eq!(parse_age("12h").unwrap(), Duration::hours(12));
+    assert_eq!(parse_age("2w").unwrap(), Duration::weeks(2));
+    assert_eq!(parse_age(" 30m ").unwrap(), Duration::minutes(30));
+    assert_eq!(parse_age("3").unwrap(), Duration::days(3));
+    assert!(parse_age("0d").is_err());
+    assert!(parse_age("7y").is_err());
+    assert!(parse_age("d").is_err());
+    assert!(parse_age("99999999999999d").unwrap_err().to_string().contains("too long"));
+    assert!(parse_age("9999999999999999w").is_err());
+}
+
+#[test]
+fn test_throwaway_comment_records_when_the_restore_was_created() {
+    let comment = throwaway_comment(at(1, 3));
+    assert_eq!(comment, "rustored:throwaway created=2024-06-01T03:00:00Z");
+    assert_eq!(parse_throwaway_comment(&comment), Some(at(1, 3)));
+    assert_eq!(parse_throwaway_comment("orders for the reporting team"), None);
+    assert_eq!(parse_throwaway_comment("rustored:throwaway created=yesterday"), None);
+}
+
+#[test]
+fn test_plan_drops_only_restores_past_their_age() {
+    let throwaways = vec![
+        Throwaway { name: "apple-restored".to_string(), created: at(1, 0) },
+        Throwaway { name: "pear-restored".to_string(), created: at(6, 0) },
+    ];
+    let plan = GcPlan::new(throwaways, Duration::days(7), at(10, 12));
+    assert_eq!(plan.expired.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), ["apple-restored"]);
+    assert_eq!(plan.kept.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), ["pear-restored"]);
+    let table = plan.table().render(false);
+    assert!(table.contains("drop") && table.contains("2024-06-01 00:00 UTC"), "{}", table);
+}
+
+#[test]
+fn test_gc_schedules_come_from_the_presets_file() {
+    let presets = RestorePresets::parse("[[gc]]\ncron = \"0 4 * * *\"\n\n[[gc]]\ncron = \"0 * * * *\"\nolder_than = \"12h\"\n").unwrap();
+    assert_eq!(presets.gc, vec![
+        GcSchedule { cron: "0 4 * * *".to_string(), older_than: "7d".to_string() },
+        GcSchedule { cron: "0 * * * *".to_string(), older_than: "12h".to_string() },
+    ]);
+    assert_eq!(presets.gc[0].next_run(at(1, 3)).unwrap(), at(1, 4));
+    assert!(GcSchedule { cron: "nightly".to_string(), older_than: "7d".to_string() }.next_run(at(1, 3)).is_err());
+    assert!(RestorePresets::parse("[[gc]]\nolder_than = \"7d\"\n").is_err());
+}
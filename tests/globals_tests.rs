@@ -0,0 +1,48 @@
+use chrono::{TimeZone, Utc};
+use rustored::globals::{find_globals, globals_key, is_globals_key, summary};
+use rustored::presets::RestoreOptions;
+use rustored::ui::models::BackupMetadata;
+
+fn snapshot(key: &str, last_modified: f64) -> BackupMetadata {
+    BackupMetadata::new(key, 1, last_modified)
+}
+
+#[test]
+fn test_globals_keys() {
+    let taken = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    assert_eq!(globals_key("postgres", taken), "postgres/globals-20250101T120000Z.sql");
+    assert_eq!(globals_key("", taken), "globals-20250101T120000Z.sql");
+    assert!(is_globals_key("postgres/globals-20250101T120000Z.sql"));
+    assert!(is_globals_key("postgres/globals-20250101T120000Z.sql.age"));
+    assert!(!is_globals_key("postgres/globals-20250101T120000Z.dump"));
+    assert!(!is_globals_key("postgres/orders-20250101T120000Z.dump"));
+}
+
+#[test]
+fn test_find_globals_taken_with_the_snapshot() {
+    let snapshots = vec![
+        snapshot("pg/globals-20250101T000000Z.sql", 100.0),
+        snapshot("pg/globals-20250103T000000Z.sql", 300.0),
+        snapshot("pg/orders-20250102T000000Z.dump", 200.0),
+        snapshot("pg/globals-20250102T000000Z.sql.gpg", 150.0),
+    ];
+    let orders = &snapshots[2];
+    assert_eq!(find_globals(&snapshots, orders).unwrap().key, "pg/globals-20250102T000000Z.sql.gpg");
+
+    // When every globals snapshot is newer, the oldest one is used
+    let early = snapshot("pg/orders-20241231T000000Z.dump", 50.0);
+    assert_eq!(find_globals(&snapshots, &early).unwrap().key, "pg/globals-20250101T000000Z.sql");
+    assert!(find_globals(&snapshots[2..3], orders).is_none());
+}
+
+#[test]
+fn test_globals_summary() {
+    assert_eq!(summary("pg/globals-1.sql", &[]), "Applied cluster globals from pg/globals-1.sql");
+    let errors = vec!["ERROR:  tablespace \"fast\" directory does not exist".to_string(), "ERROR:  x".to_string()];
+    assert_eq!(
+        summary("pg/globals-1.sql", &errors),
+        "Applied cluster globals from pg/globals-1.sql with 2 errors, first: ERROR:  tablespace \"fast\" directory does not exist"
+    );
+    let options = RestoreOptions { globals: true, ..Default::default() };
+    assert_eq!(options.summary(), "jobs=default, globals");
+}
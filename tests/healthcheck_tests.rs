@@ -0,0 +1,49 @@
+use rustored::healthcheck::{check_snapshot_age, CheckResult, HealthReport};
+use rustored::ui::models::BackupMetadata;
+
+// Fixed "now" used for age calculations
+const NOW: f64 = 1_700_000_000.0;
+
+fn snapshot(key: &str, hours_old: f64) -> BackupMetadata {
+    BackupMetadata::new(key, 1024, NOW - hours_old * 3600.0)
+}
+
+#[test]
+fn test_snapshot_age_uses_newest_snapshot() {
+    let snapshots = vec![snapshot("old.dump", 48.0), snapshot("new.dump", 2.0)];
+    let result = check_snapshot_age(&snapshots, 24.0, NOW);
+    assert!(result.ok);
+    assert_eq!(result.detail, "2.0h old");
+}
+
+#[test]
+fn test_snapshot_age_fails_when_stale_or_missing() {
+    let snapshots = vec![snapshot("old.dump", 30.0)];
+    let result = check_snapshot_age(&snapshots, 24.0, NOW);
+    assert!(!result.ok);
+    assert_eq!(result.detail, "30.0h old, limit 24h");
+
+    let result = check_snapshot_age(&[], 24.0, NOW);
+    assert!(!result.ok);
+    assert_eq!(result.detail, "no snapshots found");
+}
+
+#[test]
+fn test_report_status_line() {
+    let mut report = HealthReport {
+        checks: vec![
+            CheckResult::pass("bucket", "reachable, 3 snapshots".to_string()),
+            CheckResult::pass("postgresql", "reachable".to_string()),
+        ],
+    };
+    assert!(report.ok());
+    assert_eq!(report.status_line(), "OK - bucket: reachable, 3 snapshots; postgresql: reachable");
+
+    // A single failing check makes the whole report critical
+    report.checks.push(CheckResult::fail("latest_snapshot", "no snapshots found".to_string()));
+    assert!(!report.ok());
+    assert!(report.status_line().starts_with("CRITICAL - "));
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["checks"][2]["ok"], false);
+}
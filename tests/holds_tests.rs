@@ -0,0 +1,99 @@
+use chrono::NaiveDate;
+use rustored::holds::{place_hold, release_hold, HoldCatalog, CATALOG_KEY};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::S3Config;
+use std::sync::Arc;
+
+fn day(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn test_hold_blocks_delete_until_released() {
+    let today = day("2025-03-01");
+    let mut catalog = HoldCatalog::default();
+    catalog.create("postgres/orders-", "2025-06-30", "case 1234", today).unwrap();
+
+    assert!(catalog.is_held("postgres/orders-20250101.dump", today));
+    assert!(!catalog.is_held("postgres/users-20250101.dump", today));
+    let err = catalog.ensure_deletable("postgres/orders-20250101.dump", today).unwrap_err();
+    assert!(err.to_string().contains("case 1234"));
+
+    // The hold lapses after its last day
+    assert!(catalog.ensure_deletable("postgres/orders-20250101.dump", day("2025-07-01")).is_ok());
+
+    assert_eq!(catalog.release("postgres/orders-").unwrap(), 1);
+    assert!(catalog.ensure_deletable("postgres/orders-20250101.dump", today).is_ok());
+    assert!(catalog.release("postgres/orders-").is_err());
+}
+
+#[test]
+fn test_hold_rejects_invalid_input() {
+    let today = day("2025-03-01");
+    let mut catalog = HoldCatalog::default();
+    assert!(catalog.create("postgres/", "30/06/2025", "case", today).is_err());
+    assert!(catalog.create("postgres/", "2025-02-28", "case", today).is_err());
+    assert!(catalog.create("postgres/", "2025-06-30", " ", today).is_err());
+    assert!(catalog.holds.is_empty());
+}
+
+#[tokio::test]
+async fn test_holds_are_stored_in_bucket_and_pin_snapshots() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let dump = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(dump.path(), b"dump").unwrap();
+    store.upload_file(dump.path(), "postgres/orders-20250101.dump", None).await.unwrap();
+    store.upload_file(dump.path(), "postgres/users-20250301.dump", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store.clone());
+    let today = chrono::Utc::now().date_naive();
+    let held = place_hold(&mut browser, "postgres/orders-", "2999-12-31", "case 1234", today).await.unwrap();
+    assert_eq!(held, vec!["postgres/orders-20250101.dump".to_string()]);
+
+    // The held snapshot is pinned above the newer one
+    assert_eq!(browser.snapshots[0].key, "postgres/orders-20250101.dump");
+    assert!(browser.is_pinned(&browser.snapshots[0]));
+
+    let stored = HoldCatalog::from_json(&store.read_object(CATALOG_KEY).await.unwrap().unwrap()).unwrap();
+    assert_eq!(stored.holds.len(), 1);
+
+    assert_eq!(release_hold(&mut browser, "postgres/orders-").await.unwrap(), 1);
+    browser.load_holds().await.unwrap();
+    assert!(browser.holds.holds.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_snapshots_refuses_held_snapshots() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let dump = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(dump.path(), b"dump").unwrap();
+    store.upload_file(dump.path(), "postgres/orders-20250101.dump", None).await.unwrap();
+    store.upload_file(dump.path(), "postgres/users-20250301.dump", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store.clone());
+    let today = chrono::Utc::now().date_naive();
+    place_hold(&mut browser, "postgres/orders-", "2999-12-31", "case 1234", today).await.unwrap();
+
+    // Marking both selects both; the hold on one stops the whole delete
+    browser.toggle_mark();
+    browser.selected_index = 1;
+    browser.toggle_mark();
+    let candidates = browser.deletion_candidates();
+    assert_eq!(candidates.len(), 2);
+    let err = browser.delete_snapshots(&candidates, today).await.unwrap_err();
+    assert!(err.to_string().contains("case 1234"));
+    assert_eq!(store.list_snapshots("postgres/").await.unwrap().len(), 2);
+
+    // Unheld snapshots are deleted, unmarked and dropped from the list
+    browser.marked.clear();
+    let users: Vec<_> = browser.snapshots.iter().filter(|s| s.key.contains("users")).cloned().collect();
+    browser.delete_snapshots(&users, today).await.unwrap();
+    let remaining = store.list_snapshots("postgres/").await.unwrap();
+    assert_eq!(remaining.iter().filter(|s| s.key.ends_with(".dump")).count(), 1);
+    assert_eq!(browser.snapshots.len(), 1);
+    assert_eq!(browser.selected_index, 0);
+    assert_eq!(browser.deletion_candidates(), vec![browser.snapshots[0].clone()]);
+}
@@ -0,0 +1,100 @@
+use rustored::hooks::{source_db_from_key, MaintenanceHooks, RestoreHook, TemplateVars};
+
+#[test]
+fn test_parse_hook_specs() {
+    // URLs become URL hooks
+    assert_eq!(
+        RestoreHook::parse("https://app.internal/maintenance"),
+        Some(RestoreHook::Url("https://app.internal/maintenance".to_string()))
+    );
+
+    // Anything else is a shell command
+    assert_eq!(
+        RestoreHook::parse("./scripts/maintenance.sh on"),
+        Some(RestoreHook::Command("./scripts/maintenance.sh on".to_string()))
+    );
+
+    // Blank specifications disable the hook
+    assert_eq!(RestoreHook::parse("   "), None);
+}
+
+#[tokio::test]
+async fn test_hooks_wrap_restore_in_order() {
+    // Both hooks append their event to the same log file
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("events.log");
+    let command = format!("echo \"$RUSTORED_HOOK_EVENT $RUSTORED_TARGET\" >> {}", log.display());
+    let hooks = MaintenanceHooks::from_specs(Some(&command), Some(&command));
+
+    let result = hooks.run("mydb", async { Ok::<_, anyhow::Error>("restored") }).await;
+    assert_eq!(result.unwrap(), "restored");
+
+    let events = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(events, "maintenance_on mydb\nmaintenance_off mydb\n");
+}
+
+#[tokio::test]
+async fn test_failed_enter_hook_aborts_restore() {
+    // The restore must not run when maintenance mode cannot be enabled
+    let hooks = MaintenanceHooks::from_specs(Some("exit 1"), None);
+    let mut restored = false;
+    let result = hooks.run("mydb", async {
+        restored = true;
+        Ok::<_, anyhow::Error>(())
+    }).await;
+
+    assert!(result.is_err());
+    assert!(!restored);
+}
+
+#[tokio::test]
+async fn test_exit_hook_runs_after_failed_restore() {
+    // The application is brought back even if the restore fails
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("exited");
+    let hooks = MaintenanceHooks::from_specs(None, Some(&format!("touch {}", marker.display())));
+
+    let result = hooks.run("mydb", async { Err::<(), _>(anyhow::anyhow!("restore failed")) }).await;
+
+    assert_eq!(result.unwrap_err().to_string(), "restore failed");
+    assert!(marker.exists());
+}
+
+#[test]
+fn test_template_vars_expand() {
+    let vars = TemplateVars::new("orders_copy", "postgres/orders-20250101.dump");
+    assert_eq!(
+        vars.expand("ANALYZE; -- {{restored_db}} from {{ source_db }} ({{snapshot_key}})").unwrap(),
+        "ANALYZE; -- orders_copy from orders (postgres/orders-20250101.dump)"
+    );
+    assert_eq!(vars.expand("no variables").unwrap(), "no variables");
+    assert!(vars.expand("{{restored}}").is_err());
+    assert!(vars.expand("{{restored_db").is_err());
+}
+
+#[test]
+fn test_sql_templates_quote_a_malicious_snapshot_key() {
+    let vars = TemplateVars::new("orders_copy", "a'; DROP DATABASE prod;--");
+    assert_eq!(
+        vars.expand_sql("ALTER DATABASE {{restored_db}} OWNER TO app; COMMENT ON DATABASE {{restored_db}} IS {{snapshot_key}};").unwrap(),
+        "ALTER DATABASE \"orders_copy\" OWNER TO app; COMMENT ON DATABASE \"orders_copy\" IS 'a''; DROP DATABASE prod;--';"
+    );
+    let vars = TemplateVars::new("x\"; DROP DATABASE prod;--", "orders.dump");
+    assert_eq!(vars.expand_sql("ANALYZE {{restored_db}}").unwrap(), "ANALYZE \"x\"\"; DROP DATABASE prod;--\"");
+}
+
+#[test]
+fn test_command_templates_quote_a_malicious_snapshot_key() {
+    let vars = TemplateVars::new("orders_copy", "x;rm -rf ~");
+    assert_eq!(vars.expand_command("notify {{restored_db}} {{snapshot_key}}").unwrap(), "notify orders_copy 'x;rm -rf ~'");
+    let vars = TemplateVars::new("orders_copy", "it's;id");
+    assert_eq!(vars.expand_command("notify {{snapshot_key}}").unwrap(), "notify 'it'\\''s;id'");
+}
+
+#[test]
+fn test_source_db_from_key() {
+    assert_eq!(source_db_from_key("postgres/orders-20250101.dump"), "orders");
+    assert_eq!(source_db_from_key("orders-pre-change-20250101T120000Z.dump"), "orders");
+    assert_eq!(source_db_from_key("my-app.sql.gz"), "my-app");
+    assert_eq!(source_db_from_key("2025-01-01.dump"), "2025");
+}
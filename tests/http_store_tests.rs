@@ -0,0 +1,92 @@
+use rustored::storage::{download_url, HttpStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{S3Config, TimeoutConfig};
+use tokio::io::AsyncReadExt;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+const BODY: &[u8] = b"PGDMP rustored dump served over plain HTTP";
+
+/// Answer `Range: bytes=a-b` requests for `BODY` the way S3 does
+fn ranged(request: &Request) -> ResponseTemplate {
+    let range = request.headers.get("range").and_then(|value| value.to_str().ok()).and_then(|value| value.strip_prefix("bytes="));
+    let Some((start, end)) = range.and_then(|range| range.split_once('-')) else {
+        return ResponseTemplate::new(200).set_body_bytes(BODY);
+    };
+    let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse::<usize>().unwrap().min(BODY.len() - 1));
+    ResponseTemplate::new(206)
+        .insert_header("Content-Range", format!("bytes {}-{}/{}", start, end, BODY.len()).as_str())
+        .insert_header("ETag", "\"abc123\"")
+        .insert_header("Last-Modified", "Tue, 03 Jun 2025 10:00:00 GMT")
+        .set_body_bytes(&BODY[start..=end])
+}
+
+#[tokio::test]
+async fn test_http_store_probes_and_reads_ranges() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/dumps/http_probe.dump")).respond_with(ranged).mount(&server).await;
+
+    let store = HttpStore::new(&format!("{}/dumps/http_probe.dump?X-Amz-Signature=secret", server.uri()), &TimeoutConfig::default()).unwrap();
+    assert!(!store.display_url().contains("secret"));
+    let snapshot = store.probe().await.unwrap();
+    assert_eq!(snapshot.key, "http_probe.dump");
+    assert_eq!(snapshot.size, BODY.len() as i64);
+    assert_eq!(snapshot.etag.as_deref(), Some("\"abc123\""));
+    assert_eq!(snapshot.last_modified, 1_748_944_800.0);
+
+    let mut part = Vec::new();
+    store.read_range(&snapshot.key, 6, 13).await.unwrap().read_to_end(&mut part).await.unwrap();
+    assert_eq!(part, &BODY[6..=13]);
+    assert!(store.delete_object("http_probe.dump").await.is_err());
+    assert!(HttpStore::new("ftp://example.com/a.dump", &TimeoutConfig::default()).is_err());
+}
+
+#[tokio::test]
+async fn test_http_store_without_range_support() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/whole.dump"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+        .mount(&server)
+        .await;
+
+    let store = HttpStore::new(&format!("{}/whole.dump", server.uri()), &TimeoutConfig::default()).unwrap();
+    assert_eq!(store.probe().await.unwrap().size, BODY.len() as i64);
+    // The first part is cut from the whole file, later parts cannot be
+    let mut part = Vec::new();
+    store.read_range("whole.dump", 0, 4).await.unwrap().read_to_end(&mut part).await.unwrap();
+    assert_eq!(part, b"PGDMP");
+    assert!(store.read_range("whole.dump", 5, 9).await.is_err());
+}
+
+#[tokio::test]
+async fn test_download_url_checks_sha256() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/http_download.dump"))
+        .and(query_param("token", "t"))
+        .respond_with(ranged)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/missing.dump"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&server)
+        .await;
+    let url = format!("{}/http_download.dump?token=t", server.uri());
+    let mut browser = SnapshotBrowser::new(S3Config::default());
+
+    let path = download_url(&mut browser, &url, None).await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), BODY);
+    let sha256 = rustored::verification::file_checksum(std::path::Path::new(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let path = download_url(&mut browser, &url, Some(&sha256.to_uppercase())).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let error = download_url(&mut browser, &url, Some("0000")).await.unwrap_err();
+    assert!(error.to_string().contains("expected 0000"), "{}", error);
+    let _ = std::fs::remove_file(rustored::download_ledger::temp_download_path("http_download.dump"));
+
+    let error = download_url(&mut browser, &format!("{}/missing.dump", server.uri()), None).await.unwrap_err();
+    assert!(error.to_string().contains("403"), "{}", error);
+}
@@ -0,0 +1,140 @@
+use rustored::dead_letter::{read_dead_letters, DeadLetterWriter};
+use rustored::influx_lines::{write_lines, InfluxBucket};
+use rustored::ui::models::InfluxDbConfig;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_json, body_string_contains, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// The `metrics` bucket of the `acme` organization on a mock server
+fn bucket(server: &MockServer) -> InfluxBucket {
+    InfluxBucket {
+        host: server.uri(),
+        org: "acme".to_string(),
+        bucket: "metrics".to_string(),
+        token: Some("secret".to_string()),
+        precision: "s".to_string(),
+    }
+}
+
+/// Dead-letter writer for the `metrics` bucket, writing into `dir`
+fn dead_letters(dir: &tempfile::TempDir) -> DeadLetterWriter {
+    DeadLetterWriter::new("influxdb", "http://influxdb", "metrics", "points", dir.path().join("metrics.dead-letter.ndjson"))
+}
+
+/// Snapshot of the given lines
+fn snapshot(lines: &[&str]) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), lines.join("\n") + "\n").unwrap();
+    file
+}
+
+#[test]
+fn test_config_needs_host_org_and_bucket() {
+    let mut config = InfluxDbConfig { host: Some("http://localhost:8086".to_string()), org: Some("acme".to_string()), ..Default::default() };
+    assert!(config.target().is_none());
+    config.bucket = Some("metrics".to_string());
+    let target = config.target().unwrap();
+    assert_eq!((target.bucket.as_str(), target.precision.as_str(), target.token), ("metrics", "ns", None));
+}
+
+#[tokio::test]
+async fn test_write_lines_creates_bucket_and_waits_out_rate_limits() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2/buckets"))
+        .and(query_param("name", "metrics"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"buckets": []})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2/orgs"))
+        .and(query_param("org", "acme"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"orgs": [{"id": "0123", "name": "acme"}]})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2/buckets"))
+        .and(body_json(json!({"orgID": "0123", "name": "metrics", "retentionRules": []})))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({"id": "4567"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // The first write is turned away until InfluxDB catches up
+    Mock::given(method("POST"))
+        .and(path("/api/v2/write"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2/write"))
+        .and(query_param("org", "acme"))
+        .and(query_param("bucket", "metrics"))
+        .and(query_param("precision", "s"))
+        .and(header("authorization", "Token secret"))
+        .and(body_string_contains("cpu,host=a usage=0.5 1700000000\ncpu,host=b usage=0.7 1700000000"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let file = snapshot(&["# DML", "cpu,host=a usage=0.5 1700000000", "cpu,host=b usage=0.7 1700000000"]);
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let callback = move |fraction: f32| reported.lock().unwrap().push(fraction);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let written = write_lines(&reqwest::Client::new(), &bucket(&server), file.path(), Some(&callback), &mut rejected).await.unwrap();
+
+    assert_eq!(written, 2);
+    assert_eq!(progress.lock().unwrap().last().copied(), Some(1.0));
+    assert!(rejected.finish().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_write_lines_splits_large_chunks_and_dead_letters_refused_lines() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2/buckets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"buckets": [{"id": "4567", "name": "metrics"}]})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2/write"))
+        .and(body_string_contains("host=a"))
+        .and(body_string_contains("host=b"))
+        .respond_with(ResponseTemplate::new(413).set_body_json(json!({"code": "request too large", "message": "payload too large"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2/write"))
+        .and(body_string_contains("host=b"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({"code": "invalid", "message": "unable to parse 'cpu,host=b usage=': missing field value"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).and(path("/api/v2/write")).respond_with(ResponseTemplate::new(204)).expect(1).mount(&server).await;
+    let file = snapshot(&["cpu,host=a usage=0.5 1700000000", "cpu,host=b usage= 1700000000"]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let written = write_lines(&reqwest::Client::new(), &bucket(&server), file.path(), None, &mut rejected).await.unwrap();
+
+    assert_eq!(written, 1);
+    let report = rejected.finish().unwrap().unwrap();
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].record, json!("cpu,host=b usage= 1700000000"));
+    assert!(letters[0].reason.contains("missing field value"));
+}
+
+#[tokio::test]
+async fn test_write_lines_rejects_unknown_precision() {
+    let server = MockServer::start().await;
+    let file = snapshot(&["cpu usage=1 1"]);
+    let dir = tempfile::tempdir().unwrap();
+    let target = InfluxBucket { precision: "h".to_string(), ..bucket(&server) };
+    let error = write_lines(&reqwest::Client::new(), &target, file.path(), None, &mut dead_letters(&dir)).await.unwrap_err();
+    assert!(error.to_string().contains("Unknown InfluxDB precision h"));
+}
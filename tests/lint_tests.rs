@@ -0,0 +1,58 @@
+use chrono::{TimeZone, Utc};
+use rustored::lint::{lint, lint_bucket, Finding, LintIssue, NamingTemplate, DEFAULT_TEMPLATE};
+use rustored::storage::DemoStore;
+use rustored::ui::models::BackupMetadata;
+
+fn object(key: &str) -> BackupMetadata {
+    BackupMetadata::new(key, 5, 0.0)
+}
+
+#[test]
+fn test_templates_match_rustored_names() {
+    let template = NamingTemplate::parse(DEFAULT_TEMPLATE).unwrap();
+    assert!(template.matches("postgres", "postgres/orders-20250101T120000Z.dump"));
+    assert!(template.matches("postgres", "postgres/orders-pre-change-20250101T120000Z.dump.zst.age"));
+    assert!(template.matches("postgres", "postgres/globals-20250101T120000Z.sql"));
+    assert!(!template.matches("postgres", "postgres/orders-final-v2.dump"));
+
+    let strict = NamingTemplate::parse("{db}/{db}-{date}.dump").unwrap();
+    assert!(strict.matches("postgres", "postgres/orders/orders-20250101.dump"));
+    assert!(!strict.matches("postgres", "postgres/orders-20250101.dump"), "a template with a / matches the key below the prefix");
+    assert!(NamingTemplate::parse("{database}.dump").unwrap_err().to_string().contains("Unknown placeholder {database}"));
+    assert!(NamingTemplate::parse("{db.dump").unwrap_err().to_string().contains("Unclosed"));
+}
+
+#[test]
+fn test_lint_reports_strays_and_unpaired_manifests() {
+    let template = NamingTemplate::parse(DEFAULT_TEMPLATE).unwrap();
+    let objects = [
+        object("postgres/orders-20250101T120000Z.dump"),
+        object("postgres/orders-20250101T120000Z.dump.backup.json"),
+        object("postgres/billing-20250101T120000Z.dump"),
+        object("postgres/users-20240101T120000Z.dump.backup.json"),
+        object("postgres/copy of orders.dump"),
+        object("postgres/copy of orders.dump.backup.json"),
+        object("postgres/orders-incr-20250102T120000Z.tar"),
+        object("postgres/orders-incr-20250102T120000Z.tar.manifest.json"),
+        object("postgres/wal/000000010000000000000003"),
+        object(".rustored/holds.json"),
+    ];
+    let report = lint(&objects, "postgres", &template);
+    assert_eq!(report.checked, 8, "catalog objects and WAL are not checked");
+    assert_eq!(report.issues, vec![
+        LintIssue { key: "postgres/billing-20250101T120000Z.dump".to_string(), finding: Finding::MissingManifest },
+        LintIssue { key: "postgres/copy of orders.dump".to_string(), finding: Finding::Nonconforming },
+        LintIssue { key: "postgres/users-20240101T120000Z.dump.backup.json".to_string(), finding: Finding::OrphanedManifest },
+    ]);
+    assert_eq!(report.summary(), "3 problems in 8 objects");
+    assert!(!report.is_clean());
+}
+
+#[tokio::test]
+async fn test_lint_bucket_lists_the_store() {
+    let store = DemoStore::new("postgres", Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap());
+    let template = NamingTemplate::parse("{name}-{timestamp}.{ext}").unwrap();
+    let report = lint_bucket(&store, "postgres", &template).await.unwrap();
+    assert!(report.checked > 0);
+    assert!(report.issues.iter().all(|issue| issue.finding == Finding::MissingManifest), "demo snapshots follow the default template");
+}
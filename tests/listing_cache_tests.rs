@@ -0,0 +1,143 @@
+use rustored::listing_cache::ListingCache;
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{BackupMetadata, S3Config};
+use wiremock::matchers::{method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn snapshot(key: &str) -> BackupMetadata {
+    BackupMetadata::new(key, 4, 0.0)
+}
+
+fn s3_config(endpoint_url: &str) -> S3Config {
+    S3Config {
+        bucket: "dumps".to_string(),
+        region: "us-east-1".to_string(),
+        prefix: "pg/".to_string(),
+        endpoint_url: endpoint_url.to_string(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        path_style: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_listing_cache_round_trip_per_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ListingCache::new(dir.path().join("cache"));
+    assert_eq!(cache.load("s3://dumps/pg/"), None);
+
+    cache.save("s3://dumps/pg/", &[snapshot("pg/a.dump")]).unwrap();
+    cache.save("s3://dumps/es/", &[snapshot("es/a.json"), snapshot("es/b.json")]).unwrap();
+    let listing = cache.load("s3://dumps/pg/").unwrap();
+    assert_eq!(listing.snapshots, vec![snapshot("pg/a.dump")]);
+    assert!(listing.saved_at > 0.0);
+    assert_eq!(cache.load("s3://dumps/es/").unwrap().snapshots.len(), 2);
+
+    // A corrupt cache is ignored rather than failing startup
+    std::fs::write(cache.path_for("s3://dumps/pg/"), b"{not json").unwrap();
+    assert_eq!(cache.load("s3://dumps/pg/"), None);
+}
+
+#[tokio::test]
+async fn test_browser_shows_cached_listing_until_refreshed() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("list-type", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>dumps</Name><Prefix>pg/</Prefix><KeyCount>1</KeyCount><IsTruncated>false</IsTruncated>\
+             <Contents><Key>pg/live.dump</Key><LastModified>2025-01-01T00:00:00.000Z</LastModified><ETag>\"e\"</ETag><Size>4</Size></Contents></ListBucketResult>",
+        ))
+        .mount(&server)
+        .await;
+
+    // Without a cache there is nothing to show
+    let mut browser = SnapshotBrowser::new(s3_config(&server.uri()));
+    assert!(!browser.show_cached_listing());
+
+    // A complete live listing is cached and clears the stale marker
+    browser.listing_cache = Some(ListingCache::new(dir.path()));
+    browser.load_snapshots().await.unwrap();
+    assert_eq!(browser.stale_since, None);
+    assert!(!browser.refreshing);
+
+    // The next start shows it straight away, marked stale
+    let mut restarted = SnapshotBrowser::new(s3_config(&server.uri()));
+    restarted.listing_cache = Some(ListingCache::new(dir.path()));
+    assert!(restarted.show_cached_listing());
+    assert_eq!(restarted.snapshots, browser.snapshots);
+    assert!(restarted.stale_since.is_some());
+
+    // A failed refresh keeps the stale listing on show
+    server.reset().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("<Error><Code>AccessDenied</Code><Message>Access Denied</Message></Error>"))
+        .mount(&server)
+        .await;
+    assert!(restarted.load_snapshots().await.is_err());
+    assert_eq!(restarted.snapshots.len(), 1);
+    assert!(restarted.stale_since.is_some());
+    assert!(!restarted.refreshing);
+
+    // Other sources are cached separately
+    let mut other = SnapshotBrowser::new(S3Config { prefix: "es/".to_string(), ..s3_config(&server.uri()) });
+    other.listing_cache = Some(ListingCache::new(dir.path()));
+    assert!(!other.show_cached_listing());
+}
+
+#[test]
+fn test_merge_listing_keeps_unchanged_snapshots() {
+    let mut snapshots = vec![snapshot("pg/a.dump"), snapshot("pg/b.dump")];
+    let replaced = BackupMetadata { etag: Some("\"new\"".to_string()), ..snapshot("pg/b.dump") };
+    let changed = rustored::listing_cache::merge_listing(
+        &mut snapshots,
+        vec![snapshot("pg/a.dump"), replaced.clone(), snapshot("pg/c.dump")],
+    );
+    assert_eq!(changed, 2);
+    assert_eq!(snapshots, vec![snapshot("pg/a.dump"), replaced, snapshot("pg/c.dump")]);
+}
+
+/// ListObjectsV2 response body listing `keys`
+fn list_body(keys: &[&str]) -> String {
+    let contents: String = keys
+        .iter()
+        .map(|key| format!("<Contents><Key>{}</Key><LastModified>2025-01-01T00:00:00.000Z</LastModified><ETag>\"e\"</ETag><Size>4</Size></Contents>", key))
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>dumps</Name><Prefix>pg/</Prefix><KeyCount>{}</KeyCount><IsTruncated>false</IsTruncated>{}</ListBucketResult>",
+        keys.len(), contents
+    )
+}
+
+#[tokio::test]
+async fn test_refresh_lists_only_new_snapshots() {
+    use wiremock::matchers::query_param_is_missing;
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("list-type", "2"))
+        .and(query_param_is_missing("start-after"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(list_body(&["pg/a.dump", "pg/b.dump"])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(query_param("start-after", "pg/b.dump"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(list_body(&["pg/c.dump"])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // The first refresh has nothing to start after, so it lists everything
+    let mut browser = SnapshotBrowser::new(s3_config(&server.uri()));
+    assert_eq!(browser.refresh_snapshots().await.unwrap(), 2);
+    let first_refresh = browser.last_refreshed.unwrap();
+
+    // Later refreshes only ask for keys after the last one
+    assert_eq!(browser.refresh_snapshots().await.unwrap(), 1);
+    let mut keys: Vec<_> = browser.snapshots.iter().map(|s| s.key.as_str()).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["pg/a.dump", "pg/b.dump", "pg/c.dump"]);
+    assert!(browser.last_refreshed.unwrap() >= first_refresh);
+}
@@ -0,0 +1,96 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustored::ui::local_file::{complete_path, local_snapshot};
+use rustored::ui::models::PopupState;
+use rustored::ui::rustored::RustoredApp;
+
+fn app() -> RustoredApp {
+    let none = None;
+    RustoredApp::new(&none, &none, &none, &none, &none, &none, false, &Some("localhost".to_string()), &Some(5432), &none, &none, false, &Some("orders".to_string()), &none, &none, &none)
+}
+
+async fn press(app: &mut RustoredApp, code: KeyCode) {
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(code, KeyModifiers::NONE)).await;
+}
+
+#[test]
+fn test_paths_complete_as_far_as_the_entries_agree() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("orders-20250101.dump"), "x").unwrap();
+    std::fs::write(dir.path().join("orders-20250102.dump"), "x").unwrap();
+    std::fs::write(dir.path().join(".hidden"), "x").unwrap();
+    std::fs::create_dir(dir.path().join("archive")).unwrap();
+    let root = format!("{}/", dir.path().display());
+
+    let (completed, names) = complete_path(&format!("{}or", root));
+    assert_eq!(completed, format!("{}orders-2025010", root));
+    assert_eq!(names, vec!["orders-20250101.dump", "orders-20250102.dump"]);
+
+    let (completed, names) = complete_path(&format!("{}ar", root));
+    assert_eq!(completed, format!("{}archive/", root), "directories complete with a slash");
+    assert_eq!(names, vec!["archive/"]);
+
+    let (_, names) = complete_path(&root);
+    assert_eq!(names.len(), 3, "hidden entries are left out: {:?}", names);
+    assert_eq!(complete_path(&format!("{}.", root)).1, vec![".hidden"]);
+    assert_eq!(complete_path(&format!("{}missing/", root)), (format!("{}missing/", root), vec![]));
+}
+
+#[test]
+fn test_local_snapshots_must_be_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("orders-20250101.dump");
+    std::fs::write(&path, "12345").unwrap();
+    let snapshot = local_snapshot(&path.display().to_string()).unwrap();
+    assert_eq!(snapshot.key, path.display().to_string());
+    assert_eq!(snapshot.size, 5);
+    assert!(snapshot.last_modified > 0.0);
+    assert!(local_snapshot(&dir.path().display().to_string()).unwrap_err().to_string().contains("is not a file"));
+    assert!(local_snapshot(&dir.path().join("missing.dump").display().to_string()).is_err());
+}
+
+#[tokio::test]
+async fn test_local_file_goes_through_the_restore_plan_without_a_download() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("orders-20250101.dump");
+    std::fs::write(&path, vec![0u8; 2000]).unwrap();
+    let mut app = app();
+
+    press(&mut app, KeyCode::Char('L')).await;
+    assert_eq!(app.popup_state, PopupState::LocalFile(String::new(), vec![]));
+    for c in format!("{}/orders", dir.path().display()).chars() {
+        press(&mut app, KeyCode::Char(c)).await;
+    }
+    press(&mut app, KeyCode::Tab).await;
+    let PopupState::LocalFile(typed, names) = &app.popup_state else {
+        panic!("expected the local file prompt, got {:?}", app.popup_state);
+    };
+    assert_eq!(typed, &path.display().to_string());
+    assert_eq!(names, &vec!["orders-20250101.dump".to_string()]);
+
+    press(&mut app, KeyCode::Enter).await;
+    let PopupState::ConfirmRestore(snapshot) = &app.popup_state else {
+        panic!("expected the restore confirmation, got {:?}", app.popup_state);
+    };
+    assert_eq!(snapshot.key, path.display().to_string());
+    let command = rustored::ui::command::restore_command(&app, snapshot);
+    assert!(command.ends_with(&format!("restore orders {} --target postgres", path.display())), "{}", command);
+
+    press(&mut app, KeyCode::Char('y')).await;
+    let PopupState::RestorePlan(_, lines) = &app.popup_state else {
+        panic!("expected the restore plan, got {:?}", app.popup_state);
+    };
+    assert!(lines.contains(&format!("Source: local file {}", path.display())), "{:?}", lines);
+    assert!(lines.contains(&"Disk: none, read from the local file".to_string()), "{:?}", lines);
+}
+
+#[tokio::test]
+async fn test_unreadable_local_file_is_reported() {
+    let mut app = app();
+    app.popup_state = PopupState::LocalFile("/nonexistent/orders.dump".to_string(), vec![]);
+    press(&mut app, KeyCode::Enter).await;
+    let PopupState::Error(message) = &app.popup_state else {
+        panic!("expected an error, got {:?}", app.popup_state);
+    };
+    assert!(message.starts_with("Cannot restore /nonexistent/orders.dump"), "{}", message);
+    assert!(app.downloaded.is_none());
+}
@@ -0,0 +1,117 @@
+use chrono::{TimeZone, Utc};
+use rustored::manifest::{is_manifest_key, manifest_key, read_manifest, record_backup, write_manifest, Manifest};
+use rustored::retention::{prune, RetentionPolicy};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::S3Config;
+use std::sync::Arc;
+
+/// SHA-256 of the four bytes `dump`
+const DUMP_SHA256: &str = "b6ca0868bca6a2926b70aa1a71592038d9030fe26d4214edcfbd6cf41f2f4654";
+
+fn manifest(key: &str) -> Manifest {
+    Manifest {
+        key: key.to_string(),
+        sha256: DUMP_SHA256.to_string(),
+        size: 4,
+        format: "custom".to_string(),
+        pg_dump_version: Some("16.2".to_string()),
+        database: "orders".to_string(),
+        started: Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap(),
+        duration_seconds: 65.25,
+        bundle: None,
+    }
+}
+
+fn dump_file() -> tempfile::NamedTempFile {
+    let dump = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(dump.path(), b"dump").unwrap();
+    dump
+}
+
+#[test]
+fn test_manifest_keys() {
+    assert_eq!(manifest_key("postgres/orders.dump"), "postgres/orders.dump.backup.json");
+    assert!(is_manifest_key("postgres/orders.dump.backup.json"));
+    assert!(!is_manifest_key("postgres/orders.dump"));
+}
+
+#[test]
+fn test_manifest_for_dump_checksums_and_times_the_dump() {
+    let dump = dump_file();
+    let started = Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap();
+    let finished = started + chrono::Duration::milliseconds(1500);
+    let manifest = Manifest::for_dump("postgres/orders.dump", "orders", dump.path(), started, finished).unwrap();
+
+    assert_eq!(manifest.sha256, DUMP_SHA256);
+    assert_eq!((manifest.size, manifest.format.as_str(), manifest.duration_seconds), (4, "custom", 1.5));
+    assert_eq!(Manifest::from_json(&manifest.to_json().unwrap()).unwrap(), manifest);
+}
+
+#[test]
+fn test_manifest_display() {
+    let manifest = manifest("postgres/orders.dump");
+    assert_eq!(manifest.source(), "orders (pg_dump 16.2)");
+    assert_eq!(
+        manifest.lines(),
+        vec![
+            "Source: orders, custom format, pg_dump 16.2".to_string(),
+            "Dumped 2024-06-01 03:00:00 UTC in 65.2s".to_string(),
+            format!("SHA-256: {}", DUMP_SHA256),
+        ]
+    );
+    let unknown = Manifest { pg_dump_version: None, ..manifest };
+    assert_eq!(unknown.source(), "orders");
+}
+
+#[tokio::test]
+async fn test_record_backup_writes_readable_manifest() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    let dump = dump_file();
+    let now = Utc::now();
+    let written = record_backup(&store, "postgres/orders.dump", "orders", dump.path(), now, now).await.unwrap();
+    assert_eq!(read_manifest(&store, "postgres/orders.dump").await.unwrap(), Some(written));
+    assert_eq!(read_manifest(&store, "postgres/users.dump").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_browser_reads_manifests_and_deletes_them_with_snapshots() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let dump = dump_file();
+    for key in ["postgres/orders.dump", "postgres/users.dump"] {
+        store.upload_file(dump.path(), key, None).await.unwrap();
+    }
+    write_manifest(store.as_ref(), &manifest("postgres/orders.dump")).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store.clone());
+    browser.load_snapshots().await.unwrap();
+
+    // The manifest enriches its snapshot rather than being listed as one
+    let mut keys: Vec<&str> = browser.snapshots.iter().map(|snapshot| snapshot.key.as_str()).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["postgres/orders.dump", "postgres/users.dump"]);
+    assert_eq!(browser.manifests.get("postgres/orders.dump"), Some(&manifest("postgres/orders.dump")));
+    assert!(!browser.manifests.contains_key("postgres/users.dump"));
+
+    let orders = browser.snapshots.iter().find(|snapshot| snapshot.key == "postgres/orders.dump").cloned().unwrap();
+    browser.delete_snapshots(&[orders], Utc::now().date_naive()).await.unwrap();
+    assert_eq!(store.read_object(&manifest_key("postgres/orders.dump")).await.unwrap(), None);
+    assert!(browser.manifests.is_empty());
+}
+
+#[tokio::test]
+async fn test_prune_deletes_manifests_with_snapshots() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    let dump = dump_file();
+    for key in ["orders-1.dump", "orders-2.dump"] {
+        store.upload_file(dump.path(), key, None).await.unwrap();
+        write_manifest(&store, &manifest(key)).await.unwrap();
+    }
+    let policy = RetentionPolicy { prefix: "orders-".to_string(), daily: 1, ..Default::default() };
+    let plan = prune(&store, "", &[policy], Utc::now().date_naive(), false).await.unwrap();
+
+    assert_eq!(plan.keep.iter().map(|kept| kept.key.as_str()).collect::<Vec<_>>(), vec!["orders-2.dump"]);
+    assert_eq!(store.read_object(&manifest_key("orders-1.dump")).await.unwrap(), None);
+    assert!(store.read_object(&manifest_key("orders-2.dump")).await.unwrap().is_some());
+}
@@ -0,0 +1,139 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_bulk::bulk_restore;
+use rustored::mapping::RestoreMapping;
+use rustored::restore::RestoreTarget;
+use rustored::targets::ElasticsearchRestoreTarget;
+use rustored::ui::models::{BulkConfig, ElasticsearchConfig, RestoreTarget as TargetKind};
+use rustored::ui::rustored::RustoredApp;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const MAPPING: &str = r#"
+[[name]]
+from = "prod-logs-*"
+to = "staging-logs-*"
+
+[[name]]
+from = "orders"
+to = "orders-copy"
+
+[[field]]
+from = "customer.email"
+to = "customer.contact_email"
+
+[[field]]
+from = "level"
+to = "severity"
+"#;
+
+fn mapping() -> RestoreMapping {
+    RestoreMapping::parse(MAPPING).unwrap()
+}
+
+#[test]
+fn test_map_name_globs_and_exact_names() {
+    let mapping = mapping();
+    assert_eq!(mapping.map_name("prod-logs-2024.06"), "staging-logs-2024.06");
+    assert_eq!(mapping.map_name("orders"), "orders-copy");
+    assert_eq!(mapping.map_name("orders-2"), "orders-2");
+    assert_eq!(mapping.map_name("prod-metrics"), "prod-metrics");
+    assert_eq!(RestoreMapping::default().map_name("orders"), "orders");
+}
+
+#[test]
+fn test_map_document_renames_source_fields() {
+    let mapping = mapping();
+    let hit = json!({"_id": "1", "_source": {"level": "warn", "customer": {"email": "a@example.com", "id": 7}}});
+    assert_eq!(
+        mapping.map_document(hit),
+        json!({"_id": "1", "_source": {"severity": "warn", "customer": {"contact_email": "a@example.com", "id": 7}}})
+    );
+    // Bare documents are renamed in place, and missing fields are left alone
+    assert_eq!(mapping.map_document(json!({"level": "info", "n": 1})), json!({"severity": "info", "n": 1}));
+    assert_eq!(mapping.map_document(json!({"n": 1})), json!({"n": 1}));
+}
+
+#[test]
+fn test_map_point_renames_payload_fields_only() {
+    let point = json!({"id": 1, "vector": [0.5], "level": "top", "payload": {"level": "debug"}});
+    assert_eq!(
+        mapping().map_point(point),
+        json!({"id": 1, "vector": [0.5], "level": "top", "payload": {"severity": "debug"}})
+    );
+}
+
+#[test]
+fn test_mapping_lines_and_parse_errors() {
+    assert_eq!(
+        mapping().lines("prod-logs-1"),
+        vec![
+            "Mapping renames prod-logs-1 to staging-logs-1".to_string(),
+            "Renaming fields: customer.email -> customer.contact_email, level -> severity".to_string(),
+        ]
+    );
+    assert!(RestoreMapping::default().lines("orders").is_empty());
+
+    let err = RestoreMapping::parse("[[name]]\nfrom = \"a-*-*\"\nto = \"b-*\"\n").unwrap_err();
+    assert!(err.to_string().contains("at most one"));
+    let err = RestoreMapping::parse("[[name]]\nfrom = \"orders\"\nto = \"copy-*\"\n").unwrap_err();
+    assert!(err.to_string().contains("needs one in 'from'"));
+    assert!(RestoreMapping::parse("[[field]]\nfrom = \"a\"\nto = \"\"\n").is_err());
+}
+
+#[test]
+fn test_app_mapping_lines_follow_restore_target() {
+    let mut app = RustoredApp::new(
+        &None, &None, &None, &None, &None, &None, false,
+        &None, &None, &None, &None, false, &None,
+        &Some("http://localhost:9200".to_string()), &Some("orders".to_string()), &None,
+    );
+    app.set_restore_mapping(mapping());
+    assert!(app.mapping_lines().is_empty());
+    app.restore_target = TargetKind::Elasticsearch;
+    assert_eq!(app.mapping_lines()[0], "Mapping renames orders to orders-copy");
+    assert_eq!(app.es_config.target_index().as_deref(), Some("orders-copy"));
+}
+
+#[tokio::test]
+async fn test_elasticsearch_target_looks_up_mapped_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/staging-logs-1/_stats/docs,store"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    let target = ElasticsearchRestoreTarget::new(ElasticsearchConfig {
+        host: Some(server.uri()),
+        index: Some("prod-logs-1".to_string()),
+        mapping: mapping(),
+        ..Default::default()
+    });
+    let impact = target.impact().await.unwrap().unwrap();
+    assert_eq!((impact.name.as_str(), impact.exists), ("staging-logs-1", false));
+}
+
+#[tokio::test]
+async fn test_bulk_restore_sends_mapped_documents() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[{"index":{"status":201}}]}"#))
+        .mount(&server)
+        .await;
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "{\"_id\":\"1\",\"_source\":{\"level\":\"warn\"}}\n").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut dead_letters =
+        DeadLetterWriter::new("elasticsearch", "http://es", "orders", "documents", dir.path().join("orders.dead-letter.ndjson"));
+    let bulk = BulkConfig { disable_refresh: false, ..Default::default() };
+
+    let indexed = bulk_restore(&reqwest::Client::new(), None, &server.uri(), "orders", file.path(), &bulk, &mapping(), None, &mut dead_letters)
+        .await
+        .unwrap();
+    assert_eq!(indexed, 1);
+    let requests = server.received_requests().await.unwrap();
+    let body = requests.iter().find(|request| request.url.path() == "/_bulk").map(|request| String::from_utf8_lossy(&request.body).to_string()).unwrap();
+    assert!(body.contains(r#"{"severity":"warn"}"#), "{}", body);
+}
@@ -0,0 +1,132 @@
+use rustored::dead_letter::{read_dead_letters, DeadLetterWriter};
+use rustored::mapping::RestoreMapping;
+use rustored::meili_documents::{add_documents, detect_primary_key};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Snapshot of `count` movies keyed by `movie_id`
+fn snapshot(count: usize) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let lines: Vec<String> = (0..count).map(|i| format!(r#"{{"movie_id":{},"title":"Movie {}"}}"#, i, i)).collect();
+    std::fs::write(file.path(), lines.join("\n") + "\n").unwrap();
+    file
+}
+
+/// Dead-letter writer for the `movies` index, writing into `dir`
+fn dead_letters(dir: &tempfile::TempDir) -> DeadLetterWriter {
+    DeadLetterWriter::new("meilisearch", "http://meili", "movies", "documents", dir.path().join("movies.dead-letter.ndjson"))
+}
+
+/// A task that always reports `task` when polled
+async fn mock_task(server: &MockServer, uid: u64, task: serde_json::Value) {
+    Mock::given(method("GET"))
+        .and(path(format!("/tasks/{}", uid)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(task))
+        .mount(server)
+        .await;
+}
+
+#[test]
+fn test_detect_primary_key() {
+    assert_eq!(detect_primary_key(&json!({"id": 1, "user_id": 2})).unwrap(), "id");
+    assert_eq!(detect_primary_key(&json!({"movieId": 1, "title": "x"})).unwrap(), "movieId");
+    let error = detect_primary_key(&json!({"movie_id": 1, "user_id": 2})).unwrap_err().to_string();
+    assert!(error.contains("movie_id, user_id") && error.contains("--meili-primary-key"));
+    assert!(detect_primary_key(&json!({"title": "x"})).is_err());
+}
+
+#[tokio::test]
+async fn test_add_documents_creates_index_and_waits_for_tasks() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/indexes/movies")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/indexes"))
+        .and(body_json(json!({"uid": "movies", "primaryKey": "movie_id"})))
+        .respond_with(ResponseTemplate::new(202).set_body_json(json!({"taskUid": 0})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_task(&server, 0, json!({"uid": 0, "status": "succeeded"})).await;
+    Mock::given(method("POST"))
+        .and(path("/indexes/movies/documents"))
+        .and(query_param("primaryKey", "movie_id"))
+        .and(header("authorization", "Bearer secret"))
+        .and(header("content-type", "application/x-ndjson"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(json!({"taskUid": 1})))
+        .mount(&server)
+        .await;
+    // The first poll finds the task still processing
+    Mock::given(method("GET"))
+        .and(path("/tasks/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"uid": 1, "status": "processing"})))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    mock_task(&server, 1, json!({"uid": 1, "status": "succeeded"})).await;
+    let file = snapshot(3);
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let callback = move |fraction: f32| reported.lock().unwrap().push(fraction);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let client = reqwest::Client::new();
+    let indexed = add_documents(&client, &server.uri(), "movies", Some("secret"), None, file.path(), &RestoreMapping::default(), Some(&callback), &mut rejected).await.unwrap();
+
+    assert_eq!(indexed, 3);
+    let polls = server.received_requests().await.unwrap().iter().filter(|r| r.url.path() == "/tasks/1").count();
+    assert_eq!(polls, 2);
+    assert_eq!(progress.lock().unwrap().last().copied(), Some(1.0));
+    assert!(rejected.finish().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_add_documents_dead_letters_failed_task() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/indexes/movies"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"uid": "movies", "primaryKey": "movie_id"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/indexes/movies/documents"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(json!({"taskUid": 7})))
+        .mount(&server)
+        .await;
+    mock_task(&server, 7, json!({
+        "uid": 7,
+        "status": "failed",
+        "error": {"code": "invalid_document_id", "message": "Document identifier `1.5` is invalid."}
+    })).await;
+    let file = snapshot(2);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = dead_letters(&dir);
+    let indexed = add_documents(&reqwest::Client::new(), &server.uri(), "movies", None, None, file.path(), &RestoreMapping::default(), None, &mut rejected).await.unwrap();
+    assert_eq!(indexed, 0);
+
+    let report = rejected.finish().unwrap().unwrap();
+    assert_eq!(report.count, 2);
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters[0].record, json!({"movie_id": 0, "title": "Movie 0"}));
+    assert_eq!(letters[0].reason, "invalid_document_id: Document identifier `1.5` is invalid.");
+}
+
+#[tokio::test]
+async fn test_add_documents_refuses_other_primary_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/indexes/movies"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"uid": "movies", "primaryKey": "id"})))
+        .mount(&server)
+        .await;
+    let file = snapshot(1);
+
+    let dir = tempfile::tempdir().unwrap();
+    let error = add_documents(&reqwest::Client::new(), &server.uri(), "movies", None, Some("movie_id"), file.path(), &RestoreMapping::default(), None, &mut dead_letters(&dir))
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Index movies has primary key id, not movie_id");
+}
@@ -0,0 +1,59 @@
+use chrono::{TimeZone, Utc};
+use rustored::holds::{HoldCatalog, CATALOG_KEY};
+use rustored::manifest::Manifest;
+use rustored::migration::{migrate, version_of, MetadataKind, FORMAT_VERSION};
+use rustored::storage::{DemoStore, SnapshotStore};
+
+const LEGACY_MANIFEST: &str = r#"{"key": "pg/orders.dump", "sha256": "abc", "size": 5, "format": "custom",
+    "database": "orders", "started": "2025-01-01T00:00:00Z", "duration_seconds": 1.5}"#;
+
+const LEGACY_HOLDS: &str = r#"{"holds": [{"prefix": "pg/orders", "until": "2030-01-01", "reason": "case 7", "created": "2025-01-01"}]}"#;
+
+async fn upload(store: &DemoStore, key: &str, body: &str) {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), body).unwrap();
+    store.upload_file(file.path(), key, None).await.unwrap();
+}
+
+#[test]
+fn test_unstamped_objects_are_read_and_new_ones_are_stamped() {
+    let manifest = Manifest::from_json(LEGACY_MANIFEST.as_bytes()).unwrap();
+    assert_eq!(manifest.database, "orders");
+    let written: serde_json::Value = serde_json::from_slice(&manifest.to_json().unwrap()).unwrap();
+    assert_eq!(version_of(&written), FORMAT_VERSION);
+    assert_eq!(Manifest::from_json(&manifest.to_json().unwrap()).unwrap(), manifest);
+
+    let newer = r#"{"format_version": 99, "holds": []}"#;
+    let err = HoldCatalog::from_json(newer.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "Failed to parse hold catalog");
+    assert!(format!("{:#}", err).contains("format version 99, written by a newer rustored"), "{:#}", err);
+    assert_eq!(MetadataKind::of_key(".rustored/migration-backups/20250101T000000Z/.rustored/holds.json"), None);
+    assert_eq!(MetadataKind::of_key("pg/orders.dump.backup.json"), Some(MetadataKind::Manifest));
+}
+
+#[tokio::test]
+async fn test_migrate_upgrades_the_bucket_and_keeps_the_old_objects() {
+    let now = Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap();
+    let store = DemoStore::new("pg", now);
+    upload(&store, "pg/orders.dump.backup.json", LEGACY_MANIFEST).await;
+    upload(&store, CATALOG_KEY, LEGACY_HOLDS).await;
+    upload(&store, "pg/broken.dump.backup.json", "{not json").await;
+
+    let report = migrate(&store, true, now).await.unwrap();
+    assert_eq!((report.upgraded(), report.failed()), (2, 1));
+    assert_eq!(report.summary(true), format!("3 metadata objects: would upgrade 2, 0 already at version {}, 1 failed", FORMAT_VERSION));
+    assert_eq!(store.read_object(CATALOG_KEY).await.unwrap().unwrap(), LEGACY_HOLDS.as_bytes(), "a dry run writes nothing");
+
+    let report = migrate(&store, false, now).await.unwrap();
+    assert_eq!(report.upgraded(), 2);
+    assert!(report.summary(false).ends_with("; old objects copied under .rustored/migration-backups/20250314T120000Z/"));
+    let backup = store.read_object(&format!(".rustored/migration-backups/20250314T120000Z/{}", CATALOG_KEY)).await.unwrap().unwrap();
+    assert_eq!(backup, LEGACY_HOLDS.as_bytes());
+    let upgraded: serde_json::Value = serde_json::from_slice(&store.read_object(CATALOG_KEY).await.unwrap().unwrap()).unwrap();
+    assert_eq!(version_of(&upgraded), FORMAT_VERSION);
+    assert_eq!(upgraded["holds"][0]["reason"], "case 7");
+    assert_eq!(store.read_object("pg/broken.dump.backup.json").await.unwrap().unwrap(), b"{not json", "unreadable objects are left alone");
+
+    let report = migrate(&store, false, now).await.unwrap();
+    assert_eq!((report.upgraded(), report.failed()), (0, 1), "current objects are not rewritten again");
+}
@@ -0,0 +1,215 @@
+use prost::Message;
+use rustored::dead_letter::{read_dead_letters, DeadLetterWriter};
+use rustored::mapping::RestoreMapping;
+use rustored::milvus_proto::{
+    data_type, BoolResponse, CollectionRequest, CollectionSchema, CreateCollectionRequest, CreateIndexRequest, FieldSchema, FlushRequest,
+    InsertRequest, KeyValuePair, MutationResult, Status, StatusResponse,
+};
+use rustored::milvus_rows::{collection_name, collection_schema, import_rows, milvus_row, Columns, MilvusClient};
+use rustored::ui::models::TimeoutConfig;
+use serde_json::{json, Map, Value};
+use std::sync::{Arc, Mutex};
+use tonic::codegen::{http, BoxFuture, Context, Poll, Service};
+use tonic::server::{NamedService, UnaryService};
+
+/// A row as a JSON object
+fn row(value: Value) -> Map<String, Value> {
+    value.as_object().unwrap().clone()
+}
+
+/// Snapshot of `count` rows with 2-dimensional vectors
+fn snapshot(count: usize) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let lines: Vec<String> = (0..count)
+        .map(|i| json!({"id": i, "vector": [0.1, 0.2], "title": format!("Movie {}", i)}).to_string())
+        .collect();
+    std::fs::write(file.path(), lines.join("\n") + "\n").unwrap();
+    file
+}
+
+#[test]
+fn test_collection_names_are_valid_milvus_collections() {
+    assert_eq!(collection_name("movies-restored"), "movies_restored");
+    assert_eq!(collection_name("2024.movies"), "_2024_movies");
+    assert_eq!(collection_name("movies"), "movies");
+}
+
+#[test]
+fn test_rows_take_qdrant_payload_fields() {
+    let point = json!({"id": 7, "vector": [1.0, 2.0], "payload": {"title": "Alien", "year": 1979}, "rating": null});
+    assert_eq!(
+        milvus_row(point, &RestoreMapping::default()).unwrap(),
+        row(json!({"id": 7, "vector": [1.0, 2.0], "title": "Alien", "year": 1979}))
+    );
+    assert!(milvus_row(json!([1, 2]), &RestoreMapping::default()).is_err());
+    assert!(milvus_row(json!({"payload": "x"}), &RestoreMapping::default()).is_err());
+}
+
+#[test]
+fn test_schema_is_built_from_the_first_row() {
+    let schema = collection_schema("movies", &row(json!({"id": 1, "vector": [0.1, 0.2, 0.3], "title": "x"})), "vector").unwrap();
+    assert!(schema.enable_dynamic_field);
+    let (id, vector) = (&schema.fields[0], &schema.fields[1]);
+    assert_eq!((id.name.as_str(), id.data_type, id.is_primary_key, id.auto_id), ("id", data_type::INT64, true, false));
+    assert_eq!((vector.name.as_str(), vector.data_type), ("vector", data_type::FLOAT_VECTOR));
+    assert_eq!(vector.type_params, vec![KeyValuePair { key: "dim".to_string(), value: "3".to_string() }]);
+    // The schema survives the trip through CreateCollection's bytes
+    assert_eq!(CollectionSchema::decode(&schema.encode_to_vec()[..]).unwrap(), schema);
+
+    let uuid = collection_schema("movies", &row(json!({"id": "a1", "embedding": [0.1]})), "embedding").unwrap();
+    assert_eq!(uuid.fields[0].data_type, data_type::VARCHAR);
+    let generated = collection_schema("movies", &row(json!({"vector": [0.1]})), "vector").unwrap();
+    assert!(generated.fields[0].auto_id);
+    assert!(collection_schema("movies", &row(json!({"id": 1, "embedding": [0.1]})), "vector").is_err());
+}
+
+#[test]
+fn test_rows_are_laid_out_in_the_schema_columns() {
+    let schema = CollectionSchema {
+        name: "movies".to_string(),
+        fields: vec![
+            FieldSchema { name: "id".to_string(), is_primary_key: true, data_type: data_type::INT64, ..Default::default() },
+            FieldSchema {
+                name: "vector".to_string(),
+                data_type: data_type::FLOAT_VECTOR,
+                type_params: vec![KeyValuePair { key: "dim".to_string(), value: "2".to_string() }],
+                ..Default::default()
+            },
+            FieldSchema { name: "year".to_string(), data_type: data_type::INT16, ..Default::default() },
+        ],
+        enable_dynamic_field: true,
+        ..Default::default()
+    };
+    let mut columns = Columns::new(&schema);
+    assert!(columns.has_ids());
+    columns.push(row(json!({"id": 1, "vector": [0.5, 0.25], "year": 1979, "title": "Alien"}))).unwrap();
+    // Rows that do not fit leave the columns as they were
+    assert!(columns.push(row(json!({"id": 2, "vector": [0.5], "year": 1986}))).unwrap_err().contains("dimensions"));
+    assert!(columns.push(row(json!({"id": 3, "vector": [0.5, 0.5], "year": 100000}))).is_err());
+    assert!(columns.push(row(json!({"id": 4, "vector": [0.5, 0.5]}))).unwrap_err().contains("missing field year"));
+    assert_eq!(columns.len(), 1);
+
+    let data = columns.take();
+    assert!(columns.is_empty());
+    assert_eq!(data[0].scalars.as_ref().unwrap().long_data.as_ref().unwrap().data, vec![1]);
+    let vectors = data[1].vectors.as_ref().unwrap();
+    assert_eq!((vectors.dim, &vectors.float_vector.as_ref().unwrap().data), (2, &vec![0.5, 0.25]));
+    assert_eq!(data[2].scalars.as_ref().unwrap().int_data.as_ref().unwrap().data, vec![1979]);
+    assert!(data[3].is_dynamic);
+    let meta = &data[3].scalars.as_ref().unwrap().json_data.as_ref().unwrap().data[0];
+    assert_eq!(serde_json::from_slice::<Value>(meta).unwrap(), json!({"title": "Alien"}));
+}
+
+/// Requests a mock Milvus received
+#[derive(Clone, Default)]
+struct MockMilvus {
+    calls: Arc<Mutex<Vec<String>>>,
+    authorization: Arc<Mutex<Option<String>>>,
+    created: Arc<Mutex<Option<CollectionSchema>>>,
+    inserts: Arc<Mutex<Vec<InsertRequest>>>,
+}
+
+impl NamedService for MockMilvus {
+    const NAME: &'static str = "milvus.proto.milvus.MilvusService";
+}
+
+/// A unary gRPC method answered by a closure
+struct Unary<F>(F);
+
+impl<Req, Resp, F: FnMut(Req) -> Resp> UnaryService<Req> for Unary<F> {
+    type Response = Resp;
+    type Future = std::future::Ready<Result<tonic::Response<Resp>, tonic::Status>>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        std::future::ready(Ok(tonic::Response::new((self.0)(request.into_inner()))))
+    }
+}
+
+/// Answer a gRPC request with `answer`
+async fn answer<Req, Resp>(request: http::Request<tonic::body::Body>, answer: impl FnMut(Req) -> Resp) -> http::Response<tonic::body::Body>
+where
+    Req: Message + Default + Send + 'static,
+    Resp: Message + Send + 'static,
+{
+    tonic::server::Grpc::new(tonic_prost::ProstCodec::<Resp, Req>::default()).unary(Unary(answer), request).await
+}
+
+impl Service<http::Request<tonic::body::Body>> for MockMilvus {
+    type Response = http::Response<tonic::body::Body>;
+    type Error = std::convert::Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let mock = self.clone();
+        Box::pin(async move {
+            let method = request.uri().path().rsplit('/').next().unwrap_or_default().to_string();
+            mock.calls.lock().unwrap().push(method.clone());
+            *mock.authorization.lock().unwrap() = request.headers().get("authorization").map(|value| value.to_str().unwrap().to_string());
+            Ok(match method.as_str() {
+                "HasCollection" => answer(request, |_: CollectionRequest| BoolResponse { status: Some(Status::default()), value: false }).await,
+                "CreateCollection" => answer(request, |create: CreateCollectionRequest| {
+                    *mock.created.lock().unwrap() = Some(CollectionSchema::decode(&create.schema[..]).unwrap());
+                    Status::default()
+                }).await,
+                "CreateIndex" => answer(request, |_: CreateIndexRequest| Status::default()).await,
+                "LoadCollection" => answer(request, |_: CollectionRequest| Status::default()).await,
+                "Upsert" | "Insert" => answer(request, |insert: InsertRequest| {
+                    mock.inserts.lock().unwrap().push(insert);
+                    // The second row of the batch fails
+                    MutationResult { status: Some(Status::default()), succ_index: vec![0, 2], err_index: vec![1], insert_cnt: 2 }
+                }).await,
+                "Flush" => answer(request, |_: FlushRequest| StatusResponse { status: Some(Status::default()) }).await,
+                other => panic!("unexpected Milvus call {}", other),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_import_rows_creates_collection_and_dead_letters_failed_rows() {
+    let mock = MockMilvus::default();
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    tokio::spawn(tonic::transport::Server::builder().add_service(mock.clone()).serve(address));
+    let host = format!("http://{}", address);
+    let mut client = None;
+    for _ in 0..50 {
+        match MilvusClient::connect(&host, Some("root:Milvus"), None, &TimeoutConfig::default()).await {
+            Ok(connected) => {
+                client = Some(connected);
+                break;
+            }
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(20)).await,
+        }
+    }
+    let mut client = client.expect("mock Milvus did not start");
+
+    let file = snapshot(3);
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let callback = move |fraction: f32| reported.lock().unwrap().push(fraction);
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = DeadLetterWriter::new("milvus", &host, "movies", "rows", dir.path().join("movies.dead-letter.ndjson"));
+    let imported = import_rows(&mut client, "movies", "vector", "COSINE", file.path(), &RestoreMapping::default(), Some(&callback), &mut rejected)
+        .await
+        .unwrap();
+
+    assert_eq!(imported, 2);
+    assert_eq!(progress.lock().unwrap().last().copied(), Some(1.0));
+    assert_eq!(*mock.calls.lock().unwrap(), vec!["HasCollection", "CreateCollection", "CreateIndex", "LoadCollection", "Upsert", "Flush"]);
+    assert_eq!(mock.authorization.lock().unwrap().as_deref(), Some("cm9vdDpNaWx2dXM="));
+    let created = mock.created.lock().unwrap().clone().unwrap();
+    assert_eq!(created.name, "movies");
+    let insert = mock.inserts.lock().unwrap()[0].clone();
+    assert_eq!(insert.num_rows, 3);
+    assert_eq!(insert.fields_data.iter().map(|field| field.field_name.as_str()).collect::<Vec<_>>(), vec!["id", "vector", "$meta"]);
+
+    let report = rejected.finish().unwrap().unwrap();
+    assert_eq!(report.count, 1);
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters[0].record["id"], json!(1));
+    assert_eq!(letters[0].reason, "rejected by Milvus");
+}
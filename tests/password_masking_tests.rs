@@ -8,8 +8,10 @@ use rustored::ui::models::{
 #[test]
 fn test_postgres_password_masking() {
     // Create a new PostgresConfig with a password
-    let mut pg_config = PostgresConfig::default();
-    pg_config.password = Some("postgres_password".to_string());
+    let pg_config = PostgresConfig {
+        password: Some("postgres_password".to_string()),
+        ..Default::default()
+    };
     
     // Define variables to test both editing and non-editing scenarios
     let is_editing = true; // When editing, password should be visible
@@ -60,8 +62,10 @@ fn test_postgres_password_masking() {
 #[test]
 fn test_qdrant_api_key_masking() {
     // Create a new QdrantConfig with an API key
-    let mut qdrant_config = QdrantConfig::default();
-    qdrant_config.api_key = Some("qdrant_api_key".to_string());
+    let qdrant_config = QdrantConfig {
+        api_key: Some("qdrant_api_key".to_string()),
+        ..Default::default()
+    };
     
     // Define variables to test both editing and non-editing scenarios
     let is_editing = true; // When editing, password should be visible
@@ -112,11 +116,15 @@ fn test_qdrant_api_key_masking() {
 #[test]
 fn test_empty_password_handling() {
     // Create configs with empty passwords/secrets
-    let mut pg_config = PostgresConfig::default();
-    pg_config.password = Some("".to_string());
+    let pg_config = PostgresConfig {
+        password: Some("".to_string()),
+        ..Default::default()
+    };
     
-    let mut qdrant_config = QdrantConfig::default();
-    qdrant_config.api_key = Some("".to_string());
+    let qdrant_config = QdrantConfig {
+        api_key: Some("".to_string()),
+        ..Default::default()
+    };
     
     // When not editing, empty passwords should remain empty (not masked)
     let pg_masked_value = if pg_config.password.clone().unwrap_or_default().is_empty() {
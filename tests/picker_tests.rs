@@ -0,0 +1,69 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustored::picker::{filter_snapshots, fuzzy_score, Picker, PickerAction};
+use rustored::ui::models::BackupMetadata;
+
+fn snapshot(key: &str, last_modified: f64) -> BackupMetadata {
+    BackupMetadata::new(key, 1024 * 1024, last_modified)
+}
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+#[test]
+fn test_fuzzy_score_prefers_adjacent_and_segment_starts() {
+    assert_eq!(fuzzy_score("xyz", "pg/orders.dump"), None);
+    assert_eq!(fuzzy_score("gzp", "pg/orders.dump.gz"), None);
+    assert!(fuzzy_score("ORD", "pg/orders.dump").is_some());
+    assert!(fuzzy_score("ord", "pg/orders.dump") > fuzzy_score("ord", "pg/o-r-d.dump"));
+    assert!(fuzzy_score("od", "pg/orders.dump") < fuzzy_score("od", "pg/o_daily.dump"));
+    // Shorter keys win a tie
+    assert!(fuzzy_score("a", "a.dump") > fuzzy_score("a", "a.dump.gz"));
+}
+
+#[test]
+fn test_filter_snapshots_orders_by_score_then_newest() {
+    let snapshots = vec![
+        snapshot("pg/users-1.dump", 1.0),
+        snapshot("pg/orders-1.dump", 1.0),
+        snapshot("pg/users-2.dump", 2.0),
+    ];
+    assert_eq!(filter_snapshots("", &snapshots), vec![2, 0, 1]);
+    assert_eq!(filter_snapshots("users", &snapshots), vec![2, 0]);
+    assert_eq!(filter_snapshots("ord", &snapshots), vec![1]);
+}
+
+#[test]
+fn test_picker_narrows_moves_and_picks() {
+    let snapshots = vec![
+        snapshot("pg/users-1.dump", 1.0),
+        snapshot("pg/orders-1.dump", 1.0),
+        snapshot("pg/users-2.dump", 2.0),
+    ];
+    let mut picker = Picker::new(&snapshots);
+    assert_eq!(picker.visible_lines(2).len(), 2);
+
+    for c in "user".chars() {
+        assert_eq!(picker.handle_key(key(KeyCode::Char(c))), PickerAction::Continue);
+    }
+    assert_eq!(picker.matches, vec![2, 0]);
+    picker.handle_key(key(KeyCode::Down));
+    picker.handle_key(key(KeyCode::Down));
+    assert_eq!(picker.selected, 1);
+    let lines = picker.visible_lines(1);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].0.starts_with("pg/users-1.dump  1.00 MB"));
+    assert!(lines[0].1);
+    assert_eq!(picker.handle_key(key(KeyCode::Enter)), PickerAction::Pick(0));
+
+    // Editing the query resets the selection
+    picker.handle_key(key(KeyCode::Backspace));
+    assert_eq!(picker.query, "use");
+    assert_eq!(picker.selected, 0);
+
+    // Nothing to pick when nothing matches
+    picker.handle_key(key(KeyCode::Char('z')));
+    assert_eq!(picker.handle_key(key(KeyCode::Enter)), PickerAction::Continue);
+    assert_eq!(picker.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)), PickerAction::Cancel);
+    assert_eq!(picker.handle_key(key(KeyCode::Esc)), PickerAction::Cancel);
+}
@@ -0,0 +1,95 @@
+use chrono::{TimeZone, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustored::pitr::{base_time, parse_target_time, recovery_settings, segments_until, RecoveryWindow};
+use rustored::ui::models::{BackupMetadata, FocusField, PopupState};
+use rustored::ui::rustored::RustoredApp;
+use rustored::wal::group_chains;
+use std::path::Path;
+
+const BASE: &str = "pg/base/20250301T030000Z-000000010000000000000002.base.tar";
+
+fn object(key: &str, last_modified: f64) -> BackupMetadata {
+    BackupMetadata::new(key, 16, last_modified)
+}
+
+/// A base backup finished at 03:00 with segments archived at 03:10, 03:20 and, after a gap, 03:40
+fn objects() -> Vec<BackupMetadata> {
+    let at = |minute| Utc.with_ymd_and_hms(2025, 3, 1, 3, minute, 0).unwrap().timestamp() as f64;
+    vec![
+        object(BASE, at(0)),
+        object("pg/wal/000000010000000000000002.zst", at(10)),
+        object("pg/wal/000000010000000000000003.zst.age", at(20)),
+        object("pg/wal/000000010000000000000005", at(40)),
+    ]
+}
+
+#[test]
+fn test_recovery_window_ends_before_the_first_gap() {
+    assert_eq!(base_time(BASE), Some(Utc.with_ymd_and_hms(2025, 3, 1, 3, 0, 0).unwrap()));
+    assert_eq!(base_time(&format!("{}.age", BASE)), base_time(BASE));
+    assert_eq!(base_time("pg/orders-20250301T030000Z.dump"), None);
+
+    let chain = group_chains(&objects()).remove(BASE).unwrap();
+    let window = RecoveryWindow::of(&chain).unwrap();
+    assert_eq!(window.to_string(), "2025-03-01 03:00:00 to 2025-03-01 03:20:00");
+    assert!(window.check(Utc.with_ymd_and_hms(2025, 3, 1, 3, 15, 0).unwrap()).is_ok());
+    let error = window.check(Utc.with_ymd_and_hms(2025, 3, 1, 3, 30, 0).unwrap()).unwrap_err();
+    assert!(error.to_string().contains("outside the recoverable window"), "{}", error);
+    assert!(window.check(Utc.with_ymd_and_hms(2025, 3, 1, 2, 59, 0).unwrap()).is_err(), "the base backup is not consistent before it finished");
+}
+
+#[test]
+fn test_only_the_segments_up_to_the_target_are_fetched() {
+    let chain = group_chains(&objects()).remove(BASE).unwrap();
+    let early = Utc.with_ymd_and_hms(2025, 3, 1, 3, 5, 0).unwrap();
+    assert_eq!(segments_until(&chain, early), vec!["000000010000000000000002"]);
+    let late = Utc.with_ymd_and_hms(2025, 3, 1, 3, 20, 0).unwrap();
+    assert_eq!(segments_until(&chain, late), vec!["000000010000000000000002", "000000010000000000000003"]);
+}
+
+#[test]
+fn test_target_times_and_recovery_settings() {
+    let target = Utc.with_ymd_and_hms(2025, 3, 1, 14, 30, 0).unwrap();
+    assert_eq!(parse_target_time("2025-03-01 14:30").unwrap(), target);
+    assert_eq!(parse_target_time(" 2025-03-01 14:30:00 ").unwrap(), target);
+    assert_eq!(parse_target_time("2025-03-01T16:30:00+02:00").unwrap(), target);
+    assert!(parse_target_time("yesterday").is_err());
+
+    let settings = recovery_settings(Path::new("/tmp/pitr.wal"), Path::new("/tmp/pitr"), target, 5433);
+    assert!(settings.contains("restore_command = 'cp \"/tmp/pitr.wal/%f\" \"%p\"'\n"), "{}", settings);
+    assert!(settings.contains("recovery_target_time = '2025-03-01 14:30:00+00'\n"), "{}", settings);
+    assert!(settings.contains("recovery_target_action = 'promote'\n"), "{}", settings);
+    assert!(settings.contains("archive_mode = 'off'\n"), "the scratch instance must not archive into the live cluster's WAL");
+    assert!(settings.contains("port = 5433\n"), "{}", settings);
+    assert!(settings.contains("unix_socket_directories = '/tmp/pitr'\n"), "{}", settings);
+}
+
+#[tokio::test]
+async fn test_enter_on_a_base_backup_asks_for_a_recovery_target() {
+    let none = None;
+    let mut app = RustoredApp::new(&none, &none, &none, &none, &none, &none, false, &none, &None, &none, &none, false, &none, &none, &none, &none);
+    app.snapshot_browser.snapshots = vec![objects().remove(0)];
+    app.snapshot_browser.wal_chains = group_chains(&objects());
+    app.focus = FocusField::SnapshotList;
+
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    let PopupState::RecoverTarget(snapshot, window, typed) = &app.popup_state else {
+        panic!("expected the recovery target prompt, got {:?}", app.popup_state);
+    };
+    assert_eq!(snapshot.key, BASE);
+    assert_eq!(window, "2025-03-01 03:00:00 to 2025-03-01 03:20:00");
+    assert_eq!(typed, "2025-03-01 03:20:00", "the latest recoverable time is offered");
+
+    // A time outside the window fails before anything is downloaded
+    for _ in 0..8 {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE)).await;
+    }
+    for c in "04:00".chars() {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)).await;
+    }
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    let PopupState::Error(message) = &app.popup_state else {
+        panic!("expected an error, got {:?}", app.popup_state);
+    };
+    assert!(message.contains("outside the recoverable window"), "{}", message);
+}
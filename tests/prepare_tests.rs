@@ -0,0 +1,61 @@
+use rustored::prepare::{PrepareAction, TargetState};
+use rustored::presets::RestorePresets;
+
+const PRESETS: &str = r#"
+[[preset]]
+pattern = "orders*"
+jobs = 4
+
+[[target]]
+name = "staging"
+roles = ["app", "readonly"]
+extensions = ["pg_trgm"]
+settings = { work_mem = "64MB", statement_timeout = "30s" }
+
+[[target]]
+name = "dev"
+roles = ["app"]
+"#;
+
+fn state() -> TargetState {
+    TargetState {
+        roles: ["postgres", "app"].map(String::from).into(),
+        extensions: ["plpgsql"].map(String::from).into(),
+        available_extensions: ["plpgsql", "pg_trgm"].map(String::from).into(),
+        settings: [("work_mem".to_string(), "4MB".to_string()), ("statement_timeout".to_string(), "30s".to_string())].into(),
+    }
+}
+
+#[test]
+fn test_target_profiles_are_selected_by_name() {
+    let presets = RestorePresets::parse(PRESETS).unwrap();
+    assert_eq!(presets.presets.len(), 1);
+    assert_eq!(presets.target_profile(Some("dev")).unwrap().roles, vec!["app"]);
+    assert!(presets.target_profile(Some("prod")).is_err());
+    // With several profiles one has to be named
+    assert!(presets.target_profile(None).is_err());
+    assert!(RestorePresets::default().target_profile(None).is_err());
+}
+
+#[test]
+fn test_plan_only_includes_missing_state() {
+    let presets = RestorePresets::parse(PRESETS).unwrap();
+    let profile = presets.target_profile(Some("staging")).unwrap();
+    let actions = profile.plan(&state()).unwrap();
+    assert_eq!(actions, vec![
+        PrepareAction::CreateRole("readonly".to_string()),
+        PrepareAction::CreateExtension("pg_trgm".to_string()),
+        PrepareAction::SetSetting { name: "work_mem".to_string(), from: Some("4MB".to_string()), to: "64MB".to_string() },
+    ]);
+    assert_eq!(actions[0].sql(), "CREATE ROLE \"readonly\";");
+    assert_eq!(actions[2].sql(), "ALTER SYSTEM SET work_mem = '64MB';");
+    assert_eq!(actions[2].to_string(), "set work_mem from 4MB to 64MB");
+}
+
+#[test]
+fn test_plan_rejects_unavailable_extensions() {
+    let mut presets = RestorePresets::parse(PRESETS).unwrap();
+    presets.targets[0].extensions.push("postgis".to_string());
+    let err = presets.targets[0].plan(&state()).unwrap_err();
+    assert_eq!(err.to_string(), "Extensions not installed on the server: postgis");
+}
@@ -0,0 +1,156 @@
+use rustored::hooks::TemplateVars;
+use rustored::presets::{matches_pattern, RestoreMode, RestoreOptions, RestorePresets};
+
+const PRESETS: &str = r#"
+[[preset]]
+pattern = "orders*"
+jobs = 8
+drop_indexes = true
+masking_ruleset = "A"
+post_hook = "/usr/local/bin/mask {{restored_db}}"
+
+[[preset]]
+pattern = "*"
+jobs = 2
+"#;
+
+#[test]
+fn test_matches_pattern() {
+    assert!(matches_pattern("orders*", "orders-20250101.dump"));
+    assert!(matches_pattern("orders*", "orders"));
+    assert!(matches_pattern("*-prod-?.dump", "users-prod-1.dump"));
+    assert!(matches_pattern("*", ""));
+    assert!(!matches_pattern("orders*", "archived-orders.dump"));
+    assert!(!matches_pattern("*-prod-?.dump", "users-prod-10.dump"));
+}
+
+#[test]
+fn test_first_matching_preset_wins() {
+    let presets = RestorePresets::parse(PRESETS).unwrap();
+    assert_eq!(presets.presets.len(), 2);
+
+    // Snapshot keys are matched on their file name
+    let preset = presets.find("backups/orders-20250101.dump").unwrap();
+    assert_eq!(preset.pattern, "orders*");
+    assert_eq!(preset.options.jobs, Some(8));
+    assert_eq!(preset.options.masking_ruleset.as_deref(), Some("A"));
+
+    let preset = presets.find("backups/users.dump").unwrap();
+    assert_eq!(preset.pattern, "*");
+    assert!(!preset.options.drop_indexes);
+}
+
+#[test]
+fn test_invalid_presets_are_rejected() {
+    assert!(RestorePresets::parse("[[preset]]\njobs = 4\n").is_err());
+    assert!(RestorePresets::parse("[[preset]]\npattern = \"x\"\njobs = \"many\"\n").is_err());
+}
+
+#[test]
+fn test_pg_restore_args_and_summary() {
+    let options = RestoreOptions {
+        jobs: Some(4),
+        drop_indexes: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        options.pg_restore_args(),
+        vec!["--jobs", "4", "--section", "pre-data", "--section", "data"]
+    );
+    assert_eq!(options.summary(), "jobs=4, drop indexes");
+    assert!(RestoreOptions::default().pg_restore_args().is_empty());
+
+    let options = RestoreOptions { mode: RestoreMode::Clean, ..Default::default() };
+    assert_eq!(options.summary(), "jobs=default, into existing (clean)");
+    assert_eq!(RestoreMode::Clean.next(), RestoreMode::New);
+    // Restoring over a database is never taken from a preset
+    assert!(RestorePresets::parse("[[preset]]\npattern = \"x\"\nmode = \"clean\"\n").is_err());
+}
+
+#[test]
+fn test_gentle_restores_pace_themselves() {
+    let presets = RestorePresets::parse("[[preset]]\npattern = \"x\"\ngentle = true\ncopy_rate = \"20M\"\n").unwrap();
+    let options = &presets.presets[0].options;
+    assert_eq!(options.section_pause(), Some(std::time::Duration::from_secs(30)));
+    assert_eq!(options.copy_rate_limit().unwrap(), Some(20 * 1024 * 1024));
+    assert_eq!(options.summary(), "jobs=default, gentle (pause 30s, copy 20M/s)");
+
+    let options = RestoreOptions { gentle: false, ..options.clone() };
+    assert_eq!((options.section_pause(), options.copy_rate_limit().unwrap()), (None, None), "pacing only applies when gentle");
+    assert!(RestorePresets::parse("[[preset]]\npattern = \"x\"\nsection_pause_seconds = 5\n").is_err(), "a pause needs gentle");
+    let err = RestorePresets::parse("[[preset]]\npattern = \"x\"\ngentle = true\ncopy_rate = \"fast\"\n").unwrap_err();
+    assert!(err.to_string().contains("Invalid preset x: Invalid copy_rate"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_post_hook_receives_masking_ruleset() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("hook.out");
+    let options = RestoreOptions {
+        masking_ruleset: Some("A".to_string()),
+        post_hook: Some(format!(
+            "echo \"$RUSTORED_HOOK_EVENT $RUSTORED_TARGET $RUSTORED_MASKING_RULESET\" > {}",
+            out.display()
+        )),
+        ..Default::default()
+    };
+    options.run_post_hook(&TemplateVars::new("orders", "orders-20250101.dump")).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap().trim(), "post_restore orders A");
+}
+
+#[tokio::test]
+async fn test_post_hook_expands_template_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("hook.out");
+    let options = RestoreOptions {
+        post_hook: Some(format!(
+            "echo \"{{{{restored_db}}}} {{{{ source_db }}}} $RUSTORED_SNAPSHOT_KEY\" > {}",
+            out.display()
+        )),
+        ..Default::default()
+    };
+    let vars = TemplateVars::new("orders_copy", "postgres/orders-20250101T120000Z.dump");
+    options.run_post_hook(&vars).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap().trim(),
+        "orders_copy orders postgres/orders-20250101T120000Z.dump"
+    );
+}
+
+#[tokio::test]
+async fn test_post_hook_runs_a_malicious_snapshot_key_as_one_argument() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("hook.out");
+    let marker = dir.path().join("injected");
+    let options = RestoreOptions {
+        post_hook: Some(format!("printf '%s\\n' {{{{snapshot_key}}}} > {}", out.display())),
+        ..Default::default()
+    };
+    let key = format!("x;touch {}", marker.display());
+    options.run_post_hook(&TemplateVars::new("orders", &key)).await.unwrap();
+    assert!(!marker.exists(), "the snapshot key ran as a command");
+    assert_eq!(std::fs::read_to_string(&out).unwrap().trim(), key);
+}
+
+#[tokio::test]
+async fn test_pre_hook_describes_the_snapshot_and_target() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("hook.out");
+    let options = RestoreOptions {
+        pre_hook: Some(format!(
+            "echo \"$RUSTORED_HOOK_EVENT $RUSTORED_DATASTORE $RUSTORED_TARGET_HOST {{{{restored_db}}}} $RUSTORED_SOURCE_DB\" > {}",
+            out.display()
+        )),
+        ..Default::default()
+    };
+    assert_eq!(options.summary(), format!("jobs=default, pre-hook={}", options.pre_hook.as_deref().unwrap()));
+    let vars = TemplateVars::new("orders", "postgres/orders-20250101.dump").with_target("postgres", "db.internal");
+    options.run_pre_hook(&vars).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap().trim(), "pre_restore postgres db.internal orders orders");
+    // Without a post hook nothing runs
+    options.run_post_hook(&vars).await.unwrap();
+
+    let failing = RestoreOptions { pre_hook: Some("echo paused elsewhere >&2; exit 3".to_string()), ..Default::default() };
+    let error = failing.run_pre_hook(&vars).await.unwrap_err().to_string();
+    assert!(error.contains("pre_restore hook exited") && error.contains("paused elsewhere"), "{}", error);
+}
@@ -0,0 +1,111 @@
+use rustored::presets::RestorePresets;
+use rustored::profiles::{banner, from_env, is_production, to_toml, write_profile, ConnectionProfile, TargetServer};
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(var, value)| (var.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn test_profile_from_env_leaves_secrets_out() {
+    let imported = from_env("legacy", vars(&[
+        ("PG_HOST", "db.internal"),
+        ("PG_PORT", "6432"),
+        ("PG_USE_SSL", "TRUE"),
+        ("PG_PASSWORD", "hunter2"),
+        ("PG_DB_NAME", ""),
+        ("S3_BUCKET", "acme-backups"),
+        ("S3_PATH_STYLE", "false"),
+        ("S3_SECRET_ACCESS_KEY", "abc"),
+        ("S3_MAX_KEYS", "1000"),
+        ("HOME", "/root"),
+    ])).unwrap();
+
+    assert_eq!(imported.profile, ConnectionProfile {
+        name: "legacy".to_string(),
+        host: Some("db.internal".to_string()),
+        port: Some(6432),
+        use_ssl: Some(true),
+        bucket: Some("acme-backups".to_string()),
+        path_style: Some(false),
+        ..Default::default()
+    });
+    assert_eq!(imported.left_out, vec!["PG_PASSWORD", "S3_MAX_KEYS", "S3_SECRET_ACCESS_KEY"]);
+    assert!(!to_toml(&imported.profile).unwrap().contains("hunter2"));
+
+    let error = from_env("legacy", vars(&[("PG_PORT", "postgres")])).unwrap_err().to_string();
+    assert!(error.contains("PG_PORT"), "{}", error);
+    assert!(from_env("legacy", vars(&[("S3_PATH_STYLE", "yes")])).is_err());
+}
+
+#[test]
+fn test_write_profile_appends_to_the_presets_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rustored.toml");
+    std::fs::write(&path, "# Restores of the orders database\n[[preset]]\npattern = \"orders*\"\njobs = 4").unwrap();
+
+    let profile = ConnectionProfile { name: "staging".to_string(), host: Some("db.staging".to_string()), port: Some(5433), ..Default::default() };
+    write_profile(&path, &profile).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.starts_with("# Restores of the orders database\n"));
+
+    let presets = RestorePresets::parse(&contents).unwrap();
+    assert_eq!(presets.presets.len(), 1);
+    assert_eq!(presets.profiles, vec![profile.clone()]);
+
+    let error = write_profile(&path, &profile).unwrap_err().to_string();
+    assert!(error.contains("already has a profile named staging"), "{}", error);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+
+    // A missing file is created
+    let new = dir.path().join("new.toml");
+    write_profile(&new, &profile).unwrap();
+    assert_eq!(RestorePresets::parse(&std::fs::read_to_string(&new).unwrap()).unwrap().profiles.len(), 1);
+}
+
+#[test]
+fn test_profiles_are_looked_up_by_name() {
+    let mut presets = RestorePresets::parse("[[profile]]\nname = \"prod\"\nhost = \"db.prod\"\n").unwrap();
+    assert!(presets.profile("prod").unwrap_err().to_string().contains("--presets-file"));
+
+    presets.source = Some("rustored.toml".into());
+    assert_eq!(presets.profile("prod").unwrap().host.as_deref(), Some("db.prod"));
+    assert!(presets.profile("staging").is_err());
+
+    let presets = RestorePresets::parse("[[profile]]\nname = \"prod\"\nenvironment = \"Production\"\n").unwrap();
+    let environment = presets.profiles[0].environment.as_deref().unwrap();
+    assert!(is_production(environment));
+    assert!(!is_production("staging"));
+    assert_eq!(banner(environment, "us-east-1"), "PRODUCTION — us-east-1");
+    assert_eq!(banner("staging", ""), "STAGING");
+
+    let error = RestorePresets::parse("[[profile]]\nname = \"prod\"\nhots = \"db.prod\"\n").unwrap_err().to_string();
+    assert!(error.contains("line 3"), "{}", error);
+}
+
+#[test]
+fn test_profiles_list_servers_to_restore_onto() {
+    let presets = RestorePresets::parse(concat!(
+        "[[profile]]\nname = \"staging\"\nenvironment = \"staging\"\nhost = \"db-a.staging\"\n",
+        "[[profile.server]]\nname = \"staging-a\"\nhost = \"db-a.staging\"\n",
+        "[[profile.server]]\nname = \"staging-b\"\nhost = \"db-b.staging\"\nport = 6432\nenvironment = \"qa\"\n",
+    )).unwrap();
+    let servers = &presets.profiles[0].servers;
+    assert_eq!(servers.len(), 2);
+    assert_eq!(servers[1], TargetServer {
+        name: "staging-b".to_string(),
+        environment: Some("qa".to_string()),
+        host: "db-b.staging".to_string(),
+        port: Some(6432),
+        ..Default::default()
+    });
+    assert_eq!(servers[1].label(), "staging-b        db-b.staging:6432 (qa)");
+    // Servers survive being written back out
+    assert_eq!(RestorePresets::parse(&to_toml(&presets.profiles[0]).unwrap()).unwrap().profiles, presets.profiles);
+
+    let error = RestorePresets::parse("[[profile]]\nname = \"staging\"\n[[profile.server]]\nname = \"dev\"\n").unwrap_err().to_string();
+    assert!(error.contains("host"), "{}", error);
+    let error = RestorePresets::parse("[[profile]]\nname = \"staging\"\n[[profile.server]]\nname = \"dev\"\nhost = \"a\"\n[[profile.server]]\nname = \"dev\"\nhost = \"b\"\n")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("dev"), "{}", error);
+}
@@ -0,0 +1,35 @@
+use rustored::progress::{Progress, ProgressBoard};
+
+#[test]
+fn test_progress_keeps_latest_value_per_job() {
+    let board = ProgressBoard::default();
+    let mut updates = board.subscribe();
+    let download = board.reporter("Download of pg/a.dump");
+    let restore = board.reporter("Restore of pg/a.dump");
+    updates.borrow_and_update();
+
+    // A flood of updates leaves one pending change and only the latest value
+    for i in 0..100_000 {
+        download.report(Progress { fraction: i as f32 / 100_000.0, rate: 1024.0 });
+    }
+    restore.fraction(0.5);
+    assert!(updates.has_changed().unwrap());
+    assert_eq!(updates.borrow_and_update().len(), 2);
+    assert!(!updates.has_changed().unwrap());
+    assert_eq!(board.latest("Download of pg/a.dump").unwrap().fraction, 99_999.0 / 100_000.0);
+    assert_eq!(board.latest("Restore of pg/a.dump"), Some(Progress { fraction: 0.5, rate: 0.0 }));
+
+    // Reporting an unchanged value does not wake the renderer
+    restore.fraction(0.5);
+    assert!(!updates.has_changed().unwrap());
+
+    // The callback form used by restore targets clamps and keeps the rate
+    download.callback()(2.0);
+    assert_eq!(board.latest("Download of pg/a.dump"), Some(Progress { fraction: 1.0, rate: 1024.0 }));
+
+    // Finished jobs are dropped, so the board does not grow
+    download.finish();
+    board.finish("Restore of pg/a.dump");
+    assert!(board.jobs().is_empty());
+    assert_eq!(board.latest("Download of pg/a.dump"), None);
+}
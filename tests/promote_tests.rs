@@ -0,0 +1,91 @@
+use chrono::{Duration, TimeZone};
+use rustored::promote::{grant_statement, load_history, record_promotion, retired_name, PromotePlan, PromotionHistory};
+use rustored::storage::CloudStore;
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::S3Config;
+use std::sync::Arc;
+
+#[test]
+fn test_retired_name_fits_identifier() {
+    let now = chrono::Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    assert_eq!(retired_name("orders", now), "orders-retired-20250102030405");
+
+    let long = "a".repeat(80);
+    let name = retired_name(&long, now);
+    assert_eq!(name.len(), 63);
+    assert!(name.ends_with("-retired-20250102030405"));
+}
+
+#[test]
+fn test_promote_plan_steps() {
+    let now = chrono::Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    let mut plan = PromotePlan::new("orders", "apple-restored", now);
+    assert_eq!(plan.steps(), vec![
+        "Disconnect all sessions from orders and apple-restored",
+        "Rename live orders to orders-retired-20250102030405 (kept)",
+        "Rename restored apple-restored to orders",
+    ]);
+    plan.transfer_ownership = true;
+    assert_eq!(plan.steps().last().unwrap(), "Give orders the owner and grants of the old orders");
+}
+
+#[test]
+fn test_grant_statement() {
+    assert_eq!(grant_statement("orders", "PUBLIC", "CONNECT", false), "GRANT CONNECT ON DATABASE \"orders\" TO PUBLIC;");
+    assert_eq!(
+        grant_statement("orders", "app", "TEMPORARY", true),
+        "GRANT TEMPORARY ON DATABASE \"orders\" TO \"app\" WITH GRANT OPTION;"
+    );
+}
+
+#[test]
+fn test_rollback_candidate_checks_window_and_history() {
+    let promoted_at = chrono::Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    let mut history = PromotionHistory::default();
+    history.record("db1:5432", &PromotePlan::new("orders", "apple-restored", promoted_at), promoted_at);
+    let window = Duration::hours(24);
+
+    let promotion = history.rollback_candidate("db1:5432", "orders", promoted_at + Duration::hours(2), window).unwrap();
+    assert_eq!(promotion.restored_db, "apple-restored");
+    assert_eq!(promotion.retired_db, "orders-retired-20250102030405");
+
+    let err = history.rollback_candidate("db1:5432", "orders", promoted_at + Duration::hours(25), window).unwrap_err();
+    assert!(err.to_string().contains("outside the 24 hour rollback window"));
+    assert!(history.rollback_candidate("db1:5432", "users", promoted_at, window).is_err());
+
+    history.mark_rolled_back("db1:5432", "orders", promoted_at + Duration::hours(1));
+    let err = history.rollback_candidate("db1:5432", "orders", promoted_at + Duration::hours(2), window).unwrap_err();
+    assert!(err.to_string().contains("already rolled back"));
+}
+
+#[test]
+fn test_rollback_history_is_kept_per_server() {
+    let promoted_at = chrono::Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    let mut history = PromotionHistory::default();
+    history.record("db1:5432", &PromotePlan::new("orders", "apple-restored", promoted_at), promoted_at);
+    history.record("db2:5432", &PromotePlan::new("orders", "pear-restored", promoted_at), promoted_at);
+    let window = Duration::hours(24);
+
+    let promotion = history.rollback_candidate("db1:5432", "orders", promoted_at, window).unwrap();
+    assert_eq!(promotion.restored_db, "apple-restored");
+    assert!(history.rollback_candidate("db1:6432", "orders", promoted_at, window).is_err());
+
+    history.mark_rolled_back("db2:5432", "orders", promoted_at);
+    assert!(history.rollback_candidate("db1:5432", "orders", promoted_at, window).is_ok());
+    assert!(history.rollback_candidate("db2:5432", "orders", promoted_at, window).is_err());
+}
+
+#[tokio::test]
+async fn test_promotions_are_recorded_in_the_bucket() {
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "pg/".to_string(), ..Default::default() });
+    browser.set_store(Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new())));
+    let now = chrono::Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+    record_promotion(&mut browser, "db1:5432", &PromotePlan::new("orders", "apple-restored", now), now).await.unwrap();
+    record_promotion(&mut browser, "db1:5432", &PromotePlan::new("users", "pear-restored", now), now).await.unwrap();
+
+    let history = load_history(&mut browser).await.unwrap();
+    assert_eq!(history.promotions.len(), 2);
+    assert_eq!(history.promotions[0].live_db, "orders");
+    assert_eq!(history.promotions[0].server, "db1:5432");
+    assert_eq!(history.promotions[1].rolled_back_at, None);
+}
@@ -0,0 +1,44 @@
+use chrono::{TimeZone, Utc};
+use rustored::gc::{parse_throwaway_comment, throwaway_comment};
+use rustored::provenance::{operator, Provenance};
+
+fn provenance(throwaway: bool) -> Provenance {
+    Provenance {
+        snapshot_key: Some("postgres/orders 2024/orders-20240601T030000Z.dump".to_string()),
+        restored_at: Utc.with_ymd_and_hms(2024, 6, 2, 9, 30, 0).unwrap(),
+        operator: Some("alice".to_string()),
+        throwaway,
+    }
+}
+
+#[test]
+fn test_provenance_round_trips_through_the_database_comment() {
+    let kept = provenance(false);
+    assert_eq!(kept.to_comment(), "rustored:restore created=2024-06-02T09:30:00Z by=alice snapshot=postgres/orders 2024/orders-20240601T030000Z.dump");
+    assert_eq!(Provenance::from_comment(&kept.to_comment()), Some(kept.clone()));
+    let throwaway = provenance(true);
+    assert_eq!(Provenance::from_comment(&throwaway.to_comment()), Some(throwaway.clone()));
+    assert_eq!(kept.summary(), "postgres/orders 2024/orders-20240601T030000Z.dump 2024-06-02 09:30 UTC by alice");
+
+    assert_eq!(Provenance::from_comment("reporting copy, ask Sam"), None);
+    assert_eq!(Provenance::from_comment("rustored:restore by=alice"), None);
+}
+
+#[test]
+fn test_gc_still_finds_throwaways_once_their_provenance_is_recorded() {
+    let created = Utc.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap();
+    let tagged = Provenance::from_comment(&throwaway_comment(created)).unwrap();
+    assert!(tagged.throwaway && tagged.snapshot_key.is_none() && tagged.operator.is_none());
+    assert_eq!(tagged.restored_at, created);
+
+    assert_eq!(parse_throwaway_comment(&provenance(true).to_comment()), Some(provenance(true).restored_at));
+    assert_eq!(parse_throwaway_comment(&provenance(false).to_comment()), None);
+}
+
+#[test]
+fn test_operator_can_be_named_explicitly() {
+    std::env::set_var("RUSTORED_OPERATOR", "release-bot");
+    assert_eq!(operator(), "release-bot");
+    std::env::remove_var("RUSTORED_OPERATOR");
+    assert!(!operator().is_empty());
+}
@@ -0,0 +1,99 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_index::sidecar_path;
+use rustored::mapping::RestoreMapping;
+use rustored::qdrant_points::{collection_body, read_collection_params, upload_points, QdrantConnection};
+use rustored::ui::models::{QdrantUploadConfig, TimeoutConfig, TlsConfig};
+use serde_json::json;
+use wiremock::matchers::{body_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// The `GET /collections/products` answer of a collection with 4-dimensional Dot vectors
+fn exported() -> serde_json::Value {
+    json!({
+        "result": {
+            "status": "green",
+            "points_count": 2,
+            "config": {
+                "params": {"vectors": {"size": 4, "distance": "Dot"}, "shard_number": 2, "on_disk_payload": true},
+                "hnsw_config": {"m": 16}
+            }
+        },
+        "status": "ok",
+        "time": 0.001
+    })
+}
+
+/// Server where collection `products` is missing and takes any upsert
+async fn missing_collection() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/collections/products")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("PUT")).and(path("/collections/products/points")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    server
+}
+
+#[test]
+fn test_collection_is_sampled_from_first_point() {
+    let upload = QdrantUploadConfig::default();
+    let first = json!({"id": 1, "vector": {"text": [0.1, 0.2, 0.3], "colbert": [[0.1, 0.2], [0.3, 0.4]], "keywords": {"indices": [1], "values": [0.5]}}});
+    assert_eq!(
+        collection_body(None, &upload, &first).unwrap(),
+        json!({
+            "vectors": {
+                "text": {"size": 3, "distance": "Cosine"},
+                "colbert": {"size": 2, "distance": "Cosine", "multivector_config": {"comparator": "max_sim"}}
+            },
+            "sparse_vectors": {"keywords": {}}
+        })
+    );
+
+    let configured = QdrantUploadConfig { vector_size: Some(8), distance: Some("euclidean".to_string()), ..upload.clone() };
+    assert_eq!(collection_body(None, &configured, &json!({"id": 1, "vector": [0.5, 0.5]})).unwrap(), json!({"vectors": {"size": 8, "distance": "Euclid"}}));
+    assert_eq!(collection_body(None, &configured, &json!({"id": 1})).unwrap(), json!({"vectors": {"size": 8, "distance": "Euclid"}}));
+
+    assert!(collection_body(None, &upload, &json!({"id": 1})).unwrap_err().to_string().contains("set the vector size"));
+    let unknown = QdrantUploadConfig { distance: Some("hamming".to_string()), ..upload };
+    assert!(collection_body(None, &unknown, &first).unwrap_err().to_string().contains("Unknown Qdrant distance hamming"));
+}
+
+#[tokio::test]
+async fn test_missing_collection_is_created_from_stored_params() {
+    let server = missing_collection().await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products"))
+        .and(body_json(json!({"vectors": {"size": 4, "distance": "Dot"}, "shard_number": 2, "on_disk_payload": true})))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.ndjson");
+    std::fs::write(&snapshot, "{\"id\":1,\"vector\":[0.1,0.2,0.3,0.4]}\n").unwrap();
+    std::fs::write(sidecar_path(&snapshot), exported().to_string()).unwrap();
+    assert_eq!(read_collection_params(&snapshot).unwrap().unwrap()["vectors"], json!({"size": 4, "distance": "Dot"}));
+
+    let connection = QdrantConnection::http(reqwest::Client::new(), &server.uri(), None);
+    let mut dead_letters = DeadLetterWriter::new("qdrant", &server.uri(), "products", "points", dir.path().join("products.dead-letter.ndjson"));
+    let loaded = upload_points(&connection, "products", &snapshot, &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+    assert_eq!(loaded, 1);
+}
+
+#[tokio::test]
+async fn test_configured_distance_overrides_stored_one() {
+    let server = missing_collection().await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products"))
+        .and(body_json(json!({"vectors": {"size": 4, "distance": "Manhattan"}})))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.ndjson");
+    std::fs::write(&snapshot, "{\"id\":1,\"vector\":[0.1,0.2,0.3,0.4]}\n").unwrap();
+    std::fs::write(sidecar_path(&snapshot), json!({"vectors": {"size": 4, "distance": "Dot"}}).to_string()).unwrap();
+
+    let upload = QdrantUploadConfig { distance: Some("Manhattan".to_string()), ..QdrantUploadConfig::default() };
+    let connection = QdrantConnection::connect(&server.uri(), None, &TimeoutConfig::default(), &TlsConfig::default(), &upload).await.unwrap();
+    let mut dead_letters = DeadLetterWriter::new("qdrant", &server.uri(), "products", "points", dir.path().join("products.dead-letter.ndjson"));
+    upload_points(&connection, "products", &snapshot, &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+}
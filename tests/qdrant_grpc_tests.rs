@@ -0,0 +1,58 @@
+use qdrant_client::qdrant::{NamedVectors, PointStruct, Vector, Vectors};
+use qdrant_client::Payload;
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::mapping::RestoreMapping;
+use rustored::qdrant_points::{grpc_point, upload_points, QdrantConnection};
+use rustored::ui::models::{QdrantTransport, QdrantUploadConfig, TimeoutConfig, TlsConfig};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn test_grpc_url_keeps_host_and_replaces_port() {
+    let upload = QdrantUploadConfig::default();
+    assert_eq!(upload.grpc_url("http://qdrant:6333").unwrap(), "http://qdrant:6334");
+    assert_eq!(upload.grpc_url("http://localhost:6333/").unwrap(), "http://localhost:6334");
+
+    let custom = QdrantUploadConfig { grpc_port: Some(7334), ..upload };
+    assert_eq!(custom.grpc_url("https://vectors.example.com").unwrap(), "https://vectors.example.com:7334");
+    assert!(custom.grpc_url("qdrant:6333").is_err());
+}
+
+#[test]
+fn test_points_convert_to_grpc() {
+    let dense = grpc_point(&json!({"id": 7, "vector": [0.5, 0.25], "payload": {"color": "red", "stock": 3}})).unwrap();
+    let payload = Payload::try_from(json!({"color": "red", "stock": 3})).unwrap();
+    assert_eq!(dense, PointStruct::new(7, Vectors::from(Vector::new_dense(vec![0.5, 0.25])), payload));
+
+    let named = grpc_point(&json!({
+        "id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+        "vector": {"text": [1.0], "keywords": {"indices": [3, 9], "values": [0.5, 0.75]}}
+    }))
+    .unwrap();
+    let vectors = NamedVectors::default().add_vector("text", Vector::new_dense(vec![1.0])).add_vector("keywords", Vector::new_sparse(vec![3, 9], vec![0.5, 0.75]));
+    assert_eq!(named, PointStruct::new("5c56c793-69f3-4fbf-87e6-c4bf54c28c26", vectors, Payload::new()));
+
+    assert!(grpc_point(&json!({"id": -1, "vector": [0.5]})).unwrap_err().contains("not an unsigned integer"));
+    assert!(grpc_point(&json!({"id": 1})).unwrap_err().contains("no vector"));
+    assert!(grpc_point(&json!({"id": 1, "vector": {"text": ["a"]}})).unwrap_err().contains("vector text"));
+}
+
+#[tokio::test]
+async fn test_unreachable_grpc_falls_back_to_http() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/collections/products")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("PUT")).and(path("/collections/products/points")).respond_with(ResponseTemplate::new(200)).expect(1).mount(&server).await;
+    // A port nothing listens on
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let upload = QdrantUploadConfig { transport: QdrantTransport::Grpc, grpc_port: Some(closed), ..QdrantUploadConfig::default() };
+
+    let connection = QdrantConnection::connect(&server.uri(), None, &TimeoutConfig::default(), &TlsConfig::default(), &upload).await.unwrap();
+    assert!(!connection.is_grpc());
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.ndjson");
+    std::fs::write(&snapshot, "{\"id\":1,\"vector\":[0.1,0.2]}\n").unwrap();
+    let mut dead_letters = DeadLetterWriter::new("qdrant", &server.uri(), "products", "points", dir.path().join("products.dead-letter.ndjson"));
+    let loaded = upload_points(&connection, "products", &snapshot, &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+    assert_eq!(loaded, 1);
+}
@@ -0,0 +1,114 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::mapping::RestoreMapping;
+use rustored::qdrant_points::{upload_points, QdrantConnection};
+use rustored::qdrant_snapshot::{is_native_snapshot, is_native_snapshot_key};
+use rustored::restore::{ImpactAction, RestoreImpact};
+use rustored::ui::models::{QdrantUploadConfig, TimeoutConfig, TlsConfig};
+use serde_json::json;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A tar archive of one empty block, as far as its header magic goes
+fn tar_archive(name: &str) -> Vec<u8> {
+    let mut archive = vec![0u8; 1024];
+    archive[..name.len()].copy_from_slice(name.as_bytes());
+    archive[257..263].copy_from_slice(b"ustar\0");
+    archive
+}
+
+#[test]
+fn test_native_snapshots_are_recognised() {
+    assert!(is_native_snapshot_key("qdrant/products-3187-2024-06-01-03-00-00.snapshot"));
+    assert!(is_native_snapshot_key("qdrant/products-3187-2024-06-01-03-00-00.snapshot.zst"));
+    assert!(!is_native_snapshot_key("qdrant/products-2024-06-01.ndjson"));
+    assert!(!is_native_snapshot_key("qdrant/products.snapshot.json"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.snapshot");
+    std::fs::write(&snapshot, tar_archive("config.json")).unwrap();
+    assert!(is_native_snapshot(&snapshot).unwrap());
+    std::fs::write(&snapshot, "{\"id\":1,\"vector\":[0.1,0.2]}\n").unwrap();
+    assert!(!is_native_snapshot(&snapshot).unwrap());
+}
+
+#[test]
+fn test_native_snapshot_replaces_existing_collection() {
+    let existing = RestoreImpact { exists: true, count: Some(12), ..RestoreImpact::missing("collection", "products", "points") };
+    let impact = existing.clone().for_snapshot("qdrant/products-3187-2024-06-01-03-00-00.snapshot");
+    assert_eq!(impact.action(), ImpactAction::Replace);
+    assert_eq!(impact.lines()[1], "Restore will replace its points with the snapshot's; points not in the snapshot are lost");
+    assert_eq!(existing.clone().for_snapshot("qdrant/products.ndjson").action(), ImpactAction::Append);
+
+    // Milvus collections hold rows, and are never recovered from a Qdrant snapshot
+    let milvus = RestoreImpact { exists: true, ..RestoreImpact::missing("collection", "products", "rows") };
+    assert_eq!(milvus.for_snapshot("milvus/products.snapshot").action(), ImpactAction::Append);
+}
+
+#[tokio::test]
+async fn test_native_snapshot_is_uploaded_for_recovery() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/collections/products/snapshots/upload"))
+        .and(query_param("priority", "snapshot"))
+        .and(query_param("wait", "true"))
+        .and(header("api-key", "secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": true, "status": "ok"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/collections/products"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": {"points_count": 42}})))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).and(path("/collections/products/points")).respond_with(ResponseTemplate::new(200)).expect(0).mount(&server).await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.snapshot");
+    let archive = tar_archive("config.json");
+    std::fs::write(&snapshot, &archive).unwrap();
+
+    let connection = QdrantConnection::http(reqwest::Client::new(), &server.uri(), Some("secret"));
+    let mut dead_letters = DeadLetterWriter::new("qdrant", &server.uri(), "products", "points", dir.path().join("products.dead-letter.ndjson"));
+    let progress = |_: f32| {};
+    let points = upload_points(&connection, "products", &snapshot, &RestoreMapping::default(), Some(&progress), &mut dead_letters).await.unwrap();
+    assert_eq!(points, 42);
+
+    let requests = server.received_requests().await.unwrap();
+    let upload = requests.iter().find(|request| request.method.as_str() == "POST").unwrap();
+    let content_type = upload.headers.get("content-type").unwrap().to_str().unwrap();
+    let boundary = content_type.strip_prefix("multipart/form-data; boundary=").expect(content_type);
+    let head = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"snapshot\"; filename=\"products.snapshot\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        boundary
+    );
+    let mut expected = head.into_bytes();
+    expected.extend_from_slice(&archive);
+    expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    assert_eq!(upload.body, expected);
+}
+
+#[tokio::test]
+async fn test_snapshot_recovery_outlasts_the_read_timeout() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/collections/products"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": {"points_count": 7}})))
+        .mount(&server)
+        .await;
+    // Recovering takes longer than the one second read timeout
+    Mock::given(method("POST"))
+        .and(path("/collections/products/snapshots/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": true})).set_delay(std::time::Duration::from_secs(2)))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.snapshot");
+    std::fs::write(&snapshot, tar_archive("config.json")).unwrap();
+
+    let timeouts = TimeoutConfig::new(None, Some(1), None);
+    let connection = QdrantConnection::connect(&server.uri(), None, &timeouts, &TlsConfig::default(), &QdrantUploadConfig::default()).await.unwrap();
+    let mut dead_letters = DeadLetterWriter::new("qdrant", &server.uri(), "products", "points", dir.path().join("products.dead-letter.ndjson"));
+    let points = upload_points(&connection, "products", &snapshot, &RestoreMapping::default(), None, &mut dead_letters).await.unwrap();
+    assert_eq!(points, 7);
+}
@@ -0,0 +1,78 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::mapping::RestoreMapping;
+use rustored::qdrant_points::{upload_points, QdrantConnection};
+use rustored::ui::models::{QdrantUploadConfig, TimeoutConfig, TlsConfig};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Server where collection `products` exists
+async fn existing_collection() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/collections/products")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    server
+}
+
+/// Load `count` points into `products` on `server` in batches of two, one at a time
+///
+/// # Returns
+///
+/// The points loaded and every progress value reported
+async fn load(server: &MockServer, count: u64, timeouts: &TimeoutConfig) -> anyhow::Result<(u64, Vec<f32>)> {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("products.ndjson");
+    let points: String = (1..=count).map(|id| format!("{{\"id\":{},\"vector\":[0.1,0.2]}}\n", id)).collect();
+    std::fs::write(&snapshot, points).unwrap();
+    let upload = QdrantUploadConfig { batch_size: Some(2), concurrency: Some(1), ..QdrantUploadConfig::default() };
+    let connection = QdrantConnection::connect(&server.uri(), None, timeouts, &TlsConfig::default(), &upload).await?;
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let sink = reported.clone();
+    let progress = move |fraction: f32| sink.lock().unwrap().push(fraction);
+    let mut dead_letters = DeadLetterWriter::new("qdrant", &server.uri(), "products", "points", dir.path().join("products.dead-letter.ndjson"));
+    let loaded = upload_points(&connection, "products", &snapshot, &RestoreMapping::default(), Some(&progress), &mut dead_letters).await?;
+    let reported = reported.lock().unwrap().clone();
+    Ok((loaded, reported))
+}
+
+#[tokio::test]
+async fn test_points_are_upserted_in_batches_with_progress_by_points() {
+    let server = existing_collection().await;
+    Mock::given(method("PUT")).and(path("/collections/products/points")).respond_with(ResponseTemplate::new(200)).expect(3).mount(&server).await;
+
+    let (loaded, progress) = load(&server, 5, &TimeoutConfig::default()).await.unwrap();
+    assert_eq!(loaded, 5);
+    assert_eq!(progress, vec![0.4, 0.8, 1.0]);
+}
+
+#[tokio::test]
+async fn test_upsert_is_resent_after_server_error() {
+    let server = existing_collection().await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(serde_json::json!({"status": {"error": "optimizing"}})))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).and(path("/collections/products/points")).respond_with(ResponseTemplate::new(200)).expect(1).mount(&server).await;
+
+    let (loaded, _) = load(&server, 2, &TimeoutConfig::default()).await.unwrap();
+    assert_eq!(loaded, 2);
+}
+
+#[tokio::test]
+async fn test_upsert_is_resent_after_timeout() {
+    let server = existing_collection().await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/products/points"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(3)))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).and(path("/collections/products/points")).respond_with(ResponseTemplate::new(200)).expect(1).mount(&server).await;
+
+    let timeouts = TimeoutConfig::new(None, None, Some(1));
+    let (loaded, _) = load(&server, 1, &timeouts).await.unwrap();
+    assert_eq!(loaded, 1);
+}
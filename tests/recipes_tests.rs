@@ -0,0 +1,56 @@
+use rustored::presets::RestorePresets;
+use rustored::recipes::{find, toggle, RecipeRef, RECIPES};
+use std::collections::BTreeMap;
+
+#[test]
+fn test_render_fills_defaults_and_params() {
+    let sql = RecipeRef::parse("reset-passwords:table=accounts,password=it's dev").unwrap().render().unwrap();
+    assert!(sql.contains(r#"UPDATE "public"."accounts" SET "password_hash" = crypt('it''s dev', gen_salt('bf'));"#), "{}", sql);
+    assert!(!sql.contains("{{"));
+
+    // Every built-in recipe renders with its defaults
+    for recipe in RECIPES {
+        assert!(!recipe.render(&BTreeMap::new()).unwrap().contains("{{"), "{}", recipe.name);
+    }
+}
+
+#[test]
+fn test_render_rejects_bad_params() {
+    let err = RecipeRef::parse("reset-passwords:table=users; drop table users").unwrap_err();
+    assert!(err.to_string().contains("Invalid table"), "{}", err);
+    let err = RecipeRef::parse("truncate-audit-tables:pattern=$recipe$").unwrap_err();
+    assert!(err.to_string().contains("not allowed"), "{}", err);
+    let err = RecipeRef::parse("truncate-audit-tables:table=audit").unwrap_err();
+    assert!(err.to_string().contains("has no parameter 'table'"), "{}", err);
+    let err = RecipeRef::parse("drop-everything").unwrap_err();
+    assert!(err.to_string().contains("Unknown recipe 'drop-everything'"), "{}", err);
+    assert!(RecipeRef::parse("reset-passwords:password").is_err());
+}
+
+#[test]
+fn test_presets_select_recipes_by_name() {
+    let presets = RestorePresets::parse(
+        r#"
+        [[preset]]
+        pattern = "orders*"
+        recipes = ["disable-email-triggers", { name = "reset-passwords", password = "dev" }]
+        "#,
+    )
+    .unwrap();
+    let options = &presets.find("orders-20250101.dump").unwrap().options;
+    assert_eq!(options.recipes[0], RecipeRef::new("disable-email-triggers"));
+    assert_eq!(options.recipes[1].params["password"], "dev");
+    assert!(options.summary().contains("recipes=disable-email-triggers,reset-passwords"));
+
+    let err = RestorePresets::parse("[[preset]]\npattern = \"orders*\"\nrecipes = [\"nope\"]\n").unwrap_err();
+    assert!(err.to_string().starts_with("Invalid preset orders*: Unknown recipe 'nope'"), "{}", err);
+}
+
+#[test]
+fn test_toggle_adds_and_removes() {
+    let mut recipes = vec![RecipeRef::parse("reset-passwords:password=dev").unwrap()];
+    toggle(&mut recipes, "truncate-audit-tables");
+    toggle(&mut recipes, "reset-passwords");
+    assert_eq!(recipes, vec![RecipeRef::new("truncate-audit-tables")]);
+    assert!(find("truncate-audit-tables").is_some());
+}
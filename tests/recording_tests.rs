@@ -0,0 +1,113 @@
+use chrono::{TimeZone, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use rustored::ui::models::{FocusField, InputMode};
+use rustored::ui::recording::{decode_key, encode_key, SessionRecorder, SessionReplay, SessionState, REDACTED_KEY};
+use rustored::ui::rustored::RustoredApp;
+
+fn app() -> RustoredApp {
+    RustoredApp::new(
+        &None, &None, &None, &None, &None, &None, false,
+        &None, &None, &None, &None, false, &None,
+        &None, &None, &None,
+    )
+}
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+#[test]
+fn test_keys_round_trip_through_text() {
+    let keys = [
+        (key(KeyCode::Char('j'), KeyModifiers::NONE), "j"),
+        (key(KeyCode::Char('G'), KeyModifiers::SHIFT), "G"),
+        (key(KeyCode::Char('+'), KeyModifiers::NONE), "+"),
+        (key(KeyCode::Char('z'), KeyModifiers::CONTROL), "Ctrl+z"),
+        (key(KeyCode::Char('1'), KeyModifiers::ALT), "Alt+1"),
+        (key(KeyCode::Enter, KeyModifiers::NONE), "Enter"),
+        (key(KeyCode::Up, KeyModifiers::SHIFT), "Shift+Up"),
+        (key(KeyCode::F(5), KeyModifiers::NONE), "F5"),
+    ];
+    for (event, text) in keys {
+        assert_eq!(encode_key(&event), text);
+        let decoded = decode_key(text).unwrap();
+        assert_eq!(decoded.code, event.code);
+        if !matches!(event.code, KeyCode::Char(_)) {
+            assert_eq!(decoded.modifiers, event.modifiers);
+        }
+    }
+    assert_eq!(decode_key("Ctrl++").unwrap(), key(KeyCode::Char('+'), KeyModifiers::CONTROL));
+    assert_eq!(decode_key(REDACTED_KEY).unwrap().code, KeyCode::Char('*'));
+    assert!(decode_key("Hyper").is_err());
+}
+
+#[test]
+fn test_recorder_redacts_credentials_and_records_state() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+    let mut app = app();
+    let recorder = SessionRecorder::create(&path, false, Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap()).unwrap();
+
+    recorder.record_key(&key(KeyCode::Down, KeyModifiers::NONE), &app);
+    recorder.record_state(&app);
+    app.focus = FocusField::PgPassword;
+    app.input_mode = InputMode::Editing;
+    recorder.record_key(&key(KeyCode::Char('s'), KeyModifiers::NONE), &app);
+    recorder.record_key(&key(KeyCode::Enter, KeyModifiers::NONE), &app);
+    app.focus = FocusField::PgHost;
+    recorder.record_key(&key(KeyCode::Char('d'), KeyModifiers::NONE), &app);
+
+    let recording = std::fs::read_to_string(&path).unwrap();
+    assert!(!recording.contains("\"key\":\"s\""));
+    assert!(recording.contains(REDACTED_KEY));
+    assert!(recording.contains("\"key\":\"Enter\""));
+    assert!(recording.contains("\"key\":\"d\""));
+
+    let replay = SessionReplay::load(&path, 0.0).unwrap();
+    assert_eq!(replay.recorded_at, Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap());
+    assert!(!replay.finished());
+}
+
+#[test]
+fn test_replay_rejects_bad_recordings() {
+    assert!(SessionReplay::parse("{\"event\":\"key\",\"at_ms\":0,\"key\":\"j\"}\n".as_bytes(), 0.0).is_err());
+    let newer = "{\"event\":\"start\",\"version\":99,\"rustored\":\"9.0.0\",\"recorded_at\":\"2025-03-14T12:00:00Z\",\"demo\":true}\n";
+    assert!(SessionReplay::parse(newer.as_bytes(), 0.0).is_err());
+    assert!(SessionReplay::parse("not json\n".as_bytes(), 0.0).is_err());
+}
+
+#[test]
+fn test_state_differences_name_each_field() {
+    let expected = SessionState::of(&app());
+    let mut actual = expected.clone();
+    assert!(expected.differences(&actual).is_empty());
+    actual.popup = "Error".to_string();
+    actual.selected = 3;
+    assert_eq!(expected.differences(&actual), vec!["popup Hidden -> Error", "selected 0 -> 3"]);
+}
+
+#[tokio::test]
+async fn test_replay_drives_the_demo_tui() {
+    let recording = r#"{"event":"start","version":1,"rustored":"0.2.0","recorded_at":"2025-03-14T12:00:00Z","demo":true}
+{"event":"key","at_ms":10,"key":"Down"}
+{"event":"state","at_ms":11,"state":{"focus":"SnapshotList","input_mode":"Normal","popup":"Hidden","target":"Postgres","selected":1}}
+{"event":"key","at_ms":20,"key":"Down"}
+{"event":"state","at_ms":21,"state":{"focus":"SnapshotList","input_mode":"Normal","popup":"Hidden","target":"Postgres","selected":5}}
+{"event":"key","at_ms":30,"key":"Tab"}
+{"event":"state","at_ms":31,"state":{"focus":"Bucket","input_mode":"Normal","popup":"Hidden","target":"Postgres","selected":2}}
+"#;
+    let replay = SessionReplay::parse(recording.as_bytes(), 0.0).unwrap();
+    let mut app = app();
+    rustored::demo::enable(&mut app, replay.recorded_at);
+    app.replay = Some(replay);
+    let mut terminal = Terminal::new(TestBackend::new(160, 48)).unwrap();
+    app.run(&mut terminal).await.unwrap();
+
+    let replay = app.replay.take().unwrap();
+    assert!(replay.finished());
+    assert_eq!(replay.replayed, 3);
+    assert_eq!(replay.divergences, vec!["key 2 (Down): selected 5 -> 2"]);
+    assert!(replay.summary().contains("differed"));
+}
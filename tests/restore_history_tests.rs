@@ -0,0 +1,71 @@
+use rustored::restore_history::{estimate, format_duration, RestoreHistory, RestoreRecord};
+use std::time::Duration;
+
+fn record(datastore: &str, source_db: &str, bytes: u64, seconds: f64) -> RestoreRecord {
+    RestoreRecord { datastore: datastore.to_string(), source_db: source_db.to_string(), target_host: String::new(), bytes, seconds, finished_at: 0.0 }
+}
+
+#[test]
+fn test_estimate_prefers_restores_of_the_same_database() {
+    let records = vec![
+        record("postgres", "orders", 1_000, 10.0),
+        record("postgres", "users", 1_000, 1.0),
+        record("elasticsearch", "orders", 1_000, 100.0),
+    ];
+    let orders = estimate(&records, "postgres", "db.internal", "orders", 3_000).unwrap();
+    assert_eq!((orders.duration, orders.samples), (Duration::from_secs(30), 1));
+    assert_eq!(orders.describe(), "about 30s (from 1 past restore)");
+
+    // Without restores of the database, every restore into the target counts
+    let other = estimate(&records, "postgres", "db.internal", "billing", 2_000).unwrap();
+    assert_eq!((other.duration, other.samples), (Duration::from_secs(11), 2));
+    assert!(estimate(&records, "qdrant", "db.internal", "orders", 1_000).is_none());
+
+    assert_eq!(format_duration(Duration::from_secs(59)), "59s");
+    assert_eq!(format_duration(Duration::from_secs(250)), "4m 10s");
+    assert_eq!(format_duration(Duration::from_secs(7_500)), "2h 5m");
+}
+
+#[test]
+fn test_estimate_prefers_restores_into_the_same_server() {
+    let on = |host: &str, record: RestoreRecord| RestoreRecord { target_host: host.to_string(), ..record };
+    let records = vec![
+        on("fast.internal", record("postgres", "orders", 1_000, 1.0)),
+        on("slow.internal", record("postgres", "users", 1_000, 20.0)),
+        record("postgres", "orders", 1_000, 5.0),
+    ];
+    let slow = estimate(&records, "postgres", "slow.internal", "orders", 1_000).unwrap();
+    assert_eq!(slow.duration, Duration::from_secs(20), "any restore into the server beats one of the database elsewhere");
+    assert_eq!(slow.describe(), "about 20s (from 1 past restore into slow.internal)");
+    let fast = estimate(&records, "postgres", "fast.internal", "orders", 1_000).unwrap();
+    assert_eq!((fast.duration, fast.target_host.as_deref()), (Duration::from_secs(1), Some("fast.internal")));
+
+    // Unknown servers, and records from before servers were recorded, fall back to the database
+    let new = estimate(&records, "postgres", "new.internal", "orders", 1_000).unwrap();
+    assert_eq!((new.duration, new.samples, new.target_host), (Duration::from_secs(3), 2, None));
+    let old: RestoreRecord = serde_json::from_str(r#"{"datastore":"postgres","source_db":"orders","bytes":1,"seconds":1.0,"finished_at":0.0}"#).unwrap();
+    assert_eq!(old.target_host, "");
+}
+
+#[test]
+fn test_history_keeps_the_most_recent_restores() {
+    let dir = tempfile::tempdir().unwrap();
+    let history = RestoreHistory::in_dir(dir.path().join("cache"));
+    assert!(history.load().is_empty());
+    assert!(history.estimate("postgres", "", "orders", 1_000).is_none());
+
+    for seconds in 1..=205 {
+        history.record(record("postgres", "orders", 1_000, seconds as f64)).unwrap();
+    }
+    let records = history.load();
+    assert_eq!(records.len(), 200);
+    assert_eq!(records[0].seconds, 6.0);
+
+    // The estimate follows the ten most recent restores, and unreadable lines are skipped
+    let path = dir.path().join("cache").join(rustored::restore_history::HISTORY_FILE);
+    let mut contents = std::fs::read_to_string(&path).unwrap();
+    contents.push_str("not json\n");
+    std::fs::write(&path, contents).unwrap();
+    let estimate = history.estimate("postgres", "", "orders", 1_000).unwrap();
+    assert_eq!((estimate.duration, estimate.samples), (Duration::from_millis(200_500), 10));
+}
@@ -0,0 +1,118 @@
+use rustored::presets::{RestoreMode, RestoreOptions};
+use rustored::restore::{ImpactAction, RestoreImpact, RestoreTarget};
+use rustored::targets::{ElasticsearchRestoreTarget, PostgresRestoreTarget, QdrantRestoreTarget};
+use rustored::ui::models::{ElasticsearchConfig, PostgresConfig, QdrantConfig, TimeoutConfig};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn es_target(host: &str, index: &str) -> ElasticsearchRestoreTarget {
+    ElasticsearchRestoreTarget::new(ElasticsearchConfig { host: Some(host.to_string()), index: Some(index.to_string()), ..Default::default() })
+}
+
+fn qdrant_target(host: &str, collection: &str) -> QdrantRestoreTarget {
+    QdrantRestoreTarget::new(QdrantConfig {
+        host: Some(host.to_string()),
+        collection: Some(collection.to_string()),
+        api_key: Some("secret".to_string()),
+        timeouts: TimeoutConfig::default(),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_elasticsearch_impact_reports_documents_and_size() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/orders/_stats/docs,store"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"_all":{"primaries":{"docs":{"count":1234},"store":{"size_in_bytes":5200000}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/missing/_stats/docs,store"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let impact = es_target(&server.uri(), "orders").impact().await.unwrap().unwrap();
+    assert_eq!(impact.action(), ImpactAction::Append);
+    assert!(impact.needs_typed_confirmation());
+    assert_eq!(impact.lines(), vec![
+        "Index orders holds 1234 documents, 5.20 MB".to_string(),
+        "Restore will append to it; documents with the same ID are overwritten".to_string(),
+    ]);
+
+    let impact = es_target(&server.uri(), "missing").impact().await.unwrap().unwrap();
+    assert_eq!(impact.action(), ImpactAction::Create);
+    assert!(!impact.needs_typed_confirmation());
+    assert_eq!(impact.lines(), vec!["Index missing does not exist and will be created".to_string()]);
+}
+
+#[tokio::test]
+async fn test_qdrant_impact_reports_points() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/collections/vectors"))
+        .and(header("api-key", "secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"result":{"status":"green","points_count":0},"status":"ok"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/collections/broken"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+
+    // An empty collection is appended to without typing its name
+    let impact = qdrant_target(&server.uri(), "vectors").impact().await.unwrap().unwrap();
+    assert_eq!(impact, RestoreImpact {
+        kind: "collection",
+        name: "vectors".to_string(),
+        unit: "points",
+        exists: true,
+        count: Some(0),
+        size_bytes: None,
+        existing: ImpactAction::Append,
+    });
+    assert!(!impact.needs_typed_confirmation());
+    assert_eq!(impact.lines()[0], "Collection vectors holds 0 points");
+
+    assert!(qdrant_target(&server.uri(), "broken").impact().await.is_err());
+}
+
+#[tokio::test]
+async fn test_impact_needs_confirmation_when_count_unknown() {
+    let impact = RestoreImpact { count: None, ..RestoreImpact::missing("index", "orders", "documents") };
+    assert!(!impact.needs_typed_confirmation());
+    let impact = RestoreImpact { exists: true, ..impact };
+    assert!(impact.needs_typed_confirmation());
+    assert_eq!(impact.lines()[0], "Index orders holds an unknown number of documents");
+
+    // PostgreSQL restores into a new database, so there is nothing to preview
+    let postgres = PostgresRestoreTarget { config: PostgresConfig::default(), options: Default::default() };
+    assert_eq!(postgres.impact().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_restoring_over_a_database_always_needs_its_name() {
+    let empty = RestoreImpact {
+        exists: true,
+        count: Some(0),
+        existing: ImpactAction::Recreate,
+        ..RestoreImpact::missing("database", "orders", "tables")
+    };
+    assert!(empty.needs_typed_confirmation());
+    assert_eq!(empty.action(), ImpactAction::Recreate);
+    assert!(empty.lines()[1].contains("drop it"));
+    let missing = RestoreImpact { exists: false, ..empty };
+    assert!(!missing.needs_typed_confirmation());
+    assert_eq!(missing.action(), ImpactAction::Create);
+
+    // An unreachable server still leaves a database to confirm
+    let options = RestoreOptions { mode: RestoreMode::Clean, ..Default::default() };
+    let config = PostgresConfig { host: Some("127.0.0.1".to_string()), port: Some(1), db_name: Some("orders".to_string()), ..Default::default() };
+    let impact = PostgresRestoreTarget { config, options }.impact().await.unwrap().unwrap();
+    assert_eq!((impact.name.as_str(), impact.action()), ("orders", ImpactAction::Clean));
+    assert!(impact.needs_typed_confirmation());
+}
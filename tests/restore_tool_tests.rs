@@ -0,0 +1,54 @@
+use rustored::backup::restore_database;
+use rustored::presets::RestoreOptions;
+use rustored::restore_tool::{custom_command, RestoreTool};
+
+#[test]
+fn test_tool_is_chosen_from_magic_bytes() {
+    assert_eq!(RestoreTool::detect(b"PGDMP\x01\x0e\x00", None), RestoreTool::PgRestore);
+    let mut tar = vec![0u8; 512];
+    tar[257..262].copy_from_slice(b"ustar");
+    assert_eq!(RestoreTool::detect(&tar, None), RestoreTool::PgRestore);
+    assert_eq!(RestoreTool::detect(b"--\n-- PostgreSQL database dump\n--\nSET statement_timeout = 0;\n", None), RestoreTool::Psql);
+    assert_eq!(RestoreTool::detect("\u{feff}CREATE TABLE caf\u{e9}".as_bytes(), None), RestoreTool::Psql);
+    assert_eq!(RestoreTool::detect(b"LEGACY\x00\x01\x02", Some("myrestore {file} {db}")), RestoreTool::Custom("myrestore {file} {db}".to_string()));
+    assert_eq!(RestoreTool::detect(b"LEGACY\x00\x01\x02", None), RestoreTool::PgRestore, "without a template pg_restore reports the unknown format");
+    assert_eq!(RestoreTool::detect(b"SELECT 1;", Some("myrestore {file} {db}")), RestoreTool::Psql, "SQL never goes to the custom command");
+}
+
+#[test]
+fn test_plan_describes_the_tool_before_download() {
+    assert_eq!(RestoreTool::expected("pg/orders-20250101T000000Z.dump.age", None, None), "pg_restore");
+    assert_eq!(RestoreTool::expected("pg/orders-20250101T000000Z.sql.gz", None, None), "psql");
+    assert_eq!(RestoreTool::expected("pg/orders.bak", None, Some("myrestore {file} {db}")), "pg_restore, psql or custom command `myrestore {file} {db}`, from the file's first bytes");
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), b"LEGACY\x00").unwrap();
+    assert_eq!(RestoreTool::expected("pg/orders.dump", Some(file.path()), Some("myrestore {file} {db}")), "custom command `myrestore {file} {db}`");
+}
+
+#[test]
+fn test_custom_command_fills_in_placeholders_per_argument() {
+    let cmd = custom_command("myrestore --db={db} {file} {host}:{port} {user}", "/tmp/my dump.bak", "orders", "db.internal", 5433, Some("restore")).unwrap();
+    let cmd = cmd.as_std();
+    assert_eq!(cmd.get_program(), "myrestore");
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+    assert_eq!(args, vec!["--db=orders", "/tmp/my dump.bak", "db.internal:5433", "restore"]);
+    assert!(custom_command("  ", "f", "d", "h", 1, None).is_err());
+}
+
+#[tokio::test]
+async fn test_unrecognised_snapshot_is_restored_with_the_custom_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("orders.bak");
+    std::fs::write(&input, b"LEGACY\x00\x01").unwrap();
+    let output = dir.path().join("restored");
+    std::fs::create_dir(&output).unwrap();
+    let options = RestoreOptions { restore_command: Some(format!("cp {{file}} {}/{{db}}.out", output.display())), ..Default::default() };
+
+    restore_database("orders", input.to_str().unwrap(), "localhost", 5432, None, None, false, &options).await.unwrap();
+    assert_eq!(std::fs::read(output.join("orders.out")).unwrap(), b"LEGACY\x00\x01");
+
+    let options = RestoreOptions { restore_command: Some("false {file}".to_string()), ..Default::default() };
+    let err = restore_database("orders", input.to_str().unwrap(), "localhost", 5432, None, None, false, &options).await.unwrap_err();
+    assert!(err.to_string().contains("custom command `false {file}` failed"), "{}", err);
+}
@@ -0,0 +1,160 @@
+use chrono::NaiveDate;
+use rustored::as_of::parse_as_of;
+use rustored::holds::{HoldCatalog, CATALOG_KEY};
+use rustored::presets::RestorePresets;
+use rustored::retention::{plan_prune, prune, RetentionPolicy};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::models::BackupMetadata;
+
+fn snapshot(key: &str, taken: &str) -> BackupMetadata {
+    BackupMetadata::new(key, 4, parse_as_of(taken).unwrap().timestamp() as f64)
+}
+
+fn policy(prefix: &str, daily: usize, weekly: usize, monthly: usize) -> RetentionPolicy {
+    RetentionPolicy { prefix: prefix.to_string(), daily, weekly, monthly }
+}
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()
+}
+
+fn kept_keys(snapshots: &[BackupMetadata], policies: &[RetentionPolicy], holds: &HoldCatalog) -> Vec<String> {
+    plan_prune(snapshots, policies, holds, today()).unwrap().keep.into_iter().map(|kept| kept.key).collect()
+}
+
+#[test]
+fn test_keeps_newest_per_day_week_and_month() {
+    let snapshots = vec![
+        snapshot("orders-0610b", "2024-06-10 12:00"),
+        snapshot("orders-0610a", "2024-06-10 03:00"),
+        snapshot("orders-0609", "2024-06-09 03:00"),
+        snapshot("orders-0608", "2024-06-08 03:00"),
+        snapshot("orders-0601", "2024-06-01 03:00"),
+        snapshot("orders-0520", "2024-05-20 03:00"),
+        snapshot("orders-0515", "2024-05-15 03:00"),
+        snapshot("orders-0430", "2024-04-30 03:00"),
+    ];
+    let plan = plan_prune(&snapshots, &[policy("orders-", 2, 2, 2)], &HoldCatalog::default(), today()).unwrap();
+
+    let keep: Vec<(&str, Vec<&str>)> = plan
+        .keep
+        .iter()
+        .map(|kept| (kept.key.as_str(), kept.reasons.iter().map(String::as_str).collect()))
+        .collect();
+    assert_eq!(
+        keep,
+        vec![
+            // 2024-06-10 is a Monday, so it starts week 24 and 06-09 ends week 23
+            ("orders-0610b", vec!["daily 2024-06-10", "weekly 2024-W24", "monthly 2024-06"]),
+            ("orders-0609", vec!["daily 2024-06-09", "weekly 2024-W23"]),
+            ("orders-0520", vec!["monthly 2024-05"]),
+        ]
+    );
+    let deleted: Vec<&str> = plan.delete.iter().map(|snapshot| snapshot.key.as_str()).collect();
+    assert_eq!(deleted, vec!["orders-0610a", "orders-0608", "orders-0601", "orders-0515", "orders-0430"]);
+}
+
+#[test]
+fn test_first_matching_policy_governs_and_others_are_left_alone() {
+    let snapshots = vec![
+        snapshot("orders-2", "2024-06-02 03:00"),
+        snapshot("orders-1", "2024-06-01 03:00"),
+        snapshot("users-2", "2024-06-02 03:00"),
+        snapshot("users-1", "2024-06-01 03:00"),
+        snapshot("misc-1", "2024-06-01 03:00"),
+    ];
+    let policies = [policy("orders-", 2, 0, 0), policy("", 1, 0, 0)];
+    let plan = plan_prune(&snapshots, &policies, &HoldCatalog::default(), today()).unwrap();
+    assert_eq!(
+        plan.keep.iter().map(|kept| kept.key.as_str()).collect::<Vec<_>>(),
+        vec!["orders-2", "orders-1", "users-2"]
+    );
+    assert_eq!(plan.delete.iter().map(|snapshot| snapshot.key.as_str()).collect::<Vec<_>>(), vec!["users-1", "misc-1"]);
+
+    // A policy for another prefix does not touch these snapshots
+    let plan = plan_prune(&snapshots, &[policy("logs-", 1, 0, 0)], &HoldCatalog::default(), today()).unwrap();
+    assert!(plan.keep.is_empty() && plan.delete.is_empty());
+}
+
+#[test]
+fn test_held_and_pre_change_snapshots_are_kept() {
+    let snapshots = vec![
+        snapshot("orders-3", "2024-06-03 03:00"),
+        snapshot("orders-pre-change-2", "2024-06-02 03:00"),
+        snapshot("orders-1", "2024-06-01 03:00"),
+    ];
+    let mut holds = HoldCatalog::default();
+    holds.create("orders-1", "2024-12-31", "CASE-1", today()).unwrap();
+    let plan = plan_prune(&snapshots, &[policy("orders-", 1, 0, 0)], &holds, today()).unwrap();
+    assert!(plan.delete.is_empty());
+    assert_eq!(plan.keep[1].reasons, vec!["pre-change"]);
+    assert_eq!(plan.keep[2].reasons, vec!["held"]);
+    assert_eq!(kept_keys(&snapshots, &[policy("orders-", 1, 0, 0)], &HoldCatalog::default()), vec!["orders-3", "orders-pre-change-2"]);
+}
+
+#[test]
+fn test_base_backups_are_pruned_with_their_wal() {
+    let snapshots = vec![
+        snapshot("pg/base/20240608T030000Z-000000010000000000000002.base.tar", "2024-06-08 03:00"),
+        snapshot("pg/wal/000000010000000000000002", "2024-06-08 04:00"),
+        snapshot("pg/wal/000000010000000000000003", "2024-06-09 04:00"),
+        snapshot("pg/base/20240610T030000Z-000000010000000000000004.base.tar", "2024-06-10 03:00"),
+        snapshot("pg/wal/000000010000000000000004", "2024-06-10 04:00"),
+    ];
+    let plan = plan_prune(&snapshots, &[policy("pg/", 1, 0, 0)], &HoldCatalog::default(), today()).unwrap();
+    let deleted: Vec<&str> = plan.delete.iter().map(|snapshot| snapshot.key.as_str()).collect();
+    assert_eq!(deleted, vec![snapshots[0].key.as_str(), snapshots[1].key.as_str(), snapshots[2].key.as_str()]);
+    assert_eq!(plan.keep.len(), 1);
+
+    // A held segment keeps its whole chain
+    let mut holds = HoldCatalog::default();
+    holds.create("pg/wal/000000010000000000000003", "2024-12-31", "CASE-2", today()).unwrap();
+    let plan = plan_prune(&snapshots, &[policy("pg/", 1, 0, 0)], &holds, today()).unwrap();
+    assert!(plan.delete.is_empty());
+    assert_eq!(plan.keep[1].reasons, vec!["held"]);
+}
+
+#[test]
+fn test_policy_keeping_nothing_is_rejected() {
+    let err = plan_prune(&[], &[policy("orders-", 0, 0, 0)], &HoldCatalog::default(), today()).unwrap_err();
+    assert!(err.to_string().contains("keeps nothing"));
+    assert_eq!(policy("orders-", 7, 4, 12).summary(), "keep 7 daily, 4 weekly, 12 monthly under 'orders-'");
+}
+
+#[test]
+fn test_presets_parse_retention() {
+    let presets = RestorePresets::parse(
+        r#"
+[[retention]]
+prefix = "postgres/orders-"
+daily = 7
+weekly = 4
+monthly = 12
+"#,
+    )
+    .unwrap();
+    assert_eq!(presets.retention, vec![policy("postgres/orders-", 7, 4, 12)]);
+}
+
+#[tokio::test]
+async fn test_prune_store_honours_dry_run_and_holds() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    for key in ["orders-1", "orders-2", "orders-3"] {
+        store.write_object(key, b"dump".to_vec()).await.unwrap();
+    }
+    let mut holds = HoldCatalog::default();
+    let today = chrono::Utc::now().date_naive();
+    holds.create("orders-1", "2999-12-31", "CASE-1", today).unwrap();
+    store.write_object(CATALOG_KEY, holds.to_json().unwrap()).await.unwrap();
+    let policies = [policy("orders-", 1, 0, 0)];
+
+    let plan = prune(&store, "", &policies, today, true).await.unwrap();
+    assert_eq!(plan.delete.iter().map(|snapshot| snapshot.key.as_str()).collect::<Vec<_>>(), vec!["orders-2"]);
+    assert!(store.read_object("orders-2").await.unwrap().is_some());
+
+    prune(&store, "", &policies, today, false).await.unwrap();
+    assert!(store.read_object("orders-2").await.unwrap().is_none());
+    assert!(store.read_object("orders-1").await.unwrap().is_some());
+    assert!(store.read_object("orders-3").await.unwrap().is_some());
+    assert!(store.read_object(CATALOG_KEY).await.unwrap().is_some());
+}
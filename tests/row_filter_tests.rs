@@ -0,0 +1,81 @@
+use rustored::row_filter::{RowFilters, StagedCopy};
+use rustored::toc::Toc;
+
+const FILTERS: &str = r#"
+[[filter]]
+table = "public.orders"
+exclude_where = "deleted_at IS NOT NULL"
+
+[[filter]]
+table = "sales.*"
+where = "amount > 0"
+"#;
+
+const LIST: &str = "\
+;
+; Selected TOC Entries:
+;
+216; 1259 16520 TABLE public orders owner
+219; 1259 16541 TABLE sales invoices owner
+222; 1259 16542 TABLE public customers owner
+3370; 0 16520 TABLE DATA public orders owner
+3373; 0 16541 TABLE DATA sales invoices owner
+3374; 0 16542 TABLE DATA public customers owner
+3217; 2606 16527 CONSTRAINT public orders orders_pkey owner
+";
+
+fn rewrite(predicate: &str, script: &str) -> String {
+    let mut rewriter = StagedCopy::new(predicate);
+    let lines: Vec<u8> = script.split_inclusive('\n').flat_map(|line| rewriter.line(line.as_bytes())).collect();
+    String::from_utf8(lines).unwrap()
+}
+
+#[test]
+fn test_filters_parse_and_build_predicates() {
+    let filters = RowFilters::parse(FILTERS).unwrap();
+    assert_eq!(filters.find(("public", "orders")).unwrap().predicate(), "NOT COALESCE((deleted_at IS NOT NULL), false)");
+    assert_eq!(filters.find(("sales", "invoices")).unwrap().predicate(), "(amount > 0)");
+    assert!(filters.find(("public", "customers")).is_none());
+
+    let err = RowFilters::parse("[[filter]]\ntable = \"orders\"\n").unwrap_err();
+    assert!(err.to_string().contains("set where, exclude_where or both"), "{}", err);
+    assert!(RowFilters::parse("[[filter]]\nwhere = \"true\"\n").is_err(), "the table is required");
+    assert!(RowFilters::parse("[[filter]]\ntable = \"orders\"\nwhere = \"a\"\n[[filter]]\ntable = \"orders\"\nwhere = \"b\"\n").is_err());
+}
+
+#[test]
+fn test_plan_leaves_filtered_data_out_of_the_use_list() {
+    let filters = RowFilters::parse(FILTERS).unwrap();
+    let toc = Toc::parse(LIST, "");
+    let (list, staged) = filters.plan(&toc, &[], &[]).unwrap();
+    assert!(list.contains(";3370; 0 16520 TABLE DATA public orders owner\n"));
+    assert!(list.contains(";3373; 0 16541 TABLE DATA sales invoices owner\n"));
+    assert!(list.contains("\n3374; 0 16542 TABLE DATA public customers owner\n"));
+    assert!(list.contains("\n3217; 2606 16527 CONSTRAINT public orders orders_pkey owner\n"));
+    let tags: Vec<&str> = staged.iter().map(|(entry, _)| entry.tag.as_str()).collect();
+    assert_eq!(tags, ["orders", "invoices"]);
+
+    let (list, staged) = filters.plan(&toc, &[], &["sales.*".to_string()]).unwrap();
+    assert_eq!(staged.len(), 1, "tables left out of the restore are not loaded");
+    assert!(list.contains(";3373; 0 16541 TABLE DATA sales invoices owner\n") && !list.contains(";;"));
+
+    let stray = RowFilters::parse("[[filter]]\ntable = \"audit.*\"\nwhere = \"true\"\n").unwrap();
+    assert_eq!(stray.plan(&toc, &[], &[]).unwrap_err().to_string(), "Row filter for audit.* matches no table in the snapshot");
+}
+
+#[test]
+fn test_copy_blocks_are_staged_and_filtered() {
+    let script = "SET client_encoding = 'UTF8';\nCOPY public.orders (id, deleted_at) FROM stdin;\n1\t\\N\n2\t2025-01-01\n\\.\n\n";
+    assert_eq!(rewrite("NOT COALESCE((deleted_at IS NOT NULL), false)", script), "\
+SET client_encoding = 'UTF8';
+CREATE TEMP TABLE pg_temp.rustored_staged_rows (LIKE public.orders INCLUDING DEFAULTS);
+COPY pg_temp.rustored_staged_rows (id, deleted_at) FROM stdin;
+1\t\\N
+2\t2025-01-01
+\\.
+INSERT INTO public.orders (id, deleted_at) SELECT id, deleted_at FROM pg_temp.rustored_staged_rows WHERE NOT COALESCE((deleted_at IS NOT NULL), false);
+DROP TABLE pg_temp.rustored_staged_rows;
+
+");
+    assert_eq!(rewrite("true", "SELECT 1;\n"), "SELECT 1;\n", "other statements pass through");
+}
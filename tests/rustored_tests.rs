@@ -4,8 +4,10 @@ use rustored::ui::models::s3_config::S3Config;
 #[test]
 fn test_s3_secret_key_masking() {
     // Create a new S3Config with a secret key
-    let mut s3_config = S3Config::default();
-    s3_config.secret_access_key = "supersecret".to_string();
+    let s3_config = S3Config {
+        secret_access_key: "supersecret".to_string(),
+        ..Default::default()
+    };
     
     // Test that the secret key is fully masked when not editing
     let display_text = s3_config.get_secret_key_display(false, "");
@@ -0,0 +1,128 @@
+use chrono::{TimeZone, Utc};
+use rustored::presets::RestorePresets;
+use rustored::schedule::{load_status, ScheduleJob, ScheduleStatus, STATUS_KEY};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::S3Config;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn nightly(database: &str) -> ScheduleJob {
+    ScheduleJob { database: database.to_string(), cron: "0 3 * * *".to_string(), jitter_seconds: 0, label: None, jobs: None }
+}
+
+#[test]
+fn test_schedules_parse_from_presets() {
+    let presets = RestorePresets::parse(
+        r#"
+        [[schedule]]
+        database = "orders"
+        cron = "*/15 * * * *"
+        jitter_seconds = 60
+
+        [[schedule]]
+        database = "billing"
+        cron = "0 3 * * *"
+        label = "nightly"
+        jobs = 4
+        "#,
+    )
+    .unwrap();
+    assert_eq!(presets.schedules.len(), 2);
+    assert_eq!(presets.schedules[0].jitter_seconds, 60);
+    assert_eq!(presets.schedules[1].label.as_deref(), Some("nightly"));
+    assert_eq!(presets.schedules[1].jitter_seconds, 0);
+    assert_eq!(presets.schedules[0].jobs, None);
+    assert_eq!(presets.schedules[1].jobs, Some(4));
+}
+
+#[test]
+fn test_next_run_follows_cron_in_utc() {
+    let job = nightly("orders");
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    assert_eq!(job.next_run(now).unwrap(), Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap());
+
+    // A run at exactly the fire time is next due a day later
+    let fired = Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap();
+    assert_eq!(job.next_run(fired).unwrap(), Utc.with_ymd_and_hms(2025, 1, 3, 3, 0, 0).unwrap());
+
+    let invalid = ScheduleJob { cron: "every night".to_string(), ..nightly("orders") };
+    let err = invalid.next_run(now).unwrap_err();
+    assert!(err.to_string().contains("Invalid cron expression"));
+    assert!(ScheduleStatus::new(&[invalid], now).is_err());
+}
+
+#[test]
+fn test_jitter_stays_within_bound() {
+    assert_eq!(nightly("orders").jitter(), Duration::ZERO);
+    let job = ScheduleJob { jitter_seconds: 30, ..nightly("orders") };
+    assert!((0..100).all(|_| job.jitter() <= Duration::from_secs(30)));
+}
+
+#[test]
+fn test_running_job_is_skipped_not_started_twice() {
+    let jobs = vec![nightly("orders"), ScheduleJob { cron: "0 4 * * *".to_string(), ..nightly("billing") }];
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let mut status = ScheduleStatus::new(&jobs, now).unwrap();
+    assert_eq!(status.next_wake(), Some(Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap()));
+
+    // Only the first job is due at 03:00
+    let three = Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap();
+    assert_eq!(status.due(three), vec![0]);
+    assert!(status.start(0, &jobs[0], three).unwrap());
+    assert_eq!(status.jobs[0].next_run, Utc.with_ymd_and_hms(2025, 1, 3, 3, 0, 0).unwrap());
+
+    // Still running a day later, so that run is skipped
+    let next_day = Utc.with_ymd_and_hms(2025, 1, 3, 3, 0, 0).unwrap();
+    assert!(!status.start(0, &jobs[0], next_day).unwrap());
+    assert_eq!(status.jobs[0].skipped, 1);
+    assert_eq!(status.jobs[0].running_since, Some(three));
+
+    // Once finished the job runs again and the result is kept
+    let done = Utc.with_ymd_and_hms(2025, 1, 3, 5, 0, 0).unwrap();
+    status.finish(0, &Ok("postgres/orders-20250102T030000Z.dump".to_string()), done);
+    assert_eq!(status.jobs[0].running_since, None);
+    let last = status.jobs[0].last_run.as_ref().unwrap();
+    assert_eq!((last.started, last.finished), (three, done));
+    assert!(status.start(0, &jobs[0], Utc.with_ymd_and_hms(2025, 1, 4, 3, 0, 0).unwrap()).unwrap());
+}
+
+#[test]
+fn test_status_lines_show_next_and_last_run() {
+    let jobs = vec![nightly("orders"), nightly("billing")];
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let mut status = ScheduleStatus::new(&jobs, now).unwrap();
+    let three = Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap();
+    status.start(1, &jobs[1], three).unwrap();
+    status.finish(1, &Err(anyhow::anyhow!("pg_dump failed")), three);
+
+    let lines = status.lines();
+    assert_eq!(lines[0], "orders [0 3 * * *]: next 2025-01-02 03:00 UTC, not run yet");
+    assert_eq!(lines[1], "billing [0 3 * * *]: next 2025-01-03 03:00 UTC, last failed 2025-01-02 03:00 UTC: pg_dump failed");
+    assert_eq!(lines[2], "Updated 2025-01-01 12:00 UTC");
+}
+
+#[tokio::test]
+async fn test_status_round_trips_through_bucket() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    assert_eq!(load_status(&store).await.unwrap(), None);
+
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let status = ScheduleStatus::new(&[nightly("orders")], now).unwrap();
+    store.write_object(STATUS_KEY, status.to_json().unwrap()).await.unwrap();
+    assert_eq!(load_status(&store).await.unwrap(), Some(status));
+}
+
+#[tokio::test]
+async fn test_status_is_hidden_from_snapshot_list() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    store.write_object(STATUS_KEY, ScheduleStatus::new(&[nightly("orders")], now).unwrap().to_json().unwrap()).await.unwrap();
+    store.write_object("orders-20250101.dump", b"dump".to_vec()).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config::default());
+    browser.set_store(store);
+    browser.load_snapshots().await.unwrap();
+    let keys: Vec<&str> = browser.snapshots.iter().map(|snapshot| snapshot.key.as_str()).collect();
+    assert_eq!(keys, vec!["orders-20250101.dump"]);
+}
@@ -0,0 +1,149 @@
+use rustored::share::{export, import, load_catalog, ShareDescriptor, SharedCatalog, SHARE_VERSION};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::S3Config;
+use std::sync::Arc;
+
+fn descriptor(key: &str) -> ShareDescriptor {
+    ShareDescriptor {
+        version: SHARE_VERSION,
+        backend: "S3".to_string(),
+        bucket: "team-a-dumps".to_string(),
+        key: key.to_string(),
+        version_id: None,
+        size: 1024,
+        etag: Some("\"abc\"".to_string()),
+        sha256: Some("9f86d081".to_string()),
+        database: Some("orders".to_string()),
+        decryption: Some("age".to_string()),
+        exported: chrono::DateTime::from_timestamp(1_717_200_000, 0).unwrap(),
+        signature: String::new(),
+    }
+}
+
+#[test]
+fn test_descriptors_are_signed_with_the_share_secret() {
+    let mut share = descriptor("postgres/orders-20240601.dump");
+    assert!(share.sign("").is_err());
+    share.sign("team-secret").unwrap();
+    assert_eq!(share.signature.len(), 64);
+    share.verify("team-secret").unwrap();
+    let parsed = ShareDescriptor::from_json(&share.to_json().unwrap()).unwrap();
+    parsed.verify("team-secret").unwrap();
+
+    assert!(share.verify("other-secret").is_err());
+    let mut moved = share.clone();
+    moved.bucket = "attacker-dumps".to_string();
+    assert!(moved.verify("team-secret").unwrap_err().to_string().contains("changed after it was signed"));
+    let mut future = share.clone();
+    future.version = SHARE_VERSION + 1;
+    assert!(future.verify("team-secret").unwrap_err().to_string().contains("format version"));
+}
+
+#[test]
+fn test_catalog_replaces_an_earlier_share_of_the_same_snapshot() {
+    let mut catalog = SharedCatalog::default();
+    assert!(!catalog.register(descriptor("a.dump")));
+    assert!(!catalog.register(descriptor("b.dump")));
+    let mut again = descriptor("a.dump");
+    again.size = 2048;
+    assert!(catalog.register(again));
+    assert_eq!(catalog.shares.len(), 2);
+    assert_eq!(catalog.find("a.dump").unwrap().size, 2048);
+    assert!(catalog.find("c.dump").is_none());
+    let table = catalog.table().render(false);
+    assert!(table.contains("team-a-dumps") && table.contains("2024-06-01"), "{}", table);
+}
+
+#[tokio::test]
+async fn test_import_records_the_share_in_the_bucket() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let mut browser = SnapshotBrowser::new(S3Config::default());
+    browser.set_store(store.clone());
+    let mut share = descriptor("postgres/orders-20240601.dump");
+    share.sign("team-secret").unwrap();
+    let bytes = share.to_json().unwrap();
+
+    assert!(import(&mut browser, &bytes, "wrong").await.is_err());
+    assert!(load_catalog(store.as_ref()).await.unwrap().shares.is_empty());
+    let (imported, replaced) = import(&mut browser, &bytes, "team-secret").await.unwrap();
+    assert_eq!((imported, replaced), (share.clone(), false));
+    let (_, replaced) = import(&mut browser, &bytes, "team-secret").await.unwrap();
+    assert!(replaced);
+    assert_eq!(load_catalog(store.as_ref()).await.unwrap().shares, vec![share]);
+    // The shared catalog is not a snapshot
+    browser.load_snapshots().await.unwrap();
+    assert!(browser.snapshots.is_empty());
+
+    // Only S3 buckets can be handed to another team
+    store.write_object("postgres/orders-20240601.dump", b"dump".to_vec()).await.unwrap();
+    let error = export(&mut browser, "postgres/orders-20240601.dump", None, "team-secret").await.unwrap_err();
+    assert!(error.to_string().contains("Only S3"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_shared_snapshot_is_restored_from_the_sharing_bucket() {
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/team-a/"))
+        .and(query_param("list-type", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>team-a</Name><Prefix>pg/</Prefix><KeyCount>1</KeyCount><IsTruncated>false</IsTruncated>\
+             <Contents><Key>pg/orders.dump</Key><LastModified>2025-01-01T00:00:00.000Z</LastModified><ETag>\"e\"</ETag><Size>4</Size></Contents></ListBucketResult>",
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .and(path("/team-a/pg/orders.dump"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("x-amz-server-side-encryption", "aws:kms")
+            .insert_header("x-amz-server-side-encryption-aws-kms-key-id", "alias/backups"))
+        .mount(&server)
+        .await;
+    // Everything else, manifests and catalogs included, does not exist
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("<Error><Code>NoSuchKey</Code></Error>"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let config = |bucket: &str| S3Config {
+        bucket: bucket.to_string(),
+        region: "us-east-1".to_string(),
+        prefix: "pg/".to_string(),
+        endpoint_url: server.uri(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        path_style: true,
+        ..Default::default()
+    };
+
+    let mut team_a = SnapshotBrowser::new(config("team-a"));
+    let share = export(&mut team_a, "pg/orders.dump", None, "team-secret").await.unwrap();
+    assert_eq!((share.bucket.as_str(), share.size, share.etag.as_deref()), ("team-a", 4, Some("\"e\"")));
+    assert_eq!(share.decryption.as_deref(), Some("SSE-KMS (alias/backups)"));
+    assert_eq!(share.sha256, None);
+
+    let mut changed = share.clone();
+    changed.size = 5;
+    changed.sign("team-secret").unwrap();
+    for (bucket, share) in [("team-b", share), ("team-c", changed)] {
+        let catalog = SharedCatalog { shares: vec![share] };
+        Mock::given(method("GET"))
+            .and(path(format!("/{}/.rustored/shared.json", bucket)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(catalog.to_json().unwrap()))
+            .mount(&server)
+            .await;
+    }
+
+    let mut team_b = SnapshotBrowser::new(config("team-b"));
+    assert!(rustored::share::open_shared(&mut team_b, "pg/other.dump", "team-secret").await.is_err());
+    assert!(rustored::share::open_shared(&mut team_b, "pg/orders.dump", "wrong").await.is_err());
+    let snapshot = rustored::share::open_shared(&mut team_b, "pg/orders.dump", "team-secret").await.unwrap();
+    assert_eq!((snapshot.key.as_str(), snapshot.size), ("pg/orders.dump", 4));
+
+    let mut team_c = SnapshotBrowser::new(config("team-c"));
+    let error = rustored::share::open_shared(&mut team_c, "pg/orders.dump", "team-secret").await.unwrap_err();
+    assert!(error.to_string().contains("has changed since it was shared"), "{}", error);
+}
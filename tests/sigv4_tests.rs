@@ -0,0 +1,83 @@
+use rustored::dead_letter::DeadLetterWriter;
+use rustored::es_bulk::bulk_restore;
+use rustored::mapping::RestoreMapping;
+use rustored::sigv4::{SigV4Signer, SigningCredentials};
+use rustored::ui::models::BulkConfig;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Signer with the example keys of the AWS documentation
+fn signer(session_token: Option<&str>) -> SigV4Signer {
+    SigV4Signer::new("us-east-1", "es", SigningCredentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: session_token.map(str::to_string),
+    })
+}
+
+fn search_request() -> reqwest::Request {
+    reqwest::Client::new()
+        .post("https://search-orders.us-east-1.es.amazonaws.com/orders/_search?size=1&q=a%20b")
+        .body(r#"{"query":{"match_all":{}}}"#)
+        .build()
+        .unwrap()
+}
+
+fn header(request: &reqwest::Request, name: &str) -> String {
+    request.headers()[name].to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_sign_matches_known_signature() {
+    let mut request = search_request();
+    let now = "2024-06-01T03:00:00Z".parse().unwrap();
+    signer(None).sign(&mut request, now).unwrap();
+
+    assert_eq!(header(&request, "x-amz-date"), "20240601T030000Z");
+    assert_eq!(header(&request, "x-amz-content-sha256"), "baa6846b65b050d71831bb2e4cd6e6f1593902f6d82b16a6c1f9979d14cfcd12");
+    assert_eq!(
+        header(&request, "authorization"),
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240601/us-east-1/es/aws4_request, \
+         SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+         Signature=b0727d8465b6bcc67158954099cccd99be1bc1784c46100de8e584a3345b38bb"
+    );
+    assert!(request.headers().get("x-amz-security-token").is_none());
+}
+
+#[test]
+fn test_sign_includes_session_token() {
+    let mut request = search_request();
+    signer(Some("token")).sign(&mut request, chrono::Utc::now()).unwrap();
+    assert_eq!(header(&request, "x-amz-security-token"), "token");
+    assert!(header(&request, "authorization").contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"));
+    assert!(!format!("{:?}", signer(Some("token"))).contains("EXAMPLEKEY"), "the secret key is not logged");
+}
+
+#[tokio::test]
+async fn test_bulk_restore_signs_every_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD")).and(path("/orders")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"errors":false,"items":[]}"#))
+        .mount(&server)
+        .await;
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "{\"n\":1}\n{\"n\":2}\n").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut dead_letters = DeadLetterWriter::new("opensearch", &server.uri(), "orders", "documents", dir.path().join("orders.ndjson"));
+
+    let bulk = BulkConfig::new(Some(1), None, true);
+    let indexed = bulk_restore(&reqwest::Client::new(), Some(&signer(None)), &server.uri(), "orders", file.path(), &bulk, &RestoreMapping::default(), None, &mut dead_letters)
+        .await
+        .unwrap();
+    assert_eq!(indexed, 2);
+    let requests = server.received_requests().await.unwrap();
+    // The index check, the data stream check and two bulk requests
+    assert_eq!(requests.len(), 4);
+    for request in requests {
+        let authorization = request.headers["authorization"].to_str().unwrap().to_string();
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"), "{} {} was not signed", request.method, request.url);
+        assert!(authorization.contains("/us-east-1/es/aws4_request"));
+    }
+}
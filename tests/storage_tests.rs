@@ -0,0 +1,347 @@
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{GcsConfig, S3Config};
+
+fn gcs_config() -> GcsConfig {
+    GcsConfig {
+        bucket: "gcs-snapshots".to_string(),
+        prefix: "nightly/".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_gcs_config_requires_bucket() {
+    let config = GcsConfig::default();
+    assert!(config.verify_settings().is_err());
+    assert!(config.create_client().is_err());
+    assert!(gcs_config().verify_settings().is_ok());
+}
+
+#[test]
+fn test_browser_source_follows_backend() {
+    let s3_config = S3Config {
+        bucket: "s3-snapshots".to_string(),
+        prefix: "postgres/".to_string(),
+        ..Default::default()
+    };
+    let mut browser = SnapshotBrowser::new(s3_config);
+    assert_eq!(browser.bucket(), "s3-snapshots");
+    assert_eq!(browser.prefix(), "postgres/");
+
+    // Setting a GCS config switches the snapshot source
+    browser.gcs_config = Some(gcs_config());
+    assert_eq!(browser.bucket(), "gcs-snapshots");
+    assert_eq!(browser.prefix(), "nightly/");
+}
+
+#[tokio::test]
+async fn test_s3_client_falls_back_to_credential_chain() {
+    let mut config = S3Config { bucket: "dumps".to_string(), ..Default::default() };
+    assert!(!config.has_static_credentials());
+    assert_eq!(config.credentials_source(), "default AWS credential chain");
+    // Credentials are resolved lazily, so the client builds without any keys
+    assert!(config.create_client().await.is_ok());
+
+    config.access_key_id = "key".to_string();
+    config.secret_access_key = "secret".to_string();
+    assert!(config.has_static_credentials());
+    assert_eq!(config.credentials_source(), "access keys");
+}
+
+#[tokio::test]
+async fn test_gcs_client_with_service_account_path() {
+    // A missing service-account file is reported when the client is built
+    let config = GcsConfig {
+        service_account_path: Some("/nonexistent/service-account.json".to_string()),
+        ..gcs_config()
+    };
+    let mut browser = SnapshotBrowser::new(S3Config::default());
+    browser.gcs_config = Some(config);
+    assert!(browser.init_client().await.is_err());
+}
+
+#[test]
+fn test_azure_config_fields_and_masking() {
+    use rustored::ui::models::{AzureConfig, FocusField};
+
+    let mut config = AzureConfig::default();
+    assert!(config.verify_settings().is_err());
+    for field in AzureConfig::focus_fields() {
+        assert!(AzureConfig::contains_field(*field));
+    }
+    assert!(!AzureConfig::contains_field(FocusField::Bucket));
+
+    config.set_field_value(FocusField::AzureAccount, "acct".to_string());
+    config.set_field_value(FocusField::AzureContainer, "dumps".to_string());
+    config.set_field_value(FocusField::AzureSasToken, "?sv=2024&sig=abc".to_string());
+    assert!(config.verify_settings().is_ok());
+    assert_eq!(config.get_field_value(FocusField::AzureContainer), "dumps");
+
+    // Secrets stay hidden unless being edited
+    assert_eq!(config.get_field_display(FocusField::AzureSasToken, false, ""), "[hidden]");
+    assert_eq!(config.get_field_display(FocusField::AzureAccessKey, false, ""), "");
+    assert_eq!(config.get_field_display(FocusField::AzureSasToken, true, "typed"), "typed");
+}
+
+#[test]
+fn test_browser_prefers_azure_source() {
+    let mut browser = SnapshotBrowser::new(S3Config::default());
+    browser.gcs_config = Some(gcs_config());
+    browser.azure_config = Some(rustored::ui::models::AzureConfig {
+        account: "acct".to_string(),
+        container: "dumps".to_string(),
+        prefix: "pg/".to_string(),
+        ..Default::default()
+    });
+    assert_eq!(browser.bucket(), "dumps");
+    assert_eq!(browser.prefix(), "pg/");
+}
+
+#[tokio::test]
+async fn test_browser_prefers_sftp_source() {
+    let mut browser = SnapshotBrowser::new(S3Config::default());
+    browser.gcs_config = Some(gcs_config());
+    let sftp = rustored::ui::models::SftpConfig {
+        host: "127.0.0.1".to_string(),
+        port: 1,
+        username: "backup".to_string(),
+        prefix: "/srv/dumps/pg".to_string(),
+        ..Default::default()
+    };
+    assert!(sftp.verify_settings().is_ok());
+    assert!(rustored::ui::models::SftpConfig { username: String::new(), ..sftp.clone() }.verify_settings().is_err());
+
+    browser.sftp_config = Some(sftp);
+    assert_eq!(browser.bucket(), "127.0.0.1");
+    assert_eq!(browser.prefix(), "/srv/dumps/pg");
+
+    // Nothing listens on port 1, so connecting fails instead of falling back to GCS
+    assert!(browser.init_client().await.is_err());
+}
+
+/// ListObjectsV2 response body listing `keys`, continued by `next` when set
+fn list_page(keys: &[&str], next: Option<&str>) -> String {
+    let contents: String = keys
+        .iter()
+        .map(|key| format!(
+            "<Contents><Key>{}</Key><LastModified>2025-01-01T00:00:00.000Z</LastModified><ETag>\"e-{}\"</ETag><Size>4</Size></Contents>",
+            key, key
+        ))
+        .collect();
+    let next = next
+        .map(|token| format!("<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>", token))
+        .unwrap_or_else(|| "<IsTruncated>false</IsTruncated>".to_string());
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>dumps</Name><Prefix>pg/</Prefix><KeyCount>{}</KeyCount>{}{}</ListBucketResult>",
+        keys.len(), next, contents
+    )
+}
+
+#[tokio::test]
+async fn test_s3_listing_follows_continuation_tokens() {
+    use wiremock::matchers::{method, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("list-type", "2"))
+        .and(query_param_is_missing("continuation-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(list_page(&["pg/a.dump", "pg/b.dump"], Some("t1"))))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(query_param("continuation-token", "t1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(list_page(&["pg/c.dump"], None)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let s3_config = S3Config {
+        bucket: "dumps".to_string(),
+        region: "us-east-1".to_string(),
+        prefix: "pg/".to_string(),
+        endpoint_url: server.uri(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        path_style: true,
+        ..Default::default()
+    };
+    let mut browser = SnapshotBrowser::new(s3_config);
+    browser.load_snapshots().await.unwrap();
+    let mut keys: Vec<_> = browser.snapshots.iter().map(|s| s.key.as_str()).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["pg/a.dump", "pg/b.dump", "pg/c.dump"]);
+    assert_eq!(browser.snapshots[0].etag.as_deref().map(|e| e.starts_with("\"e-")), Some(true));
+
+    // A cap stops listing without fetching the next page
+    browser.max_keys = Some(2);
+    browser.load_snapshots().await.unwrap();
+    assert_eq!(browser.snapshots.len(), 2);
+}
+
+#[test]
+fn test_sse_customer_key_parsing() {
+    // 32 zero bytes, with the MD5 S3 expects alongside it
+    let key = rustored::storage::SseCustomerKey::from_base64("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+    assert_eq!(key.key_md5, "cLyPS3KoaSFGi/joRB3OUQ==");
+    // The key itself never shows up in logs
+    assert!(!format!("{:?}", key).contains("AAAA"));
+
+    assert!(rustored::storage::SseCustomerKey::from_base64("not base64!").is_err());
+    assert!(rustored::storage::SseCustomerKey::from_base64("AAAA").is_err());
+
+    let config = S3Config { sse_customer_key: "AAAA".to_string(), ..Default::default() };
+    assert!(config.customer_key().is_err());
+    assert_eq!(S3Config::default().customer_key().unwrap(), None);
+}
+
+#[test]
+fn test_explain_s3_encryption_errors() {
+    use rustored::storage::explain_s3_error;
+
+    let kms = explain_s3_error("pg/a.dump", Some("AccessDenied"), Some("User is not authorized to perform kms:Decrypt")).unwrap();
+    assert!(kms.contains("Grant kms:Decrypt"));
+
+    let missing_key = explain_s3_error(
+        "pg/a.dump",
+        Some("InvalidRequest"),
+        Some("The object was stored using a form of Server Side Encryption. The correct parameters must be provided to retrieve the object."),
+    ).unwrap();
+    assert!(missing_key.contains("--sse-customer-key"));
+
+    let wrong_key = explain_s3_error(
+        "pg/a.dump",
+        Some("AccessDenied"),
+        Some("Requests specifying Server Side Encryption with Customer provided keys must provide the correct secret key."),
+    ).unwrap();
+    assert!(wrong_key.contains("different SSE-C customer key"));
+
+    // Unrelated errors are left alone
+    assert_eq!(explain_s3_error("pg/a.dump", Some("AccessDenied"), Some("Access Denied")), None);
+    assert_eq!(explain_s3_error("pg/a.dump", Some("NoSuchKey"), None), None);
+}
+
+#[tokio::test]
+async fn test_s3_reports_kms_encryption_and_decrypt_errors() {
+    use rustored::storage::SnapshotEncryption;
+    use rustored::ui::models::BackupMetadata;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let key_arn = "arn:aws:kms:us-east-1:123456789012:key/abcd";
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("x-amz-server-side-encryption", "aws:kms")
+            .insert_header("x-amz-server-side-encryption-aws-kms-key-id", key_arn))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(403).set_body_string(
+            "<Error><Code>AccessDenied</Code><Message>User is not authorized to perform: kms:Decrypt</Message></Error>",
+        ))
+        .mount(&server)
+        .await;
+
+    let s3_config = S3Config {
+        bucket: "dumps".to_string(),
+        region: "us-east-1".to_string(),
+        endpoint_url: server.uri(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        path_style: true,
+        ..Default::default()
+    };
+    let mut browser = SnapshotBrowser::new(s3_config);
+    browser.load_encryption("pg/a.dump").await;
+    assert_eq!(browser.selected_encryption, Some(SnapshotEncryption::Kms { key_id: Some(key_arn.to_string()) }));
+
+    // The download fails with advice instead of a bare access denied
+    let snapshot = BackupMetadata::new("pg/a.dump", 10, 0.0);
+    let dir = tempfile::tempdir().unwrap();
+    let downloaded = browser.download_snapshot(&snapshot, &dir.path().join("a.dump")).await.unwrap();
+    assert_eq!(downloaded, None);
+    match &browser.popup_state {
+        rustored::ui::models::PopupState::Error(message) => assert!(message.contains("Grant kms:Decrypt"), "{}", message),
+        other => panic!("expected an error popup, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_s3_version_history_restores_older_version() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use rustored::ui::models::{BackupMetadata, FocusField, PopupState};
+    use wiremock::matchers::{method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let version = |id: &str, key: &str, day: u32| format!(
+        "<Version><Key>{}</Key><VersionId>{}</VersionId><IsLatest>false</IsLatest><LastModified>2025-01-0{}T00:00:00.000Z</LastModified><ETag>\"e-{}\"</ETag><Size>4</Size><StorageClass>STANDARD</StorageClass></Version>",
+        key, id, day, id
+    );
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("versions", ""))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListVersionsResult><Name>dumps</Name><Prefix>pg/a.dump</Prefix><IsTruncated>false</IsTruncated>{}{}{}</ListVersionsResult>",
+            version("v1", "pg/a.dump", 1),
+            version("v2", "pg/a.dump", 2),
+            // A longer key under the same prefix is not a version of this one
+            version("x1", "pg/a.dump.manifest.json", 3),
+        )))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(query_param("versionId", "v1"))
+        .respond_with(ResponseTemplate::new(206).set_body_string("old!"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut app = rustored::ui::rustored::RustoredApp::new(
+        &Some("dumps".to_string()),
+        &Some("us-east-1".to_string()),
+        &Some("pg/".to_string()),
+        &Some(server.uri()),
+        &Some("key".to_string()),
+        &Some("secret".to_string()),
+        true,
+        &None,
+        &None,
+        &None,
+        &None,
+        false,
+        &None,
+        &None,
+        &None,
+        &None,
+    );
+    app.snapshot_browser.snapshots = vec![BackupMetadata::new("pg/a.dump", 4, 0.0)];
+    app.focus = FocusField::SnapshotList;
+
+    // 'h' lists the versions newest first, and Enter picks one to restore
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE)).await;
+    let PopupState::VersionHistory(_, versions, 0) = &app.popup_state else {
+        panic!("expected version history popup, got {:?}", app.popup_state);
+    };
+    let ids: Vec<_> = versions.iter().map(|v| v.version_id.as_deref().unwrap()).collect();
+    assert_eq!(ids, vec!["v2", "v1"]);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    let older = match &app.popup_state {
+        PopupState::ConfirmRestore(older) => older.clone(),
+        other => panic!("expected restore confirmation, got {:?}", other),
+    };
+    assert_eq!(older.version_id.as_deref(), Some("v1"));
+    assert!(rustored::ui::command::restore_command(&app, &older).contains("--version-id v1"));
+
+    // The download reads that version
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.dump");
+    app.snapshot_browser.download_snapshot(&older, &path).await.unwrap().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "old!");
+
+    // The command line finds the same version by id
+    let found = app.snapshot_browser.find_snapshot("pg/a.dump", Some("v1")).await.unwrap();
+    assert_eq!(found, older);
+    assert!(app.snapshot_browser.find_snapshot("pg/a.dump", Some("v9")).await.is_err());
+}
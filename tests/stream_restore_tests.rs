@@ -0,0 +1,83 @@
+use rustored::presets::{RestoreMode, RestoreOptions};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::stream_restore::{estimated_lines, is_plain_sql, open_snapshot, psql_error, restore_sql_stream, ProgressReader};
+use rustored::ui::models::{BackupMetadata, PostgresConfig};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
+
+fn snapshot(key: &str, size: i64) -> BackupMetadata {
+    BackupMetadata::new(key, size, 0.0)
+}
+
+#[test]
+fn test_only_plain_sql_dumps_are_streamed() {
+    assert!(is_plain_sql("postgres/orders-20240601T030000Z.sql"));
+    assert!(!is_plain_sql("postgres/orders-20240601T030000Z.dump"));
+    assert!(is_plain_sql("postgres/orders-20240601T030000Z.sql.gz"));
+    assert!(is_plain_sql("postgres/orders-20240601T030000Z.sql.zst"));
+    assert!(is_plain_sql("postgres/orders-20240601T030000Z.sql.lz4"));
+    assert!(!is_plain_sql("postgres/orders-20240601T030000Z.dump.gz"));
+    assert!(!is_plain_sql("postgres/orders-20240601T030000Z.sql.age"));
+    assert!(!is_plain_sql("postgres/globals-20240601T030000Z.sql"));
+    assert!(!is_plain_sql("postgres/globals-20240601T030000Z.sql.gz"));
+}
+
+#[tokio::test]
+async fn test_snapshot_body_streams_with_progress() {
+    let store = CloudStore::new("Memory", object_store::memory::InMemory::new());
+    let body = b"CREATE TABLE orders (id int);\n".repeat(100);
+    store.write_object("postgres/orders.sql", body.clone()).await.unwrap();
+
+    let fractions = Arc::new(Mutex::new(Vec::new()));
+    let seen = fractions.clone();
+    let input = open_snapshot(&store, &snapshot("postgres/orders.sql", body.len() as i64)).await.unwrap();
+    let mut reader = ProgressReader::new(input, body.len() as u64, Box::new(move |fraction| seen.lock().unwrap().push(fraction)));
+    let mut read = Vec::new();
+    reader.read_to_end(&mut read).await.unwrap();
+    assert_eq!(read, body);
+    let fractions = fractions.lock().unwrap().clone();
+    assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", fractions);
+    assert_eq!(fractions.last(), Some(&1.0));
+
+    assert!(open_snapshot(&store, &snapshot("postgres/empty.sql", 0)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_sql_stream_refuses_archive_only_options() {
+    let pg_config = PostgresConfig::default();
+    let refused = [
+        RestoreOptions { include_tables: vec!["orders".to_string()], ..Default::default() },
+        RestoreOptions { drop_indexes: true, ..Default::default() },
+        RestoreOptions { mode: RestoreMode::Clean, ..Default::default() },
+    ];
+    for options in refused {
+        let error = restore_sql_stream(&pg_config, &b"SELECT 1;"[..], "orders.sql", 9, &options, None).await.unwrap_err();
+        assert!(error.to_string().contains("plain SQL dump"), "{}", error);
+    }
+}
+
+#[test]
+fn test_line_total_is_estimated_from_the_compressed_share_read() {
+    assert_eq!(estimated_lines(0, 0, 1000), 0);
+    assert_eq!(estimated_lines(5000, 250, 1000), 20000);
+    assert_eq!(estimated_lines(20000, 1000, 1000), 20000);
+    // A short object never estimates fewer lines than were already seen
+    assert_eq!(estimated_lines(300, 1200, 1000), 300);
+}
+
+#[test]
+fn test_psql_error_names_the_dump_line() {
+    let stderr = "psql:<stdin>:12: NOTICE:  table \"orders\" does not exist, skipping\n\
+                  psql:<stdin>:4821: ERROR:  relation \"customers\" does not exist\n\
+                  LINE 1: INSERT INTO customers VALUES (1);\n";
+    let error = psql_error(stderr, 90000);
+    assert!(error.starts_with("psql stopped at line 4821 of about 90000 in the dump: ERROR:  relation \"customers\" does not exist"), "{}", error);
+    assert!(error.contains("LINE 1: INSERT INTO customers"), "{}", error);
+    assert!(!error.contains("NOTICE"), "{}", error);
+
+    let error = psql_error("psql:<stdin>:7: ERROR:  syntax error at or near \"CREAT\"\n", 0);
+    assert_eq!(error, "psql stopped at line 7 of the dump: ERROR:  syntax error at or near \"CREAT\"");
+
+    let error = psql_error("psql: error: connection to server failed: FATAL:  password authentication failed\n", 0);
+    assert!(error.starts_with("psql failed: psql: error: connection"), "{}", error);
+}
@@ -0,0 +1,41 @@
+use crossterm::style::Color;
+use rustored::table::{use_color, Align, Cell, Table};
+
+#[test]
+fn test_table_aligns_columns() {
+    let mut table = Table::new(&[("NAME", Align::Left), ("SIZE", Align::Right), ("OWNER", Align::Left)]);
+    table.row(vec!["orders".into(), "1024 MB".into(), "app".into()]);
+    table.row(vec!["postgres".into(), "8 kB".into(), "postgres".into()]);
+    // Missing cells are left blank
+    table.row(vec!["restricted".into()]);
+
+    assert_eq!(
+        table.render(false),
+        "NAME           SIZE  OWNER\n\
+         orders      1024 MB  app\n\
+         postgres       8 kB  postgres\n\
+         restricted\n"
+    );
+}
+
+#[test]
+fn test_table_colors_only_when_enabled() {
+    let mut table = Table::new(&[("STATE", Align::Left), ("PREFIX", Align::Left)]);
+    table.row(vec![Cell::colored("active", Color::Green), "pg/".into()]);
+
+    let plain = table.render(false);
+    assert!(!plain.contains('\x1b'));
+    assert_eq!(plain, "STATE   PREFIX\nactive  pg/\n");
+
+    // Escape codes wrap the padded text, so columns still line up
+    let colored = table.render(true);
+    assert!(colored.contains("\x1b["));
+    assert!(colored.contains("active"));
+    assert!(colored.lines().nth(1).unwrap().ends_with("  pg/"));
+}
+
+#[test]
+fn test_no_color_flag_disables_color() {
+    assert!(!use_color(true));
+    assert!(Table::new(&[("NAME", Align::Left)]).is_empty());
+}
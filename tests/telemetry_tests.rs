@@ -0,0 +1,114 @@
+use anyhow::anyhow;
+use rustored::telemetry::{self, instrument, parse_headers, parse_traceparent, statsd_lines, traces_request, Span, SpanData, TelemetryConfig};
+use std::time::{Duration, SystemTime};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn span(name: &str, error: Option<&str>) -> SpanData {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    SpanData {
+        trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+        span_id: "00f067aa0ba902b7".to_string(),
+        parent_span_id: None,
+        name: name.to_string(),
+        start,
+        end: start + Duration::from_millis(1500),
+        attributes: vec![("key".to_string(), "pg/orders.dump".to_string())],
+        error: error.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_settings_are_parsed() {
+    assert_eq!(
+        parse_headers("x-api-key=abc, x-team = data ,").unwrap(),
+        vec![("x-api-key".to_string(), "abc".to_string()), ("x-team".to_string(), "data".to_string())]
+    );
+    assert!(parse_headers("x-api-key").is_err());
+
+    assert_eq!(
+        parse_traceparent("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"),
+        Some(("4bf92f3577b34da6a3ce929d0e0e4736".to_string(), "00f067aa0ba902b7".to_string()))
+    );
+    assert_eq!(parse_traceparent("00-4bf92f35-00f067aa0ba902b7-01"), None);
+    assert_eq!(parse_traceparent("not a traceparent"), None);
+}
+
+#[test]
+fn test_spans_are_rendered_for_statsd_and_otlp() {
+    assert_eq!(statsd_lines("rustored", &span("download", None)), vec!["rustored.download.duration:1500|ms", "rustored.download.ok:1|c"]);
+    assert_eq!(statsd_lines("rustored", &span("pg_restore", Some("pg_restore failed")))[1], "rustored.pg_restore.failed:1|c");
+
+    let mut child = span("pg_restore", Some("pg_restore failed"));
+    child.parent_span_id = Some("b7ad6b7169203331".to_string());
+    let request = traces_request("rustored", &[span("restore", None), child]);
+    let spans = &request["resourceSpans"][0]["scopeSpans"][0]["spans"];
+    assert_eq!(request["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"], "rustored");
+    assert_eq!(spans[0]["startTimeUnixNano"], "1700000000000000000");
+    assert_eq!(spans[0]["status"]["code"], 1);
+    assert!(spans[0].get("parentSpanId").is_none());
+    assert_eq!(spans[1]["parentSpanId"], "b7ad6b7169203331");
+    assert_eq!(spans[1]["status"]["message"], "pg_restore failed");
+
+    let metrics = telemetry::metrics_request("rustored", &[span("download", None)]);
+    let metric = &metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"][0];
+    assert_eq!(metric["name"], "rustored.phase.duration");
+    assert_eq!(metric["gauge"]["dataPoints"][0]["asDouble"], 1.5);
+}
+
+// Telemetry is initialized once per process, so the exporters are checked in one test
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restore_phases_are_exported() {
+    let collector = MockServer::start().await;
+    for signal in ["/v1/traces", "/v1/metrics"] {
+        Mock::given(method("POST"))
+            .and(path(signal))
+            .and(header("x-api-key", "abc"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&collector)
+            .await;
+    }
+    let statsd = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    statsd.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let guard = telemetry::init(TelemetryConfig {
+        otlp_endpoint: Some(collector.uri()),
+        otlp_headers: vec![("x-api-key".to_string(), "abc".to_string())],
+        statsd: Some(statsd.local_addr().unwrap().to_string()),
+        service_name: "rustored".to_string(),
+        traceparent: None,
+    })
+    .unwrap();
+
+    let restore = Span::operation("restore").with("key", "pg/orders.dump");
+    instrument(Span::start("download"), async { Ok(()) }).await.unwrap();
+    let failed: anyhow::Result<()> = instrument(Span::start("pg_restore"), async { Err(anyhow!("pg_restore failed")) }).await;
+    restore.end(&failed);
+    telemetry::flush().await;
+
+    let mut buf = [0u8; 512];
+    let len = statsd.recv(&mut buf).unwrap();
+    let packet = String::from_utf8_lossy(&buf[..len]).to_string();
+    assert!(packet.starts_with("rustored.download.duration:"), "{}", packet);
+    assert!(packet.ends_with("rustored.download.ok:1|c"), "{}", packet);
+
+    let requests = collector.received_requests().await.unwrap();
+    let traces = requests.iter().find(|request| request.url.path() == "/v1/traces").unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&traces.body).unwrap();
+    let spans = body["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap();
+    let names: Vec<&str> = spans.iter().map(|span| span["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["download", "pg_restore", "restore"]);
+    let root = &spans[2];
+    assert!(root.get("parentSpanId").is_none());
+    for child in &spans[..2] {
+        assert_eq!(child["traceId"], root["traceId"]);
+        assert_eq!(child["parentSpanId"], root["spanId"]);
+    }
+    assert_eq!(spans[1]["status"]["code"], 2);
+
+    // A phase outside an operation starts its own trace and waits for the next flush
+    instrument(Span::start("validation"), async { Ok(()) }).await.unwrap();
+    assert_eq!(collector.received_requests().await.unwrap().len(), 2);
+    drop(guard);
+    assert_eq!(collector.received_requests().await.unwrap().len(), 4);
+}
@@ -0,0 +1,20 @@
+use rustored::progress::Progress;
+use rustored::ui::terminal_status::{describe_job, status_title};
+
+#[test]
+fn test_describe_job_names_the_database() {
+    assert_eq!(describe_job("Restore of backups/orders-20250101T000000Z.dump"), "restoring orders");
+    assert_eq!(describe_job("Download of orders-pre-change-20250101T120000Z.dump.age"), "downloading orders");
+    assert_eq!(describe_job("Restore of bundle backups/shop-20250101T000000Z.dump"), "restoring bundle shop");
+    assert_eq!(describe_job("Prune of backups"), "prune backups");
+    assert_eq!(describe_job("Verification"), "verification");
+}
+
+#[test]
+fn test_status_title_shows_first_job_and_progress() {
+    assert_eq!(status_title(&[]), "rustored");
+    let restore = ("Restore of backups/orders-20250101T000000Z.dump".to_string(), Progress { fraction: 0.423, rate: 0.0 });
+    assert_eq!(status_title(std::slice::from_ref(&restore)), "rustored: restoring orders 42%");
+    let download = ("Download of backups/users-20250101T000000Z.dump".to_string(), Progress { fraction: 1.0, rate: 1024.0 });
+    assert_eq!(status_title(&[restore, download]), "rustored: restoring orders 42% (+1 more)");
+}
@@ -0,0 +1,193 @@
+use rustored::toc::{is_selected, pattern_matches, TableSelection, Toc};
+
+const LIST: &str = "\
+;
+; Archive created at 2025-03-14 03:00:00 UTC
+;     dbname: orders
+;
+; Selected TOC Entries:
+;
+6; 2615 16518 SCHEMA - sales owner
+221; 1255 16560 FUNCTION public f() owner
+218; 1259 16530 TABLE public Audit Log owner
+217; 1259 16529 SEQUENCE public Audit Log_id_seq owner
+3380; 0 0 SEQUENCE OWNED BY public Audit Log_id_seq owner
+216; 1259 16520 TABLE public orders owner
+3381; 0 0 COMMENT public COLUMN orders.note owner
+219; 1259 16541 TABLE sales invoices owner
+220; 1259 16556 VIEW sales big owner
+3212; 2604 16523 DEFAULT public orders id owner
+3372; 0 16530 TABLE DATA public Audit Log owner
+3370; 0 16520 TABLE DATA public orders owner
+3373; 0 16541 TABLE DATA sales invoices owner
+3219; 2606 16535 CONSTRAINT public Audit Log Audit Log_pkey owner
+3217; 2606 16527 CONSTRAINT public orders orders_pkey owner
+3215; 1259 16528 INDEX public orders_customer_idx owner
+3223; 3381 16555 STATISTICS public inv_stats owner
+3225; 2606 16549 FK CONSTRAINT sales invoices invoices_order_id_fkey owner
+";
+
+const SCRIPT: &str = r#"
+--
+-- Name: Audit Log_id_seq; Type: SEQUENCE OWNED BY; Schema: public; Owner: owner
+--
+
+ALTER SEQUENCE public."Audit Log_id_seq" OWNED BY public."Audit Log".id;
+
+--
+-- Name: COLUMN orders.note; Type: COMMENT; Schema: public; Owner: owner
+--
+
+COMMENT ON COLUMN public.orders.note IS 'Free text';
+
+--
+-- Name: orders id; Type: DEFAULT; Schema: public; Owner: owner
+--
+
+ALTER TABLE ONLY public.orders ALTER COLUMN id SET DEFAULT nextval('public.orders_id_seq'::regclass);
+
+--
+-- Name: Audit Log Audit Log_pkey; Type: CONSTRAINT; Schema: public; Owner: owner
+--
+
+ALTER TABLE ONLY public."Audit Log"
+    ADD CONSTRAINT "Audit Log_pkey" PRIMARY KEY (id);
+
+--
+-- Name: orders orders_pkey; Type: CONSTRAINT; Schema: public; Owner: owner
+--
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);
+
+--
+-- Name: orders_customer_idx; Type: INDEX; Schema: public; Owner: owner
+--
+
+CREATE INDEX orders_customer_idx ON public.orders USING btree (customer);
+
+--
+-- Name: inv_stats; Type: STATISTICS; Schema: public; Owner: owner
+--
+
+CREATE STATISTICS public.inv_stats ON id, amount FROM sales.invoices;
+
+--
+-- Name: invoices invoices_order_id_fkey; Type: FK CONSTRAINT; Schema: sales; Owner: owner
+--
+
+ALTER TABLE ONLY sales.invoices
+    ADD CONSTRAINT invoices_order_id_fkey FOREIGN KEY (order_id) REFERENCES public.orders(id);
+"#;
+
+fn relation(schema: &str, name: &str) -> Option<(String, String)> {
+    Some((schema.to_string(), name.to_string()))
+}
+
+#[test]
+fn test_entries_are_tied_to_their_tables() {
+    let toc = Toc::parse(LIST, SCRIPT);
+    let entries: Vec<_> = toc.entries().collect();
+    assert_eq!(entries.len(), 18);
+    assert_eq!(toc.lines.iter().filter(|line| line.is_err()).count(), 6);
+
+    let find = |desc: &str, tag: &str| entries.iter().find(|entry| entry.desc == desc && entry.tag == tag).unwrap();
+    assert_eq!(find("SCHEMA", "sales").schema, None);
+    assert_eq!(find("SCHEMA", "sales").relation, None);
+    assert_eq!(find("TABLE", "Audit Log").relation, relation("public", "Audit Log"));
+    assert_eq!(find("SEQUENCE OWNED BY", "Audit Log_id_seq").relation, relation("public", "Audit Log"));
+    assert_eq!(find("SEQUENCE", "Audit Log_id_seq").relation, None);
+    assert_eq!(find("COMMENT", "COLUMN orders.note").relation, relation("public", "orders"));
+    assert_eq!(find("DEFAULT", "orders id").relation, relation("public", "orders"));
+    assert_eq!(find("TABLE DATA", "Audit Log").relation, relation("public", "Audit Log"));
+    assert_eq!(find("CONSTRAINT", "Audit Log Audit Log_pkey").relation, relation("public", "Audit Log"));
+    assert_eq!(find("INDEX", "orders_customer_idx").relation, relation("public", "orders"));
+    assert_eq!(find("STATISTICS", "inv_stats").relation, relation("sales", "invoices"));
+    let fk = find("FK CONSTRAINT", "invoices invoices_order_id_fkey");
+    assert_eq!((fk.relation.clone(), fk.references.clone()), (relation("sales", "invoices"), relation("public", "orders")));
+
+    let relations: Vec<String> = toc.relations().iter().map(|(schema, name)| format!("{}.{}", schema, name)).collect();
+    assert_eq!(relations, vec!["public.Audit Log", "public.orders", "sales.big", "sales.invoices"]);
+}
+
+#[test]
+fn test_use_list_comments_out_other_tables() {
+    let toc = Toc::parse(LIST, SCRIPT);
+    let list = toc.use_list(&[], &["orders".to_string()]).unwrap();
+    let skipped: Vec<&str> = list.lines().filter(|line| line.starts_with(";3") || line.starts_with(";2")).collect();
+    // The foreign key of invoices goes too, since it references orders
+    assert_eq!(skipped, vec![
+        ";216; 1259 16520 TABLE public orders owner",
+        ";3381; 0 0 COMMENT public COLUMN orders.note owner",
+        ";3212; 2604 16523 DEFAULT public orders id owner",
+        ";3370; 0 16520 TABLE DATA public orders owner",
+        ";3217; 2606 16527 CONSTRAINT public orders orders_pkey owner",
+        ";3215; 1259 16528 INDEX public orders_customer_idx owner",
+        ";3225; 2606 16549 FK CONSTRAINT sales invoices invoices_order_id_fkey owner",
+    ]);
+    assert!(list.starts_with(";\n; Archive created at"));
+    assert_eq!(list.lines().count(), LIST.lines().count());
+
+    let list = toc.use_list(&["sales.*".to_string()], &["big".to_string()]).unwrap();
+    assert!(list.contains("\n219; 1259 16541 TABLE sales invoices owner\n"));
+    assert!(list.contains("\n;220; 1259 16556 VIEW sales big owner\n"));
+    assert!(list.contains("\n6; 2615 16518 SCHEMA - sales owner\n"), "schemas are always restored");
+
+    let error = toc.use_list(&["sales.orders".to_string()], &[]).unwrap_err().to_string();
+    assert_eq!(error, "--include-table sales.orders matches no table in the snapshot");
+    assert!(toc.use_list(&["orders".to_string()], &["public.*".to_string()]).is_err());
+}
+
+#[test]
+fn test_preview_lists_restored_objects() {
+    let toc = Toc::parse(LIST, SCRIPT);
+    let preview = toc.preview(&[], &[]).unwrap();
+    assert_eq!(preview[0], "18 objects: 1 SCHEMA, 1 FUNCTION, 3 TABLE, 1 SEQUENCE, 1 SEQUENCE OWNED BY, 1 COMMENT, 1 VIEW, 1 DEFAULT, 3 TABLE DATA, 2 CONSTRAINT, 1 INDEX, 1 STATISTICS, 1 FK CONSTRAINT");
+    assert_eq!(preview[1], "SCHEMA sales");
+    assert!(preview.contains(&"TABLE public.Audit Log".to_string()));
+    assert!(preview.contains(&"FK CONSTRAINT sales.invoices invoices_order_id_fkey".to_string()));
+    assert_eq!(preview.len(), 19);
+
+    // The table selection applies, and so do its checks
+    let preview = toc.preview(&[], &["orders".to_string()]).unwrap();
+    assert!(preview[0].starts_with("11 objects: "), "{}", preview[0]);
+    assert_eq!(preview[1], "7 left out by the table selection");
+    assert!(!preview.iter().any(|line| line.contains("orders_customer_idx")));
+    assert!(toc.preview(&["missing".to_string()], &[]).is_err());
+}
+
+#[test]
+fn test_patterns_name_tables() {
+    assert!(pattern_matches("orders", ("public", "orders")));
+    assert!(pattern_matches("public.orders", ("public", "orders")));
+    assert!(!pattern_matches("sales.orders", ("public", "orders")));
+    assert!(pattern_matches("sales.*", ("sales", "invoices")));
+    assert!(!pattern_matches("sales.*", ("public", "invoices")));
+    assert!(is_selected(&[], &[], ("public", "orders")));
+    assert!(!is_selected(&["sales.*".to_string()], &[], ("public", "orders")));
+    assert!(!is_selected(&[], &["orders".to_string()], ("public", "orders")));
+}
+
+#[test]
+fn test_table_selection_ticks_schemas_and_tables() {
+    let relations = [("public", "orders"), ("public", "customers"), ("sales", "invoices")]
+        .map(|(schema, name)| (schema.to_string(), name.to_string()));
+    let mut selection = TableSelection::new("/tmp/orders.dump", &relations, &["orders".to_string()], &[]);
+    assert_eq!(selection.rows.len(), 5);
+    assert_eq!(selection.counts(), (1, 3));
+    assert_eq!(selection.include_patterns(), vec!["public.orders"]);
+
+    // Ticking a schema ticks its tables
+    selection.move_cursor(3);
+    selection.toggle();
+    assert_eq!(selection.include_patterns(), vec!["public.orders", "sales.*"]);
+    selection.move_cursor(-1);
+    selection.toggle();
+    assert!(selection.include_patterns().is_empty(), "everything ticked restores everything");
+    assert!(selection.rows[0].ticked);
+
+    selection.toggle_all();
+    assert_eq!(selection.counts(), (0, 3));
+    selection.move_cursor(100);
+    assert_eq!(selection.cursor, 4);
+}
@@ -216,3 +216,339 @@ async fn test_connection_key_handlers() {
         assert_eq!(app.popup_state, PopupState::TestingPg, "PostgreSQL connection test handler should set popup state");
     }
 }
+
+#[tokio::test]
+async fn test_azure_settings_navigation_and_editing() {
+    let mut app = create_test_app();
+    app.snapshot_browser.azure_config = Some(rustored::ui::models::AzureConfig::default());
+    app.focus = FocusField::SnapshotList;
+
+    // Tab from the snapshot list goes to the Azure panel instead of S3
+    let tab_event = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(tab_event).await;
+    assert_eq!(app.focus, FocusField::AzureAccount);
+
+    // Down moves within the Azure fields
+    let down_event = KeyEvent::new(KeyCode::Down, KeyModifiers::NONE);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(down_event).await;
+    assert_eq!(app.focus, FocusField::AzureContainer);
+
+    // Editing a field updates the Azure config
+    let enter_event = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(enter_event).await;
+    assert_eq!(app.input_mode, InputMode::Editing);
+    for c in "dumps".chars() {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)).await;
+    }
+    let enter_event = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(enter_event).await;
+    assert_eq!(app.snapshot_browser.azure_config.as_ref().unwrap().container, "dumps");
+}
+
+#[tokio::test]
+async fn test_restore_popup_applies_and_overrides_preset() {
+    let mut app = create_test_app();
+    app.restore_presets = rustored::presets::RestorePresets::parse("[[preset]]\npattern = \"orders*\"\njobs = 8\n").unwrap();
+    app.snapshot_browser.snapshots = vec![rustored::ui::models::BackupMetadata::new("backups/orders-20250101.dump", 1024, 0.0)];
+    app.focus = FocusField::SnapshotList;
+
+    // Opening the confirmation popup seeds the options from the matching preset
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    assert!(matches!(app.popup_state, PopupState::ConfirmRestore(_)));
+    assert_eq!(app.restore_preset.as_deref(), Some("orders*"));
+    assert_eq!(app.restore_options.jobs, Some(8));
+
+    // The preset can be overridden from the popup
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('+'), KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)).await;
+    assert_eq!(app.restore_options.jobs, Some(9));
+    assert!(app.restore_options.drop_indexes);
+
+    // Toggling the preset off falls back to the defaults
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)).await;
+    assert_eq!(app.restore_preset, None);
+    assert_eq!(app.restore_options, Default::default());
+
+    // The Restore Jobs setting fills in for a preset without jobs, or none at all
+    app.pg_config.restore_jobs = Some(4);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)).await;
+    assert_eq!(app.restore_options.jobs, Some(8));
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)).await;
+    assert_eq!(app.restore_options.jobs, Some(4));
+    assert_eq!(app.restore_options.pg_restore_args(), vec!["--jobs", "4"]);
+
+    // So do the hooks from the command line
+    app.pre_restore_hook = Some("pause-traffic".to_string());
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)).await;
+    assert_eq!(app.restore_options.pre_hook.as_deref(), Some("pause-traffic"));
+    assert_eq!(app.restore_options.post_hook, None);
+}
+
+/// Text of the popup as drawn on a 120x40 screen, one line per row
+fn rendered_popup(app: &rustored::ui::rustored::RustoredApp) -> String {
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+    terminal
+        .draw(|f| rustored::ui::components::popups::render_popups::<ratatui::backend::TestBackend>(f, app))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[tokio::test]
+async fn test_generate_restore_command() {
+    let mut app = create_test_app();
+    app.restore_presets = rustored::presets::RestorePresets::parse("[[preset]]\npattern = \"orders*\"\njobs = 8\n").unwrap();
+    app.restore_presets.source = Some("/etc/rustored/presets.toml".into());
+    app.snapshot_browser.snapshots = vec![rustored::ui::models::BackupMetadata::new("backups/orders 2025.dump", 1024, 0.0)];
+    app.focus = FocusField::SnapshotList;
+
+    // 'g' on the snapshot list shows the command with the matching preset applied
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)).await;
+    let PopupState::GeneratedCommand(command, copied) = &app.popup_state else {
+        panic!("expected generated command popup, got {:?}", app.popup_state);
+    };
+    // Tests do not run in a terminal, so the copy fails and the popup says so
+    assert_eq!(copied.as_ref().unwrap_err(), "stdout is not a terminal");
+    let screen = rendered_popup(&app);
+    assert!(screen.contains("Copy failed (stdout is not a terminal), select the command below"), "{}", screen);
+    assert!(screen.contains("restore-from-s3"), "{}", screen);
+    assert_eq!(
+        command,
+        "rustored --bucket test-bucket --region us-west-2 --prefix backups/ --access-key-id test-access-key \
+         --secret-access-key \"$S3_SECRET_ACCESS_KEY\" --host localhost --port 5432 --username postgres \
+         --password \"$PG_PASSWORD\" --db-name testdb --presets-file /etc/rustored/presets.toml \
+         restore-from-s3 'backups/orders 2025.dump' --target postgres"
+    );
+
+    // Overrides made in the confirmation popup are carried over
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)).await;
+    let PopupState::GeneratedCommand(command, _) = &app.popup_state else {
+        panic!("expected generated command popup, got {:?}", app.popup_state);
+    };
+    assert!(command.ends_with("restore-from-s3 'backups/orders 2025.dump' --target postgres --no-preset --drop-indexes"));
+
+    // 'e' switches to restoring over the configured database
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE)).await;
+    assert_eq!(app.restore_options.mode, rustored::presets::RestoreMode::Recreate);
+    assert!(app.restore_impact.as_ref().is_some_and(|impact| impact.needs_typed_confirmation()));
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)).await;
+    let PopupState::GeneratedCommand(command, _) = &app.popup_state else {
+        panic!("expected generated command popup, got {:?}", app.popup_state);
+    };
+    assert!(command.ends_with("--into-existing recreate"), "{}", command);
+}
+
+#[tokio::test]
+async fn test_picked_tables_are_restored_and_generated() {
+    let mut app = create_test_app();
+    let snapshot = rustored::ui::models::BackupMetadata::new("backups/orders.dump", 1024, 0.0);
+    app.snapshot_browser.snapshots = vec![snapshot.clone()];
+    let relations = [("public", "orders"), ("public", "customers"), ("sales", "invoices")]
+        .map(|(schema, name)| (schema.to_string(), name.to_string()));
+    let selection = rustored::toc::TableSelection::new("/tmp/orders.dump", &relations, &[], &[]);
+    app.popup_state = PopupState::SelectTables(snapshot, selection);
+
+    // Untick everything, then tick one table and the sales schema
+    for code in [KeyCode::Char('a'), KeyCode::Down, KeyCode::Char(' '), KeyCode::Down, KeyCode::Down, KeyCode::Char(' '), KeyCode::Enter] {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(code, KeyModifiers::NONE)).await;
+    }
+    assert!(matches!(app.popup_state, PopupState::ConfirmRestore(_)), "{:?}", app.popup_state);
+    assert_eq!(app.restore_options.include_tables, vec!["public.orders", "sales.*"]);
+
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)).await;
+    let PopupState::GeneratedCommand(command, _) = &app.popup_state else {
+        panic!("expected generated command popup, got {:?}", app.popup_state);
+    };
+    assert!(command.ends_with("--include-table public.orders --include-table 'sales.*'"), "{}", command);
+}
+
+#[tokio::test]
+async fn test_production_asks_for_the_environment_before_deleting() {
+    use rustored::storage::{CloudStore, SnapshotStore};
+    use rustored::ui::guard_rails::GuardedAction;
+    let store = std::sync::Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let dump = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(dump.path(), b"dump").unwrap();
+    store.upload_file(dump.path(), "backups/orders-20250101.dump", None).await.unwrap();
+
+    let mut app = create_test_app();
+    app.environment = Some("production".to_string());
+    app.snapshot_browser.set_store(store.clone());
+    app.snapshot_browser.load_snapshots().await.unwrap();
+    let snapshots = app.snapshot_browser.snapshots.clone();
+    app.popup_state = PopupState::ConfirmDelete(snapshots.clone(), rustored::ui::browser::DELETE_CONFIRMATION.to_string());
+
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Enter)).await;
+    assert_eq!(app.popup_state, PopupState::ConfirmProduction(GuardedAction::Delete(snapshots.clone()), String::new()));
+
+    // A wrong name does nothing, the environment's name goes ahead
+    for c in "prod".chars() {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Char(c))).await;
+    }
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Enter)).await;
+    assert!(matches!(app.popup_state, PopupState::ConfirmProduction(_, _)));
+    assert_eq!(store.list_snapshots("backups/").await.unwrap().len(), 1);
+    for c in "uction".chars() {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Char(c))).await;
+    }
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Enter)).await;
+    assert_eq!(app.popup_state, PopupState::Success("Deleted 1 snapshot(s)".to_string()));
+    assert!(store.list_snapshots("backups/").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_restore_preview_scrolls_and_returns_to_confirmation() {
+    let mut app = create_test_app();
+    let snapshot = rustored::ui::models::BackupMetadata::new("backups/orders.dump", 1024, 0.0);
+    let lines = vec!["2 objects: 1 TABLE, 1 TABLE DATA".to_string(), "TABLE public.orders".to_string(), "TABLE DATA public.orders".to_string()];
+    app.popup_state = PopupState::RestorePreview(snapshot.clone(), lines.clone(), 0);
+
+    for code in [KeyCode::Down, KeyCode::Down, KeyCode::PageDown] {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(code, KeyModifiers::NONE)).await;
+    }
+    assert_eq!(app.popup_state, PopupState::RestorePreview(snapshot.clone(), lines, 1));
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).await;
+    assert_eq!(app.popup_state, PopupState::ConfirmRestore(snapshot));
+}
+
+#[tokio::test]
+async fn test_restore_runs_only_from_its_plan() {
+    use rustored::restore_history::{RestoreHistory, RestoreRecord};
+    let mut app = create_test_app();
+    let dir = tempfile::tempdir().unwrap();
+    let history = RestoreHistory::in_dir(dir.path());
+    history.record(RestoreRecord { datastore: "postgres".to_string(), source_db: "orders".to_string(), target_host: String::new(), bytes: 1000, seconds: 5.0, finished_at: 0.0 }).unwrap();
+    app.restore_history = Some(history);
+    app.environment = Some("production".to_string());
+    let snapshot = rustored::ui::models::BackupMetadata::new("backups/orders-20250101.dump", 2000, 0.0);
+    app.popup_state = PopupState::ConfirmRestore(snapshot.clone());
+
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE)).await;
+    let PopupState::RestorePlan(planned, lines) = &app.popup_state else {
+        panic!("expected the restore plan, got {:?}", app.popup_state);
+    };
+    assert_eq!(planned, &snapshot);
+    assert!(lines.contains(&"Source: backups/orders-20250101.dump".to_string()), "{:?}", lines);
+    assert!(lines.contains(&"Target: postgres localhost:5432, new <word>-restored database".to_string()), "{:?}", lines);
+    assert!(lines.contains(&"Tool: pg_restore".to_string()), "{:?}", lines);
+    assert!(lines.contains(&"Hooks: none".to_string()), "{:?}", lines);
+    assert!(lines.contains(&"Duration: about 10s (from 1 past restore)".to_string()), "{:?}", lines);
+    assert!(lines.iter().any(|line| line.starts_with("Disk: 2 kB in ")), "{:?}", lines);
+
+    // Going back changes nothing, and running the plan goes on to the usual confirmations
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).await;
+    assert_eq!(app.popup_state, PopupState::ConfirmRestore(snapshot.clone()));
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE)).await;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    assert!(matches!(app.popup_state, PopupState::ConfirmProduction(..)), "{:?}", app.popup_state);
+}
+
+#[tokio::test]
+async fn test_presigned_link_for_the_selected_snapshot() {
+    use rustored::storage::S3Store;
+    use rustored::ui::models::{BackupMetadata, S3Config};
+    use rustored::ui::presign::parse_link_duration;
+    use std::time::Duration;
+    assert_eq!(parse_link_duration("30m").unwrap(), Duration::from_secs(1800));
+    assert_eq!(parse_link_duration("12").unwrap(), Duration::from_secs(12 * 3600));
+    assert_eq!(parse_link_duration("7d").unwrap(), Duration::from_secs(7 * 24 * 3600));
+    assert!(parse_link_duration("8d").is_err());
+    assert!(parse_link_duration("0m").is_err());
+    assert!(parse_link_duration("1w").is_err());
+
+    // Presigning is done locally, the endpoint is never contacted
+    let config = S3Config {
+        bucket: "dumps".to_string(),
+        region: "us-east-1".to_string(),
+        endpoint_url: "http://127.0.0.1:9".to_string(),
+        access_key_id: "key".to_string(),
+        secret_access_key: "secret".to_string(),
+        path_style: true,
+        ..Default::default()
+    };
+    let store = S3Store { client: config.create_client().await.unwrap(), bucket: "dumps".to_string(), sse_customer_key: None };
+    let mut app = create_test_app();
+    app.snapshot_browser.set_store(std::sync::Arc::new(store));
+    app.snapshot_browser.snapshots = vec![BackupMetadata::new("backups/orders.dump", 10, 0.0)];
+    app.focus = FocusField::SnapshotList;
+
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Char('u'))).await;
+    assert!(matches!(&app.popup_state, PopupState::PresignDuration(_, typed) if typed == "24h"));
+    for code in [KeyCode::Backspace, KeyCode::Backspace, KeyCode::Char('h'), KeyCode::Enter] {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(code)).await;
+    }
+    let PopupState::PresignedLink(linked, url, _, copied) = &app.popup_state else {
+        panic!("expected a link, got {:?}", app.popup_state);
+    };
+    // The copy fails outside a terminal, and the link is still shown to copy by hand
+    assert_eq!(copied.as_ref().unwrap_err(), "stdout is not a terminal");
+    let screen = rendered_popup(&app);
+    assert!(screen.contains("Copy failed (stdout is not a terminal), select the link below"), "{}", screen);
+    assert!(screen.contains("http://127.0.0.1:9/dumps/backups/orders.dump?"), "{}", screen);
+    assert_eq!(linked, "backups/orders.dump");
+    assert!(url.starts_with("http://127.0.0.1:9/dumps/backups/orders.dump?"), "{}", url);
+    assert!(url.contains("X-Amz-Expires=7200"), "{}", url);
+    assert!(url.contains("X-Amz-Signature="), "{}", url);
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Esc)).await;
+    assert_eq!(app.popup_state, PopupState::Hidden);
+
+    // A duration S3 would refuse is explained
+    app.popup_state = PopupState::PresignDuration(app.snapshot_browser.snapshots[0].clone(), "30d".to_string());
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(key(KeyCode::Enter)).await;
+    assert!(matches!(&app.popup_state, PopupState::Error(message) if message.contains("7 days")));
+}
+
+#[tokio::test]
+async fn test_restore_asks_which_server_of_the_profile_to_restore_onto() {
+    use rustored::profiles::TargetServer;
+    let mut app = create_test_app();
+    app.pg_servers = vec![
+        TargetServer { name: "staging-a".to_string(), host: "127.0.0.1".to_string(), port: Some(1), environment: Some("staging".to_string()), ..Default::default() },
+        TargetServer { name: "prod".to_string(), host: "127.0.0.2".to_string(), target_db: Some("orders".to_string()), environment: Some("production".to_string()), ..Default::default() },
+    ];
+    let snapshot = rustored::ui::models::BackupMetadata::new("backups/orders-20250101.dump", 1024, 0.0);
+    app.snapshot_browser.snapshots = vec![snapshot.clone()];
+    app.focus = FocusField::SnapshotList;
+
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    assert_eq!(app.popup_state, PopupState::SelectServer(snapshot.clone(), 0));
+    for code in [KeyCode::Down, KeyCode::Down, KeyCode::Enter] {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(code, KeyModifiers::NONE)).await;
+    }
+    assert_eq!(app.popup_state, PopupState::ConfirmRestore(snapshot.clone()));
+    assert_eq!(app.pg_config.host.as_deref(), Some("127.0.0.2"));
+    // Settings the server leaves out are kept
+    assert_eq!(app.pg_config.port, Some(5432));
+    assert_eq!(app.pg_config.target_db.as_deref(), Some("orders"));
+    assert_eq!(app.environment.as_deref(), Some("production"));
+
+    // `s` asks again, starting on the server picked last; Esc keeps it
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)).await;
+    assert_eq!(app.popup_state, PopupState::SelectServer(snapshot.clone(), 1));
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).await;
+    assert_eq!(app.pg_server.as_deref(), Some("prod"));
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)).await;
+    for code in [KeyCode::Up, KeyCode::Enter] {
+        let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(code, KeyModifiers::NONE)).await;
+    }
+    assert_eq!((app.pg_config.host.as_deref(), app.pg_config.port, app.pg_config.target_db.as_deref()), (Some("127.0.0.1"), Some(1), None));
+    assert_eq!(app.environment.as_deref(), Some("staging"));
+
+    // Without servers the confirmation opens straight away
+    app.pg_servers.clear();
+    app.popup_state = PopupState::Hidden;
+    let _ = app.handle_key_event::<ratatui::backend::TestBackend>(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).await;
+    assert_eq!(app.popup_state, PopupState::ConfirmRestore(snapshot));
+}
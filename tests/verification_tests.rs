@@ -0,0 +1,151 @@
+use chrono::{TimeZone, Utc};
+use rustored::storage::{CloudStore, SnapshotStore};
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{BackupMetadata, S3Config};
+use rustored::manifest::{write_manifest, Manifest};
+use rustored::verification::{
+    quick_verify_snapshot, summarize, table, verify_snapshot, VerificationCatalog, VerifyReport, VERIFICATION_CATALOG_KEY,
+};
+use std::sync::Arc;
+
+fn snapshot(key: &str, size: i64) -> BackupMetadata {
+    BackupMetadata::new(key, size, 1_700_000_000.0)
+}
+
+#[test]
+fn test_first_checksum_is_kept_as_expected() {
+    let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+    let mut catalog = VerificationCatalog::default();
+    let orders = snapshot("postgres/orders.dump", 10);
+    assert_eq!(catalog.badge(&orders), "-");
+
+    assert!(catalog.record(&orders, "aaaa", None, Some(true), now).is_ok());
+    assert_eq!(catalog.badge(&orders), "OK 2025-03-01");
+
+    // A later download with a different checksum fails and keeps the original
+    let failed = catalog.record(&orders, "bbbb", None, Some(true), now).clone();
+    assert!(!failed.checksum_ok);
+    assert_eq!(failed.sha256, "aaaa");
+    assert_eq!(catalog.badge(&orders), "FAILED 2025-03-01");
+
+    // A replaced object starts over
+    let replaced = snapshot("postgres/orders.dump", 12);
+    assert_eq!(catalog.badge(&replaced), "-");
+    assert!(catalog.record(&replaced, "bbbb", None, None, now).is_ok());
+}
+
+#[test]
+fn test_failed_archive_check_fails_badge() {
+    let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+    let mut catalog = VerificationCatalog::default();
+    let orders = snapshot("postgres/orders.dump", 10);
+    catalog.record(&orders, "aaaa", None, Some(false), now);
+    assert_eq!(catalog.badge(&orders), "FAILED 2025-03-01");
+
+    let parsed = VerificationCatalog::from_json(&catalog.to_json().unwrap()).unwrap();
+    assert_eq!(parsed, catalog);
+}
+
+#[tokio::test]
+async fn test_verify_snapshot_caches_result_in_bucket() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    let dump = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(dump.path(), b"{\"docs\": []}").unwrap();
+    store.upload_file(dump.path(), "elasticsearch/orders.json", None).await.unwrap();
+
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "elasticsearch".to_string(), ..Default::default() });
+    browser.set_store(store.clone());
+    browser.load_snapshots().await.unwrap();
+    let orders = browser.snapshots[0].clone();
+
+    // Not a PostgreSQL archive, so only the checksum is checked
+    let result = verify_snapshot(&mut browser, &orders, Utc::now()).await.unwrap();
+    assert!(result.is_ok());
+    assert_eq!(result.archive_ok, None);
+
+    // The catalog is written to the bucket and hidden from the snapshot list
+    let stored = VerificationCatalog::from_json(&store.read_object(VERIFICATION_CATALOG_KEY).await.unwrap().unwrap()).unwrap();
+    assert!(stored.get(&orders).is_some());
+    browser.load_snapshots().await.unwrap();
+    assert_eq!(browser.snapshots.len(), 1);
+    assert!(browser.verifications.badge(&browser.snapshots[0]).starts_with("OK "));
+}
+
+#[test]
+fn test_manifest_checksum_takes_precedence() {
+    let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+    let mut catalog = VerificationCatalog::default();
+    let orders = snapshot("postgres/orders.dump", 10);
+
+    // Even the first download is checked against the manifest
+    let failed = catalog.record(&orders, "bbbb", Some("aaaa"), Some(true), now).clone();
+    assert!(!failed.checksum_ok && failed.manifest_checked);
+    let report = VerifyReport::from_verification(&orders.key, &failed);
+    assert_eq!(report.line(), "FAIL postgres/orders.dump: checksum differs from manifest, pg_restore --list ok");
+
+    let passed = catalog.record(&orders, "aaaa", Some("aaaa"), None, now).clone();
+    let report = VerifyReport::from_verification(&orders.key, &passed);
+    assert_eq!(report.line(), "PASS postgres/orders.dump: checksum matches manifest");
+    let reports = [report.clone(), VerifyReport { passed: false, ..report }];
+    assert_eq!(summarize(&reports), "2 snapshots verified: 1 passed, 1 failed");
+    assert_eq!(
+        table(&reports).render(false),
+        "RESULT  KEY                   CHECKS\npass    postgres/orders.dump  checksum matches manifest\nfail    postgres/orders.dump  checksum matches manifest\n"
+    );
+}
+
+fn manifest(key: &str, sha256: &str, size: u64) -> Manifest {
+    Manifest {
+        key: key.to_string(),
+        sha256: sha256.to_string(),
+        size,
+        format: "custom".to_string(),
+        pg_dump_version: None,
+        database: "orders".to_string(),
+        started: Utc.with_ymd_and_hms(2025, 3, 1, 3, 0, 0).unwrap(),
+        duration_seconds: 1.0,
+        bundle: None,
+    }
+}
+
+async fn browser_with(store: Arc<CloudStore>) -> SnapshotBrowser {
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "postgres".to_string(), ..Default::default() });
+    browser.set_store(store);
+    browser.load_snapshots().await.unwrap();
+    browser
+}
+
+#[tokio::test]
+async fn test_verify_snapshot_checks_manifest_checksum() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    store.write_object("postgres/orders.sql", b"select 1;".to_vec()).await.unwrap();
+    write_manifest(store.as_ref(), &manifest("postgres/orders.sql", "00", 9)).await.unwrap();
+
+    let mut browser = browser_with(store).await;
+    let orders = browser.snapshots.iter().find(|s| s.key == "postgres/orders.sql").cloned().unwrap();
+    let result = verify_snapshot(&mut browser, &orders, Utc::now()).await.unwrap();
+    assert!(!result.checksum_ok);
+    assert!(result.manifest_checked);
+}
+
+#[tokio::test]
+async fn test_quick_verify_reads_head_only() {
+    let store = Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new()));
+    store.write_object("postgres/orders.dump", b"not an archive".to_vec()).await.unwrap();
+    store.write_object("postgres/users.sql", b"select 1;".to_vec()).await.unwrap();
+    write_manifest(store.as_ref(), &manifest("postgres/users.sql", "00", 20)).await.unwrap();
+
+    let mut browser = browser_with(store.clone()).await;
+    let find = |browser: &SnapshotBrowser, key: &str| browser.snapshots.iter().find(|s| s.key == key).cloned().unwrap();
+
+    let snapshot = find(&browser, "postgres/orders.dump");
+    let report = quick_verify_snapshot(&mut browser, &snapshot).await.unwrap();
+    assert_eq!(report.line(), "FAIL postgres/orders.dump: not a pg_dump custom-format archive");
+    let snapshot = find(&browser, "postgres/users.sql");
+    let report = quick_verify_snapshot(&mut browser, &snapshot).await.unwrap();
+    assert!(report.quick);
+    assert_eq!(report.line(), "FAIL postgres/users.sql: size differs from manifest (9 bytes, expected 20)");
+
+    // Quick checks are not cached
+    assert!(store.read_object(VERIFICATION_CATALOG_KEY).await.unwrap().is_none());
+}
@@ -0,0 +1,131 @@
+use chrono::{TimeZone, Utc};
+use rustored::chains::parse_lsn;
+use rustored::compression::{Codec, Compression};
+use rustored::storage::CloudStore;
+use rustored::ui::browser::SnapshotBrowser;
+use rustored::ui::models::{BackupMetadata, S3Config};
+use rustored::wal::{self, base_key, base_start_segment, group_chains, is_wal_key, parse_segment, segment_name, start_segment};
+use std::sync::Arc;
+
+fn object(key: &str, size: i64) -> BackupMetadata {
+    BackupMetadata::new(key, size, 0.0)
+}
+
+fn browser() -> SnapshotBrowser {
+    let mut browser = SnapshotBrowser::new(S3Config { prefix: "pg".to_string(), ..Default::default() });
+    browser.set_store(Arc::new(CloudStore::new("Memory", object_store::memory::InMemory::new())));
+    browser
+}
+
+#[test]
+fn test_segment_names_follow_postgres() {
+    assert_eq!(segment_name(1, parse_lsn("0/6000028").unwrap()), "000000010000000000000006");
+    assert_eq!(segment_name(2, parse_lsn("1/FF000000").unwrap()), "0000000200000001000000FF");
+    assert_eq!(parse_segment("0000000200000001000000FF"), Some((2, 0x1FF)));
+    assert_eq!(parse_segment("00000002.history"), None);
+    assert_eq!(parse_segment("000000010000000000000006.00000028.backup"), None);
+    assert_eq!(parse_segment("000000010000000000000100"), None, "only 256 segments of 16 MB per id");
+
+    let manifest = br#"{"PostgreSQL-Backup-Manifest-Version": 1, "Files": [], "WAL-Ranges": [{"Timeline": 1, "Start-LSN": "0/6000028", "End-LSN": "0/6000100"}]}"#;
+    assert_eq!(start_segment(manifest).unwrap(), "000000010000000000000006");
+    assert!(start_segment(br#"{"WAL-Ranges": []}"#).is_err());
+}
+
+#[test]
+fn test_base_backup_keys_carry_their_start_segment() {
+    let key = base_key("pg", Utc.with_ymd_and_hms(2025, 3, 14, 3, 0, 0).unwrap(), "000000010000000000000006");
+    assert_eq!(key, "pg/base/20250314T030000Z-000000010000000000000006.base.tar");
+    assert_eq!(base_start_segment(&key), Some("000000010000000000000006"));
+    assert_eq!(base_start_segment(&format!("{}.age", key)), Some("000000010000000000000006"));
+    assert_eq!(base_start_segment("pg/orders-20250314T030000Z.dump"), None);
+    assert!(is_wal_key("pg/wal/000000010000000000000006.zst"));
+    assert!(!is_wal_key("pg/walnut.dump"));
+}
+
+#[test]
+fn test_wal_is_grouped_with_the_base_backup_it_follows() {
+    let objects = vec![
+        object("pg/wal/000000010000000000000001", 16),
+        object("pg/base/20250301T030000Z-000000010000000000000002.base.tar", 1000),
+        object("pg/wal/000000010000000000000002.zst", 4),
+        object("pg/wal/000000010000000000000003.zst.age", 4),
+        object("pg/wal/000000010000000000000005.zst", 4),
+        object("pg/wal/000000010000000000000006.00000028.backup", 1),
+        object("pg/base/20250308T030000Z-000000010000000000000007.base.tar", 1200),
+        object("pg/wal/000000010000000000000007", 16),
+        object("pg/wal/000000010000000000000008", 16),
+        object("pg/orders-20250308T030000Z.dump", 500),
+        object("other/wal/000000010000000000000009", 16),
+    ];
+    let chains = group_chains(&objects);
+    assert_eq!(chains.len(), 2);
+
+    let first = &chains["pg/base/20250301T030000Z-000000010000000000000002.base.tar"];
+    assert_eq!(first.segments.len(), 3, "the orphan before the base and the history file are left out");
+    assert_eq!(first.missing, vec!["000000010000000000000004"]);
+    assert_eq!(first.last_segment(), Some("000000010000000000000003"));
+    assert_eq!(first.size(), 1012);
+    assert!(first.summary().ends_with("1 missing"));
+
+    let second = &chains["pg/base/20250308T030000Z-000000010000000000000007.base.tar"];
+    assert_eq!(second.segments.len(), 2);
+    assert!(second.missing.is_empty());
+    assert_eq!(second.last_segment(), Some("000000010000000000000008"));
+    assert_eq!(second.summary(), "base + 2 WAL (1.23 kB)");
+}
+
+#[tokio::test]
+async fn test_push_and_fetch_round_trip() {
+    let mut browser = browser();
+    let dir = tempfile::tempdir().unwrap();
+    let segment = dir.path().join("000000010000000000000003");
+    std::fs::write(&segment, vec![7u8; 64 * 1024]).unwrap();
+
+    let compression = Compression::new(Codec::Gzip, None).unwrap();
+    let key = wal::push(&mut browser, &segment, Some(compression)).await.unwrap();
+    assert_eq!(key, "pg/wal/000000010000000000000003.gz");
+    // A retried push of the same file succeeds without uploading again
+    assert_eq!(wal::push(&mut browser, &segment, None).await.unwrap(), key);
+
+    let restored = dir.path().join("RECOVERYXLOG");
+    wal::fetch(&mut browser, "000000010000000000000003", &restored).await.unwrap();
+    assert_eq!(std::fs::read(&restored).unwrap(), std::fs::read(&segment).unwrap());
+    assert!(wal::fetch(&mut browser, "000000010000000000000004", &restored).await.is_err());
+    assert!(wal::fetch(&mut browser, "../000000010000000000000003", &restored).await.is_err());
+
+    std::fs::write(&segment, vec![8u8; 64 * 1024]).unwrap();
+    let error = wal::push(&mut browser, &segment, None).await.unwrap_err().to_string();
+    assert!(error.contains("different contents"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_browser_lists_base_backups_with_their_wal() {
+    let mut browser = browser();
+    let store = browser.store().await.unwrap();
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), b"segment").unwrap();
+    let base = "pg/base/20250301T030000Z-000000010000000000000002.base.tar";
+    for key in [base, "pg/wal/000000010000000000000002", "pg/wal/000000010000000000000003", "pg/orders.dump"] {
+        store.upload_file(file.path(), key, None).await.unwrap();
+    }
+
+    browser.load_snapshots().await.unwrap();
+    let keys: Vec<&str> = browser.snapshots.iter().map(|snapshot| snapshot.key.as_str()).collect();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&base) && keys.contains(&"pg/orders.dump"));
+    assert_eq!(browser.wal_chains[base].segments.len(), 2);
+
+    // New segments are picked up by a refresh without losing the ones already listed
+    store.upload_file(file.path(), "pg/wal/000000010000000000000004", None).await.unwrap();
+    browser.refresh_snapshots().await.unwrap();
+    assert_eq!(browser.snapshots.len(), 2);
+    assert_eq!(browser.wal_chains[base].segments.len(), 3);
+}
+
+#[test]
+fn test_prune_never_plans_wal() {
+    let policy = rustored::retention::RetentionPolicy { prefix: "pg/".to_string(), daily: 1, weekly: 0, monthly: 0 };
+    let objects = vec![object("pg/wal/000000010000000000000002", 16), object("pg/orders-1.dump", 1), object("pg/orders-2.dump", 1)];
+    let plan = rustored::retention::plan_prune(&objects, &[policy], &Default::default(), Utc::now().date_naive()).unwrap();
+    assert_eq!(plan.keep.len() + plan.delete.len(), 2);
+}
@@ -0,0 +1,123 @@
+use rustored::dead_letter::{read_dead_letters, DeadLetterWriter};
+use rustored::mapping::RestoreMapping;
+use rustored::weaviate_objects::{class_name, import_objects, object_error, weaviate_object, WeaviateCredentials};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_json, body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Snapshot of `count` exported articles with vectors
+fn snapshot(count: usize) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let lines: Vec<String> = (0..count)
+        .map(|i| json!({"class": "Article", "id": format!("00000000-0000-0000-0000-00000000000{}", i), "properties": {"title": format!("Article {}", i)}, "vector": [0.1, 0.2]}).to_string())
+        .collect();
+    std::fs::write(file.path(), lines.join("\n") + "\n").unwrap();
+    file
+}
+
+#[test]
+fn test_class_names_are_valid_weaviate_classes() {
+    assert_eq!(class_name("articles"), "Articles");
+    assert_eq!(class_name("articles-restored"), "Articles_restored");
+    assert_eq!(class_name("2024_articles"), "C2024_articles");
+    assert_eq!(class_name("Article"), "Article");
+}
+
+#[test]
+fn test_objects_are_moved_to_the_target_class() {
+    let exported = json!({"class": "Article", "id": "a", "properties": {"title": "x"}, "vector": [1.0], "creationTimeUnix": 1});
+    assert_eq!(
+        weaviate_object(exported, "Articles", &RestoreMapping::default()).unwrap(),
+        json!({"class": "Articles", "id": "a", "properties": {"title": "x"}, "vector": [1.0]})
+    );
+    // A bare document's other fields are its properties
+    let bare = json!({"id": "b", "title": "y", "vector": [2.0]});
+    assert_eq!(
+        weaviate_object(bare, "Articles", &RestoreMapping::default()).unwrap(),
+        json!({"class": "Articles", "id": "b", "properties": {"title": "y"}, "vector": [2.0]})
+    );
+    assert!(weaviate_object(json!([1, 2]), "Articles", &RestoreMapping::default()).is_err());
+    assert!(weaviate_object(json!({"properties": "x"}), "Articles", &RestoreMapping::default()).is_err());
+}
+
+#[test]
+fn test_object_error_reads_batch_results() {
+    assert_eq!(object_error(&json!({"id": "a", "result": {}})), None);
+    let rejected = json!({"id": "a", "result": {"errors": {"error": [{"message": "invalid text property"}, {"message": "bad vector"}]}}});
+    assert_eq!(object_error(&rejected).as_deref(), Some("invalid text property; bad vector"));
+}
+
+#[tokio::test]
+async fn test_import_objects_creates_class_and_dead_letters_rejected_objects() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/v1/schema/Articles")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/schema"))
+        .and(body_json(json!({"class": "Articles", "vectorizer": "none"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"class": "Articles"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/batch/objects"))
+        .and(header("authorization", "Bearer secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"id": "00000000-0000-0000-0000-000000000000", "result": {}},
+            {"id": "00000000-0000-0000-0000-000000000001", "result": {"errors": {"error": [{"message": "vector lengths don't match"}]}}},
+            {"id": "00000000-0000-0000-0000-000000000002", "result": {}},
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let file = snapshot(3);
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let callback = move |fraction: f32| reported.lock().unwrap().push(fraction);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut rejected = DeadLetterWriter::new("weaviate", "http://weaviate", "Articles", "objects", dir.path().join("Articles.dead-letter.ndjson"));
+    let credentials = WeaviateCredentials { api_key: Some("secret".to_string()), ..Default::default() };
+    let imported = import_objects(&reqwest::Client::new(), &server.uri(), "Articles", &credentials, file.path(), &RestoreMapping::default(), Some(&callback), &mut rejected)
+        .await
+        .unwrap();
+
+    assert_eq!(imported, 2);
+    assert_eq!(progress.lock().unwrap().last().copied(), Some(1.0));
+    let report = rejected.finish().unwrap().unwrap();
+    assert_eq!(report.count, 1);
+    let letters = read_dead_letters(&report.path).unwrap();
+    assert_eq!(letters[0].record["id"], json!("00000000-0000-0000-0000-000000000001"));
+    assert_eq!(letters[0].record["class"], json!("Articles"));
+    assert_eq!(letters[0].reason, "vector lengths don't match");
+}
+
+#[tokio::test]
+async fn test_oidc_client_credentials_token_is_sent() {
+    let server = MockServer::start().await;
+    let issuer = format!("{}/issuer", server.uri());
+    Mock::given(method("GET"))
+        .and(path("/v1/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"href": format!("{}/.well-known/openid-configuration", issuer), "clientId": "rustored"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/issuer/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"token_endpoint": format!("{}/token", issuer)})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/issuer/token"))
+        .and(body_string_contains("grant_type=client_credentials"))
+        .and(body_string_contains("client_secret=shh"))
+        .and(body_string_contains("scope=weaviate"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"access_token": "oidc-token", "token_type": "Bearer"})))
+        .mount(&server)
+        .await;
+
+    let credentials = WeaviateCredentials { oidc_client_secret: Some("shh".to_string()), oidc_scope: Some("weaviate".to_string()), ..Default::default() };
+    let token = credentials.token(&reqwest::Client::new(), &server.uri()).await.unwrap();
+    assert_eq!(token.as_deref(), Some("oidc-token"));
+    assert_eq!(credentials.describe(), " with OIDC client credentials");
+    assert_eq!(WeaviateCredentials::default().token(&reqwest::Client::new(), &server.uri()).await.unwrap(), None);
+}